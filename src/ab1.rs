@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 
 use bincode::{Decode, Encode};
-use na_seq::Seq;
+use na_seq::{Nucleotide, Seq};
 
 /// The data structure representing AB1 data.
 #[derive(Clone, Debug, Default, Encode, Decode)]
@@ -31,3 +31,87 @@ pub struct SeqRecordAb1 {
     /// Peak locations edited by user.
     pub peak_locations_user: Option<Vec<u16>>,
 }
+
+impl SeqRecordAb1 {
+    /// Base calls, including corrections made by the user.
+    pub fn seq(&self) -> &Seq {
+        match &self.sequence_user {
+            Some(s) => s,
+            None => &self.sequence,
+        }
+    }
+
+    /// Correct a base call. The original calls are kept in `sequence`.
+    pub fn set_base(&mut self, i: usize, nt: Nucleotide) {
+        if i >= self.sequence.len() {
+            eprintln!("Error: Base index out of range: {i}");
+            return;
+        }
+
+        let seq = self
+            .sequence_user
+            .get_or_insert_with(|| self.sequence.clone());
+        seq[i] = nt;
+    }
+
+    /// If true, the user has changed this base call from the original.
+    pub fn base_edited(&self, i: usize) -> bool {
+        match &self.sequence_user {
+            Some(s) => s.get(i) != self.sequence.get(i),
+            None => false,
+        }
+    }
+
+    /// The number of samples in the trace; the shortest of the four channels.
+    pub fn trace_len(&self) -> usize {
+        [
+            self.data_ch1.len(),
+            self.data_ch2.len(),
+            self.data_ch3.len(),
+            self.data_ch4.len(),
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(0)
+    }
+
+    /// The trace sample at a base call's peak. If peak locations are missing, we space base calls
+    /// evenly over the trace.
+    pub fn peak_sample(&self, i: usize) -> usize {
+        let peaks = match &self.peak_locations_user {
+            Some(p) => p,
+            None => &self.peak_locations,
+        };
+
+        match peaks.get(i) {
+            Some(p) => *p as usize,
+            None => {
+                let seq_len = self.sequence.len().max(1);
+                i * self.trace_len() / seq_len
+            }
+        }
+    }
+}
+
+/// UI state for the AB1 trace view.
+pub struct Ab1Ui {
+    /// The first base call displayed. 0-based.
+    pub start_i: usize,
+    /// Horizontal zoom, in pixels per trace sample.
+    pub zoom: f32,
+    /// A base call selected for correction. 0-based.
+    pub base_selected: Option<usize>,
+    /// The tab to align the read to.
+    pub ref_tab: Option<usize>,
+}
+
+impl Default for Ab1Ui {
+    fn default() -> Self {
+        Self {
+            start_i: 0,
+            zoom: 1.,
+            base_selected: None,
+            ref_tab: None,
+        }
+    }
+}
@@ -1,9 +1,17 @@
 use bio::alignment::{
     distance::simd::{bounded_levenshtein, hamming, levenshtein},
     pairwise::Aligner,
-    Alignment,
+    Alignment, AlignmentOperation,
 };
-use na_seq::{seq_aa_to_u8_lower, seq_to_u8_lower, AminoAcid, Nucleotide, Seq};
+use na_seq::{seq_aa_to_u8_lower, seq_complement, seq_to_u8_lower, AminoAcid, Nucleotide, Seq};
+
+use crate::{
+    alignment_map::{AlignmentMap, BamImport, PlacedRead, Variant, VariantSettings},
+    util::RangeIncl,
+};
+
+/// Read bases below this Phred quality are de-emphasized, and don't count towards identity.
+pub const READ_QUALITY_MIN: u8 = 20;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum AlignmentMode {
@@ -30,6 +38,228 @@ pub struct AlignmentState {
     pub dist_result: Option<u64>,
     pub text_display: String, // Ie `AlignmentResult::pretty`.
     pub mode: AlignmentMode,
+    /// A BAM file in the process of loading.
+    pub bam_import: Option<BamImport>,
+    pub bam: Option<AlignmentMap>,
+    pub bam_status: Option<String>,
+    /// The index of the reference sequence in `bam` we display coverage for.
+    pub bam_ref: usize,
+    /// Read depth over `bam_ref`, by 0-based reference position.
+    pub coverage: Option<Vec<u32>>,
+    pub variant_settings: VariantSettings,
+    /// Candidate variants over `bam_ref`, relative to the active sequence when they were called.
+    pub variants: Vec<Variant>,
+    /// Reads over `bam_ref`, placed for display, with mismatches relative to the active sequence when
+    /// they were placed. `None` if not yet computed, or out of date.
+    pub placed_reads: Option<Vec<PlacedRead>>,
+    /// Tab indices to include in a multiple alignment. The first is the reference.
+    pub msa_tabs: Vec<usize>,
+    pub msa_result: Option<MsaResult>,
+    /// A Sanger read, aligned to a sequence from another tab.
+    pub read_alignment: Option<ReadAlignment>,
+}
+
+impl AlignmentState {
+    /// Coverage, variants, and placed reads are shown against the active sequence. Clear them when it
+    /// changes, e.g. after an edit, or a tab switch.
+    pub fn clear_tracks(&mut self) {
+        self.coverage = None;
+        self.variants = Vec::new();
+        self.placed_reads = None;
+    }
+}
+
+/// A multiple sequence alignment. Rows are in input order, and are all the same length; `None` is a gap.
+pub struct MsaResult {
+    pub rows: Vec<Vec<Option<Nucleotide>>>,
+    /// The most common entry in each column. Ties go to the reference.
+    pub consensus: Vec<Option<Nucleotide>>,
+}
+
+impl MsaResult {
+    pub fn num_cols(&self) -> usize {
+        self.consensus.len()
+    }
+
+    /// True if every row has the same entry at this column.
+    pub fn column_conserved(&self, col: usize) -> bool {
+        self.rows.iter().all(|r| r[col] == self.rows[0][col])
+    }
+
+    /// The number of columns that aren't conserved.
+    pub fn mismatch_count(&self) -> usize {
+        (0..self.num_cols())
+            .filter(|c| !self.column_conserved(*c))
+            .count()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReadDiffKind {
+    /// The read has this base, vice the reference's.
+    Mismatch(Nucleotide),
+    /// The read has this base, which the reference doesn't.
+    Insertion(Nucleotide),
+    /// The read is missing the reference's base.
+    Deletion,
+}
+
+/// A difference between a read, and the reference it's aligned to.
+pub struct ReadDiff {
+    /// 1-based position on the reference. Insertions are before this position.
+    pub posit: usize,
+    pub kind: ReadDiffKind,
+    /// The read's quality here is at least `READ_QUALITY_MIN`.
+    pub high_quality: bool,
+}
+
+/// A Sanger read (e.g. from an AB1 file), aligned to a reference sequence in another tab.
+pub struct ReadAlignment {
+    pub read_tab: usize,
+    pub ref_tab: usize,
+    /// The read aligned as its reverse complement.
+    pub reverse: bool,
+    /// The reference region the read covers. May wrap around the origin of circular references.
+    pub ref_range: RangeIncl,
+    pub diffs: Vec<ReadDiff>,
+    /// The portion of high-quality aligned columns that match, from 0 to 1.
+    pub identity: f32,
+    /// The number of aligned columns where the read is high quality.
+    pub high_quality_len: usize,
+}
+
+/// Align a Sanger read to a reference, in whichever orientation aligns better. The whole read is
+/// aligned, to any part of the reference. Reads may span the origin of circular references.
+/// `quality` is per read base; if absent, all bases are treated as high quality.
+pub fn align_read(
+    read: &[Nucleotide],
+    quality: Option<&[u8]>,
+    reference: &[Nucleotide],
+    circular: bool,
+    read_tab: usize,
+    ref_tab: usize,
+) -> Option<ReadAlignment> {
+    let ref_len = reference.len();
+    if read.is_empty() || ref_len == 0 {
+        return None;
+    }
+
+    let quality_fwd = match quality {
+        Some(q) if q.len() == read.len() => q.to_vec(),
+        _ => vec![u8::MAX; read.len()],
+    };
+    let mut quality_rev = quality_fwd.clone();
+    quality_rev.reverse();
+
+    // Extend circular references past the origin, so reads spanning it align in one piece.
+    let mut ref_ext = reference.to_vec();
+    if circular {
+        ref_ext.extend(&reference[..read.len().min(ref_len)]);
+    }
+    let ref_ = seq_to_u8_lower(&ref_ext);
+
+    let score = |a: u8, b: u8| if a == b { 1i32 } else { -1i32 };
+
+    let read_rev = seq_complement(read);
+
+    let mut best: Option<(Alignment, _, _, _)> = None;
+    for (seq, qual, reverse) in [
+        (read, &quality_fwd, false),
+        (&read_rev[..], &quality_rev, true),
+    ] {
+        let seq_ = seq_to_u8_lower(seq);
+        let mut aligner = Aligner::with_capacity(seq_.len(), ref_.len(), -5, -1, &score);
+        let alignment = aligner.semiglobal(&seq_, &ref_);
+
+        let better = match &best {
+            Some((a, _, _, _)) => alignment.score > a.score,
+            None => true,
+        };
+        if better {
+            best = Some((alignment, seq, qual, reverse));
+        }
+    }
+
+    let (alignment, seq, qual, reverse) = best?;
+
+    let ref_posit = |y: usize| y % ref_len + 1;
+    let high_quality = |x: usize| qual[x.min(qual.len() - 1)] >= READ_QUALITY_MIN;
+
+    let mut diffs = Vec::new();
+    let mut matches = 0;
+    let mut high_quality_len = 0;
+
+    let (mut x, mut y) = (alignment.xstart, alignment.ystart);
+    for op in &alignment.operations {
+        match op {
+            AlignmentOperation::Match | AlignmentOperation::Subst => {
+                let hq = high_quality(x);
+                if hq {
+                    high_quality_len += 1;
+                }
+
+                if *op == AlignmentOperation::Match {
+                    if hq {
+                        matches += 1;
+                    }
+                } else {
+                    diffs.push(ReadDiff {
+                        posit: ref_posit(y),
+                        kind: ReadDiffKind::Mismatch(seq[x]),
+                        high_quality: hq,
+                    });
+                }
+                x += 1;
+                y += 1;
+            }
+            AlignmentOperation::Ins => {
+                let hq = high_quality(x);
+                if hq {
+                    high_quality_len += 1;
+                }
+                diffs.push(ReadDiff {
+                    posit: ref_posit(y),
+                    kind: ReadDiffKind::Insertion(seq[x]),
+                    high_quality: hq,
+                });
+                x += 1;
+            }
+            AlignmentOperation::Del => {
+                // Use the quality of the read bases on either side of the gap.
+                let hq = high_quality(x.saturating_sub(1)) && high_quality(x);
+                if hq {
+                    high_quality_len += 1;
+                }
+                diffs.push(ReadDiff {
+                    posit: ref_posit(y),
+                    kind: ReadDiffKind::Deletion,
+                    high_quality: hq,
+                });
+                y += 1;
+            }
+            AlignmentOperation::Xclip(len) => x += len,
+            AlignmentOperation::Yclip(len) => y += len,
+        }
+    }
+
+    let identity = if high_quality_len > 0 {
+        matches as f32 / high_quality_len as f32
+    } else {
+        0.
+    };
+
+    Some(ReadAlignment {
+        read_tab,
+        ref_tab,
+        reverse,
+        ref_range: RangeIncl::new(
+            ref_posit(alignment.ystart),
+            ref_posit(alignment.yend.max(alignment.ystart + 1) - 1),
+        ),
+        diffs,
+        identity,
+        high_quality_len,
+    })
 }
 
 #[derive(Clone, Copy)]
@@ -104,6 +334,111 @@ pub fn align_pairwise_nt(seq_0: &[Nucleotide], seq_1: &[Nucleotide]) -> (Alignme
     align_pairwise(&seq_0_, &seq_1_)
 }
 
+/// Align each sequence to the first (the reference), then merge these pairwise alignments: Gaps any
+/// sequence inserts relative to the reference are added to all rows. This is a star alignment; it's
+/// fast, and suits comparing variants of a construct, which are similar to a common reference.
+pub fn align_multiple(seqs: &[&[Nucleotide]]) -> MsaResult {
+    if seqs.is_empty() {
+        return MsaResult {
+            rows: Vec::new(),
+            consensus: Vec::new(),
+        };
+    }
+
+    let reference = seqs[0];
+    let ref_len = reference.len();
+    let ref_ = seq_to_u8_lower(reference);
+
+    let score = |a: u8, b: u8| if a == b { 1i32 } else { -1i32 };
+
+    // For each non-reference sequence: The entry aligned to each reference position, and the bases
+    // inserted before each reference position. Index `ref_len` holds bases after the reference's end.
+    let mut aligned = Vec::new();
+    for seq in &seqs[1..] {
+        let seq_ = seq_to_u8_lower(seq);
+        let mut aligner = Aligner::with_capacity(ref_.len(), seq_.len(), -5, -1, &score);
+        let alignment = aligner.global(&ref_, &seq_);
+
+        let mut at_ref = vec![None; ref_len];
+        let mut inserted = vec![Vec::new(); ref_len + 1];
+
+        let (mut x, mut y) = (alignment.xstart, alignment.ystart);
+        for op in &alignment.operations {
+            match op {
+                AlignmentOperation::Match | AlignmentOperation::Subst => {
+                    at_ref[x] = Some(seq[y]);
+                    x += 1;
+                    y += 1;
+                }
+                // A gap in the sequence.
+                AlignmentOperation::Ins => x += 1,
+                // A gap in the reference.
+                AlignmentOperation::Del => {
+                    inserted[x].push(seq[y]);
+                    y += 1;
+                }
+                AlignmentOperation::Xclip(len) => x += len,
+                AlignmentOperation::Yclip(len) => {
+                    inserted[x].extend(&seq[y..y + len]);
+                    y += len;
+                }
+            }
+        }
+        aligned.push((at_ref, inserted));
+    }
+
+    // The number of gap columns to add before each reference position.
+    let insert_lens: Vec<_> = (0..=ref_len)
+        .map(|i| {
+            aligned
+                .iter()
+                .map(|(_, ins)| ins[i].len())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut rows = Vec::with_capacity(seqs.len());
+
+    let mut ref_row = Vec::new();
+    for i in 0..=ref_len {
+        ref_row.extend(vec![None; insert_lens[i]]);
+        if i < ref_len {
+            ref_row.push(Some(reference[i]));
+        }
+    }
+    rows.push(ref_row);
+
+    for (at_ref, inserted) in &aligned {
+        let mut row = Vec::with_capacity(rows[0].len());
+        for i in 0..=ref_len {
+            row.extend(inserted[i].iter().map(|nt| Some(*nt)));
+            row.extend(vec![None; insert_lens[i] - inserted[i].len()]);
+            if i < ref_len {
+                row.push(at_ref[i]);
+            }
+        }
+        rows.push(row);
+    }
+
+    let consensus = (0..rows[0].len())
+        .map(|col| {
+            let mut best = rows[0][col];
+            let mut best_count = 0;
+            for candidate in rows.iter().map(|r| r[col]) {
+                let count = rows.iter().filter(|r| r[col] == candidate).count();
+                if count > best_count {
+                    best = candidate;
+                    best_count = count;
+                }
+            }
+            best
+        })
+        .collect();
+
+    MsaResult { rows, consensus }
+}
+
 pub fn align_pairwise_aa(seq_0: &[AminoAcid], seq_1: &[AminoAcid]) -> (Alignment, String) {
     // todo: Lots of room to configure this.
     let seq_0_ = seq_aa_to_u8_lower(seq_0);
@@ -7,11 +7,21 @@ use std::{
     fs::File,
     io,
     io::{BufRead, ErrorKind, Read},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
 };
 
 use bgzip::{index::BGZFIndex, read::IndexedBGZFReader, BGZFError, BGZFReader};
 use flate2::read::{GzDecoder, MultiGzDecoder};
+use na_seq::{
+    Nucleotide,
+    Nucleotide::{A, C, G, T},
+};
 
 /// Syntax helper for parsing multi-byte fields into primitives.
 ///
@@ -25,8 +35,15 @@ macro_rules! parse_le {
 
 const MAGIC: [u8; 4] = [b'B', b'A', b'M', 1];
 
+/// We decompress this many bytes at a time, checking for cancellation, and reporting progress between.
+const READ_CHUNK_SIZE: usize = 1 << 20;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.to_owned())
+}
+
 #[derive(Debug)]
-struct RefSeq {
+pub struct RefSeq {
     pub name: String,
     pub l_ref: u32,
 }
@@ -34,13 +51,20 @@ struct RefSeq {
 impl RefSeq {
     /// Deserialize from BAM.
     pub fn from_buf(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() < 4 {
+            return Err(invalid_data("Reference sequence is truncated"));
+        }
         let l_name = parse_le!(buf, u32, 0..4);
         let name_end = 4 + l_name as usize;
 
+        if l_name == 0 || buf.len() < name_end + 4 {
+            return Err(invalid_data("Reference sequence is truncated"));
+        }
+
         Ok(Self {
-            // todo: Don't unwrap this string parsing; map the error
             // name_end - 1: Don't parse the trailing NUL char. (In the spec, this is a null-terminated string)
-            name: String::from_utf8(buf[4..name_end - 1].to_vec()).unwrap(),
+            name: String::from_utf8(buf[4..name_end - 1].to_vec())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
             l_ref: parse_le!(buf, u32, name_end..name_end + 4),
         })
     }
@@ -52,7 +76,7 @@ impl RefSeq {
 }
 
 #[derive(Debug)]
-struct AuxData {
+pub struct AuxData {
     pub tag: [u8; 2],
     pub val_type: u8,
     /// corresponds to val_type.
@@ -60,7 +84,7 @@ struct AuxData {
 }
 
 #[derive(Debug)]
-struct Alignment {
+pub struct Alignment {
     /// Size of the entire alignment packet, *except the 4-byte block size*. Includes aux data.
     pub block_size: u32,
     pub ref_id: i32,
@@ -75,7 +99,8 @@ struct Alignment {
     pub read_name: String,
     pub cigar: Vec<u32>,
     pub seq: Vec<u8>, // 4-bit encoded
-    pub qual: String, // todo: Vec<u8>?
+    /// Phred base qualities, one per base. 0xff for each if absent.
+    pub qual: Vec<u8>,
     pub aux_data: Vec<AuxData>,
 }
 
@@ -91,8 +116,6 @@ impl Alignment {
 
         let block_size = parse_le!(buf, u32, 0..4);
 
-        println!("Block size: {:?}", block_size);
-
         let ref_id = parse_le!(buf, i32, 4..8);
         let pos = parse_le!(buf, i32, 8..12);
 
@@ -109,9 +132,15 @@ impl Alignment {
         let next_pos = parse_le!(buf, i32, 28..32);
         let tlen = parse_le!(buf, i32, 32..36);
 
+        // Make sure the variable-length fields fit in this record, vice reading past its end.
+        // Each nucleotide is 4 bits.
+        let len_required = 36 + l_read_name + 4 * n_cigar_op as usize + l_seq.div_ceil(2) + l_seq;
+        if l_read_name == 0 || len_required > buf.len() || len_required > 4 + block_size as usize {
+            return Err(invalid_data("Alignment record is truncated or malformed"));
+        }
+
         let mut i = 36;
         // -1: Ommit the trailing null.
-        println!("Len read name: {:?}", l_read_name);
         let read_name = String::from_utf8(buf[i..i + l_read_name - 1].to_vec())
             .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
         i += l_read_name;
@@ -148,11 +177,31 @@ impl Alignment {
             read_name,
             cigar,
             seq,
-            qual: String::new(), // Placeholder until auxiliary data parsing
+            qual,
             aux_data,
         })
     }
 
+    /// Decode the read's bases. Ambiguous bases are `None`.
+    pub fn bases(&self) -> Vec<Option<Nucleotide>> {
+        let mut result = Vec::new();
+        for byte in &self.seq {
+            // The high nibble is the first base of each pair.
+            for code in [byte >> 4, byte & 0xf] {
+                result.push(match code {
+                    1 => Some(A),
+                    2 => Some(C),
+                    4 => Some(G),
+                    8 => Some(T),
+                    _ => None,
+                });
+            }
+        }
+        // Odd-length reads pad the final nibble. (`qual` has one entry per base)
+        result.truncate(self.qual.len());
+        result
+    }
+
     /// Serialize to BAM.
     pub fn to_buf_(&self) -> Vec<u8> {
         // todo: If desired.
@@ -169,6 +218,8 @@ pub struct AlignmentMap {
     pub n_ref: u32,
     pub refs: Vec<RefSeq>,
     pub alignments: Vec<Alignment>,
+    /// Not part of the format: Set if we stopped reading early due to a truncated or malformed record.
+    pub truncated: bool,
 }
 
 impl AlignmentMap {
@@ -181,46 +232,59 @@ impl AlignmentMap {
             ));
         }
 
+        if buf[0..4] != MAGIC {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Incorrect BAM magic.".to_string(),
+            ));
+        }
+
         let l_text = parse_le!(buf, u32, 4..8);
         let text_end = 8 + l_text as usize;
+        if buf.len() < text_end + 4 {
+            return Err(invalid_data("Header is truncated"));
+        }
         let n_ref = parse_le!(buf, u32, text_end..text_end + 4);
         let refs_start = text_end + 4;
 
         let mut i = refs_start;
         let mut refs = Vec::new();
-        for _ in ..n_ref {
-            let ref_seq = RefSeq::from_buf(buf[i..].try_into().unwrap())?;
+        for _ in 0..n_ref {
+            let ref_seq = RefSeq::from_buf(&buf[i..])?;
             i += 9 + ref_seq.name.len();
 
             refs.push(ref_seq);
         }
 
+        // If the alignments are cut off, e.g. from an incomplete download, keep the ones before.
         let mut alignments = Vec::new();
+        let mut truncated = false;
 
         while i + 1 < buf.len() {
-            let alignment = Alignment::from_buf(buf[i..].try_into().unwrap())?;
-
-            println!("\n\n Alignment: {:?}", alignment);
-
-            i += 4 + alignment.block_size as usize;
-
-            alignments.push(alignment);
-        }
-
-        if buf[0..4] != MAGIC {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                "Incorrect BAM magic.".to_string(),
-            ));
+            match Alignment::from_buf(&buf[i..]) {
+                Ok(alignment) => {
+                    i += 4 + alignment.block_size as usize;
+                    alignments.push(alignment);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Error reading alignment {}; stopping here: {e}",
+                        alignments.len()
+                    );
+                    truncated = true;
+                    break;
+                }
+            }
         }
 
         Ok(Self {
             l_text,
-            // todo: Map the error; don't unwrap.
-            text: String::from_utf8(buf[8..text_end].to_vec()).unwrap(),
+            text: String::from_utf8(buf[8..text_end].to_vec())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
             n_ref,
             refs,
             alignments,
+            truncated,
         })
     }
 
@@ -230,24 +294,404 @@ impl AlignmentMap {
     }
 }
 
+// Flags for reads we exclude from coverage; these match the `samtools depth` defaults.
+const FLAG_UNMAPPED: u16 = 0x4;
+const FLAG_REVERSE: u16 = 0x10;
+const FLAG_SECONDARY: u16 = 0x100;
+const FLAG_QC_FAIL: u16 = 0x200;
+const FLAG_DUPLICATE: u16 = 0x400;
+
+// CIGAR operations that consume the reference, and are aligned to it: M, =, and X.
+const CIGAR_MATCH: u32 = 0;
+const CIGAR_SEQ_MATCH: u32 = 7;
+const CIGAR_SEQ_MISMATCH: u32 = 8;
+// CIGAR operations that consume the reference, but aren't aligned: D, and N.
+const CIGAR_DELETION: u32 = 2;
+const CIGAR_SKIP: u32 = 3;
+// CIGAR operations that consume the read only: I, and S.
+const CIGAR_INSERTION: u32 = 1;
+const CIGAR_SOFT_CLIP: u32 = 4;
+
+/// A read's position on a reference, from its CIGAR string. Reference positions are 0-based.
+pub struct PlacedRead {
+    /// Index into `AlignmentMap::alignments`.
+    pub alignment_i: usize,
+    pub start: usize,
+    /// Exclusive.
+    pub end: usize,
+    pub reverse: bool,
+    /// Reference ranges (start, end exclusive) aligned to read bases. Deletions and skipped regions
+    /// are between these.
+    pub blocks: Vec<(usize, usize)>,
+    /// Reference positions the read has inserted bases before.
+    pub insertions: Vec<usize>,
+    /// Reference positions where the read's base differs from `seq`'s.
+    pub mismatches: Vec<usize>,
+    /// The display row, so reads don't overlap.
+    pub row: usize,
+}
+
+/// Place each read on a reference, and stack them into rows for display. Sorted by start position.
+/// `seq` is the reference sequence, used to find mismatches.
+pub fn place_reads(map: &AlignmentMap, ref_id: usize, seq: &[Nucleotide]) -> Vec<PlacedRead> {
+    let mut result = Vec::new();
+
+    let flags_excluded = FLAG_UNMAPPED | FLAG_SECONDARY | FLAG_QC_FAIL | FLAG_DUPLICATE;
+
+    for (alignment_i, alignment) in map.alignments.iter().enumerate() {
+        if alignment.ref_id != ref_id as i32
+            || alignment.pos < 0
+            || alignment.flag & flags_excluded != 0
+        {
+            continue;
+        }
+
+        let bases = alignment.bases();
+        let start = alignment.pos as usize;
+        let mut pos = start;
+        let mut read_i = 0;
+
+        let mut blocks = Vec::new();
+        let mut insertions = Vec::new();
+        let mut mismatches = Vec::new();
+
+        for op in &alignment.cigar {
+            let op_len = (op >> 4) as usize;
+            match op & 0xf {
+                CIGAR_MATCH | CIGAR_SEQ_MATCH | CIGAR_SEQ_MISMATCH => {
+                    for j in 0..op_len {
+                        let (p, r) = (pos + j, read_i + j);
+                        if let (Some(Some(nt)), Some(nt_ref)) = (bases.get(r), seq.get(p)) {
+                            if nt != nt_ref {
+                                mismatches.push(p);
+                            }
+                        }
+                    }
+                    blocks.push((pos, pos + op_len));
+                    pos += op_len;
+                    read_i += op_len;
+                }
+                CIGAR_DELETION | CIGAR_SKIP => pos += op_len,
+                CIGAR_INSERTION => {
+                    insertions.push(pos);
+                    read_i += op_len;
+                }
+                CIGAR_SOFT_CLIP => read_i += op_len,
+                _ => (),
+            }
+        }
+
+        if pos == start {
+            continue; // Nothing aligned to the reference.
+        }
+
+        result.push(PlacedRead {
+            alignment_i,
+            start,
+            end: pos,
+            reverse: alignment.flag & FLAG_REVERSE != 0,
+            blocks,
+            insertions,
+            mismatches,
+            row: 0,
+        });
+    }
+
+    result.sort_by_key(|r| r.start);
+
+    // Put each read in the first row it fits in.
+    let mut row_ends: Vec<usize> = Vec::new();
+    for read in &mut result {
+        match row_ends.iter().position(|end| *end <= read.start) {
+            Some(row) => {
+                read.row = row;
+                row_ends[row] = read.end;
+            }
+            None => {
+                read.row = row_ends.len();
+                row_ends.push(read.end);
+            }
+        }
+    }
+
+    result
+}
+
+/// Read depth at each position of a reference sequence; index 0 is the reference's first nucleotide.
+pub fn coverage(map: &AlignmentMap, ref_id: usize) -> Vec<u32> {
+    let ref_len = match map.refs.get(ref_id) {
+        Some(r) => r.l_ref as usize,
+        None => return Vec::new(),
+    };
+    let mut result = vec![0; ref_len];
+
+    let flags_excluded = FLAG_UNMAPPED | FLAG_SECONDARY | FLAG_QC_FAIL | FLAG_DUPLICATE;
+
+    for alignment in &map.alignments {
+        if alignment.ref_id != ref_id as i32
+            || alignment.pos < 0
+            || alignment.flag & flags_excluded != 0
+        {
+            continue;
+        }
+
+        let mut pos = alignment.pos as usize;
+        for op in &alignment.cigar {
+            let len = (op >> 4) as usize;
+            match op & 0xf {
+                CIGAR_MATCH | CIGAR_SEQ_MATCH | CIGAR_SEQ_MISMATCH => {
+                    for depth in result.iter_mut().skip(pos).take(len) {
+                        *depth += 1;
+                    }
+                    pos += len;
+                }
+                CIGAR_DELETION | CIGAR_SKIP => pos += len,
+                // Insertions, clipping, and padding don't consume the reference.
+                _ => (),
+            }
+        }
+    }
+
+    result
+}
+
+const NTS: [Nucleotide; 4] = [A, C, G, T];
+
+/// Thresholds for reporting a position as a candidate variant.
+pub struct VariantSettings {
+    /// The portion of reads, 0 to 1, that must show the same non-reference base.
+    pub min_fraction: f32,
+    pub min_depth: u32,
+    /// Bases with a Phred quality below this aren't counted. Reads without qualities are always counted.
+    pub min_qual: u8,
+}
+
+impl Default for VariantSettings {
+    fn default() -> Self {
+        Self {
+            min_fraction: 0.3,
+            min_depth: 5,
+            min_qual: 20,
+        }
+    }
+}
+
+/// A position where reads disagree with the reference sequence.
+pub struct Variant {
+    /// 1-based index on the reference.
+    pub posit: usize,
+    pub ref_nt: Nucleotide,
+    pub alt: Nucleotide,
+    /// Reads with a base counted at this position.
+    pub depth: u32,
+    pub alt_count: u32,
+}
+
+impl Variant {
+    pub fn fraction(&self) -> f32 {
+        self.alt_count as f32 / self.depth as f32
+    }
+}
+
+/// Tally the bases reads show at each reference position, and report positions where a base other
+/// than the one in `seq` passes the thresholds. `seq` is the designed sequence; its index 0
+/// corresponds to reference position 0. Only substitutions are reported.
+pub fn call_variants(
+    map: &AlignmentMap,
+    ref_id: usize,
+    seq: &[Nucleotide],
+    settings: &VariantSettings,
+) -> Vec<Variant> {
+    let len = match map.refs.get(ref_id) {
+        Some(r) => (r.l_ref as usize).min(seq.len()),
+        None => return Vec::new(),
+    };
+    // Counts of A, C, G, and T.
+    let mut tally = vec![[0_u32; 4]; len];
+
+    let flags_excluded = FLAG_UNMAPPED | FLAG_SECONDARY | FLAG_QC_FAIL | FLAG_DUPLICATE;
+
+    for alignment in &map.alignments {
+        if alignment.ref_id != ref_id as i32
+            || alignment.pos < 0
+            || alignment.flag & flags_excluded != 0
+        {
+            continue;
+        }
+
+        let bases = alignment.bases();
+        let mut pos = alignment.pos as usize;
+        let mut read_i = 0;
+
+        for op in &alignment.cigar {
+            let op_len = (op >> 4) as usize;
+            match op & 0xf {
+                CIGAR_MATCH | CIGAR_SEQ_MATCH | CIGAR_SEQ_MISMATCH => {
+                    for j in 0..op_len {
+                        let (p, r) = (pos + j, read_i + j);
+                        if p >= len || r >= bases.len() {
+                            break;
+                        }
+                        let qual = alignment.qual.get(r).copied().unwrap_or(0xff);
+                        if qual != 0xff && qual < settings.min_qual {
+                            continue;
+                        }
+                        if let Some(nt) = bases[r] {
+                            if let Some(k) = NTS.iter().position(|n| *n == nt) {
+                                tally[p][k] += 1;
+                            }
+                        }
+                    }
+                    pos += op_len;
+                    read_i += op_len;
+                }
+                CIGAR_DELETION | CIGAR_SKIP => pos += op_len,
+                // Insertions and soft clipping consume the read only.
+                CIGAR_INSERTION | CIGAR_SOFT_CLIP => read_i += op_len,
+                _ => (),
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    for (i, counts) in tally.iter().enumerate() {
+        let depth: u32 = counts.iter().sum();
+        if depth < settings.min_depth.max(1) {
+            continue;
+        }
+
+        // The most common base other than the reference.
+        let mut alt = None;
+        for (k, count) in counts.iter().enumerate() {
+            if NTS[k] == seq[i] || *count == 0 {
+                continue;
+            }
+            let better = match alt {
+                Some((_, best)) => *count > best,
+                None => true,
+            };
+            if better {
+                alt = Some((NTS[k], *count));
+            }
+        }
+
+        if let Some((alt, alt_count)) = alt {
+            if alt_count as f32 / depth as f32 >= settings.min_fraction {
+                result.push(Variant {
+                    posit: i + 1,
+                    ref_nt: seq[i],
+                    alt,
+                    depth,
+                    alt_count,
+                });
+            }
+        }
+    }
+
+    result
+}
+
+/// Wraps a reader, counting the bytes read from it. We use this to report progress through the
+/// compressed file.
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
 pub fn import(path: &Path) -> io::Result<AlignmentMap> {
+    import_with_progress(path, &AtomicU32::new(0), &AtomicBool::new(false))
+}
+
+/// Import a BAM file. `progress` is set to the portion of the file read, from 0 to 1, as f32 bits.
+/// Setting `cancel` stops the import.
+pub fn import_with_progress(
+    path: &Path,
+    progress: &AtomicU32,
+    cancel: &AtomicBool,
+) -> io::Result<AlignmentMap> {
     let file = File::open(path)?;
-    let mut decoder = GzDecoder::new(file);
-    // let mut decoder = MultiGzDecoder::new(file);
+    let file_len = file.metadata()?.len().max(1);
 
-    println!("Decoding file...");
+    // BAM files are BGZF: A series of gzip members. `GzDecoder` would stop after the first.
+    let mut decoder = MultiGzDecoder::new(CountingReader {
+        inner: file,
+        count: 0,
+    });
 
     let mut buf = Vec::new();
-    // let mut buf = [0; 80_000];
-    decoder.read_to_end(&mut buf)?;
+    let mut chunk = vec![0; READ_CHUNK_SIZE];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(ErrorKind::Interrupted, "Import cancelled"));
+        }
+
+        let n = match decoder.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            // A damaged or incomplete file; parse what we decompressed.
+            Err(e) if !buf.is_empty() => {
+                eprintln!("Error decompressing BAM file; reading what we have: {e}");
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        buf.extend_from_slice(&chunk[..n]);
+
+        let portion = decoder.get_ref().count as f32 / file_len as f32;
+        progress.store(portion.min(1.).to_bits(), Ordering::Relaxed);
+    }
 
-    println!("Decode complete. Reading...");
+    AlignmentMap::from_buf(&buf)
+}
 
-    let header = AlignmentMap::from_buf(&buf);
+/// A BAM import running in a separate thread, so large files don't block the UI.
+pub struct BamImport {
+    pub path: PathBuf,
+    pub rx: Receiver<io::Result<AlignmentMap>>,
+    /// From 0 to 1, as f32 bits.
+    pub progress: Arc<AtomicU32>,
+    pub cancel: Arc<AtomicBool>,
+}
 
-    header
+impl BamImport {
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
 
-    // AlignmentMap::from_buf(&buf)
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Run `import_with_progress` in a new thread. Poll the receiver for the result.
+pub fn import_async(path: &Path) -> BamImport {
+    let (tx, rx) = mpsc::channel();
+    let progress = Arc::new(AtomicU32::new(0));
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let path_ = path.to_owned();
+    let progress_ = Arc::clone(&progress);
+    let cancel_ = Arc::clone(&cancel);
+    thread::spawn(move || {
+        // The receiver may have been dropped, e.g. if the program is closing.
+        let _ = tx.send(import_with_progress(&path_, &progress_, &cancel_));
+    });
+
+    BamImport {
+        path: path.to_owned(),
+        rx,
+        progress,
+        cancel,
+    }
 }
 
 // pub fn import(path: &Path) -> io::Result<AlignmentMap> {
@@ -372,3 +816,23 @@ fn reg_to_bins(beg: i32, end: i32, list: &mut Vec<u16>) -> usize {
     }
     list.len()
 }
+
+/// UI state for the alignment map page.
+pub struct AlignmentMapUi {
+    /// The first reference position displayed. 0-based.
+    pub view_start: usize,
+    /// Horizontal zoom, in pixels per nucleotide.
+    pub zoom: f32,
+    /// A position to jump to. 1-based, as displayed.
+    pub jump_to: usize,
+}
+
+impl Default for AlignmentMapUi {
+    fn default() -> Self {
+        Self {
+            view_start: 0,
+            zoom: 1.,
+            jump_to: 1,
+        }
+    }
+}
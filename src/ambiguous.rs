@@ -0,0 +1,181 @@
+//! Handle sequence characters other than A, C, T, and G; e.g. N, other IUPAC ambiguity codes, and
+//! gaps. Our `Nucleotide` type only models the four bases, so we store these in their positions as a
+//! placeholder nucleotide, and track them separately. This keeps feature coordinates correct, and lets us
+//! restore them on export.
+//!
+//! Placeholders still count as their base in calculations like Tm and translation. For searching, and
+//! primer and restriction site matching, we discard matches that overlap one.
+
+use na_seq::{
+    Nucleotide::{self, A, C, G, T},
+    Seq,
+};
+
+use crate::util::{index_after_insert, RangeIncl};
+
+/// Ambiguous positions in a sequence, as (1-based index, lowercase char), sorted by index.
+pub type AmbiguousList = Vec<(usize, u8)>;
+
+/// The nucleotide we store in place of an ambiguous character.
+pub const PLACEHOLDER: Nucleotide = Nucleotide::A;
+
+/// IUPAC ambiguity codes, and gap characters.
+const AMBIGUOUS_CHARS: &[u8] = b"nrykmswbdhv-.";
+
+/// Parse a sequence from a file's bytes. Ambiguous characters are stored as `PLACEHOLDER`, and returned
+/// as (1-based index, lowercase char). Whitespace and digits, e.g. from line numbering, are skipped, as
+/// are unknown characters.
+pub fn seq_from_bytes(bytes: &[u8]) -> (Seq, AmbiguousList) {
+    let mut seq = Vec::new();
+    let mut ambiguous = Vec::new();
+
+    for byte in bytes {
+        if let Ok(nt) = Nucleotide::from_u8(*byte) {
+            seq.push(nt);
+            continue;
+        }
+
+        let char = byte.to_ascii_lowercase();
+        if AMBIGUOUS_CHARS.contains(&char) {
+            seq.push(PLACEHOLDER);
+            ambiguous.push((seq.len(), char));
+        } else if !byte.is_ascii_whitespace() && !byte.is_ascii_digit() {
+            eprintln!("Unexpected char in sequence: {:?}", *byte as char);
+        }
+    }
+
+    if !ambiguous.is_empty() {
+        eprintln!(
+            "Sequence contains {} ambiguous bases or gaps; stored as placeholders",
+            ambiguous.len()
+        );
+    }
+
+    (seq, ambiguous)
+}
+
+/// The bases an IUPAC code matches. Empty for gaps and unknown characters.
+pub fn code_bases(char: u8) -> &'static [Nucleotide] {
+    match char.to_ascii_lowercase() {
+        b'a' => &[A],
+        b'c' => &[C],
+        b'g' => &[G],
+        b't' | b'u' => &[T],
+        b'r' => &[A, G],
+        b'y' => &[C, T],
+        b's' => &[C, G],
+        b'w' => &[A, T],
+        b'k' => &[G, T],
+        b'm' => &[A, C],
+        b'b' => &[C, G, T],
+        b'd' => &[A, G, T],
+        b'h' => &[A, C, T],
+        b'v' => &[A, C, G],
+        b'n' => &[A, C, G, T],
+        _ => &[],
+    }
+}
+
+/// If true, the character is an IUPAC code matching more than one base.
+pub fn is_degenerate(char: u8) -> bool {
+    code_bases(char).len() > 1
+}
+
+/// Parse a query that may contain IUPAC degenerate codes, e.g. for searching. Returns lowercase
+/// characters; gaps, and other characters, are skipped.
+pub fn degenerate_from_str(text: &str) -> Vec<u8> {
+    text.bytes()
+        .filter(|b| !code_bases(*b).is_empty())
+        .map(|b| b.to_ascii_lowercase())
+        .collect()
+}
+
+/// Write ambiguous characters back into a sequence's bytes, e.g. for export.
+pub fn restore(seq: &mut [u8], ambiguous: &[(usize, u8)]) {
+    for (i, char) in ambiguous {
+        if *i >= 1 && *i <= seq.len() {
+            seq[i - 1] = *char;
+        }
+    }
+}
+
+/// The complement of an ambiguous character; e.g. R (A or G) pairs with Y (C or T). Gaps, and codes
+/// that are their own complement, are unchanged.
+pub fn complement(char: u8) -> u8 {
+    match char {
+        b'r' => b'y',
+        b'y' => b'r',
+        b'k' => b'm',
+        b'm' => b'k',
+        b'b' => b'v',
+        b'v' => b'b',
+        b'd' => b'h',
+        b'h' => b'd',
+        _ => char,
+    }
+}
+
+/// If true, the range includes an ambiguous position. Handles ranges that wrap the origin.
+pub fn overlaps(ambiguous: &[(usize, u8)], range: RangeIncl) -> bool {
+    ambiguous.iter().any(|(i, _)| {
+        if range.start <= range.end {
+            range.contains(*i)
+        } else {
+            *i >= range.start || *i <= range.end
+        }
+    })
+}
+
+/// Positions within `range`, e.g. for a sequence extracted from it, relative to its start. Handles
+/// ranges that wrap the origin.
+pub fn in_range(ambiguous: &[(usize, u8)], range: RangeIncl, seq_len: usize) -> AmbiguousList {
+    let mut result: Vec<_> = ambiguous
+        .iter()
+        .filter(|(i, _)| {
+            if range.start <= range.end {
+                range.contains(*i)
+            } else {
+                *i >= range.start || *i <= range.end
+            }
+        })
+        .map(|(i, char)| {
+            let offset = if *i >= range.start {
+                i - range.start
+            } else {
+                i + seq_len - range.start
+            };
+            (offset + 1, *char)
+        })
+        .collect();
+
+    // For a range that wraps the origin, positions past it come first.
+    result.sort_by_key(|(i, _)| *i);
+    result
+}
+
+/// Adjust positions after the sequence changes at 1-based `edit_pos`. Uses the same convention as
+/// `remap_after_edit`. Positions within a removed range are dropped.
+pub fn remap(ambiguous: &mut AmbiguousList, edit_pos: usize, delta: isize) {
+    if delta > 0 {
+        for (i, _) in ambiguous.iter_mut() {
+            *i = index_after_insert(*i, edit_pos, delta as usize);
+        }
+    } else if delta < 0 {
+        let removed = RangeIncl::new(edit_pos, edit_pos + delta.unsigned_abs() - 1);
+        ambiguous.retain(|(i, _)| !removed.contains(*i));
+        for (i, _) in ambiguous.iter_mut() {
+            if *i > removed.end {
+                *i -= removed.len();
+            }
+        }
+    }
+}
+
+/// Add positions from newly-inserted nucleotides, e.g. pasted. `new` positions are relative to the
+/// insert; they're shifted by `offset`, the count of nucleotides before it.
+pub fn insert(ambiguous: &mut AmbiguousList, new: &[(usize, u8)], offset: usize) {
+    for (i, char) in new {
+        ambiguous.push((i + offset, *char));
+    }
+    ambiguous.sort_by_key(|(i, _)| *i);
+}
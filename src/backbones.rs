@@ -4,9 +4,9 @@
 //! [AddGene Backbone page](https://www.addgene.org/collections/empty-backbones/)
 // todo: Add more of those.
 
-use std::{fmt, fmt::Formatter};
+use std::{collections::HashSet, fmt, fmt::Formatter};
 
-use na_seq::{seq_complement, seq_from_str, SeqTopology};
+use na_seq::{seq_complement, seq_from_str, seq_to_u8_lower, Nucleotide, SeqTopology};
 use strum_macros::EnumIter;
 
 // todo: Consider auto-inferring the T7 and other promoter site instead of hard-coding.
@@ -130,6 +130,8 @@ pub enum BackboneGene {
 pub enum CloningTechnique {
     RestrictionEnzyme,
     Pcr,
+    /// Type IIS enzymes; see `golden_gate`.
+    GoldenGate,
 }
 
 pub struct Backbone {
@@ -209,6 +211,8 @@ impl Backbone {
     pub fn insert_loc(&self, technique: CloningTechnique) -> Option<usize> {
         match technique {
             CloningTechnique::RestrictionEnzyme => None,
+            // Set by the parts' overhangs.
+            CloningTechnique::GoldenGate => None,
             CloningTechnique::Pcr => {
                 let mut loc = match self.rbs {
                     Some(rbs) => {
@@ -280,6 +284,7 @@ impl Backbone {
                 label: "Promoter".to_string(),
                 color_override: None,
                 notes: vec![],
+                codon_start: 1,
             });
         }
 
@@ -291,6 +296,7 @@ impl Backbone {
                 label: "RBS".to_string(),
                 color_override: None,
                 notes: vec![],
+                codon_start: 1,
             });
         }
 
@@ -302,6 +308,7 @@ impl Backbone {
                 label: "His tag".to_string(),
                 color_override: None,
                 notes: vec![],
+                codon_start: 1,
             })
         }
 
@@ -319,6 +326,8 @@ impl Backbone {
             features,
             primers: Vec::new(),
             metadata: Default::default(), // todo: A/R
+            bookmarks: Vec::new(),
+            ambiguous: Vec::new(),
         }
     }
 }
@@ -369,6 +378,156 @@ impl<'a> BackboneFilters {
     }
 }
 
+/// Seed length used when comparing a sequence to library backbones.
+const DETECT_K: usize = 16;
+/// Report a backbone only if at least this portion of it is found in the sequence.
+const DETECT_SCORE_MIN: f32 = 0.6;
+/// Regions not found in the backbone shorter than this aren't reported as an insert; they're
+/// likely point mutations, or small scars.
+const INSERT_LEN_MIN: usize = 20;
+
+/// The library backbone an opened sequence is likely based on.
+pub struct BackboneMatch {
+    /// Index in the backbone library.
+    pub backbone_i: usize,
+    /// 0 to 1: The portion of the backbone's seeds found in the sequence.
+    pub score: f32,
+    /// The sequence contains the backbone's reverse complement.
+    pub reverse: bool,
+    /// The longest region of the sequence not found in the backbone; likely the insert.
+    pub insert: Option<RangeIncl>,
+}
+
+/// Seeds of length `DETECT_K`, at each position. Use with `seq_for_seeds`.
+fn seeds(seq: &[u8]) -> Vec<&[u8]> {
+    if seq.len() < DETECT_K {
+        return Vec::new();
+    }
+    (0..seq.len() - DETECT_K + 1)
+        .map(|i| &seq[i..i + DETECT_K])
+        .collect()
+}
+
+/// For circular sequences, extend the sequence by its start, so seeds may wrap the origin.
+fn seq_for_seeds(seq: &[Nucleotide], topology: SeqTopology) -> Vec<u8> {
+    let mut result = seq_to_u8_lower(seq);
+    if topology == SeqTopology::Circular && seq.len() >= DETECT_K {
+        result.extend_from_within(0..DETECT_K - 1);
+    }
+    result
+}
+
+/// The longest run of `false` values, treating the slice as circular if specified. Returns a
+/// 1-based range, and its length.
+fn longest_uncovered(covered: &[bool], topology: SeqTopology) -> Option<(RangeIncl, usize)> {
+    let n = covered.len();
+    if n == 0 || covered.iter().all(|c| !c) {
+        return None;
+    }
+
+    // For circular sequences, start scanning after a covered position, so runs that wrap the
+    // origin are counted once.
+    let offset = match topology {
+        SeqTopology::Circular => covered.iter().position(|c| *c).unwrap_or_default() + 1,
+        SeqTopology::Linear => 0,
+    };
+
+    let mut best: Option<(usize, usize)> = None; // (start index, len), 0-based.
+    let mut run_start = None;
+
+    for j in 0..=n {
+        let i = (j + offset) % n;
+        if j < n && !covered[i] {
+            if run_start.is_none() {
+                run_start = Some(j);
+            }
+            continue;
+        }
+
+        if let Some(start) = run_start {
+            let len = j - start;
+            if best.map(|(_, l)| len > l).unwrap_or(true) {
+                best = Some(((start + offset) % n, len));
+            }
+            run_start = None;
+        }
+    }
+
+    best.map(|(start, len)| (RangeIncl::new(start + 1, (start + len - 1) % n + 1), len))
+}
+
+/// Find which library backbone a sequence is likely based on, by comparing seeds. Also reports
+/// the divergent region, which is likely the insert.
+pub fn detect_backbone(
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    backbones: &[Backbone],
+) -> Option<BackboneMatch> {
+    if seq.len() < DETECT_K {
+        return None;
+    }
+
+    let seq_fwd = seq_for_seeds(seq, topology);
+    let seq_rev = seq_for_seeds(&seq_complement(seq), topology);
+    let seeds_fwd: HashSet<&[u8]> = seeds(&seq_fwd).into_iter().collect();
+    let seeds_rev: HashSet<&[u8]> = seeds(&seq_rev).into_iter().collect();
+
+    let mut best: Option<BackboneMatch> = None;
+
+    for (i, backbone) in backbones.iter().enumerate() {
+        let bb = seq_for_seeds(&backbone.seq, backbone.seq_topology);
+        let bb_seeds = seeds(&bb);
+        if bb_seeds.is_empty() {
+            continue;
+        }
+
+        let found_fwd = bb_seeds.iter().filter(|s| seeds_fwd.contains(*s)).count();
+        let found_rev = bb_seeds.iter().filter(|s| seeds_rev.contains(*s)).count();
+
+        let reverse = found_rev > found_fwd;
+        let score = found_fwd.max(found_rev) as f32 / bb_seeds.len() as f32;
+
+        if score < DETECT_SCORE_MIN || best.as_ref().map(|b| score <= b.score).unwrap_or(false) {
+            continue;
+        }
+
+        best = Some(BackboneMatch {
+            backbone_i: i,
+            score,
+            reverse,
+            insert: None,
+        });
+    }
+
+    let mut result = best?;
+
+    // Mark which positions of the sequence are covered by a backbone seed.
+    let backbone = &backbones[result.backbone_i];
+    let bb = if result.reverse {
+        seq_for_seeds(&seq_complement(&backbone.seq), backbone.seq_topology)
+    } else {
+        seq_for_seeds(&backbone.seq, backbone.seq_topology)
+    };
+    let bb_seeds: HashSet<&[u8]> = seeds(&bb).into_iter().collect();
+
+    let n = seq.len();
+    let mut covered = vec![false; n];
+    for (i, seed) in seeds(&seq_fwd).iter().enumerate() {
+        if bb_seeds.contains(seed) {
+            for j in i..i + DETECT_K {
+                covered[j % n] = true;
+            }
+        }
+    }
+
+    result.insert = match longest_uncovered(&covered, topology) {
+        Some((range, len)) if len >= INSERT_LEN_MIN => Some(range),
+        _ => None,
+    };
+
+    Some(result)
+}
+
 /// Load this at program init.
 pub fn load_backbone_library() -> Vec<Backbone> {
     // Mut to populate data below.
@@ -0,0 +1,211 @@
+//! This module contains code for recognizing expression cassettes: A promoter, a ribosome binding site,
+//! a coding region, and a terminator, in order and on the same strand. We compose existing features
+//! using distance and order rules, vice searching the sequence directly.
+
+use crate::{
+    cloning::{RBS_BUFFER_MAX, RBS_BUFFER_MIN},
+    misc_types::{Feature, FeatureDirection, FeatureType},
+    util::RangeIncl,
+};
+
+/// Promoters ending this far upstream of the coding region's start, or closer, are included.
+const PROMOTER_MAX_DIST: usize = 500;
+/// The RBS must end this close to the start codon.
+const RBS_MAX_DIST: usize = 30;
+/// Terminators starting this far downstream of the coding region's end, or closer, are included.
+const TERMINATOR_MAX_DIST: usize = 300;
+
+/// One element of a cassette, copied from its feature.
+#[derive(Clone)]
+pub struct CassettePart {
+    pub label: String,
+    pub range: RangeIncl,
+}
+
+impl CassettePart {
+    fn from_feature(feature: &Feature) -> Self {
+        Self {
+            label: feature.label(),
+            range: feature.range,
+        }
+    }
+}
+
+/// A coding region, and the regulatory elements found around it.
+pub struct Cassette {
+    pub direction: FeatureDirection,
+    pub promoter: Option<CassettePart>,
+    pub rbs: Option<CassettePart>,
+    pub cds: CassettePart,
+    pub terminator: Option<CassettePart>,
+}
+
+impl Cassette {
+    pub fn complete(&self) -> bool {
+        self.promoter.is_some() && self.rbs.is_some() && self.terminator.is_some()
+    }
+
+    /// Nucleotides between the RBS and the start codon, if there's an RBS.
+    pub fn rbs_spacing(&self) -> Option<usize> {
+        let rbs = self.rbs.as_ref()?;
+        Some(match self.direction {
+            FeatureDirection::Reverse => rbs.range.start - self.cds.range.end - 1,
+            _ => self.cds.range.start - rbs.range.end - 1,
+        })
+    }
+
+    /// If false, the RBS is too close or too far from the start codon for efficient translation.
+    pub fn rbs_spacing_ok(&self) -> bool {
+        match self.rbs_spacing() {
+            Some(s) => (RBS_BUFFER_MIN..=RBS_BUFFER_MAX).contains(&(s as isize)),
+            None => true,
+        }
+    }
+
+    /// The parts missing from this cassette, for display.
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut result = Vec::new();
+        if self.promoter.is_none() {
+            result.push("promoter");
+        }
+        if self.rbs.is_none() {
+            result.push("RBS");
+        }
+        if self.terminator.is_none() {
+            result.push("terminator");
+        }
+        result
+    }
+
+    /// The full cassette, from its first part to its last.
+    pub fn range(&self) -> RangeIncl {
+        let parts = [&self.promoter, &self.rbs, &self.terminator];
+        let mut result = self.cds.range;
+        for part in parts.into_iter().flatten() {
+            result.start = result.start.min(part.range.start);
+            result.end = result.end.max(part.range.end);
+        }
+        result
+    }
+
+    /// A feature spanning the cassette, e.g. to annotate it as a unit.
+    pub fn to_feature(&self) -> Feature {
+        Feature {
+            range: self.range(),
+            direction: self.direction,
+            label: format!("{} cassette", self.cds.label),
+            ..Default::default()
+        }
+    }
+}
+
+/// Distance upstream of the coding region, on its strand. `None` if `part` isn't upstream.
+fn dist_upstream(part: RangeIncl, cds: RangeIncl, direction: FeatureDirection) -> Option<usize> {
+    match direction {
+        FeatureDirection::Reverse if part.start > cds.end => Some(part.start - cds.end - 1),
+        FeatureDirection::Reverse => None,
+        _ if part.end < cds.start => Some(cds.start - part.end - 1),
+        _ => None,
+    }
+}
+
+/// Find the closest feature of a given type upstream of, or downstream of the coding region, within
+/// `max_dist`. Features must be on the coding region's strand, or have no direction.
+fn find_nearest(
+    features: &[Feature],
+    feature_type: FeatureType,
+    cds: RangeIncl,
+    direction: FeatureDirection,
+    upstream: bool,
+    max_dist: usize,
+) -> Option<CassettePart> {
+    let mut best: Option<(usize, &Feature)> = None;
+
+    for feature in features {
+        if feature.feature_type != feature_type
+            || feature.range.start > feature.range.end
+            || !(feature.direction == direction || feature.direction == FeatureDirection::None)
+        {
+            continue;
+        }
+
+        let dist = if upstream {
+            dist_upstream(feature.range, cds, direction)
+        } else {
+            // Downstream on the forward strand is upstream on the reverse.
+            let opposite = match direction {
+                FeatureDirection::Reverse => FeatureDirection::Forward,
+                _ => FeatureDirection::Reverse,
+            };
+            dist_upstream(feature.range, cds, opposite)
+        };
+
+        if let Some(d) = dist {
+            let closer = match best {
+                Some((best_d, _)) => d < best_d,
+                None => true,
+            };
+            if d <= max_dist && closer {
+                best = Some((d, feature));
+            }
+        }
+    }
+
+    best.map(|(_, f)| CassettePart::from_feature(f))
+}
+
+/// Find expression cassettes, anchored on each coding region. Coding regions with no regulatory
+/// elements nearby aren't reported. todo: Handle features that wrap the origin.
+pub fn find_cassettes(features: &[Feature]) -> Vec<Cassette> {
+    let mut result = Vec::new();
+
+    for feature in features {
+        if feature.feature_type != FeatureType::CodingRegion
+            || feature.range.start > feature.range.end
+        {
+            continue;
+        }
+
+        // Coding regions without a direction are assumed to be forward.
+        let direction = match feature.direction {
+            FeatureDirection::Reverse => FeatureDirection::Reverse,
+            _ => FeatureDirection::Forward,
+        };
+        let cds = feature.range;
+
+        let cassette = Cassette {
+            direction,
+            promoter: find_nearest(
+                features,
+                FeatureType::Promoter,
+                cds,
+                direction,
+                true,
+                PROMOTER_MAX_DIST,
+            ),
+            rbs: find_nearest(
+                features,
+                FeatureType::RibosomeBindSite,
+                cds,
+                direction,
+                true,
+                RBS_MAX_DIST,
+            ),
+            cds: CassettePart::from_feature(feature),
+            terminator: find_nearest(
+                features,
+                FeatureType::Terminator,
+                cds,
+                direction,
+                false,
+                TERMINATOR_MAX_DIST,
+            ),
+        };
+
+        if cassette.promoter.is_some() || cassette.rbs.is_some() || cassette.terminator.is_some() {
+            result.push(cassette);
+        }
+    }
+
+    result
+}
@@ -10,14 +10,17 @@ use na_seq::{
     insert_into_seq,
     ligation::{filter_multiple_seqs, filter_unique_cutters, find_common_res},
     restriction_enzyme::{find_re_matches, ReMatch, RestrictionEnzyme},
-    seq_to_str_lower, AminoAcid, CodingResult, Nucleotide, Seq,
+    seq_complement, seq_to_str_lower, AminoAcid, CodingResult,
+    Nucleotide::{self, A, C, G, T},
+    Seq, SeqTopology,
 };
 
 use crate::{
-    backbones::Backbone,
+    ambiguous,
+    backbones::{Backbone, BackboneMatch, ExpressionHost},
     file_io::GenericData,
     gui::navigation::{Page, PageSeq},
-    misc_types::{Feature, FeatureDirection, FeatureType},
+    misc_types::{range_covers, Feature, FeatureDirection, FeatureType},
     primer::{make_cloning_primers, Primer},
     state::State,
     util::RangeIncl,
@@ -34,6 +37,31 @@ pub const RBS_BUFFER: usize = 7;
 pub const RBS_BUFFER_MIN: isize = 4;
 pub const RBS_BUFFER_MAX: isize = 11;
 
+/// The Shine-Dalgarno consensus sequence, found upstream of bacterial start codons.
+const SHINE_DALGARNO: [Nucleotide; 6] = [A, G, G, A, G, G];
+/// Search this many nucleotides upstream of the start codon for a Shine-Dalgarno sequence.
+const SD_SEARCH_WINDOW: usize = 20;
+/// The minimum number of Shine-Dalgarno consensus nucleotides that must match to pass.
+const SD_MIN_MATCHES: usize = 4;
+
+/// Translate this many codons on each side of an insert junction.
+const JUNCTION_CODONS: usize = 6;
+
+/// Kozak consensus (gccRccATGG), by position relative to the A in the start codon. (ie +1)
+/// The capitalized positions, -3 and +4, are the most important.
+const KOZAK: [(isize, &[Nucleotide], char); 10] = [
+    (-6, &[G], 'g'),
+    (-5, &[C], 'c'),
+    (-4, &[C], 'c'),
+    (-3, &[A, G], 'R'),
+    (-2, &[C], 'c'),
+    (-1, &[C], 'c'),
+    (0, &[A], 'A'),
+    (1, &[T], 'T'),
+    (2, &[G], 'G'),
+    (3, &[G], 'G'),
+];
+
 pub struct CloningState {
     pub backbone_selected: BackboneSelected,
     /// Note: This is only used currently if using the opened file as the BB; otherwise
@@ -50,7 +78,13 @@ pub struct CloningState {
     pub remove_stop_codons: bool,
     /// Work-in-progress cloning product sequence.
     pub product_seq: Seq,
+    /// The primers designed for the last product made from this design. We display these on the
+    /// cloning page.
     pub product_primers: Vec<Primer>,
+    /// Determines how we evaluate the context around the start codon; eg Shine-Dalgarno vs Kozak.
+    pub expression_host: ExpressionHost,
+    /// The library backbone this tab's sequence appears to be built on, if we've checked.
+    pub backbone_detected: Option<BackboneMatch>,
 }
 
 impl Default for CloningState {
@@ -68,6 +102,8 @@ impl Default for CloningState {
             remove_stop_codons: Default::default(),
             product_seq: Default::default(),
             product_primers: Vec::new(),
+            expression_host: ExpressionHost::Bacterial,
+            backbone_detected: None,
         }
     }
 }
@@ -130,10 +166,21 @@ impl CloningState {
                 self.insert_loc,
                 seq_insert.len(),
                 &self.product_seq,
+                self.expression_host,
             );
         }
     }
 
+    /// The 1-based index in the product that sets the reading frame across the insert junctions: The start
+    /// of a tag upstream of the insert (e.g. an N-terminal His tag) if present; otherwise, the insert
+    /// itself, which we assume starts with its start codon.
+    pub fn junction_frame_start(&self, backbone: &Backbone) -> usize {
+        match backbone.his_tag {
+            Some(tag) if tag.end < self.insert_loc => tag.start,
+            _ => self.insert_loc,
+        }
+    }
+
     pub fn get_backbone<'a>(&'a self, lib: &'a [Backbone]) -> Option<&'a Backbone> {
         match self.backbone_selected {
             BackboneSelected::Library(i) => {
@@ -184,24 +231,114 @@ pub struct CloningInsertData {
     pub features_loaded: Vec<Feature>,
     /// `cloning_ins_features_loaded` indexes reference this sequence.
     pub seq_loaded: Seq,
+    pub topology_loaded: SeqTopology,
     pub feature_selected: Option<usize>,
     pub seq_insert: Seq,
     pub seq_input: String,
     pub show_insert_picker: bool,
+    /// For selecting an insert by index, e.g. from FASTA files, which don't have features. 1-based, inclusive.
+    pub manual_start: usize,
+    pub manual_end: usize,
+    pub manual_dir: FeatureDirection,
+}
+
+impl CloningInsertData {
+    /// Validate the manually-entered insert indices against the loaded sequence.
+    pub fn manual_range(&self) -> Result<RangeIncl, String> {
+        let seq_len = self.seq_loaded.len();
+
+        if self.manual_start < 1 || self.manual_end < 1 {
+            return Err("Indices start at 1".to_owned());
+        }
+        if self.manual_start > seq_len || self.manual_end > seq_len {
+            return Err(format!(
+                "Indices must be within the sequence (1 - {seq_len})"
+            ));
+        }
+        // On circular sequences, a start after the end wraps the origin.
+        if self.manual_start > self.manual_end && self.topology_loaded == SeqTopology::Linear {
+            return Err("Start must not be after end".to_owned());
+        }
+
+        Ok(RangeIncl::new(self.manual_start, self.manual_end))
+    }
+
+    /// Create a pseudo-feature from the manually-entered indices, and select it as the insert.
+    pub fn add_manual_insert(&mut self) {
+        let range = match self.manual_range() {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Invalid insert range: {e}");
+                return;
+            }
+        };
+
+        let seq_this_ft = match range.index_seq_topology(&self.seq_loaded, self.topology_loaded) {
+            Some(s) => s,
+            None => {
+                eprintln!("Error indexing the insert sequence");
+                return;
+            }
+        };
+
+        self.seq_insert = seq_oriented(&seq_this_ft, self.manual_dir);
+        self.seq_input = seq_to_str_lower(&self.seq_insert);
+
+        // Select a feature with the same range and direction if there is one, e.g. an insert added
+        // previously, vice adding a duplicate.
+        let existing = self
+            .features_loaded
+            .iter()
+            .position(|f| f.range == range && f.direction == self.manual_dir);
+
+        self.feature_selected = match existing {
+            Some(i) => Some(i),
+            None => {
+                self.features_loaded.push(Feature {
+                    range,
+                    direction: self.manual_dir,
+                    label: "Manual insert".to_owned(),
+                    ..Default::default()
+                });
+                Some(self.features_loaded.len() - 1)
+            }
+        };
+    }
+}
+
+/// Insert sequences are read 5' to 3' in the feature's direction; reverse-complement reverse features.
+pub fn seq_oriented(seq: &[Nucleotide], direction: FeatureDirection) -> Seq {
+    if direction == FeatureDirection::Reverse {
+        seq_complement(seq)
+    } else {
+        seq.to_vec()
+    }
 }
 
 /// Given a set of features and the sequence their ranges map to, set up our
 /// insert sequences.
-pub fn setup_insert_seqs(state: &mut State, features: Vec<Feature>, seq: Seq) {
+pub fn setup_insert_seqs(
+    state: &mut State,
+    features: Vec<Feature>,
+    seq: Seq,
+    topology: SeqTopology,
+) {
     // todo: Unecessary cloning if loading from file.
     state.ui.cloning_insert.features_loaded = features;
     state.ui.cloning_insert.seq_loaded = seq;
+    state.ui.cloning_insert.topology_loaded = topology;
+
+    state.ui.cloning_insert.manual_start = 1;
+    state.ui.cloning_insert.manual_end = state.ui.cloning_insert.seq_loaded.len();
 
     // Choose the initial insert as the CDS or gene with the largest len.
     let mut best = None;
     let mut best_len = 0;
     for (i, feature) in state.ui.cloning_insert.features_loaded.iter().enumerate() {
-        let len = feature.len(state.generic[state.active].seq.len());
+        let len = feature.range.len_topology(
+            state.ui.cloning_insert.seq_loaded.len(),
+            state.ui.cloning_insert.topology_loaded,
+        );
         if (feature.feature_type == FeatureType::CodingRegion
             || feature.feature_type == FeatureType::Gene)
             && len > best_len
@@ -214,10 +351,14 @@ pub fn setup_insert_seqs(state: &mut State, features: Vec<Feature>, seq: Seq) {
     if let Some(feat_i) = best {
         let feature = &state.ui.cloning_insert.features_loaded[feat_i];
 
-        if let Some(seq_this_ft) = feature.range.index_seq(&state.ui.cloning_insert.seq_loaded) {
+        if let Some(seq_this_ft) = feature.range.index_seq_topology(
+            &state.ui.cloning_insert.seq_loaded,
+            state.ui.cloning_insert.topology_loaded,
+        ) {
             state.ui.cloning_insert.feature_selected = best;
-            state.ui.cloning_insert.seq_insert = seq_this_ft.to_owned();
-            state.ui.cloning_insert.seq_input = seq_to_str_lower(seq_this_ft);
+            state.ui.cloning_insert.seq_insert = seq_oriented(&seq_this_ft, feature.direction);
+            state.ui.cloning_insert.seq_input =
+                seq_to_str_lower(&state.ui.cloning_insert.seq_insert);
         }
     }
 }
@@ -233,6 +374,10 @@ pub fn make_product_tab(state: &mut State, generic: Option<GenericData>) {
         None => state.generic[state.active].clone(),
     };
 
+    // The design stays with the tab it was made in; the product tab starts with a fresh one.
+    let design_i = state.active;
+    let insert_loc = state.cloning[design_i].insert_loc;
+
     state.generic.push(generic);
 
     // state
@@ -242,16 +387,17 @@ pub fn make_product_tab(state: &mut State, generic: Option<GenericData>) {
     state.volatile.push(Default::default());
     state.tabs_open.push(Default::default());
     state.ab1_data.push(Default::default());
+    state.cloning.push(Default::default());
 
     state.active = state.generic.len() - 1;
 
     // Make sure to create cloning primers before performing the insert, or the result will be wrong.
-    make_cloning_primers(state);
+    state.cloning[design_i].product_primers = make_cloning_primers(state, insert_loc);
 
     // todo: Unecessary clone? Due to borrow rules.
     let mut insert = state.ui.cloning_insert.seq_insert.clone();
 
-    state.insert_nucleotides(&insert, state.cloning.insert_loc);
+    state.insert_nucleotides(&insert, insert_loc);
 
     let label = match state.ui.cloning_insert.feature_selected {
         Some(i) => state.ui.cloning_insert.features_loaded[i].label.clone(),
@@ -261,10 +407,7 @@ pub fn make_product_tab(state: &mut State, generic: Option<GenericData>) {
     // todo: Eventually, we'll likely be pulling in sequences already associated with a feature;
     // todo: Use the already existing data instead.
     state.generic[state.active].features.push(Feature {
-        range: RangeIncl::new(
-            state.cloning.insert_loc,
-            state.cloning.insert_loc + insert.len() - 1,
-        ),
+        range: RangeIncl::new(insert_loc, insert_loc + insert.len() - 1),
         label,
         feature_type: FeatureType::CodingRegion,
         direction: FeatureDirection::Forward,
@@ -278,6 +421,66 @@ pub fn make_product_tab(state: &mut State, generic: Option<GenericData>) {
     state.ui.selected_item = Selection::Feature(state.generic[state.active].features.len() - 1);
 }
 
+/// Create a new tab containing a region of the active sequence; e.g. a gene and its flanks. Features
+/// entirely within the region are carried over. The region may wrap the origin of a circular sequence;
+/// the new sequence is linear.
+pub fn make_region_tab(state: &mut State, range: RangeIncl, name: &str) {
+    let data = &state.generic[state.active];
+    let seq_len = data.seq.len();
+
+    if range.start < 1 || range.end < 1 || range.start > seq_len || range.end > seq_len {
+        eprintln!("Invalid region to extract: {range}");
+        return;
+    }
+
+    let region_seq = match range.index_seq_topology(&data.seq, data.topology) {
+        Some(s) => s,
+        None => {
+            eprintln!("Invalid region to extract: {range}");
+            return;
+        }
+    };
+
+    let mut region_features = Vec::new();
+    for feature in &data.features {
+        if !range_covers(range, feature.range, seq_len) {
+            continue;
+        }
+
+        // Find the indexes in the new sequence. This handles ranges that wrap the origin.
+        let offset = if feature.range.start >= range.start {
+            feature.range.start - range.start
+        } else {
+            feature.range.start + seq_len - range.start
+        };
+
+        let mut region_feature = feature.clone();
+        region_feature.range.start = offset + 1;
+        region_feature.range.end = offset + feature.range.len_topology(seq_len, data.topology);
+
+        region_features.push(region_feature);
+    }
+
+    let region_ambiguous = ambiguous::in_range(&data.ambiguous, range, seq_len);
+
+    state.add_tab();
+    state.tabs_open.push(Default::default());
+
+    let data = &mut state.generic[state.active];
+    data.seq = region_seq;
+    data.ambiguous = region_ambiguous;
+    data.topology = SeqTopology::Linear;
+    data.features = region_features;
+    data.metadata.plasmid_name = name.to_owned();
+    state.volatile[state.active].unsaved = true; // Not yet saved to a file.
+
+    state.sync_seq_related(None);
+    state.reset_selections();
+
+    state.ui.page = Page::Sequence;
+    state.ui.page_seq = PageSeq::View;
+}
+
 /// Check if the (eg His) tag is in frame with the start of the coding region, and that there is
 /// no stop codon between the end of the coding region, and start of the sequence.
 ///
@@ -399,6 +602,162 @@ pub fn find_re_candidates<'a>(
     (res, matches_vector_common, matches_insert_common)
 }
 
+/// An assessment of the sequence around a start codon, for expression.
+#[derive(Default)]
+pub struct StartContext {
+    pub status: Status,
+    /// Each consensus nucleotide, and whether the sequence matches it.
+    pub consensus: Vec<(char, bool)>,
+    /// Shine-Dalgarno only: The number of nucleotides between it, and the start codon.
+    pub spacing: Option<usize>,
+}
+
+/// Get a nucleotide using a 0-based index that may be outside the sequence's range, treating it as
+/// circular.
+fn nt_circular(seq: &[Nucleotide], i: isize) -> Nucleotide {
+    seq[i.rem_euclid(seq.len() as isize) as usize]
+}
+
+/// A codon near a junction between the insert and backbone.
+pub struct JunctionCodon {
+    /// 1-based index of the codon's first nucleotide, in the product.
+    pub seq_i: usize,
+    pub result: CodingResult,
+    /// If true, this codon contains nucleotides from both sides of the junction.
+    pub spans: bool,
+}
+
+/// Translate the codons around a junction in the cloning product, in the frame set by `frame_start`
+/// (1-based). `junction` is the 1-based index of the first nucleotide after the junction.
+pub fn translate_junction(
+    seq: &[Nucleotide],
+    frame_start: usize,
+    junction: usize,
+) -> Vec<JunctionCodon> {
+    let mut result = Vec::new();
+    if frame_start < 1 || junction < 1 {
+        return result;
+    }
+
+    // The start of the codon containing the junction's first nucleotide.
+    let offset = (junction as isize - frame_start as isize).rem_euclid(3) as usize;
+    let junction_codon = junction - offset;
+
+    let mut start = junction_codon;
+    for _ in 0..JUNCTION_CODONS {
+        if start <= 3 {
+            break;
+        }
+        start -= 3;
+    }
+
+    for seq_i in (start..=junction_codon + 3 * JUNCTION_CODONS).step_by(3) {
+        // Offset for 1-based indexing.
+        if seq_i + 1 >= seq.len() {
+            break;
+        }
+
+        result.push(JunctionCodon {
+            seq_i,
+            result: AminoAcid::from_codons([seq[seq_i - 1], seq[seq_i], seq[seq_i + 1]]),
+            spans: seq_i < junction && seq_i + 2 >= junction,
+        });
+    }
+
+    result
+}
+
+/// Evaluate the context upstream of (and for Kozak, just downstream of) a start codon. For bacterial
+/// hosts, we look for the best Shine-Dalgarno match and its spacing. For eukaryotic ones, we compare
+/// against the Kozak consensus. `start` is the 1-based index of the start codon's first nucleotide.
+pub fn start_context(seq: &[Nucleotide], start: usize, host: ExpressionHost) -> StartContext {
+    if seq.len() < SD_SEARCH_WINDOW || start < 1 || start > seq.len() {
+        return StartContext {
+            status: Status::NotApplicable,
+            ..Default::default()
+        };
+    }
+
+    let start = start as isize - 1; // 0-based.
+
+    match host {
+        ExpressionHost::Bacterial => {
+            // (matches, spacing)
+            let mut best: Option<(usize, usize)> = None;
+
+            for spacing in 0..=SD_SEARCH_WINDOW - SHINE_DALGARNO.len() {
+                let sd_start = start - (spacing + SHINE_DALGARNO.len()) as isize;
+
+                let matches = SHINE_DALGARNO
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, nt)| nt_circular(seq, sd_start + *i as isize) == **nt)
+                    .count();
+
+                // On ties, prefer the spacing closest to our ideal.
+                let better = match best {
+                    Some((best_matches, best_spacing)) => {
+                        matches > best_matches
+                            || (matches == best_matches
+                                && spacing.abs_diff(RBS_BUFFER) < best_spacing.abs_diff(RBS_BUFFER))
+                    }
+                    None => true,
+                };
+
+                if better {
+                    best = Some((matches, spacing));
+                }
+            }
+
+            let (matches, spacing) = best.unwrap();
+            let sd_start = start - (spacing + SHINE_DALGARNO.len()) as isize;
+
+            let consensus = SHINE_DALGARNO
+                .iter()
+                .zip("AGGAGG".chars())
+                .enumerate()
+                .map(|(i, (nt, ch))| (ch, nt_circular(seq, sd_start + i as isize) == *nt))
+                .collect();
+
+            let spacing_ok =
+                spacing as isize >= RBS_BUFFER_MIN && spacing as isize <= RBS_BUFFER_MAX;
+
+            let status = if matches >= SD_MIN_MATCHES && spacing_ok {
+                Status::Pass
+            } else {
+                Status::Fail
+            };
+
+            StartContext {
+                status,
+                consensus,
+                spacing: Some(spacing),
+            }
+        }
+        _ => {
+            let consensus: Vec<(char, bool)> = KOZAK
+                .iter()
+                .map(|(offset, accepted, ch)| {
+                    (*ch, accepted.contains(&nt_circular(seq, start + offset)))
+                })
+                .collect();
+
+            // A strong Kozak context has a purine at -3, and G at +4.
+            let status = if consensus[3].1 && consensus[9].1 {
+                Status::Pass
+            } else {
+                Status::Fail
+            };
+
+            StartContext {
+                status,
+                consensus,
+                spacing: None,
+            }
+        }
+    }
+}
+
 /// Validation checks for cloning.
 #[derive(Default)]
 pub struct CloneStatus {
@@ -408,6 +767,7 @@ pub struct CloneStatus {
     // todo: Check for terminator?
     pub direction: Status,
     pub tag_frame: Status,
+    pub start_context: StartContext,
     // pub primer_quality: Status,
     // pub re_dist: Status,
 }
@@ -418,6 +778,7 @@ impl CloneStatus {
         insert_loc: usize,
         insert_len: usize,
         seq_product: &[Nucleotide],
+        expression_host: ExpressionHost,
     ) -> Self {
         let rbs_dist = match backbone.rbs {
             Some(rbs) => {
@@ -467,12 +828,16 @@ impl CloneStatus {
 
         let tag_frame = tag_in_frame(seq_product, &his_tag_shifted, insert_loc);
 
+        // Note: As above, we assume the insert starts with its start codon.
+        let start_context = start_context(seq_product, insert_loc, expression_host);
+
         Self {
             rbs_dist,
             downstream_of_promoter,
             upstream_of_terminator,
             direction,
             tag_frame,
+            start_context,
         }
     }
 }
@@ -0,0 +1,88 @@
+//! This module contains code for joining two sequences end to end; e.g. a promoter cassette and a gene.
+//! Features, bookmarks, and ambiguous bases are carried over, with the second sequence's shifted past
+//! the first.
+
+use na_seq::{seq_complement, SeqTopology};
+
+use crate::{ambiguous, file_io::GenericData, misc_types::FeatureDirection, util::RangeIncl};
+
+/// UI state for the concatenation tool.
+#[derive(Default)]
+pub struct ConcatUi {
+    /// Tab indices.
+    pub tab_first: usize,
+    pub tab_second: usize,
+    pub rc_first: bool,
+    pub rc_second: bool,
+}
+
+/// Reverse-complement a sequence, along with its features and bookmarks.
+pub fn reverse_complement(data: &GenericData) -> GenericData {
+    let seq_len = data.seq.len();
+    let mut result = data.clone();
+
+    result.seq = seq_complement(&data.seq);
+
+    for feature in &mut result.features {
+        feature.range = RangeIncl::new(
+            seq_len + 1 - feature.range.end,
+            seq_len + 1 - feature.range.start,
+        );
+        feature.direction = match feature.direction {
+            FeatureDirection::Forward => FeatureDirection::Reverse,
+            FeatureDirection::Reverse => FeatureDirection::Forward,
+            FeatureDirection::None => FeatureDirection::None,
+        };
+    }
+
+    for (posit, _) in &mut result.bookmarks {
+        *posit = seq_len + 1 - *posit;
+    }
+
+    for (posit, char) in &mut result.ambiguous {
+        *posit = seq_len + 1 - *posit;
+        *char = ambiguous::complement(*char);
+    }
+
+    result
+}
+
+/// Join two sequences, with `second` appended to the end of `first`. Primers from both are
+/// included; their matches are found again on the result. The result is linear, so features that
+/// wrap either input's origin are dropped.
+pub fn concatenate(first: &GenericData, second: &GenericData) -> GenericData {
+    let offset = first.seq.len();
+
+    let mut result = first.clone();
+    result.topology = SeqTopology::Linear;
+    result.seq.extend(&second.seq);
+
+    result.features.retain(|f| f.range.start <= f.range.end);
+
+    for feature in &second.features {
+        if feature.range.start > feature.range.end {
+            continue;
+        }
+        let mut feature = feature.clone();
+        feature.range.start += offset;
+        feature.range.end += offset;
+        result.features.push(feature);
+    }
+
+    result.primers.extend(second.primers.iter().cloned());
+
+    for (posit, name) in &second.bookmarks {
+        result.bookmarks.push((posit + offset, name.clone()));
+    }
+
+    for (posit, char) in &second.ambiguous {
+        result.ambiguous.push((posit + offset, *char));
+    }
+
+    result.metadata.plasmid_name = format!(
+        "{} + {}",
+        first.metadata.plasmid_name, second.metadata.plasmid_name
+    );
+
+    result
+}
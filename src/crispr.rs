@@ -0,0 +1,228 @@
+//! This module contains code for finding CRISPR guide RNAs: We scan both strands for PAM sites, and
+//! list the protospacer immediately upstream of each, scored for properties that affect guide
+//! performance.
+
+use std::{collections::HashMap, fmt, fmt::Formatter};
+
+use na_seq::{
+    seq_complement, Nucleotide,
+    Nucleotide::{A, C, G, T},
+    Seq,
+};
+
+use crate::{misc_types::FeatureDirection, util::RangeIncl};
+
+/// SpCas9 and most of its variants use 20 nt spacers.
+pub const GUIDE_LEN: usize = 20;
+/// PAM-proximal nucleotides where mismatches are least tolerated. We use these for off-target checks.
+const SEED_LEN: usize = 12;
+
+// Pol III transcription terminates at runs of Ts.
+const POLY_T_LEN: usize = 4;
+// Repeats of this length, within a guide, either direct or inverted.
+const REPEAT_LEN: usize = 5;
+
+const GC_MIN: f32 = 0.4;
+const GC_MAX: f32 = 0.7;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum PamType {
+    /// SpCas9
+    #[default]
+    Ngg,
+    /// A weaker, alternative SpCas9 PAM.
+    Nag,
+    Custom,
+}
+
+impl fmt::Display for PamType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::Ngg => "NGG",
+            Self::Nag => "NAG",
+            Self::Custom => "Custom",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+/// UI state for the guide finder.
+#[derive(Default)]
+pub struct CrisprUi {
+    pub pam: PamType,
+    /// 5' to 3'. A, C, T, G, and N (any) only.
+    pub pam_custom: String,
+}
+
+impl CrisprUi {
+    /// The PAM to search for. `None` if the custom PAM is invalid. `None` items are wildcards.
+    pub fn pam_pattern(&self) -> Option<Vec<Option<Nucleotide>>> {
+        let text = match self.pam {
+            PamType::Ngg => "NGG",
+            PamType::Nag => "NAG",
+            PamType::Custom => &self.pam_custom,
+        };
+
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut result = Vec::new();
+        for char in text.trim().chars() {
+            result.push(match char.to_ascii_uppercase() {
+                'N' => None,
+                'A' => Some(A),
+                'C' => Some(C),
+                'T' => Some(T),
+                'G' => Some(G),
+                _ => return None,
+            });
+        }
+        Some(result)
+    }
+}
+
+pub struct Guide {
+    /// The spacer, 5' to 3', on the strand it targets. Excludes the PAM.
+    pub seq: Seq,
+    pub pam: Seq,
+    /// 1-based indexing, inclusive, on the top strand. Excludes the PAM.
+    pub range: RangeIncl,
+    pub direction: FeatureDirection,
+    pub gc_portion: f32,
+    /// A run of Ts that will terminate U6 (Pol III) transcription.
+    pub poly_t: bool,
+    /// A direct or inverted repeat within the guide; these can cause misfolding.
+    pub repeat: bool,
+    /// Other sites in this sequence with the same seed region and a PAM.
+    pub off_targets: usize,
+    /// 0 to 1. Higher is better.
+    pub score: f32,
+}
+
+fn has_poly_t(seq: &[Nucleotide]) -> bool {
+    seq.windows(POLY_T_LEN).any(|w| w.iter().all(|nt| *nt == T))
+}
+
+fn has_repeat(seq: &[Nucleotide]) -> bool {
+    for (i, a) in seq.windows(REPEAT_LEN).enumerate() {
+        let a_rc = seq_complement(a);
+        for b in seq[i + 1..].windows(REPEAT_LEN) {
+            if a == b || a_rc == b {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn score_guide(gc_portion: f32, poly_t: bool, repeat: bool, off_targets: usize) -> f32 {
+    let mut result = 1.;
+
+    // Penalize GC content outside the ideal range, proportional to its distance.
+    if gc_portion < GC_MIN {
+        result -= (GC_MIN - gc_portion) * 2.;
+    } else if gc_portion > GC_MAX {
+        result -= (gc_portion - GC_MAX) * 2.;
+    }
+
+    if poly_t {
+        result -= 0.5;
+    }
+    if repeat {
+        result -= 0.2;
+    }
+    result -= 0.2 * off_targets.min(3) as f32;
+
+    result.max(0.)
+}
+
+fn pam_matches(seq: &[Nucleotide], pam: &[Option<Nucleotide>]) -> bool {
+    seq.len() == pam.len()
+        && seq
+            .iter()
+            .zip(pam)
+            .all(|(nt, p)| p.is_none() || *p == Some(*nt))
+}
+
+/// Find guides on one strand. Ranges are 0-based start indices on this strand.
+fn find_on_strand(seq: &[Nucleotide], pam: &[Option<Nucleotide>]) -> Vec<(usize, Seq, Seq)> {
+    let mut result = Vec::new();
+    if seq.len() < GUIDE_LEN + pam.len() {
+        return result;
+    }
+
+    // todo: Handle PAMs that wrap the origin of circular sequences.
+    for start in 0..=seq.len() - GUIDE_LEN - pam.len() {
+        let pam_start = start + GUIDE_LEN;
+        let pam_seq = &seq[pam_start..pam_start + pam.len()];
+        if pam_matches(pam_seq, pam) {
+            result.push((start, seq[start..pam_start].to_vec(), pam_seq.to_vec()));
+        }
+    }
+
+    result
+}
+
+/// Find candidate guides on both strands, sorted by descending score. Off-targets are checked only
+/// against this sequence.
+pub fn find_guides(seq: &[Nucleotide], pam: &[Option<Nucleotide>]) -> Vec<Guide> {
+    if pam.is_empty() {
+        return Vec::new();
+    }
+
+    let seq_len = seq.len();
+    let seq_rc = seq_complement(seq);
+
+    let mut candidates = Vec::new();
+    for (start, spacer, pam_seq) in find_on_strand(seq, pam) {
+        let range = RangeIncl::new(start + 1, start + GUIDE_LEN);
+        candidates.push((spacer, pam_seq, range, FeatureDirection::Forward));
+    }
+    for (start, spacer, pam_seq) in find_on_strand(&seq_rc, pam) {
+        // Convert to top-strand indexing.
+        let range = RangeIncl::new(seq_len - start - GUIDE_LEN + 1, seq_len - start);
+        candidates.push((spacer, pam_seq, range, FeatureDirection::Reverse));
+    }
+
+    // The number of candidates sharing each seed region.
+    let mut seed_counts: HashMap<&[Nucleotide], usize> = HashMap::new();
+    for (spacer, ..) in &candidates {
+        *seed_counts
+            .entry(&spacer[GUIDE_LEN - SEED_LEN..])
+            .or_default() += 1;
+    }
+
+    let mut result: Vec<_> = candidates
+        .iter()
+        .map(|(spacer, pam_seq, range, direction)| {
+            // Exclude this guide's own site.
+            let off_targets = seed_counts[&spacer[GUIDE_LEN - SEED_LEN..]] - 1;
+
+            let gc_portion =
+                spacer.iter().filter(|nt| matches!(nt, C | G)).count() as f32 / GUIDE_LEN as f32;
+            let poly_t = has_poly_t(spacer);
+            let repeat = has_repeat(spacer);
+
+            Guide {
+                seq: spacer.clone(),
+                pam: pam_seq.clone(),
+                range: *range,
+                direction: *direction,
+                gc_portion,
+                poly_t,
+                repeat,
+                off_targets,
+                score: score_guide(gc_portion, poly_t, repeat, off_targets),
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    result
+}
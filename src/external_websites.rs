@@ -1,9 +1,19 @@
-//! For opening the browser to NCBI BLAST, PDB etc.
+//! For opening the browser to NCBI BLAST, PDB etc, and fetching records from NCBI.
 //!
 //! PDB Search API: https://search.rcsb.org/#search-api
 //! PDB Data API: https://data.rcsb.org/#data-api
-
-use std::{io, time::Duration};
+//! NCBI EFetch: https://www.ncbi.nlm.nih.gov/books/NBK25499/#chapter4.EFetch
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use bincode::{Decode, Encode};
 use na_seq::{seq_aa_to_str, seq_to_str_lower, Nucleotide};
@@ -12,9 +22,24 @@ use serde_json::{self};
 use ureq::{self, Agent};
 use url::Url;
 
-use crate::{protein::Protein, state::State, Selection};
+use crate::{
+    file_io::{genbank::import_genbank, GenericData},
+    protein::Protein,
+    state::State,
+    Selection,
+};
 
 const NCBI_BLAST_URL: &str = "https://blast.ncbi.nlm.nih.gov/Blast.cgi";
+const NCBI_EFETCH_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/efetch.fcgi";
+/// Fetched GenBank records are saved here, so we only download each once.
+const NCBI_CACHE_DIR: &str = "ncbi_cache";
+/// NCBI allows 3 requests per second without an API key.
+const NCBI_REQUEST_INTERVAL: Duration = Duration::from_millis(340);
+// GenBank records can be large; allow more time than for other queries.
+const NCBI_HTTP_TIMEOUT: u64 = 20; // In seconds
+
+/// The time of the most recent NCBI request, shared across fetch threads.
+static NCBI_LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
 
 const PDB_BASE_URL: &str = "https://www.rcsb.org/structure";
 const PDB_3D_VIEW_URL: &str = "https://www.rcsb.org/3d-view";
@@ -308,3 +333,111 @@ pub fn load_pdb_structure(pdb_id: &str) {
         eprintln!("Failed to open the web browser: {:?}", e);
     }
 }
+
+/// UI state for fetching a sequence from NCBI by accession.
+#[derive(Default)]
+pub struct NcbiFetchUi {
+    pub accession: String,
+    /// Receives the result from the fetch thread; `Some` while a fetch is in progress.
+    pub pending: Option<Receiver<Result<GenericData, String>>>,
+    pub status: Option<String>,
+}
+
+/// Accessions contain letters, numbers, underscores (e.g. RefSeq), and a version after a period.
+fn accession_valid(accession: &str) -> bool {
+    !accession.is_empty()
+        && accession
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn ncbi_cache_path(accession: &str) -> PathBuf {
+    PathBuf::from(NCBI_CACHE_DIR).join(format!("{accession}.gbk"))
+}
+
+/// Block until we can make another request without exceeding NCBI's rate limit.
+fn ncbi_rate_limit() {
+    let mut last = match NCBI_LAST_REQUEST.lock() {
+        Ok(l) => l,
+        Err(e) => e.into_inner(),
+    };
+
+    if let Some(t) = *last {
+        let elapsed = t.elapsed();
+        if elapsed < NCBI_REQUEST_INTERVAL {
+            thread::sleep(NCBI_REQUEST_INTERVAL - elapsed);
+        }
+    }
+    *last = Some(Instant::now());
+}
+
+/// Load a GenBank record from NCBI's nucleotide database, using EFetch. We cache records to disk,
+/// and load from the cache when available. This blocks; run it from a separate thread.
+pub fn fetch_genbank(accession: &str) -> Result<GenericData, String> {
+    let accession = accession.trim().to_uppercase();
+    if !accession_valid(&accession) {
+        return Err(format!("Invalid accession: {accession}"));
+    }
+
+    let path = ncbi_cache_path(&accession);
+    if path.exists() {
+        match import_genbank(&path) {
+            Ok(data) => return Ok(data),
+            // Fall through to re-download a corrupt cache entry.
+            Err(e) => eprintln!("Error loading cached NCBI record; downloading: {e}"),
+        }
+    }
+
+    let agent: Agent = ureq::AgentBuilder::new()
+        .timeout_read(Duration::from_secs(NCBI_HTTP_TIMEOUT))
+        .timeout_write(Duration::from_secs(HTTP_TIMEOUT))
+        .build();
+
+    ncbi_rate_limit();
+
+    let text = match agent
+        .get(NCBI_EFETCH_URL)
+        .query("db", "nuccore")
+        .query("id", &accession)
+        .query("rettype", "gbwithparts")
+        .query("retmode", "text")
+        .call()
+    {
+        Ok(resp) => resp
+            .into_string()
+            .map_err(|e| format!("Error reading the NCBI response: {e}"))?,
+        // NCBI returns HTTP 400 for accessions it doesn't have.
+        Err(ureq::Error::Status(code, _)) => {
+            return Err(format!("No record found for {accession} (HTTP {code})"))
+        }
+        Err(e) => return Err(format!("Unable to reach NCBI: {e}")),
+    };
+
+    // Errors are sometimes returned as a successful response, without a record.
+    if !text.trim_start().starts_with("LOCUS") {
+        return Err(format!("No record found for {accession}"));
+    }
+
+    if let Err(e) = fs::create_dir_all(NCBI_CACHE_DIR).and_then(|_| fs::write(&path, &text)) {
+        return Err(format!("Error saving the NCBI record: {e}"));
+    }
+
+    import_genbank(&path).map_err(|e| {
+        // Don't keep a record we can't parse.
+        let _ = fs::remove_file(&path);
+        format!("Error parsing the GenBank record: {e}")
+    })
+}
+
+/// Run `fetch_genbank` in a new thread, so it doesn't block the UI. Poll the receiver for the result.
+pub fn fetch_genbank_async(accession: &str) -> Receiver<Result<GenericData, String>> {
+    let (tx, rx) = mpsc::channel();
+    let accession = accession.to_owned();
+
+    thread::spawn(move || {
+        // The receiver may have been dropped, e.g. if the program is closing.
+        let _ = tx.send(fetch_genbank(&accession));
+    });
+
+    rx
+}
@@ -11,16 +11,21 @@ use na_seq::{
 
 use crate::{
     misc_types::{
-        Feature, FeatureDirection,
+        AnnotationMatch, Feature, FeatureDirection,
         FeatureType::{
             self, AntibioticResistance, CodingRegion, Generic, Ori, Promoter, ProteinBind,
             RibosomeBindSite, Terminator,
         },
+        ANNOTATION_MATCH_NOTE,
     },
     reading_frame::ReadingFrame,
-    util::{match_subseq, RangeIncl},
+    util::{match_subseq_fuzzy, RangeIncl},
 };
 
+/// Minimum identity (0 to 1) for a reference sequence to be annotated. Below 1, we annotate near matches,
+/// e.g. variants of common genes with point mutations.
+const FEATURE_ANNOTATION_MATCH_THRESH: f32 = 0.95;
+
 struct FeatureMapItem {
     name: String,
     feature_type: FeatureType,
@@ -79,20 +84,26 @@ pub fn find_his_tags(seq: &[Nucleotide]) -> Vec<Feature> {
                 let his_len = (i - tag_open.unwrap()) / 3;
 
                 if his_len >= 6 {
-                    let range = match orf {
-                        ReadingFrame::Fwd0 | ReadingFrame::Fwd1 | ReadingFrame::Fwd2 => {
-                            // RangeIncl::new(tag_open.unwrap() + 1 + offset, i + 2 + offset)
-                            RangeIncl::new(tag_open.unwrap() + 1 + offset, i + offset)
-                        }
-                        _ => RangeIncl::new(
-                            seq_len_full - (i + 2 + offset),
-                            seq_len_full - (tag_open.unwrap() + offset) - 1,
-                        ),
+                    let (range, direction) = if orf.is_reverse() {
+                        // Index `j` on the offset reverse complement is `seq_len_full - (j + offset)`
+                        // on the forward strand. The tag ends at `i - 1`.
+                        (
+                            RangeIncl::new(
+                                seq_len_full + 1 - (i + offset),
+                                seq_len_full - (tag_open.unwrap() + offset),
+                            ),
+                            FeatureDirection::Reverse,
+                        )
+                    } else {
+                        (
+                            RangeIncl::new(tag_open.unwrap() + 1 + offset, i + offset),
+                            FeatureDirection::Forward,
+                        )
                     };
 
-                    // todo: Likely you want the appropriate direction, determined by the reading frame.
                     result.push(Feature {
                         range,
+                        direction,
                         feature_type: CodingRegion,
                         label: format!("{his_len}×His"),
                         ..Default::default()
@@ -202,11 +213,14 @@ fn find_misc(seq: &[Nucleotide]) -> Vec<Feature> {
     let mut result = Vec::new();
 
     for item in items {
-        let (matches_fwd, matches_rev) = match_subseq(&item.seq, seq);
+        let (matches_fwd, matches_rev) =
+            match_subseq_fuzzy(&item.seq, seq, FEATURE_ANNOTATION_MATCH_THRESH);
+
+        let ref_len = item.seq.len();
 
         for (i, matches) in [matches_fwd, matches_rev].into_iter().enumerate() {
             // todo: Consider short descriptive notes for each.
-            for range in matches {
+            for (range, mismatches) in matches {
                 let dir = if i == 0 {
                     FeatureDirection::Forward
                 } else {
@@ -221,6 +235,14 @@ fn find_misc(seq: &[Nucleotide]) -> Vec<Feature> {
                     feature_type: item.feature_type,
                     label: item.name.clone(),
                     direction,
+                    notes: vec![(
+                        ANNOTATION_MATCH_NOTE.to_owned(),
+                        AnnotationMatch {
+                            mismatches,
+                            len: ref_len,
+                        }
+                        .to_note(),
+                    )],
                     ..Default::default()
                 });
             }
@@ -5,8 +5,8 @@
 //! (NIH article on GenBank)[https://www.ncbi.nlm.nih.gov/genbank/]
 
 use std::{
-    fs::File,
-    io::{self, ErrorKind},
+    fs::{self, File},
+    io::{self, Cursor, ErrorKind, Write},
     path::Path,
 };
 
@@ -19,19 +19,99 @@ use gb_io::{
 use na_seq::{seq_complement, seq_to_u8_lower, Nucleotide, SeqTopology};
 
 use crate::{
+    ambiguous,
     file_io::{get_filename, GenericData},
     misc_types::{Feature, FeatureDirection, FeatureType, Metadata, Reference},
     primer::{Primer, PrimerData, PrimerDirection, PrimerMatch},
     util::RangeIncl,
 };
 
+// The canonical ORIGIN layout: 60 nucleotides per line, in groups of 10, each line starting with its
+// right-aligned position.
+const ORIGIN_LINE_LEN: usize = 60;
+const ORIGIN_GROUP_LEN: usize = 10;
+
+/// Gap characters, which we keep in the ORIGIN block. gb-io doesn't accept these when parsing.
+const GAP_CHARS: &[u8] = b"-.";
+
+/// A gap in a record's sequence: (0-based index, char).
+type Gap = (usize, u8);
+
+/// Format a sequence as the body of a GenBank ORIGIN block; ie excluding the `ORIGIN` and `//` lines.
+fn origin_block(seq: &[u8]) -> String {
+    let mut result = String::new();
+
+    for (i, line) in seq.chunks(ORIGIN_LINE_LEN).enumerate() {
+        result.push_str(&format!("{:>9}", i * ORIGIN_LINE_LEN + 1));
+        for group in line.chunks(ORIGIN_GROUP_LEN) {
+            result.push(' ');
+            result.push_str(&String::from_utf8_lossy(group).to_lowercase());
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Rewrite each record's ORIGIN block in the canonical layout. On import, this makes us tolerant of
+/// whitespace variations (including tabs and CRLF line endings), missing or incorrect line numbers, and
+/// mixed case. On export, it ensures the strict layout some tools require.
+///
+/// Gaps are kept, or if `gap_replacement` is set, replaced with it. Returns the gaps in each record.
+fn normalize_origin(text: &str, gap_replacement: Option<u8>) -> (String, Vec<Vec<Gap>>) {
+    let mut result = String::with_capacity(text.len());
+    let mut gaps: Vec<Vec<Gap>> = Vec::new();
+    // `Some` while inside an ORIGIN block.
+    let mut seq: Option<Vec<u8>> = None;
+
+    for line in text.lines() {
+        match &mut seq {
+            Some(s) => {
+                if line.trim_start().starts_with("//") {
+                    result.push_str(&origin_block(s));
+                    result.push_str("//\n");
+                    seq = None;
+                } else {
+                    for b in line.bytes() {
+                        if GAP_CHARS.contains(&b) {
+                            gaps.last_mut().unwrap().push((s.len(), b));
+                            s.push(gap_replacement.unwrap_or(b));
+                        } else if b.is_ascii_alphabetic() {
+                            s.push(b);
+                        }
+                    }
+                }
+            }
+            None => {
+                if line.starts_with("ORIGIN") {
+                    result.push_str("ORIGIN\n");
+                    seq = Some(Vec::new());
+                    gaps.push(Vec::new());
+                } else {
+                    result.push_str(line.trim_end_matches('\r'));
+                    result.push('\n');
+                }
+            }
+        }
+    }
+
+    // A record missing its terminating `//`.
+    if let Some(s) = seq {
+        result.push_str(&origin_block(&s));
+    }
+
+    (result, gaps)
+}
+
 /// Read a file in the GenBank format.
 /// [Rust docs ref of fields](https://docs.rs/gb-io/latest/gb_io/seq/struct.Seq.html)
 pub fn import_genbank(path: &Path) -> io::Result<GenericData> {
-    let file = File::open(path)?;
+    let text = fs::read_to_string(path)?;
+    // gb-io doesn't parse gaps; read them as `n`, and restore them after.
+    let (normalized, gaps) = normalize_origin(&text, Some(b'n'));
 
     // todo: This currently only handles a single sequene. It returns the first found.
-    for seq in SeqReader::new(file) {
+    for seq in SeqReader::new(Cursor::new(normalized.into_bytes())) {
         let seq = seq.map_err(|e| {
             io::Error::new(
                 ErrorKind::InvalidData,
@@ -39,17 +119,15 @@ pub fn import_genbank(path: &Path) -> io::Result<GenericData> {
             )
         })?;
 
-        let mut seq_ = Vec::new();
-
-        for nt in &seq.seq {
-            match Nucleotide::from_u8(*nt) {
-                Ok(n) => seq_.push(n),
-                Err(_) => {
-                    eprintln!("Unexpected char in GenBank sequence: {:?}", nt);
-                }
+        let mut seq_bytes = seq.seq.clone();
+        for (i, char) in gaps.first().into_iter().flatten() {
+            if *i < seq_bytes.len() {
+                seq_bytes[*i] = *char;
             }
         }
 
+        let (seq_, ambiguous) = ambiguous::seq_from_bytes(&seq_bytes);
+
         let topology = match seq.topology {
             gb_io::seq::Topology::Linear => SeqTopology::Linear,
             gb_io::seq::Topology::Circular => SeqTopology::Circular,
@@ -106,6 +184,8 @@ pub fn import_genbank(path: &Path) -> io::Result<GenericData> {
             features,
             primers,
             metadata,
+            bookmarks: Vec::new(),
+            ambiguous,
         });
     }
 
@@ -203,10 +283,20 @@ fn parse_features_primers(
         // Parse notes from qualifiers other than label and direction.
         // let mut notes = HashMap::new();
         let mut notes = Vec::new();
+        let mut codon_start = 1;
         for (qual_key, val) in &feature.qualifiers {
             if qual_key == "label" || qual_key == "direction" {
                 continue; // We handle these separately.
             }
+            if qual_key == "codon_start" {
+                if let Some(v) = val {
+                    match v.trim().parse::<u8>() {
+                        Ok(c) if (1..=3).contains(&c) => codon_start = c,
+                        _ => eprintln!("Invalid codon_start qualifier: {v}"),
+                    }
+                }
+                continue;
+            }
             if let Some(v) = val {
                 // notes.insert(qual_key.to_string(), v.clone());
                 notes.push((qual_key.to_string(), v.clone()));
@@ -249,6 +339,7 @@ fn parse_features_primers(
                     sequence,
                     name: label.clone(),
                     description,
+                    mods: Default::default(),
                     volatile,
                 });
                 continue;
@@ -261,6 +352,7 @@ fn parse_features_primers(
                 label: label.clone(),
                 color_override: None,
                 notes: notes.clone(),
+                codon_start,
             })
         }
     }
@@ -274,11 +366,12 @@ pub fn export_genbank(
     primer_matches: &[(PrimerMatch, String)],
     path: &Path,
 ) -> io::Result<()> {
-    let file = File::create(path)?;
+    let mut file = File::create(path)?;
 
     let mut gb_data = gb_io::seq::Seq::empty();
 
     gb_data.seq = seq_to_u8_lower(&data.seq);
+    ambiguous::restore(&mut gb_data.seq, &data.ambiguous);
 
     gb_data.topology = match data.topology {
         SeqTopology::Circular => gb_io::seq::Topology::Circular,
@@ -296,6 +389,10 @@ pub fn export_genbank(
             qualifiers.push(((&*note.0).into(), Some(note.1.clone())));
         }
 
+        if feature.feature_type == FeatureType::CodingRegion {
+            qualifiers.push(("codon_start".into(), Some(feature.codon_start.to_string())));
+        }
+
         match feature.direction {
             FeatureDirection::Forward => {
                 qualifiers.push(("direction".into(), Some("RIGHT".to_owned())))
@@ -409,6 +506,130 @@ pub fn export_genbank(
         })
     }
 
-    let mut writer = SeqWriter::new(file);
-    writer.write(&gb_data)
+    // Write to a buffer first, so we can make the sequence block conform exactly.
+    let mut buf = Vec::new();
+    SeqWriter::new(&mut buf).write(&gb_data)?;
+
+    let (normalized, _) = normalize_origin(&String::from_utf8_lossy(&buf), None);
+    file.write_all(normalized.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use na_seq::seq_from_str;
+
+    use super::*;
+
+    /// Export, then re-import, using a temporary file.
+    fn round_trip(data: &GenericData, name: &str) -> (GenericData, String) {
+        let path = env::temp_dir().join(format!("plascad_test_{name}.gb"));
+        export_genbank(data, &[], &path).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        let result = import_genbank(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        (result, text)
+    }
+
+    #[test]
+    fn import_export_round_trip() {
+        // 130 nucleotides, in an irregular ORIGIN layout: Upper case, 50 per line, and no numbering.
+        let seq_text = "gatc".repeat(32) + "ga";
+        let mut text = "LOCUS       test                     130 bp    DNA     circular\n\
+            FEATURES             Location/Qualifiers\n\
+            \x20    gene            complement(11..70)\n\
+            \x20                    /label=\"geneA\"\n\
+            \x20    misc_feature    100..125\n\
+            ORIGIN\n"
+            .to_owned();
+        for line in seq_text.as_bytes().chunks(50) {
+            text.push_str(&String::from_utf8_lossy(line).to_uppercase());
+            text.push('\n');
+        }
+        text.push_str("//\n");
+
+        let path = env::temp_dir().join("plascad_test_import.gb");
+        fs::write(&path, &text).unwrap();
+        let data = import_genbank(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(data.seq, seq_from_str(&seq_text));
+        let ranges: Vec<_> = data.features.iter().map(|f| f.range).collect();
+        assert_eq!(
+            ranges,
+            vec![RangeIncl::new(11, 70), RangeIncl::new(100, 125)]
+        );
+
+        let (result, exported) = round_trip(&data, "round_trip");
+
+        assert_eq!(result.seq, data.seq);
+        assert!(result.topology == SeqTopology::Circular);
+        let ranges_result: Vec<_> = result.features.iter().map(|f| f.range).collect();
+        assert_eq!(ranges_result, ranges);
+        assert!(result.features[0].direction == FeatureDirection::Reverse);
+
+        // The ORIGIN block is in the canonical layout: 60 nucleotides per line, numbered.
+        let origin: Vec<_> = exported
+            .lines()
+            .skip_while(|l| !l.starts_with("ORIGIN"))
+            .skip(1)
+            .take_while(|l| !l.starts_with("//"))
+            .collect();
+        assert_eq!(origin.len(), 3);
+        assert_eq!(
+            origin[0],
+            format!(
+                "        1 {}",
+                "gatcgatcga tcgatcgatc ".repeat(3).trim_end()
+            )
+        );
+        assert!(origin[1].starts_with("       61 "));
+        for line in &origin {
+            let nts = line.bytes().filter(|b| b.is_ascii_alphabetic()).count();
+            assert!(nts == ORIGIN_LINE_LEN || line.starts_with("      121"));
+        }
+    }
+
+    #[test]
+    fn codon_start_round_trip() {
+        let data = GenericData {
+            seq: seq_from_str("gatggctaaacgttgaccc"),
+            features: vec![Feature {
+                range: RangeIncl::new(1, 16),
+                feature_type: FeatureType::CodingRegion,
+                direction: FeatureDirection::Forward,
+                label: "CDS".to_owned(),
+                codon_start: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let (result, text) = round_trip(&data, "codon_start");
+
+        assert!(text.contains("/codon_start="));
+        assert_eq!(result.features.len(), 1);
+        assert_eq!(result.features[0].codon_start, 2);
+        // It's parsed as the feature's codon start, not a note.
+        assert!(result.features[0].notes.is_empty());
+    }
+
+    #[test]
+    fn gaps_round_trip() {
+        let data = GenericData {
+            // Ambiguous bases are stored as placeholders.
+            seq: seq_from_str("gatcaagatcagatc"),
+            ambiguous: vec![(5, b'-'), (6, b'n'), (11, b'.')],
+            ..Default::default()
+        };
+
+        let (result, text) = round_trip(&data, "gaps");
+
+        assert!(text.contains("gatc-ngatc .gatc"));
+        assert_eq!(result.seq, data.seq);
+        assert_eq!(result.ambiguous, data.ambiguous);
+    }
 }
@@ -0,0 +1,370 @@
+//! Import and export features in the GFF3 format. This contains annotations only; the sequence
+//! is stored separately, e.g. in a FASTA file.
+//!
+//! [GFF3 spec](https://github.com/The-Sequence-Ontology/Specifications/blob/master/gff3.md)
+
+use std::{
+    fs::File,
+    io,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use na_seq::SeqTopology;
+
+use crate::{
+    file_io::{primer_bind_features, GenericData, PrimerExport},
+    misc_types::{Feature, FeatureDirection, FeatureType},
+    util::RangeIncl,
+};
+
+pub const DEFAULT_GFF_FILE: &str = "export.gff3";
+
+/// Characters we must escape in the seqid and attribute columns.
+const RESERVED: [char; 7] = ['%', ';', '=', '&', ',', '\t', '\n'];
+
+/// Sequence Ontology terms, as used in GFF3's type column.
+fn so_term(feature_type: FeatureType) -> &'static str {
+    match feature_type {
+        FeatureType::Gene => "gene",
+        FeatureType::Ori => "origin_of_replication",
+        FeatureType::RibosomeBindSite => "ribosome_entry_site",
+        FeatureType::Promoter => "promoter",
+        FeatureType::Primer => "primer_binding_site",
+        FeatureType::CodingRegion => "CDS",
+        FeatureType::LongTerminalRepeat => "long_terminal_repeat",
+        FeatureType::Source => "region",
+        FeatureType::Exon => "exon",
+        FeatureType::Transcript => "transcript",
+        FeatureType::ProteinBind => "protein_binding_site",
+        FeatureType::Terminator => "terminator",
+        _ => "sequence_feature",
+    }
+}
+
+fn feature_type_from_so(term: &str) -> FeatureType {
+    match term.to_lowercase().as_ref() {
+        "gene" => FeatureType::Gene,
+        "origin_of_replication" | "rep_origin" => FeatureType::Ori,
+        "ribosome_entry_site" | "rbs" => FeatureType::RibosomeBindSite,
+        "promoter" => FeatureType::Promoter,
+        "primer_binding_site" | "primer_bind" => FeatureType::Primer,
+        "cds" => FeatureType::CodingRegion,
+        "long_terminal_repeat" | "ltr" => FeatureType::LongTerminalRepeat,
+        "region" => FeatureType::Source,
+        "exon" => FeatureType::Exon,
+        "transcript" | "mrna" => FeatureType::Transcript,
+        "protein_binding_site" | "protein_bind" => FeatureType::ProteinBind,
+        "terminator" => FeatureType::Terminator,
+        _ => FeatureType::Generic,
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::new();
+    for char in text.chars() {
+        if RESERVED.contains(&char) {
+            result += &format!("%{:02X}", char as u32);
+        } else {
+            result.push(char);
+        }
+    }
+    result
+}
+
+fn unescape(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = Vec::new();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(v) = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                result.push(v);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Export the features of a sequence as GFF3. Features that wrap the origin end past the sequence
+/// length, per the spec's handling of circular sequences. GFF3 has no primer records, so primers are
+/// only included if `primer_export` includes binding site features.
+pub fn export_gff(data: &GenericData, primer_export: PrimerExport, path: &Path) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    let seq_len = data.seq.len();
+    let seqid = if data.metadata.plasmid_name.is_empty() {
+        "sequence".to_owned()
+    } else {
+        escape(&data.metadata.plasmid_name)
+    };
+
+    writeln!(file, "##gff-version 3")?;
+    writeln!(file, "##sequence-region {seqid} 1 {seq_len}")?;
+
+    if data.topology == SeqTopology::Circular {
+        writeln!(
+            file,
+            "{seqid}\tPlasCAD\tregion\t1\t{seq_len}\t.\t.\t.\tIs_circular=true"
+        )?;
+    }
+
+    let primer_features = if primer_export.features() {
+        primer_bind_features(&data.primers)
+    } else {
+        Vec::new()
+    };
+
+    for feature in data.features.iter().chain(&primer_features) {
+        // We export the circular region above, vice source features.
+        if feature.feature_type == FeatureType::Source {
+            continue;
+        }
+
+        let end = if feature.range.start > feature.range.end {
+            feature.range.end + seq_len
+        } else {
+            feature.range.end
+        };
+
+        let strand = match feature.direction {
+            FeatureDirection::Forward => "+",
+            FeatureDirection::Reverse => "-",
+            FeatureDirection::None => ".",
+        };
+
+        let phase = if feature.feature_type == FeatureType::CodingRegion {
+            (feature.codon_start.clamp(1, 3) - 1).to_string()
+        } else {
+            ".".to_owned()
+        };
+
+        let mut attributes = Vec::new();
+        if !feature.label.is_empty() {
+            attributes.push(format!("Name={}", escape(&feature.label)));
+        }
+        // Attributes are keyed; notes that share a key are exported as one multi-value attribute.
+        let mut notes: Vec<(&str, Vec<String>)> = Vec::new();
+        for (key, value) in &feature.notes {
+            // Skip keys that would collide with the name, or are empty.
+            if key.is_empty() || key == "Name" {
+                continue;
+            }
+            match notes.iter_mut().find(|(k, _)| k == key) {
+                Some((_, values)) => values.push(escape(value)),
+                None => notes.push((key, vec![escape(value)])),
+            }
+        }
+        for (key, values) in notes {
+            attributes.push(format!("{}={}", escape(key), values.join(",")));
+        }
+        let attributes = if attributes.is_empty() {
+            ".".to_owned()
+        } else {
+            attributes.join(";")
+        };
+
+        writeln!(
+            file,
+            "{seqid}\tPlasCAD\t{}\t{}\t{end}\t.\t{strand}\t{phase}\t{attributes}",
+            so_term(feature.feature_type),
+            feature.range.start,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Import features from a GFF3 file, for a sequence of the given length. Returns the features, and a
+/// description of each record skipped. Records past the end of the sequence are skipped; records
+/// wrapping the origin are kept if the sequence is circular. If the file annotates several sequences,
+/// we import the first's, and skip the rest.
+pub fn import_gff(
+    path: &Path,
+    seq_len: usize,
+    topology: SeqTopology,
+) -> io::Result<(Vec<Feature>, Vec<String>)> {
+    let file = File::open(path)?;
+
+    let mut features = Vec::new();
+    let mut skipped = Vec::new();
+    let mut seqid = None;
+
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line_num = i + 1;
+
+        // Embedded sequences follow this directive; we don't use them.
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() != 9 {
+            skipped.push(format!("Line {line_num}: Expected 9 columns"));
+            continue;
+        }
+
+        let record_seqid = unescape(cols[0]);
+        match &seqid {
+            Some(id) if *id != record_seqid => {
+                skipped.push(format!(
+                    "Line {line_num}: For sequence {record_seqid}; we import {id} only"
+                ));
+                continue;
+            }
+            Some(_) => (),
+            None => seqid = Some(record_seqid),
+        }
+
+        let (start, end) = match (cols[3].parse::<usize>(), cols[4].parse::<usize>()) {
+            (Ok(s), Ok(e)) => (s, e),
+            _ => {
+                skipped.push(format!("Line {line_num}: Invalid coordinates"));
+                continue;
+            }
+        };
+
+        // The whole-sequence region, e.g. from our export.
+        if cols[2] == "region" && start == 1 && end == seq_len {
+            continue;
+        }
+
+        if start == 0 || start > end || start > seq_len {
+            skipped.push(format!(
+                "Line {line_num}: {start}..{end} is outside the sequence ({seq_len} bp)"
+            ));
+            continue;
+        }
+
+        let end = if end <= seq_len {
+            end
+        } else if topology == SeqTopology::Circular && end - seq_len < start {
+            // Wraps the origin.
+            end - seq_len
+        } else {
+            skipped.push(format!(
+                "Line {line_num}: {start}..{end} is outside the sequence ({seq_len} bp)"
+            ));
+            continue;
+        };
+
+        let direction = match cols[6] {
+            "+" => FeatureDirection::Forward,
+            "-" => FeatureDirection::Reverse,
+            _ => FeatureDirection::None,
+        };
+
+        let mut label = String::new();
+        let mut notes = Vec::new();
+        for attr in cols[8].split(';') {
+            let (key, value) = match attr.split_once('=') {
+                Some((k, v)) => (unescape(k.trim()), v.trim()),
+                None => continue,
+            };
+            match key.as_ref() {
+                "Name" => label = unescape(value),
+                // Use the ID as a label only if there's no name.
+                "ID" if label.is_empty() => label = unescape(value),
+                // Multi-value attributes are comma-separated; we store each value as a note.
+                _ => {
+                    for v in value.split(',') {
+                        notes.push((key.clone(), unescape(v)));
+                    }
+                }
+            }
+        }
+
+        let codon_start = match cols[7].parse::<u8>() {
+            Ok(phase) if phase <= 2 => phase + 1,
+            _ => 1,
+        };
+
+        features.push(Feature {
+            range: RangeIncl::new(start, end),
+            feature_type: feature_type_from_so(cols[2]),
+            direction,
+            label,
+            notes,
+            codon_start,
+            ..Default::default()
+        });
+    }
+
+    Ok((features, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use na_seq::seq_from_str;
+
+    use super::*;
+
+    #[test]
+    fn notes_round_trip() {
+        let data = GenericData {
+            seq: seq_from_str(&"gatc".repeat(10)),
+            features: vec![Feature {
+                range: RangeIncl::new(5, 20),
+                label: "geneA".to_owned(),
+                notes: vec![
+                    ("note".to_owned(), "first".to_owned()),
+                    ("gene".to_owned(), "abc".to_owned()),
+                    ("note".to_owned(), "second, with a comma".to_owned()),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let path = env::temp_dir().join("plascad_test_notes.gff3");
+        export_gff(&data, PrimerExport::Native, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let (features, skipped) = import_gff(&path, data.seq.len(), data.topology).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert!(text.contains("note=first,second%2C with a comma;gene=abc"));
+        assert!(skipped.is_empty());
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].label, "geneA");
+        assert_eq!(
+            features[0].notes,
+            vec![
+                ("note".to_owned(), "first".to_owned()),
+                ("note".to_owned(), "second, with a comma".to_owned()),
+                ("gene".to_owned(), "abc".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_first_seqid() {
+        let text = "##gff-version 3\n\
+            seqA\t.\tgene\t1\t10\t.\t+\t.\tName=a\n\
+            seqB\t.\tgene\t5\t15\t.\t+\t.\tName=b\n\
+            seqA\t.\tgene\t20\t30\t.\t-\t.\tName=c\n";
+
+        let path = env::temp_dir().join("plascad_test_seqid.gff3");
+        fs::write(&path, text).unwrap();
+        let (features, skipped) = import_gff(&path, 40, SeqTopology::Linear).unwrap();
+        fs::remove_file(&path).ok();
+
+        let labels: Vec<_> = features.iter().map(|f| f.label.as_str()).collect();
+        assert_eq!(labels, vec!["a", "c"]);
+        assert_eq!(skipped.len(), 1);
+    }
+}
@@ -1,20 +1,35 @@
 //! This module contains code for saving and loading in several file formats.
 
-use std::{path::Path, sync::Arc};
+use std::{fmt, fmt::Formatter, path::Path, sync::Arc};
 
 use egui_file_dialog::{FileDialog, FileDialogConfig};
 use na_seq::{Seq, SeqTopology};
 
 use crate::{
-    file_io::save::{DEFAULT_DNA_FILE, DEFAULT_FASTA_FILE, DEFAULT_GENBANK_FILE, QUICKSAVE_FILE},
-    misc_types::{Feature, Metadata},
-    primer::Primer,
+    ambiguous::AmbiguousList,
+    file_io::{
+        gff::DEFAULT_GFF_FILE,
+        oligo_order::DEFAULT_ORDER_FILE,
+        primer_csv::DEFAULT_PRIMER_CSV_FILE,
+        project::DEFAULT_PROJECT_FILE,
+        report::DEFAULT_REPORT_FILE,
+        save::{DEFAULT_DNA_FILE, DEFAULT_FASTA_FILE, DEFAULT_GENBANK_FILE, QUICKSAVE_FILE},
+    },
+    misc_types::{Feature, FeatureDirection, FeatureType, Metadata},
+    primer::{Primer, PrimerDirection},
+    primer_library::DEFAULT_PRIMER_LIB_EXPORT_FILE,
+    primer_qc::DEFAULT_QC_FILE,
 };
 
 pub mod ab1;
 mod ab1_tags;
 pub mod genbank;
+pub mod gff;
+pub mod oligo_order;
 mod pcad;
+pub mod primer_csv;
+pub mod project;
+pub mod report;
 pub mod save;
 pub mod snapgene;
 
@@ -27,6 +42,80 @@ pub struct GenericData {
     pub features: Vec<Feature>,
     pub primers: Vec<Primer>,
     pub metadata: Metadata,
+    /// Named positions of interest; lighter-weight than features. (1-based index, name). These are
+    /// saved in the PlasCAD format only; GenBank and SnapGene exports skip them.
+    pub bookmarks: Vec<(usize, String)>,
+    /// Non-ACTG characters, e.g. N and gaps: (1-based index, char). These positions hold a placeholder
+    /// nucleotide in `seq`. See the `ambiguous` module.
+    pub ambiguous: AmbiguousList,
+}
+
+/// How to export primers. Primer binding sites as features let other tools that don't read a format's
+/// primer records show where primers bind.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum PrimerExport {
+    /// Primer records only.
+    #[default]
+    Native,
+    /// Primer records, and a feature for each binding site.
+    Both,
+    /// Binding site features, vice primer records.
+    Features,
+}
+
+impl fmt::Display for PrimerExport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::Native => "Primer records",
+            Self::Both => "Records and binding sites",
+            Self::Features => "Binding sites only",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+impl PrimerExport {
+    pub fn records(self) -> bool {
+        self != Self::Features
+    }
+
+    pub fn features(self) -> bool {
+        self != Self::Native
+    }
+}
+
+/// Create a primer binding site feature for each of each primer's matches. Primers should have their
+/// matches synced.
+pub fn primer_bind_features(primers: &[Primer]) -> Vec<Feature> {
+    let mut result = Vec::new();
+
+    for primer in primers {
+        for prim_match in &primer.volatile.matches {
+            let direction = match prim_match.direction {
+                PrimerDirection::Forward => FeatureDirection::Forward,
+                PrimerDirection::Reverse => FeatureDirection::Reverse,
+            };
+
+            let mut notes = Vec::new();
+            if let Some(descrip) = &primer.description {
+                if !descrip.is_empty() {
+                    notes.push(("note".to_owned(), descrip.clone()));
+                }
+            }
+
+            result.push(Feature {
+                range: prim_match.range,
+                feature_type: FeatureType::Primer,
+                direction,
+                label: primer.name.clone(),
+                notes,
+                ..Default::default()
+            });
+        }
+    }
+
+    result
 }
 
 pub struct FileDialogs {
@@ -36,6 +125,22 @@ pub struct FileDialogs {
     pub export_genbank: FileDialog,
     pub export_dna: FileDialog,
     pub cloning_load: FileDialog,
+    pub export_all: FileDialog,
+    pub export_report: FileDialog,
+    pub import_bam: FileDialog,
+    pub export_gff: FileDialog,
+    pub import_gff: FileDialog,
+    pub export_primer_lib: FileDialog,
+    pub import_primer_lib: FileDialog,
+    pub export_primer_qc: FileDialog,
+    pub import_annotations: FileDialog,
+    pub save_project: FileDialog,
+    pub load_project: FileDialog,
+    pub export_svg: FileDialog,
+    pub export_png: FileDialog,
+    pub import_primer_csv: FileDialog,
+    pub export_primer_csv: FileDialog,
+    pub export_order: FileDialog,
 }
 
 impl Default for FileDialogs {
@@ -76,7 +181,9 @@ impl Default for FileDialogs {
                     || ext == "dna"
                     || ext == "ab1"
             }),
-        );
+        )
+        // We identify formats by content on import, so misnamed files can be opened from here.
+        .add_file_filter("All files", Arc::new(|_| true));
 
         let save = FileDialog::new()
             // .add_quick_access("Project", |s| {
@@ -124,10 +231,138 @@ impl Default for FileDialogs {
             .default_file_name(DEFAULT_DNA_FILE)
             .id("5");
 
-        let cloning_import = FileDialog::with_config(cfg_import)
+        let cloning_import = FileDialog::with_config(cfg_import.clone())
             .default_file_filter("PCAD/FASTA/GB/SG")
             .id("6");
 
+        let export_all = FileDialog::new().id("7");
+
+        let export_report = FileDialog::new()
+            .default_file_name(DEFAULT_REPORT_FILE)
+            .id("8");
+
+        let import_bam = FileDialog::new()
+            .add_file_filter(
+                "BAM files",
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("bam")
+                }),
+            )
+            .default_file_filter("BAM files")
+            .id("9");
+
+        let gff_filter = |p: &Path| {
+            let ext = p.extension().unwrap_or_default().to_ascii_lowercase();
+            ext == "gff" || ext == "gff3"
+        };
+
+        let export_gff = FileDialog::new()
+            .add_file_filter("GFF3 files", Arc::new(gff_filter))
+            .default_file_filter("GFF3 files")
+            .default_file_name(DEFAULT_GFF_FILE)
+            .id("10");
+
+        let import_gff = FileDialog::new()
+            .add_file_filter("GFF3 files", Arc::new(gff_filter))
+            .default_file_filter("GFF3 files")
+            .id("11");
+
+        let primer_lib_filter = |p: &Path| {
+            let ext = p.extension().unwrap_or_default().to_ascii_lowercase();
+            ext == "fasta" || ext == "fa"
+        };
+
+        let export_primer_lib = FileDialog::new()
+            .add_file_filter("FASTA files", Arc::new(primer_lib_filter))
+            .default_file_filter("FASTA files")
+            .default_file_name(DEFAULT_PRIMER_LIB_EXPORT_FILE)
+            .id("12");
+
+        let import_primer_lib = FileDialog::new()
+            .add_file_filter("FASTA files", Arc::new(primer_lib_filter))
+            .default_file_filter("FASTA files")
+            .id("13");
+
+        let export_primer_qc = FileDialog::new()
+            .default_file_name(DEFAULT_QC_FILE)
+            .id("14");
+
+        let import_annotations = FileDialog::with_config(cfg_import)
+            .default_file_filter("PCAD/FASTA/GB/SG")
+            .id("15");
+
+        let project_filter = |p: &Path| {
+            p.extension()
+                .unwrap_or_default()
+                .eq_ignore_ascii_case("pcadproj")
+        };
+
+        let save_project = FileDialog::new()
+            .add_file_filter("PlasCAD projects", Arc::new(project_filter))
+            .default_file_filter("PlasCAD projects")
+            .default_file_name(DEFAULT_PROJECT_FILE)
+            .id("16");
+
+        let load_project = FileDialog::new()
+            .add_file_filter("PlasCAD projects", Arc::new(project_filter))
+            .default_file_filter("PlasCAD projects")
+            .id("17");
+
+        let export_svg = FileDialog::new()
+            .add_file_filter(
+                "SVG images",
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("svg")
+                }),
+            )
+            .default_file_filter("SVG images")
+            .id("18");
+
+        let export_png = FileDialog::new()
+            .add_file_filter(
+                "PNG images",
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("png")
+                }),
+            )
+            .default_file_filter("PNG images")
+            .id("19");
+
+        let primer_csv_filter = |p: &Path| {
+            let ext = p.extension().unwrap_or_default().to_ascii_lowercase();
+            ext == "csv" || ext == "tsv"
+        };
+
+        let import_primer_csv = FileDialog::new()
+            .add_file_filter("CSV/TSV files", Arc::new(primer_csv_filter))
+            .default_file_filter("CSV/TSV files")
+            .id("20");
+
+        let export_primer_csv = FileDialog::new()
+            .add_file_filter("CSV/TSV files", Arc::new(primer_csv_filter))
+            .default_file_filter("CSV/TSV files")
+            .default_file_name(DEFAULT_PRIMER_CSV_FILE)
+            .id("21");
+
+        let export_order = FileDialog::new()
+            .add_file_filter(
+                "CSV files",
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("csv")
+                }),
+            )
+            .default_file_filter("CSV files")
+            .default_file_name(DEFAULT_ORDER_FILE)
+            .id("22");
+
         Self {
             save,
             // load: load_,
@@ -136,6 +371,22 @@ impl Default for FileDialogs {
             export_genbank,
             export_dna,
             cloning_load: cloning_import,
+            export_all,
+            export_report,
+            import_bam,
+            export_gff,
+            import_gff,
+            export_primer_lib,
+            import_primer_lib,
+            export_primer_qc,
+            import_annotations,
+            save_project,
+            load_project,
+            export_svg,
+            export_png,
+            import_primer_csv,
+            export_primer_csv,
+            export_order,
             // selected: None,
         }
     }
@@ -153,3 +404,47 @@ fn get_filename(path: &Path) -> String {
         String::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::save::{import_format, ImportFormat, StateToSave};
+
+    /// Write a temporary file with the given name, and identify its format.
+    fn detect(content: &[u8], name: &str) -> Option<ImportFormat> {
+        let path = env::temp_dir().join(format!("plascad_test_{name}"));
+        fs::write(&path, content).unwrap();
+
+        let result = import_format(&path);
+        fs::remove_file(&path).ok();
+        result
+    }
+
+    #[test]
+    fn fasta_saved_as_genbank() {
+        let format = detect(b">pUC19 fragment\nACGTACGTAC\n", "fasta.gb");
+        assert!(format == Some(ImportFormat::Fasta));
+    }
+
+    #[test]
+    fn genbank_saved_as_fasta() {
+        // With a byte order mark, and leading blank lines.
+        let content = b"\xef\xbb\xbf\n\nLOCUS       test      10 bp    DNA     linear\nORIGIN\n        1 acgtacgtac\n//\n";
+        let format = detect(content, "genbank.fasta");
+        assert!(format == Some(ImportFormat::GenBank));
+    }
+
+    #[test]
+    fn pcad_saved_as_txt() {
+        let format = detect(&StateToSave::default().to_bytes(), "pcad.txt");
+        assert!(format == Some(ImportFormat::Pcad));
+    }
+
+    #[test]
+    fn extension_fallback() {
+        // Content we don't recognize falls back to the extension.
+        assert!(detect(b"acgtacgtac\n", "plain.fa") == Some(ImportFormat::Fasta));
+        assert!(detect(b"acgtacgtac\n", "plain.txt").is_none());
+    }
+}
@@ -0,0 +1,133 @@
+//! Export primers as an oligo order sheet, in IDT's bulk input format for tubes: A CSV with name,
+//! sequence, scale, and purification columns. This can be uploaded, or pasted into the bulk entry
+//! form.
+//!
+//! [IDT bulk input](https://www.idtdna.com/site/order/oligoentry)
+
+use std::{fs::File, io, io::Write, path::Path};
+
+use bincode::{Decode, Encode};
+
+use crate::primer::Primer;
+
+pub const DEFAULT_ORDER_FILE: &str = "idt_order.csv";
+
+/// Synthesis scale.
+#[derive(Clone, Copy, PartialEq, Encode, Decode)]
+pub enum OrderScale {
+    Nmol25,
+    Nmol100,
+    Nmol250,
+    Umol1,
+    Umol10,
+}
+
+impl OrderScale {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Nmol25 => "25 nmol",
+            Self::Nmol100 => "100 nmol",
+            Self::Nmol250 => "250 nmol",
+            Self::Umol1 => "1 µmol",
+            Self::Umol10 => "10 µmol",
+        }
+    }
+
+    /// The code IDT's bulk input uses.
+    pub fn order_code(self) -> &'static str {
+        match self {
+            Self::Nmol25 => "25nm",
+            Self::Nmol100 => "100nm",
+            Self::Nmol250 => "250nm",
+            Self::Umol1 => "1um",
+            Self::Umol10 => "10um",
+        }
+    }
+}
+
+pub const ORDER_SCALES: [OrderScale; 5] = [
+    OrderScale::Nmol25,
+    OrderScale::Nmol100,
+    OrderScale::Nmol250,
+    OrderScale::Umol1,
+    OrderScale::Umol10,
+];
+
+#[derive(Clone, Copy, PartialEq, Encode, Decode)]
+pub enum Purification {
+    /// Standard desalting.
+    Standard,
+    Page,
+    Hplc,
+    IeHplc,
+}
+
+impl Purification {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard desalting",
+            Self::Page => "PAGE",
+            Self::Hplc => "HPLC",
+            Self::IeHplc => "IE HPLC",
+        }
+    }
+
+    /// The code IDT's bulk input uses.
+    pub fn order_code(self) -> &'static str {
+        match self {
+            Self::Standard => "STD",
+            Self::Page => "PAGE",
+            Self::Hplc => "HPLC",
+            Self::IeHplc => "IEHPLC",
+        }
+    }
+}
+
+pub const PURIFICATIONS: [Purification; 4] = [
+    Purification::Standard,
+    Purification::Page,
+    Purification::Hplc,
+    Purification::IeHplc,
+];
+
+/// Applied to each primer in the order. Saved in preferences.
+#[derive(Clone, Encode, Decode)]
+pub struct OrderSettings {
+    pub scale: OrderScale,
+    pub purification: Purification,
+}
+
+impl Default for OrderSettings {
+    fn default() -> Self {
+        Self {
+            scale: OrderScale::Nmol25,
+            purification: Purification::Standard,
+        }
+    }
+}
+
+/// Write an order sheet for these primers. Sequences are as tuned, and include tails and 5'
+/// modification codes; see `Primer::order_text`.
+pub fn export_order(primers: &[Primer], settings: &OrderSettings, path: &Path) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    writeln!(file, "Name,Sequence,Scale,Purification")?;
+    for primer in primers {
+        if primer.sequence.is_empty() {
+            continue;
+        }
+
+        // IDT doesn't accept commas in names.
+        let name = primer.name.replace(',', " ");
+        writeln!(
+            file,
+            "{},{},{},{}",
+            name.trim(),
+            primer.order_text(),
+            settings.scale.order_code(),
+            settings.purification.order_code(),
+        )?;
+    }
+
+    Ok(())
+}
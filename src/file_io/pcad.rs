@@ -12,9 +12,12 @@ use bincode::config;
 use na_seq::{deser_seq_bin, serialize_seq_bin};
 use num_enum::TryFromPrimitive;
 
-use crate::file_io::save::StateToSave;
+use crate::{
+    file_io::save::StateToSave,
+    save_compat::{FeatureV0, PortionsStateV0, PrimerV0},
+};
 
-const START_BYTES: [u8; 2] = [0xca, 0xfe]; // Arbitrary, used as a sanity check.
+pub const START_BYTES: [u8; 2] = [0xca, 0xfe]; // Arbitrary, used as a sanity check.
 const PACKET_START: u8 = 0x11;
 const PACKET_OVERHEAD: usize = 6; // packet start, packet type, message size.
 
@@ -30,6 +33,8 @@ pub enum PacketType {
     // PathLoaded = 10,
     Topology = 11,
     Ab1 = 12,
+    Bookmarks = 13,
+    Ambiguous = 14,
 }
 
 /// Byte 0: Standard packet start. Bytes 1-4: u32 of payload len. Bytes 5[..]: Payload.
@@ -141,12 +146,24 @@ impl StateToSave {
             payload: bincode::encode_to_vec(&self.ab1_data, cfg).unwrap(),
         };
 
+        let bookmarks_packet = Packet {
+            type_: PacketType::Bookmarks,
+            payload: bincode::encode_to_vec(&self.generic.bookmarks, cfg).unwrap(),
+        };
+
+        let ambiguous_packet = Packet {
+            type_: PacketType::Ambiguous,
+            payload: bincode::encode_to_vec(&self.generic.ambiguous, cfg).unwrap(),
+        };
+
         result.extend(&seq_packet.to_bytes());
         result.extend(&features_packet.to_bytes());
         result.extend(&primers_packet.to_bytes());
         result.extend(&metadata_packet.to_bytes());
         result.extend(&topology_packet.to_bytes());
         result.extend(&ab1_packet.to_bytes());
+        result.extend(&bookmarks_packet.to_bytes());
+        result.extend(&ambiguous_packet.to_bytes());
 
         // result.extend(&ion_concentrations_packet.to_bytes());
         result.extend(&portions_packet.to_bytes());
@@ -193,14 +210,37 @@ impl StateToSave {
                     Ok(v) => result.generic.seq = v,
                     Err(e) => eprintln!("Error decoding sequence packet: {e}"),
                 },
-                PacketType::Features => match bincode::decode_from_slice(&packet.payload, cfg) {
-                    Ok(v) => result.generic.features = v.0,
-                    Err(e) => eprintln!("Error decoding features packet: {e}"),
-                },
-                PacketType::Primers => match bincode::decode_from_slice(&packet.payload, cfg) {
-                    Ok(v) => result.generic.primers = v.0,
-                    Err(e) => eprintln!("Error decoding primers packet: {e}"),
-                },
+                PacketType::Features => {
+                    // Fall back to the format used prior to adding codon start.
+                    match bincode::decode_from_slice(&packet.payload, cfg) {
+                        Ok((v, len)) if len == packet.payload.len() => result.generic.features = v,
+                        _ => match bincode::decode_from_slice::<Vec<FeatureV0>, _>(
+                            &packet.payload,
+                            cfg,
+                        ) {
+                            Ok(v) => {
+                                result.generic.features = v.0.into_iter().map(Into::into).collect()
+                            }
+                            Err(e) => eprintln!("Error decoding features packet: {e}"),
+                        },
+                    }
+                }
+                PacketType::Primers => {
+                    // Fall back to the format used prior to primer modifications. We check that the
+                    // whole payload was consumed, since legacy data may otherwise decode as garbage.
+                    match bincode::decode_from_slice(&packet.payload, cfg) {
+                        Ok((v, len)) if len == packet.payload.len() => result.generic.primers = v,
+                        _ => match bincode::decode_from_slice::<Vec<PrimerV0>, _>(
+                            &packet.payload,
+                            cfg,
+                        ) {
+                            Ok(v) => {
+                                result.generic.primers = v.0.into_iter().map(Into::into).collect()
+                            }
+                            Err(e) => eprintln!("Error decoding primers packet: {e}"),
+                        },
+                    }
+                }
                 PacketType::Metadata => match bincode::decode_from_slice(&packet.payload, cfg) {
                     Ok(v) => result.generic.metadata = v.0,
                     Err(e) => eprintln!("Error decoding metadata packet: {e}"),
@@ -215,14 +255,31 @@ impl StateToSave {
                 //         Err(e) => eprintln!("Error decoding ion concentrations packet: {e}"),
                 //     }
                 // }
-                PacketType::Portions => match bincode::decode_from_slice(&packet.payload, cfg) {
-                    Ok(v) => result.portions = v.0,
-                    Err(e) => eprintln!("Error decoding portions packet: {e}"),
-                },
+                PacketType::Portions => {
+                    // Fall back to the format used prior to dilution series.
+                    match bincode::decode_from_slice(&packet.payload, cfg) {
+                        Ok((v, len)) if len == packet.payload.len() => result.portions = v,
+                        _ => match bincode::decode_from_slice::<PortionsStateV0, _>(
+                            &packet.payload,
+                            cfg,
+                        ) {
+                            Ok(v) => result.portions = v.0.into(),
+                            Err(e) => eprintln!("Error decoding portions packet: {e}"),
+                        },
+                    }
+                }
                 PacketType::Ab1 => match bincode::decode_from_slice(&packet.payload, cfg) {
                     Ok(v) => result.ab1_data = v.0,
                     Err(e) => eprintln!("Error decoding AB1 packet: {e}"),
                 },
+                PacketType::Bookmarks => match bincode::decode_from_slice(&packet.payload, cfg) {
+                    Ok(v) => result.generic.bookmarks = v.0,
+                    Err(e) => eprintln!("Error decoding bookmarks packet: {e}"),
+                },
+                PacketType::Ambiguous => match bincode::decode_from_slice(&packet.payload, cfg) {
+                    Ok(v) => result.generic.ambiguous = v.0,
+                    Err(e) => eprintln!("Error decoding ambiguous bases packet: {e}"),
+                },
                 // PacketType::PathLoaded => match bincode::decode_from_slice(&packet.payload, cfg) {
                 //     Ok(v) => result.path_loaded = v.0,
                 //     Err(e) => eprintln!("Error decoding Seq packet: {e}"),
@@ -233,3 +290,47 @@ impl StateToSave {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        misc_types::{FeatureDirection, FeatureType},
+        util::RangeIncl,
+    };
+
+    #[test]
+    fn features_v0_fallback() {
+        // A features packet saved before codon start was added.
+        let features: Vec<_> = ["lacZ", "AmpR"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| FeatureV0 {
+                range: RangeIncl::new(i * 100 + 1, i * 100 + 50),
+                feature_type: FeatureType::CodingRegion,
+                direction: FeatureDirection::Forward,
+                label: label.to_owned(),
+                color_override: None,
+                notes: vec![("note".to_owned(), "test".to_owned())],
+            })
+            .collect();
+
+        let packet = Packet {
+            type_: PacketType::Features,
+            payload: bincode::encode_to_vec(&features, config::standard()).unwrap(),
+        };
+
+        let mut bytes = START_BYTES.to_vec();
+        bytes.extend(&packet.to_bytes());
+
+        let result = StateToSave::from_bytes(&bytes).unwrap().generic.features;
+
+        assert_eq!(result.len(), 2);
+        for (feature, label) in result.iter().zip(["lacZ", "AmpR"]) {
+            assert_eq!(feature.label, label);
+            assert_eq!(feature.codon_start, 1);
+            assert_eq!(feature.notes.len(), 1);
+        }
+        assert_eq!(result[1].range, RangeIncl::new(101, 150));
+    }
+}
@@ -0,0 +1,160 @@
+//! Import and export primer lists as CSV or TSV, e.g. for editing in a spreadsheet. Columns are
+//! name, sequence, and description; the description is optional. Tails and modifications aren't
+//! included.
+
+use std::{
+    fs::File,
+    io,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use na_seq::{seq_to_str_lower, Nucleotide};
+
+use crate::primer::{Primer, PrimerData};
+
+pub const DEFAULT_PRIMER_CSV_FILE: &str = "primers.csv";
+
+/// Use tabs for `.tsv` files, and commas otherwise.
+fn delimiter(path: &Path) -> char {
+    if path
+        .extension()
+        .unwrap_or_default()
+        .eq_ignore_ascii_case("tsv")
+    {
+        '\t'
+    } else {
+        ','
+    }
+}
+
+/// Quote a field if it contains the delimiter or quotes. Line breaks are replaced with spaces, since
+/// import reads one row per line.
+fn escape_field(field: &str, delim: char) -> String {
+    let field = field.replace("\r\n", " ").replace(['\r', '\n'], " ");
+    if field.contains(delim) || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// Split a line into fields. Handles quoted fields, including escaped quotes ("").
+fn split_line(line: &str, delim: char) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == delim {
+            result.push(field.trim().to_owned());
+            field = String::new();
+        } else {
+            field.push(c);
+        }
+    }
+    result.push(field.trim().to_owned());
+
+    result
+}
+
+/// Export primers to a CSV or TSV file, with a header row.
+pub fn export_primers_csv(primers: &[Primer], path: &Path) -> io::Result<()> {
+    let delim = delimiter(path);
+    let mut file = File::create(path)?;
+
+    writeln!(file, "name{delim}sequence{delim}description")?;
+    for primer in primers {
+        writeln!(
+            file,
+            "{}{delim}{}{delim}{}",
+            escape_field(&primer.name, delim),
+            seq_to_str_lower(&primer.sequence),
+            escape_field(&primer.description.clone().unwrap_or_default(), delim),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Import primers from a CSV or TSV file. A header row is optional. Rows with a missing or invalid
+/// sequence are skipped, vice failing the import. Returns the primers, and the number of rows
+/// skipped.
+pub fn import_primers_csv(path: &Path) -> io::Result<(Vec<Primer>, usize)> {
+    let delim = delimiter(path);
+    let file = File::open(path)?;
+
+    let mut result = Vec::new();
+    let mut skipped = 0;
+
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields = split_line(&line, delim);
+
+        if i == 0 && fields[0].eq_ignore_ascii_case("name") {
+            continue;
+        }
+
+        if fields.len() < 2 || fields[1].is_empty() {
+            eprintln!("Skipping primer CSV row {}: Missing sequence", i + 1);
+            skipped += 1;
+            continue;
+        }
+
+        // Allow spaces in the sequence, e.g. between codons.
+        let mut sequence = Vec::new();
+        let mut valid = true;
+        for b in fields[1].bytes().filter(|b| !b.is_ascii_whitespace()) {
+            match Nucleotide::from_u8(b) {
+                Ok(nt) => sequence.push(nt),
+                Err(_) => {
+                    valid = false;
+                    break;
+                }
+            }
+        }
+
+        if !valid {
+            eprintln!(
+                "Skipping primer CSV row {}: Invalid nucleotides in {}",
+                i + 1,
+                fields[1]
+            );
+            skipped += 1;
+            continue;
+        }
+
+        let description = match fields.get(2) {
+            Some(d) if !d.is_empty() => Some(d.clone()),
+            _ => None,
+        };
+
+        result.push(Primer {
+            volatile: PrimerData::new(&sequence),
+            sequence,
+            name: fields[0].clone(),
+            description,
+            mods: Default::default(),
+        });
+    }
+
+    Ok((result, skipped))
+}
@@ -0,0 +1,188 @@
+//! Project files bundle all open tabs, the primer library, and workspace settings into a single
+//! `.pcadproj` file. This lets users save, restore, and share a multi-construct working session as a
+//! unit. Each tab is stored in the PCAD format, so tabs load with the same fallbacks for older saves
+//! as single files do.
+//!
+//! Format: Start bytes, a big-endian u16 version, then the Bincode-encoded project.
+
+use std::{fs, io, io::ErrorKind, path::Path};
+
+use bincode::{config, Decode, Encode};
+use na_seq::seq_to_str_lower;
+
+use crate::{
+    file_io::save::StateToSave,
+    gui::navigation::{Page, PageSeq, Tab},
+    primer::{IonConcentrations, Primer},
+    state::State,
+};
+
+pub const DEFAULT_PROJECT_FILE: &str = "project.pcadproj";
+
+const START_BYTES: [u8; 3] = [0xca, 0xfe, 0x50]; // Arbitrary; distinct from single PCAD files.
+
+/// Increment this when the format changes, and handle prior versions in `from_bytes`.
+const PROJECT_VERSION: u16 = 0;
+
+/// Per-tab settings that aren't included in the PCAD format.
+#[derive(Encode, Decode)]
+struct TabSettings {
+    tab: Tab,
+    cloning_insert_loc: usize,
+    cloning_remove_stop_codons: bool,
+}
+
+#[derive(Encode, Decode)]
+struct ProjectToSave {
+    /// Each in the PCAD format.
+    tabs: Vec<Vec<u8>>,
+    tab_settings: Vec<TabSettings>,
+    active: usize,
+    page: Page,
+    page_seq: PageSeq,
+    ion_concentrations: IonConcentrations,
+    primer_lib: Vec<Primer>,
+}
+
+impl ProjectToSave {
+    fn from_state(state: &State) -> Self {
+        let mut tabs = Vec::new();
+        let mut tab_settings = Vec::new();
+
+        for i in 0..state.generic.len() {
+            tabs.push(StateToSave::from_state(state, i).to_bytes());
+            tab_settings.push(TabSettings {
+                tab: state.tabs_open[i].clone(),
+                cloning_insert_loc: state.cloning[i].insert_loc,
+                cloning_remove_stop_codons: state.cloning[i].remove_stop_codons,
+            });
+        }
+
+        Self {
+            tabs,
+            tab_settings,
+            active: state.active,
+            page: state.ui.page,
+            page_seq: state.ui.page_seq,
+            ion_concentrations: state.ion_concentrations.clone(),
+            primer_lib: state.primer_lib.primers.clone(),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut result = START_BYTES.to_vec();
+        result.extend(PROJECT_VERSION.to_be_bytes());
+        result.extend(bincode::encode_to_vec(self, config::standard()).unwrap());
+        result
+    }
+
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let header_len = START_BYTES.len() + 2;
+        if bytes.len() < header_len || bytes[0..START_BYTES.len()] != START_BYTES {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid start bytes in project file.",
+            ));
+        }
+
+        let version = u16::from_be_bytes(bytes[START_BYTES.len()..header_len].try_into().unwrap());
+        if version > PROJECT_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("This project was saved by a newer version of PlasCAD (format {version})."),
+            ));
+        }
+
+        bincode::decode_from_slice(&bytes[header_len..], config::standard())
+            .map(|v| v.0)
+            .map_err(|e| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unable to decode project: {e}"),
+                )
+            })
+    }
+}
+
+/// Save all open tabs, and workspace settings, to a project file.
+pub fn save_project(state: &State, path: &Path) -> io::Result<()> {
+    fs::write(path, ProjectToSave::from_state(state).to_bytes())
+}
+
+/// Replace the open tabs with those from a project file, and restore its workspace settings. Primers
+/// from the project are added to the library, vice replacing it. Returns the number of tabs loaded.
+pub fn load_project(state: &mut State, path: &Path) -> io::Result<usize> {
+    let project = ProjectToSave::from_bytes(&fs::read(path)?)?;
+
+    let mut loaded = Vec::new();
+    for (i, bytes) in project.tabs.iter().enumerate() {
+        match StateToSave::from_bytes(bytes) {
+            Ok(v) => loaded.push((v, project.tab_settings.get(i))),
+            Err(e) => eprintln!("Error loading tab {i} from project: {e}"),
+        }
+    }
+
+    if loaded.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "The project contains no tabs.",
+        ));
+    }
+
+    // Start from a single blank tab.
+    state.generic = vec![Default::default()];
+    state.ab1_data = vec![Default::default()];
+    state.tabs_open = vec![Default::default()];
+    state.portions = vec![Default::default()];
+    state.volatile = vec![Default::default()];
+    state.cloning = vec![Default::default()];
+    state.active = 0;
+    state.ui.cloning_tab_synced = None;
+    state.ui.re.tabs_selected.clear();
+    state.ui.golden_gate.tabs.clear();
+    state.ui.golden_gate.result = None;
+    state.alignment.msa_tabs.clear();
+    state.alignment.msa_result = None;
+    state.alignment.read_alignment = None;
+    state.ui.ab1.ref_tab = None;
+
+    for (i, (tab_data, settings)) in loaded.iter().enumerate() {
+        // Add a tab explicitly, so empty tabs in the project aren't overwritten by the next one.
+        if i > 0 {
+            state.add_tab();
+            state.tabs_open.push(Default::default());
+        }
+        state.load(tab_data);
+
+        if let Some(settings) = settings {
+            let cloning = &mut state.cloning[state.active];
+            cloning.insert_loc = settings.cloning_insert_loc;
+            cloning.remove_stop_codons = settings.cloning_remove_stop_codons;
+
+            // The project may have been shared from another computer.
+            let path = settings.tab.path.clone().filter(|p| p.exists());
+            state.tabs_open[state.active] = Tab {
+                path,
+                ab1: settings.tab.ab1,
+            };
+        }
+    }
+
+    for primer in &project.primer_lib {
+        state.primer_lib.add(primer);
+    }
+
+    state.active = project.active.min(state.generic.len() - 1);
+    state.ui.page = project.page;
+    state.ui.page_seq = project.page_seq;
+    state.ion_concentrations = project.ion_concentrations;
+
+    state.sync_pcr();
+    state.sync_re_sites();
+    state.sync_reading_frame();
+    state.ui.seq_input = seq_to_str_lower(state.get_seq());
+    state.reset_selections();
+    state.save_prefs();
+
+    Ok(loaded.len())
+}
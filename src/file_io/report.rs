@@ -0,0 +1,414 @@
+//! Human-readable reports of sequence data, e.g. for lab notebooks. These include metadata, features,
+//! primers, and RE sites, as plain text or simple HTML. Unlike our other formats, these are export-only.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use na_seq::{
+    restriction_enzyme::{ReMatch, RestrictionEnzyme},
+    seq_complement, seq_to_str_lower, Nucleotide, SeqTopology,
+};
+
+use crate::{
+    file_io::GenericData,
+    misc_types::{Feature, FeatureDirection, FeatureType},
+};
+
+pub const DEFAULT_REPORT_FILE: &str = "report.txt";
+
+// Nucleotides per line in the sequence section.
+const SEQ_LINE_LEN: usize = 60;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::Text => "Text",
+            Self::Html => "HTML",
+        };
+        write!(f, "{val}")
+    }
+}
+
+/// Which sections to include in the report, and its format.
+pub struct ReportSettings {
+    pub format: ReportFormat,
+    pub metadata: bool,
+    pub features: bool,
+    pub primers: bool,
+    pub re_sites: bool,
+    pub sequence: bool,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self {
+            format: Default::default(),
+            metadata: true,
+            features: true,
+            primers: true,
+            re_sites: true,
+            sequence: true,
+        }
+    }
+}
+
+/// A table, used to build either format. We keep the layout format-agnostic until writing.
+struct Section {
+    title: String,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// The feature's sequence, read in its direction. Handles features that wrap the origin.
+fn feature_seq(feature: &Feature, seq: &[Nucleotide]) -> String {
+    let nts = if feature.range.start <= feature.range.end {
+        feature.range.index_seq(seq).unwrap_or_default().to_vec()
+    } else {
+        if feature.range.start > seq.len() || feature.range.end > seq.len() {
+            return String::new();
+        }
+        let mut v = seq[feature.range.start - 1..].to_vec();
+        v.extend(&seq[..feature.range.end]);
+        v
+    };
+
+    if feature.direction == FeatureDirection::Reverse {
+        seq_to_str_lower(&seq_complement(&nts))
+    } else {
+        seq_to_str_lower(&nts)
+    }
+}
+
+fn metadata_section(data: &GenericData) -> Section {
+    let md = &data.metadata;
+    let topology = match data.topology {
+        SeqTopology::Circular => "Circular",
+        SeqTopology::Linear => "Linear",
+    };
+
+    let mut rows = vec![
+        vec!["Name".to_owned(), md.plasmid_name.clone()],
+        vec!["Length".to_owned(), format!("{} bp", data.seq.len())],
+        vec!["Topology".to_owned(), topology.to_owned()],
+    ];
+
+    for (label, val) in [
+        ("Definition", &md.definition),
+        ("Accession", &md.accession),
+        ("Version", &md.version),
+        ("Organism", &md.organism),
+        ("Source", &md.source),
+        ("Keywords", &md.keywords),
+    ] {
+        if let Some(v) = val {
+            rows.push(vec![label.to_owned(), v.clone()]);
+        }
+    }
+
+    if let Some((y, m, d)) = md.date {
+        rows.push(vec!["Date".to_owned(), format!("{y}-{m:02}-{d:02}")]);
+    }
+
+    for comment in &md.comments {
+        rows.push(vec!["Comment".to_owned(), comment.clone()]);
+    }
+
+    Section {
+        title: "Metadata".to_owned(),
+        header: Vec::new(),
+        rows,
+    }
+}
+
+fn feature_section(data: &GenericData) -> Section {
+    let mut rows = Vec::new();
+    for feature in &data.features {
+        if feature.feature_type == FeatureType::Source {
+            continue; // From GenBank; generally the whole seq.
+        }
+
+        rows.push(vec![
+            feature.label(),
+            feature.feature_type.to_string(),
+            format!("{}..{}", feature.range.start, feature.range.end),
+            feature.len(data.seq.len()).to_string(),
+            feature.direction.to_string(),
+            feature_seq(feature, &data.seq),
+        ]);
+    }
+
+    Section {
+        title: "Features".to_owned(),
+        header: ["Name", "Type", "Location", "Len", "Dir", "Sequence"]
+            .map(String::from)
+            .to_vec(),
+        rows,
+    }
+}
+
+fn primer_section(data: &GenericData) -> Section {
+    let mut rows = Vec::new();
+    for primer in &data.primers {
+        let (tm, gc, qual) = match &primer.volatile.metrics {
+            Some(m) => (
+                format!("{:.1}", m.melting_temp),
+                format!("{:.0}", m.gc_portion * 100.),
+                format!("{:.2}", m.quality_score),
+            ),
+            None => (String::new(), String::new(), String::new()),
+        };
+
+        rows.push(vec![
+            primer.name.clone(),
+            seq_to_str_lower(&primer.sequence),
+            primer.sequence.len().to_string(),
+            tm,
+            gc,
+            qual,
+            primer.location_descrip(),
+        ]);
+    }
+
+    Section {
+        title: "Primers".to_owned(),
+        header: [
+            "Name",
+            "Sequence (5' to 3')",
+            "Len",
+            "TM (°C)",
+            "GC %",
+            "Quality",
+            "Matches",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows,
+    }
+}
+
+/// One row per enzyme, with the cut positions.
+fn re_section(re_matches: &[ReMatch], re_lib: &[RestrictionEnzyme]) -> Section {
+    // (lib index, cut positions); in order of first appearance.
+    let mut by_re: Vec<(usize, Vec<usize>)> = Vec::new();
+    for re_match in re_matches {
+        if re_match.lib_index >= re_lib.len() {
+            eprintln!("Invalid RE index in report");
+            continue;
+        }
+        let cut_i = re_match.seq_index + 1 + re_lib[re_match.lib_index].cut_after as usize;
+
+        match by_re.iter_mut().find(|(i, _)| *i == re_match.lib_index) {
+            Some((_, cuts)) => cuts.push(cut_i),
+            None => by_re.push((re_match.lib_index, vec![cut_i])),
+        }
+    }
+
+    by_re.sort_by(|a, b| re_lib[a.0].name.cmp(&re_lib[b.0].name));
+
+    let rows = by_re
+        .iter()
+        .map(|(i, cuts)| {
+            let cuts_text: Vec<_> = cuts.iter().map(|c| c.to_string()).collect();
+            vec![
+                re_lib[*i].name.clone(),
+                cuts.len().to_string(),
+                cuts_text.join(", "),
+            ]
+        })
+        .collect();
+
+    Section {
+        title: "Restriction sites".to_owned(),
+        header: ["Enzyme", "Cuts", "Cut positions"]
+            .map(String::from)
+            .to_vec(),
+        rows,
+    }
+}
+
+/// Split the sequence into numbered lines, in the style of GenBank's ORIGIN section.
+fn seq_lines(seq: &[Nucleotide]) -> Vec<String> {
+    seq.chunks(SEQ_LINE_LEN)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let blocks: Vec<_> = chunk.chunks(10).map(seq_to_str_lower).collect();
+            format!("{:>9} {}", i * SEQ_LINE_LEN + 1, blocks.join(" "))
+        })
+        .collect()
+}
+
+fn text_section(section: &Section, result: &mut String) {
+    result.push_str(&format!(
+        "\n{}\n{}\n",
+        section.title,
+        "=".repeat(section.title.len())
+    ));
+
+    if section.rows.is_empty() {
+        result.push_str("(None)\n");
+        return;
+    }
+
+    // Pad columns to the width of their longest entry.
+    let col_count = section.rows[0].len();
+    let mut widths = vec![0; col_count];
+    for row in section.rows.iter().chain(std::iter::once(&section.header)) {
+        for (i, cell) in row.iter().enumerate().take(col_count) {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let mut write_row = |row: &[String]| {
+        let cells: Vec<_> = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{cell:<w$}", w = widths[i]))
+            .collect();
+        result.push_str(cells.join("  ").trim_end());
+        result.push('\n');
+    };
+
+    if !section.header.is_empty() {
+        write_row(&section.header);
+    }
+    for row in &section.rows {
+        write_row(row);
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_section(section: &Section, result: &mut String) {
+    result.push_str(&format!("<h2>{}</h2>\n", html_escape(&section.title)));
+
+    if section.rows.is_empty() {
+        result.push_str("<p>(None)</p>\n");
+        return;
+    }
+
+    result.push_str("<table>\n");
+    if !section.header.is_empty() {
+        result.push_str("<tr>");
+        for cell in &section.header {
+            result.push_str(&format!("<th>{}</th>", html_escape(cell)));
+        }
+        result.push_str("</tr>\n");
+    }
+
+    for row in &section.rows {
+        result.push_str("<tr>");
+        for cell in row {
+            result.push_str(&format!("<td>{}</td>", html_escape(cell)));
+        }
+        result.push_str("</tr>\n");
+    }
+    result.push_str("</table>\n");
+}
+
+/// Assemble a report from sequence data, including the sections specified.
+pub fn make_report(
+    data: &GenericData,
+    re_matches: &[ReMatch],
+    re_lib: &[RestrictionEnzyme],
+    settings: &ReportSettings,
+) -> String {
+    let mut sections = Vec::new();
+    if settings.metadata {
+        sections.push(metadata_section(data));
+    }
+    if settings.features {
+        sections.push(feature_section(data));
+    }
+    if settings.primers {
+        sections.push(primer_section(data));
+    }
+    if settings.re_sites {
+        sections.push(re_section(re_matches, re_lib));
+    }
+
+    let title = if data.metadata.plasmid_name.is_empty() {
+        "Sequence report".to_owned()
+    } else {
+        data.metadata.plasmid_name.clone()
+    };
+
+    let mut result = String::new();
+
+    match settings.format {
+        ReportFormat::Text => {
+            result.push_str(&format!("{title}\n"));
+            for section in &sections {
+                text_section(section, &mut result);
+            }
+
+            if settings.sequence {
+                result.push_str("\nSequence\n========\n");
+                for line in seq_lines(&data.seq) {
+                    result.push_str(&format!("{line}\n"));
+                }
+            }
+        }
+        ReportFormat::Html => {
+            result.push_str(&format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+                <style>table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #999; \
+                padding: 2px 6px; text-align: left; font-family: monospace; }}</style>\n\
+                </head>\n<body>\n<h1>{0}</h1>\n",
+                html_escape(&title)
+            ));
+
+            for section in &sections {
+                html_section(section, &mut result);
+            }
+
+            if settings.sequence {
+                result.push_str("<h2>Sequence</h2>\n<pre>\n");
+                for line in seq_lines(&data.seq) {
+                    result.push_str(&format!("{line}\n"));
+                }
+                result.push_str("</pre>\n");
+            }
+
+            result.push_str("</body>\n</html>\n");
+        }
+    }
+
+    result
+}
+
+pub fn export_report(
+    data: &GenericData,
+    re_matches: &[ReMatch],
+    re_lib: &[RestrictionEnzyme],
+    settings: &ReportSettings,
+    path: &Path,
+) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(make_report(data, re_matches, re_lib, settings).as_bytes())?;
+    Ok(())
+}
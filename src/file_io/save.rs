@@ -4,6 +4,8 @@
 
 use std::{
     env,
+    fmt::Display,
+    fs,
     fs::File,
     io,
     io::{ErrorKind, Read, Write},
@@ -16,37 +18,49 @@ use bincode::{
     Decode, Encode,
 };
 use bio::io::fasta;
+use directories::ProjectDirs;
 use eframe::egui::Ui;
 use na_seq::{deser_seq_bin, seq_to_u8_lower, serialize_seq_bin, Nucleotide, Seq, SeqTopology};
 
 use crate::{
     ab1::SeqRecordAb1,
+    ambiguous::{self, AmbiguousList},
     feature_db_load::find_features,
     file_io::{
         ab1::import_ab1,
         genbank::{export_genbank, import_genbank},
+        oligo_order::OrderSettings,
+        pcad::START_BYTES as PCAD_START_BYTES,
         snapgene::{export_snapgene, import_snapgene},
         GenericData,
     },
     gui::{
         navigation::{Page, PageSeq, PageSeqTop, Tab},
         set_window_title,
+        theme::{MapTheme, PaletteType},
     },
     misc_types::{Feature, Metadata},
     pcr::PcrUi,
     portions::PortionsState,
-    primer::{IonConcentrations, Primer},
+    primer::{IonConcentrations, Primer, PrimerLenSettings},
+    primer_metrics::ScoringWeights,
     state::State,
+    util::{merge_feature_sets, strip_filename_chars, CoordBase},
     Selection, SeqVisibility, StateUi,
 };
 
 pub const QUICKSAVE_FILE: &str = "quicksave.pcad";
 pub const DEFAULT_PREFS_FILE: &str = "pcad_prefs.pp";
+/// Unsaved tabs are stored in files with this prefix, so we can restore them on the next run.
+const SESSION_TAB_PREFIX: &str = "session_tab_";
 
 pub const DEFAULT_FASTA_FILE: &str = "export.fasta";
 pub const DEFAULT_GENBANK_FILE: &str = "export.gbk";
 pub const DEFAULT_DNA_FILE: &str = "export.dna";
 
+// Read this many bytes from the start of a file to identify its format.
+const FORMAT_SNIFF_LEN: u64 = 512;
+
 /// Sequence-related data to save in our own file format, GBK, or Snapgene.
 #[derive(Default)]
 pub struct StateToSave {
@@ -71,6 +85,8 @@ impl Encode for GenericData {
         self.features.encode(encoder)?;
         self.primers.encode(encoder)?;
         self.metadata.encode(encoder)?;
+        self.bookmarks.encode(encoder)?;
+        self.ambiguous.encode(encoder)?;
 
         Ok(())
     }
@@ -87,6 +103,8 @@ impl Decode for GenericData {
         let features = Vec::<Feature>::decode(decoder)?;
         let primers = Vec::<Primer>::decode(decoder)?;
         let metadata = Metadata::decode(decoder)?;
+        let bookmarks = Vec::<(usize, String)>::decode(decoder)?;
+        let ambiguous = AmbiguousList::decode(decoder)?;
 
         Ok(Self {
             seq,
@@ -94,6 +112,8 @@ impl Decode for GenericData {
             features,
             primers,
             metadata,
+            bookmarks,
+            ambiguous,
         })
     }
 }
@@ -105,9 +125,9 @@ impl StateToSave {
             // insert_loc: state.cloning_insert_loc, // todo: Not fully handled.
             // ion_concentrations: state.ion_concentrations[active].clone(),
             // path_loaded: state.path_loaded[active].clone(),
-            portions: state.portions[state.active].clone(),
+            portions: state.portions[active].clone(),
             // ab1_data: state.ab1_data.clone(),
-            ab1_data: state.ab1_data[state.active].clone(),
+            ab1_data: state.ab1_data[active].clone(),
             path_loaded: None, // todo: Is this correct?
         }
     }
@@ -142,19 +162,30 @@ pub struct PrefsToSave {
     seq_visibility: SeqVisibility,
     hide_map_feature_editor: bool,
     tabs_open: Vec<Tab>,
+    active_tab: usize,
+    /// The defaults, vice the values in use when saving.
     ion_concentrations: IonConcentrations,
+    palette: PaletteType,
+    coord_base: CoordBase,
+    map_theme: MapTheme,
+    tm_target: f32,
+    tm_pair_max_diff: f32,
+    primer_lens: PrimerLenSettings,
+    scoring_weights: ScoringWeights,
+    order_settings: OrderSettings,
+    window_size: (f32, f32),
 }
 
 impl PrefsToSave {
-    pub fn from_state(
-        state: &StateUi,
-        tabs_open_: &[Tab],
-        ion_concentrations: &IonConcentrations,
-    ) -> Self {
-        // Remove the empty paths; we can't load them.
+    pub fn from_state(state: &StateUi, tabs_open_: &[Tab], active: usize) -> Self {
+        // Remove the empty paths; we can't load them. Remap the active tab index to match.
         let mut tabs_open = Vec::new();
-        for t in tabs_open_ {
+        let mut active_tab = 0;
+        for (i, t) in tabs_open_.iter().enumerate() {
             if t.path.is_some() {
+                if i <= active {
+                    active_tab = tabs_open.len();
+                }
                 tabs_open.push(t.clone());
             }
         }
@@ -168,12 +199,22 @@ impl PrefsToSave {
             seq_visibility: state.seq_visibility.clone(),
             hide_map_feature_editor: state.hide_map_feature_editor,
             tabs_open,
-            ion_concentrations: ion_concentrations.clone(),
+            active_tab,
+            ion_concentrations: state.ion_defaults.clone(),
+            palette: state.palette,
+            coord_base: state.coord_base,
+            map_theme: state.map_theme,
+            tm_target: state.tm_target,
+            tm_pair_max_diff: state.tm_pair_max_diff,
+            primer_lens: state.primer_lens.clone(),
+            scoring_weights: state.scoring_weights.clone(),
+            order_settings: state.order_settings.clone(),
+            window_size: state.window_size,
         }
     }
 
     /// Used to load to state. The result is data from this struct, augmented with default values.
-    pub fn to_state(&self) -> (StateUi, Vec<Tab>, IonConcentrations) {
+    pub fn to_state(&self) -> (StateUi, Vec<Tab>, usize, IonConcentrations) {
         (
             StateUi {
                 page: self.page,
@@ -183,15 +224,72 @@ impl PrefsToSave {
                 selected_item: self.selected_item,
                 seq_visibility: self.seq_visibility.clone(),
                 hide_map_feature_editor: self.hide_map_feature_editor,
+                palette: self.palette,
+                coord_base: self.coord_base,
+                map_theme: self.map_theme,
+                ion_defaults: self.ion_concentrations.clone(),
+                tm_target: self.tm_target,
+                tm_pair_max_diff: self.tm_pair_max_diff,
+                primer_lens: self.primer_lens.clone(),
+                scoring_weights: self.scoring_weights.clone(),
+                order_settings: self.order_settings.clone(),
+                window_size: self.window_size,
                 // last_file_opened: self.last_file_opened.clone(),
                 ..Default::default()
             },
             self.tabs_open.clone(),
+            self.active_tab,
             self.ion_concentrations.clone(),
         )
     }
 }
 
+/// The platform's directory for our preference-related files, e.g. unsaved tabs stored between
+/// program runs. Creates it if needed. Falls back to the working directory.
+pub fn prefs_dir() -> PathBuf {
+    let dir = match ProjectDirs::from("", "", "PlasCAD") {
+        Some(d) => d.config_dir().to_owned(),
+        None => return PathBuf::new(),
+    };
+
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Unable to create the preferences directory {:?}: {e}", dir);
+        return PathBuf::new();
+    }
+
+    dir
+}
+
+/// The file an unsaved tab is stored to between program runs.
+pub fn session_tab_path(i: usize) -> PathBuf {
+    prefs_dir().join(format!("{SESSION_TAB_PREFIX}{i}.pcad"))
+}
+
+/// Delete session files past the first `count`, e.g. for unsaved tabs that were closed.
+pub fn remove_session_tabs(count: usize) {
+    let mut i = count;
+    loop {
+        let path = session_tab_path(i);
+        if !path.exists() {
+            break;
+        }
+
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Error removing session file {:?}: {e}", path);
+            break;
+        }
+        i += 1;
+    }
+}
+
+/// If true, this path is an unsaved tab's session file, vice one the user saved.
+pub fn is_session_tab(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with(SESSION_TAB_PREFIX))
+        .unwrap_or(false)
+}
+
 /// Save to file, using Bincode. We currently use this for preference files.
 pub fn save<T: Encode>(path: &Path, data: &T) -> io::Result<()> {
     let config = config::standard();
@@ -221,44 +319,52 @@ pub fn load<T: Decode>(path: &Path) -> io::Result<T> {
 }
 
 /// Export a sequence in FASTA format.
-pub fn export_fasta(seq: &[Nucleotide], name: &str, path: &Path) -> io::Result<()> {
+pub fn export_fasta(
+    seq: &[Nucleotide],
+    ambiguous: &[(usize, u8)],
+    name: &str,
+    path: &Path,
+) -> io::Result<()> {
     let file = File::create(path)?;
 
     let mut writer = fasta::Writer::new(file);
 
-    writer.write(
-        name,
-        Some("A DNA export from PlasCAD"),
-        seq_to_u8_lower(seq).as_slice(),
-    )?;
+    let mut seq = seq_to_u8_lower(seq);
+    ambiguous::restore(&mut seq, ambiguous);
+
+    writer.write(name, Some("A DNA export from PlasCAD"), seq.as_slice())?;
 
     Ok(())
 }
 
-/// Import from a FASTA file. (Seq, plasmid name (id), description)
-pub fn import_fasta(path: &Path) -> io::Result<(Seq, String, String)> {
+/// Import from a FASTA file. (Seq, ambiguous bases, plasmid name (id), description)
+pub fn import_fasta(path: &Path) -> io::Result<(Seq, AmbiguousList, String, String)> {
     let file = File::open(path)?;
 
     let mut records = fasta::Reader::new(file).records();
 
     let mut result = Vec::new();
+    let mut ambiguous_all = Vec::new();
 
     // todo: Do we want id, or description?
     let mut id = String::new();
     let mut description = String::new();
 
     while let Some(Ok(record)) = records.next() {
-        for r in record.seq() {
-            result.push(Nucleotide::from_u8(*r)?);
-            record.id().clone_into(&mut id); // Note that this overrides previous records, if applicable.
-            record
-                .desc()
-                .unwrap_or_default()
-                .clone_into(&mut description)
+        let (seq, ambiguous) = ambiguous::seq_from_bytes(record.seq());
+        for (i, char) in ambiguous {
+            ambiguous_all.push((i + result.len(), char));
         }
+        result.extend(seq);
+
+        record.id().clone_into(&mut id); // Note that this overrides previous records, if applicable.
+        record
+            .desc()
+            .unwrap_or_default()
+            .clone_into(&mut description)
     }
 
-    Ok((result, id, description))
+    Ok((result, ambiguous_all, id, description))
 }
 
 /// Save a new file, eg a cloning or PCR product.
@@ -305,13 +411,84 @@ pub fn save_new_product(name: &str, state: &mut State, ui: &mut Ui) {
     }
 }
 
+/// Formats we can import from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Pcad,
+    Fasta,
+    SnapGene,
+    GenBank,
+    Ab1,
+    /// We recognize these, but can't yet import them.
+    Embl,
+}
+
+impl ImportFormat {
+    /// Identify a format from the start of a file's content. `None` if inconclusive.
+    pub fn from_content(bytes: &[u8]) -> Option<Self> {
+        // Binary formats: SnapGene's cookie packet, AB1's ABIF header, and our start bytes.
+        if bytes.len() >= 13 && bytes[0] == 0x09 && &bytes[5..13] == b"SnapGene" {
+            return Some(Self::SnapGene);
+        }
+        if bytes.starts_with(b"ABIF") {
+            return Some(Self::Ab1);
+        }
+        if bytes.starts_with(&PCAD_START_BYTES) {
+            return Some(Self::Pcad);
+        }
+
+        // Text formats. Skip a UTF-8 byte order mark, and leading whitespace.
+        let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+        let text = &text[start..];
+
+        if text.starts_with(b"LOCUS") {
+            Some(Self::GenBank)
+        } else if text.starts_with(b">") {
+            Some(Self::Fasta)
+        } else if text.starts_with(b"ID   ") {
+            Some(Self::Embl)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension().and_then(|ext| ext.to_str())?;
+
+        match extension.to_lowercase().as_ref() {
+            "pcad" => Some(Self::Pcad),
+            // Does this work for FASTQ too?
+            "fasta" | "fa" => Some(Self::Fasta),
+            "dna" => Some(Self::SnapGene),
+            "gb" | "gbk" => Some(Self::GenBank),
+            "ab1" => Some(Self::Ab1),
+            "embl" => Some(Self::Embl),
+            _ => None,
+        }
+    }
+}
+
+/// Identify a file's format from its first bytes, since files are often misnamed; e.g. GenBank saved as
+/// `.txt`. Falls back to the extension if the content is inconclusive.
+pub fn import_format(path: &Path) -> Option<ImportFormat> {
+    let mut start = Vec::new();
+    if let Ok(file) = File::open(path) {
+        if let Err(e) = file.take(FORMAT_SNIFF_LEN).read_to_end(&mut start) {
+            eprintln!("Error reading the start of a file to import: {e}");
+        }
+    }
+
+    ImportFormat::from_content(&start).or_else(|| ImportFormat::from_extension(path))
+}
+
 /// Load state from a file of various formats.
 pub fn load_import(path: &Path) -> Option<StateToSave> {
     let mut result = StateToSave::default();
 
-    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-        match extension.to_lowercase().as_ref() {
-            "pcad" => {
+    if let Some(format) = import_format(path) {
+        match format {
+            ImportFormat::Pcad => {
                 let state_loaded = StateToSave::load_from_file(path);
                 match state_loaded {
                     Ok(s) => {
@@ -331,10 +508,10 @@ pub fn load_import(path: &Path) -> Option<StateToSave> {
                     }
                 };
             }
-            // Does this work for FASTQ too?
-            "fasta" | "fa" => {
-                if let Ok((seq, id, description)) = import_fasta(path) {
+            ImportFormat::Fasta => {
+                if let Ok((seq, ambiguous, id, description)) = import_fasta(path) {
                     result.generic.seq = seq;
+                    result.generic.ambiguous = ambiguous;
                     result.generic.metadata.plasmid_name = id;
                     result.generic.metadata.comments = vec![description];
                     // FASTA is seq-only data, so don't attempt to save over it.
@@ -345,7 +522,7 @@ pub fn load_import(path: &Path) -> Option<StateToSave> {
                     return Some(result);
                 }
             }
-            "dna" => {
+            ImportFormat::SnapGene => {
                 if let Ok(data) = import_snapgene(path) {
                     result.generic = data;
                     // We do not mark the path as opened if using SnapGene, since we currently can not
@@ -354,7 +531,7 @@ pub fn load_import(path: &Path) -> Option<StateToSave> {
                     return Some(result);
                 }
             }
-            "gb" | "gbk" => {
+            ImportFormat::GenBank => {
                 if let Ok(data) = import_genbank(path) {
                     result.generic = data;
                     // result.path_loaded = Some(Tab {
@@ -365,7 +542,7 @@ pub fn load_import(path: &Path) -> Option<StateToSave> {
                     return Some(result);
                 }
             }
-            "ab1" => {
+            ImportFormat::Ab1 => {
                 if let Ok(data) = import_ab1(path) {
                     if data.len() >= 1 {
                         result.ab1_data = data[0].clone(); // todo: Note that this assumes len 1 of results.
@@ -378,45 +555,150 @@ pub fn load_import(path: &Path) -> Option<StateToSave> {
                     return Some(result);
                 }
             }
-
-            _ => {
-                eprintln!(
-                    "The file to import must be in PlasCAD, FASTA, GenBank, SnapGene, or AB1 format."
-                )
+            ImportFormat::Embl => {
+                eprintln!("EMBL files can't be imported yet. Try GenBank or FASTA.")
             }
         }
+    } else {
+        eprintln!("The file to import must be in PlasCAD, FASTA, GenBank, SnapGene, or AB1 format.")
     }
     None
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Pcad,
+    GenBank,
+    SnapGene,
+    Fasta,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Pcad => "pcad",
+            Self::GenBank => "gbk",
+            Self::SnapGene => "dna",
+            Self::Fasta => "fasta",
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Pcad => "PlasCAD",
+            Self::GenBank => "GenBank",
+            Self::SnapGene => "SnapGene",
+            Self::Fasta => "FASTA",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+/// Export a single tab's data in the specified format.
+fn export_tab(state: &State, tab_i: usize, format: ExportFormat, path: &Path) -> io::Result<()> {
+    let data = &state.generic[tab_i];
+
+    match format {
+        ExportFormat::Pcad => StateToSave::from_state(state, tab_i).save_to_file(path),
+        ExportFormat::GenBank => {
+            let mut primer_matches = Vec::new();
+            for primer in &data.primers {
+                for prim_match in &primer.volatile.matches {
+                    primer_matches.push((prim_match.clone(), primer.name.clone()));
+                }
+            }
+            export_genbank(data, &primer_matches, path)
+        }
+        ExportFormat::SnapGene => export_snapgene(data, state.ui.primer_export, path),
+        ExportFormat::Fasta => export_fasta(
+            &data.seq,
+            &data.ambiguous,
+            &data.metadata.plasmid_name,
+            path,
+        ),
+    }
+}
+
+/// Export all open tabs to a directory, named by plasmid name. If multiple tabs share a name, we
+/// append an index. AB1 tabs are skipped. Returns the number of files written, and error messages
+/// for any that failed.
+pub fn export_all(state: &State, dir: &Path, format: ExportFormat) -> (usize, Vec<String>) {
+    let mut written = 0;
+    let mut errors = Vec::new();
+    let mut names_used: Vec<String> = Vec::new();
+
+    for (i, tab) in state.tabs_open.iter().enumerate() {
+        if tab.ab1 || i >= state.generic.len() {
+            continue;
+        }
+
+        let plasmid_name = &state.generic[i].metadata.plasmid_name;
+        let base = if plasmid_name.is_empty() {
+            "a_plasmid".to_string()
+        } else {
+            strip_filename_chars(plasmid_name)
+                .to_lowercase()
+                .replace(' ', "_")
+        };
+
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while names_used.contains(&name) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+
+        let path = dir.join(format!("{name}.{}", format.extension()));
+        names_used.push(name);
+
+        match export_tab(state, i, format, &path) {
+            Ok(_) => written += 1,
+            Err(e) => errors.push(format!("{}: {e}", path.display())),
+        }
+    }
+
+    (written, errors)
+}
+
 /// Save the current file ("save" vice "save as") if there is one; if not, quicksave to an anonymous file.
-pub fn save_current_file(state: &State) {
+pub fn save_current_file(state: &mut State) {
+    let mut saved = false;
+
     match &state.tabs_open[state.active].path {
         Some(path) => {
             if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
                 match extension.to_lowercase().as_ref() {
                     "pcad" => {
                         // if let Err(e) = save(path, &StateToSave::from_state(state, state.active)) {
-                        if let Err(e) =
-                            StateToSave::from_state(state, state.active).save_to_file(&path)
-                        {
-                            eprintln!("Error saving in PlasCAD format: {}", e);
-                        };
+                        match StateToSave::from_state(state, state.active).save_to_file(path) {
+                            Ok(_) => saved = true,
+                            Err(e) => eprintln!("Error saving in PlasCAD format: {}", e),
+                        }
                     }
                     // Does this work for FASTQ too?
                     "fasta" => {
-                        if let Err(e) = export_fasta(
+                        match export_fasta(
                             state.get_seq(),
+                            &state.generic[state.active].ambiguous,
                             &state.generic[state.active].metadata.plasmid_name,
-                            &path,
+                            path,
                         ) {
-                            eprintln!("Error exporting to FASTA: {:?}", e);
-                        };
+                            Ok(_) => saved = true,
+                            Err(e) => eprintln!("Error exporting to FASTA: {:?}", e),
+                        }
                     }
                     "dna" => {
-                        if let Err(e) = export_snapgene(&state.generic[state.active], path) {
-                            eprintln!("Error exporting to SnapGene: {:?}", e);
-                        };
+                        match export_snapgene(
+                            &state.generic[state.active],
+                            state.ui.primer_export,
+                            path,
+                        ) {
+                            Ok(_) => saved = true,
+                            Err(e) => eprintln!("Error exporting to SnapGene: {:?}", e),
+                        }
                     }
                     "gb" | "gbk" => {
                         let mut primer_matches = Vec::new();
@@ -426,11 +708,10 @@ pub fn save_current_file(state: &State) {
                             }
                         }
 
-                        if let Err(e) =
-                            export_genbank(&state.generic[state.active], &primer_matches, path)
-                        {
-                            eprintln!("Error exporting to GenBank: {:?}", e);
-                        };
+                        match export_genbank(&state.generic[state.active], &primer_matches, path) {
+                            Ok(_) => saved = true,
+                            Err(e) => eprintln!("Error exporting to GenBank: {:?}", e),
+                        }
                     }
                     _ => {
                         eprintln!("Unexpected file format loading.")
@@ -447,4 +728,65 @@ pub fn save_current_file(state: &State) {
             }
         }
     }
+
+    // Quicksaves don't count; the tab still has no file.
+    if saved {
+        state.volatile[state.active].unsaved = false;
+    }
+}
+
+/// Add features, and optionally primers, from another file of the same construct, keeping this
+/// sequence. Features are placed by coordinate; primers are matched against this sequence when
+/// synced. Returns a description of the result, or `None` if the file couldn't be loaded.
+pub fn import_annotations(
+    path: &Path,
+    data: &mut GenericData,
+    include_primers: bool,
+) -> Option<String> {
+    let imported = load_import(path)?.generic;
+
+    let seq_len = data.seq.len();
+    let mut in_range = Vec::new();
+    let mut out_of_range = 0;
+    for feature in &imported.features {
+        if feature.range.start == 0 || feature.range.start > seq_len || feature.range.end > seq_len
+        {
+            out_of_range += 1;
+        } else {
+            in_range.push(feature.clone());
+        }
+    }
+
+    let features_prev = data.features.len();
+    merge_feature_sets(&mut data.features, &in_range);
+
+    let mut result = format!("Added {} features", data.features.len() - features_prev);
+
+    if include_primers {
+        let mut added = 0;
+        for primer in &imported.primers {
+            if !data
+                .primers
+                .iter()
+                .any(|p| p.name == primer.name && p.sequence == primer.sequence)
+            {
+                data.primers.push(primer.clone());
+                added += 1;
+            }
+        }
+        result += &format!(", {added} primers");
+    }
+
+    if out_of_range > 0 {
+        result += &format!("; skipped {out_of_range} past the end of the sequence");
+    }
+
+    if imported.seq.len() != seq_len {
+        result += &format!(
+            ". Warning: The imported sequence is {} bp, vice {seq_len} bp here; coordinates may be off",
+            imported.seq.len()
+        );
+    }
+
+    Some(result)
 }
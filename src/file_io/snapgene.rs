@@ -22,16 +22,17 @@ const HTML_TAGS: [&str; 8] = [
     "<html>", "</html>", "<body>", "</body>", "<i>", "</i>", "<b>", "</b>",
 ];
 
-use na_seq::{seq_from_str, seq_to_str_lower, Nucleotide, Seq, SeqTopology};
+use na_seq::{seq_from_str, seq_to_str_lower, Seq, SeqTopology};
 
 use crate::{
+    ambiguous::{self, AmbiguousList},
     file_io::{
-        get_filename,
+        get_filename, primer_bind_features,
         snapgene::feature_xml::{
             FeatureSnapGene, Features, Notes, PrimerSnapGene, Primers, Qualifier, QualifierValue,
             Segment,
         },
-        GenericData,
+        GenericData, PrimerExport,
     },
     misc_types::{Feature, FeatureDirection, FeatureType},
     primer::{Primer, PrimerData},
@@ -112,7 +113,8 @@ pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
                 match parse_dna(payload) {
                     Ok(v) => {
                         result.seq = v.0;
-                        result.topology = v.1;
+                        result.ambiguous = v.1;
+                        result.topology = v.2;
                     }
                     Err(e) => eprintln!("Error parsing DNA packet: {:?}", e),
                 }
@@ -160,7 +162,7 @@ pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
     Ok(result)
 }
 
-fn parse_dna(payload: &[u8]) -> io::Result<(Seq, SeqTopology)> {
+fn parse_dna(payload: &[u8]) -> io::Result<(Seq, AmbiguousList, SeqTopology)> {
     if payload.is_empty() {
         return Err(io::Error::new(ErrorKind::InvalidData, "Empty DNA packet"));
     }
@@ -168,16 +170,7 @@ fn parse_dna(payload: &[u8]) -> io::Result<(Seq, SeqTopology)> {
     let flags = payload[0];
     let sequence = &payload[1..];
 
-    let mut seq = Vec::new();
-
-    for nt in sequence {
-        match Nucleotide::from_u8(*nt) {
-            Ok(n) => seq.push(n),
-            Err(_) => {
-                eprintln!("Unexpected char in DNA sequence: {:?}", nt);
-            }
-        }
-    }
+    let (seq, ambiguous) = ambiguous::seq_from_bytes(sequence);
 
     let topology = if flags & 0x01 != 0 {
         SeqTopology::Circular
@@ -187,7 +180,7 @@ fn parse_dna(payload: &[u8]) -> io::Result<(Seq, SeqTopology)> {
 
     println!("Flags: {flags}");
 
-    Ok((seq, topology))
+    Ok((seq, ambiguous, topology))
 }
 
 // todo: Consider a sub-module for XML parsing.
@@ -419,6 +412,7 @@ fn parse_features(payload: &[u8]) -> io::Result<Vec<Feature>> {
                 label: name.clone(),
                 color_override,
                 notes: notes.clone(),
+                codon_start: 1,
             });
         }
     }
@@ -449,6 +443,7 @@ fn parse_primers(payload: &[u8]) -> io::Result<Vec<Primer>> {
             sequence: seq,
             name: primer_sg.name.clone(),
             description: Some(primer_sg.description.clone()),
+            mods: Default::default(),
             volatile,
         });
     }
@@ -556,7 +551,8 @@ fn export_primers(buf: &mut Vec<u8>, primers: &[Primer]) -> io::Result<()> {
     let mut primers_sg = Primers { inner: Vec::new() };
     for primer in primers {
         primers_sg.inner.push(PrimerSnapGene {
-            sequence: seq_to_str_lower(&primer.sequence),
+            // SnapGene primers include non-binding portions, so export the full synthesized sequence.
+            sequence: seq_to_str_lower(&primer.synthesis_seq()),
             name: primer.name.clone(),
             description: primer.description.clone().unwrap_or_default(),
         });
@@ -579,7 +575,11 @@ fn export_primers(buf: &mut Vec<u8>, primers: &[Primer]) -> io::Result<()> {
 }
 
 /// Export our local state into the SnapGene dna format. This includes sequence, features, and primers.
-pub fn export_snapgene(data: &GenericData, path: &Path) -> io::Result<()> {
+pub fn export_snapgene(
+    data: &GenericData,
+    primer_export: PrimerExport,
+    path: &Path,
+) -> io::Result<()> {
     let mut file = File::create(path)?;
 
     let mut buf = Vec::new();
@@ -600,10 +600,21 @@ pub fn export_snapgene(data: &GenericData, path: &Path) -> io::Result<()> {
         SeqTopology::Linear => 0,
     };
     buf.push(flag);
-    buf.extend(seq_to_str_lower(&data.seq).as_bytes());
+    let mut seq = seq_to_str_lower(&data.seq).into_bytes();
+    ambiguous::restore(&mut seq, &data.ambiguous);
+    buf.extend(seq);
+
+    if primer_export.features() {
+        let mut features = data.features.clone();
+        features.extend(primer_bind_features(&data.primers));
+        export_features(&mut buf, &features)?;
+    } else {
+        export_features(&mut buf, &data.features)?;
+    }
 
-    export_features(&mut buf, &data.features)?;
-    export_primers(&mut buf, &data.primers)?;
+    if primer_export.records() {
+        export_primers(&mut buf, &data.primers)?;
+    }
     file.write_all(&buf)?;
 
     Ok(())
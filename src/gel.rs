@@ -0,0 +1,130 @@
+//! Predict the bands of a restriction digest on an agarose gel: Fragment sizes from cut positions,
+//! and DNA ladders to compare them against.
+
+use na_seq::{
+    restriction_enzyme::{ReMatch, RestrictionEnzyme},
+    SeqTopology,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ladder {
+    Kb1,
+    Kb1Plus,
+    Bp100,
+}
+
+impl Ladder {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Kb1 => "1 kb",
+            Self::Kb1Plus => "1 kb Plus",
+            Self::Bp100 => "100 bp",
+        }
+    }
+
+    /// Band sizes in bp, from largest to smallest. From NEB's ladders of the same names.
+    pub fn sizes(self) -> &'static [usize] {
+        match self {
+            Self::Kb1 => &[
+                10_000, 8_000, 6_000, 5_000, 4_000, 3_000, 2_000, 1_500, 1_000, 500,
+            ],
+            Self::Kb1Plus => &[
+                10_000, 8_000, 6_000, 5_000, 4_000, 3_000, 2_000, 1_500, 1_200, 1_000, 900, 800,
+                700, 600, 500, 400, 300, 200, 100,
+            ],
+            Self::Bp100 => &[
+                1_517, 1_200, 1_000, 900, 800, 700, 600, 500, 400, 300, 200, 100,
+            ],
+        }
+    }
+}
+
+pub const LADDERS: [Ladder; 3] = [Ladder::Kb1, Ladder::Kb1Plus, Ladder::Bp100];
+
+/// UI state for the gel page.
+pub struct GelUi {
+    pub ladder: Ladder,
+}
+
+impl Default for GelUi {
+    fn default() -> Self {
+        Self {
+            ladder: Ladder::Kb1,
+        }
+    }
+}
+
+/// Positions the selected enzymes cut at, combined into one digest. Each cut is immediately before
+/// the position, which is 1-based. Sorted, without duplicates.
+pub fn cut_positions(
+    res: &[RestrictionEnzyme],
+    re_matches: &[ReMatch],
+    re_lib: &[RestrictionEnzyme],
+    seq_len: usize,
+) -> Vec<usize> {
+    let mut result = Vec::new();
+    if seq_len == 0 {
+        return result;
+    }
+
+    for re_match in re_matches {
+        let re = match re_lib.get(re_match.lib_index) {
+            Some(r) => r,
+            None => continue,
+        };
+        if !res.iter().any(|r| r.name == re.name) {
+            continue;
+        }
+
+        let mut cut_i = re_match.seq_index + 1 + re.cut_after as usize;
+        // Sites near the end of a circular sequence may cut past the origin.
+        if cut_i > seq_len {
+            cut_i -= seq_len;
+        }
+        result.push(cut_i);
+    }
+
+    result.sort();
+    result.dedup();
+    result
+}
+
+/// Fragment lengths from a digest, largest first. For circular sequences, the fragment containing
+/// the origin wraps around it. An uncut sequence produces a single fragment of its full length.
+pub fn fragment_lens(cuts: &[usize], seq_len: usize, topology: SeqTopology) -> Vec<usize> {
+    let mut result = Vec::new();
+    if seq_len == 0 {
+        return result;
+    }
+
+    // A cut before the first nucleotide doesn't cut a linear sequence.
+    let cuts: Vec<_> = match topology {
+        SeqTopology::Linear => cuts
+            .iter()
+            .filter(|c| **c > 1 && **c <= seq_len)
+            .copied()
+            .collect(),
+        SeqTopology::Circular => cuts.to_vec(),
+    };
+
+    if cuts.is_empty() {
+        return vec![seq_len];
+    }
+
+    for pair in cuts.windows(2) {
+        result.push(pair[1] - pair[0]);
+    }
+
+    let first = cuts[0];
+    let last = cuts[cuts.len() - 1];
+    match topology {
+        SeqTopology::Linear => {
+            result.push(first - 1);
+            result.push(seq_len - last + 1);
+        }
+        SeqTopology::Circular => result.push(seq_len - last + first),
+    }
+
+    result.sort_by(|a, b| b.cmp(a));
+    result
+}
@@ -0,0 +1,116 @@
+//! Search all open tabs for a feature label, or a sequence motif. Useful in a project with many
+//! constructs that share parts.
+
+use na_seq::{seq_from_str, Nucleotide};
+
+use crate::{
+    ambiguous,
+    file_io::GenericData,
+    misc_types::MIN_SEARCH_LEN,
+    util::{match_subseq_fuzzy, RangeIncl},
+};
+
+/// What a hit matched.
+#[derive(Clone, Copy, PartialEq)]
+pub enum HitType {
+    /// Inner: feature index.
+    Feature(usize),
+    /// Either strand. The strand and mismatch count are included in the hit's description.
+    Motif,
+}
+
+#[derive(Clone)]
+pub struct GlobalHit {
+    pub tab: usize,
+    pub range: RangeIncl,
+    pub hit_type: HitType,
+    pub description: String,
+}
+
+pub struct GlobalFindUi {
+    pub show: bool,
+    pub text: String,
+    /// 0 to 1. Applies to motif matches only.
+    pub min_identity: f32,
+    /// `None` if a search hasn't been run.
+    pub hits: Option<Vec<GlobalHit>>,
+}
+
+impl Default for GlobalFindUi {
+    fn default() -> Self {
+        Self {
+            show: false,
+            text: String::new(),
+            min_identity: 1.,
+            hits: None,
+        }
+    }
+}
+
+/// If the text consists only of nucleotides, and is long enough, parse it as a motif.
+fn motif_from_text(text: &str) -> Option<Vec<Nucleotide>> {
+    let text = text.trim();
+    if text.len() < MIN_SEARCH_LEN
+        || !text
+            .chars()
+            .all(|c| matches!(c.to_ascii_lowercase(), 'a' | 'c' | 't' | 'g'))
+    {
+        return None;
+    }
+    Some(seq_from_str(text))
+}
+
+/// Find features whose label contains the text (case-insensitive), and, if the text is a nucleotide
+/// sequence, matches of it on either strand. Results are ordered by tab.
+pub fn global_find(tabs: &[GenericData], text: &str, min_identity: f32) -> Vec<GlobalHit> {
+    let mut result = Vec::new();
+
+    let text_lower = text.trim().to_lowercase();
+    if text_lower.is_empty() {
+        return result;
+    }
+    let motif = motif_from_text(text);
+
+    for (tab, data) in tabs.iter().enumerate() {
+        for (i, feature) in data.features.iter().enumerate() {
+            if feature.label.to_lowercase().contains(&text_lower) {
+                result.push(GlobalHit {
+                    tab,
+                    range: feature.range,
+                    hit_type: HitType::Feature(i),
+                    description: format!(
+                        "{} ({})",
+                        feature.label,
+                        feature.feature_type.to_string()
+                    ),
+                });
+            }
+        }
+
+        if let Some(motif) = &motif {
+            let (fwd, rev) = match_subseq_fuzzy(motif, &data.seq, min_identity);
+            for (matches, reverse) in [(fwd, false), (rev, true)] {
+                for (range, mismatches) in matches {
+                    if ambiguous::overlaps(&data.ambiguous, range) {
+                        continue;
+                    }
+                    let dir = if reverse { "Rev" } else { "Fwd" };
+                    let description = if mismatches == 0 {
+                        format!("Motif, {dir}")
+                    } else {
+                        format!("Motif, {dir}, {mismatches} mismatches")
+                    };
+
+                    result.push(GlobalHit {
+                        tab,
+                        range,
+                        hit_type: HitType::Motif,
+                        description,
+                    });
+                }
+            }
+        }
+    }
+
+    result
+}
@@ -0,0 +1,255 @@
+//! Golden Gate assembly planning. Type IIS enzymes cut outside their (non-palindromic) recognition
+//! site, so each part carries its own fusion overhangs; parts ligate in order when each part's right
+//! overhang matches the next part's left one. We find these overhangs for each junction, and flag
+//! problems that cause misassembly: Internal sites, mismatched or repeated overhangs, and palindromes.
+//!
+//! Our restriction enzyme library assumes palindromic sites, cut symmetrically, so we define Type IIS
+//! enzymes here.
+
+use na_seq::{seq_complement, seq_from_str, seq_to_str_lower, Nucleotide, SeqTopology};
+
+use crate::{ambiguous, file_io::GenericData, util::match_subseq};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TypeIisEnzyme {
+    BsaI,
+    BbsI,
+    BsmBI,
+    SapI,
+}
+
+impl TypeIisEnzyme {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::BsaI => "BsaI",
+            Self::BbsI => "BbsI",
+            Self::BsmBI => "BsmBI",
+            Self::SapI => "SapI",
+        }
+    }
+
+    /// The recognition site, 5' to 3', on the strand it cuts downstream of.
+    pub fn site(self) -> Vec<Nucleotide> {
+        let site = match self {
+            Self::BsaI => "ggtctc",
+            Self::BbsI => "gaagac",
+            Self::BsmBI => "cgtctc",
+            Self::SapI => "gctcttc",
+        };
+        seq_from_str(site)
+    }
+
+    /// Distance from the end of the site to the top and bottom strand cuts; e.g. BsaI is GGTCTC(1/5).
+    /// The difference is the overhang length.
+    fn cut_offsets(self) -> (usize, usize) {
+        match self {
+            Self::BsaI | Self::BsmBI => (1, 5),
+            Self::BbsI => (2, 6),
+            Self::SapI => (1, 4),
+        }
+    }
+
+    pub fn overhang_len(self) -> usize {
+        let (top, bottom) = self.cut_offsets();
+        bottom - top
+    }
+}
+
+pub const TYPE_IIS_ENZYMES: [TypeIisEnzyme; 4] = [
+    TypeIisEnzyme::BsaI,
+    TypeIisEnzyme::BbsI,
+    TypeIisEnzyme::BsmBI,
+    TypeIisEnzyme::SapI,
+];
+
+/// UI state for the Golden Gate planner.
+pub struct GoldenGateUi {
+    pub enzyme: TypeIisEnzyme,
+    /// Tab indices, in assembly order.
+    pub tabs: Vec<usize>,
+    /// `None` if the assembly hasn't been planned since the last change.
+    pub result: Option<GoldenGateAssembly>,
+}
+
+impl Default for GoldenGateUi {
+    fn default() -> Self {
+        Self {
+            enzyme: TypeIisEnzyme::BsaI,
+            tabs: Vec::new(),
+            result: None,
+        }
+    }
+}
+
+/// A part's sites and overhangs. Overhangs are on the top strand, 5' to 3'.
+pub struct GoldenGatePart {
+    pub tab: usize,
+    /// From the site on the top strand, at the part's left (5') end.
+    pub overhang_left: Option<Vec<Nucleotide>>,
+    /// From the site on the bottom strand, at the part's right (3') end.
+    pub overhang_right: Option<Vec<Nucleotide>>,
+    /// Sites beyond the pair flanking the part. These cut the part during assembly.
+    pub internal_sites: usize,
+}
+
+/// The junction between a part, and the one following it. The last part joins the first, since the
+/// assembly is circular.
+pub struct GoldenGateJunction {
+    /// Indices into the assembly's parts.
+    pub part_left: usize,
+    pub part_right: usize,
+    /// The left part's right overhang. `None` if it's missing.
+    pub overhang: Option<Vec<Nucleotide>>,
+    /// The right part's left overhang matches this junction's.
+    pub compatible: bool,
+    /// An overhang that is its own reverse complement can ligate to a copy of itself, flipped.
+    pub palindromic: bool,
+    /// Indices of other junctions with the same overhang, on either strand.
+    pub duplicates: Vec<usize>,
+}
+
+pub struct GoldenGateAssembly {
+    pub parts: Vec<GoldenGatePart>,
+    pub junctions: Vec<GoldenGateJunction>,
+}
+
+impl GoldenGateAssembly {
+    /// If true, every junction has a unique, compatible overhang, and no part has internal sites.
+    pub fn valid(&self) -> bool {
+        self.parts.iter().all(|p| p.internal_sites == 0)
+            && self.junctions.iter().all(|j| {
+                j.overhang.is_some() && j.compatible && !j.palindromic && j.duplicates.is_empty()
+            })
+    }
+}
+
+/// Display an overhang, or a placeholder if it's missing.
+pub fn overhang_to_str(overhang: &Option<Vec<Nucleotide>>) -> String {
+    match overhang {
+        Some(o) => seq_to_str_lower(o).to_uppercase(),
+        None => "—".to_string(),
+    }
+}
+
+/// Read `len` nucleotides starting at a 0-based index, which may be negative, or past the end; we
+/// wrap around the origin. Returns `None` for linear sequences if this runs off either end.
+fn read_wrapped(
+    seq: &[Nucleotide],
+    start: isize,
+    len: usize,
+    circular: bool,
+) -> Option<Vec<Nucleotide>> {
+    let seq_len = seq.len() as isize;
+    if seq_len == 0 {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(len);
+    for i in start..start + len as isize {
+        if !circular && (i < 0 || i >= seq_len) {
+            return None;
+        }
+        result.push(seq[i.rem_euclid(seq_len) as usize]);
+    }
+    Some(result)
+}
+
+/// Find a part's overhangs. We take the first site on the top strand as the left end, and the last on
+/// the bottom strand as the right end; this suits both inserts, and destination vectors, whose sites
+/// point outward from the dropout.
+fn find_part(tab: usize, data: &GenericData, enzyme: TypeIisEnzyme) -> GoldenGatePart {
+    let seq = &data.seq;
+    let circular = data.topology == SeqTopology::Circular;
+    let site_len = enzyme.site().len() as isize;
+    let (top, bottom) = enzyme.cut_offsets();
+    let oh_len = enzyme.overhang_len();
+
+    let (mut fwd, mut rev) = match_subseq(&enzyme.site(), seq);
+    fwd.retain(|r| !ambiguous::overlaps(&data.ambiguous, *r));
+    rev.retain(|r| !ambiguous::overlaps(&data.ambiguous, *r));
+
+    // Ranges are 1-based; convert to 0-based starts.
+    let overhang_left = fwd.first().and_then(|r| {
+        read_wrapped(
+            seq,
+            r.start as isize - 1 + site_len + top as isize,
+            oh_len,
+            circular,
+        )
+    });
+
+    let overhang_right = rev.last().and_then(|r| {
+        read_wrapped(
+            seq,
+            r.start as isize - 1 - bottom as isize,
+            oh_len,
+            circular,
+        )
+    });
+
+    let internal_sites = (fwd.len() + rev.len()).saturating_sub(2);
+
+    GoldenGatePart {
+        tab,
+        overhang_left,
+        overhang_right,
+        internal_sites,
+    }
+}
+
+/// Plan an assembly of the given tabs, in order.
+pub fn plan_assembly(
+    tabs: &[usize],
+    data: &[GenericData],
+    enzyme: TypeIisEnzyme,
+) -> GoldenGateAssembly {
+    let parts: Vec<_> = tabs
+        .iter()
+        .filter(|t| **t < data.len())
+        .map(|t| find_part(*t, &data[*t], enzyme))
+        .collect();
+
+    let mut junctions = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let part_right = (i + 1) % parts.len();
+        let overhang = part.overhang_right.clone();
+
+        let compatible = overhang.is_some() && overhang == parts[part_right].overhang_left;
+        let palindromic = match &overhang {
+            Some(o) => seq_complement(o) == *o,
+            None => false,
+        };
+
+        junctions.push(GoldenGateJunction {
+            part_left: i,
+            part_right,
+            overhang,
+            compatible,
+            palindromic,
+            duplicates: Vec::new(),
+        });
+    }
+
+    for i in 0..junctions.len() {
+        let overhang = match junctions[i].overhang.clone() {
+            Some(o) => o,
+            None => continue,
+        };
+        let overhang_rc = seq_complement(&overhang);
+
+        junctions[i].duplicates = junctions
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| {
+                *j != i
+                    && match &other.overhang {
+                        Some(o) => *o == overhang || *o == overhang_rc,
+                        None => false,
+                    }
+            })
+            .map(|(j, _)| j)
+            .collect();
+    }
+
+    GoldenGateAssembly { parts, junctions }
+}
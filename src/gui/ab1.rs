@@ -3,37 +3,64 @@
 use copypasta::{ClipboardContext, ClipboardProvider};
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, RichText, Sense, Shape,
-        Slider, Stroke, Ui,
+        pos2, vec2, Align2, Color32, ComboBox, FontFamily, FontId, Frame, Pos2, Rect, RichText,
+        Sense, Shape, Slider, Stroke, Ui,
     },
     emath::RectTransform,
-    epaint::PathShape,
 };
-use na_seq::{seq_to_str_lower, Nucleotide};
+use na_seq::{seq_to_str_lower, Nucleotide, SeqTopology};
 
 use crate::{
-    ab1::SeqRecordAb1,
+    ab1::{Ab1Ui, SeqRecordAb1},
+    alignment::{align_read, ReadAlignment, ReadDiffKind, READ_QUALITY_MIN},
     feature_db_load::find_features,
     file_io::GenericData,
-    gui::{BACKGROUND_COLOR, COL_SPACING, ROW_SPACING},
+    gui::{
+        navigation::{get_tab_names, Page, PageSeq},
+        select_color_text,
+        sequence::seq_view::COLOR_READ_DIFF,
+        theme::COLOR_ACTION,
+        BACKGROUND_COLOR, COL_SPACING, ROW_SPACING,
+    },
     misc_types::Metadata,
     state::State,
-    util::merge_feature_sets,
+    util::{disp_i, disp_range, merge_feature_sets},
 };
 
-const NT_COLOR: Color32 = Color32::from_rgb(180, 220, 220);
-const NT_WIDTH: f32 = 8.; // pixels
 const STROKE_WIDTH_PEAK: f32 = 1.;
-const PEAK_WIDTH_DIV2: f32 = 2.;
 
 // Peak  heights are normallized, so that the maximum value is this.
 const PEAK_MAX_HEIGHT: f32 = 120.;
 
+// Vertical layout, in pixels.
+const TRACE_Y: f32 = 140.;
+const NT_Y: f32 = 160.;
+const QUALITY_Y: f32 = 174.;
+const QUALITY_MAX_HEIGHT: f32 = 30.;
+/// Phred scores are rarely above this.
+const QUALITY_MAX: u8 = 60;
+
+/// Space left of the first base call displayed, in pixels.
+const OFFSET_X: f32 = 12.;
+
+const ZOOM_MIN: f32 = 0.25;
+const ZOOM_MAX: f32 = 6.;
+
+// Phred quality score thresholds. 20 is a 1% chance of an incorrect call, and 30 is 0.1%.
+const QUALITY_HIGH: u8 = 30;
+const QUALITY_LOW: u8 = 20;
+
 const COLOR_A: Color32 = Color32::from_rgb(20, 220, 20);
 const COLOR_C: Color32 = Color32::from_rgb(130, 130, 255);
 const COLOR_T: Color32 = Color32::from_rgb(255, 100, 100);
 const COLOR_G: Color32 = Color32::from_rgb(200, 200, 200);
 
+const COLOR_QUALITY_HIGH: Color32 = Color32::from_rgb(80, 140, 200);
+const COLOR_QUALITY_MED: Color32 = Color32::GOLD;
+const COLOR_QUALITY_LOW: Color32 = Color32::from_rgb(220, 60, 60);
+const COLOR_SELECTED: Color32 = Color32::from_rgb(90, 90, 40);
+const COLOR_EDITED: Color32 = Color32::YELLOW;
+
 /// This mapping is based off conventions in other software.
 fn nt_color_map(nt: Nucleotide) -> Color32 {
     match nt {
@@ -44,138 +71,393 @@ fn nt_color_map(nt: Nucleotide) -> Color32 {
     }
 }
 
-/// Map sequence index to a horizontal pixel.
-fn index_to_posit(i: usize, num_nts: usize, ui: &Ui) -> f32 {
-    // todo: Cap if width too small for nt len. Varying zoom, etc.
-    // let width = ui.available_width();
-    //
-    // let num_nts_disp = width / NT_WIDTH;
+/// Base calls are in their nucleotide's color, dimmed for lower quality.
+fn base_color(nt: Nucleotide, quality: Option<u8>) -> Color32 {
+    let color = nt_color_map(nt);
+    match quality {
+        Some(q) if q < QUALITY_LOW => color.gamma_multiply(0.35),
+        Some(q) if q < QUALITY_HIGH => color.gamma_multiply(0.65),
+        _ => color,
+    }
+}
 
-    // let scale_factor = width / num_nts as f32;
-    i as f32 * NT_WIDTH
+fn quality_color(quality: u8) -> Color32 {
+    if quality >= QUALITY_HIGH {
+        COLOR_QUALITY_HIGH
+    } else if quality >= QUALITY_LOW {
+        COLOR_QUALITY_MED
+    } else {
+        COLOR_QUALITY_LOW
+    }
+}
+
+/// Map a trace sample index to a horizontal pixel.
+fn sample_to_x(sample: usize, sample_start: usize, zoom: f32) -> f32 {
+    OFFSET_X + (sample as f32 - sample_start as f32) * zoom
 }
 
-/// Plot the peaks and confidence values; draw letters
-fn plot(data: &SeqRecordAb1, to_screen: &RectTransform, start_i: usize, ui: &mut Ui) -> Vec<Shape> {
+/// The trace sample at the left edge of the view.
+fn view_start_sample(data: &SeqRecordAb1, ui_data: &Ab1Ui) -> usize {
+    data.peak_sample(ui_data.start_i)
+        .saturating_sub((OFFSET_X / ui_data.zoom) as usize)
+}
+
+/// Find the base call nearest a horizontal pixel position.
+fn base_at_x(data: &SeqRecordAb1, ui_data: &Ab1Ui, x: f32) -> Option<usize> {
+    let sample_start = view_start_sample(data, ui_data);
+    let sample = sample_start as f32 + (x - OFFSET_X) / ui_data.zoom;
+
+    let mut result = None;
+    let mut dist_min = f32::MAX;
+    for i in ui_data.start_i.saturating_sub(1)..data.seq().len() {
+        let dist = (data.peak_sample(i) as f32 - sample).abs();
+        if dist > dist_min {
+            break; // Peaks are in order.
+        }
+        dist_min = dist;
+        result = Some(i);
+    }
+
+    result
+}
+
+/// Plot the four channels of the trace, base calls, and quality values.
+fn plot(
+    data: &SeqRecordAb1,
+    to_screen: &RectTransform,
+    ui_data: &Ab1Ui,
+    ui: &mut Ui,
+) -> Vec<Shape> {
     let mut result = Vec::new();
 
-    let nt_y = 160.;
-    let plot_y = nt_y - 20.;
+    let seq = data.seq();
+    let trace_len = data.trace_len();
+    if seq.is_empty() || trace_len == 0 {
+        return result;
+    }
 
-    // todo: Don't run this calc every time.
-    let data_scaler = {
-        let mut max_peak = 0;
-        for i in 0..data.data_ch1.len() {
-            if i > 40 {
-                // todo: Sloppy performance saver.
-                continue;
-            }
+    let width = to_screen.from().width();
+    let zoom = ui_data.zoom;
 
-            let ch1 = data.data_ch1[i];
-            let ch2 = data.data_ch2[i];
-            let ch3 = data.data_ch3[i];
-            let ch4 = data.data_ch4[i];
+    let sample_start = view_start_sample(data, ui_data).min(trace_len - 1);
+    let sample_end = (sample_start + (width / zoom) as usize).min(trace_len);
 
-            for ch in [ch1, ch2, ch3, ch4] {
-                if ch > max_peak {
-                    max_peak = ch;
-                }
+    // Scale peaks to the tallest one in view.
+    let data_scaler = {
+        let mut max_peak = 1;
+        for ch in [
+            &data.data_ch1,
+            &data.data_ch2,
+            &data.data_ch3,
+            &data.data_ch4,
+        ] {
+            for v in &ch[sample_start..sample_end] {
+                max_peak = max_peak.max(*v);
             }
         }
 
         PEAK_MAX_HEIGHT / max_peak as f32
     };
 
-    let width = ui.available_width();
-    let num_nts_disp = width / NT_WIDTH;
+    // Display the trace.
+    for (ch, color) in [
+        (&data.data_ch1, COLOR_G),
+        (&data.data_ch2, COLOR_A),
+        (&data.data_ch3, COLOR_T),
+        (&data.data_ch4, COLOR_C),
+    ] {
+        let points = (sample_start..sample_end)
+            .map(|s| {
+                to_screen
+                    * pos2(
+                        sample_to_x(s, sample_start, zoom),
+                        TRACE_Y - ch[s] as f32 * data_scaler,
+                    )
+            })
+            .collect();
+
+        result.push(Shape::line(points, Stroke::new(STROKE_WIDTH_PEAK, color)));
+    }
 
     // Display nucleotides and quality values.
-    for i_pos in 0..num_nts_disp as usize {
-        let i = start_i + i_pos;
-        if data.sequence.is_empty() || i > data.sequence.len() - 1 {
+    for (i, nt) in seq.iter().enumerate().skip(ui_data.start_i) {
+        let sample = data.peak_sample(i);
+        if sample >= sample_end {
             break;
         }
-
-        let nt = data.sequence[i];
-
-        let x_pos = index_to_posit(i_pos, data.sequence.len(), ui);
-
-        if x_pos > ui.available_width() - 15. {
-            continue; // todo: QC the details, if you need to_screen here etc.
+        let x = sample_to_x(sample, sample_start, zoom);
+
+        let quality = match &data.quality {
+            Some(q) => q.get(i).copied(),
+            None => None,
+        };
+
+        if ui_data.base_selected == Some(i) {
+            result.push(Shape::rect_filled(
+                Rect::from_center_size(to_screen * pos2(x, NT_Y), vec2(12., 18.)),
+                2.,
+                COLOR_SELECTED,
+            ));
         }
 
-        let nt_color = nt_color_map(nt);
-
         result.push(ui.ctx().fonts(|fonts| {
             Shape::text(
                 fonts,
-                to_screen * pos2(x_pos, nt_y),
+                to_screen * pos2(x, NT_Y),
                 Align2::CENTER_CENTER,
-                &nt.to_str_lower(),
+                nt.to_str_lower(),
                 FontId::new(12., FontFamily::Monospace),
-                nt_color,
+                base_color(*nt, quality),
             )
         }));
 
-        // todo: Display quality values below.
-        // let quality = data.quality[i]; // todo: Index check.
+        if data.base_edited(i) {
+            result.push(Shape::line_segment(
+                [
+                    to_screen * pos2(x - 4., NT_Y + 8.),
+                    to_screen * pos2(x + 4., NT_Y + 8.),
+                ],
+                Stroke::new(2., COLOR_EDITED),
+            ));
+        }
+
+        if let Some(q) = quality {
+            let height = q.min(QUALITY_MAX) as f32 / QUALITY_MAX as f32 * QUALITY_MAX_HEIGHT;
+            result.push(Shape::rect_filled(
+                Rect::from_two_pos(
+                    to_screen * pos2(x - 2., QUALITY_Y),
+                    to_screen * pos2(x + 2., QUALITY_Y + height),
+                ),
+                0.,
+                quality_color(q),
+            ));
+        }
     }
 
-    // Display data.
-    for i_pos in 0..num_nts_disp as usize * 4 {
-        let i = start_i * 4 + i_pos;
-        if data.data_ch1.is_empty() || i > data.data_ch1.len() - 1 {
-            break;
+    result
+}
+
+/// Select from open AB1 traces.
+fn trace_selector(state: &mut State, ui: &mut Ui) {
+    let plasmid_names: &Vec<_> = &state
+        .generic
+        .iter()
+        .map(|v| v.metadata.plasmid_name.as_str())
+        .collect();
+
+    let traces: Vec<_> = get_tab_names(&state.tabs_open, plasmid_names, true)
+        .into_iter()
+        .filter(|(_, i)| state.tabs_open[*i].ab1)
+        .collect();
+
+    if traces.len() < 2 {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Traces:");
+        for (name, i) in traces {
+            if ui
+                .button(select_color_text(&name, i == state.active))
+                .clicked()
+            {
+                state.active = i;
+                state.ui.ab1.start_i = 0;
+                state.ui.ab1.base_selected = None;
+            }
         }
+    });
+    ui.add_space(ROW_SPACING / 2.);
+}
+
+/// Correct the selected base call.
+fn base_edit(state: &mut State, ui: &mut Ui) {
+    let data = &state.ab1_data[state.active];
 
-        let x_pos = index_to_posit(i_pos, data.sequence.len(), ui) / 4.;
-        if x_pos > ui.available_width() - 15. {
-            continue; // todo: QC the details, if you need to_screen here etc.
+    let i = match state.ui.ab1.base_selected {
+        Some(i) if i < data.seq().len() => i,
+        _ => {
+            ui.label("Click a base call to correct it.");
+            return;
         }
+    };
 
-        let ch1 = data.data_ch1[i];
-        let ch2 = data.data_ch2[i];
-        let ch3 = data.data_ch3[i];
-        let ch4 = data.data_ch4[i];
-        // todo: Index error handling.
-
-        for (ch, color) in [
-            (ch1, COLOR_G),
-            (ch2, COLOR_A),
-            (ch3, COLOR_T),
-            (ch4, COLOR_C),
-        ] {
-            let stroke = Stroke::new(STROKE_WIDTH_PEAK, color);
-
-            // todo: Autoscale  height
-            let base_pos = pos2(x_pos, plot_y);
-
-            // todo: These may get too thin.
-            let top_left = base_pos + vec2(-PEAK_WIDTH_DIV2, ch as f32 * -data_scaler);
-            let top_right = base_pos + vec2(PEAK_WIDTH_DIV2, ch as f32 * -data_scaler);
-            let bottom_left = base_pos + vec2(-PEAK_WIDTH_DIV2, 0.);
-            let bottom_right = base_pos + vec2(PEAK_WIDTH_DIV2, 0.);
-
-            result.push(ui.ctx().fonts(|fonts| {
-                // Shape::Path(PathShape::convex_polygon(
-                Shape::Path(PathShape::closed_line(
-                    vec![
-                        to_screen * top_left,
-                        to_screen * bottom_left,
-                        to_screen * bottom_right,
-                        to_screen * top_right,
-                    ],
-                    // color,
-                    stroke,
-                ))
-            }));
+    let nt = data.seq()[i];
+    let quality = match &data.quality {
+        Some(q) => match q.get(i) {
+            Some(v) => format!(", Q{v}"),
+            None => String::new(),
+        },
+        None => String::new(),
+    };
+
+    ui.label(format!("Base {}: {}{quality}", i + 1, nt.to_str_lower()));
+
+    if data.base_edited(i) {
+        ui.label(
+            RichText::new(format!("(called {})", data.sequence[i].to_str_lower()))
+                .color(COLOR_EDITED),
+        );
+    }
+
+    ui.label("Change to:");
+    for nt_new in [Nucleotide::A, Nucleotide::C, Nucleotide::G, Nucleotide::T] {
+        if ui
+            .button(RichText::new(nt_new.to_str_lower().to_uppercase()).color(nt_color_map(nt_new)))
+            .clicked()
+        {
+            state.ab1_data[state.active].set_base(i, nt_new);
         }
     }
+}
 
-    result
+/// E.g. "1204 A→g", "560 +t", or "88 -C". Reference bases are uppercase, and read bases lowercase.
+fn read_diff_text(posit: usize, kind: ReadDiffKind, reference: &[Nucleotide]) -> String {
+    let ref_nt = match reference.get(posit - 1) {
+        Some(nt) => nt.to_str_lower().to_uppercase(),
+        None => String::new(),
+    };
+
+    match kind {
+        ReadDiffKind::Mismatch(nt) => {
+            format!("{} {ref_nt}→{}", disp_i(posit), nt.to_str_lower())
+        }
+        ReadDiffKind::Insertion(nt) => format!("{} +{}", disp_i(posit), nt.to_str_lower()),
+        ReadDiffKind::Deletion => format!("{} -{ref_nt}", disp_i(posit)),
+    }
+}
+
+fn read_align_summary(aln: &ReadAlignment, reference: &[Nucleotide], ui: &mut Ui) {
+    let diffs_high = aln.diffs.iter().filter(|d| d.high_quality).count();
+    let diffs_low = aln.diffs.len() - diffs_high;
+
+    let orientation = if aln.reverse {
+        ", as its reverse complement"
+    } else {
+        ""
+    };
+
+    ui.label(format!(
+        "{:.1}% identity over {} high-quality bp, covering {}{orientation}. {diffs_high} difference(s), \
+        and {diffs_low} in low-quality regions.",
+        aln.identity * 100.,
+        aln.high_quality_len,
+        disp_range(aln.ref_range),
+    ));
+
+    if aln.diffs.is_empty() {
+        return;
+    }
+
+    let color_low = COLOR_READ_DIFF.gamma_multiply(0.35);
+    ui.horizontal_wrapped(|ui| {
+        for diff in &aln.diffs {
+            let color = if diff.high_quality {
+                COLOR_READ_DIFF
+            } else {
+                color_low
+            };
+            ui.label(RichText::new(read_diff_text(diff.posit, diff.kind, reference)).color(color));
+            ui.add_space(COL_SPACING / 2.);
+        }
+    });
+}
+
+/// Align the read to a sequence in another tab, e.g. to verify a clone, and summarize the differences.
+fn read_align_disp(state: &mut State, ui: &mut Ui) {
+    let plasmid_names: &Vec<_> = &state
+        .generic
+        .iter()
+        .map(|v| v.metadata.plasmid_name.as_str())
+        .collect();
+
+    let refs: Vec<_> = get_tab_names(&state.tabs_open, plasmid_names, true)
+        .into_iter()
+        .filter(|(_, i)| !state.tabs_open[*i].ab1)
+        .collect();
+
+    if refs.is_empty() {
+        ui.label("Open a sequence to align this read to, e.g. to verify a clone.");
+        return;
+    }
+
+    let mut show_on_seq = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Align to:");
+
+        let selected_text = match state.ui.ab1.ref_tab {
+            Some(i) => match refs.iter().find(|(_, j)| *j == i) {
+                Some((name, _)) => name.clone(),
+                None => String::new(),
+            },
+            None => String::new(),
+        };
+
+        ComboBox::from_id_salt(4_900)
+            .width(160.)
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for (name, i) in &refs {
+                    ui.selectable_value(&mut state.ui.ab1.ref_tab, Some(*i), name);
+                }
+            });
+
+        if let Some(ref_tab) = state.ui.ab1.ref_tab {
+            ui.add_space(COL_SPACING);
+
+            if ui
+                .button(RichText::new("Align").color(COLOR_ACTION))
+                .on_hover_text(format!(
+                    "Align the base calls, including corrections, to this sequence. Differences \
+                    where the read's quality is below {READ_QUALITY_MIN} are de-emphasized."
+                ))
+                .clicked()
+            {
+                let read = &state.ab1_data[state.active];
+                let reference = &state.generic[ref_tab];
+
+                state.alignment.read_alignment = align_read(
+                    read.seq(),
+                    read.quality.as_deref(),
+                    &reference.seq,
+                    reference.topology == SeqTopology::Circular,
+                    state.active,
+                    ref_tab,
+                );
+            }
+        }
+
+        if let Some(aln) = &state.alignment.read_alignment {
+            if aln.read_tab == state.active {
+                ui.add_space(COL_SPACING);
+                if ui
+                    .button("Show on sequence")
+                    .on_hover_text("Mark the differences on the reference's sequence view.")
+                    .clicked()
+                {
+                    show_on_seq = true;
+                }
+            }
+        }
+    });
+
+    if let Some(aln) = &state.alignment.read_alignment {
+        if aln.read_tab == state.active {
+            read_align_summary(aln, &state.generic[aln.ref_tab].seq, ui);
+
+            if show_on_seq {
+                state.active = aln.ref_tab;
+                state.ui.page = Page::Sequence;
+                state.ui.page_seq = PageSeq::View;
+            }
+        }
+    }
 }
 
 pub fn ab1_page(state: &mut State, ui: &mut Ui) {
+    trace_selector(state, ui);
+
     ui.horizontal(|ui| {
         let data = &state.ab1_data[state.active];
 
@@ -183,9 +465,9 @@ pub fn ab1_page(state: &mut State, ui: &mut Ui) {
 
         ui.add_space(COL_SPACING * 2.);
 
-        if ui.button(RichText::new("🗐 Copy sequence")).on_hover_text("Copy this sequence to the clipboard.").clicked() {
+        if ui.button(RichText::new("🗐 Copy sequence")).on_hover_text("Copy this sequence to the clipboard, including corrections.").clicked() {
             let mut ctx = ClipboardContext::new().unwrap();
-            ctx.set_contents(seq_to_str_lower(&data.sequence)).unwrap();
+            ctx.set_contents(seq_to_str_lower(data.seq())).unwrap();
         }
 
         ui.add_space(COL_SPACING);
@@ -203,7 +485,7 @@ pub fn ab1_page(state: &mut State, ui: &mut Ui) {
             }.to_owned().replace(".ab1", "");
 
             let generic = GenericData {
-                seq: data.sequence.clone(),
+                seq: data.seq().clone(),
                 metadata: Metadata {
                     plasmid_name,
                     ..Default::default()
@@ -218,58 +500,88 @@ pub fn ab1_page(state: &mut State, ui: &mut Ui) {
             state.volatile.push(Default::default());
             state.tabs_open.push(Default::default());
             state.ab1_data.push(Default::default());
+            state.cloning.push(Default::default());
 
             state.active = state.generic.len() - 1;
+            state.volatile[state.active].unsaved = true; // Not yet saved to a file.
 
             // state.sync_seq_related(None);
 
             // Annotate. Don't add duplicates.
-            let features = find_features(&state.get_seq());
+            let features = find_features(state.get_seq());
             merge_feature_sets(&mut state.generic[state.active].features, &features)
 
         }
     });
     ui.add_space(ROW_SPACING / 2.);
 
+    // The new tab isn't an AB1 one.
+    if !state.tabs_open[state.active].ab1 {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Zoom:");
+        ui.add(Slider::new(&mut state.ui.ab1.zoom, ZOOM_MIN..=ZOOM_MAX).logarithmic(true));
+        ui.add_space(COL_SPACING);
+
+        base_edit(state, ui);
+
+        if state.ab1_data[state.active].sequence_user.is_some() {
+            ui.add_space(COL_SPACING);
+            if ui
+                .button("Revert corrections")
+                .on_hover_text("Restore all base calls to the original ones from the file.")
+                .clicked()
+            {
+                state.ab1_data[state.active].sequence_user = None;
+            }
+        }
+    });
+    ui.add_space(ROW_SPACING / 2.);
+
+    read_align_disp(state, ui);
+    ui.add_space(ROW_SPACING / 2.);
+
     let data = &state.ab1_data[state.active];
     let width = ui.available_width();
-    let num_nts_disp = width / NT_WIDTH;
     ui.spacing_mut().slider_width = width - 60.;
 
+    // Scroll by base call. Allow scrolling until the last call is at the right edge of the view.
     let slider_max = {
-        let v = data.sequence.len() as isize - num_nts_disp as isize + 1;
-        if v > 0 {
-            v as usize
-        } else {
-            0
-        }
+        let samples_disp = (width / state.ui.ab1.zoom) as usize;
+        let last = data.trace_len().saturating_sub(samples_disp);
+        (0..data.seq().len())
+            .find(|i| data.peak_sample(*i) >= last)
+            .unwrap_or(0)
     };
 
-    ui.add(Slider::new(&mut state.ui.ab1_start_i, 0..=slider_max));
+    ui.add(Slider::new(&mut state.ui.ab1.start_i, 0..=slider_max));
 
     ui.add_space(ROW_SPACING / 2.);
 
-    let mut shapes = Vec::new();
-
     Frame::canvas(ui.style())
         .fill(BACKGROUND_COLOR)
         .show(ui, |ui| {
-            let data = &state.ab1_data[state.active];
-
             let (response, _painter) = {
                 let desired_size = vec2(ui.available_width(), ui.available_height());
                 ui.allocate_painter(desired_size, Sense::click())
             };
 
             let to_screen = RectTransform::from_to(
-                // Rect::from_min_size(pos2(0., -VERITICAL_CIRCLE_OFFSET), response.rect.size()),
                 Rect::from_min_size(Pos2::ZERO, response.rect.size()),
                 response.rect,
             );
 
-            let rect_size = response.rect.size();
+            if response.clicked() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let pos_rel = to_screen.inverse() * pos;
+                    state.ui.ab1.base_selected =
+                        base_at_x(&state.ab1_data[state.active], &state.ui.ab1, pos_rel.x);
+                }
+            }
 
-            shapes.append(&mut plot(data, &to_screen, state.ui.ab1_start_i, ui));
+            let shapes = plot(&state.ab1_data[state.active], &to_screen, &state.ui.ab1, ui);
 
             ui.painter().extend(shapes);
         });
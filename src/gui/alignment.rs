@@ -1,15 +1,35 @@
-use eframe::egui::{Color32, FontFamily, FontId, RichText, ScrollArea, TextEdit, Ui};
-use na_seq::{seq_aa_from_str, seq_aa_to_str, seq_from_str, seq_to_str_lower};
+use std::{io, sync::mpsc::TryRecvError, time::Duration};
+
+use eframe::egui::{
+    text::LayoutJob, Color32, ComboBox, FontFamily, FontId, Grid, ProgressBar, RichText,
+    ScrollArea, TextEdit, TextFormat, Ui,
+};
+use na_seq::{seq_aa_from_str, seq_aa_to_str, seq_from_str, seq_to_str_lower, Nucleotide};
 
 use crate::{
-    alignment::{align_pairwise_aa, align_pairwise_nt, distance_aa, distance_nt, AlignmentMode},
+    alignment::{
+        align_multiple, align_pairwise_aa, align_pairwise_nt, distance_aa, distance_nt,
+        AlignmentMode, MsaResult,
+    },
+    alignment_map::{call_variants, coverage, import_async},
     gui::{
+        navigation::{get_tab_names, Page, PageSeq},
+        select_color_text,
+        sequence::seq_view::COLOR_VARIANT,
         theme::{COLOR_ACTION, COLOR_INFO},
         COL_SPACING, ROW_SPACING,
     },
     state::State,
 };
 
+/// Columns per line of the multiple alignment.
+const MSA_LINE_LEN: usize = 80;
+
+const COLOR_MSA_MATCH: Color32 = Color32::LIGHT_GRAY;
+const COLOR_MSA_GAP: Color32 = Color32::DARK_GRAY;
+/// Behind columns that aren't conserved.
+const COLOR_MSA_COL_BG: Color32 = Color32::from_rgb(60, 50, 30);
+
 fn mode_btn(state: &mut State, mode: AlignmentMode, name: &str, ui: &mut Ui) {
     let color = if state.alignment.mode == mode {
         Color32::LIGHT_BLUE
@@ -78,6 +98,393 @@ fn input_area(state: &mut State, seq_b: bool, ui: &mut Ui) {
     }
 }
 
+/// Import a BAM file in a separate thread, showing its progress. We poll for the result here.
+pub fn bam_import(state: &mut State, ui: &mut Ui) {
+    let mut result = None;
+    if let Some(import) = &state.alignment.bam_import {
+        match import.rx.try_recv() {
+            Ok(r) => result = Some(r),
+            Err(TryRecvError::Empty) => {
+                // Keep the progress bar moving while the UI is otherwise idle.
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+            }
+            Err(TryRecvError::Disconnected) => {
+                result = Some(Err(io::Error::other("The import stopped unexpectedly")));
+            }
+        }
+    }
+
+    if let Some(result) = result {
+        state.alignment.bam_import = None;
+        match result {
+            Ok(map) => {
+                let mut status = format!(
+                    "Loaded {} alignments against {} reference sequence(s)",
+                    map.alignments.len(),
+                    map.refs.len()
+                );
+                if map.truncated {
+                    status += ". The file is truncated or damaged; later alignments were skipped.";
+                }
+                state.alignment.bam_status = Some(status);
+
+                // Default to the reference that matches the active sequence's length, if there is one.
+                let seq_len = state.get_seq().len();
+                state.alignment.bam_ref = map
+                    .refs
+                    .iter()
+                    .position(|r| r.l_ref as usize == seq_len)
+                    .unwrap_or(0);
+                state.alignment.coverage = Some(coverage(&map, state.alignment.bam_ref));
+                state.alignment.placed_reads = None;
+                state.alignment.bam = Some(map);
+                sync_variants(state);
+            }
+            Err(e) => {
+                eprintln!("Error importing BAM file: {e}");
+                state.alignment.bam_status = Some(format!("Error importing BAM file: {e}"));
+            }
+        }
+    }
+
+    ui.horizontal(|ui| {
+        match &state.alignment.bam_import {
+            Some(import) => {
+                ui.label(format!("Loading {}", import.path.display()));
+                ui.add(
+                    ProgressBar::new(import.progress())
+                        .desired_width(200.)
+                        .show_percentage(),
+                );
+                if ui.button("Cancel").clicked() {
+                    import.cancel();
+                }
+            }
+            None => {
+                if ui
+                    .button("Import BAM")
+                    .on_hover_text("Load an alignment map, e.g. from sequencing reads.")
+                    .clicked()
+                {
+                    state.ui.file_dialogs.import_bam.select_file();
+                }
+            }
+        }
+
+        if let Some(status) = &state.alignment.bam_status {
+            ui.add_space(COL_SPACING);
+            ui.label(RichText::new(status).color(COLOR_INFO));
+        }
+    });
+
+    let mut hide_coverage = false;
+    let mut ref_changed = false;
+    if let Some(map) = &state.alignment.bam {
+        if !map.refs.is_empty() {
+            let ref_prev = state.alignment.bam_ref;
+            ui.horizontal(|ui| {
+                ui.label("Coverage reference:").on_hover_text(
+                    "Show read depth over this reference on the sequence page. It should be the \
+                    sequence in the active tab.",
+                );
+                ComboBox::from_id_salt(4_300)
+                    .width(200.)
+                    .selected_text(&map.refs[state.alignment.bam_ref.min(map.refs.len() - 1)].name)
+                    .show_ui(ui, |ui| {
+                        for (i, ref_seq) in map.refs.iter().enumerate() {
+                            ui.selectable_value(&mut state.alignment.bam_ref, i, &ref_seq.name);
+                        }
+                    });
+
+                if state.alignment.coverage.is_none() {
+                    // It's cleared when the active sequence changes.
+                    if ui
+                        .button(RichText::new("Show coverage").color(COLOR_ACTION))
+                        .on_hover_text(
+                            "Show read depth, and call variants over this reference, against the active sequence.",
+                        )
+                        .clicked()
+                    {
+                        ref_changed = true;
+                    }
+                }
+
+                if ui
+                    .button("Hide coverage")
+                    .on_hover_text("Remove the coverage track from the sequence page.")
+                    .clicked()
+                {
+                    hide_coverage = true;
+                }
+            });
+
+            if state.alignment.bam_ref != ref_prev || ref_changed {
+                state.alignment.coverage = Some(coverage(map, state.alignment.bam_ref));
+                state.alignment.placed_reads = None;
+                ref_changed = true;
+            }
+        }
+    }
+
+    if hide_coverage {
+        state.alignment.bam = None;
+        state.alignment.coverage = None;
+        state.alignment.bam_status = None;
+        state.alignment.variants = Vec::new();
+        state.alignment.placed_reads = None;
+    }
+
+    if ref_changed {
+        sync_variants(state);
+    }
+
+    state.ui.file_dialogs.import_bam.update(ui.ctx());
+
+    if let Some(path) = state.ui.file_dialogs.import_bam.take_selected() {
+        state.alignment.bam_status = None;
+        state.alignment.bam_import = Some(import_async(&path));
+    }
+}
+
+/// Call variants from the loaded alignments, against the active sequence.
+fn sync_variants(state: &mut State) {
+    state.alignment.variants = match &state.alignment.bam {
+        Some(map) => call_variants(
+            map,
+            state.alignment.bam_ref,
+            &state.generic[state.active].seq,
+            &state.alignment.variant_settings,
+        ),
+        None => Vec::new(),
+    };
+}
+
+/// Variant calling settings, and a table of the candidate variants found.
+fn variants_disp(state: &mut State, ui: &mut Ui) {
+    if state.alignment.bam.is_none() {
+        return;
+    }
+
+    let settings = &mut state.alignment.variant_settings;
+    ui.horizontal(|ui| {
+        ui.heading("Variants");
+        ui.add_space(COL_SPACING);
+
+        ui.label("Min fraction (%):")
+            .on_hover_text("Report positions where at least this portion of reads show the same non-reference base.");
+        let mut entry = format!("{:.0}", settings.min_fraction * 100.);
+        if ui.add(TextEdit::singleline(&mut entry).desired_width(30.)).changed() {
+            settings.min_fraction = entry.parse::<f32>().unwrap_or(0.).clamp(0., 100.) / 100.;
+        }
+
+        ui.label("Min depth:")
+            .on_hover_text("Skip positions covered by fewer reads than this.");
+        let mut entry = settings.min_depth.to_string();
+        if ui.add(TextEdit::singleline(&mut entry).desired_width(30.)).changed() {
+            settings.min_depth = entry.parse().unwrap_or(0);
+        }
+
+        ui.label("Min base quality:")
+            .on_hover_text("Don't count bases with a Phred quality score below this.");
+        let mut entry = settings.min_qual.to_string();
+        if ui.add(TextEdit::singleline(&mut entry).desired_width(30.)).changed() {
+            settings.min_qual = entry.parse().unwrap_or(0);
+        }
+
+        ui.add_space(COL_SPACING);
+    });
+
+    ui.horizontal(|ui| {
+        if ui
+            .button(RichText::new("Call variants").color(COLOR_ACTION))
+            .on_hover_text("Compare the loaded reads against the sequence in the active tab.")
+            .clicked()
+        {
+            sync_variants(state);
+        }
+
+        ui.label(format!(
+            "{} candidate variants",
+            state.alignment.variants.len()
+        ));
+    });
+
+    let mut jump_to = None;
+    ScrollArea::vertical()
+        .id_salt(201)
+        .max_height(200.)
+        .show(ui, |ui| {
+            Grid::new(202).striped(true).show(ui, |ui| {
+                for label in ["Position", "Ref", "Alt", "Fraction", "Depth", ""] {
+                    ui.label(RichText::new(label).strong());
+                }
+                ui.end_row();
+
+                for variant in &state.alignment.variants {
+                    ui.label(variant.posit.to_string());
+                    ui.label(variant.ref_nt.to_str_lower().to_uppercase());
+                    ui.label(
+                        RichText::new(variant.alt.to_str_lower().to_uppercase())
+                            .color(COLOR_VARIANT),
+                    );
+                    ui.label(format!("{:.0}%", variant.fraction() * 100.));
+                    ui.label(variant.depth.to_string());
+                    if ui.button("View").clicked() {
+                        jump_to = Some(variant.posit);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    if let Some(posit) = jump_to {
+        state.ui.text_cursor_i = Some(posit);
+        state.ui.scroll_to_seq_i = Some(posit);
+        state.ui.page = Page::Sequence;
+        state.ui.page_seq = PageSeq::View;
+    }
+}
+
+/// Lay out one line of an alignment row, starting at column `start`. Entries differing from the
+/// consensus are highlighted, as are columns that aren't conserved.
+fn msa_line(
+    row: &[Option<Nucleotide>],
+    msa: &MsaResult,
+    start: usize,
+    consensus: bool,
+) -> LayoutJob {
+    let font_id = FontId::new(14., FontFamily::Monospace);
+    let mut job = LayoutJob::default();
+
+    for (i, entry) in row.iter().enumerate() {
+        let col = start + i;
+
+        let (text, mut color) = match entry {
+            Some(nt) => (nt.to_str_lower().to_uppercase(), COLOR_MSA_MATCH),
+            None => ("-".to_owned(), COLOR_MSA_GAP),
+        };
+        if consensus {
+            if entry.is_some() {
+                color = COLOR_INFO;
+            }
+        } else if *entry != msa.consensus[col] {
+            color = COLOR_VARIANT;
+        }
+
+        let background = if msa.column_conserved(col) {
+            Color32::TRANSPARENT
+        } else {
+            COLOR_MSA_COL_BG
+        };
+
+        job.append(
+            &text,
+            0.,
+            TextFormat {
+                font_id: font_id.clone(),
+                color,
+                background,
+                ..Default::default()
+            },
+        );
+    }
+    job
+}
+
+/// Select tabs to align, and display their multiple alignment, with a consensus.
+fn msa_disp(state: &mut State, ui: &mut Ui) {
+    ui.heading("Multiple alignment");
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Sequences:").on_hover_text(
+            "Click to add a tab to the alignment, or remove it. The first one selected is the \
+            reference; each other sequence is aligned to it.",
+        );
+
+        let plasmid_names: &Vec<_> = &state
+            .generic
+            .iter()
+            .map(|v| v.metadata.plasmid_name.as_str())
+            .collect();
+
+        let tabs = &mut state.alignment.msa_tabs;
+        for (name, i) in get_tab_names(&state.tabs_open, plasmid_names, true) {
+            let position = tabs.iter().position(|t| *t == i);
+            let text = match position {
+                Some(p) => format!("{}: {name}", p + 1),
+                None => name,
+            };
+
+            if ui
+                .button(select_color_text(&text, position.is_some()))
+                .clicked()
+            {
+                match position {
+                    Some(p) => {
+                        tabs.remove(p);
+                    }
+                    None => tabs.push(i),
+                }
+                state.alignment.msa_result = None;
+            }
+        }
+    });
+
+    if state.alignment.msa_tabs.len() >= 2
+        && ui
+            .button(RichText::new("Align selected").color(COLOR_ACTION))
+            .clicked()
+    {
+        let seqs: Vec<_> = state
+            .alignment
+            .msa_tabs
+            .iter()
+            .map(|t| state.generic[*t].seq.as_slice())
+            .collect();
+        state.alignment.msa_result = Some(align_multiple(&seqs));
+    }
+
+    let msa = match &state.alignment.msa_result {
+        Some(m) => m,
+        None => return,
+    };
+
+    ui.label(format!(
+        "{} columns, {} not conserved",
+        msa.num_cols(),
+        msa.mismatch_count()
+    ));
+    ui.add_space(ROW_SPACING / 2.);
+
+    let names: Vec<_> = state
+        .alignment
+        .msa_tabs
+        .iter()
+        .map(|t| state.generic[*t].metadata.plasmid_name.clone())
+        .collect();
+
+    for (line, start) in (0..msa.num_cols()).step_by(MSA_LINE_LEN).enumerate() {
+        let end = (start + MSA_LINE_LEN).min(msa.num_cols());
+
+        Grid::new(5_000 + line).show(ui, |ui| {
+            ui.label("");
+            ui.label(RichText::new((start + 1).to_string()).color(COLOR_MSA_GAP));
+            ui.end_row();
+
+            for (row, name) in msa.rows.iter().zip(&names) {
+                ui.label(name);
+                ui.label(msa_line(&row[start..end], msa, start, false));
+                ui.end_row();
+            }
+
+            ui.label(RichText::new("Consensus").color(COLOR_INFO));
+            ui.label(msa_line(&msa.consensus[start..end], msa, start, true));
+            ui.end_row();
+        });
+        ui.add_space(ROW_SPACING / 2.);
+    }
+}
+
 pub fn alignment_page(state: &mut State, ui: &mut Ui) {
     ui.add_space(ROW_SPACING);
 
@@ -91,6 +498,12 @@ pub fn alignment_page(state: &mut State, ui: &mut Ui) {
     });
     ui.add_space(ROW_SPACING);
 
+    bam_import(state, ui);
+    ui.add_space(ROW_SPACING);
+
+    variants_disp(state, ui);
+    ui.add_space(ROW_SPACING);
+
     ScrollArea::vertical().id_salt(200).show(ui, |ui| {
         input_area(state, false, ui);
         ui.add_space(ROW_SPACING);
@@ -146,5 +559,10 @@ pub fn alignment_page(state: &mut State, ui: &mut Ui) {
                 ui.label(dist_type_text);
             });
         }
+
+        if state.alignment.mode == AlignmentMode::Dna {
+            ui.add_space(ROW_SPACING);
+            msa_disp(state, ui);
+        }
     });
 }
@@ -0,0 +1,469 @@
+//! A view of reads from a BAM file, placed on their reference using their CIGAR strings, under a
+//! coverage histogram. Similar to a genome browser's read track.
+
+use eframe::{
+    egui::{
+        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, RichText, Sense, Shape,
+        Slider, Stroke, Ui,
+    },
+    emath::RectTransform,
+};
+
+use crate::{
+    alignment_map::{place_reads, AlignmentMapUi, PlacedRead},
+    gui::{
+        alignment::bam_import, int_field, theme::COLOR_INFO, BACKGROUND_COLOR, COL_SPACING,
+        ROW_SPACING,
+    },
+    state::State,
+    util::{disp_i, disp_range, RangeIncl},
+};
+
+/// Space left and right of the reference, in pixels.
+const OFFSET_X: f32 = 10.;
+
+// Vertical layout, in pixels.
+const COVERAGE_Y: f32 = 8.;
+const COVERAGE_HEIGHT: f32 = 60.;
+const RULER_Y: f32 = COVERAGE_Y + COVERAGE_HEIGHT + 8.;
+const READS_Y: f32 = RULER_Y + 24.;
+const ROW_HEIGHT: f32 = 6.;
+const READ_HEIGHT: f32 = 4.;
+
+/// Minimum distance between ruler ticks, in pixels.
+const TICK_SPACING_MIN: f32 = 80.;
+
+const ZOOM_MIN: f32 = 0.01;
+const ZOOM_MAX: f32 = 20.;
+
+const COLOR_COVERAGE: Color32 = Color32::from_rgb(100, 180, 255);
+const COLOR_RULER: Color32 = Color32::LIGHT_GRAY;
+const COLOR_FORWARD: Color32 = Color32::from_rgb(110, 150, 210);
+const COLOR_REVERSE: Color32 = Color32::from_rgb(210, 130, 130);
+const COLOR_GAP: Color32 = Color32::from_rgb(120, 120, 120);
+const COLOR_MISMATCH: Color32 = Color32::from_rgb(255, 60, 60);
+const COLOR_INSERTION: Color32 = Color32::from_rgb(190, 80, 255);
+
+/// Number of nucleotides in view.
+fn nts_disp(width: f32, zoom: f32) -> usize {
+    ((width - 2. * OFFSET_X) / zoom).max(1.) as usize
+}
+
+/// Map a 0-based reference position to a horizontal pixel.
+fn index_to_x(i: usize, ui_data: &AlignmentMapUi) -> f32 {
+    OFFSET_X + (i as f32 - ui_data.view_start as f32) * ui_data.zoom
+}
+
+/// The 0-based reference position at a horizontal pixel.
+fn x_to_index(x: f32, ui_data: &AlignmentMapUi) -> usize {
+    ui_data.view_start + ((x - OFFSET_X) / ui_data.zoom).max(0.) as usize
+}
+
+/// Ruler tick spacing, in nucleotides: 1, 2, or 5, times a power of 10.
+fn tick_interval(zoom: f32) -> usize {
+    let mut scale = 1;
+    loop {
+        for mult in [1, 2, 5] {
+            if (scale * mult) as f32 * zoom >= TICK_SPACING_MIN {
+                return scale * mult;
+            }
+        }
+        scale *= 10;
+    }
+}
+
+fn text(pos: Pos2, align: Align2, val: String, color: Color32, ui: &Ui) -> Shape {
+    ui.ctx().fonts(|fonts| {
+        Shape::text(
+            fonts,
+            pos,
+            align,
+            val,
+            FontId::new(12., FontFamily::Proportional),
+            color,
+        )
+    })
+}
+
+/// Read depth in view. Each bar shows the maximum depth of the positions it covers. Scaled to the
+/// deepest position in view.
+fn draw_coverage(
+    coverage: &[u32],
+    view_end: usize,
+    to_screen: &RectTransform,
+    ui_data: &AlignmentMapUi,
+    ui: &Ui,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    let view_start = ui_data.view_start.min(view_end);
+    let in_view = &coverage[view_start..view_end];
+    if in_view.is_empty() {
+        return result;
+    }
+
+    let depth_max = in_view.iter().copied().max().unwrap_or(0).max(1);
+
+    // One bar per pixel column, or per nucleotide if zoomed in past that.
+    let width = in_view.len() as f32 * ui_data.zoom;
+    let num_bars = (width as usize).min(in_view.len()).max(1);
+    for bar in 0..num_bars {
+        let start = bar * in_view.len() / num_bars;
+        let end = ((bar + 1) * in_view.len() / num_bars).max(start + 1);
+        let depth = in_view[start..end].iter().copied().max().unwrap_or(0);
+        if depth == 0 {
+            continue;
+        }
+
+        let height = depth as f32 / depth_max as f32 * COVERAGE_HEIGHT;
+        result.push(Shape::rect_filled(
+            Rect::from_two_pos(
+                to_screen
+                    * pos2(
+                        index_to_x(view_start + start, ui_data),
+                        COVERAGE_Y + COVERAGE_HEIGHT - height,
+                    ),
+                to_screen
+                    * pos2(
+                        index_to_x(view_start + end, ui_data),
+                        COVERAGE_Y + COVERAGE_HEIGHT,
+                    ),
+            ),
+            0.,
+            COLOR_COVERAGE,
+        ));
+    }
+
+    result.push(text(
+        to_screen * pos2(OFFSET_X, COVERAGE_Y),
+        Align2::LEFT_TOP,
+        format!("Max depth in view: {depth_max}"),
+        COLOR_INFO,
+        ui,
+    ));
+
+    result
+}
+
+/// Reference positions, labeled as displayed elsewhere.
+fn draw_ruler(
+    view_end: usize,
+    to_screen: &RectTransform,
+    ui_data: &AlignmentMapUi,
+    ui: &Ui,
+) -> Vec<Shape> {
+    let mut result = vec![Shape::line_segment(
+        [
+            to_screen * pos2(index_to_x(ui_data.view_start, ui_data), RULER_Y),
+            to_screen * pos2(index_to_x(view_end, ui_data), RULER_Y),
+        ],
+        Stroke::new(1., COLOR_RULER),
+    )];
+
+    let interval = tick_interval(ui_data.zoom);
+    // Ticks are at 1-based positions that are multiples of the interval.
+    let first = (ui_data.view_start / interval + 1) * interval;
+
+    for posit in (first..=view_end).step_by(interval) {
+        // Center the tick on its nucleotide.
+        let x = index_to_x(posit - 1, ui_data) + ui_data.zoom / 2.;
+        result.push(Shape::line_segment(
+            [
+                to_screen * pos2(x, RULER_Y - 4.),
+                to_screen * pos2(x, RULER_Y + 4.),
+            ],
+            Stroke::new(1., COLOR_RULER),
+        ));
+        result.push(text(
+            to_screen * pos2(x, RULER_Y + 6.),
+            Align2::CENTER_TOP,
+            disp_i(posit).to_string(),
+            COLOR_RULER,
+            ui,
+        ));
+    }
+
+    result
+}
+
+/// Draw reads in view, one row at a time. Aligned blocks are filled, deletions and skipped regions
+/// are a thin line, and mismatches and insertions are marked.
+fn draw_reads(
+    reads: &[PlacedRead],
+    view_end: usize,
+    rows_max: usize,
+    to_screen: &RectTransform,
+    ui_data: &AlignmentMapUi,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    let x_min = OFFSET_X;
+    let x_max = index_to_x(view_end, ui_data);
+    let clamp = |x: f32| x.clamp(x_min, x_max);
+
+    // Reads are sorted by start position.
+    let end_i = reads.partition_point(|r| r.start < view_end);
+    for read in &reads[..end_i] {
+        if read.end <= ui_data.view_start || read.row >= rows_max {
+            continue;
+        }
+
+        let y = READS_Y + read.row as f32 * ROW_HEIGHT;
+        let y_center = y + READ_HEIGHT / 2.;
+
+        let color = if read.reverse {
+            COLOR_REVERSE
+        } else {
+            COLOR_FORWARD
+        };
+
+        if read.blocks.len() > 1 {
+            result.push(Shape::line_segment(
+                [
+                    to_screen * pos2(clamp(index_to_x(read.start, ui_data)), y_center),
+                    to_screen * pos2(clamp(index_to_x(read.end, ui_data)), y_center),
+                ],
+                Stroke::new(1., COLOR_GAP),
+            ));
+        }
+
+        for (start, end) in &read.blocks {
+            if *end <= ui_data.view_start || *start >= view_end {
+                continue;
+            }
+            result.push(Shape::rect_filled(
+                Rect::from_two_pos(
+                    to_screen * pos2(clamp(index_to_x(*start, ui_data)), y),
+                    to_screen * pos2(clamp(index_to_x(*end, ui_data)), y + READ_HEIGHT),
+                ),
+                0.,
+                color,
+            ));
+        }
+
+        // Keep marks at least a pixel wide when zoomed out.
+        let mark_width = ui_data.zoom.max(1.);
+
+        for posit in &read.mismatches {
+            if *posit < ui_data.view_start || *posit >= view_end {
+                continue;
+            }
+            let x = index_to_x(*posit, ui_data);
+            result.push(Shape::rect_filled(
+                Rect::from_two_pos(
+                    to_screen * pos2(x, y),
+                    to_screen * pos2(x + mark_width, y + READ_HEIGHT),
+                ),
+                0.,
+                COLOR_MISMATCH,
+            ));
+        }
+
+        for posit in &read.insertions {
+            if *posit <= ui_data.view_start || *posit >= view_end {
+                continue;
+            }
+            let x = index_to_x(*posit, ui_data);
+            result.push(Shape::line_segment(
+                [
+                    to_screen * pos2(x, y - 1.),
+                    to_screen * pos2(x, y + READ_HEIGHT + 1.),
+                ],
+                Stroke::new(1., COLOR_INSERTION),
+            ));
+        }
+    }
+
+    result
+}
+
+/// The read under the cursor, if any.
+fn read_at(
+    reads: &[PlacedRead],
+    pos: Pos2,
+    rows_max: usize,
+    ui_data: &AlignmentMapUi,
+) -> Option<usize> {
+    if pos.y < READS_Y || pos.x < OFFSET_X {
+        return None;
+    }
+
+    let row = ((pos.y - READS_Y) / ROW_HEIGHT) as usize;
+    if row >= rows_max {
+        return None;
+    }
+    let i = x_to_index(pos.x, ui_data);
+
+    let end_i = reads.partition_point(|r| r.start <= i);
+    reads[..end_i]
+        .iter()
+        .position(|r| r.row == row && i < r.end)
+}
+
+/// Center the view on a 1-based position, as displayed.
+fn jump_to(ui_data: &mut AlignmentMapUi, ref_len: usize, nts_disp: usize) {
+    let i = ui_data
+        .jump_to
+        .saturating_sub(1)
+        .min(ref_len.saturating_sub(1));
+    ui_data.view_start = i
+        .saturating_sub(nts_disp / 2)
+        .min(ref_len.saturating_sub(nts_disp));
+}
+
+pub fn alignment_map_page(state: &mut State, ui: &mut Ui) {
+    ui.add_space(ROW_SPACING);
+    ui.heading("Alignment map");
+    ui.add_space(ROW_SPACING / 2.);
+
+    bam_import(state, ui);
+    ui.add_space(ROW_SPACING / 2.);
+
+    let ref_len = match &state.alignment.coverage {
+        Some(c) => c.len(),
+        None => {
+            if state.alignment.bam.is_some() {
+                ui.label("Show coverage to view reads against the active sequence.");
+            } else {
+                ui.label("Load a BAM file to view its reads against the active sequence.");
+            }
+            return;
+        }
+    };
+
+    if state.alignment.placed_reads.is_none() {
+        if let Some(map) = &state.alignment.bam {
+            state.alignment.placed_reads = Some(place_reads(
+                map,
+                state.alignment.bam_ref,
+                &state.generic[state.active].seq,
+            ));
+        }
+    }
+
+    let seq_len = state.generic[state.active].seq.len();
+    if ref_len != seq_len {
+        ui.label(
+            RichText::new(format!(
+                "The BAM reference is {ref_len} bp; this sequence is {seq_len} bp. Mismatches may \
+                not be accurate."
+            ))
+            .color(Color32::GOLD),
+        );
+    }
+
+    let width = ui.available_width();
+    let ui_data = &mut state.ui.alignment_map;
+
+    let num_reads = match &state.alignment.placed_reads {
+        Some(r) => r.len(),
+        None => 0,
+    };
+
+    ui.horizontal(|ui| {
+        ui.label("Zoom:");
+        ui.add(
+            Slider::new(&mut ui_data.zoom, ZOOM_MIN..=ZOOM_MAX)
+                .logarithmic(true)
+                .suffix(" px/bp"),
+        );
+        ui.add_space(COL_SPACING);
+
+        int_field(&mut ui_data.jump_to, "Jump to:", ui);
+        if ui
+            .button("Go")
+            .on_hover_text("Center the view on this position.")
+            .clicked()
+        {
+            jump_to(ui_data, ref_len, nts_disp(width, ui_data.zoom));
+        }
+        ui.add_space(COL_SPACING);
+
+        ui.label(RichText::new(format!("{num_reads} reads placed")).color(COLOR_INFO));
+    });
+    ui.add_space(ROW_SPACING / 2.);
+
+    let view_len = nts_disp(width, ui_data.zoom);
+    let view_start_max = ref_len.saturating_sub(view_len);
+    ui_data.view_start = ui_data.view_start.min(view_start_max);
+
+    ui.spacing_mut().slider_width = width - 60.;
+    ui.add(Slider::new(&mut ui_data.view_start, 0..=view_start_max).show_value(false));
+    ui.add_space(ROW_SPACING / 2.);
+
+    let view_end = (ui_data.view_start + view_len).min(ref_len);
+
+    let reads = match &state.alignment.placed_reads {
+        Some(r) => r,
+        None => return,
+    };
+    let coverage = match &state.alignment.coverage {
+        Some(c) => c,
+        None => return,
+    };
+    let ui_data = &state.ui.alignment_map;
+
+    Frame::canvas(ui.style())
+        .fill(BACKGROUND_COLOR)
+        .show(ui, |ui| {
+            let (response, _painter) = {
+                let desired_size = vec2(ui.available_width(), ui.available_height());
+                ui.allocate_painter(desired_size, Sense::hover())
+            };
+
+            let to_screen = RectTransform::from_to(
+                Rect::from_min_size(Pos2::ZERO, response.rect.size()),
+                response.rect,
+            );
+
+            let rows_max = ((response.rect.height() - READS_Y) / ROW_HEIGHT).max(0.) as usize;
+
+            let mut shapes = draw_coverage(coverage, view_end, &to_screen, ui_data, ui);
+            shapes.append(&mut draw_ruler(view_end, &to_screen, ui_data, ui));
+            shapes.append(&mut draw_reads(
+                reads, view_end, rows_max, &to_screen, ui_data,
+            ));
+
+            // Reads that don't fit vertically.
+            let hidden = reads
+                .iter()
+                .filter(|r| r.row >= rows_max && r.start < view_end && r.end > ui_data.view_start)
+                .count();
+            if hidden > 0 {
+                shapes.push(text(
+                    to_screen * pos2(response.rect.width() - OFFSET_X, COVERAGE_Y),
+                    Align2::RIGHT_TOP,
+                    format!("{hidden} reads in view not shown"),
+                    Color32::GOLD,
+                    ui,
+                ));
+            }
+
+            ui.painter().extend(shapes);
+
+            if let Some(pos) = response.hover_pos() {
+                let pos_rel = to_screen.inverse() * pos;
+
+                if pos_rel.y < RULER_Y {
+                    let i = x_to_index(pos_rel.x, ui_data);
+                    if i < view_end {
+                        response.on_hover_text(format!("{}: depth {}", disp_i(i + 1), coverage[i]));
+                    }
+                } else if let Some(read_i) = read_at(reads, pos_rel, rows_max, ui_data) {
+                    let read = &reads[read_i];
+                    if let Some(map) = &state.alignment.bam {
+                        let alignment = &map.alignments[read.alignment_i];
+                        let strand = if read.reverse { "reverse" } else { "forward" };
+                        response.on_hover_text(format!(
+                            "{}\n{}, {strand}\nMAPQ: {}  Mismatches: {}  Insertions: {}",
+                            alignment.read_name,
+                            disp_range(RangeIncl::new(read.start + 1, read.end)),
+                            alignment.mapq,
+                            read.mismatches.len(),
+                            read.insertions.len(),
+                        ));
+                    }
+                }
+            }
+        });
+}
@@ -1,41 +1,66 @@
 //! A module for the circular view of a plasmid
 
 use core::f32::consts::TAU;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    io::ErrorKind,
+    path::Path,
+};
 
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, CursorIcon, FontFamily, FontId, Frame, Pos2, Rect, RichText,
-        ScrollArea, Sense, Shape, Slider, Stroke, Ui,
+        pos2, vec2, Align2, Button, Color32, ComboBox, CursorIcon, FontFamily, FontId, Frame, Pos2,
+        Rect, RichText, ScrollArea, Sense, Shape, Slider, Stroke, TextEdit, Ui, Vec2, Window,
     },
     emath::RectTransform,
     epaint::{CircleShape, PathShape},
 };
 use na_seq::{
     restriction_enzyme::{ReMatch, RestrictionEnzyme},
-    seq_to_str_lower,
+    seq_to_str_lower, AaIdent, Nucleotide, SeqTopology,
 };
 
 use crate::{
     gui::{
-        feature_from_index, feature_table::feature_table, get_cursor_text, lin_maps,
-        lin_maps::MINI_DISP_NT_LEN, navigation::NAV_BUTTON_COLOR, select_feature, COLOR_RE,
-        COLOR_SEQ, COL_SPACING, PRIMER_FWD_COLOR, ROW_SPACING, SPLIT_SCREEN_MAX_HEIGHT,
+        bookmark_disp, feature_from_index,
+        feature_table::{direction_picker, feature_table, feature_type_picker},
+        features_cut_by_re, get_cursor_text, handle_measure_click, int_field,
+        ligation::buffer_rec_disp,
+        lin_maps,
+        lin_maps::MINI_DISP_NT_LEN,
+        map_image::copy_map_button,
+        map_svg::shapes_to_svg,
+        measure_disp,
+        navigation::NAV_BUTTON_COLOR,
+        primer_from_index, re_cut_index, re_cut_text, re_cuts_coding, select_feature,
+        sequence::seq_view::{COLOR_BOOKMARK, COLOR_MEASURE},
+        theme::{map_colors, map_theme, palette, palette_type, MapTheme, COLOR_ACTION},
+        COLOR_SEQ, COL_SPACING, ROW_SPACING, SPLIT_SCREEN_MAX_HEIGHT,
     },
     misc_types::{Feature, FeatureDirection, FeatureType},
     primer::Primer,
+    protein::{aa_seq_text, translate_feature},
     state::State,
-    Selection,
+    util::{disp_i, gc_window, RangeIncl},
+    Selection, StateUi,
 };
 
-const BACKGROUND_COLOR: Color32 = Color32::from_rgb(10, 20, 10);
-
-const BACKBONE_COLOR: Color32 = Color32::from_rgb(180, 180, 180);
 const BACKBONE_WIDTH: f32 = 10.;
 
 pub const TICK_COLOR: Color32 = Color32::from_rgb(180, 220, 220);
 const TICK_WIDTH: f32 = 2.;
 pub const RE_WIDTH: f32 = 2.;
-const TICK_SPACING: usize = 500; // Nucleotides between ticks
+// Candidate nucleotide counts between ticks. We use the smallest that keeps the tick count at or below
+// `TICK_COUNT_MAX`.
+const TICK_SPACINGS: [usize; 11] = [
+    10, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000,
+];
+const TICK_COUNT_MAX: usize = 20;
+// Label ticks in kb at or above this sequence length.
+const TICK_KB_THRESH: usize = 10_000;
 
 const TICK_LEN: f32 = 90.; // in pixels.
 const TICK_LEN_DIV_2: f32 = TICK_LEN / 2.;
@@ -55,6 +80,11 @@ pub const FEATURE_STROKE_WIDTH: f32 = 3.;
 const PRIMER_WIDTH: f32 = 54.;
 pub const PRIMER_STROKE_WIDTH: f32 = 2.;
 
+// The GC content ring, just inside features.
+const GC_RING_OFFSET: f32 = FEATURE_WIDTH_DEFAULT / 2. + 8.;
+const GC_RING_WIDTH: f32 = 6.;
+const GC_RING_SEGMENTS: usize = 360;
+
 const TIP_LEN: f32 = 0.03; // Len of arrow tips, in radians
 const TIP_WIDTH_RATIO: f32 = 1.5; // Compared to its feature width.
 
@@ -66,15 +96,42 @@ const CIRCLE_SIZE_RATIO: f32 = 0.42;
 const SELECTION_MAX_DIST: f32 = 80.;
 
 const CENTER_TEXT_ROW_SPACING: f32 = 20.;
+// Truncate translated proteins shown in the circle center past this many characters.
+const CENTER_AA_MAX_LEN: usize = 40;
 
 const FEATURE_SLIDER_WIDTH: f32 = 180.;
 
+// Feature type legend layout, in pixels.
+const LEGEND_OFFSET: f32 = 12.;
+const LEGEND_ROW_HEIGHT: f32 = 20.;
+const LEGEND_SWATCH_SIZE: f32 = 12.;
+
+// The feature types users can show or hide on the maps.
+const FILTER_FEATURE_TYPES: [FeatureType; 13] = [
+    FeatureType::Generic,
+    FeatureType::Gene,
+    FeatureType::CodingRegion,
+    FeatureType::Promoter,
+    FeatureType::Terminator,
+    FeatureType::Ori,
+    FeatureType::RibosomeBindSite,
+    FeatureType::ProteinBind,
+    FeatureType::AntibioticResistance,
+    FeatureType::LongTerminalRepeat,
+    FeatureType::Exon,
+    FeatureType::Transcript,
+    FeatureType::Source,
+];
+
 // We limit each filled concave shape to a circumfrence segment this long, as part of a workaround to EGUI not having a great
 // way to draw concave shapes.
 const _MAX_ARC_FILL: f32 = 230.;
 
 const VERITICAL_CIRCLE_OFFSET: f32 = 22.; // Useful for leaving room for the zoomed view.
 
+// The size of exported SVG maps. Wider than tall, to leave room for labels on the sides.
+const SVG_SIZE: Vec2 = vec2(1_400., 1_200.);
+
 /// These aguments define the circle, and are used in many places in this module.
 pub struct CircleData {
     pub seq_len: usize,
@@ -102,6 +159,112 @@ impl CircleData {
     }
 }
 
+/// Shapes for features, primers, and RE sites, which are expensive to compute. We regenerate each layer
+/// only when its inputs change, vice every frame. The selected feature or primer is drawn separately,
+/// on top of these, so changing the selection doesn't invalidate them.
+#[derive(Default)]
+pub struct CircleCache {
+    key_features: u64,
+    key_primers: u64,
+    key_re: u64,
+    features: Vec<Shape>,
+    primers: Vec<Shape>,
+    re_sites: Vec<Shape>,
+}
+
+/// A hasher pre-loaded with the inputs common to all layers: The tab, view geometry, text scale,
+/// color palette, and map theme.
+fn base_hasher(active: usize, data: &CircleData, pixels_per_point: f32) -> DefaultHasher {
+    let mut hasher = DefaultHasher::new();
+
+    active.hash(&mut hasher);
+    data.seq_len.hash(&mut hasher);
+    (palette_type() as u8).hash(&mut hasher);
+    (map_theme() as u8).hash(&mut hasher);
+
+    let rect = data.to_screen.to();
+    for v in [
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.max.y,
+        data.radius,
+        pixels_per_point,
+    ] {
+        v.to_bits().hash(&mut hasher);
+    }
+
+    hasher
+}
+
+fn features_key(
+    features: &[Feature],
+    types_hidden: &[FeatureType],
+    mut hasher: DefaultHasher,
+) -> u64 {
+    for feature_type in types_hidden {
+        (*feature_type as u8).hash(&mut hasher);
+    }
+    for feature in features {
+        feature.range.start.hash(&mut hasher);
+        feature.range.end.hash(&mut hasher);
+        (feature.feature_type as u8).hash(&mut hasher);
+        (feature.direction as u8).hash(&mut hasher);
+        feature.label().hash(&mut hasher);
+        feature.color().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn primers_key(primers: &[Primer], mut hasher: DefaultHasher) -> u64 {
+    for primer in primers {
+        primer.name.hash(&mut hasher);
+        for prim_match in &primer.volatile.matches {
+            prim_match.range.start.hash(&mut hasher);
+            prim_match.range.end.hash(&mut hasher);
+            (prim_match.direction as u8).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Includes which sites are near the cursor, since we expand their labels.
+fn re_key(
+    re_matches: &[ReMatch],
+    res: &[RestrictionEnzyme],
+    features: &[Feature],
+    data: &CircleData,
+    state_ui: &StateUi,
+    mut hasher: DefaultHasher,
+) -> u64 {
+    state_ui.re.unique_cutters_only.hash(&mut hasher);
+    state_ui.re.sticky_ends_only.hash(&mut hasher);
+    state_ui.re.highlight_in_coding.hash(&mut hasher);
+
+    // Coding highlights and hover labels depend on the features cut.
+    for feature in features {
+        feature.range.start.hash(&mut hasher);
+        feature.range.end.hash(&mut hasher);
+        (feature.feature_type as u8).hash(&mut hasher);
+        feature.label().hash(&mut hasher);
+    }
+
+    let hover_dist = (data.seq_len / 360).max(1);
+    for (i, re_match) in re_matches.iter().enumerate() {
+        re_match.lib_index.hash(&mut hasher);
+        re_match.seq_index.hash(&mut hasher);
+        re_match.match_count.hash(&mut hasher);
+
+        if let (Some(cursor_i), Some(re)) = (state_ui.cursor_seq_i, res.get(re_match.lib_index)) {
+            let cut_i = re_cut_index(re_match, re, data.seq_len);
+            if cursor_i.abs_diff(cut_i) <= hover_dist {
+                i.hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
 /// Create points for an arc. Can be used with line_segment to draw the arc.
 /// Two of these can be used with convex_polygon to make a filled  arc segment.
 // Adapted from PR https://github.com/emilk/egui/pull/4836/files
@@ -154,12 +317,37 @@ fn angle_to_pixel(angle: f32, radius: f32) -> Pos2 {
     pos2(angle.cos() * radius, angle.sin() * radius)
 }
 
-/// Draw a tick every 1kbp.
+/// Choose the tick spacing, in nucleotides, based on sequence length.
+fn tick_spacing(seq_len: usize) -> usize {
+    for spacing in TICK_SPACINGS {
+        if seq_len / spacing <= TICK_COUNT_MAX {
+            return spacing;
+        }
+    }
+    TICK_SPACINGS[TICK_SPACINGS.len() - 1]
+}
+
+/// Eg "1500", or "1.5 kb" for large sequences.
+fn tick_label(i: usize, seq_len: usize) -> String {
+    if seq_len < TICK_KB_THRESH {
+        return i.to_string();
+    }
+
+    if i.is_multiple_of(1_000) {
+        format!("{} kb", i / 1_000)
+    } else {
+        format!("{:.1} kb", i as f32 / 1_000.)
+    }
+}
+
+/// Draw ticks around the backbone, spaced based on sequence length.
 fn draw_ticks(data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
     let mut result = Vec::new();
 
-    for i_div_1k in 0..data.seq_len / TICK_SPACING {
-        let i = i_div_1k * TICK_SPACING;
+    let spacing = tick_spacing(data.seq_len);
+
+    for i in (0..data.seq_len).step_by(spacing) {
+        let label = tick_label(i, data.seq_len);
 
         let angle = seq_i_to_angle(i, data.seq_len);
 
@@ -170,7 +358,7 @@ fn draw_ticks(data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
 
         result.push(Shape::line_segment(
             [data.to_screen * point_inner, data.to_screen * point_outer],
-            Stroke::new(TICK_WIDTH, TICK_COLOR),
+            Stroke::new(TICK_WIDTH, map_colors().tick),
         ));
 
         let (label_pt, label_align) = if angle > TAU / 2. {
@@ -189,19 +377,9 @@ fn draw_ticks(data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
                 fonts,
                 data.to_screen * label_pt,
                 label_align,
-                i.to_string(),
+                label,
                 FontId::new(16., FontFamily::Proportional),
-                TICK_COLOR,
-            )
-        }));
-        result.push(ui.ctx().fonts(|fonts| {
-            Shape::text(
-                fonts,
-                data.to_screen * label_pt,
-                label_align,
-                i.to_string(),
-                FontId::new(16., FontFamily::Proportional),
-                TICK_COLOR,
+                map_colors().tick,
             )
         }));
     }
@@ -283,7 +461,7 @@ fn draw_filled_arc(
 
     result.push(Shape::convex_polygon(
         points_patch,
-        BACKGROUND_COLOR,
+        map_colors().background,
         Stroke::NONE,
     ));
 
@@ -317,6 +495,7 @@ fn draw_features(
     features: &[Feature],
     data: &CircleData,
     selected: Selection,
+    types_hidden: &[FeatureType],
     ui: &mut Ui,
 ) -> Vec<Shape> {
     let mut result = Vec::new();
@@ -324,9 +503,8 @@ fn draw_features(
     for (i, feature) in features.iter().enumerate() {
         // Draw the arc segment.
 
-        // Source features generally take up the whole plasmid length.
-        // Alternative: Filter by features that take up the whole length.
-        if feature.feature_type == FeatureType::Source {
+        // By default, this hides Source features, which generally take up the whole plasmid length.
+        if types_hidden.contains(&feature.feature_type) {
             continue;
         }
         // todo: Adjust feature, tick etc width (stroke width, and dimensions from cicle) based on window size.
@@ -344,10 +522,10 @@ fn draw_features(
                 if j == i {
                     FEATURE_OUTLINE_SELECTED
                 } else {
-                    FEATURE_OUTLINE_COLOR
+                    map_colors().outline
                 }
             }
-            _ => FEATURE_OUTLINE_COLOR,
+            _ => map_colors().outline,
         };
 
         let stroke = Stroke::new(FEATURE_STROKE_WIDTH, stroke_color);
@@ -469,7 +647,6 @@ fn draw_primers(
     let radius_inner = data.radius - PRIMER_WIDTH / 2.;
 
     for (i, primer) in primers.iter().enumerate() {
-        // todo: Do not run these calcs each time. Cache.
         for prim_match in &primer.volatile.matches {
             let angle_start = seq_i_to_angle(prim_match.range.start, data.seq_len);
             let angle_end = seq_i_to_angle(prim_match.range.end, data.seq_len);
@@ -550,7 +727,7 @@ fn draw_primers(
     result
 }
 
-/// Change the selected feature's range from the sequence or map views.
+/// Change the selected feature's range, label, type, and direction from the sequence or map views.
 /// todo: Move out of circle.rs, as it's also used in the seq view now.
 pub fn feature_range_sliders(state: &mut State, ui: &mut Ui) {
     if let Selection::Feature(feat_i) = &mut state.ui.selected_item {
@@ -574,10 +751,53 @@ pub fn feature_range_sliders(state: &mut State, ui: &mut Ui) {
             // if feature.range.start > feature.range.end {
             //     feature.range.end = feature.range.start + 1;
             // }
+
+            ui.add_space(COL_SPACING);
+            ui.label("Label:");
+            if ui
+                .add(TextEdit::singleline(&mut feature.label).desired_width(100.))
+                .gained_focus()
+            {
+                state.ui.text_edit_active = true; // Disable character entries in the sequence.
+            }
+
+            ui.label("Type:");
+            if feature_type_picker(&mut feature.feature_type, 3_700, ui) {
+                feature.color_override = None;
+            }
+
+            ui.label("Dir:");
+            direction_picker(&mut feature.direction, 3_701, ui);
         }
     }
 }
 
+/// Checkboxes to show or hide each feature type on the maps. These apply to copied map images as well.
+fn feature_type_filter(types_hidden: &mut Vec<FeatureType>, ui: &mut Ui) {
+    ui.menu_button("Feature types ⏷", |ui| {
+        for feature_type in FILTER_FEATURE_TYPES {
+            let mut shown = !types_hidden.contains(&feature_type);
+            if ui.checkbox(&mut shown, feature_type.to_string()).changed() {
+                if shown {
+                    types_hidden.retain(|t| *t != feature_type);
+                } else {
+                    types_hidden.push(feature_type);
+                }
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Show all").clicked() {
+                types_hidden.clear();
+            }
+            if ui.button("Hide all").clicked() {
+                *types_hidden = FILTER_FEATURE_TYPES.to_vec();
+            }
+        });
+    });
+}
+
 fn top_details(state: &mut State, ui: &mut Ui) {
     // todo: A/R
     // display_filters(&mut state.ui, ui);
@@ -594,6 +814,11 @@ fn top_details(state: &mut State, ui: &mut Ui) {
     ui.checkbox(&mut state.ui.seq_visibility.show_res, "");
     ui.add_space(COL_SPACING / 2.);
 
+    if state.ui.re.res_selected.len() >= 2 {
+        buffer_rec_disp(&state.ui.re.res_selected, ui);
+        ui.add_space(COL_SPACING / 2.);
+    }
+
     ui.label("Features:");
     ui.checkbox(&mut state.ui.seq_visibility.show_features, "");
     ui.add_space(COL_SPACING / 2.);
@@ -602,6 +827,36 @@ fn top_details(state: &mut State, ui: &mut Ui) {
     ui.checkbox(&mut state.ui.seq_visibility.show_primers, "");
     ui.add_space(COL_SPACING / 2.);
 
+    ui.label("Legend:");
+    ui.checkbox(&mut state.ui.seq_visibility.show_legend, "");
+    ui.add_space(COL_SPACING / 2.);
+
+    ui.label("GC:").on_hover_text(
+        "Show GC content, averaged over a sliding window. Blue is AT-rich, and red is GC-rich.",
+    );
+    ui.checkbox(&mut state.ui.seq_visibility.show_gc, "");
+    if state.ui.seq_visibility.show_gc {
+        int_field(&mut state.ui.seq_visibility.gc_window, "Window:", ui);
+    }
+    ui.add_space(COL_SPACING / 2.);
+
+    ui.label("Theme:").on_hover_text(
+        "Light and Print use a white background, e.g. for publication figures. This also applies \
+        to exported images.",
+    );
+    ComboBox::from_id_salt(4_600)
+        .width(60.)
+        .selected_text(state.ui.map_theme.to_string())
+        .show_ui(ui, |ui| {
+            for theme in [MapTheme::Dark, MapTheme::Light, MapTheme::Print] {
+                ui.selectable_value(&mut state.ui.map_theme, theme, theme.to_string());
+            }
+        });
+    ui.add_space(COL_SPACING / 2.);
+
+    feature_type_filter(&mut state.ui.seq_visibility.feature_types_hidden, ui);
+    ui.add_space(COL_SPACING / 2.);
+
     // Sliders to edit the feature.
     feature_range_sliders(state, ui);
 
@@ -609,6 +864,210 @@ fn top_details(state: &mut State, ui: &mut Ui) {
     ui.label("Cursor:");
     let cursor_posit_text = get_cursor_text(state.ui.cursor_seq_i, state.get_seq().len());
     ui.heading(cursor_posit_text);
+
+    ui.add_space(COL_SPACING);
+    measure_disp(state, ui);
+
+    ui.add_space(COL_SPACING);
+    bookmark_disp(state, ui);
+
+    ui.add_space(COL_SPACING);
+    copy_map_button(state.ui.map_image.map_rect, state, ui);
+}
+
+/// Color a GC content (0 to 1) on a heat scale: Blue for AT-rich, and red for GC-rich. Used on the
+/// circular and linear maps.
+pub fn gc_color(gc: f32) -> Color32 {
+    let neutral = (140., 140., 140.);
+    let (end, portion) = if gc < 0.5 {
+        ((60., 120., 255.), (0.5 - gc) / 0.25)
+    } else {
+        ((255., 80., 60.), (gc - 0.5) / 0.25)
+    };
+    let portion = portion.min(1.);
+
+    let lerp = |a: f32, b: f32| (a + (b - a) * portion) as u8;
+    Color32::from_rgb(
+        lerp(neutral.0, end.0),
+        lerp(neutral.1, end.1),
+        lerp(neutral.2, end.2),
+    )
+}
+
+/// Draw GC content as a heat ring just inside the features. Each segment shows the mean of the
+/// windowed GC content over the nucleotides it covers.
+fn draw_gc_ring(seq: &[Nucleotide], window: usize, data: &CircleData) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    let gc = gc_window(seq, window, true);
+    if gc.is_empty() {
+        return result;
+    }
+
+    let radius = data.radius - GC_RING_OFFSET;
+    let num_segs = GC_RING_SEGMENTS.min(gc.len());
+
+    for seg in 0..num_segs {
+        let start = seg * gc.len() / num_segs;
+        let end = ((seg + 1) * gc.len() / num_segs).max(start + 1);
+        let mean = gc[start..end].iter().sum::<f32>() / (end - start) as f32;
+
+        let point_start =
+            angle_to_pixel(seq_i_to_angle(start, data.seq_len), radius) + data.center.to_vec2();
+        let point_end =
+            angle_to_pixel(seq_i_to_angle(end, data.seq_len), radius) + data.center.to_vec2();
+
+        result.push(Shape::line_segment(
+            [data.to_screen * point_start, data.to_screen * point_end],
+            Stroke::new(GC_RING_WIDTH, gc_color(mean)),
+        ));
+    }
+
+    result
+}
+
+/// Mark measurement points with lines across the backbone.
+fn draw_measure_points(points: &[Option<usize>], data: &CircleData) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for i in points.iter().flatten() {
+        let angle = seq_i_to_angle(*i, data.seq_len);
+
+        let point_inner = angle_to_pixel(angle, data.radius - RE_LEN_DIV_2) + data.center.to_vec2();
+        let point_outer = angle_to_pixel(angle, data.radius + RE_LEN_DIV_2) + data.center.to_vec2();
+
+        result.push(Shape::line_segment(
+            [data.to_screen * point_inner, data.to_screen * point_outer],
+            Stroke::new(RE_WIDTH, COLOR_MEASURE),
+        ));
+    }
+
+    result
+}
+
+/// A legend of the feature types present, with their colors, in the map's lower left. (The zoomed
+/// linear map takes the top.)
+fn draw_legend(
+    features: &[Feature],
+    types_hidden: &[FeatureType],
+    data: &CircleData,
+    ui: &mut Ui,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    // In order of first appearance.
+    let mut types = Vec::new();
+    for feature in features {
+        if matches!(
+            feature.feature_type,
+            FeatureType::Selection | FeatureType::Primer
+        ) || types_hidden.contains(&feature.feature_type)
+        {
+            continue;
+        }
+        if !types.contains(&feature.feature_type) {
+            types.push(feature.feature_type);
+        }
+    }
+
+    let height = data.to_screen.from().height();
+    for (i, feature_type) in types.iter().enumerate() {
+        let (r, g, b) = feature_type.color();
+        let y = height - LEGEND_OFFSET - (types.len() - i) as f32 * LEGEND_ROW_HEIGHT;
+
+        let swatch = Rect::from_min_size(
+            data.to_screen * pos2(LEGEND_OFFSET, y),
+            vec2(LEGEND_SWATCH_SIZE, LEGEND_SWATCH_SIZE),
+        );
+        result.push(Shape::rect_filled(swatch, 2., Color32::from_rgb(r, g, b)));
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                data.to_screen
+                    * pos2(
+                        LEGEND_OFFSET * 2. + LEGEND_SWATCH_SIZE,
+                        y + LEGEND_SWATCH_SIZE / 2.,
+                    ),
+                Align2::LEFT_CENTER,
+                feature_type.to_string(),
+                FontId::new(13., FontFamily::Proportional),
+                map_colors().text,
+            )
+        }));
+    }
+
+    result
+}
+
+/// Mark bookmarks with a small flag outside the backbone, labeled with the bookmark name.
+fn draw_bookmarks(bookmarks: &[(usize, String)], data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for (i, name) in bookmarks {
+        let angle = seq_i_to_angle(*i, data.seq_len);
+
+        let point_inner = angle_to_pixel(angle, data.radius) + data.center.to_vec2();
+        let point_outer =
+            angle_to_pixel(angle, data.radius + RE_LEN_DIV_2 * 2.) + data.center.to_vec2();
+
+        result.push(Shape::line_segment(
+            [data.to_screen * point_inner, data.to_screen * point_outer],
+            Stroke::new(2., COLOR_BOOKMARK),
+        ));
+
+        let (label_pt, label_align) = if angle > TAU / 2. {
+            (point_outer + vec2(-4., 0.), Align2::RIGHT_BOTTOM)
+        } else {
+            (point_outer + vec2(4., 0.), Align2::LEFT_BOTTOM)
+        };
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                data.to_screen * label_pt,
+                label_align,
+                format!("📌 {name}"),
+                FontId::new(13., FontFamily::Proportional),
+                COLOR_BOOKMARK,
+            )
+        }));
+    }
+
+    result
+}
+
+/// Mark a cut position picked from the cut site list.
+fn draw_cut_pointer(cut_i: usize, data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
+    let angle = seq_i_to_angle(cut_i, data.seq_len);
+
+    let point_inner =
+        angle_to_pixel(angle, data.radius - RE_LEN_DIV_2 * 2.) + data.center.to_vec2();
+    let point_outer =
+        angle_to_pixel(angle, data.radius + RE_LEN_DIV_2 * 3.) + data.center.to_vec2();
+
+    let (label_pt, label_align) = if angle > TAU / 2. {
+        (point_outer + vec2(-4., 0.), Align2::RIGHT_CENTER)
+    } else {
+        (point_outer + vec2(4., 0.), Align2::LEFT_CENTER)
+    };
+
+    vec![
+        Shape::line_segment(
+            [data.to_screen * point_inner, data.to_screen * point_outer],
+            Stroke::new(2., COLOR_ACTION),
+        ),
+        ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                data.to_screen * label_pt,
+                label_align,
+                format!("✂ {}", disp_i(cut_i)),
+                FontId::new(14., FontFamily::Proportional),
+                COLOR_ACTION,
+            )
+        }),
+    ]
 }
 
 /// Find the sequence index under the cursor, if it is over the sequence.
@@ -632,6 +1091,122 @@ fn find_cursor_i(cursor_pos: Option<(f32, f32)>, data: &CircleData) -> Option<us
     }
 }
 
+/// Double-clicking the backbone where there's no feature or primer opens a form to add a feature
+/// there. Double-clicks on features and primers are left to the single-click selection.
+fn handle_dblclick_add(state: &mut State, data: &CircleData) {
+    if !state.ui.dblclick_pending_handle {
+        return;
+    }
+    state.ui.dblclick_pending_handle = false;
+
+    // Ignore double-clicks outside the map circle, e.g. in a window over it.
+    let pos_rel = match state.ui.cursor_pos {
+        Some(p) => data.from_screen * pos2(p.0, p.1),
+        None => return,
+    };
+    if (pos_rel - data.center).length() > data.radius + SELECTION_MAX_DIST {
+        return;
+    }
+
+    let data = &state.generic[state.active];
+    let i = match state.ui.cursor_seq_i {
+        Some(i) if !data.seq.is_empty() => i.max(1),
+        _ => return,
+    };
+
+    if feature_from_index(&Some(i), &data.features).is_some()
+        || primer_from_index(&Some(i), &data.primers).is_some()
+    {
+        return;
+    }
+
+    state.ui.quick_feature_add_at = Some(RangeIncl::new(i, i));
+    state.ui.selected_item = Selection::None;
+}
+
+/// A small form to add a feature at the position double-clicked. `pos` is in screen coordinates.
+fn quick_feature_add(state: &mut State, pos: Pos2, ui: &mut Ui) {
+    let mut range = match state.ui.quick_feature_add_at {
+        Some(r) => r,
+        None => return,
+    };
+
+    let seq_len = state.get_seq().len();
+    let mut open = true;
+    let mut add = false;
+
+    Window::new("Add feature")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .fixed_pos(pos)
+        .show(ui.ctx(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                if ui
+                    .add(
+                        TextEdit::singleline(&mut state.ui.quick_feature_add_name)
+                            .desired_width(100.),
+                    )
+                    .gained_focus()
+                {
+                    state.ui.text_edit_active = true; // Disable character entries in the sequence.
+                }
+            });
+
+            ui.horizontal(|ui| {
+                int_field(&mut range.start, "Start:", ui);
+                int_field(&mut range.end, "End:", ui);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Type:");
+                feature_type_picker(&mut state.ui.quick_feature_add_type, 4_400, ui);
+                direction_picker(&mut state.ui.quick_feature_add_dir, 4_401, ui);
+            });
+
+            let valid = range.start >= 1
+                && range.end >= 1
+                && range.start <= seq_len
+                && range.end <= seq_len;
+            if !valid {
+                ui.label(
+                    RichText::new(format!("Indices must be within 1 - {seq_len}"))
+                        .color(Color32::LIGHT_RED),
+                );
+            }
+
+            if ui
+                .add_enabled(
+                    valid,
+                    Button::new(RichText::new("➕ Add").color(COLOR_ACTION)),
+                )
+                .clicked()
+            {
+                add = true;
+            }
+        });
+
+    if add {
+        state.snapshot();
+        let features = &mut state.generic[state.active].features;
+        features.push(Feature {
+            range,
+            feature_type: state.ui.quick_feature_add_type,
+            direction: state.ui.quick_feature_add_dir,
+            label: state.ui.quick_feature_add_name.clone(),
+            ..Default::default()
+        });
+
+        state.ui.selected_item = Selection::Feature(features.len() - 1);
+        state.ui.quick_feature_add_name = String::new();
+        state.ui.text_edit_active = false;
+        open = false;
+    }
+
+    state.ui.quick_feature_add_at = if open { Some(range) } else { None };
+}
+
 /// Helper fn.
 fn draw_text(text: &str, pos: Pos2, font_size: f32, color: Color32, ui: &mut Ui) -> Shape {
     ui.ctx().fonts(|fonts| {
@@ -651,34 +1226,56 @@ fn draw_text(text: &str, pos: Pos2, font_size: f32, color: Color32, ui: &mut Ui)
 fn draw_re_sites(
     re_matches: &[ReMatch],
     res: &[RestrictionEnzyme],
+    features: &[Feature],
     data: &CircleData,
-    unique_cutters_only: bool,
-    sticky_ends_only: bool,
+    state_ui: &StateUi,
     ui: &mut Ui,
 ) -> Vec<Shape> {
     let mut result = Vec::new();
-    for (i, re_match) in re_matches.iter().enumerate() {
+    // Count only the sites we draw; skipped sites would otherwise break the label alternation.
+    let mut drawn_count = 0;
+
+    for re_match in re_matches {
         if re_match.lib_index >= res.len() {
             eprintln!("Invalid RE selected");
             return result;
         }
         let re = &res[re_match.lib_index];
 
-        if (unique_cutters_only && re_match.match_count > 1)
-            || (sticky_ends_only && re.makes_blunt_ends())
+        if (state_ui.re.unique_cutters_only && re_match.match_count > 1)
+            || (state_ui.re.sticky_ends_only && re.makes_blunt_ends())
         {
             continue;
         }
 
-        let cut_i = re_match.seq_index + 1; // to display in the right place.
-        let angle = seq_i_to_angle(cut_i + re.cut_after as usize, data.seq_len);
+        let cut_i = re_cut_index(re_match, re, data.seq_len);
+        let angle = seq_i_to_angle(cut_i, data.seq_len);
+
+        let features_cut = features_cut_by_re(re_match, re, features, data.seq_len);
+        let color = if state_ui.re.highlight_in_coding && re_cuts_coding(&features_cut, features) {
+            palette().re_in_coding
+        } else {
+            palette().re
+        };
+
+        // When hovering near the cut site, list the features it cuts within. We scale the hover
+        // distance with sequence length, so it's roughly constant on screen.
+        let mut label_text = re.name.clone();
+        if let Some(cursor_i) = state_ui.cursor_seq_i {
+            let hover_dist = (data.seq_len / 360).max(1);
+            if cursor_i.abs_diff(cut_i) <= hover_dist {
+                if let Some(text) = re_cut_text(&features_cut, features) {
+                    label_text = format!("{label_text}: {text}");
+                }
+            }
+        }
 
         let point_inner = angle_to_pixel(angle, data.radius - RE_LEN_DIV_2) + data.center.to_vec2();
         let point_outer = angle_to_pixel(angle, data.radius + RE_LEN_DIV_2) + data.center.to_vec2();
 
         result.push(Shape::line_segment(
             [data.to_screen * point_inner, data.to_screen * point_outer],
-            Stroke::new(RE_WIDTH, COLOR_RE),
+            Stroke::new(RE_WIDTH, color),
         ));
 
         let (mut label_pt, label_align) = if angle > TAU / 2. {
@@ -691,18 +1288,19 @@ fn draw_re_sites(
         };
 
         // Alternate label vertical position, to reduce changes of overlaps.
-        if i % 2 == 0 {
+        if drawn_count % 2 == 0 {
             label_pt.y += 22.;
         }
+        drawn_count += 1;
 
         result.push(ui.ctx().fonts(|fonts| {
             Shape::text(
                 fonts,
                 data.to_screen * label_pt,
                 label_align,
-                &re.name,
+                label_text,
                 FontId::new(16., FontFamily::Proportional),
-                COLOR_RE,
+                color,
             )
         }));
     }
@@ -711,15 +1309,34 @@ fn draw_re_sites(
 
 /// For drawing feature data in the center of the circle. This may be used for the feature hovered over,
 /// or selected.
-fn draw_feature_text(feature: &Feature, data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
+fn draw_feature_text(
+    feature: &Feature,
+    seq: &[Nucleotide],
+    aa_ident_disp: AaIdent,
+    data: &CircleData,
+    ui: &mut Ui,
+) -> Vec<Shape> {
     let mut result = Vec::new();
 
-    let labels = vec![
+    let mut labels = vec![
         feature.label.clone(),
         feature.location_descrip(data.seq_len),
         feature.feature_type.to_string(),
     ];
 
+    if feature.feature_type == FeatureType::CodingRegion {
+        let aa_seq = translate_feature(feature, seq);
+        labels.push(format!("{} aa", aa_seq.len()));
+
+        // Long proteins are truncated here; the full sequence is shown in the sequence view.
+        let mut aa_text = aa_seq_text(&aa_seq, aa_ident_disp);
+        if aa_text.chars().count() > CENTER_AA_MAX_LEN {
+            aa_text = aa_text.chars().take(CENTER_AA_MAX_LEN).collect();
+            aa_text.push('…');
+        }
+        labels.push(aa_text);
+    }
+
     let (r, g, b) = feature.color();
     let color = Color32::from_rgb(r, g, b);
 
@@ -760,7 +1377,7 @@ fn draw_feature_text(feature: &Feature, data: &CircleData, ui: &mut Ui) -> Vec<S
                 Align2::LEFT_CENTER,
                 &text,
                 FontId::new(13., FontFamily::Proportional),
-                TICK_COLOR,
+                map_colors().text,
             )
         }));
         i += 1;
@@ -786,7 +1403,7 @@ fn draw_primer_text(primer: &Primer, data: &CircleData, ui: &mut Ui) -> Vec<Shap
     // };
     //
     // todo: Rev or neutral A/R
-    let color = PRIMER_FWD_COLOR;
+    let color = palette().primer_fwd;
 
     let mut i = 0; // Rows
 
@@ -808,6 +1425,20 @@ fn draw_primer_text(primer: &Primer, data: &CircleData, ui: &mut Ui) -> Vec<Shap
     result
 }
 
+/// Display a summary of the plasmid: Its name, and length.
+fn draw_center_summary(plasmid_name: &str, data: &CircleData, ui: &mut Ui) -> Vec<Shape> {
+    vec![
+        draw_text(plasmid_name, data.center_rel, 16., map_colors().text, ui),
+        draw_text(
+            &format!("{} bp", data.seq_len),
+            pos2(data.center_rel.x, data.center_rel.y + 20.),
+            13.,
+            map_colors().text,
+            ui,
+        ),
+    ]
+}
+
 /// Draw text in the center of the circle; eg general plasmid information, or information
 /// about a feature. This is the selected feature if available; then hovered-over if available;
 /// then general plasmid information.
@@ -820,7 +1451,13 @@ fn draw_center_text(data: &CircleData, state: &mut State, ui: &mut Ui) -> Vec<Sh
                 eprintln!("Invalid selected feature");
             }
             let feature = &state.generic[state.active].features[*feat_i];
-            result.append(&mut draw_feature_text(feature, data, ui));
+            result.append(&mut draw_feature_text(
+                feature,
+                state.get_seq(),
+                state.ui.aa_ident_disp,
+                data,
+                ui,
+            ));
         }
         Selection::Primer(prim_i) => {
             if *prim_i >= state.generic[state.active].primers.len() {
@@ -830,34 +1467,28 @@ fn draw_center_text(data: &CircleData, state: &mut State, ui: &mut Ui) -> Vec<Sh
             let primer = &state.generic[state.active].primers[*prim_i];
             result.append(&mut draw_primer_text(primer, data, ui));
         }
-        Selection::None => {
-            match &state.ui.feature_hover {
-                Some(feat_i) => {
-                    if *feat_i >= state.generic[state.active].features.len() {
-                        eprintln!("Invalid hover feature");
-                    }
-                    let feature = &state.generic[state.active].features[*feat_i];
-                    result.append(&mut draw_feature_text(feature, data, ui));
-                }
-                None => {
-                    // Display a summary of the plasmid
-                    result.push(draw_text(
-                        &state.generic[state.active].metadata.plasmid_name,
-                        data.center_rel,
-                        16.,
-                        TICK_COLOR,
-                        ui,
-                    ));
-                    result.push(draw_text(
-                        &format!("{} bp", data.seq_len),
-                        pos2(data.center_rel.x, data.center_rel.y + 20.),
-                        13.,
-                        TICK_COLOR,
-                        ui,
-                    ));
+        Selection::None => match &state.ui.feature_hover {
+            Some(feat_i) => {
+                if *feat_i >= state.generic[state.active].features.len() {
+                    eprintln!("Invalid hover feature");
                 }
+                let feature = &state.generic[state.active].features[*feat_i];
+                result.append(&mut draw_feature_text(
+                    feature,
+                    state.get_seq(),
+                    state.ui.aa_ident_disp,
+                    data,
+                    ui,
+                ));
             }
-        }
+            None => {
+                result.append(&mut draw_center_summary(
+                    &state.generic[state.active].metadata.plasmid_name,
+                    data,
+                    ui,
+                ));
+            }
+        },
     }
 
     result
@@ -909,10 +1540,113 @@ fn _draw_mini_seq(data: &CircleData, state: &State, ui: &mut Ui) -> Vec<Shape> {
     result
 }
 
+/// Export the circular map as an SVG, at a fixed size. See `map_export_shapes`.
+pub fn export_svg(state: &State, path: &Path, ui: &mut Ui) -> io::Result<()> {
+    if state.get_seq().is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "There is no sequence to export.",
+        ));
+    }
+
+    let shapes = map_export_shapes(state, SVG_SIZE, ui);
+    fs::write(
+        path,
+        shapes_to_svg(&shapes, SVG_SIZE, map_colors().background),
+    )
+}
+
+/// Shapes for exporting the circular map, e.g. as an image, in a canvas of `size`. These are the same
+/// as on-screen, omitting layers hidden in the visibility settings. Interactive items like the selection,
+/// cursor, and measurement points are omitted; the center shows the plasmid summary.
+pub fn map_export_shapes(state: &State, size: Vec2, ui: &mut Ui) -> Vec<Shape> {
+    let rect = Rect::from_min_size(Pos2::ZERO, size);
+    let to_screen = RectTransform::from_to(rect, rect);
+
+    let radius = size.x.min(size.y) * CIRCLE_SIZE_RATIO;
+    let seq_len = state.get_seq().len();
+    if seq_len == 0 {
+        return Vec::new();
+    }
+
+    let data = CircleData::new(seq_len, pos2(size.x / 2., size.y / 2.), radius, to_screen);
+
+    let visibility = &state.ui.seq_visibility;
+    let features = &state.generic[state.active].features;
+
+    let mut shapes = vec![Shape::Circle(CircleShape::stroke(
+        data.center_rel,
+        radius - BACKBONE_WIDTH / 2.,
+        Stroke::new(BACKBONE_WIDTH, map_colors().backbone),
+    ))];
+
+    if visibility.show_gc {
+        shapes.append(&mut draw_gc_ring(
+            state.get_seq(),
+            visibility.gc_window,
+            &data,
+        ));
+    }
+
+    if visibility.show_features {
+        shapes.append(&mut draw_features(
+            features,
+            &data,
+            Selection::None,
+            &visibility.feature_types_hidden,
+            ui,
+        ));
+    }
+
+    shapes.append(&mut draw_ticks(&data, ui));
+
+    if visibility.show_primers {
+        shapes.append(&mut draw_primers(
+            &state.generic[state.active].primers,
+            &data,
+            Selection::None,
+            ui,
+        ));
+    }
+
+    if visibility.show_res {
+        shapes.append(&mut draw_re_sites(
+            &state.volatile[state.active].restriction_enzyme_matches,
+            &state.restriction_enzyme_lib,
+            features,
+            &data,
+            &state.ui,
+            ui,
+        ));
+    }
+
+    shapes.append(&mut draw_bookmarks(
+        &state.generic[state.active].bookmarks,
+        &data,
+        ui,
+    ));
+
+    if visibility.show_features && visibility.show_legend {
+        shapes.append(&mut draw_legend(
+            features,
+            &visibility.feature_types_hidden,
+            &data,
+            ui,
+        ));
+    }
+
+    shapes.append(&mut draw_center_summary(
+        &state.generic[state.active].metadata.plasmid_name,
+        &data,
+        ui,
+    ));
+
+    shapes
+}
+
 pub fn circle_page(state: &mut State, ui: &mut Ui) {
     let mut shapes = Vec::new();
 
-    // todo: ABility to select light mode, and other tools useful for publication.
     if !state.ui.hide_map_feature_editor {
         // Limit the top section height.
         let screen_height = ui.ctx().available_rect().height();
@@ -960,14 +1694,32 @@ pub fn circle_page(state: &mut State, ui: &mut Ui) {
             CursorIcon::Default
         });
 
+    // A circle doesn't represent a linear sequence; display it as a linear map instead.
+    if state.generic[state.active].topology == SeqTopology::Linear {
+        lin_maps::seq_lin_disp(
+            &state.generic[state.active],
+            true,
+            state.ui.selected_item,
+            &state.ui.re.res_selected,
+            state.ui.cursor_seq_i,
+            &state.ui,
+            &state.volatile[state.active].restriction_enzyme_matches,
+            &state.restriction_enzyme_lib,
+            ui,
+        );
+        return;
+    }
+
     Frame::canvas(ui.style())
-        .fill(BACKGROUND_COLOR)
+        .fill(map_colors().background)
         .show(ui, |ui| {
             let (response, _painter) = {
                 let desired_size = vec2(ui.available_width(), ui.available_height());
                 ui.allocate_painter(desired_size, Sense::click())
             };
 
+            state.ui.map_image.map_rect = Some(response.rect);
+
             let to_screen = RectTransform::from_to(
                 // Rect::from_min_size(pos2(0., -VERITICAL_CIRCLE_OFFSET), response.rect.size()),
                 Rect::from_min_size(Pos2::ZERO, response.rect.size()),
@@ -994,44 +1746,131 @@ pub fn circle_page(state: &mut State, ui: &mut Ui) {
                 );
             }
 
+            handle_measure_click(state);
             select_feature(state, &data.from_screen);
+            handle_dblclick_add(state, &data);
 
             // Draw the backbone circle
             shapes.push(Shape::Circle(CircleShape::stroke(
                 data.center_rel,
                 radius - BACKBONE_WIDTH / 2.,
-                Stroke::new(BACKBONE_WIDTH, BACKBONE_COLOR),
+                Stroke::new(BACKBONE_WIDTH, map_colors().backbone),
             )));
 
-            // Draw features first, so other items like ticks will be displayed in front of the concave fill circlex.
-            if state.ui.seq_visibility.show_features {
-                shapes.append(&mut draw_features(
-                    &state.generic[state.active].features,
+            if state.ui.seq_visibility.show_gc {
+                shapes.append(&mut draw_gc_ring(
+                    state.get_seq(),
+                    state.ui.seq_visibility.gc_window,
                     &data,
-                    state.ui.selected_item,
-                    ui,
                 ));
             }
 
+            // Features, primers, and RE sites are expensive to lay out; we only regenerate them when
+            // their inputs change. The selected item is drawn over its cached shape each frame.
+            let hasher = base_hasher(state.active, &data, ui.ctx().pixels_per_point());
+            let features = &state.generic[state.active].features;
+            let primers = &state.generic[state.active].primers;
+
+            // Draw features first, so other items like ticks will be displayed in front of the concave fill circlex.
+            if state.ui.seq_visibility.show_features {
+                let types_hidden = &state.ui.seq_visibility.feature_types_hidden;
+                let key = features_key(features, types_hidden, hasher.clone());
+                if key != state.ui.circle_cache.key_features {
+                    state.ui.circle_cache.features =
+                        draw_features(features, &data, Selection::None, types_hidden, ui);
+                    state.ui.circle_cache.key_features = key;
+                }
+                shapes.extend(state.ui.circle_cache.features.iter().cloned());
+
+                // The selected feature, and others in a multi-selection.
+                let selected = match state.ui.selected_item {
+                    Selection::Feature(i) => Some(i),
+                    _ => None,
+                };
+                for &i in state.ui.selected_features.iter().chain(&selected) {
+                    if i < features.len() {
+                        shapes.append(&mut draw_features(
+                            &features[i..=i],
+                            &data,
+                            Selection::Feature(0),
+                            types_hidden,
+                            ui,
+                        ));
+                    }
+                }
+            }
+
             shapes.append(&mut draw_ticks(&data, ui));
 
             if state.ui.seq_visibility.show_primers {
-                shapes.append(&mut draw_primers(
-                    &state.generic[state.active].primers,
-                    &data,
-                    state.ui.selected_item,
-                    ui,
-                ));
+                let key = primers_key(primers, hasher.clone());
+                if key != state.ui.circle_cache.key_primers {
+                    state.ui.circle_cache.primers =
+                        draw_primers(primers, &data, Selection::None, ui);
+                    state.ui.circle_cache.key_primers = key;
+                }
+                shapes.extend(state.ui.circle_cache.primers.iter().cloned());
+
+                if let Selection::Primer(i) = state.ui.selected_item {
+                    if i < primers.len() {
+                        shapes.append(&mut draw_primers(
+                            &primers[i..=i],
+                            &data,
+                            Selection::Primer(0),
+                            ui,
+                        ));
+                    }
+                }
             }
 
             // tood: Check mark to edit this visibility on the page
             if state.ui.seq_visibility.show_res {
-                shapes.append(&mut draw_re_sites(
-                    &state.volatile[state.active].restriction_enzyme_matches,
+                let re_matches = &state.volatile[state.active].restriction_enzyme_matches;
+                let key = re_key(
+                    re_matches,
                     &state.restriction_enzyme_lib,
+                    features,
+                    &data,
+                    &state.ui,
+                    hasher,
+                );
+                if key != state.ui.circle_cache.key_re {
+                    state.ui.circle_cache.re_sites = draw_re_sites(
+                        re_matches,
+                        &state.restriction_enzyme_lib,
+                        features,
+                        &data,
+                        &state.ui,
+                        ui,
+                    );
+                    state.ui.circle_cache.key_re = key;
+                }
+                shapes.extend(state.ui.circle_cache.re_sites.iter().cloned());
+            }
+
+            shapes.append(&mut draw_measure_points(
+                &[state.ui.measure.point_a, state.ui.measure.point_b],
+                &data,
+            ));
+
+            shapes.append(&mut draw_bookmarks(
+                &state.generic[state.active].bookmarks,
+                &data,
+                ui,
+            ));
+
+            if let Some(cut_i) = state.ui.re.cut_pointed {
+                // This may be from a different tab.
+                if cut_i <= seq_len {
+                    shapes.append(&mut draw_cut_pointer(cut_i, &data, ui));
+                }
+            }
+
+            if state.ui.seq_visibility.show_features && state.ui.seq_visibility.show_legend {
+                shapes.append(&mut draw_legend(
+                    &state.generic[state.active].features,
+                    &state.ui.seq_visibility.feature_types_hidden,
                     &data,
-                    state.ui.re.unique_cutters_only,
-                    state.ui.re.sticky_ends_only,
                     ui,
                 ));
             }
@@ -1053,5 +1892,55 @@ pub fn circle_page(state: &mut State, ui: &mut Ui) {
             }
 
             ui.painter().extend(shapes);
+
+            if let Some(range) = state.ui.quick_feature_add_at {
+                let angle = seq_i_to_angle(range.start, seq_len);
+                let pos = data.center_rel + angle_to_pixel(angle, data.radius).to_vec2();
+                quick_feature_add(state, pos, ui);
+            }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::{CentralPanel, Context};
+
+    use super::*;
+
+    /// The tick shapes for a sequence, drawn in a canvas of 800 points, enlarged by `zoom`.
+    fn tick_shapes(seq_len: usize, zoom: f32) -> Vec<Shape> {
+        let ctx = Context::default();
+        let mut result = Vec::new();
+
+        let _ = ctx.run(Default::default(), |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                let rect = Rect::from_min_size(Pos2::ZERO, vec2(800., 800.));
+                let to_screen = RectTransform::from_to(
+                    rect,
+                    Rect::from_min_size(Pos2::ZERO, rect.size() * zoom),
+                );
+                let data = CircleData::new(seq_len, rect.center(), 300., to_screen);
+
+                result = draw_ticks(&data, ui);
+            });
+        });
+
+        result
+    }
+
+    #[test]
+    fn tick_shape_count() {
+        // Ticks every 200 nucleotides: 0, 200, ..., 2,600.
+        for zoom in [1., 2.5] {
+            let shapes = tick_shapes(2_686, zoom);
+
+            let labels = shapes
+                .iter()
+                .filter(|s| matches!(s, Shape::Text(_)))
+                .count();
+            // A line, and a single label, per tick.
+            assert_eq!(labels, 14);
+            assert_eq!(shapes.len(), 28);
+        }
+    }
+}
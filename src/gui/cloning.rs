@@ -4,33 +4,43 @@ use std::borrow::Cow;
 use eframe::egui::{
     Color32, ComboBox, Frame, Grid, RichText, ScrollArea, Stroke, TextEdit, Ui, Vec2,
 };
-use na_seq::{insert_into_seq, seq_from_str, seq_to_str_lower, Nucleotide};
+use na_seq::{seq_to_str_lower, AaIdent, CodingResult, Nucleotide};
 use strum::IntoEnumIterator;
 
 use crate::{
-    backbones::{Backbone, BackboneFilters, CloningTechnique},
+    backbones::{detect_backbone, Backbone, BackboneFilters, CloningTechnique, ExpressionHost},
     cloning::{
-        make_product_tab, setup_insert_seqs, BackboneSelected, CloneStatus, CloningInsertData,
-        Status, RE_INSERT_BUFFER,
+        make_product_tab, seq_oriented, setup_insert_seqs, translate_junction, BackboneSelected,
+        CloneStatus, CloningInsertData, CloningState, Status, RE_INSERT_BUFFER,
     },
     file_io::{save::load_import, GenericData},
+    golden_gate::{overhang_to_str, plan_assembly, GoldenGateAssembly, TYPE_IIS_ENZYMES},
     gui::{
-        find_features,
+        feature_table::direction_picker,
+        find_features, int_field,
         lin_maps::seq_lin_disp,
         navigation::get_tab_names,
+        primer_table::color_from_score,
         select_color_text,
+        sequence::seq_view::COLOR_STOP_CODON,
         theme::{COLOR_ACTION, COLOR_INFO},
         COL_SPACING, ROW_SPACING,
     },
     misc_types::{Feature, FeatureType},
+    primer::{tm_target, Primer},
     state::State,
-    util::{merge_feature_sets, RangeIncl},
+    util::{disp_range, merge_feature_sets, seq_from_pasted},
 };
 
 const PASS_COLOR: Color32 = Color32::LIGHT_GREEN;
 const FAIL_COLOR: Color32 = Color32::LIGHT_RED;
 const NA_COLOR: Color32 = Color32::GOLD;
 
+// Warn about designed primers whose TM is further than this from the target, or whose quality is below
+// this.
+const PRIMER_TM_WARN_DIFF: f32 = 5.;
+const PRIMER_QUALITY_WARN: f32 = 0.5;
+
 fn filter_selector<T: fmt::Display + PartialEq + Copy + IntoEnumIterator>(
     name: &str,
     val: &mut Option<T>,
@@ -80,6 +90,36 @@ fn draw_insert_descrip(feature: &Feature, seq_loaded: &[Nucleotide], ui: &mut Ui
 fn insert_selector(data: &mut CloningInsertData, buffer: usize, ui: &mut Ui) -> bool {
     let mut clicked = false;
 
+    if !data.seq_loaded.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label("Manual region:").on_hover_text(
+                "Select an insert by index. This is required for sequences without features, \
+                such as FASTA files. Reverse regions are reverse-complemented.",
+            );
+            int_field(&mut data.manual_start, "Start:", ui);
+            int_field(&mut data.manual_end, "End:", ui);
+            ui.label("Dir:");
+            direction_picker(&mut data.manual_dir, 3_500, ui);
+            ui.add_space(COL_SPACING);
+
+            match data.manual_range() {
+                Ok(_) => {
+                    if ui
+                        .button(RichText::new("Use region").color(COLOR_ACTION))
+                        .clicked()
+                    {
+                        data.add_manual_insert();
+                        clicked = true;
+                    }
+                }
+                Err(e) => {
+                    ui.label(RichText::new(e).color(Color32::LIGHT_RED));
+                }
+            }
+        });
+        ui.add_space(ROW_SPACING / 2.);
+    }
+
     for (i, feature) in data.features_loaded.iter().enumerate() {
         // match feature.feature_type {
         //     FeatureType::CodingRegion | FeatureType::Generic | FeatureType::Gene => (),
@@ -107,23 +147,16 @@ fn insert_selector(data: &mut CloningInsertData, buffer: usize, ui: &mut Ui) ->
                     if ui.button(RichText::new("Select").color(btn_color)).clicked {
                         data.feature_selected = Some(i);
 
-                        // todo: Handle wraps with this for circular plasmids instead of truncating.
-                        let start = if buffer + 1 < feature.range.start {
-                            feature.range.start - buffer
-                        } else {
-                            1
-                        };
-
-                        let end_ = feature.range.end + buffer;
-                        let end = if end_ + 1 < data.seq_loaded.len() {
-                            end_
-                        } else {
-                            data.seq_loaded.len()
-                        };
-
-                        let buffered_range = RangeIncl::new(start, end);
-                        if let Some(seq_this_ft) = buffered_range.index_seq(&data.seq_loaded) {
-                            seq_this_ft.clone_into(&mut data.seq_insert);
+                        // On circular sequences, the buffer may wrap the origin.
+                        let buffered_range = feature.range.buffered(
+                            buffer,
+                            data.seq_loaded.len(),
+                            data.topology_loaded,
+                        );
+                        if let Some(seq_this_ft) = buffered_range
+                            .index_seq_topology(&data.seq_loaded, data.topology_loaded)
+                        {
+                            data.seq_insert = seq_oriented(&seq_this_ft, feature.direction);
                         }
                         clicked = true;
                     }
@@ -157,7 +190,8 @@ fn insert_tab_selection(state: &mut State, ui: &mut Ui) {
                 // This setup, including the break and variables, prevents borrow errors.
                 let g = gen.features.clone();
                 let s = gen.seq.clone();
-                setup_insert_seqs(state, g, s);
+                let t = gen.topology;
+                setup_insert_seqs(state, g, s, t);
 
                 state.ui.cloning_insert.show_insert_picker = true;
 
@@ -189,11 +223,12 @@ fn insert_tab_selection(state: &mut State, ui: &mut Ui) {
                     state,
                     state_loaded.generic.features.clone(),
                     state_loaded.generic.seq.clone(),
+                    state_loaded.generic.topology,
                 );
             }
         }
 
-        if !state.ui.cloning_insert.features_loaded.is_empty() {
+        if !state.ui.cloning_insert.seq_loaded.is_empty() {
             let hide_text = if state.ui.cloning_insert.show_insert_picker {
                 "Hide inserts"
             } else {
@@ -232,6 +267,117 @@ fn text_from_status(status: Status) -> RichText {
     }
 }
 
+/// Display the translation across each junction between the insert and backbone, so frame problems, e.g.
+/// with a fusion tag, are obvious.
+fn junction_disp(cloning: &CloningState, backbone: &Backbone, ui: &mut Ui) {
+    let seq = &cloning.product_seq;
+    let insert_len = seq.len().saturating_sub(backbone.seq.len());
+    if insert_len == 0 {
+        return;
+    }
+
+    let frame_start = cloning.junction_frame_start(backbone);
+
+    ui.heading("Junction translation").on_hover_text(
+        "Amino acids across each junction between the backbone and insert. The reading frame is set \
+        by an upstream tag if present, or by the start of the insert otherwise.",
+    );
+
+    for (name, junction) in [
+        ("5' junction", cloning.insert_loc),
+        ("3' junction", cloning.insert_loc + insert_len),
+    ] {
+        let codons = translate_junction(seq, frame_start, junction);
+        let mut stop_found = false;
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{name}:"));
+
+            for codon in &codons {
+                if codon.seq_i >= junction && codon.seq_i < junction + 3 && !codon.spans {
+                    ui.label(RichText::new("|").color(COLOR_INFO));
+                }
+
+                let (text, mut color) = match &codon.result {
+                    CodingResult::AminoAcid(aa) => {
+                        (aa.to_str(AaIdent::ThreeLetters).to_string(), Color32::WHITE)
+                    }
+                    CodingResult::StopCodon => {
+                        stop_found = true;
+                        ("STP".to_owned(), COLOR_STOP_CODON)
+                    }
+                };
+                if codon.spans {
+                    color = Color32::LIGHT_YELLOW;
+                }
+
+                ui.label(RichText::new(text).monospace().color(color))
+                    .on_hover_text(format!("Position {}", codon.seq_i));
+            }
+        });
+
+        if stop_found {
+            ui.label(RichText::new(format!("⚠ Stop codon near the {name}")).color(FAIL_COLOR));
+        }
+    }
+}
+
+/// Display the primers designed for the last cloning product, so they can be assessed without leaving
+/// this page.
+fn product_primers_disp(primers: &[Primer], ui: &mut Ui) {
+    ui.heading("Cloning primers");
+
+    let tm_target = tm_target();
+    let mut warnings = Vec::new();
+
+    Grid::new(1).spacing(Vec2::new(30., 4.)).show(ui, |ui| {
+        ui.label("Name");
+        ui.label("Sequence (5' to 3')");
+        ui.label("Len");
+        ui.label("TM (°C)");
+        ui.label("Quality");
+        ui.end_row();
+
+        for primer in primers {
+            ui.label(&primer.name);
+            ui.label(RichText::new(seq_to_str_lower(&primer.sequence)).monospace());
+            ui.label(primer.sequence.len().to_string());
+
+            match &primer.volatile.metrics {
+                Some(m) => {
+                    let tm_color = if (m.melting_temp - tm_target).abs() > PRIMER_TM_WARN_DIFF {
+                        warnings.push(format!(
+                            "{}: TM of {:.1}°C is far from the target of {:.1}°C",
+                            primer.name, m.melting_temp, tm_target
+                        ));
+                        Color32::LIGHT_RED
+                    } else {
+                        Color32::WHITE
+                    };
+                    ui.label(RichText::new(format!("{:.1}", m.melting_temp)).color(tm_color));
+
+                    if m.quality_score < PRIMER_QUALITY_WARN {
+                        warnings.push(format!("{}: Poor quality score", primer.name));
+                    }
+                    ui.label(
+                        RichText::new(format!("{:.0}", m.quality_score * 100.))
+                            .color(color_from_score(m.quality_score)),
+                    );
+                }
+                None => {
+                    ui.label("-");
+                    ui.label("-");
+                }
+            }
+            ui.end_row();
+        }
+    });
+
+    for warning in warnings {
+        ui.label(RichText::new(format!("⚠ {warning}")).color(FAIL_COLOR));
+    }
+}
+
 fn checklist(status: &CloneStatus, rbs_dist: Option<isize>, ui: &mut Ui) {
     ui.heading("Product checklist:");
 
@@ -262,6 +408,27 @@ fn checklist(status: &CloneStatus, rbs_dist: Option<isize>, ui: &mut Ui) {
         ui.label(text_from_status(status.tag_frame));
         ui.add_space(COL_SPACING);
 
+        ui.label("Start codon context:").on_hover_text(
+            "For bacterial hosts, checks for a Shine-Dalgarno sequence a suitable distance upstream \
+            of the start codon. For eukaryotic hosts, compares to the Kozak consensus; a purine at -3 \
+            and G at +4 are required to pass.",
+        );
+        ui.label(text_from_status(status.start_context.status));
+
+        // Show which consensus positions match.
+        let spacing_prev = ui.spacing().item_spacing.x;
+        ui.spacing_mut().item_spacing.x = 0.;
+        for (ch, matched) in &status.start_context.consensus {
+            let color = if *matched { PASS_COLOR } else { FAIL_COLOR };
+            ui.label(RichText::new(ch.to_string()).color(color).monospace());
+        }
+        ui.spacing_mut().item_spacing.x = spacing_prev;
+
+        if let Some(spacing) = status.start_context.spacing {
+            ui.label(format!(" ({spacing}nt)"));
+        }
+        ui.add_space(COL_SPACING);
+
         // ui.label("Primer quality:");
         // ui.label(RichText::new("Fail").color(FAIL_COLOR));
         // ui.add_space(COL_SPACING);
@@ -288,6 +455,77 @@ fn backbone_filters(filters: &mut BackboneFilters, ui: &mut Ui) {
     });
 }
 
+/// Identify which library backbone the opened sequence is built on, and where its insert likely is.
+fn backbone_detect_disp(state: &mut State, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        if ui
+            .button(RichText::new("Identify backbone").color(COLOR_ACTION))
+            .on_hover_text(
+                "Compare this sequence to the backbone library, to find which it's likely based on. \
+                The longest region not found in the backbone is reported as the insert.",
+            )
+            .clicked()
+        {
+            let data = &state.generic[state.active];
+            state.cloning[state.active].backbone_detected =
+                detect_backbone(&data.seq, data.topology, &state.backbone_lib);
+
+            if state.cloning[state.active].backbone_detected.is_none() {
+                state.ui.backbone_detect_status = Some(
+                    "No library backbone matches this sequence".to_owned(),
+                );
+            } else {
+                state.ui.backbone_detect_status = None;
+            }
+        }
+
+        if let Some(status) = &state.ui.backbone_detect_status {
+            ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+        }
+
+        let detected = match &state.cloning[state.active].backbone_detected {
+            Some(d) => d,
+            None => return,
+        };
+
+        if detected.backbone_i >= state.backbone_lib.len() {
+            eprintln!("Invalid index in backbone lib");
+            return;
+        }
+
+        ui.label("Likely based on:");
+        ui.label(
+            RichText::new(&state.backbone_lib[detected.backbone_i].name).color(COLOR_INFO),
+        );
+
+        let color = color_from_score(detected.score);
+        ui.label(RichText::new(format!("{:.0}% found", detected.score * 100.)).color(color))
+            .on_hover_text("The portion of the backbone's sequence found in this one.");
+
+        if detected.reverse {
+            ui.label("(reverse complement)");
+        }
+
+        ui.add_space(COL_SPACING);
+
+        match detected.insert {
+            Some(insert) => {
+                ui.label(format!("Insert: {}", disp_range(insert)));
+                if ui
+                    .button("Select")
+                    .on_hover_text("Select the insert in the sequence view.")
+                    .clicked()
+                {
+                    state.ui.text_selection = Some(insert);
+                }
+            }
+            None => {
+                ui.label("No insert found");
+            }
+        }
+    });
+}
+
 /// A UI element that allows the user to choose which backbone to clone into.
 fn backbone_selector(
     backbone_selected: &mut BackboneSelected,
@@ -370,11 +608,200 @@ fn backbone_selector(
     changed
 }
 
+/// Choose the parts to assemble, in order.
+fn golden_gate_parts(state: &mut State, ui: &mut Ui) -> bool {
+    let mut changed = false;
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Parts, in order:").on_hover_text(
+            "Click to add a tab to the assembly, or remove it. Include the destination vector; the \
+            assembly is circular, so the last part joins the first.",
+        );
+
+        let plasmid_names: &Vec<_> = &state
+            .generic
+            .iter()
+            .map(|v| v.metadata.plasmid_name.as_str())
+            .collect();
+
+        let gg = &mut state.ui.golden_gate;
+        for (name, i) in get_tab_names(&state.tabs_open, plasmid_names, true) {
+            let position = gg.tabs.iter().position(|t| *t == i);
+            let text = match position {
+                Some(p) => format!("{}: {name}", p + 1),
+                None => name,
+            };
+
+            if ui
+                .button(select_color_text(&text, position.is_some()))
+                .clicked()
+            {
+                match position {
+                    Some(p) => {
+                        gg.tabs.remove(p);
+                    }
+                    None => gg.tabs.push(i),
+                }
+                changed = true;
+            }
+        }
+    });
+
+    changed
+}
+
+fn golden_gate_table(assembly: &GoldenGateAssembly, state: &State, ui: &mut Ui) {
+    let part_name = |i: usize| {
+        let tab = assembly.parts[i].tab;
+        format!("{}: {}", i + 1, state.generic[tab].metadata.plasmid_name)
+    };
+
+    let (text, color) = if assembly.valid() {
+        (
+            "PASS: Each junction has a unique, compatible overhang",
+            PASS_COLOR,
+        )
+    } else {
+        ("FAIL: The parts may not assemble as planned", FAIL_COLOR)
+    };
+    ui.label(RichText::new(text).color(color).strong());
+
+    for (i, part) in assembly.parts.iter().enumerate() {
+        if part.internal_sites > 0 {
+            ui.label(
+                RichText::new(format!(
+                    "⚠ {} has {} internal {} site(s). It will be cut during assembly.",
+                    part_name(i),
+                    part.internal_sites,
+                    state.ui.golden_gate.enzyme.to_str()
+                ))
+                .color(FAIL_COLOR),
+            );
+        }
+    }
+
+    Grid::new(4_501).spacing(Vec2::new(30., 4.)).show(ui, |ui| {
+        ui.label("Junction");
+        ui.label("Left part");
+        ui.label("Right part");
+        ui.label("Overhang");
+        ui.label("Status");
+        ui.end_row();
+
+        for (i, junction) in assembly.junctions.iter().enumerate() {
+            ui.label((i + 1).to_string());
+            ui.label(part_name(junction.part_left));
+            ui.label(part_name(junction.part_right));
+            ui.label(RichText::new(overhang_to_str(&junction.overhang)).monospace());
+
+            let (text, color) = if junction.overhang.is_none() {
+                ("No site".to_string(), FAIL_COLOR)
+            } else if !junction.compatible {
+                let right = &assembly.parts[junction.part_right].overhang_left;
+                (format!("Mismatch: {}", overhang_to_str(right)), FAIL_COLOR)
+            } else if !junction.duplicates.is_empty() {
+                let others: Vec<_> = junction
+                    .duplicates
+                    .iter()
+                    .map(|j| (j + 1).to_string())
+                    .collect();
+                (
+                    format!("Same as junction {}", others.join(", ")),
+                    FAIL_COLOR,
+                )
+            } else if junction.palindromic {
+                ("Palindromic".to_string(), NA_COLOR)
+            } else {
+                ("OK".to_string(), PASS_COLOR)
+            };
+            ui.label(RichText::new(text).color(color));
+            ui.end_row();
+        }
+    });
+
+    if assembly.junctions.iter().any(|j| !j.duplicates.is_empty()) {
+        ui.label(
+            RichText::new(
+                "⚠ Multiple junctions share an overhang; parts may assemble in the wrong order.",
+            )
+            .color(FAIL_COLOR),
+        );
+    }
+}
+
+/// Plan a Golden Gate assembly of open tabs, using a Type IIS enzyme.
+fn golden_gate_disp(state: &mut State, ui: &mut Ui) {
+    ui.heading("Golden Gate assembly");
+
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Enzyme:");
+
+        let enzyme_prev = state.ui.golden_gate.enzyme;
+        ComboBox::from_id_salt(4_500)
+            .width(80.)
+            .selected_text(state.ui.golden_gate.enzyme.to_str())
+            .show_ui(ui, |ui| {
+                for enzyme in TYPE_IIS_ENZYMES {
+                    ui.selectable_value(&mut state.ui.golden_gate.enzyme, enzyme, enzyme.to_str());
+                }
+            });
+
+        if state.ui.golden_gate.enzyme != enzyme_prev {
+            changed = true;
+        }
+
+        let enzyme = state.ui.golden_gate.enzyme;
+        ui.label(
+            RichText::new(format!(
+                "{} ({} nt overhangs)",
+                seq_to_str_lower(&enzyme.site()).to_uppercase(),
+                enzyme.overhang_len()
+            ))
+            .color(COLOR_INFO),
+        );
+
+        ui.add_space(COL_SPACING);
+        if ui
+            .button(RichText::new("Plan assembly").color(COLOR_ACTION))
+            .on_hover_text("Find overhangs again; e.g. after editing a part.")
+            .clicked()
+        {
+            changed = true;
+        }
+    });
+
+    if golden_gate_parts(state, ui) {
+        changed = true;
+    }
+
+    if changed {
+        let gg = &mut state.ui.golden_gate;
+        gg.result = if gg.tabs.is_empty() {
+            None
+        } else {
+            Some(plan_assembly(&gg.tabs, &state.generic, gg.enzyme))
+        };
+    }
+
+    if let Some(assembly) = &state.ui.golden_gate.result {
+        ui.add_space(ROW_SPACING / 2.);
+        golden_gate_table(assembly, state, ui);
+    }
+}
+
 pub fn cloning_page(state: &mut State, ui: &mut Ui) {
     ScrollArea::vertical().id_salt(100).show(ui, |ui| {
         let mut sync = false;
 
-        ui.heading("Cloning (Currently supports PCR-based cloning only");
+        // Each tab has its own cloning design; re-sync derived data when viewing a different one.
+        if state.ui.cloning_tab_synced != Some(state.active) {
+            state.ui.cloning_tab_synced = Some(state.active);
+            sync = true;
+        }
+
+        ui.heading("Cloning (Currently supports PCR-based and Golden Gate cloning only)");
         //     ui.label("For a given insert, automatically select a backbone, and either restriction enzymes, or PCR primers to use\
         // to clone the insert into the backbone.");
 
@@ -389,7 +816,7 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
                 remove the codon, so the tag is coded for",
             );
             if ui
-                .checkbox(&mut state.cloning.remove_stop_codons, "")
+                .checkbox(&mut state.cloning[state.active].remove_stop_codons, "")
                 .changed()
             {
                 sync = true;
@@ -398,7 +825,7 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
         ui.add_space(ROW_SPACING);
 
         // todo: DRY with below getting the backbone, but we have a borrow error when moving that up.
-        let data_vec = match state.cloning.backbone_selected {
+        let data_vec = match state.cloning[state.active].backbone_selected {
             BackboneSelected::Library(i) => {
                 if i >= state.backbone_lib.len() {
                     eprintln!("Invalid index in backbone lib");
@@ -424,9 +851,9 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
             true,
             state.ui.selected_item,
             &state.ui.re.res_selected,
-            Some(state.cloning.insert_loc),
+            Some(state.cloning[state.active].insert_loc),
             &state.ui,
-            &state.cloning.re_matches_vec_common,
+            &state.cloning[state.active].re_matches_vec_common,
             &state.restriction_enzyme_lib,
             ui,
         );
@@ -434,7 +861,7 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
         ui.add_space(ROW_SPACING);
 
         // A minimap for the insert
-        if let Some(data) = &state.cloning.data_insert {
+        if let Some(data) = &state.cloning[state.active].data_insert {
             seq_lin_disp(
                 data,
                 true,
@@ -442,7 +869,7 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
                 &state.ui.re.res_selected,
                 None,
                 &state.ui,
-                &state.cloning.re_matches_insert_common,
+                &state.cloning[state.active].re_matches_insert_common,
                 &state.restriction_enzyme_lib,
                 ui,
             );
@@ -464,6 +891,8 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
         }
 
         ui.heading("Backbones (vectors)");
+        backbone_detect_disp(state, ui);
+        ui.add_space(ROW_SPACING / 2.);
         backbone_filters(&mut state.ui.backbone_filters, ui);
         ui.add_space(ROW_SPACING);
 
@@ -471,12 +900,13 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
         let backbones_filtered = state.ui.backbone_filters.apply(&state.backbone_lib);
 
         let plasmid_name = &state.generic[state.active].metadata.plasmid_name;
+        let cloning = &mut state.cloning[state.active];
         let backbone_just_picked = backbone_selector(
-            &mut state.cloning.backbone_selected,
+            &mut cloning.backbone_selected,
             &backbones_filtered,
             plasmid_name,
             &state.generic[state.active],
-            &mut state.cloning.backbone,
+            &mut cloning.backbone,
             ui,
         );
 
@@ -485,8 +915,8 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
         }
 
         // todo: This is DRY with get_backbone due to borrow error.
-        // let backbone = state.cloning.get_backbone(&state.backbone_lib);
-        let backbone = match state.cloning.backbone_selected {
+        // let backbone = cloning.get_backbone(&state.backbone_lib);
+        let backbone = match cloning.backbone_selected {
             BackboneSelected::Library(i) => {
                 if i >= state.backbone_lib.len() {
                     eprintln!("Invalid index in backbone lib");
@@ -495,27 +925,25 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
                     Some(&state.backbone_lib[i])
                 }
             }
-            BackboneSelected::Opened => match state.cloning.backbone.as_ref() {
-                Some(bb) => Some(bb),
-                None => None,
-            },
+            BackboneSelected::Opened => cloning.backbone.as_ref(),
         };
 
         // These variables prevent borrow errors on backbone.
         let mut clone_initiated = false;
+        let mut product_backbone = None;
 
         if let Some(backbone) = backbone {
             let rbs_dist = backbone
                 .rbs
-                .map(|r| state.cloning.insert_loc as isize - r.end as isize);
+                .map(|r| cloning.insert_loc as isize - r.end as isize);
 
             ui.add_space(ROW_SPACING);
             ui.label("Restriction enzymes:");
-            if state.cloning.res_common.is_empty() {
+            if cloning.res_common.is_empty() {
                 ui.label("(None)");
             }
 
-            for candidate in &state.cloning.res_common {
+            for candidate in &cloning.res_common {
                 ui.label(&candidate.name);
             }
 
@@ -528,21 +956,48 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
                 .clicked()
             {
                 if let Some(insert_loc) = backbone.insert_loc(CloningTechnique::Pcr) {
-                    state.cloning.insert_loc = insert_loc;
+                    cloning.insert_loc = insert_loc;
                 }
                 sync = true;
             }
 
             ui.horizontal(|ui| {
                 ui.label("Insert location:");
-                ui.label(RichText::new(format!("{}", state.cloning.insert_loc)).color(COLOR_INFO));
+                ui.label(RichText::new(format!("{}", cloning.insert_loc)).color(COLOR_INFO));
+
+                ui.add_space(COL_SPACING);
+                ui.label("Expression host:").on_hover_text(
+                    "Used to check the context around the start codon: Shine-Dalgarno for \
+                    bacterial hosts; Kozak otherwise.",
+                );
+
+                let host_prev = cloning.expression_host;
+                ComboBox::from_id_salt(1001)
+                    .width(80.)
+                    .selected_text(cloning.expression_host.to_string())
+                    .show_ui(ui, |ui| {
+                        for host in ExpressionHost::iter() {
+                            ui.selectable_value(
+                                &mut cloning.expression_host,
+                                host,
+                                host.to_string(),
+                            );
+                        }
+                    });
+
+                if cloning.expression_host != host_prev {
+                    sync = true;
+                }
             });
             ui.add_space(ROW_SPACING);
 
             // todo: Only if there is a result
             if true {
                 ui.add_space(ROW_SPACING);
-                checklist(&state.cloning.status, rbs_dist, ui);
+                checklist(&cloning.status, rbs_dist, ui);
+
+                ui.add_space(ROW_SPACING);
+                junction_disp(cloning, backbone, ui);
 
                 ui.add_space(ROW_SPACING);
 
@@ -558,12 +1013,12 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
 
             ui.horizontal(|ui| {
                 ui.label("Insert location: ");
-                let mut entry = state.cloning.insert_loc.to_string();
+                let mut entry = cloning.insert_loc.to_string();
                 if ui
                     .add(TextEdit::singleline(&mut entry).desired_width(40.))
                     .changed()
                 {
-                    state.cloning.insert_loc = entry.parse().unwrap_or(0);
+                    cloning.insert_loc = entry.parse().unwrap_or(0);
                     sync = true;
                 }
 
@@ -571,29 +1026,43 @@ pub fn cloning_page(state: &mut State, ui: &mut Ui) {
             });
 
             if clone_initiated {
-                make_product_tab(state, Some(backbone.data.clone()));
-                // Annotate the vector, for now at least.
-                let features = find_features(&state.get_seq());
-                // We assume the product has been made active.
-                merge_feature_sets(&mut state.generic[state.active].features, &features)
+                product_backbone = Some(backbone.data.clone());
             }
         }
 
+        if let Some(backbone_data) = product_backbone {
+            make_product_tab(state, Some(backbone_data));
+            // Annotate the vector, for now at least.
+            let features = find_features(state.get_seq());
+            // We assume the product has been made active.
+            merge_feature_sets(&mut state.generic[state.active].features, &features)
+        }
+
+        if !state.cloning[state.active].product_primers.is_empty() {
+            ui.add_space(ROW_SPACING);
+            product_primers_disp(&state.cloning[state.active].product_primers, ui);
+            ui.add_space(ROW_SPACING);
+        }
+
         let resp_insert_editor = ui.add(
             TextEdit::multiline(&mut state.ui.cloning_insert.seq_input)
                 .desired_width(ui.available_width()),
         );
         if resp_insert_editor.changed() {
             // Forces only valid NTs to be included in the string.
-            state.ui.cloning_insert.seq_insert = seq_from_str(&state.ui.cloning_insert.seq_input);
+            state.ui.cloning_insert.seq_insert =
+                seq_from_pasted(&state.ui.cloning_insert.seq_input).0;
             state.ui.cloning_insert.seq_input =
                 seq_to_str_lower(&state.ui.cloning_insert.seq_insert);
 
             sync = true;
         }
 
+        ui.add_space(ROW_SPACING);
+        golden_gate_disp(state, ui);
+
         if sync {
-            state.cloning.sync(
+            state.cloning[state.active].sync(
                 &mut state.ui.cloning_insert.seq_insert,
                 &state.backbone_lib,
                 &state.restriction_enzyme_lib,
@@ -1,22 +1,34 @@
 //! GUI code for the features editor and related.
 
 use eframe::egui::{
-    Color32, ComboBox, CursorIcon, Frame, RichText, ScrollArea, Stroke, TextEdit, Ui,
+    Button, Color32, ComboBox, CursorIcon, Frame, RichText, ScrollArea, Stroke, TextEdit, Ui,
 };
+use na_seq::seq_to_str_lower;
 
 use crate::{
-    gui::{int_field, theme::COLOR_ACTION, COL_SPACING, ROW_SPACING},
+    cassette::{find_cassettes, CassettePart},
+    crispr::{find_guides, Guide, PamType},
+    file_io::GenericData,
+    gui::{
+        delete_confirm, int_field, theme::COLOR_ACTION, toggle_feature_multi_select, COL_SPACING,
+        ROW_SPACING,
+    },
+    inverted_repeats::{find_hairpins, InvertedRepeat},
     misc_types::{
         Feature,
         FeatureDirection::{self, Forward, Reverse},
         FeatureType,
     },
+    reading_frame::frame_for_feature,
     state::State,
-    util::RangeIncl,
+    util::{disp_range, RangeIncl},
     Color, Selection,
 };
 
 const LABEL_EDIT_WIDTH: f32 = 140.;
+// Plasmids can have many inverted repeats; don't list them all.
+const INVERTED_REPEAT_DISP_MAX: usize = 100;
+const GUIDE_DISP_MAX: usize = 50;
 
 /// A color selector for use with feature addition and editing.
 fn color_picker(val: &mut Option<Color>, feature_color: Color, ui: &mut Ui) {
@@ -56,54 +68,225 @@ pub fn direction_picker(val: &mut FeatureDirection, id: usize, ui: &mut Ui) {
         });
 }
 
-/// A selector for use with feature addition and editing.
+/// A selector for use with feature addition and editing. Returns true if the type changed.
 /// todo: Generic selector creator?
-fn feature_type_picker(val: &mut FeatureType, id: usize, ui: &mut Ui) {
+pub fn feature_type_picker(val: &mut FeatureType, id: usize, ui: &mut Ui) -> bool {
+    let mut changed = false;
+
     ComboBox::from_id_salt(id)
         .width(140.)
         .selected_text(val.to_string())
         .show_ui(ui, |ui| {
             for feature_type in [
                 FeatureType::Generic,
+                FeatureType::Gene,
                 FeatureType::CodingRegion,
+                FeatureType::Promoter,
+                FeatureType::Terminator,
                 FeatureType::Ori,
                 // FeatureType::RnaPolyBindSite,
                 FeatureType::RibosomeBindSite,
+                FeatureType::ProteinBind,
                 FeatureType::AntibioticResistance,
                 FeatureType::LongTerminalRepeat,
                 FeatureType::Exon,
                 FeatureType::Transcript,
                 // todo: Source?
             ] {
-                ui.selectable_value(val, feature_type, feature_type.to_string());
+                if ui
+                    .selectable_value(val, feature_type, feature_type.to_string())
+                    .changed()
+                {
+                    changed = true;
+                }
             }
         });
+
+    changed
+}
+
+/// Change the type, direction, or color of all features in a multi-selection, or delete them.
+fn feature_bulk_disp(state: &mut State, ui: &mut Ui) {
+    let feature_count = state.generic[state.active].features.len();
+    state.ui.selected_features.retain(|i| *i < feature_count);
+
+    if state.ui.selected_features.is_empty() {
+        return;
+    }
+
+    let mut deleted = false;
+    let mut cleared = false;
+    let features_prev = state.generic[state.active].features.clone();
+
+    ui.horizontal(|ui| {
+        let features = &mut state.generic[state.active].features;
+        let selected = &state.ui.selected_features;
+        let bulk = &mut state.ui.feature_bulk;
+
+        ui.label(
+            RichText::new(format!("{} features selected", selected.len())).color(COLOR_ACTION),
+        )
+        .on_hover_text(
+            "Ctrl or Shift-click features in this table, or on the maps, to select several.",
+        );
+        ui.add_space(COL_SPACING);
+
+        ui.label("Type:");
+        feature_type_picker(&mut bulk.feature_type, 3_900, ui);
+        if ui.button("Set").clicked() {
+            for i in selected {
+                features[*i].feature_type = bulk.feature_type;
+                features[*i].color_override = None;
+            }
+        }
+        ui.add_space(COL_SPACING);
+
+        ui.label("Dir:");
+        direction_picker(&mut bulk.direction, 3_901, ui);
+        if ui.button("Set").clicked() {
+            for i in selected {
+                features[*i].direction = bulk.direction;
+            }
+        }
+        ui.add_space(COL_SPACING);
+
+        ui.label("Custom color:");
+        color_picker(&mut bulk.color, bulk.feature_type.color(), ui);
+        if ui
+            .button("Set")
+            .on_hover_text("If custom color is unchecked, features use their type's color.")
+            .clicked()
+        {
+            for i in selected {
+                features[*i].color_override = bulk.color;
+            }
+        }
+        ui.add_space(COL_SPACING);
+
+        if ui.button("Clear selection").clicked() {
+            cleared = true;
+        }
+
+        ui.add_space(COL_SPACING); // Less likely to accidentally delete.
+
+        if ui
+            .button(RichText::new("Delete selected 🗑").color(Color32::RED))
+            .clicked()
+        {
+            deleted = true;
+        }
+    });
+    ui.add_space(ROW_SPACING);
+
+    // The type, direction, and color are set in place.
+    snapshot_features_edit(state, features_prev);
+
+    if deleted {
+        state.snapshot();
+        let mut selected = state.ui.selected_features.clone();
+        selected.sort_unstable();
+        selected.dedup();
+
+        // Remove from the highest index down, so the remaining indices stay valid.
+        for i in selected.into_iter().rev() {
+            state.generic[state.active].features.remove(i);
+        }
+        state.ui.selected_item = Selection::None;
+    }
+
+    if deleted || cleared {
+        state.ui.selected_features.clear();
+    }
+}
+
+/// Store an undo step if features were edited in place, e.g. by a text field. `prev` are the features
+/// before.
+fn snapshot_features_edit(state: &mut State, prev: Vec<Feature>) {
+    if state.generic[state.active].features == prev {
+        return;
+    }
+
+    let data_prev = GenericData {
+        features: prev,
+        ..state.generic[state.active].clone()
+    };
+    state.snapshot_prev(&data_prev);
 }
 
 pub fn feature_table(state: &mut State, ui: &mut Ui) {
     feature_add_disp(state, ui);
     ui.add_space(ROW_SPACING);
 
+    feature_bulk_disp(state, ui);
+    let features_prev = state.generic[state.active].features.clone();
+
     let mut removed = None;
+    let mut merge_with_next = None;
+    let mut multi_clicked = None;
+    let mut frame_changed = false;
+    let feature_count = state.generic[state.active].features.len();
+    let seq_len = state.get_seq().len();
+    let multi_select = ui.input(|i| i.modifiers.ctrl || i.modifiers.shift);
+
+    // Features can only be merged with the next if they overlap or touch.
+    let topology = state.generic[state.active].topology;
+    let mergeable: Vec<_> = state.generic[state.active]
+        .features
+        .windows(2)
+        .map(|f| f[0].merge(&f[1], seq_len, topology).is_some())
+        .collect();
+
     for (i, feature) in state.generic[state.active].features.iter_mut().enumerate() {
+        let frame_prev = (feature.codon_start, feature.direction);
+
         let mut border_width = 0.;
         if let Selection::Feature(j) = state.ui.selected_item {
             if i == j {
                 border_width = 1.;
             }
         }
+        if state.ui.selected_features.contains(&i) {
+            border_width = 1.;
+        }
 
         Frame::none()
             .stroke(Stroke::new(border_width, Color32::LIGHT_RED))
             .inner_margin(border_width)
             .show(ui, |ui| {
-                if ui
-                    .heading(RichText::new(&feature.label).color(COLOR_ACTION))
-                    .on_hover_cursor(CursorIcon::PointingHand)
-                    .clicked()
-                {
-                    state.ui.selected_item = Selection::Feature(i);
-                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .heading(RichText::new(&feature.label).color(COLOR_ACTION))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        if multi_select {
+                            multi_clicked = Some(i);
+                        } else {
+                            state.ui.selected_item = Selection::Feature(i);
+                            state.ui.selected_features.clear();
+                        }
+                    }
+
+                    if let Some(annot_match) = feature.annotation_match() {
+                        ui.add_space(COL_SPACING);
+                        let (text, color) = if annot_match.exact() {
+                            ("Exact match".to_owned(), Color32::LIGHT_GREEN)
+                        } else {
+                            (
+                                format!(
+                                    "Near match: {:.1}%, {} mismatches",
+                                    annot_match.identity() * 100.,
+                                    annot_match.mismatches
+                                ),
+                                Color32::LIGHT_YELLOW,
+                            )
+                        };
+                        ui.label(RichText::new(text).color(color)).on_hover_text(
+                            "This feature was annotated automatically; this is its identity to the \
+                            reference sequence.",
+                        );
+                    }
+                });
 
                 ui.horizontal(|ui| {
                     int_field(&mut feature.range.start, "Start:", ui);
@@ -115,11 +298,39 @@ pub fn feature_table(state: &mut State, ui: &mut Ui) {
                     );
 
                     ui.label("Type:");
-                    feature_type_picker(&mut feature.feature_type, 100 + i, ui);
+                    if feature_type_picker(&mut feature.feature_type, 100 + i, ui) {
+                        // Reclassifying a feature should show the new type's color on the maps.
+                        feature.color_override = None;
+                    }
 
                     ui.label("Dir:");
                     direction_picker(&mut feature.direction, 300 + i, ui);
 
+                    if feature.feature_type == FeatureType::CodingRegion {
+                        ui.label("Frame:").on_hover_text(
+                            "The position of the first complete codon, relative to the start of \
+                            the feature. (GenBank codon_start) Set this, and the direction, if the \
+                            translation is in the wrong frame.",
+                        );
+                        ComboBox::from_id_salt(500 + i)
+                            .width(32.)
+                            .selected_text(feature.codon_start.to_string())
+                            .show_ui(ui, |ui| {
+                                for v in 1..=3 {
+                                    ui.selectable_value(&mut feature.codon_start, v, v.to_string());
+                                }
+                            });
+                        ui.label(frame_for_feature(feature, seq_len).to_string())
+                            .on_hover_text(
+                                "The reading frame this feature is translated in, for the \
+                                sequence as a whole.",
+                            );
+
+                        if (feature.codon_start, feature.direction) != frame_prev {
+                            frame_changed = true;
+                        }
+                    }
+
                     ui.label("Custom color:");
                     color_picker(
                         &mut feature.color_override,
@@ -150,6 +361,22 @@ pub fn feature_table(state: &mut State, ui: &mut Ui) {
                         state.ui.selected_item = Selection::Feature(i);
                     }
 
+                    if i + 1 < feature_count
+                        && ui
+                            .add_enabled(mergeable[i], Button::new("Merge next"))
+                            .on_hover_text(
+                                "Combine this feature with the one below it, into a single \
+                                feature spanning both. Keeps the label of the larger.",
+                            )
+                            .on_disabled_hover_text(
+                                "This feature and the one below it don't overlap or touch, so \
+                                they can't be merged.",
+                            )
+                            .clicked()
+                    {
+                        merge_with_next = Some(i);
+                    }
+
                     ui.add_space(COL_SPACING); // Less likely to accidentally delete.
 
                     if ui
@@ -173,8 +400,36 @@ pub fn feature_table(state: &mut State, ui: &mut Ui) {
 
         ui.add_space(ROW_SPACING);
     }
+    snapshot_features_edit(state, features_prev);
+
+    if let Some(i) = multi_clicked {
+        toggle_feature_multi_select(state, i);
+    }
+
+    if frame_changed {
+        // Update the reading frames used for the protein view.
+        state.sync_seq_related(None);
+    }
+
     if let Some(rem_i) = removed {
+        state.snapshot();
         state.generic[state.active].features.remove(rem_i);
+        state.ui.selected_features.clear();
+    }
+
+    if let Some(i) = merge_with_next {
+        state.snapshot();
+        let data = &mut state.generic[state.active];
+        match data.features[i].merge(&data.features[i + 1], data.seq.len(), data.topology) {
+            Some(merged) => {
+                data.features[i] = merged;
+                data.features.remove(i + 1);
+            }
+            None => eprintln!("Unable to merge features that don't overlap or touch"),
+        }
+
+        state.ui.selected_item = Selection::Feature(i);
+        state.ui.selected_features.clear();
     }
 }
 
@@ -195,6 +450,7 @@ pub fn feature_add_disp(state: &mut State, ui: &mut Ui) {
                 );
             }
 
+            state.snapshot();
             state.generic[state.active].features.push(Feature {
                 range: RangeIncl::new(
                     state.ui.feature_add.start_posit,
@@ -205,13 +461,353 @@ pub fn feature_add_disp(state: &mut State, ui: &mut Ui) {
                 label: state.ui.feature_add.label.clone(),
                 color_override: None,
                 notes: Default::default(),
+                codon_start: 1,
+            });
+        }
+
+        if state.generic[state.active]
+            .features
+            .iter()
+            .any(|f| f.annotation_match().is_some())
+        {
+            ui.add_space(COL_SPACING);
+            if ui
+                .button("Sort by match")
+                .on_hover_text(
+                    "Sort automatically-annotated features by their identity to the reference \
+                    sequence, best first. Other features are placed last.",
+                )
+                .clicked()
+            {
+                state.snapshot();
+                state.generic[state.active].features.sort_by(|a, b| {
+                    let a = a.annotation_match().map(|m| m.identity()).unwrap_or(-1.);
+                    let b = b.annotation_match().map(|m| m.identity()).unwrap_or(-1.);
+                    b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal)
+                });
+                state.ui.selected_item = Selection::None;
+                state.ui.selected_features.clear();
+            }
+        }
+    });
+}
+
+fn feature_from_inverted_repeat(ir: &InvertedRepeat) -> Feature {
+    let (feature_type, label) = if ir.terminator {
+        (FeatureType::Terminator, "Terminator")
+    } else {
+        (FeatureType::Generic, "Inverted repeat")
+    };
+
+    Feature {
+        range: ir.range(),
+        feature_type,
+        label: label.to_owned(),
+        ..Default::default()
+    }
+}
+
+/// Search for inverted repeats (hairpins), and flag likely terminators. These can be added as features.
+fn inverted_repeat_disp(state: &mut State, ui: &mut Ui) {
+    let mut added = Vec::new();
+
+    ui.horizontal(|ui| {
+        ui.heading("Inverted repeats");
+        ui.add_space(COL_SPACING);
+
+        int_field(&mut state.ui.inverted_repeat.min_stem, "Min stem:", ui);
+        int_field(&mut state.ui.inverted_repeat.max_loop, "Max loop:", ui);
+
+        if ui
+            .button(RichText::new("Find").color(COLOR_ACTION))
+            .on_hover_text(
+                "Find sequences followed closely by their reverse complement. These can form \
+                hairpins and cruciforms. GC-rich hairpins followed by a poly-T tract are flagged as \
+                likely terminators.",
+            )
+            .clicked()
+        {
+            let repeats = find_hairpins(
+                state.get_seq(),
+                state.ui.inverted_repeat.min_stem,
+                state.ui.inverted_repeat.max_loop,
+            );
+            state.volatile[state.active].inverted_repeats = Some(repeats);
+        }
+
+        if let Some(repeats) = &state.volatile[state.active].inverted_repeats {
+            if repeats.iter().any(|ir| ir.terminator) && ui.button("Add terminators").clicked() {
+                for ir in repeats.iter().filter(|ir| ir.terminator) {
+                    added.push(feature_from_inverted_repeat(ir));
+                }
+            }
+        }
+    });
+
+    if let Some(repeats) = &state.volatile[state.active].inverted_repeats {
+        if repeats.is_empty() {
+            ui.label("No inverted repeats found.");
+        }
+
+        for ir in repeats.iter().take(INVERTED_REPEAT_DISP_MAX) {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{}..{} / {}..{}",
+                    ir.stem_5p.start, ir.stem_5p.end, ir.stem_3p.start, ir.stem_3p.end
+                ));
+                ui.label(format!(
+                    "Stem: {} bp  Loop: {} nt",
+                    ir.stem_len(),
+                    ir.loop_len()
+                ));
+
+                if ir.terminator {
+                    let (r, g, b) = FeatureType::Terminator.color();
+                    ui.label(RichText::new("Likely terminator").color(Color32::from_rgb(r, g, b)));
+                }
+
+                if ui.button("Add feature").clicked() {
+                    added.push(feature_from_inverted_repeat(ir));
+                }
+            });
+        }
+
+        if repeats.len() > INVERTED_REPEAT_DISP_MAX {
+            ui.label(format!(
+                "...and {} more. Increase the minimum stem length to narrow results.",
+                repeats.len() - INVERTED_REPEAT_DISP_MAX
+            ));
+        }
+    }
+
+    if !added.is_empty() {
+        state.snapshot();
+        state.generic[state.active].features.extend(added);
+    }
+}
+
+fn feature_from_guide(guide: &Guide) -> Feature {
+    Feature {
+        range: guide.range,
+        direction: guide.direction,
+        label: "gRNA".to_owned(),
+        ..Default::default()
+    }
+}
+
+/// Search for CRISPR guides adjacent to PAM sites, on both strands. These can be added as features.
+fn guide_disp(state: &mut State, ui: &mut Ui) {
+    let mut added = Vec::new();
+
+    ui.horizontal(|ui| {
+        ui.heading("CRISPR guides");
+        ui.add_space(COL_SPACING);
+
+        ui.label("PAM:");
+        ComboBox::from_id_salt(3_400)
+            .width(80.)
+            .selected_text(state.ui.crispr.pam.to_string())
+            .show_ui(ui, |ui| {
+                for pam in [PamType::Ngg, PamType::Nag, PamType::Custom] {
+                    ui.selectable_value(&mut state.ui.crispr.pam, pam, pam.to_string());
+                }
+            });
+
+        if state.ui.crispr.pam == PamType::Custom {
+            ui.add(TextEdit::singleline(&mut state.ui.crispr.pam_custom).desired_width(60.))
+                .on_hover_text("The PAM, 5' to 3'. Use A, C, T, G, and N for any nucleotide.");
+        }
+
+        let pam = state.ui.crispr.pam_pattern();
+        if pam.is_none() {
+            ui.label(RichText::new("Invalid PAM").color(Color32::LIGHT_RED));
+        }
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .add_enabled(
+                pam.is_some(),
+                Button::new(RichText::new("Find").color(COLOR_ACTION)),
+            )
+            .on_hover_text(
+                "Find 20 nt guides immediately upstream of PAM sites, on both strands. Guides are \
+                ranked by GC content, poly-T tracts (which terminate U6 transcription), internal \
+                repeats, and sites elsewhere in this sequence sharing their seed region.",
+            )
+            .clicked()
+        {
+            if let Some(pam) = pam {
+                let guides = find_guides(state.get_seq(), &pam);
+                state.volatile[state.active].guides = Some(guides);
+            }
+        }
+    });
+
+    if let Some(guides) = &state.volatile[state.active].guides {
+        if guides.is_empty() {
+            ui.label("No guides found.");
+        }
+
+        for guide in guides.iter().take(GUIDE_DISP_MAX) {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "{} {}",
+                        seq_to_str_lower(&guide.seq),
+                        seq_to_str_lower(&guide.pam)
+                    ))
+                    .monospace(),
+                );
+                ui.label(format!(
+                    "{}..{} {}",
+                    guide.range.start,
+                    guide.range.end,
+                    guide.direction.to_string()
+                ));
+                ui.label(format!("GC: {:.0}%", guide.gc_portion * 100.));
+                ui.label(format!("Score: {:.2}", guide.score));
+
+                if guide.poly_t {
+                    ui.label(RichText::new("Poly-T").color(Color32::LIGHT_RED));
+                }
+                if guide.repeat {
+                    ui.label(RichText::new("Repeat").color(Color32::LIGHT_YELLOW));
+                }
+                if guide.off_targets > 0 {
+                    ui.label(
+                        RichText::new(format!("Off-targets: {}", guide.off_targets))
+                            .color(Color32::LIGHT_RED),
+                    );
+                }
+
+                if ui.button("Add feature").clicked() {
+                    added.push(feature_from_guide(guide));
+                }
             });
         }
+
+        if guides.len() > GUIDE_DISP_MAX {
+            ui.label(format!(
+                "...and {} more, with lower scores.",
+                guides.len() - GUIDE_DISP_MAX
+            ));
+        }
+    }
+
+    if !added.is_empty() {
+        state.snapshot();
+        state.generic[state.active].features.extend(added);
+    }
+}
+
+fn cassette_part_text(name: &str, part: &Option<CassettePart>) -> String {
+    match part {
+        Some(p) => format!("{name}: {} ({})", p.label, disp_range(p.range)),
+        None => format!("{name}: -"),
+    }
+}
+
+/// Group promoters, RBSs, coding regions, and terminators into expression cassettes. These can be
+/// added as features.
+fn cassette_disp(state: &mut State, ui: &mut Ui) {
+    let mut added = Vec::new();
+
+    ui.horizontal(|ui| {
+        ui.heading("Expression cassettes");
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button(RichText::new("Find").color(COLOR_ACTION))
+            .on_hover_text(
+                "Find coding regions with a promoter and RBS upstream, and a terminator downstream, on \
+                the same strand. This uses existing features; annotate the sequence first. Partial \
+                cassettes are listed with their missing parts.",
+            )
+            .clicked()
+        {
+            let cassettes = find_cassettes(&state.generic[state.active].features);
+            state.volatile[state.active].cassettes = Some(cassettes);
+        }
+
+        if let Some(cassettes) = &state.volatile[state.active].cassettes {
+            if cassettes.iter().any(|c| c.complete()) && ui.button("Add complete").clicked() {
+                for cassette in cassettes.iter().filter(|c| c.complete()) {
+                    added.push(cassette.to_feature());
+                }
+            }
+        }
     });
+
+    if let Some(cassettes) = &state.volatile[state.active].cassettes {
+        if cassettes.is_empty() {
+            ui.label("No cassettes found.");
+        }
+
+        for cassette in cassettes {
+            ui.horizontal(|ui| {
+                let range = cassette.range();
+                ui.label(RichText::new(disp_range(range)).color(COLOR_ACTION));
+                ui.label(cassette.direction.to_string());
+
+                if cassette.complete() {
+                    ui.label(RichText::new("Complete").color(Color32::LIGHT_GREEN));
+                } else {
+                    ui.label(
+                        RichText::new(format!("Missing: {}", cassette.missing().join(", ")))
+                            .color(Color32::LIGHT_YELLOW),
+                    );
+                }
+
+                if !cassette.rbs_spacing_ok() {
+                    ui.label(
+                        RichText::new(format!(
+                            "RBS spacing: {} nt",
+                            cassette.rbs_spacing().unwrap_or_default()
+                        ))
+                        .color(Color32::LIGHT_RED),
+                    )
+                    .on_hover_text(
+                        "The RBS is unusually close to, or far from the start codon; this may \
+                        reduce translation.",
+                    );
+                }
+
+                if ui.button("Add feature").clicked() {
+                    added.push(cassette.to_feature());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.add_space(COL_SPACING);
+                ui.label(cassette_part_text("Promoter", &cassette.promoter));
+                ui.add_space(COL_SPACING);
+                ui.label(cassette_part_text("RBS", &cassette.rbs));
+                ui.add_space(COL_SPACING);
+                ui.label(cassette_part_text("CDS", &Some(cassette.cds.clone())));
+                ui.add_space(COL_SPACING);
+                ui.label(cassette_part_text("Terminator", &cassette.terminator));
+            });
+        }
+    }
+
+    if !added.is_empty() {
+        state.snapshot();
+        state.generic[state.active].features.extend(added);
+    }
 }
 
 pub fn features_page(state: &mut State, ui: &mut Ui) {
     ScrollArea::vertical().show(ui, |ui| {
+        delete_confirm(state, ui);
         feature_table(state, ui);
+
+        ui.add_space(ROW_SPACING);
+        inverted_repeat_disp(state, ui);
+
+        ui.add_space(ROW_SPACING);
+        guide_disp(state, ui);
+
+        ui.add_space(ROW_SPACING);
+        cassette_disp(state, ui);
     });
 }
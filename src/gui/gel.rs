@@ -0,0 +1,205 @@
+//! A virtual agarose gel of a restriction digest of the active sequence, using the enzymes selected
+//! on the digest page. Bands are positioned by log size, next to a DNA ladder.
+
+use eframe::egui::{
+    vec2, Align2, Color32, ComboBox, FontFamily, FontId, Painter, Pos2, Rect, RichText, Sense,
+    Shape, Stroke, Ui,
+};
+use na_seq::SeqTopology;
+
+use crate::{
+    gel::{cut_positions, fragment_lens, LADDERS},
+    gui::{theme::COLOR_INFO, COL_SPACING, ROW_SPACING},
+    state::State,
+    util::map_linear,
+};
+
+const GEL_WIDTH: f32 = 280.;
+const GEL_HEIGHT: f32 = 520.;
+/// Space at the top of the gel for lane labels and wells.
+const WELL_Y: f32 = 36.;
+const MARGIN_BOTTOM: f32 = 20.;
+
+const LANE_WIDTH: f32 = 56.;
+const LANE_X_LADDER: f32 = 110.;
+const LANE_X_DIGEST: f32 = 210.;
+/// Band thickness, in pixels.
+const BAND_HEIGHT: f32 = 4.;
+
+const COLOR_GEL: Color32 = Color32::from_rgb(25, 25, 40);
+const COLOR_WELL: Color32 = Color32::from_rgb(60, 60, 80);
+const COLOR_BAND: Color32 = Color32::from_rgb(240, 230, 255);
+const COLOR_LABEL: Color32 = Color32::LIGHT_GRAY;
+
+/// The vertical position of a band of this size, in pixels from the gel's top. Migration distance is
+/// roughly linear with the log of size.
+fn band_y(size: usize, size_range: (f32, f32)) -> f32 {
+    map_linear(
+        (size as f32).log10(),
+        (size_range.1.log10(), size_range.0.log10()),
+        (WELL_Y + 16., GEL_HEIGHT - MARGIN_BOTTOM),
+    )
+}
+
+fn band(lane_x: f32, y: f32, count: usize, origin: Pos2) -> Shape {
+    // Co-migrating fragments appear brighter.
+    let height = BAND_HEIGHT * (count as f32).sqrt();
+    Shape::rect_filled(
+        Rect::from_center_size(origin + vec2(lane_x, y), vec2(LANE_WIDTH, height)),
+        1.,
+        COLOR_BAND,
+    )
+}
+
+fn draw_gel(ladder: &[usize], fragments: &[usize], painter: &Painter, origin: Pos2) {
+    let mut shapes = vec![Shape::rect_filled(
+        Rect::from_min_size(origin, vec2(GEL_WIDTH, GEL_HEIGHT)),
+        4.,
+        COLOR_GEL,
+    )];
+
+    // Include fragments outside the ladder's range, so they don't run off the gel.
+    let all = ladder.iter().chain(fragments);
+    let size_min = all.clone().copied().min().unwrap_or(100) as f32 * 0.8;
+    let size_max = all.copied().max().unwrap_or(10_000) as f32 * 1.2;
+    let size_range = (size_min.max(1.), size_max);
+
+    let font = FontId::new(12., FontFamily::Proportional);
+
+    for (lane_x, label) in [(LANE_X_LADDER, "Ladder"), (LANE_X_DIGEST, "Digest")] {
+        shapes.push(Shape::rect_filled(
+            Rect::from_center_size(origin + vec2(lane_x, WELL_Y), vec2(LANE_WIDTH, 6.)),
+            1.,
+            COLOR_WELL,
+        ));
+        shapes.push(painter.fonts(|f| {
+            Shape::text(
+                f,
+                origin + vec2(lane_x, WELL_Y - 20.),
+                Align2::CENTER_CENTER,
+                label,
+                font.clone(),
+                COLOR_LABEL,
+            )
+        }));
+    }
+
+    for size in ladder {
+        let y = band_y(*size, size_range);
+        shapes.push(band(LANE_X_LADDER, y, 1, origin));
+        shapes.push(painter.fonts(|f| {
+            Shape::text(
+                f,
+                origin + vec2(LANE_X_LADDER - LANE_WIDTH / 2. - 8., y),
+                Align2::RIGHT_CENTER,
+                size.to_string(),
+                font.clone(),
+                COLOR_LABEL,
+            )
+        }));
+    }
+
+    let mut i = 0;
+    while i < fragments.len() {
+        // Fragments are sorted; group those of equal size.
+        let count = fragments[i..]
+            .iter()
+            .take_while(|f| **f == fragments[i])
+            .count();
+        shapes.push(band(
+            LANE_X_DIGEST,
+            band_y(fragments[i], size_range),
+            count,
+            origin,
+        ));
+        i += count;
+    }
+
+    // Outline the gel.
+    shapes.push(Shape::rect_stroke(
+        Rect::from_min_size(origin, vec2(GEL_WIDTH, GEL_HEIGHT)),
+        4.,
+        Stroke::new(1., COLOR_WELL),
+    ));
+
+    painter.extend(shapes);
+}
+
+pub fn gel_page(state: &mut State, ui: &mut Ui) {
+    let data = &state.generic[state.active];
+    let seq_len = data.seq.len();
+
+    let cuts = cut_positions(
+        &state.ui.re.res_selected,
+        &state.volatile[state.active].restriction_enzyme_matches,
+        &state.restriction_enzyme_lib,
+        seq_len,
+    );
+    let fragments = fragment_lens(&cuts, seq_len, data.topology);
+
+    ui.add_space(ROW_SPACING);
+    ui.horizontal(|ui| {
+        ui.heading("Virtual digest");
+        ui.add_space(COL_SPACING);
+
+        ui.label("Ladder:");
+        ComboBox::from_id_salt(4_800)
+            .width(80.)
+            .selected_text(state.ui.gel.ladder.to_str())
+            .show_ui(ui, |ui| {
+                for ladder in LADDERS {
+                    ui.selectable_value(&mut state.ui.gel.ladder, ladder, ladder.to_str());
+                }
+            });
+        ui.add_space(COL_SPACING);
+
+        if state.ui.re.res_selected.is_empty() {
+            ui.label("Select enzymes on the Digest page to digest this sequence with.");
+        } else {
+            let names: Vec<_> = state
+                .ui
+                .re
+                .res_selected
+                .iter()
+                .map(|re| re.name.as_str())
+                .collect();
+            ui.label(RichText::new(names.join(", ")).color(COLOR_INFO));
+        }
+    });
+    ui.add_space(ROW_SPACING);
+
+    if seq_len == 0 {
+        return;
+    }
+
+    ui.horizontal_top(|ui| {
+        let (response, painter) = ui.allocate_painter(vec2(GEL_WIDTH, GEL_HEIGHT), Sense::hover());
+        draw_gel(
+            state.ui.gel.ladder.sizes(),
+            &fragments,
+            &painter,
+            response.rect.min,
+        );
+
+        ui.add_space(COL_SPACING);
+
+        ui.vertical(|ui| {
+            ui.label(format!(
+                "{} fragment(s), from {} cut(s)",
+                fragments.len(),
+                cuts.len()
+            ));
+            for size in &fragments {
+                ui.label(RichText::new(format!("{size} bp")).color(COLOR_INFO));
+            }
+
+            if cuts.is_empty() && state.generic[state.active].topology == SeqTopology::Circular {
+                ui.add_space(ROW_SPACING);
+                ui.label(
+                    "This plasmid isn't cut. Uncut circular DNA is mostly supercoiled, and runs \
+                    faster than linear DNA of the same size.",
+                );
+            }
+        });
+    });
+}
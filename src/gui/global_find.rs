@@ -0,0 +1,167 @@
+//! A window to find features and sequence motifs across all open tabs.
+
+use eframe::egui::{self, Color32, Context, RichText, ScrollArea, TextEdit, Ui};
+use na_seq::seq_to_str_lower;
+
+use crate::{
+    global_find::{global_find, GlobalHit, HitType},
+    gui::{
+        navigation::{get_tab_names, Page, PageSeq},
+        set_window_title,
+        theme::COLOR_ACTION,
+        COL_SPACING, ROW_SPACING,
+    },
+    state::State,
+    util::disp_range,
+    Selection,
+};
+
+const RESULTS_HEIGHT: f32 = 400.;
+
+pub fn global_find_window(state: &mut State, ctx: &Context) {
+    let mut open = state.ui.global_find.show;
+
+    egui::Window::new("Find in all tabs")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let mut run = false;
+
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut state.ui.global_find.text)
+                        .hint_text("Feature label, or sequence")
+                        .desired_width(200.),
+                );
+                if response.gained_focus() {
+                    // Disable character entries in the sequence.
+                    state.ui.text_edit_active = true;
+                }
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    run = true;
+                }
+
+                if ui
+                    .button(RichText::new("🔍 Find").color(COLOR_ACTION))
+                    .clicked()
+                {
+                    run = true;
+                }
+
+                ui.add_space(COL_SPACING);
+                ui.label("Min identity:");
+                let mut entry = format!("{:.0}", state.ui.global_find.min_identity * 100.);
+                if ui
+                    .add(TextEdit::singleline(&mut entry).desired_width(24.))
+                    .on_hover_text(
+                        "For sequence searches, the minimum percent of nucleotides that must match.",
+                    )
+                    .changed()
+                {
+                    if let Ok(v) = entry.parse::<f32>() {
+                        state.ui.global_find.min_identity = (v / 100.).clamp(0.5, 1.);
+                    }
+                }
+                ui.label("%");
+            });
+
+            if run {
+                state.ui.global_find.hits = Some(global_find(
+                    &state.generic,
+                    &state.ui.global_find.text,
+                    state.ui.global_find.min_identity,
+                ));
+            }
+
+            let hits = match &state.ui.global_find.hits {
+                Some(h) => h,
+                None => return,
+            };
+
+            ui.add_space(ROW_SPACING / 2.);
+
+            if hits.is_empty() {
+                ui.label("No matches in any open tab.");
+                return;
+            }
+
+            let plasmid_names: Vec<_> = state
+                .generic
+                .iter()
+                .map(|v| v.metadata.plasmid_name.as_str())
+                .collect();
+            let tab_names = get_tab_names(&state.tabs_open, &plasmid_names, true);
+
+            let mut jump_to = None;
+            ScrollArea::vertical()
+                .id_salt(204)
+                .max_height(RESULTS_HEIGHT)
+                .show(ui, |ui| {
+                    let mut tab_prev = None;
+                    for (i, hit) in hits.iter().enumerate() {
+                        // Results are ordered by tab; group them under a heading.
+                        if tab_prev != Some(hit.tab) {
+                            let name = tab_names
+                                .get(hit.tab)
+                                .map(|(n, _)| n.clone())
+                                .unwrap_or_default();
+                            let count = hits.iter().filter(|h| h.tab == hit.tab).count();
+                            ui.add_space(ROW_SPACING / 2.);
+                            ui.label(
+                                RichText::new(format!("{name}: {count}"))
+                                    .color(Color32::WHITE)
+                                    .strong(),
+                            );
+                            tab_prev = Some(hit.tab);
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(disp_range(hit.range))
+                                .on_hover_text("Open this tab, and view the match.")
+                                .clicked()
+                            {
+                                jump_to = Some(i);
+                            }
+                            ui.label(&hit.description);
+                        });
+                    }
+                });
+
+            if let Some(i) = jump_to {
+                let hit = hits[i].clone();
+                jump_to_hit(state, &hit, ui);
+            }
+        });
+
+    state.ui.global_find.show = open;
+}
+
+/// Open the hit's tab, select the match, and scroll the sequence view to it.
+fn jump_to_hit(state: &mut State, hit: &GlobalHit, ui: &mut Ui) {
+    // Tabs may have been closed since the search.
+    if hit.tab >= state.generic.len() {
+        return;
+    }
+
+    state.active = hit.tab;
+    set_window_title(&state.tabs_open[hit.tab], ui);
+    state.alignment.clear_tracks();
+    state.ui.seq_input = seq_to_str_lower(state.get_seq());
+
+    match hit.hit_type {
+        HitType::Feature(feature_i) => {
+            state.ui.selected_item = Selection::Feature(feature_i);
+            state.ui.text_selection = None;
+        }
+        HitType::Motif => {
+            state.ui.selected_item = Selection::None;
+            state.ui.text_selection = Some(hit.range);
+        }
+    }
+
+    state.ui.text_cursor_i = Some(hit.range.start);
+    state.ui.scroll_to_seq_i = Some(hit.range.start);
+    state.ui.page = Page::Sequence;
+    state.ui.page_seq = PageSeq::View;
+}
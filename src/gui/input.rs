@@ -3,9 +3,10 @@
 use std::{mem, path::PathBuf};
 
 use eframe::egui::{Event, InputState, Key, PointerButton, Ui};
-use na_seq::{seq_from_str, Nucleotide};
+use na_seq::Nucleotide;
 
 use crate::{
+    ambiguous,
     file_io::{
         save,
         save::{load_import, StateToSave, QUICKSAVE_FILE},
@@ -15,8 +16,8 @@ use crate::{
         set_window_title,
     },
     state::State,
-    util::RangeIncl,
-    StateUi,
+    util::{seq_from_pasted, RangeIncl},
+    Selection, StateUi,
 };
 
 /// Handle hotkeys and clicks that affect all pages.
@@ -62,10 +63,84 @@ fn handle_global(state: &mut State, ip: &InputState) {
         // }
 
         state.ui.click_pending_handle = true;
+        state.ui.click_multi_select = ip.modifiers.ctrl || ip.modifiers.shift;
     }
 
-    if ip.pointer.button_double_clicked(PointerButton::Primary) {
-        state.ui.dblclick_pending_handle = true;
+    // Reset each frame, so a double-click a page didn't handle, e.g. outside its canvas, isn't handled
+    // later.
+    state.ui.dblclick_pending_handle = ip.pointer.button_double_clicked(PointerButton::Primary);
+}
+
+/// Undo with Ctrl + Z, and redo with Ctrl + Y or Ctrl + Shift + Z. Text fields handle these keys
+/// themselves while focused.
+fn handle_undo(state: &mut State, ip: &InputState) {
+    if !ip.modifiers.ctrl {
+        return;
+    }
+
+    if ip.key_pressed(Key::Y) || (ip.key_pressed(Key::Z) && ip.modifiers.shift) {
+        state.redo();
+    } else if ip.key_pressed(Key::Z) {
+        state.undo();
+    }
+}
+
+/// Keyboard navigation of the feature and primer tables: Arrow keys move the selected row, Enter shows the
+/// selection on the map, and Delete requests its removal.
+fn handle_table_nav(state: &mut State, ip: &InputState) {
+    let len = match state.ui.page {
+        Page::Features => state.generic[state.active].features.len(),
+        Page::Primers => state.generic[state.active].primers.len(),
+        _ => return,
+    };
+
+    if len == 0 {
+        return;
+    }
+
+    let selected = match (state.ui.page, state.ui.selected_item) {
+        (Page::Features, Selection::Feature(i)) | (Page::Primers, Selection::Primer(i)) => Some(i),
+        _ => None,
+    };
+
+    let mut new_sel = selected;
+    if ip.key_pressed(Key::ArrowDown) {
+        new_sel = match selected {
+            Some(i) => Some((i + 1).min(len - 1)),
+            None => Some(0),
+        };
+    }
+    if ip.key_pressed(Key::ArrowUp) {
+        new_sel = match selected {
+            Some(i) => Some(i.saturating_sub(1)),
+            None => Some(len - 1),
+        };
+    }
+
+    if new_sel != selected {
+        if let Some(i) = new_sel {
+            state.ui.selected_item = match state.ui.page {
+                Page::Features => Selection::Feature(i),
+                _ => Selection::Primer(i),
+            };
+            state.ui.delete_pending = false;
+        }
+    }
+
+    if selected.is_none() {
+        return;
+    }
+
+    if ip.key_pressed(Key::Enter) {
+        state.ui.page = Page::Map;
+    }
+
+    if ip.key_pressed(Key::Delete) {
+        state.ui.delete_pending = true;
+    }
+
+    if ip.key_pressed(Key::Escape) {
+        state.ui.delete_pending = false;
     }
 }
 
@@ -110,6 +185,9 @@ fn handle_seq_selection(state_ui: &mut StateUi, dragging: bool) {
 pub fn handle_input(state: &mut State, ui: &mut Ui) {
     let mut reset_window_title = false; // This setup avoids borrow errors.
 
+    // Don't navigate tables while the user is typing into a field.
+    let widget_focused = ui.ctx().memory(|m| m.focused().is_some());
+
     ui.ctx().input(|ip| {
         // Check for file drop
         if let Some(dropped_files) = ip.raw.dropped_files.first() {
@@ -137,6 +215,11 @@ pub fn handle_input(state: &mut State, ui: &mut Ui) {
             }
         }
 
+        if !widget_focused {
+            handle_table_nav(state, ip);
+            handle_undo(state, ip);
+        }
+
         if let Page::Sequence = state.ui.page {
             // This is a bit awk; borrow errors.
             let mut move_cursor: Option<i32> = None;
@@ -189,11 +272,12 @@ pub fn handle_input(state: &mut State, ui: &mut Ui) {
                     if ip.key_pressed(Key::G) {
                         state.insert_nucleotides(&[Nucleotide::G], i);
                     }
+                    // `i` is the nucleotide after the cursor.
                     if ip.key_pressed(Key::Backspace) && i > 1 {
-                        state.remove_nucleotides(RangeIncl::new(i - 2, i - 2));
+                        state.remove_nucleotides(RangeIncl::new(i - 1, i - 1));
                     }
                     if ip.key_pressed(Key::Delete) {
-                        state.remove_nucleotides(RangeIncl::new(i - 1, i - 1));
+                        state.remove_nucleotides(RangeIncl::new(i, i));
                     }
 
                     // Paste nucleotides
@@ -205,8 +289,16 @@ pub fn handle_input(state: &mut State, ui: &mut Ui) {
                             }
                             Event::Copy => {}
                             Event::Paste(pasted_text) => {
-                                state.insert_nucleotides(&seq_from_str(pasted_text), i);
-                                move_cursor = Some(pasted_text.len() as i32);
+                                // Move the cursor by the nucleotides inserted, vice the raw text,
+                                // which may include numbers and whitespace.
+                                let (seq, ambiguous) = seq_from_pasted(pasted_text);
+                                state.insert_nucleotides(&seq, i);
+                                ambiguous::insert(
+                                    &mut state.generic[state.active].ambiguous,
+                                    &ambiguous,
+                                    i - 1,
+                                );
+                                move_cursor = Some(seq.len() as i32);
                             }
                             _ => (),
                         }
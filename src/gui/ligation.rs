@@ -2,30 +2,37 @@
 
 use std::{collections::HashMap, path::PathBuf};
 
+use copypasta::{ClipboardContext, ClipboardProvider};
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, RichText, ScrollArea,
-        Sense, Shape, Stroke, Ui,
+        pos2, vec2, Align2, Color32, ComboBox, FontFamily, FontId, Frame, Pos2, Rect, RichText,
+        ScrollArea, Sense, Shape, Stroke, Ui,
     },
     emath::RectTransform,
 };
 use na_seq::{
     ligation,
     ligation::{digest, LigationFragment},
+    restriction_enzyme::RestrictionEnzyme,
     seq_to_str_lower,
 };
 
 use crate::{
+    concatenate::{concatenate, reverse_complement},
     gui::{
         circle::{FEATURE_OUTLINE_COLOR, FEATURE_STROKE_WIDTH},
+        features_cut_by_re,
         lin_maps::seq_lin_disp,
-        navigation::{get_tab_names, Tab},
-        select_color_text,
-        theme::COLOR_ACTION,
+        navigation::{get_tab_names, Page, PageSeq, Tab},
+        re_cut_index, re_cut_text, re_cuts_coding, select_color_text,
+        theme::{palette, COLOR_ACTION},
         BACKGROUND_COLOR, COL_SPACING, ROW_SPACING,
     },
+    oligo_anneal::{anneal_primers, re_ends, re_fragment, DuplexEnd},
+    re_buffers::{recommend_buffer, BufferRecommendation, MIN_ACTIVITY},
+    re_groups::find_relations,
     state::State,
-    util::{filter_res, map_linear, name_from_path},
+    util::{disp_i, filter_res, map_linear, name_from_path},
 };
 
 // This X offset must have room for the RE Nts displayed on the left.
@@ -44,6 +51,37 @@ const SEQ_COLORS: [Color32; 4] = [
     Color32::LIGHT_GRAY,
 ];
 
+/// Show which buffer to use for digesting with the selected enzymes together, or that they should be
+/// used sequentially.
+pub fn buffer_rec_disp(res: &[RestrictionEnzyme], ui: &mut Ui) {
+    match recommend_buffer(res) {
+        BufferRecommendation::None => (),
+        BufferRecommendation::Unknown(names) => {
+            ui.label(format!("No buffer data for {}", names.join(", ")));
+        }
+        BufferRecommendation::Single { buffer, activity } => {
+            ui.label("Double digest buffer:");
+            ui.label(
+                RichText::new(format!("{} ({activity}% activity)", buffer.to_str()))
+                    .color(Color32::LIGHT_GREEN),
+            );
+        }
+        BufferRecommendation::Sequential(steps) => {
+            let steps: Vec<_> = steps
+                .iter()
+                .map(|(name, buffer)| format!("{name} in {}", buffer.to_str()))
+                .collect();
+            ui.label(
+                RichText::new(format!("Digest sequentially: {}", steps.join(", then ")))
+                    .color(Color32::GOLD),
+            )
+            .on_hover_text(format!(
+                "No single buffer gives more than {MIN_ACTIVITY}% activity for all selected enzymes."
+            ));
+        }
+    }
+}
+
 /// Draw a graphical depiction of digestion products.
 fn draw_graphics(products: &[LigationFragment], seq_len: usize, ui: &mut Ui) {
     Frame::canvas(ui.style())
@@ -295,6 +333,451 @@ fn tab_selection(
 //     }
 // }
 
+/// Anneal two of the active tab's primers (or oligos) into a double-stranded product.
+fn anneal_disp(state: &mut State, ui: &mut Ui) {
+    ui.heading("Oligo annealing");
+    ui.add_space(ROW_SPACING / 2.);
+
+    let primers = &state.generic[state.active].primers;
+    if primers.len() < 2 {
+        ui.label("Add at least two primers to this sequence to anneal them.");
+        return;
+    }
+
+    if state.ui.anneal.primer_top >= primers.len() {
+        state.ui.anneal.primer_top = 0;
+    }
+    if state.ui.anneal.primer_bottom >= primers.len() {
+        state.ui.anneal.primer_bottom = 0;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Top oligo:");
+        ComboBox::from_id_salt(3_300)
+            .width(120.)
+            .selected_text(&primers[state.ui.anneal.primer_top].name)
+            .show_ui(ui, |ui| {
+                for (i, primer) in primers.iter().enumerate() {
+                    ui.selectable_value(&mut state.ui.anneal.primer_top, i, &primer.name);
+                }
+            });
+        ui.add_space(COL_SPACING);
+
+        ui.label("Bottom oligo:");
+        ComboBox::from_id_salt(3_301)
+            .width(120.)
+            .selected_text(&primers[state.ui.anneal.primer_bottom].name)
+            .show_ui(ui, |ui| {
+                for (i, primer) in primers.iter().enumerate() {
+                    ui.selectable_value(&mut state.ui.anneal.primer_bottom, i, &primer.name);
+                }
+            });
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button(RichText::new("Anneal").color(COLOR_ACTION))
+            .on_hover_text("Find the best alignment between the top oligo, and the bottom oligo's reverse complement.")
+            .clicked()
+        {
+            state.ui.anneal.result = anneal_primers(
+                &primers[state.ui.anneal.primer_top],
+                &primers[state.ui.anneal.primer_bottom],
+            );
+        }
+    });
+
+    let mut open_tab = false;
+    if let Some(duplex) = &state.ui.anneal.result {
+        ui.add_space(ROW_SPACING / 2.);
+        ui.label(RichText::new(duplex.diagram()).monospace());
+        ui.add_space(ROW_SPACING / 2.);
+
+        ui.horizontal(|ui| {
+            let (text, color) = if duplex.complementary() {
+                ("Complementary", Color32::LIGHT_GREEN)
+            } else {
+                ("Not complementary", Color32::LIGHT_RED)
+            };
+            ui.label(RichText::new(text).color(color));
+            ui.add_space(COL_SPACING);
+
+            ui.label(format!("Overlap: {} bp", duplex.overlap_len));
+            ui.add_space(COL_SPACING);
+            ui.label(format!("Mismatches: {}", duplex.mismatches));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Left end: {}", duplex.left));
+            ui.add_space(COL_SPACING);
+            ui.label(format!("Right end: {}", duplex.right));
+            ui.add_space(COL_SPACING);
+
+            if ui
+                .button(RichText::new("Open in new tab").color(COLOR_ACTION))
+                .on_hover_text("Create a new sequence from the annealed product, including both strands' overhangs.")
+                .clicked()
+            {
+                open_tab = true;
+            }
+        });
+    }
+
+    if open_tab {
+        let generic = match &state.ui.anneal.result {
+            Some(d) => d.to_generic("Annealed oligos"),
+            None => return,
+        };
+
+        state.add_tab();
+        state.tabs_open.push(Default::default());
+        state.generic[state.active] = generic;
+        state.volatile[state.active].unsaved = true; // Not yet saved to a file.
+
+        state.sync_seq_related(None);
+
+        state.ui.page = Page::Sequence;
+        state.ui.page_seq = PageSeq::View;
+    }
+}
+
+/// The ends an enzyme leaves, described by the overhang on the top strand.
+fn overhang_text(re: &RestrictionEnzyme) -> String {
+    match re_ends(re) {
+        // The top strand overhangs on the fragment downstream of a 5' cut, and upstream of a 3' one.
+        Some((left @ DuplexEnd::Overhang5p(_), _)) => left.to_string(),
+        Some((_, right)) => right.to_string(),
+        None => "Overhang: degenerate".to_owned(),
+    }
+}
+
+/// List the cut positions of each selected enzyme in the active sequence, with its recognition
+/// sequence and overhang. Click a position to view it in the sequence, or right-click to view it on
+/// the map.
+fn re_cut_list(state: &mut State, ui: &mut Ui) {
+    let seq_len = state.generic[state.active].seq.len();
+    let re_matches = &state.volatile[state.active].restriction_enzyme_matches;
+
+    // (Enzyme, cut positions)
+    let mut cuts: Vec<(&RestrictionEnzyme, Vec<usize>)> = Vec::new();
+    for re in &state.ui.re.res_selected {
+        let mut positions = Vec::new();
+        for re_match in re_matches {
+            if re_match.lib_index >= state.restriction_enzyme_lib.len()
+                || state.restriction_enzyme_lib[re_match.lib_index].name != re.name
+            {
+                continue;
+            }
+
+            positions.push(re_cut_index(re_match, re, seq_len));
+        }
+        cuts.push((re, positions));
+    }
+
+    if cuts.is_empty() {
+        return;
+    }
+
+    let mut jump_to = None;
+    let mut to_map = false;
+
+    ui.horizontal(|ui| {
+        ui.heading("Cut sites");
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button("📋 Copy")
+            .on_hover_text("Copy the enzymes and cut positions as text, e.g. for a protocol.")
+            .clicked()
+        {
+            let mut text = String::new();
+            for (re, positions) in &cuts {
+                let positions: Vec<String> =
+                    positions.iter().map(|p| disp_i(*p).to_string()).collect();
+                text += &format!(
+                    "{}\t{}\t{}\n",
+                    re.name,
+                    re.cut_depiction(),
+                    positions.join(", ")
+                );
+            }
+            let mut ctx = ClipboardContext::new().unwrap();
+            ctx.set_contents(text).unwrap();
+        }
+    });
+    ui.add_space(ROW_SPACING / 2.);
+
+    for (re, positions) in &cuts {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(&re.name).color(Color32::WHITE));
+            ui.label(re.cut_depiction());
+
+            ui.label(overhang_text(re));
+            ui.add_space(COL_SPACING);
+
+            if positions.is_empty() {
+                ui.label("No sites in this sequence");
+            }
+
+            for posit in positions {
+                let button = ui.button(disp_i(*posit).to_string()).on_hover_text(
+                    "Click to view in the sequence. Right-click to view on the map.",
+                );
+                if button.clicked() {
+                    jump_to = Some(*posit);
+                } else if button.secondary_clicked() {
+                    jump_to = Some(*posit);
+                    to_map = true;
+                }
+            }
+        });
+
+        re_relations_disp(re, &state.restriction_enzyme_lib, ui);
+    }
+
+    if let Some(posit) = jump_to {
+        state.ui.re.cut_pointed = Some(posit);
+        state.ui.text_cursor_i = Some(posit);
+        state.ui.scroll_to_seq_i = Some(posit);
+        if to_map {
+            state.ui.page = Page::Map;
+        } else {
+            state.ui.page = Page::Sequence;
+            state.ui.page_seq = PageSeq::View;
+        }
+    }
+}
+
+/// List enzymes that could substitute for this one: Isoschizomers, and enzymes that leave compatible ends.
+fn re_relations_disp(re: &RestrictionEnzyme, lib: &[RestrictionEnzyme], ui: &mut Ui) {
+    let relations = find_relations(re, lib);
+    let names = |indices: &[usize]| {
+        indices
+            .iter()
+            .map(|i| lib[*i].name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    ui.horizontal_wrapped(|ui| {
+        ui.add_space(COL_SPACING);
+
+        if !relations.isoschizomers.is_empty() {
+            ui.label("Isoschizomers:")
+                .on_hover_text("Recognize the same site, and cut at the same position.");
+            ui.label(RichText::new(names(&relations.isoschizomers)).color(Color32::WHITE));
+            ui.add_space(COL_SPACING);
+        }
+
+        if !relations.neoschizomers.is_empty() {
+            ui.label("Neoschizomers:")
+                .on_hover_text("Recognize the same site, but cut at a different position.");
+            ui.label(RichText::new(names(&relations.neoschizomers)).color(Color32::WHITE));
+            ui.add_space(COL_SPACING);
+        }
+
+        if matches!(re_ends(re), Some((DuplexEnd::Blunt, _))) {
+            ui.label("Compatible: Any blunt cutter");
+        } else if !relations.compatible.is_empty() {
+            ui.label("Compatible ends:").on_hover_text(
+                "Different sites, but leave overhangs that ligate to this enzyme's.",
+            );
+            ui.label(RichText::new(names(&relations.compatible)).color(Color32::WHITE));
+        }
+    });
+}
+
+/// Select two restriction sites, and copy the fragment between them, including the overhangs left by
+/// each enzyme.
+fn re_fragment_disp(state: &mut State, ui: &mut Ui) {
+    ui.heading("Restriction fragment");
+    ui.add_space(ROW_SPACING / 2.);
+
+    let re_matches = &state.volatile[state.active].restriction_enzyme_matches;
+    if re_matches.is_empty() {
+        ui.label("Select restriction enzymes to find their sites in this sequence.");
+        return;
+    }
+
+    let data = &mut state.ui.re_fragment;
+    if data.site_left >= re_matches.len() {
+        data.site_left = 0;
+    }
+    if data.site_right >= re_matches.len() {
+        data.site_right = 0;
+    }
+
+    let seq_len = state.generic[state.active].seq.len();
+    let re_lib = &state.restriction_enzyme_lib;
+    let site_text = |i: usize| {
+        let re_match = &re_matches[i];
+        match re_lib.get(re_match.lib_index) {
+            Some(re) => format!(
+                "{} {}",
+                re.name,
+                disp_i(re_cut_index(re_match, re, seq_len))
+            ),
+            None => String::new(),
+        }
+    };
+
+    let mut copy = false;
+    ui.horizontal(|ui| {
+        for (label, site, id) in [
+            ("Left site:", &mut data.site_left, 4_200),
+            ("Right site:", &mut data.site_right, 4_201),
+        ] {
+            ui.label(label);
+            ComboBox::from_id_salt(id)
+                .width(120.)
+                .selected_text(site_text(*site))
+                .show_ui(ui, |ui| {
+                    for i in 0..re_matches.len() {
+                        ui.selectable_value(site, i, site_text(i));
+                    }
+                });
+            ui.add_space(COL_SPACING);
+        }
+
+        if ui
+            .button(RichText::new("📋 Copy").color(COLOR_ACTION))
+            .on_hover_text("Copy the fragment as text, showing both strands and their overhangs.")
+            .clicked()
+        {
+            copy = true;
+        }
+    });
+
+    let fragment = re_fragment(
+        &state.generic[state.active].seq,
+        state.generic[state.active].topology,
+        &re_matches[data.site_left],
+        &re_matches[data.site_right],
+        re_lib,
+    );
+
+    if let Some(fragment) = &fragment {
+        ui.add_space(ROW_SPACING / 2.);
+        ui.horizontal(|ui| {
+            ui.label(format!("Top strand: {} nt", fragment.top.len()));
+            ui.add_space(COL_SPACING);
+            ui.label(format!("Left end: {}", fragment.left));
+            ui.add_space(COL_SPACING);
+            ui.label(format!("Right end: {}", fragment.right));
+            ui.add_space(COL_SPACING);
+
+            if fragment.right.ligates_with(&fragment.left) {
+                ui.label(RichText::new("Ends are compatible").color(Color32::GREEN))
+                    .on_hover_text("The fragment's ends can be ligated to each other, e.g. to re-circularize it.");
+            } else {
+                ui.label("Ends are not compatible");
+            }
+        });
+
+        if copy {
+            let mut ctx = ClipboardContext::new().unwrap();
+            ctx.set_contents(fragment.diagram()).unwrap();
+        }
+    } else {
+        ui.label("No fragment between these sites.");
+    }
+}
+
+/// Join two open sequences end to end, optionally reverse-complementing either, and open the result.
+fn concat_disp(state: &mut State, ui: &mut Ui) {
+    ui.heading("Concatenate");
+    ui.add_space(ROW_SPACING / 2.);
+
+    let tab_count = state.generic.len();
+    if state.ui.concat.tab_first >= tab_count {
+        state.ui.concat.tab_first = 0;
+    }
+    if state.ui.concat.tab_second >= tab_count {
+        state.ui.concat.tab_second = 0;
+    }
+
+    let plasmid_names: &Vec<_> = &state
+        .generic
+        .iter()
+        .map(|v| v.metadata.plasmid_name.as_str())
+        .collect();
+    let tab_names = get_tab_names(&state.tabs_open, plasmid_names, true);
+
+    let mut open_tab = false;
+    ui.horizontal(|ui| {
+        let data = &mut state.ui.concat;
+        for (label, tab, rc, id) in [
+            ("First:", &mut data.tab_first, &mut data.rc_first, 4_100),
+            ("Second:", &mut data.tab_second, &mut data.rc_second, 4_101),
+        ] {
+            ui.label(label);
+            ComboBox::from_id_salt(id)
+                .width(120.)
+                .selected_text(&tab_names[*tab].0)
+                .show_ui(ui, |ui| {
+                    for (name, i) in &tab_names {
+                        ui.selectable_value(tab, *i, name);
+                    }
+                });
+
+            ui.label("Rev comp:");
+            ui.checkbox(rc, "");
+            ui.add_space(COL_SPACING);
+        }
+
+        if ui
+            .button("⬌ Swap")
+            .on_hover_text("Swap the order of the sequences.")
+            .clicked()
+        {
+            std::mem::swap(&mut data.tab_first, &mut data.tab_second);
+            std::mem::swap(&mut data.rc_first, &mut data.rc_second);
+        }
+        ui.add_space(COL_SPACING);
+
+        let len =
+            state.generic[data.tab_first].seq.len() + state.generic[data.tab_second].seq.len();
+        ui.label(format!("Product: {len} bp"));
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button(RichText::new("Open in new tab").color(COLOR_ACTION))
+            .on_hover_text(
+                "Create a new linear sequence, with the second sequence appended to the first. \
+                Features, primers, and bookmarks from both are included.",
+            )
+            .clicked()
+        {
+            open_tab = true;
+        }
+    });
+
+    if open_tab {
+        let data = &state.ui.concat;
+
+        let mut first = state.generic[data.tab_first].clone();
+        if data.rc_first {
+            first = reverse_complement(&first);
+        }
+        let mut second = state.generic[data.tab_second].clone();
+        if data.rc_second {
+            second = reverse_complement(&second);
+        }
+
+        let generic = concatenate(&first, &second);
+
+        state.add_tab();
+        state.tabs_open.push(Default::default());
+        state.generic[state.active] = generic;
+        state.volatile[state.active].unsaved = true; // Not yet saved to a file.
+
+        state.sync_seq_related(None);
+        state.reset_selections();
+
+        state.ui.page = Page::Sequence;
+        state.ui.page_seq = PageSeq::View;
+    }
+}
+
 pub fn ligation_page(state: &mut State, ui: &mut Ui) {
     // todo: Scrolling is not working
     ScrollArea::vertical().id_salt(100).show(ui, |ui| {
@@ -339,6 +822,16 @@ pub fn ligation_page(state: &mut State, ui: &mut Ui) {
 
             ui.add_space(COL_SPACING);
 
+            ui.label("Non-cutters:").on_hover_text("Show restriction enzymes that don't cut any selected sequence, vice ones that do. For example, to choose enzymes that won't cut an insert.");
+            ui.checkbox(&mut state.ui.re.non_cutters, "");
+
+            ui.add_space(COL_SPACING);
+
+            ui.label("Highlight cuts in coding regions:").on_hover_text("Color sites that cut within a coding region differently on the maps. (Affects display on other pages as well).");
+            ui.checkbox(&mut state.ui.re.highlight_in_coding, "");
+
+            ui.add_space(COL_SPACING);
+
             // If we've c fmt
             // hanged filters, update REs selected IRT these filtersx.
             if changed_filters {
@@ -377,10 +870,17 @@ pub fn ligation_page(state: &mut State, ui: &mut Ui) {
         // todo: Highlight common (and later, compatible) RE matches among fragments.
 
         ui.horizontal(|ui| {
-            ui.heading("Restriction enzymes matched");
-            ui.add_space(COL_SPACING);
+            if state.ui.re.non_cutters {
+                ui.heading("Restriction enzymes that don't cut");
+                ui.add_space(COL_SPACING);
 
-            ui.label("Click to select for digestion.");
+                ui.label(format!("{} enzymes", res_matched.len()));
+            } else {
+                ui.heading("Restriction enzymes matched");
+                ui.add_space(COL_SPACING);
+
+                ui.label("Click to select for digestion.");
+            }
         });
 
         let res_per_row = 8; // todo: Based on screen width etc.
@@ -397,7 +897,37 @@ pub fn ligation_page(state: &mut State, ui: &mut Ui) {
 
                     let selected = state.ui.re.res_selected.contains(&re);
 
-                    if ui.button(select_color_text(&re.name, selected)).clicked {
+                    // Note which features this enzyme cuts within, in each selected sequence.
+                    let mut cuts_text = Vec::new();
+                    let mut cuts_coding = false;
+                    for active in &state.ui.re.tabs_selected {
+                        let features = &state.generic[*active].features;
+                        for re_match in &state.volatile[*active].restriction_enzyme_matches {
+                            if re_match.lib_index >= state.restriction_enzyme_lib.len()
+                                || state.restriction_enzyme_lib[re_match.lib_index].name != re.name
+                            {
+                                continue;
+                            }
+
+                            let seq_len = state.generic[*active].seq.len();
+                            let features_cut = features_cut_by_re(re_match, re, features, seq_len);
+                            if re_cuts_coding(&features_cut, features) {
+                                cuts_coding = true;
+                            }
+                            if let Some(text) = re_cut_text(&features_cut, features) {
+                                if !cuts_text.contains(&text) {
+                                    cuts_text.push(text);
+                                }
+                            }
+                        }
+                    }
+
+                    let mut button = ui.button(select_color_text(&re.name, selected));
+                    if !cuts_text.is_empty() {
+                        button = button.on_hover_text(cuts_text.join("\n"));
+                    }
+
+                    if button.clicked() {
                         if selected {
                             for (i, re_sel) in state.ui.re.res_selected.iter().enumerate() {
                                 if re_sel == re {
@@ -410,12 +940,23 @@ pub fn ligation_page(state: &mut State, ui: &mut Ui) {
                         }
                     }
                     ui.label(re.cut_depiction());
+
+                    if cuts_coding {
+                        ui.label(RichText::new("⚠").color(palette().re_in_coding))
+                            .on_hover_text("Cuts within a coding region");
+                    }
                     ui.add_space(COL_SPACING / 2.);
                 }
                 ui.add_space(COL_SPACING);
             });
         }
 
+        ui.horizontal(|ui| {
+            buffer_rec_disp(&state.ui.re.res_selected, ui);
+        });
+        ui.add_space(ROW_SPACING);
+
+        re_cut_list(state, ui);
         ui.add_space(ROW_SPACING);
 
         ui.horizontal(|ui| {
@@ -463,6 +1004,15 @@ pub fn ligation_page(state: &mut State, ui: &mut Ui) {
             }
         });
 
+        anneal_disp(state, ui);
+        ui.add_space(ROW_SPACING);
+
+        re_fragment_disp(state, ui);
+        ui.add_space(ROW_SPACING);
+
+        concat_disp(state, ui);
+        ui.add_space(ROW_SPACING);
+
         // Display the digestion products,
         draw_graphics(
             &state.volatile[state.active].re_digestion_products,
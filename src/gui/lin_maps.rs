@@ -2,23 +2,27 @@
 
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, Sense, Shape, Stroke,
-        Ui,
+        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, RichText, Sense, Shape,
+        Stroke, Ui, Vec2,
     },
     emath::RectTransform,
 };
-use na_seq::restriction_enzyme::{ReMatch, RestrictionEnzyme};
+use na_seq::{
+    restriction_enzyme::{ReMatch, RestrictionEnzyme},
+    SeqTopology,
+};
 
 use crate::{
     file_io::GenericData,
     gui::{
-        circle::{FEATURE_OUTLINE_COLOR, FEATURE_OUTLINE_SELECTED, FEATURE_STROKE_WIDTH, RE_WIDTH},
-        BACKGROUND_COLOR, COLOR_RE, COLOR_RE_HIGHLIGHTED, LINEAR_MAP_HEIGHT,
+        circle::{gc_color, FEATURE_OUTLINE_SELECTED, FEATURE_STROKE_WIDTH, RE_WIDTH},
+        theme::{map_colors, palette},
+        LINEAR_MAP_HEIGHT,
     },
     misc_types::{Feature, FeatureType},
     primer::{Primer, PrimerDirection},
     state::State,
-    util::{map_linear, RangeIncl},
+    util::{gc_window, map_linear, RangeIncl},
     Selection, StateUi,
 };
 
@@ -41,6 +45,14 @@ const RE_HEIGHT_DIV2: f32 = RE_HEIGHT / 2.;
 const RE_HEIGHT_HIGHLIGHTED: f32 = 40.;
 const RE_HEIGHT_HIGHLIGHTED_DIV2: f32 = RE_HEIGHT_HIGHLIGHTED / 2.;
 
+// The GC content strip, beneath the sequence.
+const GC_Y: f32 = Y_START + 32.;
+const GC_HEIGHT: f32 = 4.;
+const GC_SEGMENTS: usize = 300;
+
+const COVERAGE_HEIGHT: f32 = 50.;
+const COVERAGE_COLOR: Color32 = Color32::from_rgb(100, 180, 255);
+
 fn feature_helper(
     result: &mut Vec<Shape>,
     to_screen: &RectTransform,
@@ -100,6 +112,8 @@ fn draw_features(
     to_screen: &RectTransform,
     disp_range: RangeIncl,
     selected_item: Selection,
+    multi_selected: &[usize],
+    types_hidden: &[FeatureType],
     index_to_x: impl Fn(usize) -> f32,
     pixel_left: f32,
     pixel_right: f32,
@@ -108,21 +122,16 @@ fn draw_features(
     let mut result = Vec::new();
 
     for (i, feature) in features.iter().enumerate() {
-        if feature.feature_type == FeatureType::Source {
-            // From some GB files indicating the entire sequence.
+        // By default, this hides Source features; from some GB files indicating the entire sequence.
+        if types_hidden.contains(&feature.feature_type) {
             continue;
         }
 
         // todo: DRY with teh main view. helper fn.
         let stroke_color = match selected_item {
-            Selection::Feature(j) => {
-                if j == i {
-                    FEATURE_OUTLINE_SELECTED
-                } else {
-                    FEATURE_OUTLINE_COLOR
-                }
-            }
-            _ => FEATURE_OUTLINE_COLOR,
+            Selection::Feature(j) if j == i => FEATURE_OUTLINE_SELECTED,
+            _ if multi_selected.contains(&i) => FEATURE_OUTLINE_SELECTED,
+            _ => map_colors().outline,
         };
 
         let mut feature_range = feature.range;
@@ -173,7 +182,7 @@ fn draw_features(
                     Align2::CENTER_CENTER,
                     feature.label(),
                     FontId::new(13., FontFamily::Proportional),
-                    Color32::DARK_GREEN,
+                    map_colors().outline,
                 )
             }));
         }
@@ -309,9 +318,14 @@ fn draw_re_sites(
         let cut_i = re_match.seq_index + 1; // to display in the right place.
 
         let (font_size, color, height, label_offset) = if selected.contains(&re) {
-            (16., COLOR_RE_HIGHLIGHTED, RE_HEIGHT_HIGHLIGHTED_DIV2, 2.)
+            (
+                16.,
+                palette().re_highlighted,
+                RE_HEIGHT_HIGHLIGHTED_DIV2,
+                2.,
+            )
         } else {
-            (13., COLOR_RE, RE_HEIGHT_DIV2, -2.)
+            (13., palette().re, RE_HEIGHT_DIV2, -2.)
         };
 
         let point_top = pos2(index_to_x(cut_i), Y_START - height);
@@ -344,6 +358,43 @@ fn draw_re_sites(
     result
 }
 
+/// Draw GC content as a heat strip beneath the sequence. Each segment shows the mean of the windowed
+/// GC content over the nucleotides it covers.
+fn draw_gc(
+    data: &GenericData,
+    window: usize,
+    to_screen: &RectTransform,
+    disp_range: RangeIncl,
+    index_to_x: impl Fn(usize) -> f32,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    let gc = gc_window(&data.seq, window, data.topology == SeqTopology::Circular);
+    if gc.is_empty() {
+        return result;
+    }
+
+    // `disp_range` may extend past the end of the sequence, if it wraps around the origin.
+    let disp_len = disp_range.end + 1 - disp_range.start;
+    let step = (disp_len / GC_SEGMENTS).max(1);
+
+    for start in (disp_range.start..=disp_range.end).step_by(step) {
+        let end = (start + step).min(disp_range.end + 1);
+        let mean = (start..end).map(|i| gc[i % gc.len()]).sum::<f32>() / (end - start) as f32;
+
+        result.push(Shape::rect_filled(
+            Rect::from_two_pos(
+                to_screen * pos2(index_to_x(start), GC_Y - GC_HEIGHT / 2.),
+                to_screen * pos2(index_to_x(end), GC_Y + GC_HEIGHT / 2.),
+            ),
+            0.,
+            gc_color(mean),
+        ));
+    }
+
+    result
+}
+
 /// A general purpose linear sequence view, used on several pages.
 /// This shows features, primers, and index ticks.
 pub fn draw_linear_map(
@@ -369,7 +420,7 @@ pub fn draw_linear_map(
     }
 
     let pixel_left = OFFSET.x;
-    let pixel_right = ui.available_width() - 2. * OFFSET.x;
+    let pixel_right = to_screen.from().width() - 2. * OFFSET.x;
 
     let mut disp_range = RangeIncl::new(index_left, index_right);
 
@@ -401,12 +452,24 @@ pub fn draw_linear_map(
         )
     };
 
+    if state_ui.seq_visibility.show_gc {
+        result.append(&mut draw_gc(
+            data,
+            state_ui.seq_visibility.gc_window,
+            to_screen,
+            disp_range,
+            index_to_x,
+        ));
+    }
+
     result.append(&mut draw_features(
         &data.features,
         seq_full_len,
         to_screen,
         disp_range,
         selected_item,
+        &state_ui.selected_features,
+        &state_ui.seq_visibility.feature_types_hidden,
         index_to_x,
         pixel_left,
         pixel_right,
@@ -499,7 +562,7 @@ pub fn lin_map_zoomed(
 }
 
 /// Draw a mini sequence display in its own canvas. This displays the entire sequence, and is used on several pages.
-/// We use this where we are not drawing on an existing canvas.
+/// We use this where we are not drawing on an existing canvas. Returns the canvas area.
 pub fn seq_lin_disp(
     data: &GenericData,
     show_re_sites: bool,
@@ -510,11 +573,11 @@ pub fn seq_lin_disp(
     re_matches: &[ReMatch],
     re_lib: &[RestrictionEnzyme],
     ui: &mut Ui,
-) {
+) -> Rect {
     let seq_len = data.seq.len();
 
     Frame::canvas(ui.style())
-        .fill(BACKGROUND_COLOR)
+        .fill(map_colors().background)
         .show(ui, |ui| {
             let (response, _painter) = {
                 let desired_size = vec2(ui.available_width(), LINEAR_MAP_HEIGHT);
@@ -527,7 +590,7 @@ pub fn seq_lin_disp(
             );
 
             if seq_len == 0 {
-                return; // Prevents -1 error on index right calc.
+                return response.rect; // Prevents -1 error on index right calc.
             }
 
             let shapes = draw_linear_map(
@@ -546,5 +609,121 @@ pub fn seq_lin_disp(
             );
 
             ui.painter().extend(shapes);
+            response.rect
+        })
+        .inner
+}
+
+/// Shapes for exporting a map of the entire sequence, e.g. as an image, in a canvas of `size`. The map
+/// is at the top, as on-screen; the selection and cursor are omitted.
+pub fn map_export_shapes(state: &State, size: Vec2, ui: &mut Ui) -> Vec<Shape> {
+    let data = &state.generic[state.active];
+    if data.seq.is_empty() {
+        return Vec::new();
+    }
+
+    let rect = Rect::from_min_size(Pos2::ZERO, size);
+
+    draw_linear_map(
+        data,
+        &RectTransform::from_to(rect, rect),
+        0,
+        data.seq.len() - 1,
+        true,
+        &state.ui.re.res_selected,
+        &state.volatile[state.active].restriction_enzyme_matches,
+        &state.restriction_enzyme_lib,
+        Selection::None,
+        None,
+        &state.ui,
+        ui,
+    )
+}
+
+/// Draw read depth from loaded alignments over the entire sequence, in its own canvas. Reference
+/// position 0 maps to the sequence's first nucleotide. Each pixel column shows the maximum depth of
+/// the positions it covers.
+pub fn coverage_track(coverage: &[u32], seq_len: usize, ui: &mut Ui) {
+    if coverage.len() != seq_len {
+        ui.label(
+            RichText::new(format!(
+                "The coverage reference is {} bp; this sequence is {seq_len} bp. Positions may not match.",
+                coverage.len()
+            ))
+            .color(Color32::GOLD),
+        );
+    }
+
+    let disp_len = coverage.len().min(seq_len);
+    if disp_len == 0 {
+        return;
+    }
+    let depth_max = coverage[..disp_len]
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    Frame::canvas(ui.style())
+        .fill(map_colors().background)
+        .show(ui, |ui| {
+            let (response, _painter) = {
+                let desired_size = vec2(ui.available_width(), COVERAGE_HEIGHT);
+                ui.allocate_painter(desired_size, Sense::hover())
+            };
+
+            let to_screen = RectTransform::from_to(
+                Rect::from_min_size(Pos2::ZERO, response.rect.size()),
+                response.rect,
+            );
+
+            let pixel_left = OFFSET.x;
+            let pixel_right = response.rect.width() - OFFSET.x;
+            let width = pixel_right - pixel_left;
+
+            // Nucleotide index (0-based) to pixel, and back.
+            let index_to_x = |i: usize| pixel_left + i as f32 / disp_len as f32 * width;
+            let x_to_index = |x: f32| {
+                (((x - pixel_left) / width * disp_len as f32).max(0.) as usize).min(disp_len - 1)
+            };
+
+            let mut shapes = Vec::new();
+
+            // One bar per pixel column, or per nucleotide if there are fewer nucleotides than columns.
+            let num_bars = (width as usize).min(disp_len).max(1);
+            for bar in 0..num_bars {
+                let start = bar * disp_len / num_bars;
+                let end = ((bar + 1) * disp_len / num_bars).max(start + 1);
+                let depth = coverage[start..end].iter().copied().max().unwrap_or(0);
+                if depth == 0 {
+                    continue;
+                }
+
+                let height = depth as f32 / depth_max as f32 * (COVERAGE_HEIGHT - 2. * OFFSET.y);
+                shapes.push(Shape::rect_filled(
+                    Rect::from_two_pos(
+                        to_screen * pos2(index_to_x(start), COVERAGE_HEIGHT - OFFSET.y - height),
+                        to_screen * pos2(index_to_x(end), COVERAGE_HEIGHT - OFFSET.y),
+                    ),
+                    0.,
+                    COVERAGE_COLOR,
+                ));
+            }
+
+            ui.painter().extend(shapes);
+
+            ui.painter().text(
+                to_screen * pos2(pixel_left, 2.),
+                Align2::LEFT_TOP,
+                format!("Max depth: {depth_max}"),
+                FontId::new(12., FontFamily::Proportional),
+                map_colors().text,
+            );
+
+            if let Some(pos) = response.hover_pos() {
+                let i = x_to_index(to_screen.inverse().transform_pos(pos).x);
+                response.on_hover_text(format!("{}: depth {}", i + 1, coverage[i]));
+            }
         });
 }
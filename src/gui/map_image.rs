@@ -0,0 +1,217 @@
+//! Copy an image of the circular or linear map to the clipboard, e.g. for pasting into slides, or
+//! export it to PNG at a chosen size, e.g. for journal figures. Both render the map's shapes
+//! off-screen, vice capturing the window, so they stay sharp at resolutions higher than the window's.
+
+use std::{borrow::Cow, io, io::ErrorKind, path::Path};
+
+use arboard::{Clipboard, ImageData};
+use eframe::egui::{self, vec2, ComboBox, Context, Rect, RichText, Ui};
+use na_seq::SeqTopology;
+
+use crate::{
+    gui::{
+        circle, int_field, lin_maps,
+        map_svg::{shapes_to_svg, svg_to_pixmap, svg_to_png},
+        navigation::Page,
+        save::save_button,
+        theme::{map_colors, COLOR_ACTION},
+        COL_SPACING,
+    },
+    state::State,
+};
+
+const SCALES: [usize; 3] = [1, 2, 4];
+
+/// We lay out exported maps with their shorter side (circular), or width (linear) this many points
+/// long, so text and line widths are proportioned as on-screen. The image is then scaled to the
+/// pixel size requested.
+const PNG_LAYOUT_SIZE: f32 = 1_000.;
+
+// Limits our memory use; 8k by 8k pixels is ample for print.
+const PNG_SIZE_MAX: usize = 8_192;
+
+pub struct MapImageUi {
+    /// We render the copied image at this multiple of the map's on-screen size, in pixels.
+    pub scale: usize,
+    /// The map canvas most recently drawn, in points. Set by the page that draws it.
+    pub map_rect: Option<Rect>,
+    pub status: Option<String>,
+    pub png_show: bool,
+    /// Pixels.
+    pub png_width: usize,
+    pub png_height: usize,
+    /// We keep this open after copying; on Linux, the clipboard's contents are served by the program
+    /// that set them, while it's open.
+    pub clipboard: Option<Clipboard>,
+}
+
+impl Default for MapImageUi {
+    fn default() -> Self {
+        Self {
+            scale: 2,
+            map_rect: None,
+            status: None,
+            png_show: false,
+            png_width: 3_000,
+            png_height: 3_000,
+            clipboard: None,
+        }
+    }
+}
+
+/// A button to copy the map to the clipboard, and a selector for the image scale.
+pub fn copy_map_button(map_rect: Option<Rect>, state: &mut State, ui: &mut Ui) {
+    let rect = match map_rect {
+        Some(r) => r,
+        None => return,
+    };
+
+    if ui
+        .button(RichText::new("📋 Copy image").color(COLOR_ACTION))
+        .on_hover_text(
+            "Copy an image of the map to the clipboard, at the scale selected to the right.",
+        )
+        .clicked()
+    {
+        // Match the on-screen size, in pixels, at the scale selected.
+        let scale = ui.ctx().pixels_per_point() * state.ui.map_image.scale as f32;
+        let width = ((rect.width() * scale).round() as usize).clamp(1, PNG_SIZE_MAX);
+        let height = ((rect.height() * scale).round() as usize).clamp(1, PNG_SIZE_MAX);
+
+        let result = copy_map(state, width, height, ui);
+        state.ui.map_image.status = Some(match result {
+            Ok(()) => format!("Copied {width} × {height} image"),
+            Err(e) => format!("Error copying the image: {e}"),
+        });
+    }
+
+    let data = &mut state.ui.map_image;
+
+    ComboBox::from_id_salt(3_800)
+        .width(40.)
+        .selected_text(format!("{}x", data.scale))
+        .show_ui(ui, |ui| {
+            for scale in SCALES {
+                ui.selectable_value(&mut data.scale, scale, format!("{scale}x"));
+            }
+        });
+
+    if ui
+        .button("🖼 Export PNG")
+        .on_hover_text("Export the map to a PNG file, at a size you choose.")
+        .clicked()
+    {
+        data.png_show = true;
+    }
+
+    if let Some(status) = &data.status {
+        ui.label(status);
+    }
+}
+
+/// If true, export the circular map. Otherwise, the linear one. This matches the map displayed.
+fn export_circular(state: &State) -> bool {
+    state.ui.page == Page::Map && state.generic[state.active].topology == SeqTopology::Circular
+}
+
+/// A window to choose the size of an exported PNG, and save it.
+pub fn png_export_window(state: &mut State, ctx: &Context) {
+    let mut open = state.ui.map_image.png_show;
+
+    egui::Window::new("Export map (PNG)")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let data = &mut state.ui.map_image;
+
+            ui.horizontal(|ui| {
+                int_field(&mut data.png_width, "Width (px):", ui);
+                ui.add_space(COL_SPACING);
+                int_field(&mut data.png_height, "Height (px):", ui);
+            });
+            data.png_width = data.png_width.clamp(1, PNG_SIZE_MAX);
+            data.png_height = data.png_height.clamp(1, PNG_SIZE_MAX);
+
+            // E.g. 3000 px covers 10 inches at 300 DPI.
+            ui.label(format!(
+                "At 300 DPI: {:.1} × {:.1} in",
+                data.png_width as f32 / 300.,
+                data.png_height as f32 / 300.
+            ));
+
+            let map_name = if export_circular(state) {
+                "circular"
+            } else {
+                "linear"
+            };
+            ui.label(format!(
+                "Exports the {map_name} map; hidden layers are omitted."
+            ));
+
+            save_button(
+                &mut state.ui.file_dialogs.export_png,
+                &state.generic[state.active].metadata.plasmid_name,
+                "png",
+                "Save",
+                "Choose where to save the image.",
+                ui,
+            );
+        });
+
+    state.ui.map_image.png_show = open;
+}
+
+/// The map displayed, as an SVG document laid out for an image of the given size, in pixels.
+fn map_svg(state: &State, width: usize, height: usize, ui: &mut Ui) -> io::Result<String> {
+    if state.get_seq().is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "There is no sequence to export.",
+        ));
+    }
+
+    let (size, shapes) = if export_circular(state) {
+        let scale = PNG_LAYOUT_SIZE / width.min(height) as f32;
+        let size = vec2(width as f32, height as f32) * scale;
+        (size, circle::map_export_shapes(state, size, ui))
+    } else {
+        let scale = PNG_LAYOUT_SIZE / width as f32;
+        let size = vec2(width as f32, height as f32) * scale;
+        (size, lin_maps::map_export_shapes(state, size, ui))
+    };
+
+    Ok(shapes_to_svg(&shapes, size, map_colors().background))
+}
+
+/// Render the map displayed to a PNG file, at the size set in the export window.
+pub fn export_png(state: &State, path: &Path, ui: &mut Ui) -> io::Result<()> {
+    let width = state.ui.map_image.png_width;
+    let height = state.ui.map_image.png_height;
+
+    let svg = map_svg(state, width, height, ui)?;
+    svg_to_png(&svg, width as u32, height as u32, path)
+}
+
+/// Render the map displayed, and copy it to the clipboard.
+fn copy_map(state: &mut State, width: usize, height: usize, ui: &mut Ui) -> io::Result<()> {
+    let svg = map_svg(state, width, height, ui)?;
+    // The background is opaque, so these pixels are the same premultiplied or not.
+    let pixmap = svg_to_pixmap(&svg, width as u32, height as u32)?;
+
+    let clipboard = match &mut state.ui.map_image.clipboard {
+        Some(c) => c,
+        None => state
+            .ui
+            .map_image
+            .clipboard
+            .insert(Clipboard::new().map_err(io::Error::other)?),
+    };
+
+    clipboard
+        .set_image(ImageData {
+            width,
+            height,
+            bytes: Cow::Owned(pixmap.take()),
+        })
+        .map_err(io::Error::other)
+}
@@ -0,0 +1,194 @@
+//! Convert the shapes we draw for a map into an SVG document, e.g. for publication figures. This lets
+//! us export the same geometry shown on screen, without a separate drawing path. We also rasterize
+//! these documents, for PNG export at any size.
+
+use std::{fmt::Write, io, io::ErrorKind, path::Path};
+
+use eframe::{
+    egui::{Color32, Pos2, Shape, Vec2},
+    epaint::ColorMode,
+};
+use resvg::{tiny_skia, usvg};
+
+/// E.g. `fill="#aabbcc" fill-opacity="0.50"`. Fully transparent colors map to "none".
+fn color_attr(name: &str, color: Color32) -> String {
+    if color == Color32::TRANSPARENT {
+        return format!(r#"{name}="none""#);
+    }
+
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a == 255 {
+        format!(r##"{name}="#{r:02x}{g:02x}{b:02x}""##)
+    } else {
+        format!(
+            r##"{name}="#{r:02x}{g:02x}{b:02x}" {name}-opacity="{:.2}""##,
+            a as f32 / 255.
+        )
+    }
+}
+
+fn stroke_attrs(width: f32, color: Color32) -> String {
+    if width <= 0. {
+        return r#"stroke="none""#.to_owned();
+    }
+    format!(
+        r#"{} stroke-width="{width:.1}""#,
+        color_attr("stroke", color)
+    )
+}
+
+fn points_attr(points: &[Pos2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escape text for use in SVG.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_shape(shape: &Shape, svg: &mut String) {
+    match shape {
+        Shape::Vec(shapes) => {
+            for s in shapes {
+                write_shape(s, svg);
+            }
+        }
+        Shape::Circle(circle) => {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" {} {}/>"#,
+                circle.center.x,
+                circle.center.y,
+                circle.radius,
+                color_attr("fill", circle.fill),
+                stroke_attrs(circle.stroke.width, circle.stroke.color),
+            );
+        }
+        Shape::LineSegment { points, stroke } => {
+            let color = match stroke.color {
+                ColorMode::Solid(c) => c,
+                _ => Color32::GRAY,
+            };
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" {}/>"#,
+                points[0].x,
+                points[0].y,
+                points[1].x,
+                points[1].y,
+                stroke_attrs(stroke.width, color),
+            );
+        }
+        Shape::Path(path) => {
+            let color = match path.stroke.color {
+                ColorMode::Solid(c) => c,
+                _ => Color32::GRAY,
+            };
+            let element = if path.closed { "polygon" } else { "polyline" };
+            let _ = writeln!(
+                svg,
+                r#"<{element} points="{}" {} {} stroke-linejoin="round"/>"#,
+                points_attr(&path.points),
+                color_attr("fill", path.fill),
+                stroke_attrs(path.stroke.width, color),
+            );
+        }
+        Shape::Rect(rect) => {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="{:.1}" {} {}/>"#,
+                rect.rect.min.x,
+                rect.rect.min.y,
+                rect.rect.width(),
+                rect.rect.height(),
+                rect.rounding.nw,
+                color_attr("fill", rect.fill),
+                stroke_attrs(rect.stroke.width, rect.stroke.color),
+            );
+        }
+        Shape::Text(text) => {
+            let galley = &text.galley;
+            let font_size = match galley.job.sections.first() {
+                Some(section) => section.format.font_id.size,
+                None => 14.,
+            };
+            // `pos` is the text's upper left corner, after alignment.
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" font-size="{font_size:.0}" dominant-baseline="central" {}>{}</text>"#,
+                text.pos.x,
+                text.pos.y + galley.size().y / 2.,
+                color_attr("fill", text.fallback_color),
+                escape(galley.text()),
+            );
+        }
+        // We don't currently draw other shape types on maps.
+        _ => (),
+    }
+}
+
+/// Create an SVG document from shapes, in the order drawn; later shapes are in front.
+pub fn shapes_to_svg(shapes: &[Shape], size: Vec2, background: Color32) -> String {
+    let mut svg = String::new();
+
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" font-family="sans-serif">"#,
+        w = size.x,
+        h = size.y,
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" {}/>"#,
+        color_attr("fill", background)
+    );
+
+    for shape in shapes {
+        write_shape(shape, &mut svg);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Render an SVG document to an image of the given size, in pixels. The document is scaled to fit.
+pub fn svg_to_pixmap(svg: &str, width: u32, height: u32) -> io::Result<tiny_skia::Pixmap> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    let tree = usvg::Tree::from_str(svg, &options)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("Invalid SVG: {e}")))?;
+
+    let mut pixmap = match tiny_skia::Pixmap::new(width, height) {
+        Some(p) => p,
+        None => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "The image size must be greater than 0.",
+            ))
+        }
+    };
+
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    Ok(pixmap)
+}
+
+/// Render an SVG document to a PNG file of the given size, in pixels.
+pub fn svg_to_png(svg: &str, width: u32, height: u32, path: &Path) -> io::Result<()> {
+    svg_to_pixmap(svg, width, height)?
+        .save_png(path)
+        .map_err(|e| io::Error::other(format!("Unable to encode PNG: {e}")))
+}
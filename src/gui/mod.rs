@@ -21,10 +21,16 @@ use std::path::PathBuf;
 
 use eframe::{
     egui,
-    egui::{pos2, Color32, Context, RichText, TextEdit, ThemePreference, Ui, ViewportCommand},
+    egui::{
+        pos2, Button, Color32, Context, RichText, TextEdit, ThemePreference, Ui, ViewportCommand,
+    },
     emath::RectTransform,
 };
-use navigation::Page;
+use na_seq::{
+    restriction_enzyme::{ReMatch, RestrictionEnzyme},
+    SeqTopology,
+};
+use navigation::{Page, PageSeq};
 
 use crate::{
     external_websites,
@@ -42,25 +48,39 @@ use crate::{
 
 mod ab1;
 mod alignment;
-mod circle;
+mod alignment_map;
+pub mod circle;
 mod cloning;
 mod feature_table;
+mod gel;
+mod global_find;
 mod input;
 mod ligation;
 mod lin_maps;
+pub mod map_image;
+mod map_svg;
 mod metadata;
 pub mod navigation;
 mod pcr;
 mod portions;
+pub mod primer_library;
+pub mod primer_qc;
 pub mod primer_table;
 mod protein;
 pub mod save;
 pub mod sequence;
-mod theme;
+mod settings;
+pub mod theme;
 
 pub const WINDOW_WIDTH: f32 = 1300.;
 pub const WINDOW_HEIGHT: f32 = 1_000.;
 
+// Limits for a window size restored from preferences, so a bad value can't make the window
+// unusable or invisible.
+const WINDOW_WIDTH_MIN: f32 = 600.;
+const WINDOW_HEIGHT_MIN: f32 = 400.;
+const WINDOW_SIZE_MAX: f32 = 8_000.;
+
 pub const WINDOW_TITLE: &str = "PlasCAD";
 
 pub const ROW_SPACING: f32 = 22.;
@@ -69,20 +89,28 @@ pub const COL_SPACING: f32 = 30.;
 // Note: This is basically working, but doesn't seem to reflect this scaling factor accurately.
 pub const SPLIT_SCREEN_MAX_HEIGHT: f32 = 3.5;
 
-pub const PRIMER_FWD_COLOR: Color32 = Color32::from_rgb(255, 0, 255);
-pub const PRIMER_REV_COLOR: Color32 = Color32::LIGHT_YELLOW;
-
 pub const BACKGROUND_COLOR: Color32 = Color32::from_rgb(10, 20, 10);
 
 pub const COLOR_SEQ: Color32 = Color32::LIGHT_BLUE;
 // (0xAD, 0xD8, 0xE6)
 pub const COLOR_SEQ_DIMMED: Color32 = Color32::from_rgb(140, 160, 165); // Eg dim when there are search results
-pub const COLOR_RE: Color32 = Color32::LIGHT_RED;
-pub const COLOR_RE_HIGHLIGHTED: Color32 = Color32::YELLOW;
 
 // If using a dedicated canvas for a linear map.
 pub const LINEAR_MAP_HEIGHT: f32 = 60.;
 
+/// The initial window size, from the one saved in preferences.
+pub fn window_size_restored(saved: (f32, f32)) -> [f32; 2] {
+    let (width, height) = saved;
+    if !width.is_finite() || !height.is_finite() {
+        return [WINDOW_WIDTH, WINDOW_HEIGHT];
+    }
+
+    [
+        width.clamp(WINDOW_WIDTH_MIN, WINDOW_SIZE_MAX),
+        height.clamp(WINDOW_HEIGHT_MIN, WINDOW_SIZE_MAX),
+    ]
+}
+
 pub fn int_field(val: &mut usize, label: &str, ui: &mut Ui) {
     ui.label(label);
     let mut entry = val.to_string();
@@ -100,7 +128,7 @@ pub fn get_cursor_text(cursor_seq_i: Option<usize>, seq_len: usize) -> String {
     match cursor_seq_i {
         Some(p) => {
             if p <= seq_len {
-                p.to_string()
+                util::disp_i(p).to_string()
                 // This occurs if the cursor is on the last row, right of the last NT.
             } else {
                 String::new()
@@ -110,6 +138,202 @@ pub fn get_cursor_text(cursor_seq_i: Option<usize>, seq_len: usize) -> String {
     }
 }
 
+/// Controls and results for measuring the distance between two positions. Used on the sequence
+/// and map views.
+fn measure_disp(state: &mut State, ui: &mut Ui) {
+    let text = if state.ui.measure.active {
+        RichText::new("📏 Measure").color(Color32::GREEN)
+    } else {
+        RichText::new("📏 Measure")
+    };
+
+    if ui
+        .button(text)
+        .on_hover_text("Click two positions on the sequence to find the distance between them.")
+        .clicked()
+    {
+        state.ui.measure.active = !state.ui.measure.active;
+    }
+
+    let measure = &state.ui.measure;
+    if !measure.active && measure.point_a.is_none() {
+        return;
+    }
+
+    match (measure.point_a, measure.point_b) {
+        (Some(a), Some(b)) => {
+            let data = &state.generic[state.active];
+            let circular = matches!(data.topology, SeqTopology::Circular);
+            let (dist, mid) = util::measure_dist(a, b, data.seq.len(), circular);
+
+            ui.label(format!("A: {}  B: {}", util::disp_i(a), util::disp_i(b)));
+            ui.heading(format!("{dist} bp"));
+            ui.label(format!("Mid: {}", util::disp_i(mid)));
+        }
+        (Some(a), None) => {
+            ui.label(format!("A: {}  B: (click)", util::disp_i(a)));
+        }
+        _ => {
+            ui.label("A: (click)");
+        }
+    }
+
+    if ui.button("Clear").clicked() {
+        state.ui.measure.clear();
+    }
+}
+
+/// Controls for adding bookmarks at the text cursor, and a list to jump to them. Used on the sequence
+/// and map views. Jumping scrolls the sequence view to the bookmark, and places the cursor there.
+pub fn bookmark_disp(state: &mut State, ui: &mut Ui) {
+    let cursor = state
+        .ui
+        .text_cursor_i
+        .filter(|i| *i >= 1 && *i <= state.get_seq().len());
+
+    let response = ui.add(
+        TextEdit::singleline(&mut state.ui.bookmark_name)
+            .hint_text("Bookmark name")
+            .desired_width(80.),
+    );
+    if response.gained_focus() {
+        state.ui.text_edit_active = true; // Disable character entries in the sequence.
+    }
+
+    if ui
+        .add_enabled(cursor.is_some(), Button::new("📌 Add"))
+        .on_hover_text(
+            "Bookmark the position at the text cursor. Click the sequence to place the cursor.",
+        )
+        .clicked()
+    {
+        if let Some(i) = cursor {
+            let bookmarks = &mut state.generic[state.active].bookmarks;
+            let name = if state.ui.bookmark_name.trim().is_empty() {
+                format!("Bookmark {}", bookmarks.len() + 1)
+            } else {
+                state.ui.bookmark_name.trim().to_owned()
+            };
+
+            bookmarks.push((i, name));
+            bookmarks.sort_by_key(|(posit, _)| *posit);
+            state.ui.bookmark_name = String::new();
+        }
+    }
+
+    if state.generic[state.active].bookmarks.is_empty() {
+        return;
+    }
+
+    let mut jump_to = None;
+    let mut removed = None;
+
+    ui.menu_button("Bookmarks ⏷", |ui| {
+        for (i, (posit, name)) in state.generic[state.active].bookmarks.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(format!("{name}: {}", util::disp_i(*posit)))
+                    .clicked()
+                {
+                    jump_to = Some(*posit);
+                    ui.close_menu();
+                }
+                if ui.button(RichText::new("🗑").color(Color32::RED)).clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+    });
+
+    if let Some(i) = removed {
+        state.generic[state.active].bookmarks.remove(i);
+    }
+
+    if let Some(posit) = jump_to {
+        state.ui.text_cursor_i = Some(posit);
+        state.ui.scroll_to_seq_i = Some(posit);
+        state.ui.page = Page::Sequence;
+        state.ui.page_seq = PageSeq::View;
+    }
+}
+
+/// If measuring, use a pending click to set a measurement point, instead of its normal action.
+pub fn handle_measure_click(state: &mut State) {
+    if !state.ui.measure.active || !state.ui.click_pending_handle {
+        return;
+    }
+
+    if let Some(i) = state.ui.cursor_seq_i {
+        if i >= 1 && i <= state.get_seq().len() {
+            state.ui.measure.add_point(i);
+        }
+    }
+    state.ui.click_pending_handle = false;
+}
+
+/// Ask for confirmation before deleting the selected feature or primer; requested from the keyboard.
+fn delete_confirm(state: &mut State, ui: &mut Ui) {
+    if !state.ui.delete_pending {
+        return;
+    }
+
+    let data = &state.generic[state.active];
+    let (kind, name) = match state.ui.selected_item {
+        Selection::Feature(i) if i < data.features.len() => ("feature", &data.features[i].label),
+        Selection::Primer(i) if i < data.primers.len() => ("primer", &data.primers[i].name),
+        _ => {
+            state.ui.delete_pending = false;
+            return;
+        }
+    };
+
+    let mut confirmed = false;
+    let mut cancelled = false;
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(format!("Delete {kind} {name}?")).color(Color32::LIGHT_RED));
+
+        if ui
+            .button(RichText::new("Delete 🗑").color(Color32::RED))
+            .clicked()
+        {
+            confirmed = true;
+        }
+        if ui.button("Cancel").clicked() {
+            cancelled = true;
+        }
+    });
+    ui.add_space(ROW_SPACING / 2.);
+
+    if confirmed {
+        state.snapshot();
+        let data = &mut state.generic[state.active];
+        // Select the row that takes the deleted one's place, or the one above it if it was last.
+        match state.ui.selected_item {
+            Selection::Feature(i) => {
+                data.features.remove(i);
+                state.ui.selected_features.clear();
+                state.ui.selected_item = match data.features.len() {
+                    0 => Selection::None,
+                    len => Selection::Feature(i.min(len - 1)),
+                };
+            }
+            Selection::Primer(i) => {
+                data.primers.remove(i);
+                state.ui.selected_item = match data.primers.len() {
+                    0 => Selection::None,
+                    len => Selection::Primer(i.min(len - 1)),
+                };
+                state.sync_primer_matches(None);
+            }
+            Selection::None => (),
+        }
+    }
+
+    if confirmed || cancelled {
+        state.ui.delete_pending = false;
+    }
+}
+
 /// Handle an origin change.
 fn origin_change(state: &mut State, ui: &mut Ui) {
     if ui.button("Set origin").clicked() {
@@ -149,6 +373,110 @@ fn origin_change(state: &mut State, ui: &mut Ui) {
     }
 }
 
+/// Flag if the active tab's name matches another open tab's, and offer a unique one.
+fn name_duplicate_disp(state: &mut State, ui: &mut Ui) {
+    let names: Vec<_> = state
+        .generic
+        .iter()
+        .map(|g| g.metadata.plasmid_name.as_str())
+        .collect();
+
+    let duplicates = util::duplicate_names(&names, state.active);
+    if duplicates.is_empty() {
+        return;
+    }
+
+    let tabs: Vec<_> = duplicates.iter().map(|i| (i + 1).to_string()).collect();
+    let suggested = util::unique_name(names[state.active], &names);
+
+    ui.label(RichText::new("⚠ Duplicate").color(Color32::GOLD).small())
+        .on_hover_text(format!(
+            "Open tab {} has the same name. This may cause confusion, or overwritten files on export.",
+            tabs.join(", ")
+        ));
+
+    if ui
+        .small_button("Rename")
+        .on_hover_text(format!("Rename to \"{suggested}\""))
+        .clicked()
+    {
+        state.generic[state.active].metadata.plasmid_name = suggested;
+    }
+}
+
+/// Display the sequence's topology, and toggle it on click.
+fn topology_toggle(state: &mut State, ui: &mut Ui) {
+    let topology = state.generic[state.active].topology;
+    let (text, hover) = match topology {
+        SeqTopology::Circular => (
+            "⭕ Circular",
+            "This sequence is circular, e.g. a plasmid. Click to make it linear.",
+        ),
+        SeqTopology::Linear => (
+            "➖ Linear",
+            "This sequence is linear, e.g. a PCR product. Click to make it circular.",
+        ),
+    };
+
+    if ui.button(text).on_hover_text(hover).clicked() {
+        match topology {
+            SeqTopology::Circular => {
+                // Ask where to open the sequence before changing it.
+                state.ui.linearize_prompt = !state.ui.linearize_prompt;
+                state.ui.new_origin = 1;
+            }
+            SeqTopology::Linear => {
+                state.snapshot();
+                state.generic[state.active].topology = SeqTopology::Circular;
+                state.ui.linearize_prompt = false;
+                state.sync_seq_related(None);
+            }
+        }
+    }
+}
+
+/// Choose where to open a circular sequence, then make it linear. The chosen position becomes
+/// position 1.
+fn linearize_prompt(state: &mut State, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Open the circular sequence at:");
+
+        let mut entry = state.ui.new_origin.to_string();
+        if ui
+            .add(TextEdit::singleline(&mut entry).desired_width(60.))
+            .on_hover_text("This position will be the start of the linear sequence. Features that span the opening will wrap.")
+            .changed()
+        {
+            state.ui.new_origin = entry.parse().unwrap_or(0);
+        }
+
+        let seq_len = state.get_seq().len();
+        let valid = state.ui.new_origin >= 1 && state.ui.new_origin <= seq_len;
+
+        if ui
+            .add_enabled(
+                valid,
+                Button::new(RichText::new("Linearize").color(COLOR_ACTION)),
+            )
+            .clicked()
+        {
+            // Changing the origin snapshots the tab, for undo.
+            if state.ui.new_origin != 1 {
+                util::change_origin(state);
+            } else {
+                state.snapshot();
+            }
+            state.generic[state.active].topology = SeqTopology::Linear;
+            state.sync_seq_related(None);
+            state.ui.linearize_prompt = false;
+        }
+
+        if ui.button("Cancel").clicked() {
+            state.ui.linearize_prompt = false;
+        }
+    });
+}
+
 /// Find the index of the smallest feature that contains an index. Index is in our 1-based system.
 fn feature_from_index(index: &Option<usize>, features: &[Feature]) -> Option<usize> {
     if let Some(seq_i) = index {
@@ -178,6 +506,68 @@ fn feature_from_index(index: &Option<usize>, features: &[Feature]) -> Option<usi
     None
 }
 
+/// The index of the nucleotide immediately after a restriction enzyme's cut; 1-based. Sites near the
+/// end of a circular sequence may cut past the origin; we wrap these to its start.
+pub fn re_cut_index(re_match: &ReMatch, re: &RestrictionEnzyme, seq_len: usize) -> usize {
+    let cut_i = re_match.seq_index + 1 + re.cut_after as usize;
+    if seq_len == 0 {
+        return cut_i;
+    }
+    (cut_i - 1) % seq_len + 1
+}
+
+/// Find the indices of features a restriction enzyme cuts within. Handles features that wrap the origin.
+fn features_cut_by_re(
+    re_match: &ReMatch,
+    re: &RestrictionEnzyme,
+    features: &[Feature],
+    seq_len: usize,
+) -> Vec<usize> {
+    let mut result = Vec::new();
+    // The cut is immediately before this nucleotide; it's within a feature if the nucleotides on both
+    // sides of it are.
+    let cut_i = re_cut_index(re_match, re, seq_len);
+
+    for (i, feature) in features.iter().enumerate() {
+        if feature.feature_type == FeatureType::Source {
+            continue; // From GenBank; generally the whole seq.
+        }
+
+        let (start, end) = (feature.range.start, feature.range.end);
+        let within = if start <= end {
+            cut_i > start && cut_i <= end
+        } else {
+            cut_i > start || cut_i <= end
+        };
+
+        if within {
+            result.push(i);
+        }
+    }
+    result
+}
+
+/// Describe the features an RE site cuts within, e.g. "Cuts within AmpR". None if it cuts none.
+fn re_cut_text(feature_indices: &[usize], features: &[Feature]) -> Option<String> {
+    if feature_indices.is_empty() {
+        return None;
+    }
+
+    let names: Vec<_> = feature_indices
+        .iter()
+        .map(|i| features[*i].label())
+        .collect();
+
+    Some(format!("Cuts within {}", names.join(", ")))
+}
+
+/// If true, an RE site cuts within a coding region; a digest here would likely disrupt it.
+fn re_cuts_coding(feature_indices: &[usize], features: &[Feature]) -> bool {
+    feature_indices
+        .iter()
+        .any(|i| features[*i].feature_type == FeatureType::CodingRegion)
+}
+
 /// todo: DRY with `feature_from_index`. Combine.
 fn primer_from_index(index: &Option<usize>, primers: &[Primer]) -> Option<usize> {
     if let Some(seq_i) = index {
@@ -205,6 +595,32 @@ fn primer_from_index(index: &Option<usize>, primers: &[Primer]) -> Option<usize>
     None
 }
 
+/// Add a feature to the multi-selection, or remove it if already present. The selected item follows the
+/// most recent addition.
+pub fn toggle_feature_multi_select(state: &mut State, i: usize) {
+    let selected = &mut state.ui.selected_features;
+
+    if selected.is_empty() {
+        if let Selection::Feature(j) = state.ui.selected_item {
+            if j != i {
+                selected.push(j);
+            }
+        }
+    }
+
+    match selected.iter().position(|j| *j == i) {
+        Some(k) => {
+            selected.remove(k);
+        }
+        None => selected.push(i),
+    }
+
+    state.ui.selected_item = match selected.last() {
+        Some(j) => Selection::Feature(*j),
+        None => Selection::None,
+    };
+}
+
 /// Selects a primer or feature, if there is a click in the appropriate canvas; used in both the sequence,
 /// and map views. Currently, if there is a primer and fetaure overlayed, it selects the primer. (This isn't ideal,
 /// but acceptable for now.)
@@ -228,6 +644,15 @@ pub fn select_feature(state: &mut State, from_screen: &RectTransform) {
                 let primer_i =
                     primer_from_index(&state.ui.cursor_seq_i, &state.generic[state.active].primers);
 
+                if state.ui.click_multi_select {
+                    if let (Some(i), None) = (feature_i, primer_i) {
+                        *click_handle = false;
+                        toggle_feature_multi_select(state, i);
+                        return;
+                    }
+                }
+                state.ui.selected_features.clear();
+
                 let mut toggled_off = false;
                 if let Selection::Feature(j) = state.ui.selected_item {
                     if primer_i.is_none() {
@@ -276,6 +701,16 @@ pub fn set_window_title(tab: &Tab, ui: &mut Ui) {
 
 pub fn draw(state: &mut State, ctx: &Context) {
     ctx.options_mut(|o| o.theme_preference = ThemePreference::Dark);
+    theme::set_palette(state.ui.palette);
+    theme::set_map_theme(state.ui.map_theme);
+    util::set_coord_base(state.ui.coord_base);
+
+    // Track the window size, to restore it on the next launch. Skip while minimized.
+    if let Some(rect) = ctx.input(|i| i.viewport().inner_rect) {
+        if !ctx.input(|i| i.viewport().minimized.unwrap_or(false)) {
+            state.ui.window_size = (rect.width(), rect.height());
+        }
+    }
 
     egui::CentralPanel::default().show(ctx, |ui| {
         handle_input(state, ui);
@@ -307,13 +742,24 @@ pub fn draw(state: &mut State, ctx: &Context) {
                 ui.add_space(COL_SPACING / 2.);
 
                 ui.label("Name:");
-                ui.add(
-                    TextEdit::singleline(&mut state.generic[state.active].metadata.plasmid_name)
-                        .desired_width(260.),
-                );
+                let name = &mut state.generic[state.active].metadata.plasmid_name;
+                if ui
+                    .add(TextEdit::singleline(name).desired_width(260.))
+                    .changed()
+                {
+                    *name = util::strip_filename_chars(name);
+                }
+
+                name_duplicate_disp(state, ui);
 
                 ui.label(format!("{} bp", state.get_seq().len()));
+
+                topology_toggle(state, ui);
             });
+
+            if state.ui.linearize_prompt {
+                linearize_prompt(state, ui);
+            }
         }
 
         ui.add_space(ROW_SPACING / 2.);
@@ -326,6 +772,7 @@ pub fn draw(state: &mut State, ctx: &Context) {
             origin_change(state, ui);
 
             if ui.button("Annotate").clicked() {
+                state.snapshot();
                 // Don't add duplicates.
                 let features = find_features(&state.get_seq());
                 merge_feature_sets(&mut state.generic[state.active].features, &features)
@@ -392,6 +839,19 @@ pub fn draw(state: &mut State, ctx: &Context) {
                     state.copy_seq()
                 }
             }
+
+            ui.add_space(COL_SPACING);
+            if ui
+                .button("🔍")
+                .on_hover_text("Find a feature or sequence in all open tabs.")
+                .clicked()
+            {
+                state.ui.global_find.show = !state.ui.global_find.show;
+            }
+
+            if ui.button("⚙").on_hover_text("Settings").clicked() {
+                state.ui.show_settings = !state.ui.show_settings;
+            }
         });
 
         ui.add_space(ROW_SPACING / 2.);
@@ -407,13 +867,20 @@ pub fn draw(state: &mut State, ctx: &Context) {
             Page::Cloning => cloning::cloning_page(state, ui),
             Page::Proteins => protein::protein_page(state, ui),
             Page::Ligation => ligation::ligation_page(state, ui),
+            Page::Gel => gel::gel_page(state, ui),
+            Page::AlignmentMap => alignment_map::alignment_map_page(state, ui),
             Page::Metadata => {
                 metadata::metadata_page(&mut state.generic[state.active].metadata, ui)
             }
-            Page::Portions => portions::portions_page(&mut state.portions[state.active], ui),
+            Page::Portions => portions::portions_page(state, ui),
             Page::Ab1 => ab1::ab1_page(state, ui),
         }
     });
+
+    settings::settings_window(state, ctx);
+    global_find::global_find_window(state, ctx);
+    navigation::close_tab_window(state, ctx);
+    map_image::png_export_window(state, ctx);
 }
 
 pub fn select_color_text(text: &str, selected: bool) -> RichText {
@@ -3,11 +3,12 @@
 use std::{fmt::Display, path::PathBuf};
 
 use bincode::{Decode, Encode};
-use eframe::egui::{Color32, RichText, Ui};
+use eframe::egui::{self, Align2, Button, Color32, Context, Rect, RichText, Sense, Stroke, Ui};
 use na_seq::seq_to_str_lower;
 
 use crate::{
-    gui::{select_color_text, set_window_title, COL_SPACING, ROW_SPACING},
+    file_io::save::save_current_file,
+    gui::{select_color_text, set_window_title, theme::COLOR_ACTION, COL_SPACING, ROW_SPACING},
     state::State,
     util::name_from_path,
 };
@@ -63,6 +64,10 @@ pub enum Page {
     /// i.e. Sanger sequencing data. This page is fundamentally different from the others;
     /// it is only for .ab1 files, and is selected automatically, vice from the menu.
     Ab1,
+    /// A virtual gel of a restriction digest.
+    Gel,
+    /// Reads from a BAM file, placed on their reference.
+    AlignmentMap,
 }
 
 impl Default for Page {
@@ -86,17 +91,41 @@ impl Display for Page {
             Self::Ligation => "Digest",
             Self::Cloning => "Clone",
             Self::Ab1 => "AB1",
+            Self::Gel => "Gel",
+            Self::AlignmentMap => "Reads",
         }
         .to_owned();
         write!(f, "{}", str)
     }
 }
 
+/// Where a dragged tab would be dropped: Its index after removing it from its current position.
+/// `tab_rects` are the tab buttons' screen positions, in order.
+fn tab_drop_target(tab_rects: &[(usize, Rect)], dragged: usize, x: f32) -> usize {
+    tab_rects
+        .iter()
+        .filter(|(i, rect)| *i != dragged && rect.center().x < x)
+        .count()
+}
+
+/// Make a tab active, e.g. after clicking it.
+fn select_tab(state: &mut State, i: usize, ui: &mut Ui) {
+    state.active = i;
+    set_window_title(&state.tabs_open[i], ui);
+    state.alignment.clear_tracks();
+
+    // todo: Apt state sync fn for this?
+    state.ui.seq_input = seq_to_str_lower(state.get_seq()); // todo: Move seq_input to an indexed vector?
+                                                            // todo: Cache these instead?
+                                                            // state.sync_seq_related(None);
+}
+
 /// Selects which tab (ie file) is active
 pub fn tab_selector(state: &mut State, ui: &mut Ui) {
     // Note: This assumes generic, paths_loaded etc are always the same length. (Which they *should* be.)
 
     let mut tab_removed = None;
+    let mut tab_moved = None;
 
     ui.horizontal(|ui| {
         if ui
@@ -115,30 +144,68 @@ pub fn tab_selector(state: &mut State, ui: &mut Ui) {
             .map(|v| v.metadata.plasmid_name.as_str())
             .collect();
 
+        let mut tab_rects = Vec::new();
+        let mut dragged = None;
+        let mut drag_stopped = None;
+
         for (name, i) in get_tab_names(&state.tabs_open, plasmid_names, false) {
             // todo: DRY with page selectors.
 
-            let button = ui.button(
-                select_color_text(&name, i == state.active).background_color(TAB_BUTTON_COLOR),
+            // Mark tabs with unsaved changes.
+            let name = if state.volatile[i].unsaved {
+                format!("{name} *")
+            } else {
+                name
+            };
+
+            let button = ui.add(
+                Button::new(
+                    select_color_text(&name, i == state.active).background_color(TAB_BUTTON_COLOR),
+                )
+                .sense(Sense::click_and_drag()),
             );
 
             if button.clicked() {
-                state.active = i;
-                set_window_title(&state.tabs_open[i], ui);
-
-                // todo: Apt state sync fn for this?
-                state.ui.seq_input = seq_to_str_lower(state.get_seq()); // todo: Move seq_input to an indexed vector?
-                                                                        // todo: Cache these instead?
-                                                                        // state.sync_seq_related(None);
+                select_tab(state, i, ui);
             }
 
             if button.middle_clicked() {
                 tab_removed = Some(i);
             }
 
+            if button.dragged() {
+                dragged = Some(i);
+            }
+            if button.drag_stopped() {
+                drag_stopped = Some(i);
+            }
+
+            tab_rects.push((i, button.rect));
+
             ui.add_space(COL_SPACING / 2.);
         }
 
+        if let Some(pos) = ui.ctx().pointer_interact_pos() {
+            if let Some(i) = dragged {
+                // Show where the tab will be dropped.
+                let target = tab_drop_target(&tab_rects, i, pos.x);
+                let others: Vec<_> = tab_rects.iter().filter(|(j, _)| *j != i).collect();
+                let x = match others.get(target) {
+                    Some((_, rect)) => rect.left() - COL_SPACING / 4.,
+                    None => match others.last() {
+                        Some((_, rect)) => rect.right() + COL_SPACING / 4.,
+                        None => pos.x,
+                    },
+                };
+                ui.painter()
+                    .vline(x, tab_rects[i].1.y_range(), Stroke::new(2., Color32::WHITE));
+            }
+
+            if let Some(i) = drag_stopped {
+                tab_moved = Some((i, tab_drop_target(&tab_rects, i, pos.x)));
+            }
+        }
+
         // todo: Right-align?
         ui.add_space(2. * ROW_SPACING);
 
@@ -148,18 +215,96 @@ pub fn tab_selector(state: &mut State, ui: &mut Ui) {
                     .color(Color32::WHITE)
                     .background_color(Color32::DARK_RED),
             )
-            .on_hover_text("Shortcut: Middle click the tab to close it.")
+            .on_hover_text("Shortcut: Middle click the tab to close it. Drag tabs to reorder them.")
             .clicked
         {
             tab_removed = Some(state.active)
         };
     });
 
+    if let Some((from, to)) = tab_moved {
+        state.move_tab(from, to);
+    }
+
     if let Some(i) = tab_removed {
-        state.remove_tab(i);
+        // Confirm before discarding edits.
+        if state.volatile[i].unsaved {
+            state.ui.tab_close_pending = Some(i);
+        } else {
+            state.remove_tab(i);
+        }
     }
 }
 
+/// Asks whether to save a tab with unsaved changes before closing it.
+pub fn close_tab_window(state: &mut State, ctx: &Context) {
+    let i = match state.ui.tab_close_pending {
+        Some(i) if i < state.tabs_open.len() => i,
+        _ => {
+            state.ui.tab_close_pending = None;
+            return;
+        }
+    };
+
+    let plasmid_names: &Vec<_> = &state
+        .generic
+        .iter()
+        .map(|v| v.metadata.plasmid_name.as_str())
+        .collect();
+    let name = get_tab_names(&state.tabs_open, plasmid_names, false)[i]
+        .0
+        .clone();
+    let has_path = state.tabs_open[i].path.is_some();
+
+    egui::Window::new("Unsaved changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "{name} has unsaved changes. Save them before closing?"
+            ));
+            ui.add_space(ROW_SPACING / 2.);
+
+            ui.horizontal(|ui| {
+                let save_text = if has_path {
+                    "Save and close"
+                } else {
+                    "Save as…"
+                };
+                if ui
+                    .button(RichText::new(save_text).color(COLOR_ACTION))
+                    .clicked()
+                {
+                    select_tab(state, i, ui);
+                    state.ui.tab_close_pending = None;
+
+                    if has_path {
+                        save_current_file(state);
+                        if !state.volatile[i].unsaved {
+                            state.remove_tab(i);
+                        }
+                    } else {
+                        // This tab has no file yet; the user closes it again after saving.
+                        state.ui.file_dialogs.save.select_file();
+                    }
+                }
+
+                if ui
+                    .button(RichText::new("Close without saving").color(Color32::LIGHT_RED))
+                    .clicked()
+                {
+                    state.ui.tab_close_pending = None;
+                    state.remove_tab(i);
+                }
+
+                if ui.button("Cancel").clicked() {
+                    state.ui.tab_close_pending = None;
+                }
+            });
+        });
+}
+
 pub fn page_selector(state: &mut State, ui: &mut Ui) {
     ui.horizontal(|ui| {
         page_button(&mut state.ui.page, Page::Sequence, ui, true);
@@ -170,7 +315,9 @@ pub fn page_selector(state: &mut State, ui: &mut Ui) {
         page_button(&mut state.ui.page, Page::Cloning, ui, true);
         page_button(&mut state.ui.page, Page::Pcr, ui, true);
         page_button(&mut state.ui.page, Page::Alignment, ui, true);
+        page_button(&mut state.ui.page, Page::AlignmentMap, ui, true);
         page_button(&mut state.ui.page, Page::Ligation, ui, true);
+        page_button(&mut state.ui.page, Page::Gel, ui, true);
         page_button(&mut state.ui.page, Page::Metadata, ui, true);
         page_button(&mut state.ui.page, Page::Portions, ui, true);
     });
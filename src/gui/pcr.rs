@@ -5,13 +5,14 @@ use eframe::egui::{Color32, ComboBox, Grid, RichText, TextEdit, Ui, Vec2};
 use crate::{
     gui::{
         lin_maps,
+        map_image::copy_map_button,
+        primer_table::{heterodimer_disp, tm_pair_disp, tm_pair_max_diff_edit},
         theme::{COLOR_ACTION, COLOR_INFO},
         COL_SPACING, ROW_SPACING,
     },
-    pcr::{make_amplicon_tab, PcrUi, PolymeraseType, TempTime},
-    primer::{Primer, PrimerDirection, TM_TARGET},
+    pcr::{find_amplicon, make_amplicon_tab, PcrUi, PolymeraseType, TempTime},
+    primer::{tm_target, AnnealMatch, Primer, PrimerDirection},
     state::State,
-    util::RangeIncl,
 };
 
 fn temp_time_disp(tt: &TempTime, label: &str, ui: &mut Ui) {
@@ -45,6 +46,7 @@ where
 fn primer_dropdown(
     val: &mut usize,
     primers: &[Primer],
+    anneal_matches: &[AnnealMatch],
     direction: Option<PrimerDirection>,
     id: usize,
     ui: &mut Ui,
@@ -68,6 +70,12 @@ fn primer_dropdown(
                             dir_match = true;
                         }
                     }
+                    // Include primers that only anneal at their 3' end.
+                    for anneal in anneal_matches {
+                        if anneal.primer_i == i && anneal.match_.direction == dir {
+                            dir_match = true;
+                        }
+                    }
                     if !dir_match {
                         continue;
                     }
@@ -88,7 +96,7 @@ fn pcr_sim(state: &mut State, ui: &mut Ui) {
 
     ui.heading("PCR product generation");
 
-    lin_maps::seq_lin_disp(
+    let map_rect = lin_maps::seq_lin_disp(
         &state.generic[state.active],
         false,
         state.ui.selected_item,
@@ -99,6 +107,9 @@ fn pcr_sim(state: &mut State, ui: &mut Ui) {
         &state.restriction_enzyme_lib,
         ui,
     );
+    ui.horizontal(|ui| {
+        copy_map_button(Some(map_rect), state, ui);
+    });
     ui.add_space(ROW_SPACING / 2.);
 
     if num_primers >= 2 {
@@ -107,6 +118,7 @@ fn pcr_sim(state: &mut State, ui: &mut Ui) {
             primer_dropdown(
                 &mut state.ui.pcr.primer_fwd,
                 &state.generic[state.active].primers,
+                &state.volatile[state.active].primer_anneal_matches,
                 Some(PrimerDirection::Forward),
                 2,
                 ui,
@@ -118,6 +130,7 @@ fn pcr_sim(state: &mut State, ui: &mut Ui) {
             primer_dropdown(
                 &mut state.ui.pcr.primer_rev,
                 &state.generic[state.active].primers,
+                &state.volatile[state.active].primer_anneal_matches,
                 Some(PrimerDirection::Reverse),
                 3,
                 ui,
@@ -125,59 +138,67 @@ fn pcr_sim(state: &mut State, ui: &mut Ui) {
 
             ui.add_space(COL_SPACING);
 
-            // let fwd_primer = &state.generic[state.active].primers[state.ui.pcr.primer_fwd];
-            // let rev_primer = &state.generic[state.active].primers[state.ui.pcr.primer_rev];
-
-            // todo: Yikes on this syntax.
             if state.ui.pcr.primer_fwd == state.ui.pcr.primer_rev {
                 ui.label("Select two different primers");
-            } else if state.generic[state.active].primers[state.ui.pcr.primer_fwd]
-                .volatile
-                .matches
-                .len()
-                == 1
-                && state.generic[state.active].primers[state.ui.pcr.primer_rev]
-                    .volatile
-                    .matches
-                    .len()
-                    == 1
-            {
-                if ui
-                    .button(RichText::new("Simulate PCR").color(COLOR_ACTION))
-                    .clicked()
-                {
-                    let fwd_primer =
-                        state.generic[state.active].primers[state.ui.pcr.primer_fwd].clone();
-                    let rev_primer =
-                        state.generic[state.active].primers[state.ui.pcr.primer_rev].clone();
-
-                    // todo: Yikes.
-                    let range_fwd = fwd_primer.volatile.matches[0].range;
-                    let range_rev = rev_primer.volatile.matches[0].range;
-
-                    let range_combined = RangeIncl::new(range_fwd.start, range_rev.end);
-
-                    let product_seq = if range_combined.start > range_combined.end {
-                        range_combined
-                            .index_seq_wrap(&state.generic[state.active].seq)
-                            .unwrap()
-                    // todo unwrap is dicey.
-                    } else {
-                        range_combined
-                            .index_seq(&state.generic[state.active].seq)
-                            .unwrap()
-                            .to_vec() // todo unwrap is dicey.
-                    };
+            } else {
+                let primers = &state.generic[state.active].primers;
+                tm_pair_disp(
+                    &primers[state.ui.pcr.primer_fwd],
+                    &primers[state.ui.pcr.primer_rev],
+                    state.ui.tm_pair_max_diff,
+                    ui,
+                );
+                ui.add_space(COL_SPACING);
 
-                    make_amplicon_tab(state, product_seq, range_combined, fwd_primer, rev_primer);
+                heterodimer_disp(
+                    &primers[state.ui.pcr.primer_fwd],
+                    &primers[state.ui.pcr.primer_rev],
+                    ui,
+                );
+                ui.add_space(COL_SPACING);
+
+                match find_amplicon(
+                    &state.generic[state.active],
+                    state.ui.pcr.primer_fwd,
+                    state.ui.pcr.primer_rev,
+                    &state.volatile[state.active].primer_anneal_matches,
+                ) {
+                    Ok(sites) => {
+                        let data = &state.generic[state.active];
+                        let product_len = sites.tail_fwd.len()
+                            + sites.range.len_topology(data.seq.len(), data.topology)
+                            + sites.tail_rev.len();
+
+                        if ui
+                            .button(RichText::new("Simulate PCR").color(COLOR_ACTION))
+                            .on_hover_text(
+                                "Create a new tab containing the PCR product, including \
+                                primer tails.",
+                            )
+                            .clicked()
+                        {
+                            let primers = &state.generic[state.active].primers;
+                            let fwd_primer = primers[state.ui.pcr.primer_fwd].clone();
+                            let rev_primer = primers[state.ui.pcr.primer_rev].clone();
+
+                            make_amplicon_tab(state, &sites, fwd_primer, rev_primer);
+                        }
+
+                        ui.label(format!("Product: {} bp", product_len));
+                    }
+                    Err(e) => {
+                        ui.label(RichText::new(e).color(Color32::LIGHT_RED));
+                    }
                 }
-            } else {
-                ui.label("There must be exactly one match for each primer");
             }
         });
     } else {
         ui.label("(Add at least 2 primers to generate a PCR product)");
     }
+
+    ui.horizontal(|ui| {
+        tm_pair_max_diff_edit(&mut state.ui.tm_pair_max_diff, ui);
+    });
 }
 
 pub fn pcr_page(state: &mut State, ui: &mut Ui) {
@@ -206,6 +227,7 @@ pub fn pcr_page(state: &mut State, ui: &mut Ui) {
             primer_dropdown(
                 &mut state.ui.pcr.primer_selected,
                 &state.generic[state.active].primers,
+                &[],
                 None,
                 1,
                 ui,
@@ -225,7 +247,7 @@ pub fn pcr_page(state: &mut State, ui: &mut Ui) {
         let mut entry = format!("{:.0}", state.ui.pcr.primer_tm);
         let response = ui.add(TextEdit::singleline(&mut entry).desired_width(20.));
         if response.changed() {
-            state.ui.pcr.primer_tm = entry.parse().unwrap_or(TM_TARGET);
+            state.ui.pcr.primer_tm = entry.parse().unwrap_or(tm_target());
             state.sync_pcr();
         }
 
@@ -4,6 +4,7 @@ use eframe::{
     egui,
     egui::{Color32, ComboBox, RichText, TextEdit, Ui},
 };
+use na_seq::Nucleotide;
 
 use crate::{
     gui::{
@@ -11,9 +12,11 @@ use crate::{
         COL_SPACING, ROW_SPACING,
     },
     portions::{
-        media_prep, MediaPrepInput, PlateSize, PortionsState, Reagent, ReagentPrep, ReagentType,
-        Solution,
+        dilution_series, media_prep, DilutionSeriesInput, MediaPrepInput, PlateSize, PortionsState,
+        Reagent, ReagentPrep, ReagentType, Solution, MIN_PRACTICAL_VOLUME,
     },
+    solution_helper::{ds_dna_weight, ConversionUi, MassUnit, MolarityUnit, Quantity, VolumeUnit},
+    state::State,
 };
 // todo: Make a non-gui portions module once this becomes unweildy.
 
@@ -304,7 +307,190 @@ fn media_disp(portions: &mut PortionsState, ui: &mut Ui) {
     }
 }
 
-pub fn portions_page(portions: &mut PortionsState, ui: &mut Ui) {
+/// A float input field.
+fn float_field(val: &mut f32, label: &str, ui: &mut Ui) {
+    ui.label(label);
+    let mut entry = val.to_string();
+    if ui
+        .add(TextEdit::singleline(&mut entry).desired_width(50.))
+        .changed()
+    {
+        *val = entry.parse().unwrap_or_default();
+    }
+}
+
+fn dilution_disp(input: &mut DilutionSeriesInput, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        float_field(&mut input.stock_conc, "Stock conc:", ui);
+        ui.add_space(COL_SPACING);
+
+        float_field(&mut input.final_volume, "Final volume per step (μL):", ui);
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button(RichText::new("➕ Add step").color(COLOR_ACTION))
+            .clicked()
+        {
+            let next = match input.target_concs.last() {
+                Some(c) => c / 10.,
+                None => input.stock_conc / 10.,
+            };
+            input.target_concs.push(next);
+        }
+    });
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    let mut step_removed = None;
+    for (i, conc) in input.target_concs.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            float_field(conc, &format!("Step {} conc:", i + 1), ui);
+
+            if ui.button(RichText::new("🗑").color(Color32::RED)).clicked() {
+                step_removed = Some(i);
+            }
+        });
+    }
+
+    if let Some(rem_i) = step_removed {
+        input.target_concs.remove(rem_i);
+    }
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    match dilution_series(input) {
+        Ok(steps) => {
+            for (i, step) in steps.iter().enumerate() {
+                let source = if i == 0 {
+                    "stock".to_owned()
+                } else {
+                    format!("step {i}")
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("Step {} ({}):", i + 1, step.conc));
+                    ui.label(
+                        RichText::new(format!(
+                            "{:.2} μL of {source} ({}) + {:.2} μL diluent",
+                            step.transfer_vol, step.source_conc, step.diluent_vol
+                        ))
+                        .color(COLOR_INFO),
+                    );
+                    ui.label(format!("= {:.2} μL", step.total_vol));
+
+                    if step.transfer_vol < MIN_PRACTICAL_VOLUME {
+                        ui.label(
+                            RichText::new("Transfer volume too small to pipette accurately; use an intermediate step, or larger volumes.")
+                                .color(Color32::LIGHT_RED),
+                        );
+                    }
+                });
+            }
+        }
+        Err(e) => {
+            ui.label(RichText::new(e).color(Color32::LIGHT_RED));
+        }
+    }
+}
+
+/// Convert between mass, molarity, volume, and molecular weight; solves for one, given the others.
+fn conversion_disp(conv: &mut ConversionUi, seq: &[Nucleotide], ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Solve for:");
+        ComboBox::from_id_salt(3_100)
+            .width(90.)
+            .selected_text(conv.solve_for.to_string())
+            .show_ui(ui, |ui| {
+                for q in [
+                    Quantity::Mass,
+                    Quantity::Molarity,
+                    Quantity::Volume,
+                    Quantity::MolWeight,
+                ] {
+                    ui.selectable_value(&mut conv.solve_for, q, q.to_string());
+                }
+            });
+        ui.add_space(COL_SPACING);
+
+        let solve_for = conv.solve_for;
+
+        // The solved quantity is displayed as a result, vice editable.
+        let quantity_field = |val: &mut f32, quantity: Quantity, ui: &mut Ui| {
+            ui.label(format!("{quantity}:"));
+            if solve_for == quantity {
+                ui.label(RichText::new(format!("{val:.3}")).color(COLOR_INFO));
+            } else {
+                let mut entry = val.to_string();
+                if ui
+                    .add(TextEdit::singleline(&mut entry).desired_width(50.))
+                    .changed()
+                {
+                    *val = entry.parse().unwrap_or_default();
+                }
+            }
+        };
+
+        quantity_field(&mut conv.mass, Quantity::Mass, ui);
+        ComboBox::from_id_salt(3_101)
+            .width(40.)
+            .selected_text(conv.mass_unit.to_string())
+            .show_ui(ui, |ui| {
+                for unit in [MassUnit::Ng, MassUnit::Ug, MassUnit::Mg, MassUnit::G] {
+                    ui.selectable_value(&mut conv.mass_unit, unit, unit.to_string());
+                }
+            });
+        ui.add_space(COL_SPACING / 2.);
+
+        quantity_field(&mut conv.molarity, Quantity::Molarity, ui);
+        ComboBox::from_id_salt(3_102)
+            .width(40.)
+            .selected_text(conv.molarity_unit.to_string())
+            .show_ui(ui, |ui| {
+                for unit in [
+                    MolarityUnit::Nm,
+                    MolarityUnit::Um,
+                    MolarityUnit::Mm,
+                    MolarityUnit::M,
+                ] {
+                    ui.selectable_value(&mut conv.molarity_unit, unit, unit.to_string());
+                }
+            });
+        ui.add_space(COL_SPACING / 2.);
+
+        quantity_field(&mut conv.volume, Quantity::Volume, ui);
+        ComboBox::from_id_salt(3_103)
+            .width(40.)
+            .selected_text(conv.volume_unit.to_string())
+            .show_ui(ui, |ui| {
+                for unit in [VolumeUnit::Ul, VolumeUnit::Ml, VolumeUnit::L] {
+                    ui.selectable_value(&mut conv.volume_unit, unit, unit.to_string());
+                }
+            });
+        ui.add_space(COL_SPACING / 2.);
+
+        quantity_field(&mut conv.mol_weight, Quantity::MolWeight, ui);
+        ui.label("g/mol");
+
+        if ui
+            .button("From sequence")
+            .on_hover_text(
+                "Use the molecular weight of the active sequence, as double-stranded DNA.",
+            )
+            .clicked()
+        {
+            conv.mol_weight = ds_dna_weight(seq);
+            if conv.solve_for == Quantity::MolWeight {
+                conv.solve_for = Quantity::Mass;
+            }
+        }
+    });
+
+    conv.solve();
+}
+
+pub fn portions_page(state: &mut State, ui: &mut Ui) {
+    let portions = &mut state.portions[state.active];
+
     ui.add_space(ROW_SPACING / 2.);
 
     ui.horizontal(|ui| {
@@ -330,4 +516,16 @@ pub fn portions_page(portions: &mut PortionsState, ui: &mut Ui) {
 
     ui.heading("Growth media");
     media_disp(portions, ui);
+    ui.add_space(ROW_SPACING);
+
+    ui.heading("Serial dilution");
+    dilution_disp(&mut portions.dilution, ui);
+    ui.add_space(ROW_SPACING);
+
+    ui.heading("Mass, molarity, and volume");
+    conversion_disp(
+        &mut state.ui.conversion,
+        &state.generic[state.active].seq,
+        ui,
+    );
 }
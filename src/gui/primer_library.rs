@@ -0,0 +1,224 @@
+//! The primer library panel: Browse and search the lab's primer stock list, and add primers from it
+//! to the active tab.
+
+use eframe::egui::{Color32, RichText, ScrollArea, TextEdit, Ui};
+use na_seq::seq_to_str_lower;
+
+use crate::{
+    gui::{theme::COLOR_ACTION, COL_SPACING, ROW_SPACING},
+    primer_library::{export_primer_lib, import_primer_lib, primer_from_input},
+    state::State,
+    Selection,
+};
+
+const LIB_HEIGHT: f32 = 200.;
+
+#[derive(Default)]
+pub struct PrimerLibUi {
+    pub show: bool,
+    pub search: String,
+    pub name_input: String,
+    pub seq_input: String,
+    pub status: Option<String>,
+}
+
+/// Handle the library's import and export dialogs.
+fn handle_dialogs(state: &mut State, ui: &mut Ui) {
+    state.ui.file_dialogs.export_primer_lib.update(ui.ctx());
+    state.ui.file_dialogs.import_primer_lib.update(ui.ctx());
+
+    if let Some(path) = state.ui.file_dialogs.export_primer_lib.take_selected() {
+        state.ui.primer_lib.status =
+            Some(match export_primer_lib(&state.primer_lib.primers, &path) {
+                Ok(_) => format!("Exported {} primers", state.primer_lib.primers.len()),
+                Err(e) => {
+                    eprintln!("Error exporting the primer library: {e}");
+                    format!("Error exporting: {e}")
+                }
+            });
+    } else if let Some(path) = state.ui.file_dialogs.import_primer_lib.take_selected() {
+        state.ui.primer_lib.status = Some(match import_primer_lib(&path) {
+            Ok((primers, skipped)) => {
+                let mut added = 0;
+                for primer in &primers {
+                    if state.primer_lib.add(primer) {
+                        added += 1;
+                    }
+                }
+                state.save_prefs();
+
+                let mut status = format!(
+                    "Imported {added} primers; {} were already present",
+                    primers.len() - added
+                );
+                if !skipped.is_empty() {
+                    status += &format!(
+                        ". Skipped {}, with bases other than ACTG",
+                        skipped.join(", ")
+                    );
+                }
+                status
+            }
+            Err(e) => {
+                eprintln!("Error importing primers: {e}");
+                format!("Error importing: {e}")
+            }
+        });
+    }
+}
+
+pub fn primer_library_disp(state: &mut State, ui: &mut Ui) {
+    handle_dialogs(state, ui);
+
+    ui.horizontal(|ui| {
+        let text = if state.ui.primer_lib.show {
+            "Hide primer library"
+        } else {
+            "Show primer library"
+        };
+        if ui
+            .button(RichText::new(text).color(COLOR_ACTION))
+            .on_hover_text(
+                "Your primer stock list, shared between all sequences. Add primers from it to \
+                this sequence, to find where they bind.",
+            )
+            .clicked()
+        {
+            state.ui.primer_lib.show = !state.ui.primer_lib.show;
+        }
+
+        if !state.ui.primer_lib.show {
+            return;
+        }
+
+        ui.add_space(COL_SPACING);
+
+        if let Selection::Primer(sel_i) = state.ui.selected_item {
+            if sel_i < state.generic[state.active].primers.len()
+                && ui
+                    .button("➕ Add selected to library")
+                    .on_hover_text("Add the selected primer in this sequence to the library.")
+                    .clicked()
+            {
+                let primer = &state.generic[state.active].primers[sel_i];
+                state.ui.primer_lib.status = Some(if state.primer_lib.add(primer) {
+                    format!("Added {} to the library", primer.name)
+                } else {
+                    format!("{} is already in the library", primer.name)
+                });
+                state.save_prefs();
+            }
+        }
+
+        if ui
+            .button("Import")
+            .on_hover_text("Add primers to the library from a FASTA file.")
+            .clicked()
+        {
+            state.ui.file_dialogs.import_primer_lib.select_file();
+        }
+
+        if ui
+            .button("Export")
+            .on_hover_text(
+                "Save the library as a FASTA file. Tails and modifications aren't included.",
+            )
+            .clicked()
+        {
+            state.ui.file_dialogs.export_primer_lib.save_file();
+        }
+
+        if let Some(status) = &state.ui.primer_lib.status {
+            ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+        }
+    });
+
+    if !state.ui.primer_lib.show {
+        return;
+    }
+
+    if state.primer_lib.load_failed {
+        ui.label(
+            RichText::new(
+                "The library file couldn't be read. Changes to the library won't be saved, so \
+                it isn't overwritten.",
+            )
+            .color(Color32::LIGHT_RED),
+        );
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        ui.add(TextEdit::singleline(&mut state.ui.primer_lib.search).desired_width(120.));
+
+        ui.add_space(COL_SPACING * 2.);
+
+        ui.label("New: name");
+        ui.add(TextEdit::singleline(&mut state.ui.primer_lib.name_input).desired_width(80.));
+        ui.label("seq");
+        ui.add(TextEdit::singleline(&mut state.ui.primer_lib.seq_input).desired_width(200.));
+
+        if ui.button("➕ Add").clicked() {
+            let primer = primer_from_input(
+                &state.ui.primer_lib.name_input,
+                &state.ui.primer_lib.seq_input,
+            );
+            if primer.sequence.is_empty() {
+                state.ui.primer_lib.status = Some("Enter a primer sequence".to_owned());
+            } else {
+                state.primer_lib.add(&primer);
+                state.ui.primer_lib.name_input = String::new();
+                state.ui.primer_lib.seq_input = String::new();
+                state.save_prefs();
+            }
+        }
+    });
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    let mut add_to_tab = None;
+    let mut removed = None;
+
+    ScrollArea::vertical()
+        .id_salt(203)
+        .max_height(LIB_HEIGHT)
+        .show(ui, |ui| {
+            for i in state.primer_lib.search(&state.ui.primer_lib.search) {
+                let primer = &state.primer_lib.primers[i];
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(RichText::new("➕ Use").color(COLOR_ACTION))
+                        .on_hover_text("Add this primer to the active sequence.")
+                        .clicked()
+                    {
+                        add_to_tab = Some(i);
+                    }
+
+                    if ui
+                        .button(RichText::new("🗑").color(Color32::RED))
+                        .on_hover_text("Remove this primer from the library.")
+                        .clicked()
+                    {
+                        removed = Some(i);
+                    }
+
+                    ui.label(RichText::new(&primer.name).color(Color32::WHITE));
+                    ui.label(seq_to_str_lower(&primer.sequence));
+                    if let Some(desc) = &primer.description {
+                        ui.label(RichText::new(desc).color(Color32::LIGHT_GRAY));
+                    }
+                });
+            }
+        });
+
+    if let Some(i) = add_to_tab {
+        state.add_primer_from_lib(i);
+    }
+
+    if let Some(i) = removed {
+        state.primer_lib.remove(i);
+        state.save_prefs();
+    }
+
+    ui.add_space(ROW_SPACING);
+}
@@ -0,0 +1,170 @@
+//! Display a QC report for all primers in the active tab.
+
+use copypasta::{ClipboardContext, ClipboardProvider};
+use eframe::egui::{Color32, RichText, TextEdit, Ui};
+
+use crate::{
+    gui::{theme::COLOR_ACTION, COL_SPACING, ROW_SPACING},
+    primer_qc::{export_qc_report, qc_primers, qc_report_text, PrimerQc, QcLevel, QcThresholds},
+    state::State,
+    Selection,
+};
+
+const COLOR_PASS: Color32 = Color32::GREEN;
+const COLOR_WARN: Color32 = Color32::GOLD;
+const COLOR_FAIL: Color32 = Color32::LIGHT_RED;
+
+#[derive(Default)]
+pub struct PrimerQcUi {
+    pub thresholds: QcThresholds,
+    /// `None` if the report hasn't been run, or was closed.
+    pub results: Option<Vec<PrimerQc>>,
+}
+
+fn level_color(level: QcLevel) -> Color32 {
+    match level {
+        QcLevel::Pass => COLOR_PASS,
+        QcLevel::Warn => COLOR_WARN,
+        QcLevel::Fail => COLOR_FAIL,
+    }
+}
+
+fn float_edit(val: &mut f32, label: &str, ui: &mut Ui) {
+    ui.label(label);
+    let mut entry = format!("{:.0}", val);
+    if ui
+        .add(TextEdit::singleline(&mut entry).desired_width(24.))
+        .changed()
+    {
+        *val = entry.parse().unwrap_or(*val);
+    }
+}
+
+fn int_edit<T: ToString + std::str::FromStr + Copy>(val: &mut T, label: &str, ui: &mut Ui) {
+    ui.label(label);
+    let mut entry = val.to_string();
+    if ui
+        .add(TextEdit::singleline(&mut entry).desired_width(16.))
+        .changed()
+    {
+        *val = entry.parse().unwrap_or(*val);
+    }
+}
+
+fn thresholds_edit(thresholds: &mut QcThresholds, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        float_edit(&mut thresholds.tm_min, "TM min:", ui);
+        float_edit(&mut thresholds.tm_max, "max:", ui);
+        ui.add_space(COL_SPACING);
+
+        int_edit(&mut thresholds.gc_3p_min, "3' GC min:", ui);
+        int_edit(&mut thresholds.gc_3p_max, "max:", ui);
+        ui.add_space(COL_SPACING);
+
+        int_edit(&mut thresholds.dimer_max, "Dimer max:", ui);
+        int_edit(&mut thresholds.hairpin_stem_max, "Hairpin stem max:", ui);
+        int_edit(&mut thresholds.repeats_max, "Repeats max:", ui);
+    });
+}
+
+pub fn primer_qc_disp(state: &mut State, ui: &mut Ui) {
+    state.ui.file_dialogs.export_primer_qc.update(ui.ctx());
+
+    if let Some(path) = state.ui.file_dialogs.export_primer_qc.take_selected() {
+        if let Some(results) = &state.ui.primer_qc.results {
+            let text = qc_report_text(results, &state.generic[state.active].metadata.plasmid_name);
+            if let Err(e) = export_qc_report(&text, &path) {
+                eprintln!("Error exporting the primer QC report: {e}");
+            }
+        }
+    }
+
+    let mut run = false;
+    ui.horizontal(|ui| {
+        if ui
+            .button(RichText::new("QC report").color(COLOR_ACTION))
+            .on_hover_text(
+                "Check all primers against the thresholds below, and list any that fail, with reasons.",
+            )
+            .clicked()
+        {
+            run = true;
+        }
+
+        if state.ui.primer_qc.results.is_none() {
+            return;
+        }
+
+        if ui.button("Rerun").clicked() {
+            run = true;
+        }
+
+        if ui
+            .button("Copy")
+            .on_hover_text("Copy the report to the clipboard, as text.")
+            .clicked()
+        {
+            if let Some(results) = &state.ui.primer_qc.results {
+                let text =
+                    qc_report_text(results, &state.generic[state.active].metadata.plasmid_name);
+                let mut ctx = ClipboardContext::new().unwrap();
+                ctx.set_contents(text).unwrap();
+            }
+        }
+
+        if ui
+            .button("Export")
+            .on_hover_text("Save the report as a text file.")
+            .clicked()
+        {
+            state.ui.file_dialogs.export_primer_qc.save_file();
+        }
+
+        if ui.button("Close").clicked() {
+            state.ui.primer_qc.results = None;
+        }
+    });
+
+    if run {
+        state.sync_primer_matches(None);
+        state.sync_primer_metrics();
+        state.ui.primer_qc.results = Some(qc_primers(
+            &state.generic[state.active].primers,
+            &state.ui.primer_qc.thresholds,
+        ));
+    }
+
+    let results = match &state.ui.primer_qc.results {
+        Some(r) => r,
+        None => return,
+    };
+
+    thresholds_edit(&mut state.ui.primer_qc.thresholds, ui);
+    ui.add_space(ROW_SPACING / 2.);
+
+    let mut selected = None;
+    for qc in results {
+        let level = qc.level();
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(level.to_str()).color(level_color(level)));
+            if ui
+                .button(&qc.name)
+                .on_hover_text("Select this primer")
+                .clicked()
+            {
+                selected = Some(qc.primer_i);
+            }
+
+            for issue in &qc.issues {
+                ui.label(RichText::new(&issue.text).color(level_color(issue.level)));
+                ui.add_space(COL_SPACING / 2.);
+            }
+        });
+    }
+
+    if let Some(i) = selected {
+        state.ui.selected_item = Selection::Primer(i);
+    }
+
+    ui.add_space(ROW_SPACING);
+}
@@ -1,16 +1,34 @@
 //! This module contains code to the primer editor, QC etc.
 
-use eframe::egui::{Align, Color32, Layout, RichText, ScrollArea, TextEdit, Ui};
+use eframe::egui::{
+    Align, Color32, ComboBox, DragValue, Layout, RichText, ScrollArea, TextEdit, Ui,
+};
 use egui_extras::{Column, TableBuilder};
-use na_seq::{seq_from_str, seq_to_str_lower};
+use na_seq::{seq_from_str, seq_to_str_lower, Nucleotide};
 
 use crate::{
+    ambiguous::is_degenerate,
+    file_io::{
+        oligo_order::{export_order, ORDER_SCALES, PURIFICATIONS},
+        primer_csv::{export_primers_csv, import_primers_csv},
+    },
     gui::{
+        delete_confirm,
+        navigation::get_tab_names,
+        primer_library::primer_library_disp,
+        primer_qc::primer_qc_disp,
         theme::{COLOR_ACTION, COLOR_INFO},
         COL_SPACING, ROW_SPACING,
     },
-    primer::{make_amplification_primers, IonConcentrations, Primer, TuneSetting},
+    primer::{
+        make_amplification_primers, resuspend_volume, set_tm_target, synth_amount_nmol, tm_diff,
+        tune_primers, IonConcentrations, Mod5p, Primer, PrimerMods, ResuspendUi, SynthAmountUnit,
+        TuneSetting, TM_PAIR_MAX_DIFF, TM_TARGET,
+    },
+    primer_binding::{best_binding_site, PrimerBinding, END_3P_LEN},
+    primer_metrics::{heterodimer_score, ScoringWeights, HETERODIMER_DG_WARN},
     state::State,
+    util::disp_range,
     Selection,
 };
 
@@ -19,6 +37,8 @@ const TABLE_ROW_HEIGHT: f32 = 60.;
 const COLOR_GOOD: Color32 = Color32::GREEN;
 const COLOR_MARGINAL: Color32 = Color32::GOLD;
 const COLOR_BAD: Color32 = Color32::LIGHT_RED;
+// For 5' modifications, and tails.
+const COLOR_MODS: Color32 = Color32::from_rgb(220, 140, 255);
 
 pub const DEFAULT_TRIM_AMT: usize = 32 - 20;
 
@@ -29,7 +49,7 @@ pub const DEFAULT_TRIM_AMT: usize = 32 - 20;
 
 /// Color scores in each category according to these thresholds. These scores should be on a scale
 /// between 0 and 1.
-fn color_from_score(score: f32) -> Color32 {
+pub fn color_from_score(score: f32) -> Color32 {
     const SCORE_COLOR_THRESH: (f32, f32) = (0.5, 0.8);
 
     if score > SCORE_COLOR_THRESH.1 {
@@ -41,6 +61,61 @@ fn color_from_score(score: f32) -> Color32 {
     }
 }
 
+/// Edit the maximum acceptable TM difference between paired primers.
+pub fn tm_pair_max_diff_edit(val: &mut f32, ui: &mut Ui) {
+    ui.label("Max pair TM diff (°C):")
+        .on_hover_text("Warn if paired primers' melting temperatures differ by more than this.");
+    let mut entry = format!("{:.1}", val);
+    if ui
+        .add(TextEdit::singleline(&mut entry).desired_width(30.))
+        .changed()
+    {
+        *val = entry.parse().unwrap_or(TM_PAIR_MAX_DIFF);
+    }
+}
+
+/// Display the TM difference between two primers used together, with a warning if it's too large.
+pub fn tm_pair_disp(a: &Primer, b: &Primer, max_diff: f32, ui: &mut Ui) {
+    let diff = match tm_diff(a, b) {
+        Some(d) => d,
+        None => return,
+    };
+
+    if diff > max_diff {
+        ui.label(
+            RichText::new(format!("TM diff: {diff:.1}°C; exceeds {max_diff:.1}°C"))
+                .color(COLOR_BAD),
+        )
+        .on_hover_text(
+            "These primers' melting temperatures are far apart, so there may be no annealing \
+            temperature that works well for both. Consider tuning them.",
+        );
+    } else {
+        ui.label(RichText::new(format!("TM diff: {diff:.1}°C")).color(COLOR_GOOD));
+    }
+}
+
+/// Warn if two primers may form a 3' dimer with each other, e.g. a PCR pair.
+pub fn heterodimer_disp(a: &Primer, b: &Primer, ui: &mut Ui) {
+    let dg = heterodimer_score(&a.sequence, &b.sequence);
+    let text = format!("Dimer ΔG: {dg:.1} kcal/mol");
+
+    if dg < HETERODIMER_DG_WARN {
+        ui.label(RichText::new(text).color(COLOR_BAD))
+            .on_hover_text(format!(
+            "These primers' 3' ends may anneal to each other, forming primer dimers that reduce \
+            or prevent amplification. The most stable 3' dimer is below {HETERODIMER_DG_WARN:.0} \
+            kcal/mol. Consider redesigning one of them."
+        ));
+    } else {
+        ui.label(RichText::new(text).color(COLOR_GOOD))
+            .on_hover_text(
+            "The free energy of the most stable dimer where either primer's 3' end anneals to the \
+            other, at 37°C.",
+        );
+    }
+}
+
 /// Allows editing ion concentration, including float manip. Return if the response changed,
 /// so we can redo TM calcs downstream.
 fn ion_edit(val: &mut f32, label: &str, ui: &mut Ui) -> bool {
@@ -57,12 +132,62 @@ fn ion_edit(val: &mut f32, label: &str, ui: &mut Ui) -> bool {
     }
 }
 
+/// The match count with the active sequence. If there are none, and we've checked other tabs, list
+/// the ones it matches.
+fn match_disp(
+    count: usize,
+    tabs_matched: Option<&Vec<usize>>,
+    tab_names: &[(String, usize)],
+    ui: &mut Ui,
+) {
+    if count > 0 {
+        ui.label(RichText::new(count.to_string()).color(COLOR_GOOD))
+            .on_hover_text("Matches this sequence.");
+        return;
+    }
+
+    match tabs_matched {
+        Some(tabs) if !tabs.is_empty() => {
+            let names: Vec<_> = tabs
+                .iter()
+                .filter_map(|i| tab_names.get(*i).map(|(name, _)| name.as_str()))
+                .collect();
+
+            let text = if names.len() > 1 {
+                format!("0: {} +{}", names[0], names.len() - 1)
+            } else {
+                format!("0: {}", names.first().unwrap_or(&""))
+            };
+            ui.label(RichText::new(text).color(COLOR_MARGINAL))
+                .on_hover_text(format!(
+                    "Doesn't match this sequence. Matches: {}",
+                    names.join(", ")
+                ));
+        }
+        Some(_) => {
+            ui.label(RichText::new("0").color(COLOR_BAD))
+                .on_hover_text("Doesn't match this sequence, or any other open one.");
+        }
+        None => {
+            ui.label("0");
+        }
+    }
+}
+
 fn primer_table(state: &mut State, ui: &mut Ui) {
     let mut run_match_sync = None; // Avoids a double-mutation error.
 
+    let plasmid_names: &Vec<_> = &state
+        .generic
+        .iter()
+        .map(|v| v.metadata.plasmid_name.as_str())
+        .collect();
+    let tab_names = get_tab_names(&state.tabs_open, plasmid_names, true);
+
     TableBuilder::new(ui)
         .column(Column::initial(650.).resizable(true)) // Sequence
         .column(Column::initial(160.).resizable(true)) // Description
+        .column(Column::auto().resizable(true)) // Mods
         .column(Column::auto().resizable(true))// Len
         .column(Column::auto().resizable(true))// Len
         .column(Column::auto().resizable(true))// Matches
@@ -81,6 +206,10 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
             header.col(|ui| {
                 ui.heading("Name");
             });
+            header.col(|ui| {
+                ui.heading("Mods").on_hover_text("5' modifications (eg phosphorylation), and sequences appended to the 5' \
+                or 3' end, eg overhangs or tags. These are part of the synthesized primer, but not its template-matching region.");
+            });
             header.col(|ui| {
                 ui.heading("Len").on_hover_text("Number of nucleotides in the (tuned, if applicable) primer");
             });
@@ -88,7 +217,8 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                 ui.heading("Weight").on_hover_text("The weight of this primer, in Daltons.");
             });
             header.col(|ui| {
-                ui.heading("Mt").on_hover_text("Number of matches with the target sequence");
+                ui.heading("Mt").on_hover_text("Number of matches with the target sequence. If there are none, \
+                and open tabs have been checked, the ones it matches are listed.");
             });
             header.col(|ui| {
                 ui.heading("Qual").on_hover_text("Overall primer quality. This is an abstract estimate, taking all other listed factors into account.");
@@ -137,7 +267,7 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                                 primer.volatile.tune_setting.toggle_5p();
                                 // if primer.volatile.tunable_5p == TuneSetting::Disabled {
                                 // primer.run_calcs(&state.ion_concentrations[state.active]); // To re-sync the sequence without parts removed.
-                                primer.run_calcs(&state.ion_concentrations); // To re-sync the sequence without parts removed.
+                                primer.run_calcs(&state.ion_concentrations, &state.ui.scoring_weights); // To re-sync the sequence without parts removed.
                                 // }
                                 run_match_sync = Some(i);
                             }
@@ -147,11 +277,21 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                             );
 
                             if response.changed() {
+                                // Degenerate bases are allowed in searches, but not primers, which must
+                                // match the template exactly.
+                                if primer.volatile.sequence_input.bytes().any(is_degenerate) {
+                                    state.ui.primer_seq_warning = Some(format!(
+                                        "Removed degenerate bases (e.g. N, R, Y) from {}; primers must \
+                                        contain only A, C, T, and G.",
+                                        primer.name
+                                    ));
+                                }
+
                                 primer.sequence = seq_from_str(&primer.volatile.sequence_input);
                                 primer.volatile.sequence_input =
                                     seq_to_str_lower(&primer.sequence);
                                 // primer.run_calcs(&state.ion_concentrations[state.active]);
-                                primer.run_calcs(&state.ion_concentrations);
+                                primer.run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
                                 run_match_sync = Some(i);
                             }
 
@@ -166,7 +306,7 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                                 primer.volatile.tune_setting.toggle_3p();
                                 // if primer.volatile.tunable_3p == TuneSetting::Disabled {
                                 //     primer.run_calcs(&state.ion_concentrations[state.active]); // To re-sync the sequence without parts removed.
-                                    primer.run_calcs(&state.ion_concentrations); // To re-sync the sequence without parts removed.
+                                    primer.run_calcs(&state.ion_concentrations, &state.ui.scoring_weights); // To re-sync the sequence without parts removed.
                                 // }
                                 run_match_sync = Some(i);
                             }
@@ -179,7 +319,7 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                                         .button(RichText::new("Tune")).on_hover_text("Tune selected ends for this primer").clicked()
                                     {
                                         // primer.tune(&state.ion_concentrations[state.active]);
-                                        primer.tune(&state.ion_concentrations);
+                                        state.ui.primer_re_avoid.status = tune_primers([&mut *primer], &state.ion_concentrations, &state.ui.scoring_weights, &state.ui.primer_re_avoid.res);
                                         run_match_sync = Some(i);
                                     }
                                 }
@@ -188,7 +328,7 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                         });
 
                         // let updated_seq = primer_tune_display(primer, &state.ion_concentrations[state.active], ui);
-                        let updated_seq = primer_tune_display(primer, &state.ion_concentrations, ui);
+                        let updated_seq = primer_tune_display(primer, &state.ion_concentrations, &state.ui.scoring_weights, ui);
                         if updated_seq {
                             run_match_sync = Some(i);
                         }
@@ -198,6 +338,10 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                         ui.add(TextEdit::singleline(&mut primer.name).text_color(COLOR_INFO));
                     });
 
+                    row.col(|ui| {
+                        primer_mods_edit(&mut primer.mods, i, ui);
+                    });
+
                     row.col(|ui| {
                         let text = match &primer.volatile.metrics {
                             Some(m) => {
@@ -215,7 +359,11 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
                     });
 
                     row.col(|ui| {
-                        ui.label(primer.volatile.matches.len().to_string());
+                        let tabs_matched = state.volatile[state.active]
+                            .primer_tab_matches
+                            .as_ref()
+                            .and_then(|m| m.get(i));
+                        match_disp(primer.volatile.matches.len(), tabs_matched, &tab_names, ui);
                     });
 
                     row.col(|ui| {
@@ -301,6 +449,108 @@ fn primer_table(state: &mut State, ui: &mut Ui) {
     }
 }
 
+/// A button to export an oligo order sheet, and the scale and purification to order with.
+fn order_disp(state: &mut State, ui: &mut Ui) {
+    if ui
+        .button("Export for IDT")
+        .on_hover_text(
+            "Save this sequence's primers as a CSV order sheet for IDT's bulk input. Sequences \
+            are as tuned, and include tails and modifications.",
+        )
+        .clicked()
+    {
+        state.ui.file_dialogs.export_order.save_file();
+    }
+
+    let settings = &mut state.ui.order_settings;
+    let orig = settings.clone();
+
+    ComboBox::from_id_salt(4_700)
+        .width(70.)
+        .selected_text(settings.scale.to_str())
+        .show_ui(ui, |ui| {
+            for scale in ORDER_SCALES {
+                ui.selectable_value(&mut settings.scale, scale, scale.to_str());
+            }
+        })
+        .response
+        .on_hover_text("Synthesis scale");
+
+    ComboBox::from_id_salt(4_701)
+        .width(70.)
+        .selected_text(settings.purification.to_str())
+        .show_ui(ui, |ui| {
+            for purification in PURIFICATIONS {
+                ui.selectable_value(
+                    &mut settings.purification,
+                    purification,
+                    purification.to_str(),
+                );
+            }
+        })
+        .response
+        .on_hover_text("Purification");
+
+    if settings.scale != orig.scale || settings.purification != orig.purification {
+        state.save_prefs();
+    }
+}
+
+/// Handle the primer CSV import and export dialogs, including order sheets.
+fn handle_csv_dialogs(state: &mut State, ui: &mut Ui) {
+    state.ui.file_dialogs.import_primer_csv.update(ui.ctx());
+    state.ui.file_dialogs.export_primer_csv.update(ui.ctx());
+    state.ui.file_dialogs.export_order.update(ui.ctx());
+
+    if let Some(path) = state.ui.file_dialogs.export_order.take_selected() {
+        let primers = &state.generic[state.active].primers;
+        // Primers without a sequence aren't included.
+        let count = primers.iter().filter(|p| !p.sequence.is_empty()).count();
+        state.ui.primer_csv_status = Some(
+            match export_order(primers, &state.ui.order_settings, &path) {
+                Ok(_) => format!("Exported an order sheet for {count} primers"),
+                Err(e) => {
+                    eprintln!("Error exporting the order sheet: {e}");
+                    format!("Error exporting the order sheet: {e}")
+                }
+            },
+        );
+    } else if let Some(path) = state.ui.file_dialogs.export_primer_csv.take_selected() {
+        let primers = &state.generic[state.active].primers;
+        state.ui.primer_csv_status = Some(match export_primers_csv(primers, &path) {
+            Ok(_) => format!("Exported {} primers", primers.len()),
+            Err(e) => {
+                eprintln!("Error exporting primers: {e}");
+                format!("Error exporting primers: {e}")
+            }
+        });
+    } else if let Some(path) = state.ui.file_dialogs.import_primer_csv.take_selected() {
+        state.ui.primer_csv_status = Some(match import_primers_csv(&path) {
+            Ok((primers, skipped)) => {
+                let count = primers.len();
+                if count > 0 {
+                    state.snapshot();
+                    state.generic[state.active].primers.extend(primers);
+                    state.sync_primer_matches(None);
+                    state.sync_primer_metrics();
+                }
+
+                if skipped > 0 {
+                    format!(
+                        "Imported {count} primers; skipped {skipped} rows with invalid sequences"
+                    )
+                } else {
+                    format!("Imported {count} primers")
+                }
+            }
+            Err(e) => {
+                eprintln!("Error importing primers: {e}");
+                format!("Error importing primers: {e}")
+            }
+        });
+    }
+}
+
 pub fn primer_details(state: &mut State, ui: &mut Ui) {
     ScrollArea::vertical().show(ui, |ui| {
         ui.horizontal(|ui| {
@@ -308,6 +558,7 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
                 .button("➕ Add primer")
                 .on_hover_text("Adds a primer to the list below.");
             if add_btn.clicked() {
+                state.snapshot();
                 state.generic[state.active].primers.push(Default::default())
             }
 
@@ -321,17 +572,50 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
 
             let mut sync_primer_matches = false; // Prevents a double-borrow error.
             if ui.button("Tune all").clicked() {
-                for primer in &mut state.generic[state.active].primers {
-                    // primer.tune(&state.ion_concentrations[state.active]);
-                    primer.tune(&state.ion_concentrations);
-                    sync_primer_matches = true;
-                }
+                // primer.tune(&state.ion_concentrations[state.active]);
+                state.ui.primer_re_avoid.status = tune_primers(
+                    &mut state.generic[state.active].primers,
+                    &state.ion_concentrations,
+                    &state.ui.scoring_weights,
+                    &state.ui.primer_re_avoid.res,
+                );
+                sync_primer_matches = true;
             }
 
             if sync_primer_matches {
                 state.sync_primer_matches(None);
             }
 
+            if state.generic.len() > 1
+                && ui
+                    .button("Check open tabs")
+                    .on_hover_text("Find which other open sequences each primer matches.")
+                    .clicked()
+            {
+                state.sync_primer_tab_matches();
+            }
+
+            if ui
+                .button("Import primers (CSV)")
+                .on_hover_text(
+                    "Add primers from a CSV or TSV file, with columns name, sequence, and \
+                    (optionally) description.",
+                )
+                .clicked()
+            {
+                state.ui.file_dialogs.import_primer_csv.select_file();
+            }
+
+            if ui
+                .button("Export primers (CSV)")
+                .on_hover_text("Save this sequence's primers as a CSV or TSV file.")
+                .clicked()
+            {
+                state.ui.file_dialogs.export_primer_csv.save_file();
+            }
+
+            order_disp(state, ui);
+
             ui.add_space(COL_SPACING * 2.);
 
             ui.add_space(2. * COL_SPACING);
@@ -339,24 +623,69 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
             ui.heading("Ions: (mMol)");
 
             // if ion_edit(&mut state.ion_concentrations.monovalent, "Na+ and K+", ui)
-            if ion_edit(&mut state.ion_concentrations.monovalent, "Na+ and K+", ui)
+            let mut recalc = ion_edit(&mut state.ion_concentrations.monovalent, "Na+ and K+", ui)
                 || ion_edit(&mut state.ion_concentrations.divalent, "mg2+", ui)
                 || ion_edit(&mut state.ion_concentrations.dntp, "dNTP", ui)
-                || ion_edit(&mut state.ion_concentrations.primer, "primer (nM)", ui)
+                || ion_edit(&mut state.ion_concentrations.primer, "primer (nM)", ui);
+
+            ui.add_space(COL_SPACING);
+
+            ui.label("TM target (°C):").on_hover_text("Primer quality scores, and tuning, favor melting temperatures close to this.");
+            let mut entry = format!("{:.0}", state.ui.tm_target);
+            if ui.add(TextEdit::singleline(&mut entry).desired_width(20.)).changed() {
+                state.ui.tm_target = entry.parse().unwrap_or(TM_TARGET);
+                set_tm_target(state.ui.tm_target);
+                recalc = true;
+            }
+
+            ui.add_space(COL_SPACING);
+
+            tm_pair_max_diff_edit(&mut state.ui.tm_pair_max_diff, ui);
+
+            ui.add_space(COL_SPACING);
+
+            if ui
+                .button("Save as default")
+                .on_hover_text("Start with these ion concentrations each time the program opens. The TM target and max pair TM diff are saved automatically.")
+                .clicked()
             {
+                state.ui.ion_defaults = state.ion_concentrations.clone();
+                state.save_prefs();
+            }
+
+            if ui
+                .button("Reset")
+                .on_hover_text("Reset ion concentrations to your saved defaults.")
+                .clicked()
+            {
+                state.ion_concentrations = state.ui.ion_defaults.clone();
+                recalc = true;
+            }
+
+            if recalc {
                 for primer in &mut state.generic[state.active].primers {
                     // primer.run_calcs(&state.ion_concentrations[state.active]); // Note: We only need to run the TM calc.
-                    primer.run_calcs(&state.ion_concentrations); // Note: We only need to run the TM calc.
+                    primer.run_calcs(&state.ion_concentrations, &state.ui.scoring_weights); // Note: We only need to run the TM calc.
                 }
             }
         });
 
+        re_avoid_disp(state, ui);
+        primer_len_disp(state, ui);
+        scoring_weights_disp(state, ui);
+
+        ui.add_space(ROW_SPACING / 2.);
+        primer_library_disp(state, ui);
+        primer_qc_disp(state, ui);
+
         ui.label("Tuning instructions: Include more of the target sequence than required on the end[s] that can be tuned. These are the \
      ends that do not define your insert, gene of interest, insertion point etc. Mark that end as tunable using the \"T\" button. \
 To learn about a table column, mouse over it.");
 
         ui.add_space(ROW_SPACING);
 
+        delete_confirm(state, ui);
+
         if let Selection::Primer(sel_i) = state.ui.selected_item {
             ui.horizontal(|ui| {
                 if sel_i + 1 > state.generic[state.active].primers.len() {
@@ -370,6 +699,9 @@ To learn about a table column, mouse over it.");
                     return;
                 }
 
+                // The cached binding site is by index.
+                let primer_count = state.generic[state.active].primers.len();
+
                 if ui.button(RichText::new("⏶")).clicked() {
                     // todo: Arrow icons
                     if sel_i != 0 {
@@ -387,9 +719,16 @@ To learn about a table column, mouse over it.");
                     .button(RichText::new("Delete 🗑").color(Color32::RED))
                     .clicked()
                 {
+                    state.snapshot();
                     state.generic[state.active].primers.remove(sel_i);
                 }
 
+                if state.generic[state.active].primers.len() != primer_count
+                    || state.ui.selected_item != Selection::Primer(sel_i)
+                {
+                    state.volatile[state.active].primer_binding = None;
+                }
+
                 if ui
                     .button(RichText::new("Deselect").color(COLOR_ACTION))
                     .clicked()
@@ -405,20 +744,456 @@ To learn about a table column, mouse over it.");
                 }
 
                 ui.heading(&format!("Selected: {}", &state.generic[state.active].primers[sel_i].name));
+
+                ui.add_space(COL_SPACING);
+
+                let primers = &state.generic[state.active].primers;
+                if primers.len() >= 2 {
+                    if state.ui.primer_pair_partner >= primers.len() {
+                        state.ui.primer_pair_partner = 0;
+                    }
+
+                    ui.label("Pair with:");
+                    ComboBox::from_id_salt(3_600)
+                        .width(80.)
+                        .selected_text(&primers[state.ui.primer_pair_partner].name)
+                        .show_ui(ui, |ui| {
+                            for (i, primer) in primers.iter().enumerate() {
+                                if i != sel_i {
+                                    ui.selectable_value(&mut state.ui.primer_pair_partner, i, &primer.name);
+                                }
+                            }
+                        });
+
+                    if state.ui.primer_pair_partner != sel_i {
+                        tm_pair_disp(&primers[sel_i], &primers[state.ui.primer_pair_partner], state.ui.tm_pair_max_diff, ui);
+                    }
+                }
             });
 
+            if sel_i < state.generic[state.active].primers.len() {
+                ui.add_space(ROW_SPACING / 2.);
+                resuspend_calc(&state.generic[state.active].primers[sel_i], &mut state.ui.primer_resuspend, ui);
+
+                ui.add_space(ROW_SPACING / 2.);
+                binding_site_disp(state, sel_i, ui);
+            }
+
             ui.add_space(ROW_SPACING);
         }
 
+        handle_csv_dialogs(state, ui);
+
+        if let Some(status) = state.ui.primer_csv_status.clone() {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+                if ui.button("Dismiss").clicked() {
+                    state.ui.primer_csv_status = None;
+                }
+            });
+            ui.add_space(ROW_SPACING / 2.);
+        }
+
+        if let Some(warning) = state.ui.primer_seq_warning.clone() {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(warning).color(Color32::LIGHT_RED));
+                if ui.button("Dismiss").clicked() {
+                    state.ui.primer_seq_warning = None;
+                }
+            });
+            ui.add_space(ROW_SPACING / 2.);
+        }
+
         primer_table(state, ui);
     });
 }
 
+/// Edit a primer length setting. Returns true if it changed.
+fn len_edit(val: &mut usize, label: &str, hover: &str, ui: &mut Ui) -> bool {
+    ui.label(label).on_hover_text(hover);
+    let mut entry = val.to_string();
+    if ui
+        .add(TextEdit::singleline(&mut entry).desired_width(20.))
+        .changed()
+    {
+        *val = entry.parse().unwrap_or(0);
+        return true;
+    }
+    false
+}
+
+/// Edit the lengths used when designing and evaluating primers.
+fn primer_len_disp(state: &mut State, ui: &mut Ui) {
+    let lens = &mut state.ui.primer_lens;
+
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= len_edit(
+            &mut lens.min_len,
+            "Min primer len:",
+            "Primers shorter than this aren't evaluated, and tuning won't trim below it.",
+            ui,
+        );
+        ui.add_space(COL_SPACING);
+
+        ui.label("Untrimmed len:").on_hover_text(
+            "The length of designed primers, prior to tuning. Longer values allow longer \
+            overlaps, at the cost of tuning time.",
+        );
+        changed |= len_edit(
+            &mut lens.untrimmed_insert,
+            "insert",
+            "Cloning primers that amplify the insert, per side.",
+            ui,
+        );
+        changed |= len_edit(
+            &mut lens.untrimmed_vector,
+            "vector",
+            "Cloning primers that amplify the vector.",
+            ui,
+        );
+        changed |= len_edit(
+            &mut lens.untrimmed_amplification,
+            "whole seq",
+            "Primers that amplify the entire sequence.",
+            ui,
+        );
+
+        if ui
+            .button("Reset")
+            .on_hover_text("Reset primer lengths to their defaults.")
+            .clicked()
+        {
+            *lens = Default::default();
+            changed = true;
+        }
+    });
+
+    if let Err(e) = state.ui.primer_lens.validate() {
+        ui.label(RichText::new(format!("{e}; using the defaults.")).color(COLOR_BAD));
+    }
+
+    if changed {
+        state.ui.primer_lens.apply();
+        for primer in &mut state.generic[state.active].primers {
+            primer.run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
+        }
+        state.save_prefs();
+    }
+}
+
+/// Edit a score weight. Returns true if it changed.
+fn weight_edit(val: &mut f32, label: &str, hover: &str, ui: &mut Ui) -> bool {
+    ui.label(label).on_hover_text(hover);
+    // Negative weights make no sense, and could zero the sum.
+    ui.add(
+        DragValue::new(val)
+            .speed(0.1)
+            .range(0.0..=10.0)
+            .fixed_decimals(1),
+    )
+    .changed()
+}
+
+/// Edit how much each metric contributes to primer quality scores.
+fn scoring_weights_disp(state: &mut State, ui: &mut Ui) {
+    let weights = &mut state.ui.scoring_weights;
+
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Score weights:").on_hover_text(
+            "How much each metric contributes to the quality score, relative to the others. \
+            Tuning selects primer lengths with the best score.",
+        );
+        changed |= weight_edit(
+            &mut weights.tm,
+            "TM",
+            "Closeness of the melting temperature to the TM target.",
+            ui,
+        );
+        changed |= weight_edit(&mut weights.gc, "GC", "GC content near 50%.", ui);
+        changed |= weight_edit(
+            &mut weights.stability_3p,
+            "3' stab",
+            "2-3 Gs or Cs in the last 5 nucleotides.",
+            ui,
+        );
+        changed |= weight_edit(&mut weights.dimer, "Dimer", "Self-end dimers.", ui);
+        changed |= weight_edit(&mut weights.len, "Len", "Closeness to an ideal length.", ui);
+        changed |= weight_edit(
+            &mut weights.repeats,
+            "Repeats",
+            "Runs of a single nucleotide, dinucleotide repeats, and repeated triplets.",
+            ui,
+        );
+
+        if ui
+            .button("Reset")
+            .on_hover_text("Reset score weights to their defaults.")
+            .clicked()
+        {
+            *weights = Default::default();
+            changed = true;
+        }
+    });
+
+    if changed {
+        for data in &mut state.generic {
+            for primer in &mut data.primers {
+                primer.run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
+            }
+        }
+        state.save_prefs();
+    }
+}
+
+/// Select restriction enzymes whose recognition sites tuned primers must not contain.
+fn re_avoid_disp(state: &mut State, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Avoid RE sites:").on_hover_text(
+            "When tuning or designing primers, don't select lengths that contain these enzymes' \
+            recognition sites. This avoids internal sites that interfere with downstream cloning.",
+        );
+
+        let mut removed = None;
+        for (i, re) in state.ui.primer_re_avoid.res.iter().enumerate() {
+            if ui
+                .button(&re.name)
+                .on_hover_text("Click to remove")
+                .clicked()
+            {
+                removed = Some(i);
+            }
+        }
+
+        if let Some(i) = removed {
+            state.ui.primer_re_avoid.res.remove(i);
+        }
+
+        ComboBox::from_id_salt(3_200)
+            .width(80.)
+            .selected_text("Add")
+            .show_ui(ui, |ui| {
+                for re in &state.restriction_enzyme_lib {
+                    if state.ui.primer_re_avoid.res.contains(re) {
+                        continue;
+                    }
+                    if ui.selectable_label(false, &re.name).clicked() {
+                        state.ui.primer_re_avoid.res.push(re.clone());
+                    }
+                }
+            });
+    });
+
+    if let Some(status) = &state.ui.primer_re_avoid.status {
+        ui.label(RichText::new(status).color(COLOR_BAD));
+    }
+}
+
+/// Display a row of nucleotides, with each base's color set by whether it matches. Bases in the
+/// primer's 3' end are underlined.
+fn binding_row(
+    label: &str,
+    seq: &[Nucleotide],
+    base_matches: &[bool],
+    match_color: Color32,
+    ui: &mut Ui,
+) {
+    let end_3p = seq.len().saturating_sub(END_3P_LEN);
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.;
+        ui.label(RichText::new(format!("{label:<10}5' ")).monospace());
+
+        for (i, (nt, matches)) in seq.iter().zip(base_matches).enumerate() {
+            let color = if *matches { match_color } else { COLOR_BAD };
+            let mut text = RichText::new(nt.to_str_lower().to_uppercase())
+                .color(color)
+                .monospace();
+            if i >= end_3p {
+                text = text.underline();
+            }
+            ui.label(text);
+        }
+
+        ui.label(RichText::new(" 3'").monospace());
+    });
+}
+
+/// Show where the selected primer binds best, allowing mismatches, with each base colored by whether
+/// it matches the template. Mismatches at the 3' end are the most likely to prevent extension.
+fn binding_site_disp(state: &mut State, primer_i: usize, ui: &mut Ui) {
+    let out_of_date = match &state.volatile[state.active].primer_binding {
+        Some(b) => b.primer_i != primer_i,
+        None => true,
+    };
+
+    if out_of_date {
+        let primer = &state.generic[state.active].primers[primer_i];
+        let seq = state.get_seq();
+
+        let binding = PrimerBinding {
+            primer_i,
+            site: best_binding_site(&primer.sequence, seq),
+            sites_3p_anneal: primer.match_3p_anchored(seq).len(),
+        };
+        state.volatile[state.active].primer_binding = Some(binding);
+    }
+
+    let binding = match &state.volatile[state.active].primer_binding {
+        Some(b) => b,
+        None => return,
+    };
+    let primer = &state.generic[state.active].primers[primer_i];
+
+    let site = match &binding.site {
+        Some(s) => s,
+        None => {
+            ui.label(
+                RichText::new(
+                    "No binding site: fewer than half the primer's bases match anywhere.",
+                )
+                .color(COLOR_BAD),
+            );
+            return;
+        }
+    };
+
+    let mismatches = site.mismatches();
+    let mismatches_3p = site.mismatches_3p();
+
+    ui.horizontal(|ui| {
+        ui.label("Best binding site:");
+        ui.label(format!(
+            "{} ({})",
+            disp_range(site.range),
+            site.direction.to_str_short()
+        ));
+        ui.add_space(COL_SPACING);
+
+        let positions: Vec<_> = mismatches.iter().map(|p| p.to_string()).collect();
+        if mismatches.is_empty() {
+            ui.label(RichText::new("Exact match").color(COLOR_GOOD));
+        } else if mismatches_3p == 0 {
+            ui.label(
+                RichText::new(format!("3' end matches. Mismatches at {}", positions.join(", ")))
+                    .color(COLOR_MARGINAL),
+            );
+        } else {
+            ui.label(
+                RichText::new(format!(
+                    "{mismatches_3p} mismatches in the last {END_3P_LEN} bases; extension is unlikely. \
+                    Mismatches at {}",
+                    positions.join(", ")
+                ))
+                .color(COLOR_BAD),
+            );
+        }
+        ui.label("(from 5')");
+
+        // Extra priming sites may explain spurious products.
+        if binding.sites_3p_anneal > 1 {
+            ui.add_space(COL_SPACING);
+            ui.label(
+                RichText::new(format!("3' end anneals at {} sites", binding.sites_3p_anneal))
+                    .color(COLOR_MARGINAL),
+            )
+            .on_hover_text(
+                "Sites where the primer's 3' end anneals may prime extension, even if the rest of \
+                the primer doesn't match. These may produce spurious products.",
+            );
+        }
+    });
+
+    binding_row(
+        "Primer",
+        &primer.sequence,
+        &site.base_matches,
+        COLOR_GOOD,
+        ui,
+    );
+    binding_row(
+        "Template",
+        &site.template,
+        &site.base_matches,
+        Color32::LIGHT_GRAY,
+        ui,
+    );
+}
+
+/// Calculates how much water to add to a synthesized primer, to reach a given stock concentration.
+fn resuspend_calc(primer: &Primer, data: &mut ResuspendUi, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Resuspend. Amount synthesized:");
+        ion_edit(&mut data.amount, "", ui);
+
+        ComboBox::from_id_salt(2_500)
+            .width(70.)
+            .selected_text(data.unit.to_string())
+            .show_ui(ui, |ui| {
+                for unit in [SynthAmountUnit::Nmol, SynthAmountUnit::Od260] {
+                    ui.selectable_value(&mut data.unit, unit, unit.to_string());
+                }
+            });
+
+        ui.add_space(COL_SPACING / 2.);
+        ion_edit(&mut data.stock_conc, "Stock conc (µM):", ui);
+
+        ui.add_space(COL_SPACING);
+
+        // Tails and such are part of the synthesized primer.
+        let seq = primer.synthesis_seq();
+        let nmol = synth_amount_nmol(data.amount, data.unit, &seq);
+        // Weight is in g/mol, ie µg/µmol.
+        let mass = nmol * primer.volatile.weight / 1_000.;
+        let water = resuspend_volume(nmol, data.stock_conc);
+
+        if data.unit == SynthAmountUnit::Od260 {
+            ui.label(format!("{nmol:.1} nmol"));
+        }
+        ui.label(format!("{mass:.1} µg"));
+
+        ui.label("Add water:");
+        ui.heading(RichText::new(format!("{water:.1} µL")).color(COLOR_INFO));
+    })
+    .response
+    .on_hover_text(
+        "OD260 conversion uses an extinction coefficient estimated from base composition; \
+        it's approximate. Prefer the nmol value from the synthesis spec sheet if available.",
+    );
+}
+
+/// Edit a primer's 5' modification, and tails.
+fn primer_mods_edit(mods: &mut PrimerMods, id: usize, ui: &mut Ui) {
+    ComboBox::from_id_salt(2_000 + id)
+        .width(80.)
+        .selected_text(mods.mod_5p.to_string())
+        .show_ui(ui, |ui| {
+            for mod_ in [Mod5p::None, Mod5p::Phosphate, Mod5p::Biotin, Mod5p::Fam] {
+                ui.selectable_value(&mut mods.mod_5p, mod_, mod_.to_string());
+            }
+        });
+
+    ui.horizontal(|ui| {
+        for (label, tail) in [("5'", &mut mods.tail_5p), ("3'", &mut mods.tail_3p)] {
+            ui.label(label);
+            let mut tail_input = seq_to_str_lower(tail);
+            if ui
+                .add(TextEdit::singleline(&mut tail_input).desired_width(60.))
+                .changed()
+            {
+                *tail = seq_from_str(&tail_input);
+            }
+        }
+    });
+}
+
 /// Shows below each primer sequence. Data and controls on trimming primer size for optimization.
 /// Returns wheather a button was clicked.
 fn primer_tune_display(
     primer: &mut Primer,
     ion_concentrations: &IonConcentrations,
+    weights: &ScoringWeights,
     ui: &mut Ui,
 ) -> bool {
     // This avoids a double-mutable error
@@ -486,6 +1261,18 @@ fn primer_tune_display(
 
         // This section shows the trimmed sequence, with the removed parts visible to the left and right.
         ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+            // Modifications and tails are part of the synthesized primer, but not the matching region.
+            if !primer.mods.is_empty() {
+                ui.label(
+                    RichText::new(format!(
+                        "{}{}",
+                        primer.mods.mod_5p.order_code(),
+                        seq_to_str_lower(&primer.mods.tail_5p).to_uppercase()
+                    ))
+                    .color(COLOR_MODS),
+                );
+            }
+
             ui.label(RichText::new(&primer.volatile.seq_removed_5p).color(Color32::GRAY));
             ui.add_space(COL_SPACING / 2.);
 
@@ -498,6 +1285,13 @@ fn primer_tune_display(
 
             ui.add_space(COL_SPACING / 2.);
             ui.label(RichText::new(&primer.volatile.seq_removed_3p).color(Color32::GRAY));
+
+            if !primer.mods.tail_3p.is_empty() {
+                ui.label(
+                    RichText::new(seq_to_str_lower(&primer.mods.tail_3p).to_uppercase())
+                        .color(COLOR_MODS),
+                );
+            }
         });
 
         // Note: We need to reverse the item order for this method of right-justifying to work.
@@ -522,7 +1316,7 @@ fn primer_tune_display(
         });
 
         if tuned {
-            primer.run_calcs(ion_concentrations);
+            primer.run_calcs(ion_concentrations, weights);
         }
     });
     tuned
@@ -1,22 +1,30 @@
 //! GUI code for saving and loading. Calls business logic in `file_io/save.rs`.
 
-use std::{env, path::Path};
+use std::{env, path::Path, sync::mpsc::TryRecvError, time::Duration};
 
-use eframe::egui::Ui;
+use eframe::egui::{Button, Color32, ComboBox, RichText, TextEdit, Ui};
 use egui_file_dialog::FileDialog;
 
 use crate::{
+    external_websites::fetch_genbank_async,
     file_io::{
         genbank::export_genbank,
+        gff::{export_gff, import_gff},
+        project::{load_project, save_project},
+        report::{export_report, ReportFormat},
         save,
-        save::{export_fasta, load_import, StateToSave},
+        save::{
+            export_all, export_fasta, import_annotations, load_import, ExportFormat, StateToSave,
+        },
         snapgene::export_snapgene,
+        PrimerExport,
     },
-    gui::{navigation::Tab, set_window_title},
+    gui::{circle::export_svg, map_image::export_png, navigation::Tab, set_window_title},
     state::State,
+    util::strip_filename_chars,
 };
 
-fn save_button(
+pub fn save_button(
     dialog: &mut FileDialog,
     plasmid_name: &str,
     extension: &str,
@@ -31,7 +39,9 @@ fn save_button(
             let name = if plasmid_name.is_empty() {
                 "a_plasmid".to_string()
             } else {
-                plasmid_name.to_lowercase().replace(' ', "_")
+                strip_filename_chars(plasmid_name)
+                    .to_lowercase()
+                    .replace(' ', "_")
             };
             format!("{name}.{extension}")
         };
@@ -42,6 +52,71 @@ fn save_button(
     }
 }
 
+/// Fetch a GenBank record from NCBI by accession, and open it in a new tab. The fetch runs in a
+/// separate thread; we poll for its result here.
+fn ncbi_fetch(state: &mut State, ui: &mut Ui) {
+    let mut fetched = None;
+    if let Some(rx) = &state.ui.ncbi_fetch.pending {
+        match rx.try_recv() {
+            Ok(result) => fetched = Some(result),
+            Err(TryRecvError::Empty) => {
+                // Keep polling while the UI is otherwise idle.
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+            }
+            Err(TryRecvError::Disconnected) => {
+                fetched = Some(Err("The NCBI fetch stopped unexpectedly".to_owned()));
+            }
+        }
+    }
+
+    if let Some(result) = fetched {
+        state.ui.ncbi_fetch.pending = None;
+        match result {
+            Ok(generic) => {
+                state.ui.ncbi_fetch.status = None;
+                state.load(&StateToSave {
+                    generic,
+                    ..Default::default()
+                });
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                state.ui.ncbi_fetch.status = Some(e);
+            }
+        }
+    }
+
+    let response = ui.add(
+        TextEdit::singleline(&mut state.ui.ncbi_fetch.accession)
+            .hint_text("Accession")
+            .desired_width(80.),
+    );
+    if response.gained_focus() {
+        state.ui.text_edit_active = true; // Disable character entries in the sequence.
+    }
+
+    let fetching = state.ui.ncbi_fetch.pending.is_some();
+    let text = if fetching {
+        "Fetching..."
+    } else {
+        "Fetch NCBI"
+    };
+
+    if ui
+        .add_enabled(!fetching, Button::new(text))
+        .on_hover_text("Download a sequence from NCBI's nucleotide database by GenBank accession, and open it in a new tab.")
+        .clicked()
+        && !state.ui.ncbi_fetch.accession.trim().is_empty()
+    {
+        state.ui.ncbi_fetch.status = None;
+        state.ui.ncbi_fetch.pending = Some(fetch_genbank_async(&state.ui.ncbi_fetch.accession));
+    }
+
+    if let Some(status) = &state.ui.ncbi_fetch.status {
+        ui.label(RichText::new(status).color(Color32::LIGHT_RED));
+    }
+}
+
 fn load_button(dialog: &mut FileDialog, text: &str, hover_text: &str, ui: &mut Ui) {
     if ui.button(text).on_hover_text(hover_text).clicked() {
         dialog.select_file();
@@ -80,6 +155,28 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
         ui,
     );
 
+    ncbi_fetch(state, ui);
+
+    save_button(
+        &mut state.ui.file_dialogs.save_project,
+        "project",
+        "pcadproj",
+        "Save project",
+        "Save all open tabs, the primer library, and workspace settings to a single file.",
+        ui,
+    );
+
+    load_button(
+        &mut state.ui.file_dialogs.load_project,
+        "Open project",
+        "Open a project file. This replaces the open tabs.",
+        ui,
+    );
+
+    if let Some(status) = &state.ui.project_status {
+        ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+    }
+
     save_button(
         &mut state.ui.file_dialogs.export_fasta,
         &state.generic[state.active].metadata.plasmid_name,
@@ -107,6 +204,118 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
         ui,
     );
 
+    save_button(
+        &mut state.ui.file_dialogs.export_gff,
+        &state.generic[state.active].metadata.plasmid_name,
+        "gff3",
+        "Exp GFF3",
+        "Export features in the GFF3 format. This does not include the sequence.",
+        ui,
+    );
+
+    ui.menu_button("⏷", |ui| {
+        ui.label("Primers in SnapGene and GFF3 exports:");
+        for option in [
+            PrimerExport::Native,
+            PrimerExport::Both,
+            PrimerExport::Features,
+        ] {
+            ui.radio_value(&mut state.ui.primer_export, option, option.to_string());
+        }
+        ui.label(
+            RichText::new(
+                "Binding sites are exported as primer features, for tools that don't read primer \
+                records. GenBank always stores primers as binding sites.",
+            )
+            .small(),
+        );
+    });
+
+    load_button(
+        &mut state.ui.file_dialogs.import_gff,
+        "Imp GFF3",
+        "Add features from a GFF3 file to this sequence, by coordinate.",
+        ui,
+    );
+
+    if let Some(status) = &state.ui.gff_status {
+        ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+    }
+
+    load_button(
+        &mut state.ui.file_dialogs.import_annotations,
+        "Imp annotations",
+        "Add features from another file of the same construct (GenBank, SnapGene, or PlasCAD), \
+        keeping this sequence. Features are placed by coordinate.",
+        ui,
+    );
+    ui.checkbox(&mut state.ui.import_annotation_primers, "primers")
+        .on_hover_text("Include primers when importing annotations.");
+
+    if let Some(status) = &state.ui.import_annotation_status {
+        ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+    }
+
+    if ui
+        .button("Export all")
+        .on_hover_text("Export all open tabs to a folder, in the format selected to the right. Files are named by plasmid name.")
+        .clicked()
+    {
+        state.ui.file_dialogs.export_all.pick_directory();
+    }
+
+    ComboBox::from_id_salt(4_000)
+        .width(80.)
+        .selected_text(state.ui.export_all_format.to_string())
+        .show_ui(ui, |ui| {
+            for format in [
+                ExportFormat::Pcad,
+                ExportFormat::GenBank,
+                ExportFormat::SnapGene,
+                ExportFormat::Fasta,
+            ] {
+                ui.selectable_value(&mut state.ui.export_all_format, format, format.to_string());
+            }
+        });
+
+    if let Some(status) = &state.ui.export_all_status {
+        ui.label(RichText::new(status).color(Color32::LIGHT_GRAY));
+    }
+
+    save_button(
+        &mut state.ui.file_dialogs.export_report,
+        &state.generic[state.active].metadata.plasmid_name,
+        state.ui.report.format.extension(),
+        "Exp report",
+        "Export a human-readable report of this sequence, e.g. for a lab notebook. Set its contents \
+        and format using the menu to the right.",
+        ui,
+    );
+
+    ui.menu_button("⏷", |ui| {
+        let report = &mut state.ui.report;
+        ui.checkbox(&mut report.metadata, "Metadata");
+        ui.checkbox(&mut report.features, "Features");
+        ui.checkbox(&mut report.primers, "Primers");
+        ui.checkbox(&mut report.re_sites, "RE sites");
+        ui.checkbox(&mut report.sequence, "Sequence");
+
+        ui.separator();
+        for format in [ReportFormat::Text, ReportFormat::Html] {
+            ui.radio_value(&mut report.format, format, format.to_string());
+        }
+    });
+
+    save_button(
+        &mut state.ui.file_dialogs.export_svg,
+        &state.generic[state.active].metadata.plasmid_name,
+        "svg",
+        "Export map (SVG)",
+        "Export the circular map as a vector image, e.g. for publication figures. Layers hidden in \
+        the sequence view are omitted.",
+        ui,
+    );
+
     // todo: DRY.
     let ctx = ui.ctx();
 
@@ -115,6 +324,15 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
     state.ui.file_dialogs.export_fasta.update(ctx);
     state.ui.file_dialogs.export_genbank.update(ctx);
     state.ui.file_dialogs.export_dna.update(ctx);
+    state.ui.file_dialogs.export_all.update(ctx);
+    state.ui.file_dialogs.export_report.update(ctx);
+    state.ui.file_dialogs.export_gff.update(ctx);
+    state.ui.file_dialogs.import_gff.update(ctx);
+    state.ui.file_dialogs.import_annotations.update(ctx);
+    state.ui.file_dialogs.save_project.update(ctx);
+    state.ui.file_dialogs.load_project.update(ctx);
+    state.ui.file_dialogs.export_svg.update(ctx);
+    state.ui.file_dialogs.export_png.update(ctx);
 
     let mut sync = false;
 
@@ -130,6 +348,7 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
                     path: Some(path.to_owned()),
                     ab1: false,
                 };
+                state.volatile[state.active].unsaved = false;
                 set_window_title(&state.tabs_open[state.active], ui);
                 state.save_prefs(); // Save opened tabs.
             }
@@ -138,6 +357,7 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
     } else if let Some(path) = state.ui.file_dialogs.export_fasta.take_selected() {
         match export_fasta(
             state.get_seq(),
+            &state.generic[state.active].ambiguous,
             &state.generic[state.active].metadata.plasmid_name,
             &path,
         ) {
@@ -146,6 +366,7 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
                     path: Some(path.to_owned()),
                     ab1: false,
                 };
+                state.volatile[state.active].unsaved = false;
                 set_window_title(&state.tabs_open[state.active], ui);
                 state.save_prefs(); // Save opened tabs.
             }
@@ -165,18 +386,127 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
                     path: Some(path.to_owned()),
                     ab1: false,
                 };
+                state.volatile[state.active].unsaved = false;
                 set_window_title(&state.tabs_open[state.active], ui);
                 state.save_prefs(); // Save opened tabs.
             }
             Err(e) => eprintln!("Error exporting to GenBank: {:?}", e),
         }
+    } else if let Some(dir) = state.ui.file_dialogs.export_all.take_selected() {
+        let (written, errors) = export_all(state, &dir, state.ui.export_all_format);
+        for e in &errors {
+            eprintln!("Error exporting: {e}");
+        }
+
+        state.ui.export_all_status = Some(if errors.is_empty() {
+            format!("Exported {written} files")
+        } else {
+            format!(
+                "Exported {written} files; {} failed: {}",
+                errors.len(),
+                errors.join("; ")
+            )
+        });
+    } else if let Some(path) = state.ui.file_dialogs.export_report.take_selected() {
+        // We don't treat this as the tab's path; it's export-only.
+        if let Err(e) = export_report(
+            &state.generic[state.active],
+            &state.volatile[state.active].restriction_enzyme_matches,
+            &state.restriction_enzyme_lib,
+            &state.ui.report,
+            &path,
+        ) {
+            eprintln!("Error exporting report: {:?}", e);
+        }
+    } else if let Some(path) = state.ui.file_dialogs.export_gff.take_selected() {
+        // We don't treat this as the tab's path; it doesn't include the sequence.
+        if let Err(e) = export_gff(&state.generic[state.active], state.ui.primer_export, &path) {
+            eprintln!("Error exporting to GFF3: {:?}", e);
+        }
+    } else if let Some(path) = state.ui.file_dialogs.export_svg.take_selected() {
+        if let Err(e) = export_svg(state, &path, ui) {
+            eprintln!("Error exporting the map to SVG: {:?}", e);
+        }
+    } else if let Some(path) = state.ui.file_dialogs.export_png.take_selected() {
+        state.ui.map_image.status = Some(match export_png(state, &path, ui) {
+            Ok(_) => format!("Exported {}", path.display()),
+            Err(e) => {
+                eprintln!("Error exporting the map to PNG: {:?}", e);
+                format!("Error exporting PNG: {e}")
+            }
+        });
+    } else if let Some(path) = state.ui.file_dialogs.import_gff.take_selected() {
+        let gen = &state.generic[state.active];
+        match import_gff(&path, gen.seq.len(), gen.topology) {
+            Ok((features, skipped)) => {
+                for s in &skipped {
+                    eprintln!("Skipped GFF3 record: {s}");
+                }
+                state.ui.gff_status = Some(if skipped.is_empty() {
+                    format!("Added {} features", features.len())
+                } else {
+                    format!(
+                        "Added {} features; skipped {} out-of-range, invalid, or other-sequence records",
+                        features.len(),
+                        skipped.len()
+                    )
+                });
+
+                if !features.is_empty() {
+                    state.snapshot();
+                    state.generic[state.active].features.extend(features);
+                }
+                sync = true;
+            }
+            Err(e) => {
+                eprintln!("Error importing GFF3: {:?}", e);
+                state.ui.gff_status = Some(format!("Error importing GFF3: {e}"));
+            }
+        }
+    } else if let Some(path) = state.ui.file_dialogs.save_project.take_selected() {
+        state.ui.project_status = Some(match save_project(state, &path) {
+            Ok(_) => format!("Saved {} tabs to the project", state.generic.len()),
+            Err(e) => {
+                eprintln!("Error saving project: {:?}", e);
+                format!("Error saving project: {e}")
+            }
+        });
+    } else if let Some(path) = state.ui.file_dialogs.load_project.take_selected() {
+        let status = match load_project(state, &path) {
+            Ok(count) => {
+                set_window_title(&state.tabs_open[state.active], ui);
+                format!("Opened {count} tabs")
+            }
+            Err(e) => {
+                eprintln!("Error opening project: {:?}", e);
+                format!("Error opening project: {e}")
+            }
+        };
+        state.ui.project_status = Some(status);
+    } else if let Some(path) = state.ui.file_dialogs.import_annotations.take_selected() {
+        let data_prev = state.generic[state.active].clone();
+        state.ui.import_annotation_status = Some(
+            match import_annotations(
+                &path,
+                &mut state.generic[state.active],
+                state.ui.import_annotation_primers,
+            ) {
+                Some(status) => {
+                    state.snapshot_prev(&data_prev);
+                    sync = true;
+                    status
+                }
+                None => "Unable to load annotations from this file".to_owned(),
+            },
+        );
     } else if let Some(path) = state.ui.file_dialogs.export_dna.take_selected() {
-        match export_snapgene(&state.generic[state.active], &path) {
+        match export_snapgene(&state.generic[state.active], state.ui.primer_export, &path) {
             Ok(_) => {
                 state.tabs_open[state.active] = Tab {
                     path: Some(path.to_owned()),
                     ab1: false,
                 };
+                state.volatile[state.active].unsaved = false;
                 set_window_title(&state.tabs_open[state.active], ui);
                 state.save_prefs(); // Save opened tabs.
             }
@@ -1,26 +1,30 @@
 //! This module contains GUI code related to the sequence view.
 
-use eframe::egui::{text::CursorRange, Color32, Frame, RichText, ScrollArea, TextEdit, Ui};
-use na_seq::{seq_complement, seq_from_str, seq_to_str_lower};
+use eframe::egui::{text::CursorRange, Color32, Frame, Label, RichText, ScrollArea, TextEdit, Ui};
+use na_seq::{seq_complement, seq_to_str_lower, AaIdent, Nucleotide};
 
 // todo: monospace font for all seqs.
 use crate::gui::{COL_SPACING, ROW_SPACING};
 // todo: monospace font for all seqs.
-use crate::misc_types::{Feature, FeatureDirection, MIN_SEARCH_LEN};
+use crate::misc_types::{Feature, FeatureDirection, FeatureType, MIN_SEARCH_LEN};
 // todo: monospace font for all seqs.
+use crate::ambiguous::degenerate_from_str;
 use crate::state::State;
 use crate::{
+    cloning::make_region_tab,
     gui::{
         circle::feature_range_sliders,
         feature_table::{direction_picker, feature_table},
+        lin_maps::coverage_track,
         navigation::{page_seq_selector, page_seq_top_selector, PageSeq, PageSeqTop},
         primer_table::primer_details,
-        sequence::seq_view::sequence_vis,
-        theme::COLOR_ACTION,
-        PRIMER_FWD_COLOR, SPLIT_SCREEN_MAX_HEIGHT,
+        sequence::seq_view::{sequence_vis, COLOR_CODING_REGION},
+        theme::{palette, COLOR_ACTION},
+        SPLIT_SCREEN_MAX_HEIGHT,
     },
     primer::{Primer, PrimerData},
-    util::RangeIncl,
+    protein::{aa_seq_text, translate_feature, translate_range},
+    util::{disp_range, seq_from_pasted, RangeIncl},
     Selection,
 };
 
@@ -28,6 +32,9 @@ mod feature_overlay;
 mod primer_overlay;
 pub mod seq_view;
 
+/// The most search match positions to list when hovering over the match count.
+const SEARCH_POSITIONS_MAX: usize = 20;
+
 fn seq_editor_raw(state: &mut State, ui: &mut Ui) {
     ui.horizontal(|ui| {
         ui.heading("Sequence:");
@@ -37,15 +44,22 @@ fn seq_editor_raw(state: &mut State, ui: &mut Ui) {
     ScrollArea::vertical().id_salt(200).show(ui, |ui| {
         let response = ui.add(TextEdit::multiline(&mut state.ui.seq_input).desired_width(800.));
         if response.changed() {
-            state.generic[state.active].seq = seq_from_str(&state.ui.seq_input);
+            let (seq, ambiguous) = seq_from_pasted(&state.ui.seq_input);
+            state.replace_seq(seq, &ambiguous);
             state.ui.seq_input = seq_to_str_lower(state.get_seq());
-            state.sync_seq_related(None);
         }
     });
 }
 
-/// Displays text of the feature under the cursor, or selected, as required.
-fn feature_text(i: usize, features: &[Feature], seq_len: usize, ui: &mut Ui) {
+/// Displays text of the feature under the cursor, or selected, as required. For coding regions,
+/// this includes the translated protein.
+fn feature_text(
+    i: usize,
+    features: &[Feature],
+    seq: &[Nucleotide],
+    aa_ident_disp: AaIdent,
+    ui: &mut Ui,
+) {
     if i >= features.len() {
         eprintln!("Invalid selected feature");
         return; // todo: Ideally set the feature to none.
@@ -53,16 +67,76 @@ fn feature_text(i: usize, features: &[Feature], seq_len: usize, ui: &mut Ui) {
     let feature = &features[i];
 
     ui.label(&feature.label);
-    ui.label(feature.location_descrip(seq_len));
+    ui.label(feature.location_descrip(seq.len()));
     let (r, g, b) = feature.color();
     ui.label(RichText::new(feature.feature_type.to_string()).color(Color32::from_rgb(r, g, b)));
 
+    if feature.feature_type == FeatureType::CodingRegion {
+        let aa_seq = translate_feature(feature, seq);
+
+        ui.label(format!("{} aa", aa_seq.len()));
+        ui.add(
+            Label::new(
+                RichText::new(aa_seq_text(&aa_seq, aa_ident_disp))
+                    .color(COLOR_CODING_REGION)
+                    .monospace(),
+            )
+            .wrap(),
+        );
+    }
+
     // todo?
     for note in &feature.notes {
         // ui.label(&format!("{}: {}", note.0, note.1));
     }
 }
 
+/// Displays the selection's translation, in the selected reading frame.
+fn sel_translation_text(state: &State, ui: &mut Ui) {
+    let sel = match state.ui.text_selection {
+        Some(s) => s,
+        None => return,
+    };
+
+    let translation = match translate_range(state.get_seq(), sel, state.reading_frame) {
+        Some(t) => t,
+        None => return,
+    };
+
+    ui.label(format!(
+        "Sel ({}): {} aa",
+        state.reading_frame,
+        translation.aa_seq.len()
+    ));
+
+    let mut leftover = Vec::new();
+    if translation.skipped_5p > 0 {
+        leftover.push(format!(
+            "{} 5' base(s) out of frame",
+            translation.skipped_5p
+        ));
+    }
+    if translation.leftover_3p > 0 {
+        leftover.push(format!(
+            "{} 3' base(s) not in a full codon",
+            translation.leftover_3p
+        ));
+    }
+    if !leftover.is_empty() {
+        ui.label(RichText::new(leftover.join(", ")).color(Color32::GRAY))
+            .on_hover_text("Only full codons in the reading frame are translated.");
+    }
+
+    ui.add(
+        Label::new(
+            RichText::new(translation.text(state.ui.aa_ident_disp))
+                .color(COLOR_CODING_REGION)
+                .monospace(),
+        )
+        .wrap(),
+    );
+}
+
 fn primer_text(i: usize, primers: &[Primer], seq_len: usize, ui: &mut Ui) {
     if i >= primers.len() {
         eprintln!("Invalid selected primer");
@@ -71,9 +145,16 @@ fn primer_text(i: usize, primers: &[Primer], seq_len: usize, ui: &mut Ui) {
     let primer = &primers[i];
 
     ui.label(&primer.name);
-    ui.label(&primer.location_descrip());
-    // todo: Rev color A/R
-    ui.label(RichText::new(seq_to_str_lower(&primer.sequence)).color(PRIMER_FWD_COLOR));
+    ui.label(primer.location_descrip()).on_hover_text(
+        "Top strand positions, from the primer's 5' to 3' end. Reverse primers bind the bottom \
+        strand, so their positions read from high to low.",
+    );
+
+    let color = match primer.volatile.matches.first() {
+        Some(m) => m.direction.color(),
+        None => palette().primer_fwd,
+    };
+    ui.label(RichText::new(seq_to_str_lower(&primer.sequence)).color(color));
 
     ui.label(&primer.description.clone().unwrap_or_default());
 }
@@ -85,6 +166,7 @@ fn feature_from_sel(state: &mut State, ui: &mut Ui) {
             .button(RichText::new("➕ Add feature from sel").color(COLOR_ACTION))
             .clicked()
         {
+            state.snapshot();
             state.generic[state.active].features.push(Feature {
                 range: text_sel,
                 label: state.ui.quick_feature_add_name.clone(),
@@ -118,10 +200,12 @@ fn feature_from_sel(state: &mut State, ui: &mut Ui) {
 
             let volatile = PrimerData::new(&seq_primer);
 
+            state.snapshot();
             state.generic[state.active].primers.push(Primer {
                 sequence: seq_primer,
                 name: state.ui.quick_feature_add_name.clone(),
                 description: None,
+                mods: Default::default(),
                 volatile,
             });
 
@@ -130,6 +214,21 @@ fn feature_from_sel(state: &mut State, ui: &mut Ui) {
             state.sync_primer_metrics();
         }
 
+        let color = if state.ui.translate_sel {
+            Color32::GREEN
+        } else {
+            COLOR_ACTION
+        };
+        if ui
+            .button(RichText::new("Translate sel").color(color))
+            .on_hover_text(
+                "Show the selection's amino acid sequence, in the selected reading frame.",
+            )
+            .clicked()
+        {
+            state.ui.translate_sel = !state.ui.translate_sel;
+        }
+
         direction_picker(&mut state.ui.quick_feature_add_dir, 200, ui);
 
         ui.label("Name:");
@@ -144,6 +243,65 @@ fn feature_from_sel(state: &mut State, ui: &mut Ui) {
     }
 }
 
+/// A button to open the selected text, or the selected feature, as a new sequence.
+fn region_extract(state: &mut State, ui: &mut Ui) {
+    let (range, name) = match (state.ui.text_selection, state.ui.selected_item) {
+        (Some(sel), _) => (sel, format!("Region {}", disp_range(sel))),
+        (None, Selection::Feature(i)) if i < state.generic[state.active].features.len() => {
+            let feature = &state.generic[state.active].features[i];
+            (feature.range, feature.label())
+        }
+        _ => return,
+    };
+
+    if ui
+        .button(RichText::new("Extract to new tab").color(COLOR_ACTION))
+        .on_hover_text(
+            "Create a new linear sequence from the selected text, or the selected feature. \
+            Features within it are included.",
+        )
+        .clicked()
+    {
+        make_region_tab(state, range, &name);
+    }
+    ui.add_space(COL_SPACING);
+}
+
+/// A button to split the selected feature in two, at the text cursor. Only shown when the cursor
+/// is inside the feature.
+fn feature_split(state: &mut State, ui: &mut Ui) {
+    let feat_i = match state.ui.selected_item {
+        Selection::Feature(i) => i,
+        _ => return,
+    };
+
+    let cursor_i = match state.ui.text_cursor_i {
+        Some(i) => i,
+        None => return,
+    };
+
+    let data = &state.generic[state.active];
+    if feat_i >= data.features.len() {
+        return;
+    }
+
+    if let Some((first, second)) = data.features[feat_i].split(cursor_i, data.seq.len()) {
+        if ui
+            .button(RichText::new("✂ Split feature at cursor").color(COLOR_ACTION))
+            .on_hover_text(
+                "Split the selected feature into two, after the nucleotide at the cursor.",
+            )
+            .clicked()
+        {
+            let features = &mut state.generic[state.active].features;
+            features[feat_i] = first;
+            features.insert(feat_i + 1, second);
+        }
+
+        ui.add_space(COL_SPACING);
+    }
+}
+
 /// Component for the sequence page.
 pub fn seq_page(state: &mut State, ui: &mut Ui) {
     ui.horizontal(|ui| {
@@ -153,6 +311,10 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
 
         feature_from_sel(state, ui);
 
+        region_extract(state, ui);
+
+        feature_split(state, ui);
+
         // Sliders to edit the feature.
         feature_range_sliders(state, ui);
     });
@@ -190,7 +352,8 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
         ui.add_space(COL_SPACING);
 
         ui.label("🔍").on_hover_text(
-            "Search the sequence and its complement for this term. (Ctrl + F to highlight)",
+            "Search the sequence and its complement for this term. IUPAC codes like N, R, and Y \
+            match any of their bases. (Ctrl + F to highlight)",
         );
 
         // This nonstandard way of adding the text input is required for the auto-highlight on ctrl+F behavior.
@@ -215,8 +378,9 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
 
         if response.changed {
             state.ui.text_edit_active = true;
-            state.search_seq = seq_from_str(&state.ui.search_input);
-            state.ui.search_input = seq_to_str_lower(&state.search_seq); // Ensures only valid NTs are present.
+            state.search_seq = degenerate_from_str(&state.ui.search_input);
+            // Ensures only valid NTs, and IUPAC codes, are present.
+            state.ui.search_input = String::from_utf8_lossy(&state.search_seq).to_string();
 
             // todo: This still adds a single char, then blanks the cursor...
             state.ui.text_cursor_i = None; // Make sure we are not adding chars.
@@ -230,7 +394,18 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
             } else {
                 format!("{} matches", len)
             };
-            ui.label(text);
+            let positions: Vec<String> = state.volatile[state.active]
+                .search_matches
+                .iter()
+                .take(SEARCH_POSITIONS_MAX)
+                .map(|m| disp_range(m.range))
+                .collect();
+
+            if positions.is_empty() {
+                ui.label(text);
+            } else {
+                ui.label(text).on_hover_text(positions.join("\n"));
+            }
         }
 
         ui.add_space(COL_SPACING);
@@ -252,7 +427,8 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
             feature_text(
                 feature_i,
                 &state.generic[state.active].features,
-                state.get_seq().len(),
+                state.get_seq(),
+                state.ui.aa_ident_disp,
                 ui,
             );
         }
@@ -265,6 +441,10 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
                 ui,
             );
         }
+
+        if state.ui.translate_sel {
+            sel_translation_text(state, ui);
+        }
     });
 
     ui.add_space(ROW_SPACING / 2.);
@@ -277,6 +457,10 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
                 seq_editor_raw(state, ui);
             }
             PageSeq::View => {
+                if let Some(coverage) = &state.alignment.coverage {
+                    coverage_track(coverage, state.get_seq().len(), ui);
+                    ui.add_space(ROW_SPACING / 2.);
+                }
                 sequence_vis(state, ui);
             }
         });
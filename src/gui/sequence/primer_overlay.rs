@@ -1,11 +1,14 @@
 //! This module contains code related to drawing primer arrows in the sequence view.
 
-use eframe::egui::{Pos2, Shape, Ui};
+use eframe::egui::{pos2, Color32, Pos2, Shape, Stroke, Ui};
 
 use crate::{
-    gui::sequence::{feature_overlay, seq_view::SeqViewData},
+    gui::sequence::{
+        feature_overlay,
+        seq_view::{SeqViewData, NT_WIDTH_PX},
+    },
     misc_types::FeatureType,
-    primer::{Primer, PrimerDirection},
+    primer::{AnnealMatch, Primer, PrimerDirection, PrimerMatch},
     util,
     util::RangeIncl,
     Selection,
@@ -18,68 +21,184 @@ pub const LABEL_OFFSET: f32 = 7.;
 pub const HEIGHT: f32 = 16.;
 pub const SLANT: f32 = 12.; // slant different, in pixels, for the arrow.
 pub const SLANT_DIV2: f32 = SLANT / 2.;
+// Distance between a non-annealing tail, and the edge of its primer arrow.
+const TAIL_OFFSET: f32 = 4.;
 
-/// Add primer arrows to the display.
-pub fn draw_primers(
-    primers: &[Primer],
-    selected_item: Selection,
+/// Draw a non-annealing 5' tail as a line offset from the sequence, away from the primer arrow.
+/// `range` is the range the tail would cover, if it annealed.
+fn draw_tail(
+    range: RangeIncl,
+    direction: PrimerDirection,
+    color: Color32,
+    data: &SeqViewData,
+) -> Vec<Shape> {
+    let stroke = Stroke::new(STROKE_WIDTH, color);
+
+    // Forward primers are drawn above the sequence, and reverse ones below; match the arrows'
+    // outer edges.
+    let y_offset = match direction {
+        PrimerDirection::Forward => -VERTICAL_OFFSET_PRIMER - TAIL_OFFSET,
+        PrimerDirection::Reverse => {
+            -VERTICAL_OFFSET_PRIMER + 3. * HEIGHT + 1. + TAIL_OFFSET + data.complement_offset
+        }
+    };
+
+    util::get_feature_ranges(&range, &data.row_ranges, data.seq_len)
+        .iter()
+        .map(|r| {
+            let start = data.seq_i_to_px_rel(r.start);
+            let end = data.seq_i_to_px_rel(r.end);
+
+            Shape::line_segment(
+                [
+                    pos2(start.x, start.y + y_offset),
+                    pos2(end.x + NT_WIDTH_PX, end.y + y_offset),
+                ],
+                stroke,
+            )
+        })
+        .collect()
+}
+
+/// Draw a single primer match. The tail length is the number of 5' nucleotides outside of the
+/// match range; these are drawn as overhanging.
+fn draw_match(
+    prim_match: &PrimerMatch,
+    tail_len: usize,
+    name: &str,
+    selected: bool,
     data: &SeqViewData,
     ui: &mut Ui,
 ) -> Vec<Shape> {
     let mut shapes = Vec::new();
 
-    for (i, primer) in primers.iter().enumerate() {
-        let primer_matches = &primer.volatile.matches;
+    // We currently index primers relative to the end they started.
 
-        // todo: Do not run these calcs each time. Cache.
-        for prim_match in primer_matches {
-            // We currently index primers relative to the end they started.
-
-            // Note: Because if we're displaying above the seq and below, the base of the arrow must match,
-            // hence the offset.
-            let seq_range = match prim_match.direction {
-                PrimerDirection::Forward => {
-                    // todo: Getting an underflow, but not sure why yet.
-                    let end = if prim_match.range.end > 0 {
-                        prim_match.range.end - 1
-                    } else {
-                        prim_match.range.end
-                    };
-                    RangeIncl::new(prim_match.range.start, end)
-                }
-
-                PrimerDirection::Reverse => {
-                    RangeIncl::new(prim_match.range.start + 1, prim_match.range.end)
-                }
+    // Note: Because if we're displaying above the seq and below, the base of the arrow must match,
+    // hence the offset.
+    let seq_range = match prim_match.direction {
+        PrimerDirection::Forward => {
+            // todo: Getting an underflow, but not sure why yet.
+            let end = if prim_match.range.end > 0 {
+                prim_match.range.end - 1
+            } else {
+                prim_match.range.end
             };
+            RangeIncl::new(prim_match.range.start, end)
+        }
 
-            let feature_ranges =
-                util::get_feature_ranges(&seq_range, &data.row_ranges, data.seq_len);
+        PrimerDirection::Reverse => {
+            RangeIncl::new(prim_match.range.start + 1, prim_match.range.end)
+        }
+    };
 
-            let feature_ranges_px: Vec<(Pos2, Pos2)> = feature_ranges
-                .iter()
-                .map(|r| (data.seq_i_to_px_rel(r.start), data.seq_i_to_px_rel(r.end)))
-                .collect();
+    let feature_ranges = util::get_feature_ranges(&seq_range, &data.row_ranges, data.seq_len);
 
-            let color = prim_match.direction.color();
+    // Reverse primers are drawn below the complementary strand, if it's displayed.
+    let y_offset = match prim_match.direction {
+        PrimerDirection::Forward => 0.,
+        PrimerDirection::Reverse => data.complement_offset,
+    };
 
-            let selected = match selected_item {
-                Selection::Primer(j) => i == j,
-                _ => false,
-            };
+    let feature_ranges_px: Vec<(Pos2, Pos2)> = feature_ranges
+        .iter()
+        .map(|r| {
+            let (start, end) = (data.seq_i_to_px_rel(r.start), data.seq_i_to_px_rel(r.end));
+            (
+                pos2(start.x, start.y + y_offset),
+                pos2(end.x, end.y + y_offset),
+            )
+        })
+        .collect();
+
+    let color = prim_match.direction.color();
+
+    // The tail is upstream of forward primers, and downstream (in forward indices) of reverse ones.
+    // It wraps around the origin if required.
+    let tail_len = tail_len.min(data.seq_len.saturating_sub(1));
+    if tail_len > 0 {
+        let tail_range = match prim_match.direction {
+            PrimerDirection::Forward => RangeIncl::new(
+                (prim_match.range.start + data.seq_len - 1 - tail_len) % data.seq_len + 1,
+                (prim_match.range.start + data.seq_len - 2) % data.seq_len + 1,
+            ),
+            PrimerDirection::Reverse => RangeIncl::new(
+                prim_match.range.end % data.seq_len + 1,
+                (prim_match.range.end + tail_len - 1) % data.seq_len + 1,
+            ),
+        };
+
+        shapes.append(&mut draw_tail(
+            tail_range,
+            prim_match.direction,
+            color,
+            data,
+        ));
+    }
+
+    shapes.append(&mut feature_overlay::feature_seq_overlay(
+        &feature_ranges_px,
+        FeatureType::Primer,
+        color,
+        VERTICAL_OFFSET_PRIMER,
+        (prim_match.direction).into(),
+        name,
+        selected,
+        ui,
+    ));
 
-            // todo: PUt back; temp check on compiling.
-            shapes.append(&mut feature_overlay::feature_seq_overlay(
-                &feature_ranges_px,
-                FeatureType::Primer,
-                color,
-                VERTICAL_OFFSET_PRIMER,
-                (prim_match.direction).into(),
+    shapes
+}
+
+/// Add primer arrows to the display. This includes primers that only match the sequence at their
+/// 3' ends; their remaining 5' portion is drawn as a non-annealing tail.
+pub fn draw_primers(
+    primers: &[Primer],
+    anneal_matches: &[AnnealMatch],
+    selected_item: Selection,
+    data: &SeqViewData,
+    ui: &mut Ui,
+) -> Vec<Shape> {
+    let mut shapes = Vec::new();
+
+    if data.seq_len == 0 {
+        return shapes;
+    }
+
+    let is_selected = |i| match selected_item {
+        Selection::Primer(j) => i == j,
+        _ => false,
+    };
+
+    for (i, primer) in primers.iter().enumerate() {
+        // todo: Do not run these calcs each time. Cache.
+        for prim_match in &primer.volatile.matches {
+            shapes.append(&mut draw_match(
+                prim_match,
+                primer.mods.tail_5p.len(),
                 &primer.name,
-                selected,
+                is_selected(i),
+                data,
                 ui,
             ));
         }
     }
+
+    for anneal in anneal_matches {
+        if anneal.primer_i >= primers.len() {
+            continue;
+        }
+        let primer = &primers[anneal.primer_i];
+
+        shapes.append(&mut draw_match(
+            &anneal.match_,
+            anneal.tail_len + primer.mods.tail_5p.len(),
+            &primer.name,
+            is_selected(anneal.primer_i),
+            data,
+            ui,
+        ));
+    }
+
     shapes
 }
@@ -1,9 +1,11 @@
 //! This module contains GUI code related to the sequence visulization.
 
+use std::collections::HashMap;
+
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, ScrollArea, Sense,
-        Shape, Stroke, Ui,
+        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, RichText, ScrollArea,
+        Sense, Shape, Stroke, Ui,
     },
     emath::RectTransform,
     epaint::PathStroke,
@@ -11,19 +13,24 @@ use eframe::{
 use na_seq::amino_acids::{AminoAcid, CodingResult};
 
 use crate::{
+    alignment::{ReadAlignment, ReadDiffKind},
+    alignment_map::Variant,
+    ambiguous,
     gui::{
-        feature_from_index, get_cursor_text,
+        bookmark_disp, feature_from_index, features_cut_by_re, get_cursor_text,
+        handle_measure_click, measure_disp,
         navigation::page_button,
-        select_feature,
+        re_cut_index, re_cut_text, re_cuts_coding, select_feature,
         sequence::{
             feature_overlay::{draw_features, draw_selection},
             primer_overlay,
         },
-        BACKGROUND_COLOR, COLOR_RE, COLOR_SEQ, COLOR_SEQ_DIMMED, COL_SPACING,
+        theme::palette,
+        BACKGROUND_COLOR, COLOR_SEQ, COLOR_SEQ_DIMMED, COL_SPACING,
     },
-    reading_frame::ReadingFrame,
+    reading_frame::{find_start_stop_codons, CodonMatch, CodonType, ReadingFrame},
     state::State,
-    util::{get_row_ranges, pixel_to_seq_i, seq_i_to_pixel, RangeIncl},
+    util::{get_feature_ranges, get_row_ranges, pixel_to_seq_i, seq_i_to_pixel, RangeIncl},
     Nucleotide, Selection, StateUi,
 };
 
@@ -35,12 +42,30 @@ pub const COLOR_STOP_CODON: Color32 = Color32::from_rgb(255, 170, 100);
 pub const COLOR_CURSOR: Color32 = Color32::from_rgb(255, 255, 0);
 pub const COLOR_SEARCH_RESULTS: Color32 = Color32::from_rgb(255, 255, 130);
 pub const COLOR_SELECTED_NTS: Color32 = Color32::from_rgb(255, 60, 255);
+pub const COLOR_MEASURE: Color32 = Color32::from_rgb(120, 255, 120);
+pub const COLOR_BOOKMARK: Color32 = Color32::from_rgb(255, 200, 60);
+pub const COLOR_VARIANT: Color32 = Color32::from_rgb(255, 90, 90);
+/// Differences from an aligned Sanger read.
+pub const COLOR_READ_DIFF: Color32 = Color32::from_rgb(255, 150, 40);
+/// N, other ambiguity codes, and gaps.
+const COLOR_AMBIGUOUS: Color32 = Color32::from_rgb(150, 150, 150);
+// Translucent, so the nucleotides show through.
+const COLOR_START_CODON: Color32 = Color32::from_rgba_premultiplied(0, 110, 0, 110);
+const COLOR_STOP_CODON_HIGHLIGHT: Color32 = Color32::from_rgba_premultiplied(120, 0, 0, 120);
 
 pub const NT_WIDTH_PX: f32 = 8.; // todo: Automatic way? This is valid for monospace font, size 14.
 pub const VIEW_AREA_PAD_LEFT: f32 = 60.; // Bigger to accomodate the index display.
 pub const VIEW_AREA_PAD_RIGHT: f32 = 20.;
 // pub const SEQ_ROW_SPACING_PX: f32 = 34.;
 pub const SEQ_ROW_SPACING_PX: f32 = 40.;
+// Vertical distance between the forward strand, and the complementary one, when displayed.
+pub const COMPLEMENT_OFFSET_PX: f32 = 16.;
+pub const COLOR_COMPLEMENT: Color32 = Color32::from_rgb(140, 190, 200);
+// Additional row spacing to make room for the ruler, when displayed.
+const RULER_HEIGHT_PX: f32 = 14.;
+const RULER_INTERVAL: usize = 10;
+const RULER_FONT_SIZE: f32 = 11.;
+const COLOR_RULER: Color32 = Color32::from_rgb(170, 170, 170);
 
 pub const TEXT_X_START: f32 = VIEW_AREA_PAD_LEFT;
 pub const TEXT_Y_START: f32 = TEXT_X_START;
@@ -51,13 +76,17 @@ pub struct SeqViewData {
     pub row_ranges: Vec<RangeIncl>,
     pub to_screen: RectTransform,
     pub from_screen: RectTransform,
+    /// Vertical distance between rows; larger when showing the complementary strand.
+    pub row_spacing: f32,
+    /// The complementary strand's offset below the forward one. 0 if hidden.
+    pub complement_offset: f32,
     // /// This is `from_screen * center`. We store it here to cache.
     // pub center_rel: Pos2,
 }
 
 impl SeqViewData {
     pub fn seq_i_to_px_rel(&self, i: usize) -> Pos2 {
-        self.to_screen * seq_i_to_pixel(i, &self.row_ranges)
+        self.to_screen * seq_i_to_pixel(i, &self.row_ranges, self.row_spacing)
     }
 }
 
@@ -81,17 +110,34 @@ fn draw_re_sites(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
             continue;
         }
 
-        let cut_i = re_match.seq_index + 1; // to display in the right place.
-        let cut_pos = data.seq_i_to_px_rel(cut_i + re.cut_after as usize);
+        let cut_i = re_cut_index(re_match, re, data.seq_len);
+        let cut_pos = data.seq_i_to_px_rel(cut_i);
+
+        let bottom = pos2(cut_pos.x, cut_pos.y + 20. + data.complement_offset);
+
+        let features = &state.generic[state.active].features;
+        let features_cut = features_cut_by_re(re_match, re, features, data.seq_len);
 
-        let bottom = pos2(cut_pos.x, cut_pos.y + 20.);
+        let color = if state.ui.re.highlight_in_coding && re_cuts_coding(&features_cut, features) {
+            palette().re_in_coding
+        } else {
+            palette().re
+        };
 
         result.push(Shape::LineSegment {
             points: [cut_pos, bottom],
-            stroke: PathStroke::new(2., COLOR_RE),
+            stroke: PathStroke::new(2., color),
         });
 
-        let label_text = &re.name;
+        // When hovering near the cut site, list the features it cuts within.
+        let mut label_text = re.name.clone();
+        if let Some(cursor_i) = state.ui.cursor_seq_i {
+            if cursor_i.abs_diff(cut_i) <= 1 {
+                if let Some(text) = re_cut_text(&features_cut, features) {
+                    label_text = format!("{label_text}: {text}");
+                }
+            }
+        }
         let mut label_pos = pos2(cut_pos.x + 2., cut_pos.y - 4.);
 
         // Move the label position left if there is a nearby RE site on the right.
@@ -117,7 +163,7 @@ fn draw_re_sites(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
         // Alternate above and below for legibility.
         // This requires the RE site list to be sorted by seq index, which it currently is.
         if i_match % 2 == 0 {
-            label_pos.y += 30.;
+            label_pos.y += 30. + data.complement_offset;
         }
 
         // Add the label
@@ -128,7 +174,7 @@ fn draw_re_sites(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
                 Align2::LEFT_CENTER,
                 label_text,
                 FontId::new(16., FontFamily::Proportional),
-                COLOR_RE,
+                color,
             )
         });
         result.push(label)
@@ -161,6 +207,22 @@ pub fn display_filters(state_ui: &mut StateUi, ui: &mut Ui) {
         ui.label("Reading frame:");
         ui.checkbox(&mut state_ui.seq_visibility.show_reading_frame, "");
         ui.add_space(COL_SPACING / 2.);
+
+        ui.label("Complement:");
+        ui.checkbox(&mut state_ui.seq_visibility.show_complement, "");
+        ui.add_space(COL_SPACING / 2.);
+
+        ui.label("Ruler:");
+        ui.checkbox(&mut state_ui.seq_visibility.show_ruler, "");
+        ui.add_space(COL_SPACING / 2.);
+
+        ui.label("Start/stop:");
+        ui.checkbox(&mut state_ui.seq_visibility.show_start_stop, "");
+        if state_ui.seq_visibility.show_start_stop {
+            ui.label("Rev:");
+            ui.checkbox(&mut state_ui.seq_visibility.start_stop_reverse, "");
+        }
+        ui.add_space(COL_SPACING / 2.);
     });
 }
 
@@ -188,6 +250,34 @@ fn draw_seq_indexes(data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
     result
 }
 
+/// Draw position numbers above the sequence, every `RULER_INTERVAL` nucleotides. These are placed
+/// above forward primers.
+fn draw_ruler(data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for i in (RULER_INTERVAL..=data.seq_len).step_by(RULER_INTERVAL) {
+        let pos = data.seq_i_to_px_rel(i);
+        // Centered over the nucleotide.
+        let label_pos = pos2(
+            pos.x + NT_WIDTH_PX / 2.,
+            pos.y - primer_overlay::VERTICAL_OFFSET_PRIMER - 2.,
+        );
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                label_pos,
+                Align2::CENTER_BOTTOM,
+                i.to_string(),
+                FontId::new(RULER_FONT_SIZE, FontFamily::Proportional),
+                COLOR_RULER,
+            )
+        }));
+    }
+
+    result
+}
+
 fn orf_selector(state: &mut State, ui: &mut Ui) {
     ui.label("Reading frame:");
 
@@ -223,7 +313,7 @@ fn find_cursor_i(cursor_pos: Option<(f32, f32)>, data: &SeqViewData) -> Option<u
 
             // See note on the pad below; this is for clicking before seq start.
             if p_abs.x > (VIEW_AREA_PAD_LEFT - 2. * NT_WIDTH_PX) && p_rel.y > view_start_y {
-                let result = pixel_to_seq_i(p_abs, &data.row_ranges);
+                let result = pixel_to_seq_i(p_abs, &data.row_ranges, data.row_spacing);
                 if let Some(i) = result {
                     if i > data.seq_len + 2 {
                         // This pad allows setting the cursor a bit past the seq end.
@@ -245,6 +335,12 @@ fn find_cursor_i(cursor_pos: Option<(f32, f32)>, data: &SeqViewData) -> Option<u
 fn draw_nts(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
     let mut result = Vec::new();
 
+    let ambiguous: HashMap<usize, u8> = state.generic[state.active]
+        .ambiguous
+        .iter()
+        .copied()
+        .collect();
+
     for (i, nt) in state.get_seq().iter().enumerate() {
         let i = i + 1; // 1-based indexing.
         let pos = data.seq_i_to_px_rel(i);
@@ -253,16 +349,24 @@ fn draw_nts(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
         if state.ui.seq_visibility.show_reading_frame {
             for orf_match in &state.volatile[state.active].reading_frame_matches {
                 if orf_match.range.contains(i) {
-                    // todo: This only works for forward reading frames.
-
                     let i_orf = i - 1; // Back to 0-based indexing for this.
+                    let offset = orf_match.frame.offset();
+
+                    // Reverse frames are offset from the end of the sequence. We label each codon at
+                    // its leftmost nucleotide on the forward strand.
+                    let codon_start = if orf_match.frame.is_reverse() {
+                        (data.seq_len - offset - i_orf).is_multiple_of(3)
+                            && i_orf + 3 <= data.seq_len
+                    } else {
+                        (i_orf - offset).is_multiple_of(3) && i_orf + 3 <= data.seq_len
+                    };
 
                     // todo: Cache this; don't run it every update.
-                    if (i_orf - orf_match.frame.offset()) % 3 == 0 {
+                    if codon_start {
                         let mut codons: [Nucleotide; 3] =
                             state.get_seq()[i_orf..i_orf + 3].try_into().unwrap();
 
-                        if state.reading_frame.is_reverse() {
+                        if orf_match.frame.is_reverse() {
                             codons = [
                                 codons[2].complement(),
                                 codons[1].complement(),
@@ -386,12 +490,18 @@ fn draw_nts(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
             r
         };
 
+        // Ambiguous bases are stored as a placeholder; display the original character.
+        let (text, letter_color) = match ambiguous.get(&i) {
+            Some(char) => ((*char as char).to_string(), COLOR_AMBIGUOUS),
+            None => (nt.to_str_lower(), letter_color),
+        };
+
         result.push(ui.ctx().fonts(|fonts| {
             Shape::text(
                 fonts,
                 pos,
                 Align2::LEFT_TOP,
-                &nt.to_str_lower(),
+                &text,
                 // Note: Monospace is important for sequences.
                 FontId::new(FONT_SIZE_SEQ, FontFamily::Monospace),
                 letter_color,
@@ -402,6 +512,44 @@ fn draw_nts(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
     result
 }
 
+/// Draw the complementary strand, 3' to 5', below the forward one. Each nucleotide is aligned
+/// with the one it pairs with.
+fn draw_complement(state: &State, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    let ambiguous: HashMap<usize, u8> = state.generic[state.active]
+        .ambiguous
+        .iter()
+        .copied()
+        .collect();
+
+    for (i, nt) in state.get_seq().iter().enumerate() {
+        let mut pos = data.seq_i_to_px_rel(i + 1);
+        pos.y += data.complement_offset;
+
+        let (text, color) = match ambiguous.get(&(i + 1)) {
+            Some(char) => (
+                (ambiguous::complement(*char) as char).to_string(),
+                COLOR_AMBIGUOUS,
+            ),
+            None => (nt.complement().to_str_lower(), COLOR_COMPLEMENT),
+        };
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                pos,
+                Align2::LEFT_TOP,
+                &text,
+                FontId::new(FONT_SIZE_SEQ, FontFamily::Monospace),
+                color,
+            )
+        }));
+    }
+
+    result
+}
+
 fn draw_text_cursor(cursor_i: Option<usize>, data: &SeqViewData) -> Vec<Shape> {
     let mut result = Vec::new();
 
@@ -422,6 +570,191 @@ fn draw_text_cursor(cursor_i: Option<usize>, data: &SeqViewData) -> Vec<Shape> {
     result
 }
 
+/// Highlight start codons in green, and stop codons in red, behind the nucleotides. Codons on the
+/// complementary strand are drawn on it if displayed, and as a bar below the sequence otherwise.
+fn draw_codon_highlights(
+    codon_matches: &[CodonMatch],
+    show_reverse: bool,
+    data: &SeqViewData,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for codon in codon_matches {
+        if codon.reverse && !show_reverse {
+            continue;
+        }
+
+        let color = match codon.codon_type {
+            CodonType::Start => COLOR_START_CODON,
+            CodonType::Stop => COLOR_STOP_CODON_HIGHLIGHT,
+        };
+
+        let (y_offset, height) = if !codon.reverse {
+            (0., FONT_SIZE_SEQ + 2.)
+        } else if data.complement_offset > 0. {
+            (data.complement_offset, FONT_SIZE_SEQ + 2.)
+        } else {
+            (FONT_SIZE_SEQ + 3., 3.)
+        };
+
+        // A codon may be split across rows.
+        for r in get_feature_ranges(&codon.range, &data.row_ranges, data.seq_len) {
+            let start = data.seq_i_to_px_rel(r.start);
+            let end = data.seq_i_to_px_rel(r.end);
+
+            let rect = Rect::from_min_max(
+                pos2(start.x, start.y + y_offset),
+                pos2(end.x + NT_WIDTH_PX, end.y + y_offset + height),
+            );
+            result.push(Shape::rect_filled(rect, 0., color));
+        }
+    }
+
+    result
+}
+
+/// Mark measurement points with a vertical line, centered on their nucleotide.
+fn draw_measure_points(points: &[Option<usize>], data: &SeqViewData) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for i in points.iter().flatten() {
+        let mut top = data.seq_i_to_px_rel(*i);
+        top.x += NT_WIDTH_PX / 2.;
+        top.y -= 6.;
+        let bottom = pos2(top.x, top.y + 28. + data.complement_offset);
+
+        result.push(Shape::line_segment(
+            [top, bottom],
+            Stroke::new(2., COLOR_MEASURE),
+        ));
+    }
+
+    result
+}
+
+/// Underline candidate variants from loaded alignments, and label each with its alternate base.
+fn draw_variants(variants: &[Variant], data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for variant in variants {
+        if variant.posit > data.seq_len {
+            continue;
+        }
+        let pos = data.seq_i_to_px_rel(variant.posit);
+        let y = pos.y + FONT_SIZE_SEQ + 2.;
+
+        result.push(Shape::line_segment(
+            [pos2(pos.x, y), pos2(pos.x + NT_WIDTH_PX, y)],
+            Stroke::new(3., COLOR_VARIANT),
+        ));
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                pos2(pos.x + NT_WIDTH_PX / 2., pos.y - 2.),
+                Align2::CENTER_BOTTOM,
+                variant.alt.to_str_lower().to_uppercase(),
+                FontId::new(11., FontFamily::Monospace),
+                COLOR_VARIANT,
+            )
+        }));
+    }
+
+    result
+}
+
+/// Mark differences from an aligned Sanger read: Underline mismatches and deletions, and mark
+/// insertions between nucleotides. Differences in low-quality regions of the read are faint.
+fn draw_read_diffs(aln: &ReadAlignment, data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    let color_low = COLOR_READ_DIFF.gamma_multiply(0.35);
+
+    for diff in &aln.diffs {
+        if diff.posit > data.seq_len {
+            continue;
+        }
+        let color = if diff.high_quality {
+            COLOR_READ_DIFF
+        } else {
+            color_low
+        };
+
+        let pos = data.seq_i_to_px_rel(diff.posit);
+        let y = pos.y + FONT_SIZE_SEQ + 2.;
+
+        let label = match diff.kind {
+            ReadDiffKind::Mismatch(nt) => nt.to_str_lower(),
+            ReadDiffKind::Insertion(nt) => format!("+{}", nt.to_str_lower()),
+            ReadDiffKind::Deletion => "-".to_owned(),
+        };
+
+        let label_x = match diff.kind {
+            ReadDiffKind::Insertion(_) => {
+                result.push(Shape::line_segment(
+                    [pos2(pos.x, pos.y), pos2(pos.x, y)],
+                    Stroke::new(2., color),
+                ));
+                pos.x
+            }
+            _ => {
+                result.push(Shape::line_segment(
+                    [pos2(pos.x, y), pos2(pos.x + NT_WIDTH_PX, y)],
+                    Stroke::new(3., color),
+                ));
+                pos.x + NT_WIDTH_PX / 2.
+            }
+        };
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                pos2(label_x, pos.y - 2.),
+                Align2::CENTER_BOTTOM,
+                label,
+                FontId::new(11., FontFamily::Monospace),
+                color,
+            )
+        }));
+    }
+
+    result
+}
+
+/// Mark bookmarks with a small flag above their nucleotide, labeled with the bookmark name.
+fn draw_bookmarks(bookmarks: &[(usize, String)], data: &SeqViewData, ui: &mut Ui) -> Vec<Shape> {
+    let mut result = Vec::new();
+
+    for (i, name) in bookmarks {
+        let mut top = data.seq_i_to_px_rel(*i);
+        top.x += NT_WIDTH_PX / 2.;
+        top.y -= 10.;
+
+        result.push(Shape::convex_polygon(
+            vec![top, pos2(top.x + 7., top.y + 3.), pos2(top.x, top.y + 6.)],
+            COLOR_BOOKMARK,
+            Stroke::NONE,
+        ));
+        result.push(Shape::line_segment(
+            [top, pos2(top.x, top.y + 10.)],
+            Stroke::new(1., COLOR_BOOKMARK),
+        ));
+
+        result.push(ui.ctx().fonts(|fonts| {
+            Shape::text(
+                fonts,
+                pos2(top.x + 9., top.y + 3.),
+                Align2::LEFT_CENTER,
+                name,
+                FontId::new(11., FontFamily::Proportional),
+                COLOR_BOOKMARK,
+            )
+        }));
+    }
+
+    result
+}
+
 /// Draw the sequence with primers, insertion points, and other data visible, A/R
 pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
     let mut shapes = vec![];
@@ -433,6 +766,25 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
         / NT_WIDTH_PX) as usize;
     let row_ranges = get_row_ranges(seq_len, state.ui.nt_chars_per_row);
 
+    // Computed here, vice on sequence change, so we only pay for this when it's displayed.
+    if state.ui.seq_visibility.show_start_stop
+        && state.volatile[state.active].codon_matches.is_none()
+    {
+        state.volatile[state.active].codon_matches = Some(find_start_stop_codons(state.get_seq()));
+    }
+
+    let complement_offset = if state.ui.seq_visibility.show_complement {
+        COMPLEMENT_OFFSET_PX
+    } else {
+        0.
+    };
+    let ruler_height = if state.ui.seq_visibility.show_ruler {
+        RULER_HEIGHT_PX
+    } else {
+        0.
+    };
+    let row_spacing = SEQ_ROW_SPACING_PX + complement_offset + ruler_height;
+
     let mouse_posit_lbl = get_cursor_text(state.ui.cursor_seq_i, seq_len);
     let text_posit_lbl = get_cursor_text(state.ui.text_cursor_i, seq_len);
 
@@ -453,15 +805,58 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
         if let Some(selection) = state.ui.text_selection {
             ui.heading(format!("{selection}"));
         }
+
+        let ambiguous_count = state.generic[state.active].ambiguous.len();
+        if ambiguous_count > 0 {
+            ui.add_space(COL_SPACING);
+            ui.label(
+                RichText::new(format!("⚠ {ambiguous_count} ambiguous"))
+                    .color(COLOR_AMBIGUOUS),
+            )
+            .on_hover_text(
+                "This sequence contains N, other ambiguity codes, or gaps. These are shown in gray, and \
+                preserved on export; for calculations such as searches and primer matching, they're \
+                treated as A.",
+            );
+        }
+
+        ui.add_space(COL_SPACING);
+        measure_disp(state, ui);
+
+        ui.add_space(COL_SPACING);
+        bookmark_disp(state, ui);
+
+        if let Some(warning) = &state.ui.edit_warning {
+            ui.add_space(COL_SPACING);
+            ui.label(RichText::new(warning).color(Color32::LIGHT_RED));
+            if ui.button("Dismiss").clicked() {
+                state.ui.edit_warning = None;
+            }
+        }
+
+        if let Some(warning) = &state.ui.load_warning {
+            ui.add_space(COL_SPACING);
+            ui.label(RichText::new(warning).color(Color32::LIGHT_RED));
+            if ui.button("Dismiss").clicked() {
+                state.ui.load_warning = None;
+            }
+        }
     });
 
-    ScrollArea::vertical().show(ui, |ui| {
+    let mut scroll_area = ScrollArea::vertical();
+    // Scroll to a position requested elsewhere, e.g. jumping to a bookmark. Leave a row of margin above it.
+    if let Some(i) = state.ui.scroll_to_seq_i.take() {
+        let y = seq_i_to_pixel(i, &row_ranges, row_spacing).y;
+        scroll_area = scroll_area.vertical_scroll_offset((y - row_spacing).max(0.));
+    }
+
+    scroll_area.show(ui, |ui| {
         Frame::canvas(ui.style())
             .fill(BACKGROUND_COLOR)
             .show(ui, |ui| {
                 let (response, _painter) = {
                     // Estimate required height, based on seq len.
-                    let total_seq_height = row_ranges.len() as f32 * SEQ_ROW_SPACING_PX + 60.;
+                    let total_seq_height = row_ranges.len() as f32 * row_spacing + 60.;
 
                     let height = total_seq_height;
 
@@ -482,6 +877,8 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
                     row_ranges,
                     to_screen,
                     from_screen,
+                    row_spacing,
+                    complement_offset,
                 };
 
                 let prev_cursor_i = state.ui.cursor_seq_i;
@@ -497,6 +894,8 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
                 // Removed: We select cursor position instead now.
                 select_feature(state, &from_screen);
 
+                handle_measure_click(state);
+
                 // todo: Move this into a function A/R.
                 if state.ui.click_pending_handle {
                     // This is set up so that a click outside the text area won't reset the cursor.
@@ -509,9 +908,14 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
 
                 shapes.extend(draw_seq_indexes(&data, ui));
 
+                if state.ui.seq_visibility.show_ruler {
+                    shapes.append(&mut draw_ruler(&data, ui));
+                }
+
                 if state.ui.seq_visibility.show_primers {
                     shapes.append(&mut primer_overlay::draw_primers(
                         &state.generic[state.active].primers,
+                        &state.volatile[state.active].primer_anneal_matches,
                         state.ui.selected_item,
                         &data,
                         ui,
@@ -535,11 +939,44 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
                     shapes.append(&mut draw_selection(*selection, &data, ui));
                 }
 
+                if state.ui.seq_visibility.show_start_stop {
+                    if let Some(codon_matches) = &state.volatile[state.active].codon_matches {
+                        shapes.append(&mut draw_codon_highlights(
+                            codon_matches,
+                            state.ui.seq_visibility.start_stop_reverse,
+                            &data,
+                        ));
+                    }
+                }
+
                 // Draw nucleotides arfter the selection, so it shows through the fill.
                 shapes.append(&mut draw_nts(state, &data, ui));
 
+                if state.ui.seq_visibility.show_complement {
+                    shapes.append(&mut draw_complement(state, &data, ui));
+                }
+
                 shapes.append(&mut draw_text_cursor(state.ui.text_cursor_i, &data));
 
+                shapes.append(&mut draw_measure_points(
+                    &[state.ui.measure.point_a, state.ui.measure.point_b],
+                    &data,
+                ));
+
+                shapes.append(&mut draw_bookmarks(
+                    &state.generic[state.active].bookmarks,
+                    &data,
+                    ui,
+                ));
+
+                shapes.append(&mut draw_variants(&state.alignment.variants, &data, ui));
+
+                if let Some(aln) = &state.alignment.read_alignment {
+                    if aln.ref_tab == state.active {
+                        shapes.append(&mut draw_read_diffs(aln, &data, ui));
+                    }
+                }
+
                 ui.painter().extend(shapes);
             });
     });
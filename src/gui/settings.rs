@@ -0,0 +1,103 @@
+//! GUI code for the settings window.
+
+use eframe::egui::{self, Color32, ComboBox, Context, RichText, Sense, Ui, Vec2};
+
+use crate::{
+    gui::{
+        theme::{palette, set_palette, PaletteType},
+        ROW_SPACING,
+    },
+    misc_types::FeatureType,
+    state::State,
+    util::{set_coord_base, CoordBase},
+};
+
+const SWATCH_SIZE: f32 = 16.;
+
+/// A colored square, followed by a label.
+fn swatch(color: Color32, label: &str, ui: &mut Ui) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::splat(SWATCH_SIZE), Sense::hover());
+    ui.painter().rect_filled(rect, 2., color);
+    ui.label(label);
+}
+
+/// Show samples of the active palette's colors.
+fn palette_preview(ui: &mut Ui) {
+    let pal = palette();
+
+    ui.horizontal(|ui| {
+        swatch(pal.primer_fwd, "Primer (fwd)", ui);
+        swatch(pal.primer_rev, "Primer (rev)", ui);
+        swatch(pal.re, "RE site", ui);
+        swatch(pal.re_highlighted, "RE (selected)", ui);
+        swatch(pal.re_in_coding, "RE (in coding region)", ui);
+    });
+
+    ui.horizontal_wrapped(|ui| {
+        for feature_type in [
+            FeatureType::Generic,
+            FeatureType::Gene,
+            FeatureType::CodingRegion,
+            FeatureType::Promoter,
+            FeatureType::Terminator,
+            FeatureType::Ori,
+            FeatureType::RibosomeBindSite,
+            FeatureType::ProteinBind,
+            FeatureType::AntibioticResistance,
+            FeatureType::LongTerminalRepeat,
+            FeatureType::Exon,
+            FeatureType::Transcript,
+        ] {
+            let (r, g, b) = feature_type.color();
+            swatch(Color32::from_rgb(r, g, b), &feature_type.to_string(), ui);
+        }
+    });
+}
+
+pub fn settings_window(state: &mut State, ctx: &Context) {
+    let mut open = state.ui.show_settings;
+
+    egui::Window::new("Settings")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Color palette:");
+                ComboBox::from_id_salt(3_000)
+                    .width(160.)
+                    .selected_text(state.ui.palette.to_string())
+                    .show_ui(ui, |ui| {
+                        for pal in [PaletteType::Standard, PaletteType::Colorblind] {
+                            ui.selectable_value(&mut state.ui.palette, pal, pal.to_string());
+                        }
+                    });
+            });
+            // Apply immediately, so the preview reflects the selection.
+            set_palette(state.ui.palette);
+
+            ui.add_space(ROW_SPACING / 2.);
+            ui.horizontal(|ui| {
+                ui.label("Display positions:");
+                ComboBox::from_id_salt(3_001)
+                    .width(80.)
+                    .selected_text(state.ui.coord_base.to_string())
+                    .show_ui(ui, |ui| {
+                        for base in [CoordBase::One, CoordBase::Zero] {
+                            ui.selectable_value(&mut state.ui.coord_base, base, base.to_string());
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(
+                "How sequence positions are shown, e.g. for the cursor, features, primers, and RE \
+                sites. Positions you enter, and file formats, are unaffected.",
+            );
+            set_coord_base(state.ui.coord_base);
+
+            ui.add_space(ROW_SPACING / 2.);
+            ui.label(RichText::new("Preview:").strong());
+            palette_preview(ui);
+        });
+
+    state.ui.show_settings = open;
+}
@@ -1,4 +1,167 @@
+use std::{
+    fmt,
+    fmt::Formatter,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use bincode::{Decode, Encode};
 use eframe::egui::Color32;
 
 pub const COLOR_ACTION: Color32 = Color32::GOLD;
 pub const COLOR_INFO: Color32 = Color32::LIGHT_BLUE;
+
+/// The palette currently in use. We store this globally, since colors are read from many places
+/// that don't have access to state. Set from the UI state once per frame.
+static PALETTE_ACTIVE: AtomicU8 = AtomicU8::new(0);
+
+/// The map theme currently in use; stored globally for the same reason as the palette.
+static MAP_THEME_ACTIVE: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum PaletteType {
+    #[default]
+    Standard,
+    /// Friendly to deuteranopia and protanopia (red-green colorblindness).
+    Colorblind,
+}
+
+impl fmt::Display for PaletteType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::Standard => "Standard",
+            Self::Colorblind => "Colorblind-friendly",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+/// Colors for items drawn over the sequence, on the sequence view and maps. Feature colors
+/// are handled by `FeatureType::color()`.
+pub struct Palette {
+    pub primer_fwd: Color32,
+    pub primer_rev: Color32,
+    pub re: Color32,
+    pub re_highlighted: Color32,
+    /// RE sites that cut within a coding region.
+    pub re_in_coding: Color32,
+}
+
+impl Palette {
+    pub const STANDARD: Self = Self {
+        primer_fwd: Color32::from_rgb(255, 0, 255),
+        primer_rev: Color32::LIGHT_YELLOW,
+        re: Color32::LIGHT_RED,
+        re_highlighted: Color32::YELLOW,
+        re_in_coding: Color32::from_rgb(255, 165, 0),
+    };
+
+    /// Based on the Okabe-Ito palette.
+    pub const COLORBLIND: Self = Self {
+        primer_fwd: Color32::from_rgb(86, 180, 233),
+        primer_rev: Color32::from_rgb(240, 228, 66),
+        re: Color32::from_rgb(230, 159, 0),
+        re_highlighted: Color32::WHITE,
+        re_in_coding: Color32::from_rgb(213, 94, 0),
+    };
+}
+
+pub fn set_palette(palette: PaletteType) {
+    PALETTE_ACTIVE.store(palette as u8, Ordering::Relaxed);
+}
+
+pub fn palette_type() -> PaletteType {
+    match PALETTE_ACTIVE.load(Ordering::Relaxed) {
+        1 => PaletteType::Colorblind,
+        _ => PaletteType::Standard,
+    }
+}
+
+/// Get the colors to draw with, based on the active palette.
+pub fn palette() -> &'static Palette {
+    match palette_type() {
+        PaletteType::Standard => &Palette::STANDARD,
+        PaletteType::Colorblind => &Palette::COLORBLIND,
+    }
+}
+
+/// The background and line colors of the circular and linear maps. Light and Print suit publication
+/// figures; Print uses black lines and text only.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum MapTheme {
+    #[default]
+    Dark,
+    Light,
+    Print,
+}
+
+impl fmt::Display for MapTheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+            Self::Print => "Print",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+/// Colors for map elements other than features, primers, and RE sites; those keep their colors in all
+/// themes.
+pub struct MapColors {
+    pub background: Color32,
+    pub backbone: Color32,
+    pub tick: Color32,
+    /// Feature outlines, and their labels.
+    pub outline: Color32,
+    /// E.g. the center summary, and legend.
+    pub text: Color32,
+}
+
+impl MapColors {
+    pub const DARK: Self = Self {
+        background: Color32::from_rgb(10, 20, 10),
+        backbone: Color32::from_rgb(180, 180, 180),
+        tick: Color32::from_rgb(180, 220, 220),
+        outline: Color32::from_rgb(200, 200, 255),
+        text: Color32::from_rgb(180, 220, 220),
+    };
+
+    pub const LIGHT: Self = Self {
+        background: Color32::WHITE,
+        backbone: Color32::from_rgb(110, 110, 110),
+        tick: Color32::from_rgb(40, 70, 70),
+        outline: Color32::from_rgb(40, 40, 100),
+        text: Color32::from_rgb(30, 30, 30),
+    };
+
+    pub const PRINT: Self = Self {
+        background: Color32::WHITE,
+        backbone: Color32::BLACK,
+        tick: Color32::BLACK,
+        outline: Color32::BLACK,
+        text: Color32::BLACK,
+    };
+}
+
+pub fn set_map_theme(theme: MapTheme) {
+    MAP_THEME_ACTIVE.store(theme as u8, Ordering::Relaxed);
+}
+
+pub fn map_theme() -> MapTheme {
+    match MAP_THEME_ACTIVE.load(Ordering::Relaxed) {
+        1 => MapTheme::Light,
+        2 => MapTheme::Print,
+        _ => MapTheme::Dark,
+    }
+}
+
+/// Get the map colors to draw with, based on the active map theme.
+pub fn map_colors() -> &'static MapColors {
+    match map_theme() {
+        MapTheme::Dark => &MapColors::DARK,
+        MapTheme::Light => &MapColors::LIGHT,
+        MapTheme::Print => &MapColors::PRINT,
+    }
+}
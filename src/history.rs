@@ -0,0 +1,146 @@
+//! Undo and redo for edits to a tab's sequence, features, and primers. We store snapshots of the
+//! tab's data prior to each edit; this is simple, and restores annotations exactly, e.g. after an
+//! origin change.
+
+use std::time::{Duration, Instant};
+
+use crate::file_io::GenericData;
+
+/// The maximum number of undo steps we store per tab.
+const HISTORY_MAX: usize = 100;
+
+/// Edits closer together than this are combined into a single undo step; e.g. a burst of typing.
+const DEBOUNCE: Duration = Duration::from_millis(800);
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<GenericData>,
+    redo: Vec<GenericData>,
+    /// The time of the most recent debounced edit.
+    last_edit: Option<Instant>,
+}
+
+impl History {
+    /// Store a snapshot, prior to an edit.
+    pub fn push(&mut self, data: &GenericData) {
+        self.undo.push(data.clone());
+        if self.undo.len() > HISTORY_MAX {
+            self.undo.remove(0);
+        }
+        // A new edit invalidates the redo steps.
+        self.redo.clear();
+        self.last_edit = None;
+    }
+
+    /// Store a snapshot, prior to an edit, unless the previous debounced edit was recent.
+    pub fn push_debounced(&mut self, data: &GenericData) {
+        let now = Instant::now();
+
+        let recent = match self.last_edit {
+            Some(t) => now - t < DEBOUNCE,
+            None => false,
+        };
+
+        if !recent {
+            self.push(data);
+        }
+        self.last_edit = Some(now);
+    }
+
+    /// Returns the data to restore, if available. `current` is stored for redo.
+    pub fn undo(&mut self, current: &GenericData) -> Option<GenericData> {
+        let prev = self.undo.pop()?;
+        self.redo.push(current.clone());
+        self.last_edit = None;
+        Some(prev)
+    }
+
+    /// Returns the data to restore, if available. `current` is stored for undo.
+    pub fn redo(&mut self, current: &GenericData) -> Option<GenericData> {
+        let next = self.redo.pop()?;
+        self.undo.push(current.clone());
+        self.last_edit = None;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use na_seq::{seq_from_str, seq_to_str_lower};
+
+    use super::*;
+
+    fn data(seq: &str) -> GenericData {
+        GenericData {
+            seq: seq_from_str(seq),
+            ..Default::default()
+        }
+    }
+
+    fn seq_text(data: &GenericData) -> String {
+        seq_to_str_lower(&data.seq)
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let mut history = History::default();
+        let before = data("acgt");
+        let after = data("acgtaa");
+
+        history.push(&before);
+
+        let restored = history.undo(&after).unwrap();
+        assert_eq!(seq_text(&restored), "acgt");
+        assert!(history.undo(&restored).is_none());
+
+        let redone = history.redo(&restored).unwrap();
+        assert_eq!(seq_text(&redone), "acgtaa");
+        assert!(history.redo(&redone).is_none());
+
+        // The undo step is available again.
+        assert_eq!(seq_text(&history.undo(&redone).unwrap()), "acgt");
+    }
+
+    #[test]
+    fn edit_clears_redo() {
+        let mut history = History::default();
+
+        history.push(&data("acgt"));
+        let restored = history.undo(&data("acgtaa")).unwrap();
+
+        history.push(&restored);
+        assert!(history.redo(&data("acgtcc")).is_none());
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    #[test]
+    fn history_max() {
+        let mut history = History::default();
+
+        for i in 0..HISTORY_MAX + 5 {
+            history.push(&data(&"a".repeat(i + 1)));
+        }
+
+        assert_eq!(history.undo.len(), HISTORY_MAX);
+        // The oldest snapshots are dropped.
+        assert_eq!(history.undo[0].seq.len(), 6);
+    }
+
+    #[test]
+    fn debounced_edits_merge() {
+        let mut history = History::default();
+
+        history.push_debounced(&data("a"));
+        history.push_debounced(&data("aa"));
+        history.push_debounced(&data("aaa"));
+
+        // A burst of edits is a single undo step, restoring the state before the first.
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(seq_text(&history.undo[0]), "a");
+
+        // After a pause, the next edit is a new step.
+        history.last_edit = Some(Instant::now() - DEBOUNCE * 2);
+        history.push_debounced(&data("aaaa"));
+        assert_eq!(history.undo.len(), 2);
+    }
+}
@@ -0,0 +1,160 @@
+//! This module contains code for finding inverted repeats; these can form hairpins and cruciforms.
+//! We also use them to identify likely intrinsic (rho-independent) transcription terminators: A GC-rich
+//! hairpin, followed by a poly-T tract.
+
+use na_seq::{
+    Nucleotide,
+    Nucleotide::{A, C, G, T},
+};
+
+use crate::util::RangeIncl;
+
+// Shorter loops are sterically unfavorable.
+const MIN_LOOP: usize = 3;
+
+pub const DEFAULT_MIN_STEM: usize = 8;
+pub const DEFAULT_MAX_LOOP: usize = 9;
+
+// Terminator criteria.
+const TERM_STEM_GC_MIN: f32 = 0.6;
+// Search for the poly-T tract starting this far from the hairpin's 3' end.
+const TERM_TRACT_WINDOW: usize = 10;
+const TERM_TRACT_MIN_T: usize = 5;
+
+pub struct InvertedRepeat {
+    /// 1-based indexing, inclusive. The upstream half of the stem.
+    pub stem_5p: RangeIncl,
+    /// The downstream half of the stem; the reverse complement of `stem_5p`.
+    pub stem_3p: RangeIncl,
+    /// A GC-rich stem followed by a poly-T tract on either strand.
+    pub terminator: bool,
+}
+
+impl InvertedRepeat {
+    /// The full hairpin, including both stems and the loop.
+    pub fn range(&self) -> RangeIncl {
+        RangeIncl::new(self.stem_5p.start, self.stem_3p.end)
+    }
+
+    pub fn stem_len(&self) -> usize {
+        self.stem_5p.end - self.stem_5p.start + 1
+    }
+
+    pub fn loop_len(&self) -> usize {
+        self.stem_3p.start - self.stem_5p.end - 1
+    }
+}
+
+/// UI state for the inverted repeat search.
+pub struct InvertedRepeatUi {
+    pub min_stem: usize,
+    pub max_loop: usize,
+}
+
+impl Default for InvertedRepeatUi {
+    fn default() -> Self {
+        Self {
+            min_stem: DEFAULT_MIN_STEM,
+            max_loop: DEFAULT_MAX_LOOP,
+        }
+    }
+}
+
+/// Find inverted repeats: Pairs of sequences separated by a loop, where the second is the reverse
+/// complement of the first. Returns (5' stem, 3' stem) pairs, using 1-based indexing. Each stem is
+/// extended as far as complementarity allows, and only the smallest loop is reported for a given stem.
+pub fn find_inverted_repeats(
+    seq: &[Nucleotide],
+    min_stem: usize,
+    max_loop: usize,
+) -> Vec<(RangeIncl, RangeIncl)> {
+    let mut result = Vec::new();
+    let seq_len = seq.len();
+
+    if min_stem == 0 || seq_len < min_stem * 2 + MIN_LOOP {
+        return result;
+    }
+
+    // `loop_start` is the 0-based index of the loop's first nucleotide.
+    for loop_start in 1..seq_len {
+        for loop_len in MIN_LOOP..=max_loop {
+            let loop_end = loop_start + loop_len; // Exclusive.
+            if loop_end >= seq_len {
+                break;
+            }
+
+            // If the loop's outer nucleotides pair, this stem is reported with a shorter loop.
+            if loop_len >= MIN_LOOP + 2 && seq[loop_start].complement() == seq[loop_end - 1] {
+                continue;
+            }
+
+            let mut stem_len = 0;
+            while stem_len < loop_start
+                && loop_end + stem_len < seq_len
+                && seq[loop_start - 1 - stem_len].complement() == seq[loop_end + stem_len]
+            {
+                stem_len += 1;
+            }
+
+            if stem_len >= min_stem {
+                result.push((
+                    RangeIncl::new(loop_start - stem_len + 1, loop_start),
+                    RangeIncl::new(loop_end + 1, loop_end + stem_len),
+                ));
+            }
+        }
+    }
+
+    result
+}
+
+fn gc_portion(seq: &[Nucleotide]) -> f32 {
+    if seq.is_empty() {
+        return 0.;
+    }
+    let gc = seq.iter().filter(|nt| matches!(nt, C | G)).count();
+    gc as f32 / seq.len() as f32
+}
+
+/// If true, this hairpin has the features of an intrinsic terminator: A GC-rich stem, followed by a
+/// poly-T tract. We check the reverse strand too, where the tract is poly-A, upstream of the hairpin.
+fn is_terminator(seq: &[Nucleotide], stem_5p: &RangeIncl, stem_3p: &RangeIncl) -> bool {
+    let stem = match stem_5p.index_seq(seq) {
+        Some(s) => s,
+        None => return false,
+    };
+
+    if gc_portion(stem) < TERM_STEM_GC_MIN {
+        return false;
+    }
+
+    // Forward: T tract immediately downstream of the 3' stem. (0-based slice indices)
+    let fwd_start = stem_3p.end;
+    let fwd_end = (fwd_start + TERM_TRACT_WINDOW).min(seq.len());
+    let fwd_count = seq[fwd_start..fwd_end]
+        .iter()
+        .filter(|nt| **nt == T)
+        .count();
+
+    // Reverse: A tract immediately upstream of the 5' stem.
+    let rev_end = stem_5p.start - 1;
+    let rev_start = rev_end.saturating_sub(TERM_TRACT_WINDOW);
+    let rev_count = seq[rev_start..rev_end]
+        .iter()
+        .filter(|nt| **nt == A)
+        .count();
+
+    fwd_count >= TERM_TRACT_MIN_T || rev_count >= TERM_TRACT_MIN_T
+}
+
+/// Find inverted repeats, and classify likely terminators among them.
+pub fn find_hairpins(seq: &[Nucleotide], min_stem: usize, max_loop: usize) -> Vec<InvertedRepeat> {
+    find_inverted_repeats(seq, min_stem, max_loop)
+        .into_iter()
+        .map(|(stem_5p, stem_3p)| InvertedRepeat {
+            terminator: is_terminator(seq, &stem_5p, &stem_3p),
+            stem_5p,
+            stem_3p,
+        })
+        .collect()
+}
@@ -42,36 +42,79 @@ use reading_frame::ReadingFrameMatch;
 use state::State;
 
 use crate::{
+    ab1::Ab1Ui,
+    alignment_map::AlignmentMapUi,
     backbones::{Backbone, BackboneFilters},
     cloning::BackboneSelected,
+    concatenate::ConcatUi,
+    crispr::CrisprUi,
+    external_websites::NcbiFetchUi,
     file_io::{
-        save::{DEFAULT_DNA_FILE, DEFAULT_FASTA_FILE, DEFAULT_GENBANK_FILE, DEFAULT_PREFS_FILE},
-        FileDialogs, GenericData,
+        oligo_order::OrderSettings,
+        report::ReportSettings,
+        save::{
+            ExportFormat, DEFAULT_DNA_FILE, DEFAULT_FASTA_FILE, DEFAULT_GENBANK_FILE,
+            DEFAULT_PREFS_FILE,
+        },
+        FileDialogs, GenericData, PrimerExport,
     },
-    gui::{navigation::PageSeqTop, WINDOW_HEIGHT, WINDOW_WIDTH},
+    gel::GelUi,
+    global_find::GlobalFindUi,
+    golden_gate::GoldenGateUi,
+    gui::{
+        circle::CircleCache,
+        map_image::MapImageUi,
+        navigation::PageSeqTop,
+        primer_library::PrimerLibUi,
+        primer_qc::PrimerQcUi,
+        theme::{MapTheme, PaletteType},
+        window_size_restored, WINDOW_HEIGHT, WINDOW_WIDTH,
+    },
+    inverted_repeats::InvertedRepeatUi,
     misc_types::{FeatureDirection, FeatureType, SearchMatch},
+    oligo_anneal::{AnnealUi, ReFragmentUi},
     pcr::{PcrUi, PolymeraseType},
-    primer::{Primer, TM_TARGET},
+    primer::{
+        IonConcentrations, Primer, PrimerLenSettings, PrimerReAvoidUi, ResuspendUi,
+        TM_PAIR_MAX_DIFF, TM_TARGET,
+    },
+    primer_metrics::ScoringWeights,
+    solution_helper::ConversionUi,
     tags::TagMatch,
-    util::{get_window_title, RangeIncl},
+    util::{get_window_title, CoordBase, RangeIncl},
 };
 
 mod ab1;
 mod alignment;
 mod alignment_map;
+mod ambiguous;
 mod backbones;
+mod cassette;
 mod cloning;
+mod concatenate;
+mod crispr;
 mod external_websites;
 mod feature_db_load;
 mod file_io;
+mod gel;
+mod global_find;
+mod golden_gate;
 mod gui;
+mod history;
+mod inverted_repeats;
 mod melting_temp_calcs;
 mod misc_types;
+mod oligo_anneal;
 mod pcr;
 mod portions;
 mod primer;
+mod primer_binding;
+mod primer_library;
 mod primer_metrics;
+mod primer_qc;
 mod protein;
+mod re_buffers;
+mod re_groups;
 mod reading_frame;
 mod save_compat;
 mod solution_helper;
@@ -97,6 +140,14 @@ struct StateFeatureAdd {
     color: Option<Color>,
 }
 
+/// Values to apply to all features in a multi-selection.
+#[derive(Default)]
+struct StateFeatureBulk {
+    feature_type: FeatureType,
+    direction: FeatureDirection,
+    color: Option<Color>,
+}
+
 #[derive(Clone, Encode, Decode)]
 /// This Ui struct is used to determine which items on the sequence and map views to show and hide.
 struct SeqVisibility {
@@ -107,6 +158,22 @@ struct SeqVisibility {
     /// todo: Show and hide individual features?
     show_features: bool,
     show_reading_frame: bool,
+    /// Show the complementary strand below the forward one, in the sequence view.
+    show_complement: bool,
+    /// Show position numbers every 10 nucleotides, above the sequence.
+    show_ruler: bool,
+    /// Highlight start and stop codons in all frames, independent of features.
+    show_start_stop: bool,
+    /// Include codons on the complementary strand, when highlighting start and stop codons.
+    start_stop_reverse: bool,
+    /// List the feature types present, with their colors, on the circular map.
+    show_legend: bool,
+    /// Show a GC content track on the circular and linear maps.
+    show_gc: bool,
+    /// The window size for the GC content track, in nucleotides.
+    gc_window: usize,
+    /// Feature types not drawn on the circular and linear maps.
+    feature_types_hidden: Vec<FeatureType>,
 }
 
 impl Default for SeqVisibility {
@@ -116,6 +183,15 @@ impl Default for SeqVisibility {
             show_primers: true,
             show_features: true,
             show_reading_frame: false,
+            show_complement: false,
+            show_ruler: false,
+            show_start_stop: false,
+            start_stop_reverse: false,
+            show_legend: true,
+            show_gc: false,
+            gc_window: 100,
+            // Source features, from GenBank, generally span the whole sequence.
+            feature_types_hidden: vec![FeatureType::Source],
         }
     }
 }
@@ -134,6 +210,13 @@ pub struct ReUi {
     sticky_ends_only: bool,
     /// Only show REs that are present in at least two sequences.
     multiple_seqs: bool,
+    /// Show REs that don't cut any selected sequence, vice ones that do. E.g. for choosing enzymes
+    /// that won't cut an insert.
+    non_cutters: bool,
+    /// Color RE sites that cut within coding regions differently on the maps.
+    highlight_in_coding: bool,
+    /// A cut position (1-based) picked from the cut site list. Marked on the map.
+    cut_pointed: Option<usize>,
 }
 
 impl Default for ReUi {
@@ -144,8 +227,38 @@ impl Default for ReUi {
             unique_cutters_only: true,
             sticky_ends_only: false,
             multiple_seqs: true,
+            non_cutters: false,
+            highlight_in_coding: true,
+            cut_pointed: None,
+        }
+    }
+}
+
+/// UI state for measuring the distance between two sequence positions.
+#[derive(Default)]
+pub struct MeasureUi {
+    /// When active, clicks on the sequence and map views set measurement points instead of their normal action.
+    active: bool,
+    /// 1-based sequence indices.
+    point_a: Option<usize>,
+    point_b: Option<usize>,
+}
+
+impl MeasureUi {
+    /// Set the next point from a click. After both are set, the next click starts a new measurement.
+    pub fn add_point(&mut self, i: usize) {
+        if self.point_a.is_none() || self.point_b.is_some() {
+            self.point_a = Some(i);
+            self.point_b = None;
+        } else {
+            self.point_b = Some(i);
         }
     }
+
+    pub fn clear(&mut self) {
+        self.point_a = None;
+        self.point_b = None;
+    }
 }
 
 /// Values defined here generally aren't worth saving to file etc.
@@ -161,6 +274,10 @@ struct StateUi {
     // primer_selected: Option<usize>, // primer page only.
     feature_hover: Option<usize>, // todo: Apply similar enum logic to selection: Allow Primer::, Feature::, or None::
     selected_item: Selection,
+    /// Features selected together using Ctrl or Shift-click, for bulk operations. When not empty, this
+    /// includes the selected item.
+    selected_features: Vec<usize>,
+    feature_bulk: StateFeatureBulk,
     seq_visibility: SeqVisibility,
     hide_map_feature_editor: bool,
     /// Mouse cursor
@@ -171,6 +288,8 @@ struct StateUi {
     /// Show or hide the field to change origin
     show_origin_change: bool,
     new_origin: usize,
+    /// Show the prompt for where to open a circular sequence, when changing it to linear.
+    linearize_prompt: bool,
     /// Text-editing cursor. Used for editing on the sequence view. Chars typed
     /// will be inserted after this index. This index is 0-based.
     text_cursor_i: Option<usize>,
@@ -179,7 +298,11 @@ struct StateUi {
     click_pending_handle: bool,
     /// We use this for selecting features from the seq view
     dblclick_pending_handle: bool,
+    /// Ctrl or Shift was held during the pending click; this adds to a multi-selection.
+    click_multi_select: bool,
     cloning_insert: CloningInsertData,
+    /// The tab whose cloning design we last synced; we re-sync when this changes.
+    cloning_tab_synced: Option<usize>,
     /// Volatile; computed dynamically based on window size.
     nt_chars_per_row: usize,
     search_input: String,
@@ -191,15 +314,91 @@ struct StateUi {
     dragging: bool,
     /// 1-based indexing.
     text_selection: Option<RangeIncl>,
+    /// Show the selection's translation, in the selected reading frame.
+    translate_sel: bool,
+    backbone_detect_status: Option<String>,
     quick_feature_add_name: String,
     quick_feature_add_dir: FeatureDirection,
+    quick_feature_add_type: FeatureType,
+    /// Set when double-clicking an empty position on the map; shows a form to add a feature here.
+    quick_feature_add_at: Option<RangeIncl>,
     // todo: Protein ui A/R
     aa_ident_disp: AaIdent,
     pdb_error_received: bool,
     re: ReUi,
     backbone_filters: BackboneFilters,
     seq_edit_lock: bool,
-    ab1_start_i: usize,
+    ab1: Ab1Ui,
+    /// Colors used for primers, REs, and features.
+    palette: PaletteType,
+    map_theme: MapTheme,
+    /// Display-only; positions are 1-based internally.
+    coord_base: CoordBase,
+    show_settings: bool,
+    global_find: GlobalFindUi,
+    /// A tab with unsaved changes that the user asked to close. We confirm before closing it.
+    tab_close_pending: Option<usize>,
+    measure: MeasureUi,
+    primer_resuspend: ResuspendUi,
+    primer_lib: PrimerLibUi,
+    primer_qc: PrimerQcUi,
+    primer_re_avoid: PrimerReAvoidUi,
+    /// The lab's standard ion concentrations; the program starts with these.
+    ion_defaults: IonConcentrations,
+    /// °C. Primer quality scores are based on closeness to this.
+    tm_target: f32,
+    /// °C. We warn if paired primers' TMs differ by more than this.
+    tm_pair_max_diff: f32,
+    primer_lens: PrimerLenSettings,
+    scoring_weights: ScoringWeights,
+    /// Set when a sequence edit changes a primer's binding site.
+    edit_warning: Option<String>,
+    /// Set when degenerate bases are removed from a primer's sequence.
+    primer_seq_warning: Option<String>,
+    /// The result of the last primer CSV import or export, including order sheets.
+    primer_csv_status: Option<String>,
+    order_settings: OrderSettings,
+    /// The window's inner size, in points. Saved in preferences, and restored on launch.
+    window_size: (f32, f32),
+    /// Set when features loaded from a file had coordinates we had to fix.
+    load_warning: Option<String>,
+    /// The name to give the next bookmark added.
+    bookmark_name: String,
+    /// 1-based. Set to scroll the sequence view to this position on the next frame; e.g. to jump to
+    /// a bookmark.
+    scroll_to_seq_i: Option<usize>,
+    /// Index into the active tab's primers; compared against the selected primer on the primer page.
+    primer_pair_partner: usize,
+    conversion: ConversionUi,
+    inverted_repeat: InvertedRepeatUi,
+    anneal: AnnealUi,
+    re_fragment: ReFragmentUi,
+    concat: ConcatUi,
+    golden_gate: GoldenGateUi,
+    gel: GelUi,
+    alignment_map: AlignmentMapUi,
+    crispr: CrisprUi,
+    /// Set when requesting deletion of the selected feature or primer from the keyboard; we ask for
+    /// confirmation before removing it.
+    delete_pending: bool,
+    export_all_format: ExportFormat,
+    /// How primers are included in SnapGene and GFF3 exports.
+    primer_export: PrimerExport,
+    /// The result of the most recent "export all".
+    export_all_status: Option<String>,
+    /// The result of the last GFF3 feature import.
+    gff_status: Option<String>,
+    /// Include primers when importing annotations from another file.
+    import_annotation_primers: bool,
+    /// The result of the last annotation import.
+    import_annotation_status: Option<String>,
+    /// The result of the last project save or load.
+    project_status: Option<String>,
+    report: ReportSettings,
+    ncbi_fetch: NcbiFetchUi,
+    map_image: MapImageUi,
+    /// Volatile; shapes drawn on the circular map.
+    circle_cache: CircleCache,
 }
 
 impl Default for StateUi {
@@ -213,6 +412,8 @@ impl Default for StateUi {
             feature_add: Default::default(),
             feature_hover: Default::default(),
             selected_item: Default::default(),
+            selected_features: Vec::new(),
+            feature_bulk: Default::default(),
             seq_visibility: Default::default(),
             hide_map_feature_editor: true,
             cursor_pos: Default::default(),
@@ -220,24 +421,77 @@ impl Default for StateUi {
             file_dialogs: Default::default(),
             show_origin_change: Default::default(),
             new_origin: Default::default(),
+            linearize_prompt: false,
             text_cursor_i: Some(0),
             click_pending_handle: Default::default(),
             dblclick_pending_handle: Default::default(),
+            click_multi_select: false,
             cloning_insert: Default::default(),
+            cloning_tab_synced: None,
             nt_chars_per_row: Default::default(),
             search_input: Default::default(),
             highlight_search_input: Default::default(),
             text_edit_active: Default::default(),
             dragging: Default::default(),
             text_selection: Default::default(),
+            translate_sel: false,
+            backbone_detect_status: None,
             quick_feature_add_name: Default::default(),
             quick_feature_add_dir: Default::default(),
+            quick_feature_add_type: Default::default(),
+            quick_feature_add_at: None,
             aa_ident_disp: AaIdent::OneLetter,
             pdb_error_received: false,
             re: Default::default(),
             backbone_filters: Default::default(),
             seq_edit_lock: true,
-            ab1_start_i: Default::default(),
+            ab1: Default::default(),
+            palette: Default::default(),
+            map_theme: Default::default(),
+            coord_base: Default::default(),
+            show_settings: false,
+            global_find: Default::default(),
+            tab_close_pending: None,
+            measure: Default::default(),
+            primer_resuspend: Default::default(),
+            primer_lib: Default::default(),
+            primer_qc: Default::default(),
+            primer_re_avoid: Default::default(),
+            ion_defaults: Default::default(),
+            tm_target: TM_TARGET,
+            tm_pair_max_diff: TM_PAIR_MAX_DIFF,
+            primer_lens: Default::default(),
+            scoring_weights: Default::default(),
+            edit_warning: None,
+            primer_seq_warning: None,
+            primer_csv_status: None,
+            order_settings: Default::default(),
+            window_size: (WINDOW_WIDTH, WINDOW_HEIGHT),
+            load_warning: None,
+            bookmark_name: String::new(),
+            scroll_to_seq_i: None,
+            primer_pair_partner: 0,
+            conversion: Default::default(),
+            inverted_repeat: Default::default(),
+            anneal: Default::default(),
+            re_fragment: Default::default(),
+            concat: Default::default(),
+            golden_gate: Default::default(),
+            gel: Default::default(),
+            alignment_map: Default::default(),
+            crispr: Default::default(),
+            delete_pending: false,
+            export_all_format: ExportFormat::GenBank,
+            primer_export: Default::default(),
+            export_all_status: None,
+            gff_status: None,
+            import_annotation_primers: true,
+            import_annotation_status: None,
+            project_status: None,
+            report: Default::default(),
+            ncbi_fetch: Default::default(),
+            map_image: Default::default(),
+            circle_cache: Default::default(),
         }
     }
 }
@@ -258,15 +512,11 @@ impl Default for Selection {
 fn main() {
     let mut state = State::default();
 
-    // todo: Temp to test BAM
-    let am = alignment_map::import(&PathBuf::from_str("../../Desktop/test.bam").unwrap()).unwrap();
-    println!("Alignment map loaded: {:?}", am);
-
     state.load_prefs(&PathBuf::from_str(DEFAULT_PREFS_FILE).unwrap());
 
     // Initial load  hierarchy:
     // - Path argument (e.g. file association)
-    // - Last opened files
+    // - Last opened files, including unsaved tabs, and the active tab
     // - Quicksave
 
     let mut loaded_from_arg = false;
@@ -287,9 +537,10 @@ fn main() {
         (p, window_title)
     };
 
+    // Unsaved tabs restored from the previous session have no path, but do have data.
     let mut prev_paths_loaded = false;
-    for tab in &state.tabs_open {
-        if tab.path.is_some() {
+    for (i, tab) in state.tabs_open.iter().enumerate() {
+        if tab.path.is_some() || !state.generic[i].seq.is_empty() {
             prev_paths_loaded = true;
         }
     }
@@ -313,7 +564,7 @@ fn main() {
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([WINDOW_WIDTH, WINDOW_HEIGHT])
+            .with_inner_size(window_size_restored(state.ui.window_size))
             .with_icon(icon_data.unwrap()),
         ..Default::default()
     };
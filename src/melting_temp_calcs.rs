@@ -14,7 +14,7 @@ use na_seq::{
     Nucleotide::{self, A, C, G, T},
 };
 
-use crate::primer::{IonConcentrations, MIN_PRIMER_LEN};
+use crate::primer::{min_primer_len, IonConcentrations};
 
 const R: f32 = 1.987; // Universal gas constant (Cal/C * Mol)
 
@@ -169,7 +169,7 @@ fn _dH_dS_de(nts: (Nucleotide, Nucleotide)) -> Option<(f32, f32)> {
 /// A: Left to right, left of the slash
 /// B: Right to left, right of the slash
 /// You will find exactly one match using this approach.
-fn dH_dS_neighbors(neighbors: (Nucleotide, Nucleotide)) -> (f32, f32) {
+pub fn dH_dS_neighbors(neighbors: (Nucleotide, Nucleotide)) -> (f32, f32) {
     match neighbors {
         (A, A) | (T, T) => (-7.6, -21.3),
         (A, T) => (-7.2, -20.4),
@@ -276,7 +276,7 @@ fn salt_correction(seq: &[Nucleotide], ion: &IonConcentrations) -> Option<f32> {
 }
 
 pub fn calc_tm(seq: &[Nucleotide], ion_concentrations: &IonConcentrations) -> Option<f32> {
-    if seq.len() < MIN_PRIMER_LEN {
+    if seq.len() < min_primer_len() {
         return None;
     }
 
@@ -1,14 +1,61 @@
 //! This module contains fundamental data structures, eg related to features, metadata, etc.
 
 use bincode::{Decode, Encode};
-use na_seq::Nucleotide;
+use na_seq::{Nucleotide, SeqTopology};
 
 use crate::{
+    gui::theme::{palette_type, PaletteType},
     primer::PrimerDirection,
-    util::{match_subseq, RangeIncl},
+    util::{disp_range, match_subseq_degenerate, RangeIncl},
     Color,
 };
 pub const MIN_SEARCH_LEN: usize = 3;
+/// The note key we use to record how well an automatically-annotated feature matches its reference.
+/// Formatted as a GenBank qualifier, since we export notes as these.
+pub const ANNOTATION_MATCH_NOTE: &str = "annotation_match";
+
+/// How well an automatically-annotated feature matches its reference sequence. We store this in the
+/// feature's notes, so it's saved and exported with them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AnnotationMatch {
+    pub mismatches: usize,
+    /// The reference sequence's length.
+    pub len: usize,
+}
+
+impl AnnotationMatch {
+    /// From 0 to 1.
+    pub fn identity(&self) -> f32 {
+        if self.len == 0 {
+            return 0.;
+        }
+        self.len.saturating_sub(self.mismatches) as f32 / self.len as f32
+    }
+
+    pub fn exact(&self) -> bool {
+        self.mismatches == 0
+    }
+
+    /// E.g. "99.9% identity, 1 mismatches, 1204 nt".
+    pub fn to_note(self) -> String {
+        // todo: Update the matched length once we support partial matches.
+        format!(
+            "{:.1}% identity, {} mismatches, {} nt",
+            self.identity() * 100.,
+            self.mismatches,
+            self.len
+        )
+    }
+
+    /// Parse from the note written by `to_note`. The identity is recalculated from the counts.
+    pub fn from_note(note: &str) -> Option<Self> {
+        let mut parts = note.split(", ").skip(1);
+        let mismatches = parts.next()?.strip_suffix(" mismatches")?.parse().ok()?;
+        let len = parts.next()?.strip_suffix(" nt")?.parse().ok()?;
+
+        Some(Self { mismatches, len })
+    }
+}
 
 #[derive(Clone, Copy, PartialEq, Encode, Decode)]
 pub enum FeatureType {
@@ -66,6 +113,10 @@ impl FeatureType {
     }
 
     pub fn color(&self) -> Color {
+        if palette_type() == PaletteType::Colorblind {
+            return self.color_colorblind();
+        }
+
         match self {
             Self::Generic => (255, 0, 255),
             Self::Gene => (255, 128, 128),
@@ -85,6 +136,27 @@ impl FeatureType {
         }
     }
 
+    /// Colors distinguishable with deuteranopia and protanopia. Based on the Okabe-Ito palette.
+    fn color_colorblind(&self) -> Color {
+        match self {
+            Self::Generic => (204, 121, 167),
+            Self::Gene => (230, 159, 0),
+            Self::Ori => (0, 158, 115),
+            Self::RibosomeBindSite => (240, 228, 66),
+            Self::Promoter => (213, 94, 0),
+            Self::AntibioticResistance => (0, 114, 178),
+            Self::Primer => (0, 0, 0),
+            Self::CodingRegion => (86, 180, 233),
+            Self::LongTerminalRepeat => (170, 170, 170),
+            Self::Source => (120, 120, 120),
+            Self::Exon => (255, 240, 200),
+            Self::Transcript => (200, 230, 255),
+            Self::Selection => (255, 255, 255),
+            Self::ProteinBind => (150, 130, 200),
+            Self::Terminator => (160, 80, 40),
+        }
+    }
+
     /// Parse from a string; we use this for both SnapGene and GenBank.
     pub fn from_external_str(v: &str) -> Self {
         // todo: Update as required with more
@@ -165,7 +237,7 @@ impl FeatureDirection {
     }
 }
 
-#[derive(Clone, Encode, Decode)]
+#[derive(Clone, PartialEq, Encode, Decode)]
 pub struct Feature {
     // pub range: (usize, usize),
     /// 1-based indexing, inclusive. (Note: Could also use the builtin RangeInclusive.)
@@ -177,6 +249,9 @@ pub struct Feature {
     /// to override this.
     pub color_override: Option<Color>,
     pub notes: Vec<(String, String)>,
+    /// 1, 2, or 3. The offset of the first complete codon from the feature's 5' end. Corresponds to
+    /// GenBank's `/codon_start` qualifier; only relevant for coding regions.
+    pub codon_start: u8,
 }
 
 impl Default for Feature {
@@ -188,6 +263,7 @@ impl Default for Feature {
             label: Default::default(),
             color_override: Default::default(),
             notes: Default::default(),
+            codon_start: 1,
         }
     }
 }
@@ -201,6 +277,16 @@ impl Feature {
         }
     }
 
+    /// For automatically-annotated features, how well it matches the reference sequence. `None` if
+    /// this feature wasn't annotated from a reference.
+    pub fn annotation_match(&self) -> Option<AnnotationMatch> {
+        let (_, val) = self
+            .notes
+            .iter()
+            .find(|(key, _)| key == ANNOTATION_MATCH_NOTE)?;
+        AnnotationMatch::from_note(val)
+    }
+
     /// Get the color to draw; type color, unless overridden.
     pub fn color(&self) -> Color {
         match self.color_override {
@@ -211,25 +297,119 @@ impl Feature {
 
     /// Get the feature len, in usize.
     pub fn len(&self, seq_len: usize) -> usize {
-        if self.range.end > self.range.start {
-            self.range.end - self.range.start + 1
+        // A start past the end is a wrap through the origin.
+        range_len(self.range, seq_len)
+    }
+
+    /// Combine this feature with another into a single feature spanning both ranges. The label,
+    /// type, direction, and notes are taken from the larger of the two. On circular sequences, handles
+    /// ranges that wrap through the origin; the result is the shortest range that covers both.
+    /// Returns `None` if the features neither overlap nor touch, since the result would include the
+    /// gap between them.
+    pub fn merge(&self, other: &Self, seq_len: usize, topology: SeqTopology) -> Option<Self> {
+        let (larger, smaller) = if self.len(seq_len) >= other.len(seq_len) {
+            (self, other)
         } else {
-            // ie a wrap through the origin
-            self.range.end + seq_len - self.range.start + 1
+            (other, self)
+        };
+
+        let a = larger.range;
+        let b = smaller.range;
+
+        let mut range = a;
+        let mut best_len = None;
+
+        for candidate in [
+            a,
+            b,
+            RangeIncl::new(a.start, b.end),
+            RangeIncl::new(b.start, a.end),
+        ] {
+            // Only circular sequences can have a range that wraps the origin.
+            if topology == SeqTopology::Linear && candidate.start > candidate.end {
+                continue;
+            }
+            if !range_covers(candidate, a, seq_len) || !range_covers(candidate, b, seq_len) {
+                continue;
+            }
+
+            let len = range_len(candidate, seq_len);
+            if best_len.is_none() || len < best_len.unwrap() {
+                range = candidate;
+                best_len = Some(len);
+            }
+        }
+
+        let best_len = best_len?;
+        if best_len > range_len(a, seq_len) + range_len(b, seq_len) {
+            return None;
+        }
+
+        Some(Self {
+            range,
+            ..larger.clone()
+        })
+    }
+
+    /// Split this feature into two, after nucleotide `split_i` (1-based). Both keep the label, type,
+    /// and direction. The pair is returned in reading order, ie for reverse features, the piece at
+    /// higher indices is first. Returns `None` if the split point isn't inside the feature, including
+    /// if it's on its last nucleotide.
+    pub fn split(&self, split_i: usize, seq_len: usize) -> Option<(Self, Self)> {
+        if split_i < 1 || split_i > seq_len {
+            return None;
+        }
+
+        let offset = if split_i >= self.range.start {
+            split_i - self.range.start
+        } else {
+            split_i + seq_len - self.range.start
+        };
+
+        if offset + 1 >= self.len(seq_len) {
+            return None;
+        }
+
+        let second_start = if split_i == seq_len { 1 } else { split_i + 1 };
+
+        let first = Self {
+            range: RangeIncl::new(self.range.start, split_i),
+            ..self.clone()
+        };
+        let second = Self {
+            range: RangeIncl::new(second_start, self.range.end),
+            ..self.clone()
+        };
+
+        if self.direction == FeatureDirection::Reverse {
+            Some((second, first))
+        } else {
+            Some((first, second))
         }
     }
 
     /// Formats the indexes, and size of this feature.
     pub fn location_descrip(&self, seq_len: usize) -> String {
-        format!(
-            "{}..{}  {} bp",
-            self.range.start,
-            self.range.end,
-            self.len(seq_len)
-        )
+        format!("{}  {} bp", disp_range(self.range), self.len(seq_len))
     }
 }
 
+/// Length of a range on a circular sequence; a start past the end means it wraps the origin.
+pub fn range_len(range: RangeIncl, seq_len: usize) -> usize {
+    range.len_topology(seq_len, SeqTopology::Circular)
+}
+
+/// Determine if `outer` fully contains `inner`, on a circular sequence.
+pub fn range_covers(outer: RangeIncl, inner: RangeIncl, seq_len: usize) -> bool {
+    let offset = if inner.start >= outer.start {
+        inner.start - outer.start
+    } else {
+        inner.start + seq_len - outer.start
+    };
+
+    offset + range_len(inner, seq_len) <= range_len(outer, seq_len)
+}
+
 /// Contains sequence-level metadata.
 #[derive(Clone, Default, Encode, Decode)]
 pub struct Metadata {
@@ -264,17 +444,17 @@ pub struct Reference {
 }
 
 pub struct SearchMatch {
-    /// 0-based indexing.
+    /// 1-based indexing.
     pub range: RangeIncl,
     // todo: More A/R
 }
 
 // todo: Should this go to the `seq` library?
-/// Find exact matches in the target sequence of our search nucleotides.
+/// Find exact matches in the target sequence of our search nucleotides. The search may contain IUPAC
+/// degenerate codes, which match any base in their set.
 /// todo: Optionally support partial matches.
-/// todo:
-pub fn find_search_matches(seq: &[Nucleotide], search_seq: &[Nucleotide]) -> Vec<SearchMatch> {
-    let (mut fwd, mut rev) = match_subseq(search_seq, seq);
+pub fn find_search_matches(seq: &[Nucleotide], search_seq: &[u8]) -> Vec<SearchMatch> {
+    let (mut fwd, mut rev) = match_subseq_degenerate(search_seq, seq);
 
     fwd.append(&mut rev);
     fwd.into_iter().map(|range| SearchMatch { range }).collect()
@@ -0,0 +1,399 @@
+//! This module contains code for annealing two single-stranded oligos into a double-stranded product,
+//! e.g. for short inserts like shRNA cassettes and adaptors. We find the best alignment between the first
+//! oligo and the reverse complement of the second, and describe the overhangs at each end.
+//!
+//! Note: `LigationFragment` ends are defined by restriction enzymes, so we don't produce those here;
+//! the product can be opened in a new tab instead.
+//!
+//! We also use this duplex representation for fragments between two restriction sites, so their
+//! sticky ends can be shown and copied.
+
+use std::{fmt, fmt::Formatter};
+
+use na_seq::{
+    restriction_enzyme::{ReMatch, RestrictionEnzyme},
+    seq_complement, seq_to_str_lower, Nucleotide, Seq, SeqTopology,
+};
+
+use crate::{
+    file_io::GenericData,
+    misc_types::{Feature, FeatureDirection},
+    primer::Primer,
+    util::RangeIncl,
+};
+
+/// Require at least this many paired nucleotides to consider the oligos annealed.
+const MIN_DUPLEX_OVERLAP: usize = 6;
+
+/// One end of a duplex. Overhangs are listed 5' to 3', on the strand that overhangs. At the left end,
+/// a 5' overhang is on the top strand, and a 3' overhang is on the bottom. This is reversed at the right end.
+#[derive(Clone, PartialEq)]
+pub enum DuplexEnd {
+    Blunt,
+    Overhang5p(Seq),
+    Overhang3p(Seq),
+}
+
+impl fmt::Display for DuplexEnd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blunt => write!(f, "Blunt"),
+            Self::Overhang5p(s) => write!(f, "5' overhang: {}", seq_to_str_lower(s)),
+            Self::Overhang3p(s) => write!(f, "3' overhang: {}", seq_to_str_lower(s)),
+        }
+    }
+}
+
+impl DuplexEnd {
+    /// If true, this right end can be ligated to `left`, the left end of another duplex: Both are
+    /// blunt, or their overhangs are of the same type, and complementary.
+    pub fn ligates_with(&self, left: &Self) -> bool {
+        match (self, left) {
+            (Self::Blunt, Self::Blunt) => true,
+            (Self::Overhang5p(a), Self::Overhang5p(b))
+            | (Self::Overhang3p(a), Self::Overhang3p(b)) => *a == seq_complement(b),
+            _ => false,
+        }
+    }
+}
+
+fn nt_char(nt: Nucleotide) -> char {
+    seq_to_str_lower(&[nt]).chars().next().unwrap_or(' ')
+}
+
+pub struct AnnealedDuplex {
+    /// The first oligo, 5' to 3'.
+    pub top: Seq,
+    /// The second oligo, 5' to 3'.
+    pub bottom: Seq,
+    /// Where the bottom strand's 3' end aligns, relative to the top strand's 5' end. Negative if it
+    /// extends past it.
+    pub offset: isize,
+    pub overlap_len: usize,
+    /// Non-complementary pairs within the overlap.
+    pub mismatches: usize,
+    pub left: DuplexEnd,
+    pub right: DuplexEnd,
+}
+
+impl AnnealedDuplex {
+    /// If true, the oligos are complementary across their overlap, and this overlap is long enough to
+    /// anneal.
+    pub fn complementary(&self) -> bool {
+        self.mismatches == 0 && self.overlap_len >= MIN_DUPLEX_OVERLAP
+    }
+
+    /// The full product, as read on the top strand, including both strands' overhangs.
+    pub fn seq_full(&self) -> Seq {
+        let bottom_rc = seq_complement(&self.bottom);
+        let start = self.offset.min(0);
+        let end = (self.top.len() as isize).max(self.offset + bottom_rc.len() as isize);
+
+        (start..end)
+            .map(|i| {
+                if i >= 0 && (i as usize) < self.top.len() {
+                    self.top[i as usize]
+                } else {
+                    bottom_rc[(i - self.offset) as usize]
+                }
+            })
+            .collect()
+    }
+
+    /// A text depiction of the duplex, for display in monospace. The top strand reads 5' to 3', and
+    /// the bottom 3' to 5'. Mismatches are marked between them.
+    pub fn diagram(&self) -> String {
+        let bottom_rc = seq_complement(&self.bottom);
+        let start = self.offset.min(0);
+        let end = (self.top.len() as isize).max(self.offset + bottom_rc.len() as isize);
+
+        let mut top = String::new();
+        let mut pairs = String::new();
+        let mut bottom = String::new();
+
+        for i in start..end {
+            let top_nt = if i >= 0 && (i as usize) < self.top.len() {
+                Some(self.top[i as usize])
+            } else {
+                None
+            };
+
+            let j = i - self.offset;
+            let bottom_nt = if j >= 0 && (j as usize) < bottom_rc.len() {
+                // Display the bottom strand's actual nucleotide, vice its complement.
+                Some(bottom_rc[j as usize].complement())
+            } else {
+                None
+            };
+
+            top.push(top_nt.map(nt_char).unwrap_or(' '));
+            bottom.push(bottom_nt.map(nt_char).unwrap_or(' '));
+            pairs.push(match (top_nt, bottom_nt) {
+                (Some(t), Some(b)) if t.complement() == b => '|',
+                (Some(_), Some(_)) => 'x',
+                _ => ' ',
+            });
+        }
+
+        format!(
+            "5' {} 3'\n   {}\n3' {} 5'",
+            top.trim_end(),
+            pairs.trim_end(),
+            bottom.trim_end()
+        )
+    }
+
+    /// Create sequence data for the product, e.g. to open in a new tab. The top-strand sequence
+    /// includes both strands' overhangs; we annotate these as features.
+    pub fn to_generic(&self, name: &str) -> GenericData {
+        let seq = self.seq_full();
+        let mut features = Vec::new();
+
+        let left_len = match &self.left {
+            DuplexEnd::Blunt => 0,
+            DuplexEnd::Overhang5p(s) | DuplexEnd::Overhang3p(s) => s.len(),
+        };
+        let right_len = match &self.right {
+            DuplexEnd::Blunt => 0,
+            DuplexEnd::Overhang5p(s) | DuplexEnd::Overhang3p(s) => s.len(),
+        };
+
+        if left_len > 0 {
+            features.push(Feature {
+                range: RangeIncl::new(1, left_len),
+                label: format!("Left {}", self.left),
+                ..Default::default()
+            });
+        }
+        if right_len > 0 {
+            features.push(Feature {
+                range: RangeIncl::new(seq.len() - right_len + 1, seq.len()),
+                label: format!("Right {}", self.right),
+                ..Default::default()
+            });
+        }
+
+        // The oligos themselves.
+        let top_start = (-self.offset).max(0) as usize + 1;
+        features.push(Feature {
+            range: RangeIncl::new(top_start, top_start + self.top.len() - 1),
+            direction: FeatureDirection::Forward,
+            label: "Top oligo".to_owned(),
+            ..Default::default()
+        });
+
+        let bottom_start = (self.offset - self.offset.min(0)) as usize + 1;
+        features.push(Feature {
+            range: RangeIncl::new(bottom_start, bottom_start + self.bottom.len() - 1),
+            direction: FeatureDirection::Reverse,
+            label: "Bottom oligo".to_owned(),
+            ..Default::default()
+        });
+
+        let mut result = GenericData {
+            seq,
+            topology: SeqTopology::Linear,
+            features,
+            ..Default::default()
+        };
+        result.metadata.plasmid_name = name.to_owned();
+        result
+    }
+}
+
+/// Find the best-scoring alignment between two oligos, and describe the resulting duplex. Returns `None`
+/// if either is empty. Scoring is the count of complementary pairs, less mismatches, across the overlap.
+pub fn anneal_oligos(top: &[Nucleotide], bottom: &[Nucleotide]) -> Option<AnnealedDuplex> {
+    if top.is_empty() || bottom.is_empty() {
+        return None;
+    }
+
+    let bottom_rc = seq_complement(bottom);
+    let (len_top, len_bot) = (top.len() as isize, bottom_rc.len() as isize);
+
+    let mut best: Option<(isize, usize, usize)> = None; // (offset, overlap, mismatches)
+    let mut best_score = isize::MIN;
+
+    for offset in -(len_bot - 1)..len_top {
+        let start = offset.max(0);
+        let end = len_top.min(offset + len_bot);
+
+        let mut matches = 0;
+        let mut mismatches = 0;
+        for i in start..end {
+            if top[i as usize] == bottom_rc[(i - offset) as usize] {
+                matches += 1;
+            } else {
+                mismatches += 1;
+            }
+        }
+
+        let score = matches as isize - mismatches as isize;
+        if score > best_score {
+            best_score = score;
+            best = Some((offset, (end - start) as usize, mismatches));
+        }
+    }
+
+    let (offset, overlap_len, mismatches) = best?;
+
+    let left = if offset > 0 {
+        DuplexEnd::Overhang5p(top[..offset as usize].to_vec())
+    } else if offset < 0 {
+        // The bottom strand's 3' end, read 5' to 3'.
+        let n = (-offset) as usize;
+        DuplexEnd::Overhang3p(bottom[bottom.len() - n..].to_vec())
+    } else {
+        DuplexEnd::Blunt
+    };
+
+    let bottom_end = offset + len_bot;
+    let right = if len_top > bottom_end {
+        DuplexEnd::Overhang3p(top[bottom_end as usize..].to_vec())
+    } else if bottom_end > len_top {
+        // The bottom strand's 5' end.
+        let n = (bottom_end - len_top) as usize;
+        DuplexEnd::Overhang5p(bottom[..n].to_vec())
+    } else {
+        DuplexEnd::Blunt
+    };
+
+    Some(AnnealedDuplex {
+        top: top.to_vec(),
+        bottom: bottom.to_vec(),
+        offset,
+        overlap_len,
+        mismatches,
+        left,
+        right,
+    })
+}
+
+/// Cut positions within a recognition site, as the count of nucleotides left of the cut, on the top
+/// and bottom strands. We assume palindromic sites, as in our RE library.
+fn cut_offsets(re: &RestrictionEnzyme) -> (usize, usize) {
+    let top = re.cut_after as usize + 1;
+    (top, re.cut_seq.len().saturating_sub(top))
+}
+
+/// The ends an enzyme leaves when it cuts: (The left end of the fragment downstream of the cut, the right
+/// end of the fragment upstream of it). These use the same conventions as `re_fragment`. `None` if the
+/// overhang includes a degenerate base, e.g. N, since its sequence depends on the site cut.
+pub fn re_ends(re: &RestrictionEnzyme) -> Option<(DuplexEnd, DuplexEnd)> {
+    let (top, bottom) = cut_offsets(re);
+    let site = &re.cut_seq;
+
+    if top.max(bottom) > site.len() || top == bottom {
+        return Some((DuplexEnd::Blunt, DuplexEnd::Blunt));
+    }
+
+    let overhang: Option<Seq> = site[top.min(bottom)..top.max(bottom)]
+        .iter()
+        .map(|nt| Nucleotide::from_u8(nt.to_u8_lower()).ok())
+        .collect();
+    let overhang = overhang?;
+
+    if top < bottom {
+        Some((
+            DuplexEnd::Overhang5p(overhang.clone()),
+            DuplexEnd::Overhang5p(seq_complement(&overhang)),
+        ))
+    } else {
+        Some((
+            DuplexEnd::Overhang3p(seq_complement(&overhang)),
+            DuplexEnd::Overhang3p(overhang),
+        ))
+    }
+}
+
+/// The double-stranded fragment between two restriction sites, including the overhangs each enzyme
+/// leaves. On circular sequences, the fragment may wrap the origin; a single site gives the linearized
+/// sequence. Returns `None` if the sites don't bound a fragment.
+pub fn re_fragment(
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    site_left: &ReMatch,
+    site_right: &ReMatch,
+    re_lib: &[RestrictionEnzyme],
+) -> Option<AnnealedDuplex> {
+    if site_left.lib_index >= re_lib.len() || site_right.lib_index >= re_lib.len() {
+        eprintln!("Invalid RE index for fragment");
+        return None;
+    }
+
+    let (top_l, bottom_l) = cut_offsets(&re_lib[site_left.lib_index]);
+    let (top_r, bottom_r) = cut_offsets(&re_lib[site_right.lib_index]);
+
+    // Rotate circular sequences as required, so the fragment is contiguous. 0-based indices.
+    let (seq, start, end) = if site_right.seq_index > site_left.seq_index {
+        (seq.to_vec(), site_left.seq_index, site_right.seq_index)
+    } else if topology == SeqTopology::Circular {
+        let mut rotated = seq[site_left.seq_index..].to_vec();
+        rotated.extend(seq);
+        (
+            rotated,
+            0,
+            site_right.seq_index + seq.len() - site_left.seq_index,
+        )
+    } else {
+        return None;
+    };
+
+    // End indices are exclusive, in the working sequence's coordinates.
+    let (top_start, top_end) = (start + top_l, end + top_r);
+    let (bottom_start, bottom_end) = (start + bottom_l, end + bottom_r);
+
+    if top_end <= top_start || bottom_end <= bottom_start || top_end.max(bottom_end) > seq.len() {
+        return None;
+    }
+
+    let left = if top_start < bottom_start {
+        DuplexEnd::Overhang5p(seq[top_start..bottom_start].to_vec())
+    } else if top_start > bottom_start {
+        DuplexEnd::Overhang3p(seq_complement(&seq[bottom_start..top_start]))
+    } else {
+        DuplexEnd::Blunt
+    };
+
+    let right = if top_end > bottom_end {
+        DuplexEnd::Overhang3p(seq[bottom_end..top_end].to_vec())
+    } else if top_end < bottom_end {
+        DuplexEnd::Overhang5p(seq_complement(&seq[top_end..bottom_end]))
+    } else {
+        DuplexEnd::Blunt
+    };
+
+    Some(AnnealedDuplex {
+        top: seq[top_start..top_end].to_vec(),
+        bottom: seq_complement(&seq[bottom_start..bottom_end]),
+        offset: bottom_start as isize - top_start as isize,
+        overlap_len: top_end
+            .min(bottom_end)
+            .saturating_sub(top_start.max(bottom_start)),
+        mismatches: 0,
+        left,
+        right,
+    })
+}
+
+/// UI state for copying the fragment between two restriction sites.
+#[derive(Default)]
+pub struct ReFragmentUi {
+    /// Indices into the active tab's RE matches.
+    pub site_left: usize,
+    pub site_right: usize,
+}
+
+/// UI state for the oligo annealing tool.
+#[derive(Default)]
+pub struct AnnealUi {
+    /// Indices into the active tab's primers.
+    pub primer_top: usize,
+    pub primer_bottom: usize,
+    pub result: Option<AnnealedDuplex>,
+}
+
+/// Anneal two primers, using their full synthesized sequences (ie including tails).
+pub fn anneal_primers(top: &Primer, bottom: &Primer) -> Option<AnnealedDuplex> {
+    anneal_oligos(&top.synthesis_seq(), &bottom.synthesis_seq())
+}
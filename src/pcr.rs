@@ -1,11 +1,13 @@
 //! This module assists in identifying PCR parameters
 
 use bincode::{Decode, Encode};
-use na_seq::Seq;
+use na_seq::{seq_complement, Nucleotide, Seq, SeqTopology};
 
 use crate::{
+    file_io::GenericData,
     gui::navigation::{Page, PageSeq, Tab},
-    primer::{Primer, TM_TARGET},
+    misc_types::{range_covers, range_len, FeatureDirection},
+    primer::{tm_target, AnnealMatch, Primer, PrimerDirection},
     state::State,
     util::RangeIncl,
 };
@@ -27,7 +29,7 @@ pub struct PcrUi {
 impl Default for PcrUi {
     fn default() -> Self {
         Self {
-            primer_tm: TM_TARGET,
+            primer_tm: tm_target(),
             product_len: 1_000,
             polymerase_type: Default::default(),
             num_cycles: 30,
@@ -125,41 +127,163 @@ impl PcrParams {
     }
 }
 
-/// Create a new tab containing of the PCR amplicon.
+/// Binding sites, and non-annealing 5' tails for a primer pair that forms a PCR product.
+pub struct AmpliconSites {
+    /// The template range amplified; from the start of the forward primer's annealing region,
+    /// to the end of the reverse primer's. This may wrap the origin.
+    pub range: RangeIncl,
+    /// Non-annealing 5' portions of each primer, in 5' to 3' primer orientation. These are added to
+    /// the product's ends.
+    pub tail_fwd: Seq,
+    pub tail_rev: Seq,
+}
+
+/// Find the single binding site of a primer in a given direction, and its non-annealing 5' portion.
+/// This includes primers that only anneal at their 3' end.
+fn primer_site(
+    primer_i: usize,
+    primer: &Primer,
+    direction: PrimerDirection,
+    anneal_matches: &[AnnealMatch],
+) -> Result<(RangeIncl, Seq), String> {
+    let mut sites = Vec::new();
+
+    for match_ in &primer.volatile.matches {
+        if match_.direction == direction {
+            sites.push((match_.range, 0));
+        }
+    }
+
+    for anneal in anneal_matches {
+        if anneal.primer_i == primer_i && anneal.match_.direction == direction {
+            sites.push((anneal.match_.range, anneal.tail_len));
+        }
+    }
+
+    if sites.len() != 1 {
+        return Err(format!(
+            "{} must have exactly one binding site in the {} direction; it has {}.",
+            primer.name,
+            FeatureDirection::from(direction).to_string().to_lowercase(),
+            sites.len()
+        ));
+    }
+
+    let (range, tail_len) = sites[0];
+
+    let mut tail = primer.mods.tail_5p.clone();
+    tail.extend(&primer.sequence[..tail_len]);
+
+    Ok((range, tail))
+}
+
+/// Determine if a primer pair forms a valid PCR product on this template, and if so, where.
+pub fn find_amplicon(
+    data: &GenericData,
+    fwd_i: usize,
+    rev_i: usize,
+    anneal_matches: &[AnnealMatch],
+) -> Result<AmpliconSites, String> {
+    if fwd_i >= data.primers.len() || rev_i >= data.primers.len() {
+        return Err("Invalid primer selected".to_owned());
+    }
+
+    let (range_fwd, tail_fwd) = primer_site(
+        fwd_i,
+        &data.primers[fwd_i],
+        PrimerDirection::Forward,
+        anneal_matches,
+    )?;
+    let (range_rev, tail_rev) = primer_site(
+        rev_i,
+        &data.primers[rev_i],
+        PrimerDirection::Reverse,
+        anneal_matches,
+    )?;
+
+    let range = RangeIncl::new(range_fwd.start, range_rev.end);
+
+    // On a linear template, the reverse primer must be downstream of the forward one. On a circular one,
+    // the product may pass through the origin.
+    if range.start > range.end {
+        if let SeqTopology::Linear = data.topology {
+            return Err(
+                "The reverse primer binds upstream of the forward primer, on a linear template"
+                    .to_owned(),
+            );
+        }
+    }
+
+    // The primers must not extend past each other.
+    if range_len(range, data.seq.len()) < range_len(range_fwd, data.seq.len())
+        || range_len(range, data.seq.len()) < range_len(range_rev, data.seq.len())
+    {
+        return Err("The primers overlap past each other".to_owned());
+    }
+
+    Ok(AmpliconSites {
+        range,
+        tail_fwd,
+        tail_rev,
+    })
+}
+
+/// Create the product sequence of an amplicon: The template region, with primer tails at the ends.
+pub fn amplicon_seq(seq: &[Nucleotide], sites: &AmpliconSites) -> Seq {
+    let mut result = sites.tail_fwd.clone();
+
+    // `find_amplicon` only produces ranges that wrap the origin on circular templates.
+    if let Some(v) = sites.range.index_seq_topology(seq, SeqTopology::Circular) {
+        result.extend(v);
+    }
+
+    result.extend(seq_complement(&sites.tail_rev));
+    result
+}
+
+/// Create a new tab containing of the PCR amplicon, including primer tails. Features entirely
+/// within the amplified region are carried over.
 pub fn make_amplicon_tab(
     state: &mut State,
-    product_seq: Seq,
-    range: RangeIncl,
+    sites: &AmpliconSites,
     fwd_primer: Primer,
     rev_primer: Primer,
 ) {
+    let seq_len = state.get_seq().len();
+    let product_seq = amplicon_seq(state.get_seq(), sites);
+
     let mut product_features = Vec::new();
     for feature in &state.generic[state.active].features {
-        // todo: Handle circle wraps etc.
-        if range.start < feature.range.start && range.end > feature.range.end {
-            let mut product_feature = feature.clone();
-            // Find the indexes in the new product sequence.
-            product_feature.range.start -= range.start - 1;
-            product_feature.range.end -= range.start - 1;
-
-            product_features.push(product_feature);
+        if !range_covers(sites.range, feature.range, seq_len) {
+            continue;
         }
+
+        // Find the indexes in the new product sequence. This handles ranges that wrap the origin.
+        let offset = if feature.range.start >= sites.range.start {
+            feature.range.start - sites.range.start
+        } else {
+            feature.range.start + seq_len - sites.range.start
+        };
+
+        let mut product_feature = feature.clone();
+        product_feature.range.start = offset + sites.tail_fwd.len() + 1;
+        product_feature.range.end = product_feature.range.start + feature.len(seq_len) - 1;
+
+        product_features.push(product_feature);
     }
 
     state.add_tab();
     state.tabs_open.push(Default::default());
 
-    // if let Some(seq) = range_combined.index_seq(&state.generic.seq) {
     state.generic[state.active].seq = product_seq;
 
     // Include the primers used for PCR, and features that are included in the new segment.
-    // note that the feature indexes will need to change.
-
     let product_primers = vec![fwd_primer, rev_primer];
 
     state.generic[state.active].features = product_features;
     state.generic[state.active].primers = product_primers;
     state.generic[state.active].metadata.plasmid_name = "PCR amplicon".to_owned();
+    state.volatile[state.active].unsaved = true; // Not yet saved to a file.
 
     state.sync_seq_related(None);
     // state.sync_primer_metrics();
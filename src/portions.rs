@@ -11,6 +11,7 @@ pub struct PortionsState {
     pub solutions: Vec<Solution>,
     pub media_input: MediaPrepInput,
     pub media_result: MediaPrep,
+    pub dilution: DilutionSeriesInput,
 }
 
 impl Default for PortionsState {
@@ -22,6 +23,7 @@ impl Default for PortionsState {
             solutions: Vec::new(),
             media_input,
             media_result,
+            dilution: Default::default(),
         };
 
         result
@@ -333,3 +335,89 @@ pub fn media_prep(input: &MediaPrepInput) -> MediaPrep {
         antibiotic: volume,
     }
 }
+
+/// Below this, pipetting is inaccurate; we warn the user.
+pub const MIN_PRACTICAL_VOLUME: f32 = 1.; // μL
+
+/// Input for a serial dilution: Each step is diluted from the previous one, starting with the stock.
+/// Concentrations may use any unit, as long as it's consistent.
+#[derive(Clone, Encode, Decode)]
+pub struct DilutionSeriesInput {
+    pub stock_conc: f32,
+    /// In order.
+    pub target_concs: Vec<f32>,
+    /// The volume remaining in each tube, after transferring to the next step. μL.
+    pub final_volume: f32,
+}
+
+impl Default for DilutionSeriesInput {
+    fn default() -> Self {
+        Self {
+            stock_conc: 100.,
+            target_concs: vec![10., 1., 0.1],
+            final_volume: 100.,
+        }
+    }
+}
+
+/// One step of a serial dilution. Volumes are in μL.
+pub struct DilutionStep {
+    pub conc: f32,
+    /// The concentration of the solution we transfer from; the stock, or the previous step.
+    pub source_conc: f32,
+    /// Volume to transfer from the source.
+    pub transfer_vol: f32,
+    /// Volume of diluent (eg water or buffer) to add.
+    pub diluent_vol: f32,
+    /// Volume mixed in this tube, including what's later transferred to the next step.
+    pub total_vol: f32,
+}
+
+/// Calculate transfer and diluent volumes for a serial dilution, using C1V1 = C2V2 at each step.
+/// We work backwards, since each tube must contain its final volume, plus the volume transferred
+/// to the next step.
+pub fn dilution_series(input: &DilutionSeriesInput) -> Result<Vec<DilutionStep>, String> {
+    if input.stock_conc <= 0. {
+        return Err("Stock concentration must be positive.".to_owned());
+    }
+
+    let mut source_conc = input.stock_conc;
+    for (i, conc) in input.target_concs.iter().enumerate() {
+        if *conc <= 0. || *conc > source_conc {
+            return Err(format!(
+                "Step {}: The target concentration must be positive, and no higher than the previous step.",
+                i + 1
+            ));
+        }
+        source_conc = *conc;
+    }
+
+    let mut result = Vec::new();
+    // Volume the step after this one takes from it.
+    let mut transfer_next = 0.;
+
+    for (i, conc) in input.target_concs.iter().enumerate().rev() {
+        let source_conc = if i == 0 {
+            input.stock_conc
+        } else {
+            input.target_concs[i - 1]
+        };
+
+        let total_vol = input.final_volume + transfer_next;
+        // V1 = C2V2 / C1
+        let transfer_vol = conc * total_vol / source_conc;
+
+        result.push(DilutionStep {
+            conc: *conc,
+            source_conc,
+            transfer_vol,
+            diluent_vol: total_vol - transfer_vol,
+            total_vol,
+        });
+
+        transfer_next = transfer_vol;
+    }
+
+    result.reverse();
+    Ok(result)
+}
@@ -1,34 +1,126 @@
 //! This module contains code related to primer (oglionucleotide) design and QC.
 
+use std::{
+    fmt,
+    fmt::Formatter,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
 use bincode::{Decode, Encode};
 use eframe::egui::Color32;
-use na_seq::{seq_complement, seq_from_str, seq_to_str_lower, seq_weight, Nucleotide, Seq};
+use na_seq::{
+    restriction_enzyme::{find_re_matches, RestrictionEnzyme},
+    seq_complement, seq_from_str, seq_to_str_lower, seq_weight, Nucleotide, Seq,
+};
 
 use crate::{
-    gui::{primer_table::DEFAULT_TRIM_AMT, PRIMER_FWD_COLOR, PRIMER_REV_COLOR},
-    primer_metrics::PrimerMetrics,
+    gui::{primer_table::DEFAULT_TRIM_AMT, theme::palette},
+    primer_metrics::{PrimerMetrics, ScoringWeights},
     state::State,
-    util::{match_subseq, RangeIncl},
+    util::{disp_i, match_subseq, RangeIncl},
 };
 
-// If a primer length is below this, many calculations will be disabled for it.
+// If a primer length is below this, many calculations will be disabled for it. The default; the user
+// may configure it.
 pub const MIN_PRIMER_LEN: usize = 10;
-pub const TM_TARGET: f32 = 59.; // Also used as a default for PCR GUI.
+pub const TM_TARGET: f32 = 59.; // The default; the user may configure it. Also used for the PCR GUI.
+/// The default maximum TM difference between paired primers, in °C; the user may configure it.
+pub const TM_PAIR_MAX_DIFF: f32 = 4.;
+
+/// The TM target in use, as f32 bits; 0 means unset. We store this globally, since primer metrics are
+/// calculated from many places that don't have access to state. Set from preferences.
+static TM_TARGET_ACTIVE: AtomicU32 = AtomicU32::new(0);
+/// The minimum primer length in use. Stored globally for the same reason as the TM target.
+static MIN_PRIMER_LEN_ACTIVE: AtomicUsize = AtomicUsize::new(MIN_PRIMER_LEN);
+
+/// When a primer doesn't match the template in full (eg due to a 5' tail), require at least this
+/// many nucleotides at its 3' end to match, to consider it annealing.
+pub const MIN_ANNEAL_LEN: usize = 15;
 
 // These lenghts should be long enough for reasonablely high-length primers, should that be
-// required for optimal characteristics.
+// required for optimal characteristics. These are the defaults; the user may configure them.
 const UNTRIMMED_LEN_INSERT: usize = 30;
 const UNTRIMMED_LEN_VECTOR: usize = 32;
+const UNTRIMMED_LEN_AMPLIFICATION: usize = 32;
+
+/// Primer design lengths the user may configure. Saved in preferences.
+#[derive(Clone, PartialEq, Encode, Decode)]
+pub struct PrimerLenSettings {
+    pub min_len: usize,
+    /// Lengths of designed primers, prior to tuning.
+    pub untrimmed_insert: usize,
+    pub untrimmed_vector: usize,
+    pub untrimmed_amplification: usize,
+}
+
+impl Default for PrimerLenSettings {
+    fn default() -> Self {
+        Self {
+            min_len: MIN_PRIMER_LEN,
+            untrimmed_insert: UNTRIMMED_LEN_INSERT,
+            untrimmed_vector: UNTRIMMED_LEN_VECTOR,
+            untrimmed_amplification: UNTRIMMED_LEN_AMPLIFICATION,
+        }
+    }
+}
+
+impl PrimerLenSettings {
+    /// Tuning requires room to trim, so the minimum length must be below each untrimmed length.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_len == 0 {
+            return Err("Min primer length must be above 0".to_owned());
+        }
+
+        let untrimmed = [
+            self.untrimmed_insert,
+            self.untrimmed_vector,
+            self.untrimmed_amplification,
+        ];
+        if untrimmed.iter().any(|len| *len <= self.min_len) {
+            return Err(format!(
+                "Untrimmed lengths must be above the min primer length ({})",
+                self.min_len
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// These settings if they're valid; the defaults otherwise.
+    pub fn in_use(&self) -> Self {
+        match self.validate() {
+            Ok(()) => self.clone(),
+            Err(_) => Default::default(),
+        }
+    }
+
+    /// Set the minimum length used by primer calculations.
+    pub fn apply(&self) {
+        MIN_PRIMER_LEN_ACTIVE.store(self.in_use().min_len, Ordering::Relaxed);
+    }
+}
 
 // todo: Sort out your types.
 
 #[derive(Clone, Debug, Encode, Decode)]
 pub struct PrimerMatch {
     pub direction: PrimerDirection,
-    /// This range is in the forward direction for both primers.
+    /// This range is in the forward direction for both primers; ie `start` < `end`, on the top strand.
+    /// For reverse matches, the primer's 5' end is at `end`.
     pub range: RangeIncl,
 }
 
+/// A match of only the 3' portion of a primer to the template. The remaining 5' portion doesn't
+/// anneal; eg it's a cloning overhang, or contains a mutation.
+#[derive(Clone, Debug)]
+pub struct AnnealMatch {
+    pub primer_i: usize,
+    /// The range of the annealing portion only.
+    pub match_: PrimerMatch,
+    /// The number of nucleotides at the primer's 5' end that don't anneal.
+    pub tail_len: usize,
+}
+
 /// These are also relevant for FastCloning.
 pub struct SlicPrimers {
     pub vector_fwd: Primer,
@@ -50,24 +142,107 @@ pub enum PrimerDirection {
 }
 
 impl PrimerDirection {
+    /// An abbreviation, for use in location descriptions.
+    pub fn to_str_short(self) -> &'static str {
+        match self {
+            Self::Forward => "fwd",
+            Self::Reverse => "rev",
+        }
+    }
+
     pub fn color(&self) -> Color32 {
         match self {
-            Self::Forward => PRIMER_FWD_COLOR,
-            Self::Reverse => PRIMER_REV_COLOR,
+            Self::Forward => palette().primer_fwd,
+            Self::Reverse => palette().primer_rev,
+        }
+    }
+}
+
+/// Chemical modifications to a primer's 5' end, applied during synthesis.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum Mod5p {
+    #[default]
+    None,
+    /// Required for ligating PCR products, eg for blunt-end or KLD cloning.
+    Phosphate,
+    Biotin,
+    Fam,
+}
+
+impl fmt::Display for Mod5p {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::None => "None",
+            Self::Phosphate => "Phosphate",
+            Self::Biotin => "Biotin",
+            Self::Fam => "FAM",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+impl Mod5p {
+    /// The modification code used by IDT and similar when ordering.
+    pub fn order_code(self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Phosphate => "/5Phos/",
+            Self::Biotin => "/5Biosg/",
+            Self::Fam => "/56-FAM/",
         }
     }
 }
 
+/// Modifications, and sequences appended to a primer that aren't part of the template-matching
+/// region. These are part of the synthesized primer, but not used for matching.
+#[derive(Default, Clone, Encode, Decode)]
+pub struct PrimerMods {
+    pub mod_5p: Mod5p,
+    /// Added to the 5' end; eg an overhang, RE site, or tag.
+    pub tail_5p: Seq,
+    pub tail_3p: Seq,
+}
+
+impl PrimerMods {
+    pub fn is_empty(&self) -> bool {
+        self.mod_5p == Mod5p::None && self.tail_5p.is_empty() && self.tail_3p.is_empty()
+    }
+}
+
 #[derive(Default, Clone, Encode, Decode)]
 pub struct Primer {
+    /// The template-matching portion of the primer.
     pub sequence: Seq,
     pub name: String,
     pub description: Option<String>, // todo: Display this.
+    pub mods: PrimerMods,
     /// Data that is dynamically regenerated, and generally not important for saving and loading to files.
     pub volatile: PrimerData,
 }
 
 impl Primer {
+    /// The full sequence to synthesize, including 5' and 3' tails. Does not include
+    /// chemical modifications.
+    pub fn synthesis_seq(&self) -> Seq {
+        let mut result = self.mods.tail_5p.clone();
+        result.extend(&self.sequence);
+        result.extend(&self.mods.tail_3p);
+        result
+    }
+
+    /// A text representation of the primer for ordering, including modification codes, eg
+    /// `/5Phos/gatcATGCAT...`. Tails are in upper case, to distinguish them.
+    pub fn order_text(&self) -> String {
+        format!(
+            "{}{}{}{}",
+            self.mods.mod_5p.order_code(),
+            seq_to_str_lower(&self.mods.tail_5p).to_uppercase(),
+            seq_to_str_lower(&self.sequence),
+            seq_to_str_lower(&self.mods.tail_3p).to_uppercase(),
+        )
+    }
+
     /// Match this primer to a sequence. Check both directions.
     /// Returns direction, and start and end indexes of the sequence. If direction is reversed,
     /// the indexs matches to the reversed index.
@@ -75,7 +250,7 @@ impl Primer {
         let mut result = Vec::new();
 
         // This check prevents spurious small-sequence matches, which may be numerous otherwise.
-        if self.sequence.len() < MIN_PRIMER_LEN {
+        if self.sequence.len() < min_primer_len() {
             return result;
         }
 
@@ -97,38 +272,137 @@ impl Primer {
         result
     }
 
-    /// Formats the indexes, and size of this feature.
+    /// Find the longest 3'-anchored portions of this primer that match the sequence, in both
+    /// directions. This is for primers that don't match in full, eg due to a 5' tail. Returns the
+    /// annealing match, and the number of non-annealing nucleotides at the 5' end.
+    pub fn match_3p_anchored(&self, seq: &[Nucleotide]) -> Vec<(PrimerMatch, usize)> {
+        let mut result = Vec::new();
+
+        let len = self.sequence.len();
+        if len <= MIN_ANNEAL_LEN || len > seq.len() {
+            return result;
+        }
+
+        let seq_len = seq.len();
+        let anchor = &self.sequence[len - MIN_ANNEAL_LEN..];
+        let (matches_fwd, matches_rev) = match_subseq(anchor, seq);
+
+        // Extend the anchor towards the primer's 5' end, while it continues to match. Returns
+        // the (1-based) start, and end of the annealing range, in the coordinates of `template`.
+        let extend = |template: &[Nucleotide], anchor_start: usize| {
+            let mut ext = 0;
+            while ext < len - MIN_ANNEAL_LEN {
+                let i_template = (anchor_start + seq_len - 1 - (ext + 1)) % seq_len;
+                if template[i_template] != self.sequence[len - MIN_ANNEAL_LEN - ext - 1] {
+                    break;
+                }
+                ext += 1;
+            }
+
+            let start = (anchor_start + seq_len - 1 - ext) % seq_len + 1;
+            let end = (anchor_start + MIN_ANNEAL_LEN - 2) % seq_len + 1;
+            (start, end, len - MIN_ANNEAL_LEN - ext)
+        };
+
+        for range in matches_fwd {
+            let (start, end, tail_len) = extend(seq, range.start);
+            result.push((
+                PrimerMatch {
+                    direction: PrimerDirection::Forward,
+                    range: RangeIncl::new(start, end),
+                },
+                tail_len,
+            ));
+        }
+
+        // For reverse matches, extend along the complement, then convert back to forward indices.
+        let compl = seq_complement(seq);
+        for range in matches_rev {
+            let (start, end, tail_len) = extend(&compl, seq_len - range.end + 1);
+            result.push((
+                PrimerMatch {
+                    direction: PrimerDirection::Reverse,
+                    range: RangeIncl::new(seq_len - end + 1, seq_len - start + 1),
+                },
+                tail_len,
+            ));
+        }
+
+        result
+    }
+
+    /// Formats the indexes and strand of each match, e.g. "120..140 (fwd); 300..280 (rev)". Indexes are
+    /// on the top strand, listed 5' to 3' along the primer; reverse matches therefore read high to low.
     pub fn location_descrip(&self) -> String {
         self.volatile
             .matches
             .iter()
-            .map(|match_| format!("{}..{}", match_.range.start, match_.range.end))
+            .map(|match_| {
+                let (a, b) = match match_.direction {
+                    PrimerDirection::Forward => (match_.range.start, match_.range.end),
+                    PrimerDirection::Reverse => (match_.range.end, match_.range.start),
+                };
+                format!(
+                    "{}..{} ({})",
+                    disp_i(a),
+                    disp_i(b),
+                    match_.direction.to_str_short()
+                )
+            })
             .collect::<Vec<String>>()
             .join("; ")
     }
 
-    /// Automatically select primer length based on quality score.
-    pub fn tune(&mut self, ion: &IonConcentrations) {
-        match self.volatile.tune_setting {
-            TuneSetting::Both(_) => self.tune_both_ends(ion),
-            TuneSetting::Disabled => (),
-            _ => self.tune_single_end(ion),
+    /// Automatically select primer length based on quality score. Lengths whose sequence contains a
+    /// recognition site of any enzyme in `avoid_res` are excluded; if no length in the tunable range avoids
+    /// them, we fall back to the best-scoring length, and return an error.
+    pub fn tune(
+        &mut self,
+        ion: &IonConcentrations,
+        weights: &ScoringWeights,
+        avoid_res: &[RestrictionEnzyme],
+    ) -> Result<(), String> {
+        let satisfied = match self.volatile.tune_setting {
+            TuneSetting::Both(_) => self.tune_both_ends(ion, weights, avoid_res),
+            TuneSetting::Disabled => true,
+            _ => self.tune_single_end(ion, weights, avoid_res),
+        };
+
+        if satisfied {
+            Ok(())
+        } else {
+            let names: Vec<_> = avoid_res.iter().map(|re| re.name.as_str()).collect();
+            Err(format!(
+                "{}: No length in the tunable range avoids {}",
+                self.name,
+                names.join(", ")
+            ))
         }
     }
 
     /// Note: In its current form, this assumes only one end is tunable, prior to calling this function.
-    fn tune_single_end(&mut self, ion: &IonConcentrations) {
+    /// Returns false if no length avoids the specified RE sites.
+    fn tune_single_end(
+        &mut self,
+        ion: &IonConcentrations,
+        weights: &ScoringWeights,
+        avoid_res: &[RestrictionEnzyme],
+    ) -> bool {
         // todo: Using the seq_input as the only way we store total len feels janky.
         let len_untrimmed = self.volatile.sequence_input.len();
+        let min_len = min_primer_len();
 
-        if len_untrimmed <= MIN_PRIMER_LEN {
-            return;
+        if len_untrimmed <= min_len {
+            return true;
         }
 
         let mut best_val = 0;
         let mut best_score = 0.;
+        // The best value among those that don't contain an RE site to avoid.
+        let mut best_val_allowed = None;
+        let mut best_score_allowed = 0.;
 
-        let num_vals = len_untrimmed - MIN_PRIMER_LEN;
+        let num_vals = len_untrimmed - min_len;
 
         // When this function is called, exactly one of these ends must be enabled.
         // let (i) = match &mut self.volatile.tune_setting {
@@ -142,60 +416,74 @@ impl Primer {
             let i = match &mut self.volatile.tune_setting {
                 TuneSetting::Only5(v) => v,
                 TuneSetting::Only3(v) => v,
-                _ => return,
+                _ => return true,
             };
 
             *i = val;
-            self.run_calcs(ion);
+            self.run_calcs(ion, weights);
 
             if let Some(metrics) = &self.volatile.metrics {
                 if metrics.quality_score > best_score {
                     best_val = val;
                     best_score = metrics.quality_score;
                 }
+                if metrics.quality_score > best_score_allowed
+                    && !contains_re_site(&self.sequence, avoid_res)
+                {
+                    best_val_allowed = Some(val);
+                    best_score_allowed = metrics.quality_score;
+                }
             }
         }
 
         let i = match &mut self.volatile.tune_setting {
             TuneSetting::Only5(v) => v,
             TuneSetting::Only3(v) => v,
-            _ => return,
+            _ => return true,
         };
-        *i = best_val;
-        self.run_calcs(ion);
+        *i = best_val_allowed.unwrap_or(best_val);
+        self.run_calcs(ion, weights);
+
+        best_val_allowed.is_some()
     }
 
-    fn tune_both_ends(&mut self, ion: &IonConcentrations) {
+    /// Returns false if no combination of lengths avoids the specified RE sites.
+    fn tune_both_ends(
+        &mut self,
+        ion: &IonConcentrations,
+        weights: &ScoringWeights,
+        avoid_res: &[RestrictionEnzyme],
+    ) -> bool {
         // todo: Using the seq_input as the only way we store total len feels janky.
         let len_untrimmed = self.volatile.sequence_input.len();
+        let min_len = min_primer_len();
 
         // We need the min primer length on both sides of the anchor.
-        if len_untrimmed <= MIN_PRIMER_LEN * 2 {
-            return;
+        if len_untrimmed <= min_len * 2 {
+            return true;
         }
 
         let mut best_val = (0, 0);
         let mut best_score = 0.;
+        // The best values among those that don't contain an RE site to avoid.
+        let mut best_val_allowed = None;
+        let mut best_score_allowed = 0.;
 
         // As for single-ended, we assume this function only runs when both ends are marked tunable.
         let (anchor, _, _) = match self.volatile.tune_setting {
             TuneSetting::Both(v) => v,
-            _ => return,
+            _ => return true,
         };
 
         // We ensure we have the min primer len on either side of the anchor.
-        let num_vals_5p = if anchor < MIN_PRIMER_LEN {
-            0
-        } else {
-            anchor - MIN_PRIMER_LEN
-        };
-        let num_vals_3p = if anchor < MIN_PRIMER_LEN {
+        let num_vals_5p = anchor.saturating_sub(min_len);
+        let num_vals_3p = if anchor < min_len {
             0
         } else {
             if len_untrimmed > anchor {
                 let lhs = len_untrimmed - anchor;
-                if lhs > MIN_PRIMER_LEN {
-                    (len_untrimmed - anchor) - MIN_PRIMER_LEN
+                if lhs > min_len {
+                    (len_untrimmed - anchor) - min_len
                 } else {
                     0
                 }
@@ -210,37 +498,46 @@ impl Primer {
                 // As for single-ended, we assume this function only runs when both ends are marked tunable.
                 let (_, i_5p, i_3p) = match &mut self.volatile.tune_setting {
                     TuneSetting::Both(v) => v,
-                    _ => return,
+                    _ => return true,
                 };
 
                 *i_5p = val5;
                 *i_3p = val3;
-                self.run_calcs(ion);
+                self.run_calcs(ion, weights);
 
                 if let Some(metrics) = &self.volatile.metrics {
                     if metrics.quality_score > best_score {
                         best_val = (val5, val3);
                         best_score = metrics.quality_score;
                     }
+                    if metrics.quality_score > best_score_allowed
+                        && !contains_re_site(&self.sequence, avoid_res)
+                    {
+                        best_val_allowed = Some((val5, val3));
+                        best_score_allowed = metrics.quality_score;
+                    }
                 }
             }
         }
 
         let (_, i_5p, i_3p) = match &mut self.volatile.tune_setting {
             TuneSetting::Both(v) => v,
-            _ => return,
+            _ => return true,
         };
-        *i_5p = best_val.0;
-        *i_3p = best_val.1;
+        let val = best_val_allowed.unwrap_or(best_val);
+        *i_5p = val.0;
+        *i_3p = val.1;
 
-        self.run_calcs(ion);
+        self.run_calcs(ion, weights);
+
+        best_val_allowed.is_some()
     }
 
     /// Perform calculations on primer quality and related data. Run this when the sequence changes,
     /// the tuning values change etc.
     ///
     /// This also syncs the active sequence based on the tune settings, and calculates primer weight.
-    pub fn run_calcs(&mut self, ion_concentrations: &IonConcentrations) {
+    pub fn run_calcs(&mut self, ion_concentrations: &IonConcentrations, weights: &ScoringWeights) {
         self.volatile.weight = seq_weight(&self.sequence);
 
         let full_len = self.volatile.sequence_input.len();
@@ -268,48 +565,101 @@ impl Primer {
         }
 
         self.sequence = seq_from_str(&self.volatile.sequence_input[start..end]);
-        self.volatile.metrics = self.calc_metrics(ion_concentrations);
+        self.volatile.metrics = self.calc_metrics(ion_concentrations, weights);
 
         self.volatile.sequence_input[..start].clone_into(&mut self.volatile.seq_removed_5p);
         self.volatile.sequence_input[end..].clone_into(&mut self.volatile.seq_removed_3p);
     }
 }
 
+/// If true, a sequence contains the recognition site of at least one of these enzymes.
+fn contains_re_site(seq: &Seq, res: &[RestrictionEnzyme]) -> bool {
+    if res.is_empty() {
+        return false;
+    }
+    !find_re_matches(seq, res).is_empty()
+}
+
+/// Tune a set of primers, avoiding recognition sites of the enzymes specified. Returns a description of
+/// primers for which this constraint can't be satisfied, if any.
+pub fn tune_primers<'a>(
+    primers: impl IntoIterator<Item = &'a mut Primer>,
+    ion: &IonConcentrations,
+    weights: &ScoringWeights,
+    avoid_res: &[RestrictionEnzyme],
+) -> Option<String> {
+    let mut errors = Vec::new();
+    for primer in primers {
+        if let Err(e) = primer.tune(ion, weights, avoid_res) {
+            errors.push(e);
+        }
+    }
+
+    if errors.is_empty() {
+        None
+    } else {
+        Some(errors.join("\n"))
+    }
+}
+
+/// UI state for excluding restriction sites from primers during tuning.
+#[derive(Default)]
+pub struct PrimerReAvoidUi {
+    /// Enzymes whose recognition sites tuned primers must not contain.
+    pub res: Vec<RestrictionEnzyme>,
+    /// Set when tuning couldn't satisfy this for one or more primers.
+    pub status: Option<String>,
+}
+
+/// Take `len` nucleotides starting at a 0-based index, wrapping around the origin. `len` is limited
+/// to the sequence length.
+fn slice_circular(seq: &[Nucleotide], start: usize, len: usize) -> Seq {
+    if seq.is_empty() {
+        return Vec::new();
+    }
+    (0..len.min(seq.len()))
+        .map(|i| seq[(start + i) % seq.len()])
+        .collect()
+}
+
+/// `insert_loc` is 1-based: The insert goes immediately before this nucleotide. Values past the end
+/// wrap around the origin (circular plasmids).
 pub fn design_slic_fc_primers(
     seq_vector: &Seq,
     seq_insert: &Seq,
     mut insert_loc: usize,
+    lens: &PrimerLenSettings,
 ) -> Option<SlicPrimers> {
     if insert_loc == 0 {
         eprintln!("Error when making SLIC primers: Insert loc is 0");
         return None;
     }
+    if seq_vector.is_empty() {
+        eprintln!("Error when making SLIC primers: The vector is empty");
+        return None;
+    }
     let seq_len_vector = seq_vector.len();
     let seq_len_insert = seq_insert.len();
 
-    // todo: This likely doesn't handle the off-by-one dynamic.
-    insert_loc = insert_loc % seq_len_vector; // Wrap around the origin (circular plasmids)
+    insert_loc = (insert_loc - 1) % seq_len_vector + 1;
 
     let vector_reversed = seq_complement(seq_vector);
     let insert_reversed = seq_complement(seq_insert);
 
-    let insert_loc_reversed = seq_len_vector - insert_loc + 1;
+    // 0-based index on the reverse complement of the nucleotide immediately upstream of the insert.
+    // At the first nucleotide, this wraps to the end of the vector.
+    let insert_loc_reversed = (seq_len_vector - insert_loc + 1) % seq_len_vector;
 
-    let (seq_vector_fwd, seq_vector_rev) = {
-        let end = (insert_loc + UNTRIMMED_LEN_VECTOR) % seq_len_vector;
-        let end_reversed = (insert_loc_reversed + UNTRIMMED_LEN_VECTOR) % seq_len_vector;
-
-        (
-            seq_vector[insert_loc - 1..end].to_owned(),
-            vector_reversed[insert_loc_reversed..end_reversed].to_owned(),
-        )
-    };
+    let (seq_vector_fwd, seq_vector_rev) = (
+        slice_circular(seq_vector, insert_loc - 1, lens.untrimmed_vector),
+        slice_circular(&vector_reversed, insert_loc_reversed, lens.untrimmed_vector),
+    );
 
     let (seq_insert_fwd, seq_insert_rev) = {
-        let mut insert_end = UNTRIMMED_LEN_INSERT;
+        let mut insert_end = lens.untrimmed_insert;
         insert_end = insert_end.clamp(0, seq_len_insert);
 
-        let mut insert_end_reversed = UNTRIMMED_LEN_INSERT;
+        let mut insert_end_reversed = lens.untrimmed_insert;
         insert_end_reversed = insert_end_reversed.clamp(0, seq_len_insert);
 
         // We will combine these with vector seqs for the final insert primers.
@@ -333,6 +683,7 @@ pub fn design_slic_fc_primers(
             description: Some(
                 "SLIC cloning primer, Vector forward. Amplifies the entire vector.".to_owned(),
             ),
+            mods: Default::default(),
             volatile: Default::default(),
         },
         vector_rev: Primer {
@@ -341,6 +692,7 @@ pub fn design_slic_fc_primers(
             description: Some(
                 "SLIC cloning primer, Vector reverse. Amplifies the entire vector.".to_owned(),
             ),
+            mods: Default::default(),
             volatile: Default::default(),
         },
         insert_fwd: Primer {
@@ -349,6 +701,7 @@ pub fn design_slic_fc_primers(
             description: Some(
                 "SLIC cloning primer, Insert forward. Overlaps with the vector.".to_owned(),
             ),
+            mods: Default::default(),
             volatile: Default::default(),
         },
         insert_rev: Primer {
@@ -357,25 +710,25 @@ pub fn design_slic_fc_primers(
             description: Some(
                 "SLIC cloning primer, Insert forward. Overlaps with the vector.".to_owned(),
             ),
+            mods: Default::default(),
             volatile: Default::default(),
         },
     })
 }
 
 // todo: Use this A/R, called from the UI page.
-pub fn design_amplification_primers(seq: &[Nucleotide]) -> Option<AmplificationPrimers> {
-    // These lenghts should be long enough for reasonablely high-length primers, should that be
-    // required for optimal characteristics.
-    const UNTRIMMED_LEN: usize = 32;
-
+pub fn design_amplification_primers(
+    seq: &[Nucleotide],
+    untrimmed_len: usize,
+) -> Option<AmplificationPrimers> {
     let seq_len = seq.len();
     let reversed = seq_complement(seq);
 
     let (seq_fwd, seq_rev) = {
-        let mut end = UNTRIMMED_LEN;
+        let mut end = untrimmed_len;
         end = end.clamp(0, seq_len);
 
-        let mut end_reversed = UNTRIMMED_LEN;
+        let mut end_reversed = untrimmed_len;
         end_reversed = end_reversed.clamp(0, seq_len);
 
         (seq[..end].to_owned(), reversed[..end_reversed].to_owned())
@@ -387,12 +740,14 @@ pub fn design_amplification_primers(seq: &[Nucleotide]) -> Option<AmplificationP
             sequence: seq_fwd,
             name: "Amplification fwd".to_owned(),
             description: Some("Amplification primer, forward.".to_owned()),
+            mods: Default::default(),
             volatile: Default::default(),
         },
         rev: Primer {
             sequence: seq_rev,
             name: "Amplification rev".to_owned(),
             description: Some("Amplification primer, reverse.".to_owned()),
+            mods: Default::default(),
             volatile: Default::default(),
         },
     })
@@ -514,21 +869,20 @@ impl TuneSetting {
 }
 
 /// We run this to generate cloning primers when clicking the button
-/// Make sure to do this before inserting the insert into the sequence.
-pub fn make_cloning_primers(state: &mut State) {
+/// Make sure to do this before inserting the insert into the sequence. Returns the primers added.
+pub fn make_cloning_primers(state: &mut State, insert_loc: usize) -> Vec<Primer> {
     let seq_vector = &state.generic[state.active].seq;
     let seq_insert = &state.ui.cloning_insert.seq_insert;
+    let lens = state.ui.primer_lens.in_use();
 
-    if let Some(mut primers) =
-        design_slic_fc_primers(seq_vector, seq_insert, state.cloning.insert_loc)
-    {
+    if let Some(mut primers) = design_slic_fc_primers(seq_vector, seq_insert, insert_loc, &lens) {
         let sequence_input = seq_to_str_lower(&primers.insert_fwd.sequence);
 
         let insert_fwd_data = PrimerData {
             sequence_input,
             // Both ends are  tunable, since this glues the insert to the vector
             tune_setting: TuneSetting::Both((
-                UNTRIMMED_LEN_INSERT,
+                lens.untrimmed_insert,
                 DEFAULT_TRIM_AMT,
                 DEFAULT_TRIM_AMT,
             )), // todo: TIe the anchor to the const
@@ -540,7 +894,7 @@ pub fn make_cloning_primers(state: &mut State) {
             sequence_input,
             // Both ends are tunable, since this glues the insert to the vector
             tune_setting: TuneSetting::Both((
-                UNTRIMMED_LEN_VECTOR,
+                lens.untrimmed_vector,
                 DEFAULT_TRIM_AMT,
                 DEFAULT_TRIM_AMT,
             )), // todo: QC
@@ -577,50 +931,52 @@ pub fn make_cloning_primers(state: &mut State) {
         primers
             .insert_fwd
             // .run_calcs(&state.ion_concentrations[state.active]);
-            .run_calcs(&state.ion_concentrations);
+            .run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
         primers
             .insert_rev
             // .run_calcs(&state.ion_concentrations[state.active]);
-            .run_calcs(&state.ion_concentrations);
+            .run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
         primers
             .vector_fwd
             // .run_calcs(&state.ion_concentrations[state.active]);
-            .run_calcs(&state.ion_concentrations);
+            .run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
         primers
             .vector_rev
             // .run_calcs(&state.ion_concentrations[state.active]);
-            .run_calcs(&state.ion_concentrations);
-
-        primers
-            .insert_fwd
-            // .tune(&state.ion_concentrations[state.active]);
-            .tune(&state.ion_concentrations);
-        primers
-            .insert_rev
-            // .tune(&state.ion_concentrations[state.active]);
-            .tune(&state.ion_concentrations);
-        primers
-            .vector_fwd
-            // .tune(&state.ion_concentrations[state.active]);
-            .tune(&state.ion_concentrations);
-        primers
-            .vector_rev
-            // .tune(&state.ion_concentrations[state.active]);
-            .tune(&state.ion_concentrations);
-
-        state.generic[state.active].primers.extend([
+            .run_calcs(&state.ion_concentrations, &state.ui.scoring_weights);
+
+        state.ui.primer_re_avoid.status = tune_primers(
+            [
+                &mut primers.insert_fwd,
+                &mut primers.insert_rev,
+                &mut primers.vector_fwd,
+                &mut primers.vector_rev,
+            ],
+            &state.ion_concentrations,
+            &state.ui.scoring_weights,
+            &state.ui.primer_re_avoid.res,
+        );
+
+        let result = vec![
             primers.insert_fwd,
             primers.insert_rev,
             primers.vector_fwd,
             primers.vector_rev,
-        ]);
+        ];
+        state.generic[state.active]
+            .primers
+            .extend(result.iter().cloned());
 
         state.sync_primer_matches(None);
+        return result;
     }
+
+    Vec::new()
 }
 
 pub fn make_amplification_primers(state: &mut State) {
-    if let Some(mut primers) = design_amplification_primers(state.get_seq()) {
+    let untrimmed_len = state.ui.primer_lens.in_use().untrimmed_amplification;
+    if let Some(mut primers) = design_amplification_primers(state.get_seq(), untrimmed_len) {
         let sequence_input = seq_to_str_lower(&primers.fwd.sequence);
 
         let primer_fwd_data = PrimerData {
@@ -643,10 +999,12 @@ pub fn make_amplification_primers(state: &mut State) {
         primers.fwd.volatile = primer_fwd_data;
         primers.rev.volatile = primer_rev_data;
 
-        // primers.fwd.tune(&state.ion_concentrations[state.active]);
-        primers.fwd.tune(&state.ion_concentrations);
-        // primers.rev.tune(&state.ion_concentrations[state.active]);
-        primers.rev.tune(&state.ion_concentrations);
+        state.ui.primer_re_avoid.status = tune_primers(
+            [&mut primers.fwd, &mut primers.rev],
+            &state.ion_concentrations,
+            &state.ui.scoring_weights,
+            &state.ui.primer_re_avoid.res,
+        );
 
         state.generic[state.active]
             .primers
@@ -656,6 +1014,32 @@ pub fn make_amplification_primers(state: &mut State) {
     }
 }
 
+pub fn set_tm_target(val: f32) {
+    TM_TARGET_ACTIVE.store(val.to_bits(), Ordering::Relaxed);
+}
+
+/// The difference in melting temperature between two primers, in °C. Paired primers should be close, so
+/// they anneal at the same temperature. `None` if either doesn't have metrics calculated.
+pub fn tm_diff(a: &Primer, b: &Primer) -> Option<f32> {
+    match (&a.volatile.metrics, &b.volatile.metrics) {
+        (Some(m_a), Some(m_b)) => Some((m_a.melting_temp - m_b.melting_temp).abs()),
+        _ => None,
+    }
+}
+
+/// Primers shorter than this aren't evaluated or tuned.
+pub fn min_primer_len() -> usize {
+    MIN_PRIMER_LEN_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// The melting temperature primer quality scores are based on, in °C.
+pub fn tm_target() -> f32 {
+    match TM_TARGET_ACTIVE.load(Ordering::Relaxed) {
+        0 => TM_TARGET,
+        bits => f32::from_bits(bits),
+    }
+}
+
 #[derive(Clone, Encode, Decode)]
 /// Concentrations of common ions in the oglio solution. Affects melting temperature (TM).
 /// All values are in milliMolar.
@@ -680,3 +1064,129 @@ impl Default for IonConcentrations {
         }
     }
 }
+
+/// How the synthesized amount of a primer is specified, eg on the tube or spec sheet.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SynthAmountUnit {
+    Nmol,
+    Od260,
+}
+
+impl fmt::Display for SynthAmountUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::Nmol => "nmol",
+            Self::Od260 => "OD260",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+/// UI state for the primer resuspension calculator.
+pub struct ResuspendUi {
+    pub amount: f32,
+    pub unit: SynthAmountUnit,
+    /// Desired stock concentration, in µM.
+    pub stock_conc: f32,
+}
+
+impl Default for ResuspendUi {
+    fn default() -> Self {
+        Self {
+            amount: 25.,
+            unit: SynthAmountUnit::Nmol,
+            stock_conc: 100.,
+        }
+    }
+}
+
+/// Approximate molar extinction coefficient at 260nm, in L/(mol·cm), from base composition. This ignores
+/// nearest-neighbor effects, and applies a flat hypochromicity correction; it's accurate to ~10-20%.
+pub fn extinction_coeff(seq: &[Nucleotide]) -> f32 {
+    const HYPOCHROMICITY: f32 = 0.9;
+
+    let sum: f32 = seq
+        .iter()
+        .map(|nt| match nt {
+            Nucleotide::A => 15_400.,
+            Nucleotide::C => 7_400.,
+            Nucleotide::G => 11_500.,
+            Nucleotide::T => 8_700.,
+        })
+        .sum();
+
+    sum * HYPOCHROMICITY
+}
+
+/// Convert a synthesized amount to nmol. An OD260 unit is the amount that gives an absorbance of 1,
+/// dissolved in 1mL, with a 1cm path length.
+pub fn synth_amount_nmol(amount: f32, unit: SynthAmountUnit, seq: &[Nucleotide]) -> f32 {
+    match unit {
+        SynthAmountUnit::Nmol => amount,
+        SynthAmountUnit::Od260 => {
+            let coeff = extinction_coeff(seq);
+            if coeff == 0. {
+                return 0.;
+            }
+            // mol/L in 1mL, converted to nmol.
+            amount / coeff * 1e6
+        }
+    }
+}
+
+/// Water to add, in µL, to resuspend `nmol` of primer to `conc`, in µM.
+pub fn resuspend_volume(nmol: f32, conc: f32) -> f32 {
+    if conc <= 0. {
+        return 0.;
+    }
+    nmol / conc * 1_000.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vector forward and reverse, and insert forward and reverse primer sequences.
+    fn slic_seqs(insert_loc: usize) -> [String; 4] {
+        let lens = PrimerLenSettings {
+            untrimmed_vector: 4,
+            untrimmed_insert: 3,
+            ..Default::default()
+        };
+
+        let primers = design_slic_fc_primers(
+            &seq_from_str("aaccggttac"),
+            &seq_from_str("gatcca"),
+            insert_loc,
+            &lens,
+        )
+        .unwrap();
+
+        [
+            &primers.vector_fwd,
+            &primers.vector_rev,
+            &primers.insert_fwd,
+            &primers.insert_rev,
+        ]
+        .map(|p| seq_to_str_lower(&p.sequence))
+    }
+
+    #[test]
+    fn slic_insert_at_start() {
+        // The vector reverse primer binds upstream of the insert, wrapping to the vector's end.
+        assert_eq!(slic_seqs(1), ["aacc", "gtaa", "ttacgat", "ggtttgg"]);
+    }
+
+    #[test]
+    fn slic_insert_at_end() {
+        assert_eq!(slic_seqs(10), ["caac", "taac", "gttagat", "gttgtgg"]);
+    }
+
+    #[test]
+    fn slic_insert_past_end() {
+        // 13 wraps to 3, on this 10 nt vector.
+        assert_eq!(slic_seqs(13), ["ccgg", "ttgt", "acaagat", "ccggtgg"]);
+        assert_eq!(slic_seqs(13), slic_seqs(3));
+    }
+}
@@ -0,0 +1,117 @@
+//! Find where a primer binds best, allowing mismatches, and which of its bases mismatch the template.
+//! This helps troubleshoot failed PCRs: mismatches near the 3' end are the most likely to prevent
+//! extension, while those near the 5' end are usually tolerated.
+
+use na_seq::{seq_complement, Nucleotide};
+
+use crate::{primer::PrimerDirection, util::RangeIncl};
+
+/// The number of bases at the 3' end we check for an exact match.
+pub const END_3P_LEN: usize = 5;
+
+/// Mismatches in the 3' end count this many times over others, when choosing the best site.
+const MISMATCH_WEIGHT_3P: usize = 3;
+
+pub struct BindingSite {
+    pub direction: PrimerDirection,
+    /// On the top strand, as with `PrimerMatch`.
+    pub range: RangeIncl,
+    /// The template, in the primer's orientation; ie the sequence a perfectly-matching primer would have.
+    pub template: Vec<Nucleotide>,
+    /// For each primer base, 5' to 3': true if it matches the template.
+    pub base_matches: Vec<bool>,
+}
+
+impl BindingSite {
+    /// 1-based positions of mismatched bases, counted from the primer's 5' end.
+    pub fn mismatches(&self) -> Vec<usize> {
+        self.base_matches
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| !**m)
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+
+    /// The number of mismatches in the last `END_3P_LEN` bases.
+    pub fn mismatches_3p(&self) -> usize {
+        let len = self.base_matches.len();
+        self.base_matches[len.saturating_sub(END_3P_LEN)..]
+            .iter()
+            .filter(|m| !**m)
+            .count()
+    }
+}
+
+/// Cached for the selected primer, as the search runs over the whole sequence.
+pub struct PrimerBinding {
+    pub primer_i: usize,
+    /// `None` if no site matches at least half the primer's bases.
+    pub site: Option<BindingSite>,
+    /// Sites where the primer's 3' end anneals; see `Primer::match_3p_anchored`.
+    pub sites_3p_anneal: usize,
+}
+
+/// A site's score; lower is better. Returns `None` if it exceeds `max`.
+fn site_score(primer: &[Nucleotide], template: &[Nucleotide], max: usize) -> Option<usize> {
+    let end_3p = primer.len().saturating_sub(END_3P_LEN);
+    let mut score = 0;
+
+    for (i, (p, t)) in primer.iter().zip(template).enumerate() {
+        if p != t {
+            score += if i >= end_3p { MISMATCH_WEIGHT_3P } else { 1 };
+            if score > max {
+                return None;
+            }
+        }
+    }
+    Some(score)
+}
+
+/// Find the primer's best binding site on either strand, aligned end to end without gaps. Sites are
+/// ranked by mismatch count, with 3' mismatches weighted. Sites wrapping the origin aren't considered.
+pub fn best_binding_site(primer: &[Nucleotide], seq: &[Nucleotide]) -> Option<BindingSite> {
+    let len = primer.len();
+    let seq_len = seq.len();
+    if len == 0 || len > seq_len {
+        return None;
+    }
+
+    // Require at least half the bases to match.
+    let mut best_score = len / 2;
+    let mut best = None;
+
+    let compl = seq_complement(seq);
+    for (template, direction) in [
+        (seq, PrimerDirection::Forward),
+        (compl.as_slice(), PrimerDirection::Reverse),
+    ] {
+        for (i, window) in template.windows(len).enumerate() {
+            if let Some(score) = site_score(primer, window, best_score) {
+                if best.is_none() || score < best_score {
+                    best_score = score;
+                    best = Some((direction, i));
+                }
+            }
+        }
+    }
+
+    let (direction, i) = best?;
+    let template = match direction {
+        PrimerDirection::Forward => &seq[i..i + len],
+        PrimerDirection::Reverse => &compl[i..i + len],
+    };
+
+    // Convert indices on the complement back to the top strand.
+    let range = match direction {
+        PrimerDirection::Forward => RangeIncl::new(i + 1, i + len),
+        PrimerDirection::Reverse => RangeIncl::new(seq_len - i - len + 1, seq_len - i),
+    };
+
+    Some(BindingSite {
+        direction,
+        range,
+        template: template.to_vec(),
+        base_matches: primer.iter().zip(template).map(|(p, t)| p == t).collect(),
+    })
+}
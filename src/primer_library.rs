@@ -0,0 +1,182 @@
+//! A lab's primer stock list, independent of any one sequence. This is saved alongside preferences,
+//! and primers from it can be added to the active tab.
+
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use bio::io::fasta;
+use na_seq::{seq_from_str, seq_to_str_lower, seq_to_u8_lower, Nucleotide};
+
+use crate::{
+    file_io::save::{load, prefs_dir, save},
+    primer::{Primer, PrimerData},
+};
+
+pub const DEFAULT_PRIMER_LIB_FILE: &str = "pcad_primers.pp";
+pub const DEFAULT_PRIMER_LIB_EXPORT_FILE: &str = "primers.fasta";
+
+/// The library's file, in the preferences directory.
+pub fn primer_lib_path() -> PathBuf {
+    prefs_dir().join(DEFAULT_PRIMER_LIB_FILE)
+}
+
+#[derive(Default)]
+pub struct PrimerLibrary {
+    pub primers: Vec<Primer>,
+    /// Primers were added or removed since the library was loaded or saved.
+    pub dirty: bool,
+    /// The library file exists, but couldn't be read. We don't save over it.
+    pub load_failed: bool,
+}
+
+impl PrimerLibrary {
+    /// Load the library. If there is no file, e.g. on the first run, the library is empty.
+    pub fn load_from_file(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+
+        Ok(Self {
+            primers: load(path)?,
+            ..Default::default()
+        })
+    }
+
+    /// Save the library, if it changed since loading, and it loaded without error.
+    pub fn save_to_file(&mut self, path: &Path) -> io::Result<()> {
+        if !self.dirty || self.load_failed {
+            return Ok(());
+        }
+
+        save(path, &self.primers)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Indices of primers whose name, description, or sequence contain the search text.
+    /// Case-insensitive. An empty search matches all.
+    pub fn search(&self, text: &str) -> Vec<usize> {
+        let text = text.trim().to_lowercase();
+
+        let mut result = Vec::new();
+        for (i, primer) in self.primers.iter().enumerate() {
+            let desc = primer.description.clone().unwrap_or_default();
+            if text.is_empty()
+                || primer.name.to_lowercase().contains(&text)
+                || desc.to_lowercase().contains(&text)
+                || seq_to_str_lower(&primer.sequence).contains(&text)
+            {
+                result.push(i);
+            }
+        }
+        result
+    }
+
+    /// Add a primer, e.g. from a tab. We don't store volatile data. Returns false, without adding
+    /// it, if a primer with the same name and sequence is already present.
+    pub fn add(&mut self, primer: &Primer) -> bool {
+        if self
+            .primers
+            .iter()
+            .any(|p| p.name == primer.name && p.sequence == primer.sequence)
+        {
+            return false;
+        }
+
+        self.primers.push(Primer {
+            volatile: PrimerData::new(&primer.sequence),
+            ..primer.clone()
+        });
+        self.dirty = true;
+        true
+    }
+
+    pub fn remove(&mut self, i: usize) {
+        if i < self.primers.len() {
+            self.primers.remove(i);
+            self.dirty = true;
+        }
+    }
+}
+
+/// Create a primer from the library, for use in a tab. Its matches and metrics must be synced
+/// against the tab's sequence.
+pub fn primer_for_tab(primer: &Primer) -> Primer {
+    Primer {
+        volatile: PrimerData::new(&primer.sequence),
+        ..primer.clone()
+    }
+}
+
+/// Export primers to a multi-record FASTA file. Tails and modifications aren't included.
+pub fn export_primer_lib(primers: &[Primer], path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = fasta::Writer::new(file);
+
+    for primer in primers {
+        // FASTA IDs can't contain whitespace.
+        let id = primer.name.replace(char::is_whitespace, "_");
+        writer.write(
+            &id,
+            primer.description.as_deref(),
+            seq_to_u8_lower(&primer.sequence).as_slice(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Import primers from a multi-record FASTA file. Returns the primers, and the names of those skipped
+/// because their sequence contains characters other than A, C, T, and G.
+pub fn import_primer_lib(path: &Path) -> io::Result<(Vec<Primer>, Vec<String>)> {
+    let file = File::open(path)?;
+
+    let mut result = Vec::new();
+    let mut skipped = Vec::new();
+    for record in fasta::Reader::new(file).records() {
+        let record = record.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let sequence: Result<Vec<_>, _> = record
+            .seq()
+            .iter()
+            .map(|nt| Nucleotide::from_u8(*nt))
+            .collect();
+        let sequence = match sequence {
+            Ok(s) => s,
+            Err(_) => {
+                skipped.push(record.id().to_owned());
+                continue;
+            }
+        };
+
+        let description = match record.desc() {
+            Some(d) if !d.is_empty() => Some(d.to_owned()),
+            _ => None,
+        };
+
+        result.push(Primer {
+            volatile: PrimerData::new(&sequence),
+            sequence,
+            name: record.id().to_owned(),
+            description,
+            mods: Default::default(),
+        });
+    }
+
+    Ok((result, skipped))
+}
+
+/// Create a primer from text the user entered.
+pub fn primer_from_input(name: &str, seq: &str) -> Primer {
+    let sequence = seq_from_str(seq);
+    Primer {
+        volatile: PrimerData::new(&sequence),
+        sequence,
+        name: name.to_owned(),
+        description: None,
+        mods: Default::default(),
+    }
+}
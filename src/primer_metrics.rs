@@ -2,17 +2,50 @@
 
 use bincode::{Decode, Encode};
 use na_seq::{
-    calc_gc, Nucleotide,
+    calc_gc, seq_complement, Nucleotide,
     Nucleotide::{C, G},
 };
 
 use crate::{
-    melting_temp_calcs,
-    primer::{IonConcentrations, Primer, TuneSetting, MIN_PRIMER_LEN},
+    melting_temp_calcs::{self, dH_dS_neighbors},
+    primer::{min_primer_len, tm_target, IonConcentrations, Primer, TuneSetting},
     util::{map_linear, remove_duplicates},
-    TM_TARGET,
 };
 
+/// How much each metric contributes to a primer's overall quality score. Only the ratios between
+/// weights matter; the score is normalized by their sum. Saved in preferences.
+#[derive(Clone, PartialEq, Encode, Decode)]
+pub struct ScoringWeights {
+    pub tm: f32,
+    pub gc: f32,
+    /// 3' end stability; GC content in the last 5 nucleotides.
+    pub stability_3p: f32,
+    pub dimer: f32,
+    pub len: f32,
+    pub repeats: f32,
+}
+
+impl ScoringWeights {
+    const DEFAULT: Self = Self {
+        tm: 1.,
+        gc: 1.,
+        stability_3p: 1.,
+        dimer: 1.,
+        len: 1.5,
+        repeats: 0.5,
+    };
+
+    fn sum(&self) -> f32 {
+        self.tm + self.gc + self.stability_3p + self.dimer + self.len + self.repeats
+    }
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Metrics related to primer quality.
 #[derive(Clone, Debug, Default, Encode, Decode)]
 pub struct PrimerMetrics {
@@ -42,21 +75,12 @@ impl PrimerMetrics {
     /// Return a quality score, on a scale from 0 to 1.
     /// `dual_end` indicates if this is a double-end-tunable primer, which generally means a cloning
     /// insert primer. This affects the len-based score.
-    pub fn update_scores(&mut self, dual_end: bool) {
+    pub fn update_scores(&mut self, dual_end: bool, weights: &ScoringWeights) {
         const GC_TARGET: f32 = 0.5;
 
-        // todo: Do these weights have to add up to 1/total?
-        const WEIGHT_TM: f32 = 1.;
-        const WEIGHT_GC: f32 = 1.;
-        const WEIGHT_STAB: f32 = 1.;
-        // const WEIGHT_COMPLEXITY: f32 = 1.;
-        const WEIGHT_DIMER: f32 = 1.;
-        const WEIGHT_LEN: f32 = 1.5;
-        const WEIGHT_REPEATS: f32 = 0.5;
-
         // todo: Instead of closeness to 59, should it be >54??
         // Also: 50-60C. And within 5C of the complement primer.
-        self.tm_score = map_linear((self.melting_temp - TM_TARGET).abs(), (0., 18.), (1., 0.));
+        self.tm_score = map_linear((self.melting_temp - tm_target()).abs(), (0., 18.), (1., 0.));
         self.tm_score = self.tm_score.clamp(0., 1.);
 
         // This is currently a linear map, between 0 and 1.
@@ -131,14 +155,20 @@ impl PrimerMetrics {
             _ => 0.,
         };
 
-        self.quality_score = (WEIGHT_TM * self.tm_score
-            + WEIGHT_GC * self.gc_score
-            + WEIGHT_STAB * self.gc_3p_score
+        let weight_sum = weights.sum();
+        if weight_sum <= 0. {
+            self.quality_score = 0.;
+            return;
+        }
+
+        self.quality_score = (weights.tm * self.tm_score
+            + weights.gc * self.gc_score
+            + weights.stability_3p * self.gc_3p_score
             // + WEIGHT_COMPLEXITY * self.complexity_score
-            + WEIGHT_DIMER * self.dimer_score
-            + WEIGHT_LEN * self.len_score
-            + WEIGHT_REPEATS * self.repeats_score)
-            / 6.
+            + weights.dimer * self.dimer_score
+            + weights.len * self.len_score
+            + weights.repeats * self.repeats_score)
+            / weight_sum
     }
 }
 
@@ -231,8 +261,12 @@ impl Primer {
 
     /// Calculate all primer metrics.
     /// todo: methods on Metrics instead?
-    pub fn calc_metrics(&self, ion_concentrations: &IonConcentrations) -> Option<PrimerMetrics> {
-        if self.sequence.len() < MIN_PRIMER_LEN {
+    pub fn calc_metrics(
+        &self,
+        ion_concentrations: &IonConcentrations,
+        weights: &ScoringWeights,
+    ) -> Option<PrimerMetrics> {
+        if self.sequence.len() < min_primer_len() {
             return None;
         }
 
@@ -248,12 +282,62 @@ impl Primer {
         };
 
         let dual_ended = matches!(self.volatile.tune_setting, TuneSetting::Both(_));
-        result.update_scores(dual_ended);
+        result.update_scores(dual_ended, weights);
 
         Some(result)
     }
 }
 
+/// Heterodimers with a free energy below this, in kcal/mol, may prevent amplification. From
+/// [Premier Biosoft](http://www.premierbiosoft.com/tech_notes/PCR_Primer_Design.html): "a 3' end
+/// self dimer with a ΔG of -5 kcal/mol ... is tolerated generally."
+pub const HETERODIMER_DG_WARN: f32 = -5.;
+
+/// Kelvin; 37°C. The temperature we evaluate dimer free energy at.
+const DIMER_TEMP: f32 = 310.15;
+
+/// The free energy of the duplex at the 3' end of `a`, where `a`'s last base pairs with
+/// `b_rc[end]`. We count only the complementary run that includes `a`'s 3' terminal base.
+fn end_duplex_dg(a: &[Nucleotide], b_rc: &[Nucleotide], end: usize) -> f32 {
+    let mut run = 0;
+    while run < a.len() && run <= end && a[a.len() - 1 - run] == b_rc[end - run] {
+        run += 1;
+    }
+
+    // Sum the nearest-neighbor stacks along the run, on `a`'s strand.
+    a[a.len() - run..]
+        .windows(2)
+        .map(|nts| {
+            let (dh, ds) = dH_dS_neighbors((nts[0], nts[1]));
+            dh - DIMER_TEMP * ds / 1_000.
+        })
+        .sum()
+}
+
+/// Find the most stable 3'-anchored dimer between two primers, in either direction. That is, where
+/// either primer's 3' end anneals to the other; polymerase can extend these, consuming both. Returns
+/// its free energy in kcal/mol; lower values are more stable. Use the same primer for both to check
+/// for self-dimers.
+pub fn heterodimer_score(a: &[Nucleotide], b: &[Nucleotide]) -> f32 {
+    let mut result = 0.;
+
+    for (x, y) in [(a, b), (b, a)] {
+        if x.is_empty() || y.is_empty() {
+            continue;
+        }
+        let y_rc = seq_complement(y);
+
+        for end in 0..y_rc.len() {
+            let dg = end_duplex_dg(x, &y_rc, end);
+            if dg < result {
+                result = dg;
+            }
+        }
+    }
+
+    result
+}
+
 /// Count the number of single-nucleotide repeats in a sequence. Counts when it's > 4.
 fn single_nt_repeats(seq: &[Nucleotide]) -> u16 {
     let mut result = 0;
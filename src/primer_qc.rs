@@ -0,0 +1,225 @@
+//! Batch quality control for a set of primers: Flag any that fail configurable thresholds, with
+//! reasons. This aggregates primer metrics, and adds checks for hairpins, dimers between primers,
+//! and off-target matches.
+
+use std::{fs::File, io, io::Write, path::Path};
+
+use na_seq::{seq_complement, Nucleotide};
+
+use crate::{inverted_repeats::find_inverted_repeats, primer::Primer};
+
+pub const DEFAULT_QC_FILE: &str = "primer_qc.txt";
+
+/// The largest loop we consider when checking for hairpins in a primer.
+const HAIRPIN_MAX_LOOP: usize = 8;
+
+#[derive(Clone)]
+pub struct QcThresholds {
+    /// °C
+    pub tm_min: f32,
+    /// °C
+    pub tm_max: f32,
+    /// G and C count in the last 5 nucleotides.
+    pub gc_3p_min: u8,
+    pub gc_3p_max: u8,
+    /// Complementary bases at a primer's 3' end, with itself or another primer.
+    pub dimer_max: usize,
+    /// Stem length, in nucleotides.
+    pub hairpin_stem_max: usize,
+    pub repeats_max: u8,
+}
+
+impl Default for QcThresholds {
+    fn default() -> Self {
+        Self {
+            tm_min: 52.,
+            tm_max: 65.,
+            gc_3p_min: 2,
+            gc_3p_max: 4,
+            dimer_max: 4,
+            hairpin_stem_max: 4,
+            repeats_max: 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum QcLevel {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl QcLevel {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Pass => "PASS",
+            Self::Warn => "WARN",
+            Self::Fail => "FAIL",
+        }
+    }
+}
+
+pub struct QcIssue {
+    pub level: QcLevel,
+    pub text: String,
+}
+
+pub struct PrimerQc {
+    /// Index in the primer list.
+    pub primer_i: usize,
+    pub name: String,
+    pub issues: Vec<QcIssue>,
+}
+
+impl PrimerQc {
+    /// The most severe level of this primer's issues.
+    pub fn level(&self) -> QcLevel {
+        self.issues
+            .iter()
+            .map(|i| i.level)
+            .fold(QcLevel::Pass, |a, b| if b > a { b } else { a })
+    }
+}
+
+/// The number of bases at the 3' end of `a` that can anneal to `b`, when the two are antiparallel.
+/// Use the same primer for both to check for self-dimers.
+pub fn end_dimer_len(a: &[Nucleotide], b: &[Nucleotide]) -> usize {
+    let b_rc = seq_complement(b);
+
+    for len in (1..=a.len().min(b.len())).rev() {
+        let end = &a[a.len() - len..];
+        if b_rc.windows(len).any(|w| w == end) {
+            return len;
+        }
+    }
+    0
+}
+
+/// The longest hairpin stem in a primer.
+fn hairpin_stem(seq: &[Nucleotide], min_stem: usize) -> usize {
+    find_inverted_repeats(seq, min_stem, HAIRPIN_MAX_LOOP)
+        .iter()
+        .map(|(stem, _)| stem.len())
+        .max()
+        .unwrap_or_default()
+}
+
+/// Evaluate each primer against the thresholds. Primers should have metrics and matches synced.
+pub fn qc_primers(primers: &[Primer], thresholds: &QcThresholds) -> Vec<PrimerQc> {
+    let mut result = Vec::new();
+
+    for (i, primer) in primers.iter().enumerate() {
+        let mut issues = Vec::new();
+        let mut issue = |level, text: String| issues.push(QcIssue { level, text });
+
+        match &primer.volatile.metrics {
+            Some(metrics) => {
+                if metrics.melting_temp < thresholds.tm_min
+                    || metrics.melting_temp > thresholds.tm_max
+                {
+                    issue(
+                        QcLevel::Fail,
+                        format!(
+                            "TM {:.1}°C is outside {:.0}-{:.0}°C",
+                            metrics.melting_temp, thresholds.tm_min, thresholds.tm_max
+                        ),
+                    );
+                }
+
+                let gc_3p = metrics.gc_3p_count;
+                if gc_3p < thresholds.gc_3p_min || gc_3p > thresholds.gc_3p_max {
+                    issue(
+                        QcLevel::Fail,
+                        format!(
+                            "3' clamp: {gc_3p} G/C in the last 5 is outside {}-{}",
+                            thresholds.gc_3p_min, thresholds.gc_3p_max
+                        ),
+                    );
+                }
+
+                if metrics.repeats > thresholds.repeats_max {
+                    issue(
+                        QcLevel::Warn,
+                        format!("{} repeat runs; may misprime", metrics.repeats),
+                    );
+                }
+            }
+            None => issue(
+                QcLevel::Fail,
+                "Too short to evaluate; no metrics".to_owned(),
+            ),
+        }
+
+        let self_dimer = end_dimer_len(&primer.sequence, &primer.sequence);
+        if self_dimer > thresholds.dimer_max {
+            issue(
+                QcLevel::Fail,
+                format!("Self-dimer: {self_dimer} complementary 3' bases"),
+            );
+        }
+
+        for (j, other) in primers.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let cross = end_dimer_len(&primer.sequence, &other.sequence);
+            if cross > thresholds.dimer_max {
+                issue(
+                    QcLevel::Warn,
+                    format!(
+                        "Cross-dimer with {}: {cross} complementary 3' bases",
+                        other.name
+                    ),
+                );
+            }
+        }
+
+        let stem = hairpin_stem(&primer.sequence, thresholds.hairpin_stem_max + 1);
+        if stem > thresholds.hairpin_stem_max {
+            issue(QcLevel::Warn, format!("Hairpin with a {stem} nt stem"));
+        }
+
+        match primer.volatile.matches.len() {
+            0 => issue(QcLevel::Warn, "No full match to the sequence".to_owned()),
+            1 => (),
+            n => issue(
+                QcLevel::Fail,
+                format!("Off-target: matches the sequence at {n} sites"),
+            ),
+        }
+
+        result.push(PrimerQc {
+            primer_i: i,
+            name: primer.name.clone(),
+            issues,
+        });
+    }
+
+    result
+}
+
+/// A plain text report, e.g. to attach to an order.
+pub fn qc_report_text(results: &[PrimerQc], plasmid_name: &str) -> String {
+    let mut result = format!("Primer QC report: {plasmid_name}\n\n");
+
+    for qc in results {
+        result += &format!("{} {}\n", qc.level().to_str(), qc.name);
+        for issue in &qc.issues {
+            result += &format!("    {}: {}\n", issue.level.to_str(), issue.text);
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|r| r.level() == QcLevel::Fail)
+        .count();
+    result += &format!("\n{} primers; {failed} failed\n", results.len());
+
+    result
+}
+
+pub fn export_qc_report(text: &str, path: &Path) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(text.as_bytes())
+}
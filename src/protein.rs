@@ -4,14 +4,15 @@
 use bincode::{Decode, Encode};
 use na_seq::{
     amino_acids::{AminoAcid, CodingResult},
-    Nucleotide,
+    seq_complement, AaIdent, Nucleotide,
 };
 
 use crate::{
     external_websites::PdbData,
-    misc_types::{Feature, FeatureType},
-    reading_frame::{find_orf_matches, ReadingFrame, ReadingFrameMatch},
+    misc_types::{Feature, FeatureDirection, FeatureType},
+    reading_frame::{find_orf_matches, frame_for_feature, ReadingFrame, ReadingFrameMatch},
     state::State,
+    util::RangeIncl,
 };
 
 pub const WATER_WEIGHT: f32 = 18.015; // g/mol. We subtract these when calculating a protein's weight.
@@ -50,6 +51,14 @@ pub fn proteins_from_seq(
         for (j, om) in cr_orf_matches {
             if *j == i {
                 if let Some(seq_orf_match_dna) = om.range.index_seq(seq) {
+                    let reverse = om.frame.is_reverse();
+                    // Read reverse frames 5' to 3' on the complementary strand.
+                    let seq_orf_match_dna = if reverse {
+                        seq_complement(seq_orf_match_dna)
+                    } else {
+                        seq_orf_match_dna.to_vec()
+                    };
+
                     // todo: DRy with feature_db_load.
                     let len = seq_orf_match_dna.len();
 
@@ -59,8 +68,14 @@ pub fn proteins_from_seq(
                     let mut aa_seq_postcoding = Vec::new();
 
                     for i_ in 0..len / 3 {
-                        let i = i_ * 3; // The ORF-modified sequence index.
-                        let i_actual = i + om.range.start;
+                        // The ORF-modified sequence index.
+                        let i = i_ * 3;
+                        // The codon's first nucleotide, on the forward strand.
+                        let i_actual = if reverse {
+                            om.range.end - i
+                        } else {
+                            i + om.range.start
+                        };
 
                         let nts = &seq_orf_match_dna[i..i + 3];
 
@@ -68,9 +83,16 @@ pub fn proteins_from_seq(
                         if let CodingResult::AminoAcid(aa) =
                             AminoAcid::from_codons(nts.try_into().unwrap())
                         {
-                            if i_actual < feature.range.start {
+                            // Upstream of the feature is to its right on the forward strand, for reverse frames.
+                            let (before, after) = if reverse {
+                                (i_actual > feature.range.end, i_actual < feature.range.start)
+                            } else {
+                                (i_actual < feature.range.start, i_actual > feature.range.end)
+                            };
+
+                            if before {
                                 aa_seq_precoding.push(aa);
-                            } else if i_actual > feature.range.end {
+                            } else if after {
                                 aa_seq_postcoding.push(aa);
                             } else {
                                 aa_seq.push(aa);
@@ -110,6 +132,129 @@ pub fn proteins_from_seq(
     result
 }
 
+/// Translate a coding region feature directly from its range, starting at its codon start offset, and
+/// reading in its direction. Stops at the first stop codon. Handles features that wrap the origin.
+pub fn translate_feature(feature: &Feature, seq: &[Nucleotide]) -> Vec<AminoAcid> {
+    let mut result = Vec::new();
+
+    let seq_len = seq.len();
+    if feature.range.start < 1 || feature.range.start > seq_len || feature.range.end > seq_len {
+        return result;
+    }
+
+    let mut nts = if feature.range.start <= feature.range.end {
+        match feature.range.index_seq(seq) {
+            Some(v) => v.to_vec(),
+            None => return result,
+        }
+    } else {
+        // This feature wraps through the origin.
+        let mut v = seq[feature.range.start - 1..].to_vec();
+        v.extend(&seq[..feature.range.end]);
+        v
+    };
+
+    if feature.direction == FeatureDirection::Reverse {
+        nts = seq_complement(&nts);
+    }
+
+    // Skip the partial codon at the 5' end, if the annotated start isn't codon-aligned.
+    let offset = (feature.codon_start.clamp(1, 3) - 1) as usize;
+    if offset >= nts.len() {
+        return result;
+    }
+
+    for codon in nts[offset..].chunks_exact(3) {
+        match AminoAcid::from_codons(codon.try_into().unwrap()) {
+            CodingResult::AminoAcid(aa) => result.push(aa),
+            CodingResult::StopCodon => break,
+        }
+    }
+
+    result
+}
+
+/// A selection's translation. Only complete codons in the reading frame are translated.
+pub struct RangeTranslation {
+    /// `None` for stop codons.
+    pub aa_seq: Vec<Option<AminoAcid>>,
+    /// Bases at the selection's 5' end (in the frame's direction) before the first full codon.
+    pub skipped_5p: usize,
+    /// Bases at the selection's 3' end after the last full codon.
+    pub leftover_3p: usize,
+}
+
+impl RangeTranslation {
+    /// As with `aa_seq_text`; stop codons display as `*`.
+    pub fn text(&self, aa_ident_disp: AaIdent) -> String {
+        let sep = match aa_ident_disp {
+            AaIdent::OneLetter => "",
+            _ => " ",
+        };
+
+        self.aa_seq
+            .iter()
+            .map(|aa| match aa {
+                Some(a) => a.to_str(aa_ident_disp).to_string(),
+                None => "*".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(sep)
+    }
+}
+
+/// Translate a range of the sequence in a reading frame. Unlike `translate_feature`, this continues
+/// past stop codons. Reverse frames translate the complement, reading from the range's end.
+pub fn translate_range(
+    seq: &[Nucleotide],
+    range: RangeIncl,
+    frame: ReadingFrame,
+) -> Option<RangeTranslation> {
+    let mut nts = range.index_seq(seq)?.to_vec();
+
+    // The 0-based index of the range's first base, in the frame's direction. Reverse frames are
+    // offset from the end of the sequence; see `ReadingFrame::arrange_seq`.
+    let start = if frame.is_reverse() {
+        nts = seq_complement(&nts);
+        seq.len() - range.end
+    } else {
+        range.start - 1
+    };
+
+    let skipped_5p = ((frame.offset() + 3 - start % 3) % 3).min(nts.len());
+    let leftover_3p = (nts.len() - skipped_5p) % 3;
+
+    let aa_seq = nts[skipped_5p..]
+        .chunks_exact(3)
+        .map(
+            |codon| match AminoAcid::from_codons(codon.try_into().unwrap()) {
+                CodingResult::AminoAcid(aa) => Some(aa),
+                CodingResult::StopCodon => None,
+            },
+        )
+        .collect();
+
+    Some(RangeTranslation {
+        aa_seq,
+        skipped_5p,
+        leftover_3p,
+    })
+}
+
+/// Create a compact text representation of an AA sequence. One-letter idents are not separated;
+/// three-letter ones are separated by spaces.
+pub fn aa_seq_text(seq: &[AminoAcid], aa_ident_disp: AaIdent) -> String {
+    let sep = match aa_ident_disp {
+        AaIdent::OneLetter => "",
+        _ => " ",
+    };
+
+    seq.iter()
+        .map(|aa| aa.to_str(aa_ident_disp))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
 /// Calculates the Kyte-Doolittle value of Hydropathy index. Uses per-AA
 /// experimentally-determined hydrophobicity values, averaged over a moving window.
 ///
@@ -123,7 +268,7 @@ pub fn hydropathy_doolittle(seq: &[AminoAcid], window_size: usize) -> Vec<(usize
 
     let win_div_2 = window_size / 2; // Rounds down.
 
-    if win_div_2 - 1 >= seq.len() {
+    if window_size > seq.len() {
         eprintln!("Error with window size for hydropathy");
         return result;
     }
@@ -173,6 +318,7 @@ pub fn sync_cr_orf_matches(state: &mut State) {
         region_matches.append(&mut regions);
     }
 
+    let seq_len = state.get_seq().len();
     for (i, feature) in state.generic[state.active].features.iter().enumerate() {
         if feature.feature_type != FeatureType::CodingRegion {
             continue;
@@ -184,18 +330,32 @@ pub fn sync_cr_orf_matches(state: &mut State) {
             continue;
         }
 
-        // Find the best reading frame match, if there is one.
+        // Find the best reading frame match, if there is one. It must be on the feature's strand.
+        // Prefer the frame set by the feature's codon start; this lets the user correct the frame if
+        // more than one contains the feature.
+        let reverse = feature.direction == FeatureDirection::Reverse;
+        let feature_frame = frame_for_feature(feature, seq_len);
         let mut orf_match = None;
         let mut smallest_match = usize::MAX;
+        let mut in_feature_frame = false;
         for rm in &region_matches {
-            if !rm.range.contains(feature.range.start) || !rm.range.contains(feature.range.end) {
+            if rm.frame.is_reverse() != reverse
+                || !rm.range.contains(feature.range.start)
+                || !rm.range.contains(feature.range.end)
+            {
+                continue;
+            }
+
+            let in_frame = rm.frame == feature_frame;
+            if in_feature_frame && !in_frame {
                 continue;
             }
 
             // If multiple matches contain the range, choose the smallest one.
-            if rm.range.len() < smallest_match {
+            if (in_frame && !in_feature_frame) || rm.range.len() < smallest_match {
                 smallest_match = rm.range.len();
                 orf_match = Some(rm.clone());
+                in_feature_frame = in_frame;
             }
         }
 
@@ -218,3 +378,88 @@ pub fn protein_weight(seq: &[AminoAcid]) -> f32 {
     }
     (result - WATER_WEIGHT * (seq.len() - 1) as f32) / 1_000.
 }
+
+#[cfg(test)]
+mod tests {
+    use na_seq::{seq_from_str, seq_to_str_lower};
+
+    use super::*;
+
+    /// A short gene, MAKR and a stop codon, flanked by non-coding bases.
+    const GENE_SEQ: &str = "ccatggctaaacgttgaccc";
+
+    /// The translations of each protein found in the state's active tab.
+    fn translations(state: &mut State) -> Vec<String> {
+        sync_cr_orf_matches(state);
+        let data = &state.generic[state.active];
+
+        proteins_from_seq(
+            &data.seq,
+            &data.features,
+            &state.volatile[state.active].cr_orf_matches,
+        )
+        .iter()
+        .map(|p| aa_seq_text(&p.aa_seq, AaIdent::OneLetter))
+        .collect()
+    }
+
+    #[test]
+    fn reverse_gene_translates_as_forward() {
+        let seq = seq_from_str(GENE_SEQ);
+
+        let mut state = State::default();
+        state.generic[0].seq = seq.clone();
+        state.generic[0].features = vec![Feature {
+            range: RangeIncl::new(3, 17),
+            feature_type: FeatureType::CodingRegion,
+            direction: FeatureDirection::Forward,
+            ..Default::default()
+        }];
+        let fwd = translations(&mut state);
+
+        // The same gene, on the other strand.
+        let seq_rev = seq_complement(&seq);
+        assert_eq!(seq_to_str_lower(&seq_rev), "gggtcaacgtttagccatgg");
+
+        state.generic[0].seq = seq_rev;
+        state.generic[0].features = vec![Feature {
+            range: RangeIncl::new(4, 18),
+            feature_type: FeatureType::CodingRegion,
+            direction: FeatureDirection::Reverse,
+            ..Default::default()
+        }];
+        let rev = translations(&mut state);
+
+        assert_eq!(fwd, vec!["MAKR".to_owned()]);
+        assert_eq!(rev, fwd);
+    }
+
+    #[test]
+    fn codon_start_skips_first_base() {
+        // The gene, with an extra base before its start codon.
+        let seq = seq_from_str("gatggctaaacgttgaccc");
+        let mut feature = Feature {
+            range: RangeIncl::new(1, 16),
+            feature_type: FeatureType::CodingRegion,
+            direction: FeatureDirection::Forward,
+            ..Default::default()
+        };
+
+        let aa_text = |f: &Feature| aa_seq_text(&translate_feature(f, &seq), AaIdent::OneLetter);
+
+        // Out of frame: GAT GGC, then a TAA stop.
+        assert_eq!(aa_text(&feature), "DG");
+
+        feature.codon_start = 2;
+        assert_eq!(aa_text(&feature), "MAKR");
+    }
+
+    #[test]
+    fn reverse_orf_range() {
+        let seq = seq_complement(&seq_from_str(GENE_SEQ));
+        let matches = find_orf_matches(&seq, ReadingFrame::Rev2);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].range, RangeIncl::new(4, 18));
+    }
+}
@@ -0,0 +1,51 @@
+//! Relationships between enzymes in the restriction enzyme library, to help substitute one enzyme for
+//! another: Isoschizomers recognize the same site, and enzymes with compatible ends leave overhangs
+//! that can be ligated to each other. We compute these from the library's sites and cut positions.
+
+use na_seq::restriction_enzyme::RestrictionEnzyme;
+
+use crate::oligo_anneal::re_ends;
+
+/// Indices into the RE library.
+#[derive(Default)]
+pub struct ReRelations {
+    /// Same recognition site, and cut position.
+    pub isoschizomers: Vec<usize>,
+    /// Same recognition site, but a different cut position; the ends differ.
+    pub neoschizomers: Vec<usize>,
+    /// A different recognition site, but leaves ends that can be ligated to this enzyme's.
+    pub compatible: Vec<usize>,
+}
+
+/// If true, fragments cut by `a` and `b` can be ligated together, in either order.
+pub fn ends_compatible(a: &RestrictionEnzyme, b: &RestrictionEnzyme) -> bool {
+    match (re_ends(a), re_ends(b)) {
+        (Some((a_left, a_right)), Some((b_left, b_right))) => {
+            a_right.ligates_with(&b_left) && b_right.ligates_with(&a_left)
+        }
+        _ => false,
+    }
+}
+
+/// Find enzymes in the library related to `re`. Enzymes with the same name are skipped.
+pub fn find_relations(re: &RestrictionEnzyme, lib: &[RestrictionEnzyme]) -> ReRelations {
+    let mut result = ReRelations::default();
+
+    for (i, other) in lib.iter().enumerate() {
+        if other.name == re.name {
+            continue;
+        }
+
+        if other.cut_seq == re.cut_seq {
+            if other.cut_after == re.cut_after {
+                result.isoschizomers.push(i);
+            } else {
+                result.neoschizomers.push(i);
+            }
+        } else if ends_compatible(re, other) {
+            result.compatible.push(i);
+        }
+    }
+
+    result
+}
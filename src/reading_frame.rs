@@ -7,7 +7,10 @@ use na_seq::{
     Seq,
 };
 
-use crate::util::RangeIncl;
+use crate::{
+    misc_types::{Feature, FeatureDirection},
+    util::RangeIncl,
+};
 
 const START_CODON: [Nucleotide; 3] = [A, T, G];
 pub const STOP_CODONS: [[Nucleotide; 3]; 3] = [[T, A, A], [T, A, G], [T, G, A]];
@@ -74,6 +77,28 @@ impl Display for ReadingFrame {
     }
 }
 
+/// The reading frame a coding feature is translated in, from its direction and codon start. Reverse
+/// frames are offset from the end of the sequence; see `find_orf_matches`.
+pub fn frame_for_feature(feature: &Feature, seq_len: usize) -> ReadingFrame {
+    let codon_offset = (feature.codon_start.clamp(1, 3) - 1) as usize;
+
+    if feature.direction == FeatureDirection::Reverse {
+        // The first nucleotide of the first complete codon, as a 1-based index on the forward strand.
+        let first = feature.range.end.saturating_sub(codon_offset);
+        match (seq_len.saturating_sub(first)) % 3 {
+            0 => ReadingFrame::Rev0,
+            1 => ReadingFrame::Rev1,
+            _ => ReadingFrame::Rev2,
+        }
+    } else {
+        match (feature.range.start + codon_offset).saturating_sub(1) % 3 {
+            0 => ReadingFrame::Fwd0,
+            1 => ReadingFrame::Fwd1,
+            _ => ReadingFrame::Fwd2,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct ReadingFrameMatch {
     pub frame: ReadingFrame,
@@ -118,9 +143,10 @@ pub fn find_orf_matches(seq: &[Nucleotide], orf: ReadingFrame) -> Vec<ReadingFra
                 // RangeIncl::new(frame_open.unwrap() + 1 + offset, i + 2 + offset)
                 RangeIncl::new(frame_open.unwrap() + 1 + offset, i + 3 + offset)
             } else {
+                // Index `j` on the offset reverse complement is `seq_len_full - (j + offset)` here.
                 RangeIncl::new(
                     seq_len_full - (i + 2 + offset),
-                    seq_len_full - (frame_open.unwrap() + offset) - 1,
+                    seq_len_full - (frame_open.unwrap() + offset),
                 )
             };
 
@@ -131,3 +157,60 @@ pub fn find_orf_matches(seq: &[Nucleotide], orf: ReadingFrame) -> Vec<ReadingFra
 
     result
 }
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CodonType {
+    Start,
+    Stop,
+}
+
+/// A start or stop codon, independent of reading frame and annotated features.
+#[derive(Clone, Debug)]
+pub struct CodonMatch {
+    pub codon_type: CodonType,
+    /// True if on the complementary strand.
+    pub reverse: bool,
+    /// Indices are respective to the non-complementary seq, for both strands.
+    pub range: RangeIncl,
+}
+
+/// Find all start and stop codons, in every frame, on both strands. We use this to highlight them
+/// in the sequence view.
+pub fn find_start_stop_codons(seq: &[Nucleotide]) -> Vec<CodonMatch> {
+    let mut result = Vec::new();
+
+    if seq.len() < 3 {
+        return result;
+    }
+
+    for i in 0..seq.len() - 2 {
+        let nts: [Nucleotide; 3] = seq[i..i + 3].try_into().unwrap();
+        // Read 5' to 3' on the complementary strand.
+        let nts_rev = [
+            nts[2].complement(),
+            nts[1].complement(),
+            nts[0].complement(),
+        ];
+
+        // + 1 for our 1-based seq name convention.
+        let range = RangeIncl::new(i + 1, i + 3);
+
+        for (codon, reverse) in [(nts, false), (nts_rev, true)] {
+            let codon_type = if codon == START_CODON {
+                CodonType::Start
+            } else if STOP_CODONS.contains(&codon) {
+                CodonType::Stop
+            } else {
+                continue;
+            };
+
+            result.push(CodonMatch {
+                codon_type,
+                reverse,
+                range,
+            });
+        }
+    }
+
+    result
+}
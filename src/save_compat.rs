@@ -1,2 +1,78 @@
 //! This module contains archived state structs used to open saves from previous versions
 //! of this program, and convert them to the latest version.
+
+use bincode::{Decode, Encode};
+use na_seq::Seq;
+
+use crate::{
+    misc_types::{Feature, FeatureDirection, FeatureType},
+    portions::{MediaPrep, MediaPrepInput, PortionsState, Solution},
+    primer::{Primer, PrimerData},
+    util::RangeIncl,
+    Color,
+};
+
+/// Primers, prior to adding 5' modifications and tails.
+#[derive(Encode, Decode)]
+pub struct PrimerV0 {
+    pub sequence: Seq,
+    pub name: String,
+    pub description: Option<String>,
+    pub volatile: PrimerData,
+}
+
+impl From<PrimerV0> for Primer {
+    fn from(value: PrimerV0) -> Self {
+        Self {
+            sequence: value.sequence,
+            name: value.name,
+            description: value.description,
+            mods: Default::default(),
+            volatile: value.volatile,
+        }
+    }
+}
+
+/// Portions, prior to adding dilution series.
+#[derive(Encode, Decode)]
+pub struct PortionsStateV0 {
+    pub solutions: Vec<Solution>,
+    pub media_input: MediaPrepInput,
+    pub media_result: MediaPrep,
+}
+
+impl From<PortionsStateV0> for PortionsState {
+    fn from(value: PortionsStateV0) -> Self {
+        Self {
+            solutions: value.solutions,
+            media_input: value.media_input,
+            media_result: value.media_result,
+            dilution: Default::default(),
+        }
+    }
+}
+
+/// Features, prior to adding codon start.
+#[derive(Encode, Decode)]
+pub struct FeatureV0 {
+    pub range: RangeIncl,
+    pub feature_type: FeatureType,
+    pub direction: FeatureDirection,
+    pub label: String,
+    pub color_override: Option<Color>,
+    pub notes: Vec<(String, String)>,
+}
+
+impl From<FeatureV0> for Feature {
+    fn from(value: FeatureV0) -> Self {
+        Self {
+            range: value.range,
+            feature_type: value.feature_type,
+            direction: value.direction,
+            label: value.label,
+            color_override: value.color_override,
+            notes: value.notes,
+            codon_start: 1,
+        }
+    }
+}
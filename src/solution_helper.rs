@@ -1 +1,179 @@
 //! This module contains code for assisting with mixing common solutions
+
+use std::fmt::Display;
+
+use na_seq::{seq_complement, seq_weight, Nucleotide};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MassUnit {
+    Ng,
+    Ug,
+    Mg,
+    G,
+}
+
+impl MassUnit {
+    /// Multiply by this to convert to grams.
+    pub fn factor(&self) -> f32 {
+        match self {
+            Self::Ng => 1e-9,
+            Self::Ug => 1e-6,
+            Self::Mg => 1e-3,
+            Self::G => 1.,
+        }
+    }
+}
+
+impl Display for MassUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Ng => "ng",
+            Self::Ug => "μg",
+            Self::Mg => "mg",
+            Self::G => "g",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MolarityUnit {
+    Nm,
+    Um,
+    Mm,
+    M,
+}
+
+impl MolarityUnit {
+    /// Multiply by this to convert to mol/L.
+    pub fn factor(&self) -> f32 {
+        match self {
+            Self::Nm => 1e-9,
+            Self::Um => 1e-6,
+            Self::Mm => 1e-3,
+            Self::M => 1.,
+        }
+    }
+}
+
+impl Display for MolarityUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Nm => "nM",
+            Self::Um => "μM",
+            Self::Mm => "mM",
+            Self::M => "M",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum VolumeUnit {
+    Ul,
+    Ml,
+    L,
+}
+
+impl VolumeUnit {
+    /// Multiply by this to convert to liters.
+    pub fn factor(&self) -> f32 {
+        match self {
+            Self::Ul => 1e-6,
+            Self::Ml => 1e-3,
+            Self::L => 1.,
+        }
+    }
+}
+
+impl Display for VolumeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Ul => "μL",
+            Self::Ml => "mL",
+            Self::L => "L",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+/// The quantities related by mass = molarity × volume × molecular weight.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Mass,
+    Molarity,
+    Volume,
+    MolWeight,
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Mass => "Mass",
+            Self::Molarity => "Molarity",
+            Self::Volume => "Volume",
+            Self::MolWeight => "Mol weight",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+/// Input and result for converting between mass, molarity, volume, and molecular weight. Values are in
+/// their selected units; molecular weight is in g/mol.
+pub struct ConversionUi {
+    pub solve_for: Quantity,
+    pub mass: f32,
+    pub mass_unit: MassUnit,
+    pub molarity: f32,
+    pub molarity_unit: MolarityUnit,
+    pub volume: f32,
+    pub volume_unit: VolumeUnit,
+    pub mol_weight: f32,
+}
+
+impl Default for ConversionUi {
+    fn default() -> Self {
+        Self {
+            solve_for: Quantity::Mass,
+            mass: 0.,
+            mass_unit: MassUnit::Ug,
+            molarity: 100.,
+            molarity_unit: MolarityUnit::Nm,
+            volume: 50.,
+            volume_unit: VolumeUnit::Ul,
+            mol_weight: 0.,
+        }
+    }
+}
+
+impl ConversionUi {
+    /// Compute the quantity we're solving for from the other three. Leaves it at 0 if the inputs
+    /// don't allow a solution, eg due to division by 0.
+    pub fn solve(&mut self) {
+        // Base units: g, mol/L, L, g/mol.
+        let mass = self.mass * self.mass_unit.factor();
+        let molarity = self.molarity * self.molarity_unit.factor();
+        let volume = self.volume * self.volume_unit.factor();
+        let mw = self.mol_weight;
+
+        let div = |num: f32, denom: f32| if denom == 0. { 0. } else { num / denom };
+
+        match self.solve_for {
+            Quantity::Mass => self.mass = molarity * volume * mw / self.mass_unit.factor(),
+            Quantity::Molarity => {
+                self.molarity = div(mass, volume * mw) / self.molarity_unit.factor()
+            }
+            Quantity::Volume => self.volume = div(mass, molarity * mw) / self.volume_unit.factor(),
+            Quantity::MolWeight => self.mol_weight = div(mass, molarity * volume),
+        }
+    }
+}
+
+/// Molecular weight of double-stranded DNA, in g/mol. Eg for a plasmid.
+pub fn ds_dna_weight(seq: &[Nucleotide]) -> f32 {
+    seq_weight(seq) + seq_weight(&seq_complement(seq))
+}
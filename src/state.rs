@@ -19,25 +19,44 @@ use na_seq::{
 use crate::{
     ab1::SeqRecordAb1,
     alignment::AlignmentState,
+    ambiguous,
     backbones::{load_backbone_library, Backbone},
+    cassette::Cassette,
     cloning::CloningState,
+    crispr::Guide,
     file_io::{
-        save::{load, load_import, save, PrefsToSave, StateToSave, DEFAULT_PREFS_FILE},
+        save::{
+            is_session_tab, load, load_import, remove_session_tabs, save, session_tab_path,
+            PrefsToSave, StateToSave, DEFAULT_PREFS_FILE,
+        },
         GenericData,
     },
     gui,
     gui::navigation::Tab,
+    history::History,
+    inverted_repeats::InvertedRepeat,
     misc_types::{find_search_matches, SearchMatch, MIN_SEARCH_LEN},
     pcr::PcrParams,
     portions::PortionsState,
-    primer::IonConcentrations,
+    primer::{set_tm_target, AnnealMatch, IonConcentrations},
+    primer_binding::PrimerBinding,
+    primer_library::{primer_for_tab, primer_lib_path, PrimerLibrary},
     protein::{proteins_from_seq, sync_cr_orf_matches, Protein},
-    reading_frame::{find_orf_matches, ReadingFrame, ReadingFrameMatch},
+    reading_frame::{find_orf_matches, CodonMatch, ReadingFrame, ReadingFrameMatch},
     tags::TagMatch,
-    util::RangeIncl,
+    util::{
+        find_edit, index_after_insert, index_after_remove, remap_after_edit, repair_features,
+        RangeIncl,
+    },
     Selection, StateUi, PREFS_SAVE_INTERVAL,
 };
 
+/// Move an item within a vector; `to` is its index after removing it.
+fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) {
+    let item = items.remove(from);
+    items.insert(to, item);
+}
+
 impl eframe::App for State {
     /// This is the GUI's event loop. This also handles periodically saving preferences to disk.
     /// Note that preferences are only saved if the window is active, ie mouse movement in it or similar.
@@ -91,9 +110,13 @@ pub struct State {
     pub restriction_enzyme_lib: Vec<RestrictionEnzyme>, // Does not need to be saved
     pub backbone_lib: Vec<Backbone>,
     pub reading_frame: ReadingFrame,
-    pub search_seq: Seq,
-    pub cloning: CloningState,
+    /// Lowercase; may contain IUPAC degenerate codes.
+    pub search_seq: Vec<u8>,
+    /// The cloning design (backbone, insert location etc.) for each tab. Index corresponds to `active`.
+    pub cloning: Vec<CloningState>,
     pub alignment: AlignmentState,
+    /// The lab's primer stock list; not tied to a tab.
+    pub primer_lib: PrimerLibrary,
 }
 
 impl Default for State {
@@ -113,14 +136,25 @@ impl Default for State {
             reading_frame: Default::default(),
             volatile: vec![Default::default()],
             search_seq: Default::default(),
-            cloning: Default::default(),
+            cloning: vec![Default::default()],
             alignment: Default::default(),
+            primer_lib: Default::default(),
         };
 
         // Load the RE lib before prefs, because prefs may include loading of previously-opened files,
         // which then trigger RE match syncs.
         result.restriction_enzyme_lib = load_re_library();
         result.backbone_lib = load_backbone_library();
+        result.primer_lib = match PrimerLibrary::load_from_file(&primer_lib_path()) {
+            Ok(lib) => lib,
+            Err(e) => {
+                eprintln!("Error loading the primer library; changes to it won't be saved: {e}");
+                PrimerLibrary {
+                    load_failed: true,
+                    ..Default::default()
+                }
+            }
+        };
 
         result
     }
@@ -135,8 +169,10 @@ impl State {
         self.portions.push(Default::default());
         self.volatile.push(Default::default());
         self.ab1_data.push(Default::default());
+        self.cloning.push(Default::default());
 
         self.active = self.generic.len() - 1;
+        self.alignment.clear_tracks();
 
         // todo: DRY with reset
         self.ui.cursor_pos = None;
@@ -171,15 +207,35 @@ impl State {
         self.tabs_open.remove(i);
         self.portions.remove(i);
         self.volatile.remove(i);
-
-        let mut tab_i_removed = None;
-        for (j, tab) in self.ui.re.tabs_selected.iter().enumerate() {
-            if *tab == i {
-                tab_i_removed = Some(j);
+        self.cloning.remove(i);
+        // Tab indices have shifted.
+        self.ui.cloning_tab_synced = None;
+
+        // Drop references to the removed tab, and shift ones to its right.
+        for tabs in [
+            &mut self.ui.re.tabs_selected,
+            &mut self.ui.golden_gate.tabs,
+            &mut self.alignment.msa_tabs,
+        ] {
+            tabs.retain(|tab| *tab != i);
+            for tab in tabs.iter_mut() {
+                if *tab > i {
+                    *tab -= 1;
+                }
             }
         }
-        if let Some(j) = tab_i_removed {
-            self.ui.re.tabs_selected.remove(j);
+        self.ui.golden_gate.result = None;
+        self.alignment.msa_result = None;
+        self.alignment.read_alignment = None;
+        self.ui.ab1.ref_tab = match self.ui.ab1.ref_tab {
+            Some(tab) if tab > i => Some(tab - 1),
+            Some(tab) if tab == i => None,
+            tab => tab,
+        };
+
+        // These list other tabs by index.
+        for vol in &mut self.volatile {
+            vol.primer_tab_matches = None;
         }
 
         // Don't let the active tab overflow to the right; move it to the left if it would.
@@ -187,11 +243,69 @@ impl State {
         if (self.active > 0 && self.active <= i && n > 1) || self.active + 1 >= n {
             self.active -= 1;
         }
+        self.alignment.clear_tracks();
 
         // So these tabs don't open on the next program run.
         self.save_prefs()
     }
 
+    /// Move a tab to a new position, e.g. by dragging it. `to` is its index after removing it from
+    /// its current position.
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        let n = self.generic.len();
+        if from >= n || to >= n || from == to {
+            return;
+        }
+
+        move_item(&mut self.generic, from, to);
+        move_item(&mut self.ab1_data, from, to);
+        move_item(&mut self.tabs_open, from, to);
+        move_item(&mut self.portions, from, to);
+        move_item(&mut self.volatile, from, to);
+        move_item(&mut self.cloning, from, to);
+
+        // The new index of each tab.
+        let remap = |i: usize| {
+            if i == from {
+                to
+            } else if from < i && i <= to {
+                i - 1
+            } else if to <= i && i < from {
+                i + 1
+            } else {
+                i
+            }
+        };
+
+        self.active = remap(self.active);
+        self.ui.cloning_tab_synced = self.ui.cloning_tab_synced.map(remap);
+        self.ui.ab1.ref_tab = self.ui.ab1.ref_tab.map(remap);
+        self.ui.concat.tab_first = remap(self.ui.concat.tab_first);
+        self.ui.concat.tab_second = remap(self.ui.concat.tab_second);
+
+        for i in &mut self.ui.re.tabs_selected {
+            *i = remap(*i);
+        }
+        for i in &mut self.ui.golden_gate.tabs {
+            *i = remap(*i);
+        }
+        for i in &mut self.alignment.msa_tabs {
+            *i = remap(*i);
+        }
+        if let Some(aln) = &mut self.alignment.read_alignment {
+            aln.read_tab = remap(aln.read_tab);
+            aln.ref_tab = remap(aln.ref_tab);
+        }
+
+        // These list other tabs by index.
+        for vol in &mut self.volatile {
+            vol.primer_tab_matches = None;
+        }
+
+        // So tabs open in this order on the next program run.
+        self.save_prefs()
+    }
+
     /// Convenience function, since we call this so frequently.
     pub fn get_seq(&self) -> &[Nucleotide] {
         &self.generic[self.active].seq
@@ -203,6 +317,7 @@ impl State {
         self.tabs_open[self.active] = Default::default();
         self.portions[self.active] = Default::default();
         self.volatile[self.active] = Default::default();
+        self.cloning[self.active] = Default::default();
         // todo: Ideally we reset the window title  here, but we've having trouble with variable
         // todo scope in the input function.
 
@@ -218,32 +333,106 @@ impl State {
         let prefs_loaded: io::Result<PrefsToSave> = load(path);
 
         if let Ok(prefs) = prefs_loaded {
-            let (ui, tabs_open, ion_concentrations) = prefs.to_state();
+            let (ui, tabs_open, active_tab, ion_concentrations) = prefs.to_state();
             self.ui = ui;
+            // Start from the lab's defaults.
             self.ion_concentrations = ion_concentrations;
+            set_tm_target(self.ui.tm_target);
+            self.ui.primer_lens.apply();
 
-            for tab in &tabs_open {
+            // Map from the saved tab index to the index it was restored to, if any.
+            let mut active = None;
+            for (i, tab) in tabs_open.iter().enumerate() {
                 if let Some(path) = &tab.path {
+                    if !path.exists() {
+                        eprintln!("Unable to restore tab; file not found: {:?}", path);
+                        continue;
+                    }
+
                     if let Some(loaded) = load_import(path) {
                         self.load(&loaded);
+
+                        // Unsaved tabs stay unsaved; their session file is only a placeholder.
+                        if is_session_tab(path) {
+                            self.tabs_open[self.active].path = None;
+                        }
+
+                        if i <= active_tab {
+                            active = Some(self.active);
+                        }
                     }
                 }
             }
+
+            if let Some(i) = active {
+                self.active = i;
+                self.sync_re_sites();
+                self.sync_reading_frame();
+                self.ui.seq_input = seq_to_str_lower(self.get_seq());
+                self.reset_selections();
+            }
         }
     }
 
-    pub fn save_prefs(&self) {
+    /// Save preferences, and the list of open tabs. Unsaved tabs with data are written to
+    /// session files, so they can be restored on the next run.
+    pub fn save_prefs(&mut self) {
+        let mut tabs_open = Vec::new();
+        let mut unsaved_count = 0;
+
+        for (i, tab) in self.tabs_open.iter().enumerate() {
+            if tab.path.is_some() || i >= self.generic.len() || self.generic[i].seq.is_empty() {
+                tabs_open.push(tab.clone());
+                continue;
+            }
+
+            let path = session_tab_path(unsaved_count);
+            unsaved_count += 1;
+
+            match StateToSave::from_state(self, i).save_to_file(&path) {
+                Ok(_) => tabs_open.push(Tab {
+                    path: Some(path),
+                    ab1: tab.ab1,
+                }),
+                Err(e) => {
+                    eprintln!("Error saving unsaved tab: {e}");
+                    tabs_open.push(tab.clone());
+                }
+            }
+        }
+
+        // Files from unsaved tabs closed since the last save.
+        remove_session_tabs(unsaved_count);
+
         if let Err(e) = save(
             &PathBuf::from(DEFAULT_PREFS_FILE),
-            &PrefsToSave::from_state(&self.ui, &self.tabs_open, &self.ion_concentrations),
+            &PrefsToSave::from_state(&self.ui, &tabs_open, self.active),
         ) {
             eprintln!("Error saving prefs: {e}");
         }
+
+        if let Err(e) = self.primer_lib.save_to_file(&primer_lib_path()) {
+            eprintln!("Error saving the primer library: {e}");
+        }
+    }
+
+    /// Add a primer from the library to the active tab, and find where it matches.
+    pub fn add_primer_from_lib(&mut self, lib_i: usize) {
+        if lib_i >= self.primer_lib.primers.len() {
+            return;
+        }
+
+        let primer = primer_for_tab(&self.primer_lib.primers[lib_i]);
+        self.snapshot();
+        self.generic[self.active].primers.push(primer);
+        self.sync_primer_matches(Some(self.generic[self.active].primers.len() - 1));
+        self.sync_primer_metrics();
     }
 
     /// Runs the match search between primers and sequences. Run this when primers and sequences change.
     pub fn sync_primer_matches(&mut self, primer_i: Option<usize>) {
         let seq = &self.generic[self.active].seq.clone(); // todo; Not ideal to clone.
+        let ambiguous = self.generic[self.active].ambiguous.clone();
         let primers = match primer_i {
             Some(i) => &mut self.generic[self.active].primers[i..=i],
             // Run on all primers.
@@ -252,7 +441,69 @@ impl State {
 
         for primer in primers {
             primer.volatile.matches = primer.match_to_seq(&seq);
+            primer
+                .volatile
+                .matches
+                .retain(|m| !ambiguous::overlaps(&ambiguous, m.range));
         }
+
+        self.sync_primer_anneal_matches();
+        self.volatile[self.active].primer_tab_matches = None;
+        self.volatile[self.active].primer_binding = None;
+    }
+
+    /// Find which other open tabs each of the active tab's primers match. This checks every tab, so
+    /// we run it on demand.
+    pub fn sync_primer_tab_matches(&mut self) {
+        let mut result = Vec::new();
+
+        for primer in &self.generic[self.active].primers {
+            let mut tabs = Vec::new();
+            for (i, data) in self.generic.iter().enumerate() {
+                let matches = primer.match_to_seq(&data.seq);
+                if i != self.active
+                    && matches
+                        .iter()
+                        .any(|m| !ambiguous::overlaps(&data.ambiguous, m.range))
+                {
+                    tabs.push(i);
+                }
+            }
+            result.push(tabs);
+        }
+
+        self.volatile[self.active].primer_tab_matches = Some(result);
+    }
+
+    /// For primers that don't fully match the sequence, find where their 3' ends anneal.
+    pub fn sync_primer_anneal_matches(&mut self) {
+        if self.active >= self.volatile.len() {
+            eprintln!("Error: Volatile len too short for primer anneal sync.");
+            return;
+        }
+
+        let mut result = Vec::new();
+        let seq = &self.generic[self.active].seq;
+        let ambiguous = &self.generic[self.active].ambiguous;
+
+        for (primer_i, primer) in self.generic[self.active].primers.iter().enumerate() {
+            if !primer.volatile.matches.is_empty() {
+                continue;
+            }
+
+            for (match_, tail_len) in primer.match_3p_anchored(seq) {
+                if ambiguous::overlaps(ambiguous, match_.range) {
+                    continue;
+                }
+                result.push(AnnealMatch {
+                    primer_i,
+                    match_,
+                    tail_len,
+                });
+            }
+        }
+
+        self.volatile[self.active].primer_anneal_matches = result;
     }
 
     pub fn sync_pcr(&mut self) {
@@ -265,14 +516,26 @@ impl State {
             eprintln!("Error: Volatile len too short for RE sync.");
             return;
         }
-        self.volatile[self.active].restriction_enzyme_matches = Vec::new();
+        let data = &self.generic[self.active];
+        let lib = &self.restriction_enzyme_lib;
+
+        let mut matches = find_re_matches(&data.seq, lib);
+
+        // Discard sites that include an ambiguous base, and update the counts to match.
+        matches.retain(|m| {
+            let site_len = lib[m.lib_index].cut_seq.len();
+            let range = RangeIncl::new(m.seq_index, m.seq_index + site_len - 1);
+            !ambiguous::overlaps(&data.ambiguous, range)
+        });
+        let mut counts = vec![0; lib.len()];
+        for m in &matches {
+            counts[m.lib_index] += 1;
+        }
+        for m in &mut matches {
+            m.match_count = counts[m.lib_index];
+        }
 
-        self.volatile[self.active]
-            .restriction_enzyme_matches
-            .append(&mut find_re_matches(
-                &self.generic[self.active].seq,
-                &self.restriction_enzyme_lib,
-            ));
+        self.volatile[self.active].restriction_enzyme_matches = matches;
 
         // This sorting aids in our up/down label alternation in the display.
         self.volatile[self.active]
@@ -287,8 +550,9 @@ impl State {
 
     pub fn sync_search(&mut self) {
         if self.search_seq.len() >= MIN_SEARCH_LEN {
-            self.volatile[self.active].search_matches =
-                find_search_matches(self.get_seq(), &self.search_seq);
+            let mut matches = find_search_matches(self.get_seq(), &self.search_seq);
+            matches.retain(|m| !ambiguous::overlaps(&self.generic[self.active].ambiguous, m.range));
+            self.volatile[self.active].search_matches = matches;
         } else {
             self.volatile[self.active].search_matches = Vec::new();
         }
@@ -303,7 +567,7 @@ impl State {
     pub fn sync_primer_metrics(&mut self) {
         for primer in &mut self.generic[self.active].primers {
             // primer.run_calcs(&self.ion_concentration[self.active]);
-            primer.run_calcs(&self.ion_concentrations);
+            primer.run_calcs(&self.ion_concentrations, &self.ui.scoring_weights);
             //
             // primer.volatile[self.active].sequence_input = seq_to_str(&primer.sequence);
             //
@@ -322,48 +586,183 @@ impl State {
         }
     }
 
-    /// Upddate this sequence by inserting a sequence of interest. Shifts features based on the insert.
-    pub fn insert_nucleotides(&mut self, insert: &[Nucleotide], insert_loc: usize) {
-        insert_into_seq(&mut self.generic[self.active].seq, insert, insert_loc).ok();
+    /// Names of primers whose binding sites overlap `range`; removing it will break them.
+    fn primers_bound_at(&self, range: RangeIncl) -> Vec<String> {
+        self.generic[self.active]
+            .primers
+            .iter()
+            .filter(|p| {
+                p.volatile
+                    .matches
+                    .iter()
+                    .any(|m| m.range.start <= range.end && range.start <= m.range.end)
+            })
+            .map(|p| p.name.clone())
+            .collect()
+    }
 
-        let insert_i = insert_loc - 1; // 1-based indexing.
+    fn set_edit_warning(&mut self, primers_broken: Vec<String>) {
+        self.ui.edit_warning = if primers_broken.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "This edit changed the binding site of: {}",
+                primers_broken.join(", ")
+            ))
+        };
+    }
 
-        // Now, you have to update features affected by this insertion, shifting them right A/R.
-        for feature in &mut self.generic[self.active].features {
-            if feature.range.start > insert_i {
-                feature.range.start += insert.len();
-            }
+    /// Keep features and bookmarks on the same nucleotides after the sequence length changes. Primer
+    /// matches are found again, in `sync_seq_related`. See `remap_after_edit` for the meaning of `edit_pos` and `delta`.
+    fn remap_annotations(&mut self, edit_pos: usize, delta: isize) {
+        let data = &mut self.generic[self.active];
+        remap_after_edit(&mut data.features, edit_pos, delta);
+        ambiguous::remap(&mut data.ambiguous, edit_pos, delta);
+
+        // Bookmarks within a removed range move to the nucleotide that follows it.
+        for (posit, _) in &mut data.bookmarks {
+            *posit = if delta > 0 {
+                index_after_insert(*posit, edit_pos, delta as usize)
+            } else {
+                let removed = RangeIncl::new(edit_pos, edit_pos + delta.unsigned_abs() - 1);
+                index_after_remove(*posit, removed)
+            };
+        }
+    }
 
-            if feature.range.end > insert_i {
-                feature.range.end += insert.len();
-            }
+    /// Replace the sequence with an edited version, e.g. from the raw text editor. We find the edited
+    /// region, and remap annotations around it. `ambiguous` are the new sequence's ambiguous positions,
+    /// from `seq_from_pasted`.
+    pub fn replace_seq(&mut self, seq: Seq, ambiguous: &[(usize, u8)]) {
+        self.snapshot_debounced();
+        let (edit_pos, removed, inserted) = find_edit(self.get_seq(), &seq);
+
+        self.generic[self.active].seq = seq;
+        self.remap_annotations(edit_pos, -(removed as isize));
+        self.remap_annotations(edit_pos, inserted as isize);
+
+        // Ambiguous positions outside the edit are already tracked.
+        let new: Vec<_> = ambiguous
+            .iter()
+            .filter(|(i, _)| *i >= edit_pos && *i < edit_pos + inserted)
+            .copied()
+            .collect();
+        ambiguous::insert(&mut self.generic[self.active].ambiguous, &new, 0);
+
+        self.sync_seq_related(None);
+    }
+
+    /// Upddate this sequence by inserting a sequence of interest, before 1-based `insert_loc`. Shifts
+    /// features and bookmarks downstream of the insert; features that contain it are lengthened.
+    pub fn insert_nucleotides(&mut self, insert: &[Nucleotide], insert_loc: usize) {
+        if insert_loc == 0 {
+            eprintln!("Invalid insert location");
+            return;
         }
 
+        self.snapshot_debounced();
+
+        // An insert between a binding site's first and last nucleotides breaks it.
+        let primers_broken = self.generic[self.active]
+            .primers
+            .iter()
+            .filter(|p| {
+                p.volatile
+                    .matches
+                    .iter()
+                    .any(|m| m.range.start < insert_loc && insert_loc <= m.range.end)
+            })
+            .map(|p| p.name.clone())
+            .collect();
+
+        if insert_into_seq(&mut self.generic[self.active].seq, insert, insert_loc).is_err() {
+            eprintln!("Error inserting nucleotides at {insert_loc}");
+            return;
+        }
+
+        self.remap_annotations(insert_loc, insert.len() as isize);
+
+        self.set_edit_warning(primers_broken);
         self.sync_seq_related(None);
     }
 
-    /// One-based indexing. Similar to `insert_nucleotides`.
+    /// Remove nucleotides, using 1-based indexing. Shifts features and bookmarks downstream of the removed
+    /// range, trims features that overlap it, and removes features entirely within it.
     pub fn remove_nucleotides(&mut self, range: RangeIncl) {
-        let seq = &mut self.generic[self.active].seq;
-        if range.end + 1 > seq.len() {
+        if range.start == 0 || range.start > range.end || range.end > self.get_seq().len() {
             return;
         }
 
-        let count = range.len();
+        self.snapshot_debounced();
+        let primers_broken = self.primers_bound_at(range);
 
-        seq.drain(range.start..=range.end);
+        self.generic[self.active]
+            .seq
+            .drain(range.start - 1..range.end);
 
-        // Now, you have to update features affected by this insertion, shifting them left A/R.
-        for feature in &mut self.generic[self.active].features {
-            if feature.range.start > range.end {
-                feature.range.start -= count;
-            }
-            if feature.range.end > range.end {
-                feature.range.end -= count;
-            }
+        self.remap_annotations(range.start, -(range.len() as isize));
+
+        self.set_edit_warning(primers_broken);
+        self.sync_seq_related(None);
+    }
+
+    /// Store the active tab's data prior to an edit, so it can be undone.
+    pub fn snapshot(&mut self) {
+        self.volatile[self.active]
+            .history
+            .push(&self.generic[self.active]);
+        self.volatile[self.active].unsaved = true;
+    }
+
+    /// As `snapshot_debounced`, for an edit already made in place, e.g. by a text field. `prev` is the
+    /// tab's data before the edit.
+    pub fn snapshot_prev(&mut self, prev: &GenericData) {
+        self.volatile[self.active].history.push_debounced(prev);
+        self.volatile[self.active].unsaved = true;
+    }
+
+    /// As `snapshot`, but edits in quick succession, e.g. typing, are combined into one undo step.
+    pub fn snapshot_debounced(&mut self) {
+        self.volatile[self.active]
+            .history
+            .push_debounced(&self.generic[self.active]);
+        self.volatile[self.active].unsaved = true;
+    }
+
+    /// Undo the last edit to the active tab, if there is one.
+    pub fn undo(&mut self) {
+        if let Some(data) = self.volatile[self.active]
+            .history
+            .undo(&self.generic[self.active])
+        {
+            self.generic[self.active] = data;
+            self.volatile[self.active].unsaved = true;
+            self.sync_after_history();
+        }
+    }
+
+    /// Redo the last undone edit to the active tab, if there is one.
+    pub fn redo(&mut self) {
+        if let Some(data) = self.volatile[self.active]
+            .history
+            .redo(&self.generic[self.active])
+        {
+            self.generic[self.active] = data;
+            self.volatile[self.active].unsaved = true;
+            self.sync_after_history();
+        }
+    }
+
+    /// Selections and the cursor may refer to features, primers, or positions that no longer exist.
+    fn sync_after_history(&mut self) {
+        self.reset_selections();
+        let seq_len = self.get_seq().len();
+        if let Some(i) = self.ui.text_cursor_i {
+            self.ui.text_cursor_i = Some(i.min(seq_len));
         }
 
         self.sync_seq_related(None);
+        self.sync_re_sites();
     }
 
     /// Run this when the sequence changes.
@@ -375,6 +774,18 @@ impl State {
 
         self.sync_reading_frame();
         self.sync_search();
+        self.volatile[self.active].codon_matches = None;
+        self.volatile[self.active].inverted_repeats = None;
+        self.volatile[self.active].guides = None;
+        self.volatile[self.active].cassettes = None;
+
+        // Positions of a read aligned to this sequence are out of date.
+        if let Some(aln) = &self.alignment.read_alignment {
+            if aln.ref_tab == self.active {
+                self.alignment.read_alignment = None;
+            }
+        }
+        self.alignment.clear_tracks();
 
         sync_cr_orf_matches(self);
 
@@ -390,6 +801,7 @@ impl State {
     pub fn reset_selections(&mut self) {
         self.ui.text_selection = None;
         self.ui.selected_item = Selection::None;
+        self.ui.selected_features.clear();
     }
 
     /// Load a single tab from file.
@@ -416,6 +828,22 @@ impl State {
         };
 
         self.generic[self.active].clone_from(&loaded.generic);
+
+        let gen = &mut self.generic[self.active];
+        let fixes = repair_features(&mut gen.features, gen.seq.len(), gen.topology);
+        self.ui.load_warning = if fixes.is_empty() {
+            None
+        } else {
+            // The full list is in the console; keep the header readable.
+            let mut summary = fixes[..fixes.len().min(3)].join("; ");
+            if fixes.len() > 3 {
+                summary += "; ...";
+            }
+            Some(format!(
+                "Repaired {} feature(s) with invalid coordinates: {summary}",
+                fixes.len()
+            ))
+        };
         // self.ion_concentrations[self.active].clone_from(&loaded.ion_concentrations);
         self.portions[self.active].clone_from(&loaded.portions);
         self.ab1_data[self.active].clone_from(&loaded.ab1_data);
@@ -485,4 +913,146 @@ pub struct StateVolatile {
     /// for a given coding-region feature.
     pub cr_orf_matches: Vec<(usize, ReadingFrameMatch)>,
     pub proteins: Vec<Protein>,
+    /// 3'-anchored matches, for primers that don't match the sequence in full.
+    pub primer_anneal_matches: Vec<AnnealMatch>,
+    /// For each primer, the other open tabs whose sequences it matches. `None` if not yet checked, or
+    /// out of date.
+    pub primer_tab_matches: Option<Vec<Vec<usize>>>,
+    /// The selected primer's best binding site, allowing mismatches. `None` if not yet computed, or
+    /// out of date.
+    pub primer_binding: Option<PrimerBinding>,
+    /// Start and stop codons, for highlighting. `None` if out of date; we compute this only when
+    /// the highlight is shown.
+    pub codon_matches: Option<Vec<CodonMatch>>,
+    /// Inverted repeats (hairpins), including likely terminators. `None` if not yet searched for, or
+    /// out of date.
+    pub inverted_repeats: Option<Vec<InvertedRepeat>>,
+    /// CRISPR guide candidates, sorted by score. `None` if not yet searched for, or out of date.
+    pub guides: Option<Vec<Guide>>,
+    /// Expression cassettes composed from features. `None` if not yet searched for, or out of date.
+    pub cassettes: Option<Vec<Cassette>>,
+    /// Undo and redo snapshots of this tab's data.
+    pub history: History,
+    /// The tab has been edited since it was last saved or loaded.
+    pub unsaved: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use na_seq::{seq_from_str, SeqTopology};
+
+    use super::*;
+    use crate::{cloning::make_region_tab, misc_types::Feature, primer::Primer};
+
+    /// A 30 nt sequence, with a feature before position 10, and one from 10 to 20.
+    fn state_with_features() -> State {
+        let mut state = State::default();
+        state.generic[0].seq = seq_from_str("acgtacgtacgtacgtacgtacgtacgtac");
+        state.generic[0].features = vec![
+            Feature {
+                range: RangeIncl::new(2, 5),
+                ..Default::default()
+            },
+            Feature {
+                range: RangeIncl::new(10, 20),
+                ..Default::default()
+            },
+        ];
+        state
+    }
+
+    #[test]
+    fn insert_within_feature() {
+        let mut state = state_with_features();
+        state.insert_nucleotides(&seq_from_str("ggg"), 12);
+
+        assert_eq!(state.get_seq().len(), 33);
+        assert_eq!(state.generic[0].features[0].range, RangeIncl::new(2, 5));
+        assert_eq!(state.generic[0].features[1].range, RangeIncl::new(10, 23));
+    }
+
+    #[test]
+    fn insert_before_feature() {
+        let mut state = state_with_features();
+        state.insert_nucleotides(&seq_from_str("gg"), 7);
+
+        assert_eq!(state.generic[0].features[0].range, RangeIncl::new(2, 5));
+        assert_eq!(state.generic[0].features[1].range, RangeIncl::new(12, 22));
+    }
+
+    #[test]
+    fn remove_across_feature_end() {
+        let mut state = state_with_features();
+        state.remove_nucleotides(RangeIncl::new(18, 25));
+
+        assert_eq!(state.get_seq().len(), 22);
+        assert_eq!(state.generic[0].features[0].range, RangeIncl::new(2, 5));
+        assert_eq!(state.generic[0].features[1].range, RangeIncl::new(10, 17));
+    }
+
+    #[test]
+    fn remove_across_feature_start() {
+        let mut state = state_with_features();
+        state.remove_nucleotides(RangeIncl::new(8, 12));
+
+        assert_eq!(state.generic[0].features[0].range, RangeIncl::new(2, 5));
+        assert_eq!(state.generic[0].features[1].range, RangeIncl::new(8, 15));
+    }
+
+    #[test]
+    fn primer_matches_follow_edit() {
+        let mut state = State::default();
+        state.generic[0].seq = seq_from_str("tttttgacctgaagcttgcatgccattttt");
+        state.generic[0].primers = vec![Primer {
+            sequence: seq_from_str("gacctgaagcttgcatgcca"),
+            ..Default::default()
+        }];
+        state.sync_primer_matches(None);
+
+        // Upstream of the binding site, the match shifts.
+        state.insert_nucleotides(&seq_from_str("gg"), 3);
+        let matches = &state.generic[0].primers[0].volatile.matches;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].range, RangeIncl::new(8, 27));
+
+        // Within it, the primer no longer binds.
+        state.insert_nucleotides(&seq_from_str("gg"), 15);
+        assert!(state.generic[0].primers[0].volatile.matches.is_empty());
+    }
+
+    #[test]
+    fn region_tab_keeps_ambiguous() {
+        let mut state = state_with_features();
+        state.generic[0].topology = SeqTopology::Circular;
+        state.generic[0].ambiguous = vec![(3, b'n'), (12, b'r'), (28, b'-')];
+
+        make_region_tab(&mut state, RangeIncl::new(10, 20), "region");
+        assert_eq!(state.generic[state.active].ambiguous, vec![(3, b'r')]);
+
+        // Across the origin of the 30 nt sequence.
+        state.active = 0;
+        make_region_tab(&mut state, RangeIncl::new(25, 5), "region");
+        assert_eq!(
+            state.generic[state.active].ambiguous,
+            vec![(4, b'-'), (9, b'n')]
+        );
+    }
+
+    #[test]
+    fn primer_skips_ambiguous() {
+        let mut state = State::default();
+        state.generic[0].seq = seq_from_str("tttttgacctgaagcttgcatgccattttt");
+        state.generic[0].primers = vec![Primer {
+            sequence: seq_from_str("gacctgaagcttgcatgcca"),
+            ..Default::default()
+        }];
+
+        state.sync_primer_matches(None);
+        assert_eq!(state.generic[0].primers[0].volatile.matches.len(), 1);
+
+        // An N at position 7, stored as its placeholder base, which matches the primer.
+        state.generic[0].ambiguous = vec![(7, b'n')];
+        state.sync_primer_matches(None);
+        assert!(state.generic[0].primers[0].volatile.matches.is_empty());
+    }
 }
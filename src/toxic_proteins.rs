@@ -2,7 +2,7 @@
 
 use na_seq::{
     amino_acids::{AminoAcid, CodingResult},
-    Nucleotide,
+    seq_complement, Nucleotide,
 };
 
 use crate::protein::proteins_from_seq;
@@ -27,12 +27,12 @@ enum ToxicStatus {
 fn check_glu_tracts(seq: &[Nucleotide]) -> ToxicStatus {
     let len = seq.len();
 
-    let mut seq_rev = seq.to_vec();
-    seq_rev.reverse();
+    // Reverse frames read 5' to 3' on the complementary strand.
+    let seq_rev = seq_complement(seq);
 
     // For each reading frame, create a Vec of amino acids.
     for seq_ in &[seq, &seq_rev] {
-        for rf_offset in 0..2 {
+        for rf_offset in 0..3 {
             let mut aa_seq = Vec::new();
 
             for i_ in 0..len / 3 {
@@ -4,6 +4,7 @@ use std::{
     fmt, io,
     io::ErrorKind,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use bincode::{Decode, Encode};
@@ -11,25 +12,70 @@ use eframe::egui::{pos2, Pos2};
 use na_seq::{
     ligation::{filter_multiple_seqs, filter_unique_cutters, find_common_res},
     restriction_enzyme::RestrictionEnzyme,
-    seq_complement, Nucleotide,
+    seq_complement,
+    Nucleotide::{self, C, G},
+    Seq, SeqTopology,
 };
 
 use crate::{
+    ambiguous::{self, AmbiguousList},
     file_io::save::QUICKSAVE_FILE,
     gui::{
         navigation::{Tab, DEFAULT_TAB_NAME},
-        sequence::seq_view::{NT_WIDTH_PX, SEQ_ROW_SPACING_PX, TEXT_X_START, TEXT_Y_START},
+        sequence::seq_view::{NT_WIDTH_PX, TEXT_X_START, TEXT_Y_START},
         WINDOW_TITLE,
     },
     misc_types::Feature,
+    primer::Primer,
     state::{State, StateVolatile},
     Color, ReUi,
 };
 
-const FEATURE_ANNOTATION_MATCH_THRESH: f32 = 0.95;
 // When abbreviating a path, show no more than this many characters.
 const PATH_ABBREV_MAX_LEN: usize = 16;
 
+/// Whether to display sequence positions as 0-based. We store this globally, like the palette, since
+/// positions are formatted in many places that don't have access to state. Set once per frame.
+static COORDS_ZERO_BASED: AtomicBool = AtomicBool::new(false);
+
+/// How sequence positions are displayed. This is display-only: Internally, positions are 1-based.
+/// This applies to features, primer matches, search matches, RE cut positions, and the sequence cursor.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum CoordBase {
+    #[default]
+    One,
+    Zero,
+}
+
+impl fmt::Display for CoordBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val = match self {
+            Self::One => "1-based",
+            Self::Zero => "0-based",
+        };
+
+        write!(f, "{val}")
+    }
+}
+
+pub fn set_coord_base(base: CoordBase) {
+    COORDS_ZERO_BASED.store(base == CoordBase::Zero, Ordering::Relaxed);
+}
+
+/// Convert a (1-based) sequence position to the one we display.
+pub fn disp_i(i: usize) -> usize {
+    if COORDS_ZERO_BASED.load(Ordering::Relaxed) {
+        i.saturating_sub(1)
+    } else {
+        i
+    }
+}
+
+/// Display a (1-based) range, e.g. "10..45".
+pub fn disp_range(range: RangeIncl) -> String {
+    format!("{}..{}", disp_i(range.start), disp_i(range.end))
+}
+
 /// A replacement for std::RangeInclusive, but copy type, and directly-accessible (mutable) fields.
 /// An official replacement is eventually coming, but not for a while likely.
 #[derive(Clone, Copy, Debug, PartialEq, Encode, Decode)]
@@ -68,18 +114,25 @@ impl RangeIncl {
         Some(&seq[self.start - 1..=self.end - 1])
     }
 
-    /// A wrap-based extension to `index_seq`.
-    pub fn index_seq_wrap<T: Clone>(&self, seq: &[T]) -> Option<Vec<T>> {
-        if self.start < 1 || self.end > seq.len() || self.start <= self.end {
+    /// A topology-aware extension to `index_seq`. On circular sequences, a range whose end is before
+    /// its start wraps the origin. Returns `None` if out of bounds, or if wrapping a linear sequence.
+    pub fn index_seq_topology<T: Clone>(&self, seq: &[T], topology: SeqTopology) -> Option<Vec<T>> {
+        if self.start < 1 || self.end < 1 || self.start > seq.len() || self.end > seq.len() {
             return None;
         }
 
-        let part1 = seq[self.start - 1..].to_vec();
-        // Todo: Not sure why we're including the end here without -1. Using for PCR to get it to come out right.
-        let part2 = seq[..self.end].to_vec();
-        let mut result = part1;
-        result.extend(part2);
-        Some(result)
+        if self.start <= self.end {
+            return Some(seq[self.start - 1..self.end].to_vec());
+        }
+
+        match topology {
+            SeqTopology::Circular => {
+                let mut result = seq[self.start - 1..].to_vec();
+                result.extend_from_slice(&seq[..self.end]);
+                Some(result)
+            }
+            SeqTopology::Linear => None,
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -88,6 +141,184 @@ impl RangeIncl {
         }
         self.end - self.start + 1
     }
+
+    /// A topology-aware extension to `len`. On circular sequences, a range whose end is before its
+    /// start wraps the origin; on linear ones, it's empty.
+    pub fn len_topology(&self, seq_len: usize, topology: SeqTopology) -> usize {
+        if self.end >= self.start {
+            return self.end - self.start + 1;
+        }
+
+        match topology {
+            SeqTopology::Circular => self.end + seq_len - self.start + 1,
+            SeqTopology::Linear => 0,
+        }
+    }
+
+    /// Extend this range by `buffer` nucleotides on each side. On circular sequences, the result may
+    /// wrap the origin; on linear ones, it's truncated at the sequence ends. If the result would
+    /// cover the whole sequence, it's the whole sequence, starting at 1.
+    pub fn buffered(&self, buffer: usize, seq_len: usize, topology: SeqTopology) -> Self {
+        if seq_len == 0 {
+            return *self;
+        }
+
+        match topology {
+            SeqTopology::Circular => {
+                if self.len_topology(seq_len, topology) + 2 * buffer >= seq_len {
+                    return Self::new(1, seq_len);
+                }
+
+                // Adding `seq_len` prevents underflow; the results are back in 1..=seq_len.
+                let start = (self.start + seq_len - 1 - buffer) % seq_len + 1;
+                let end = (self.end - 1 + buffer) % seq_len + 1;
+                Self::new(start, end)
+            }
+            SeqTopology::Linear => Self::new(
+                self.start.saturating_sub(buffer).max(1),
+                (self.end + buffer).min(seq_len),
+            ),
+        }
+    }
+}
+
+/// Adjust a 1-based index for the insertion of `len` nucleotides before 1-based position `insert_loc`.
+pub fn index_after_insert(i: usize, insert_loc: usize, len: usize) -> usize {
+    if i >= insert_loc {
+        i + len
+    } else {
+        i
+    }
+}
+
+/// Adjust a 1-based index for the removal of `removed`. Indexes within the removed range move to the
+/// nucleotide that follows it.
+pub fn index_after_remove(i: usize, removed: RangeIncl) -> usize {
+    if i > removed.end {
+        i - removed.len()
+    } else if i >= removed.start {
+        removed.start
+    } else {
+        i
+    }
+}
+
+/// Adjust feature ranges after the sequence length changes at 1-based `edit_pos`. A positive `delta` is an
+/// insertion of that many nucleotides before `edit_pos`; a negative one is a removal of that many, starting
+/// at `edit_pos`. Downstream features shift; features spanning the edit grow or shrink, and features
+/// entirely removed are dropped.
+pub fn remap_after_edit(features: &mut Vec<Feature>, edit_pos: usize, delta: isize) {
+    if edit_pos == 0 || delta == 0 {
+        return;
+    }
+
+    if delta > 0 {
+        let len = delta as usize;
+
+        // A feature starting at the edit position begins after the insert; one that ends there contains it.
+        for feature in features.iter_mut() {
+            feature.range.start = index_after_insert(feature.range.start, edit_pos, len);
+            feature.range.end = index_after_insert(feature.range.end, edit_pos, len);
+        }
+        return;
+    }
+
+    let removed = RangeIncl::new(edit_pos, edit_pos + delta.unsigned_abs() - 1);
+
+    features.retain(|f| {
+        !(f.range.start <= f.range.end
+            && removed.contains(f.range.start)
+            && removed.contains(f.range.end))
+    });
+
+    // A feature that ends within the removed range now ends before it.
+    for feature in features.iter_mut() {
+        feature.range.end = if removed.contains(feature.range.end) {
+            removed.start.saturating_sub(1).max(1)
+        } else {
+            index_after_remove(feature.range.end, removed)
+        };
+        feature.range.start = index_after_remove(feature.range.start, removed);
+    }
+}
+
+/// Find the single contiguous edit that turns `old` into `new`, e.g. from a text editor. Returns the
+/// 1-based position of the edit, the count of nucleotides removed there, and the count inserted.
+pub fn find_edit(old: &[Nucleotide], new: &[Nucleotide]) -> (usize, usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+
+    let max_suffix = min(old.len(), new.len()) - prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    (
+        prefix + 1,
+        old.len() - prefix - suffix,
+        new.len() - prefix - suffix,
+    )
+}
+
+/// Fix feature ranges that can't exist on this sequence, e.g. from a malformed or hand-edited file.
+/// Indices past the end are clamped, or wrapped for circular sequences; features that can't be
+/// placed at all are dropped. Returns a description of each fix.
+pub fn repair_features(
+    features: &mut Vec<Feature>,
+    seq_len: usize,
+    topology: SeqTopology,
+) -> Vec<String> {
+    let mut result = Vec::new();
+
+    features.retain_mut(|feature| {
+        let orig = feature.range;
+        let name = feature.label();
+
+        // There's nothing to clamp to if the feature starts past the end, or ends before the start.
+        if seq_len == 0 || orig.end == 0 || orig.start > seq_len {
+            result.push(format!(
+                "Removed {name} ({}..{}): outside the sequence",
+                orig.start, orig.end
+            ));
+            return false;
+        }
+
+        if feature.range.start == 0 {
+            feature.range.start = 1;
+        }
+
+        if feature.range.end > seq_len {
+            feature.range.end = match topology {
+                // Some files express origin-spanning features with an end past the sequence length.
+                SeqTopology::Circular if feature.range.end - seq_len < feature.range.start => {
+                    feature.range.end - seq_len
+                }
+                _ => seq_len,
+            };
+        }
+
+        // On a circular sequence, start > end wraps the origin. On a linear one, it's reversed.
+        if topology == SeqTopology::Linear && feature.range.start > feature.range.end {
+            feature.range = RangeIncl::new(feature.range.end, feature.range.start);
+        }
+
+        if feature.range != orig {
+            result.push(format!(
+                "Moved {name} from {}..{} to {}..{}",
+                orig.start, orig.end, feature.range.start, feature.range.end
+            ));
+        }
+        true
+    });
+
+    for fix in &result {
+        eprintln!("Feature repair: {fix}");
+    }
+
+    result
 }
 
 impl fmt::Display for RangeIncl {
@@ -145,19 +376,19 @@ fn seq_i_to_col_row(seq_i: usize, row_ranges: &[RangeIncl]) -> (usize, usize) {
 }
 
 /// Maps sequence index, as displayed on a manually-wrapped UI display, to the relative pixel.
-pub fn seq_i_to_pixel(seq_i: usize, row_ranges: &[RangeIncl]) -> Pos2 {
+pub fn seq_i_to_pixel(seq_i: usize, row_ranges: &[RangeIncl], row_spacing: f32) -> Pos2 {
     let (col, row) = seq_i_to_col_row(seq_i, row_ranges);
     // This adjustment is used for placing the cursor at position 0; prior to the first nucleotide.
     let col = if seq_i == 0 { -1. } else { col as f32 };
 
     pos2(
         TEXT_X_START + col * NT_WIDTH_PX,
-        TEXT_Y_START + row as f32 * SEQ_ROW_SPACING_PX,
+        TEXT_Y_START + row as f32 * row_spacing,
     )
 }
 
-pub fn pixel_to_seq_i(pixel: Pos2, row_ranges: &[RangeIncl]) -> Option<usize> {
-    let row = ((pixel.y - TEXT_Y_START) / SEQ_ROW_SPACING_PX) as usize;
+pub fn pixel_to_seq_i(pixel: Pos2, row_ranges: &[RangeIncl], row_spacing: f32) -> Option<usize> {
+    let row = ((pixel.y - TEXT_Y_START) / row_spacing) as usize;
     let col = ((pixel.x - TEXT_X_START) / NT_WIDTH_PX) as usize;
 
     // todo: Index vice loop?
@@ -246,14 +477,32 @@ pub fn color_to_hex(color: Color) -> String {
     format!("#{:x}{:x}{:x}", color.0, color.1, color.2)
 }
 
+/// Find the distance, in nucleotides, between two sequence positions. For circular sequences, this uses
+/// the shorter of the two arcs between them. Returns the distance, and the midpoint along the measured path.
+pub fn measure_dist(a: usize, b: usize, seq_len: usize, circular: bool) -> (usize, usize) {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    let direct = high - low;
+
+    if !circular || seq_len == 0 || direct <= seq_len - direct {
+        return (direct, low + direct / 2);
+    }
+
+    // The shorter path goes through the origin.
+    let dist = seq_len - direct;
+    let mid = (high + dist / 2 - 1) % seq_len + 1;
+    (dist, mid)
+}
+
 /// Change the origin. This involves updating the sequence, and all features.
 pub fn change_origin(state: &mut State) {
-    let origin = &state.ui.new_origin;
+    let origin = state.ui.new_origin;
     // Note the 1-based indexing logic we use.
-    if *origin < 1 || *origin > state.get_seq().len() {
+    if origin < 1 || origin > state.get_seq().len() {
         return;
     }
 
+    state.snapshot();
+
     state.generic[state.active].seq.rotate_left(origin - 1);
 
     let seq_len = state.get_seq().len();
@@ -261,66 +510,158 @@ pub fn change_origin(state: &mut State) {
         // Convert to i32 to prevent an underflow on crash if we wrap. Use `rem_euclid`,
         // as Rust has unexpected behavior when using modulus on negatives.
         feature.range = RangeIncl::new(
-            (feature.range.start as i32 + 1 - *origin as i32).rem_euclid(seq_len as i32) as usize,
-            (feature.range.end as i32 + 1 - *origin as i32).rem_euclid(seq_len as i32) as usize,
+            (feature.range.start as i32 + 1 - origin as i32).rem_euclid(seq_len as i32) as usize,
+            (feature.range.end as i32 + 1 - origin as i32).rem_euclid(seq_len as i32) as usize,
         )
     }
 
+    for (posit, _) in &mut state.generic[state.active].ambiguous {
+        *posit = (*posit + seq_len - origin) % seq_len + 1;
+    }
+
     // todo: What else to update?
     state.sync_seq_related(None);
 }
 
+/// Parse nucleotides from text, e.g. pasted from a formatted source like a GenBank ORIGIN block or a
+/// FASTA file. Skips `ORIGIN`, `//`, and FASTA `>` header lines, digits, and whitespace;
+/// case-insensitive. Ambiguity codes are stored as placeholders, and returned as (1-based index,
+/// lowercase char); see the `ambiguous` module.
+pub fn seq_from_pasted(text: &str) -> (Seq, AmbiguousList) {
+    let mut bytes = Vec::with_capacity(text.len());
+
+    for line in text.lines() {
+        let line_ = line.trim_start();
+        if line_.starts_with("ORIGIN") || line_.starts_with("//") || line_.starts_with('>') {
+            continue;
+        }
+        bytes.extend_from_slice(line.as_bytes());
+    }
+
+    ambiguous::seq_from_bytes(&bytes)
+}
+
+/// Forward and reverse matches, as ranges on the top strand, each with its mismatch count.
+pub type FuzzyMatches = (Vec<(RangeIncl, usize)>, Vec<(RangeIncl, usize)>);
+
 /// Find indexes where a subsequence matches a larger one, in both directions. Can be used to match primers,
 /// known sequences etc. Range indicies are relative to the forward direction.
 /// todo: Partial matches as well.
 pub fn match_subseq(subseq: &[Nucleotide], seq: &[Nucleotide]) -> (Vec<RangeIncl>, Vec<RangeIncl>) {
-    let mut result = (Vec::new(), Vec::new()); // Forward, reverse
+    let (fwd, rev) = match_subseq_fuzzy(subseq, seq, 1.);
 
-    let seq_len = seq.len();
+    (
+        fwd.into_iter().map(|(range, _)| range).collect(),
+        rev.into_iter().map(|(range, _)| range).collect(),
+    )
+}
+
+/// Find start indices (0-based) where a subsequence matches, with up to `max_mismatches`. Returns
+/// (start, mismatches). Of overlapping near-matches, we keep the one with the fewest mismatches.
+/// `matches` compares a subsequence element with a base.
+fn match_strand<T>(
+    subseq: &[T],
+    seq: &[Nucleotide],
+    max_mismatches: usize,
+    matches: impl Fn(&T, &Nucleotide) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut result: Vec<(usize, usize)> = Vec::new();
     let subseq_len = subseq.len();
-    let complement = seq_complement(seq);
 
-    for seq_start in 0..seq_len {
+    for seq_start in 0..seq.len() {
         // Note: This approach handles sequence wraps, eg [circular] plasmids.
-        let seq_iter = seq.iter().cycle().skip(seq_start).take(subseq_len);
+        let seq_iter = seq[seq_start..].iter().chain(seq.iter());
+
+        let mut mismatches = 0;
+        for (a, b) in subseq.iter().zip(seq_iter) {
+            if !matches(a, b) {
+                mismatches += 1;
+                if mismatches > max_mismatches {
+                    break;
+                }
+            }
+        }
+        if mismatches > max_mismatches {
+            continue;
+        }
 
-        // let seq_iter: Vec<Nucleotide> = seq.iter().cycle().skip(seq_start).take(subseq_len).map(|nt| *nt).collect();
+        if max_mismatches > 0 {
+            if let Some(prev) = result.last_mut() {
+                if seq_start < prev.0 + subseq_len {
+                    if mismatches < prev.1 {
+                        *prev = (seq_start, mismatches);
+                    }
+                    continue;
+                }
+            }
+        }
+        result.push((seq_start, mismatches));
+    }
 
-        // let similarity = seq_similarity(&seq_iter, subseq);
-        // if similarity > FEATURE_ANNOTATION_MATCH_THRESH {
-        //     let seq_end = (seq_start + subseq_len) % seq_len;
-        //     result.0.push(RangeIncl::new(seq_start, seq_end));
-        // }
+    result
+}
 
-        // println!("Similarity: {:?}", similarity);
+/// Similar to `match_subseq`, but allows mismatches, down to `min_identity` (0 to 1). Returns each range,
+/// with its mismatch count. Mismatches only; no gaps.
+pub fn match_subseq_fuzzy(
+    subseq: &[Nucleotide],
+    seq: &[Nucleotide],
+    min_identity: f32,
+) -> FuzzyMatches {
+    let max_mismatches = ((1. - min_identity) * subseq.len() as f32).floor() as usize;
+    match_both_strands(subseq, seq, max_mismatches, |a, b| a == b)
+}
 
-        if subseq.iter().eq(seq_iter) {
-            let seq_end = (seq_start + subseq_len) % seq_len;
-            result.0.push(RangeIncl::new(seq_start + 1, seq_end));
-        }
-    }
+/// Similar to `match_subseq`, but the query may contain IUPAC degenerate codes (e.g. N, R, Y), which
+/// match any base in their set; see `ambiguous::degenerate_from_str`. The target is unambiguous.
+pub fn match_subseq_degenerate(
+    query: &[u8],
+    seq: &[Nucleotide],
+) -> (Vec<RangeIncl>, Vec<RangeIncl>) {
+    let (fwd, rev) = match_both_strands(query, seq, 0, |code, nt| {
+        ambiguous::code_bases(*code).contains(nt)
+    });
+
+    (
+        fwd.into_iter().map(|(range, _)| range).collect(),
+        rev.into_iter().map(|(range, _)| range).collect(),
+    )
+}
 
-    for seq_start in 0..seq_len {
-        let seq_iter = complement.iter().cycle().skip(seq_start).take(subseq_len);
-        // let seq_iter: Vec<Nucleotide> = complement.iter().cycle().skip(seq_start).take(subseq_len).map(|nt| *nt).collect();
+/// Find matches on both strands, as ranges on the top strand, with their mismatch counts.
+fn match_both_strands<T>(
+    subseq: &[T],
+    seq: &[Nucleotide],
+    max_mismatches: usize,
+    matches: impl Fn(&T, &Nucleotide) -> bool + Copy,
+) -> FuzzyMatches {
+    let mut result = (Vec::new(), Vec::new()); // Forward, reverse
 
-        // let similarity = seq_similarity(&seq_iter, subseq);
-        // if similarity > FEATURE_ANNOTATION_MATCH_THRESH {
-        //     let seq_end = (seq_start + subseq_len) % seq_len;
-        //     result.0.push(RangeIncl::new(seq_start, seq_end));
-        // }
-        //
+    let seq_len = seq.len();
+    let subseq_len = subseq.len();
+    if subseq_len == 0 || subseq_len > seq_len {
+        return result;
+    }
 
-        if subseq.iter().eq(seq_iter) {
-            let seq_end = (seq_start + subseq_len) % seq_len;
-            if seq_end < 1 {
-                continue;
-            }
+    let complement = seq_complement(seq);
+
+    for (seq_start, mismatches) in match_strand(subseq, seq, max_mismatches, matches) {
+        let seq_end = (seq_start + subseq_len) % seq_len;
+        result
+            .0
+            .push((RangeIncl::new(seq_start + 1, seq_end), mismatches));
+    }
 
-            result
-                .1
-                .push(RangeIncl::new(seq_len - seq_end + 1, seq_len - seq_start));
+    for (seq_start, mismatches) in match_strand(subseq, &complement, max_mismatches, matches) {
+        let seq_end = (seq_start + subseq_len) % seq_len;
+        if seq_end < 1 {
+            continue;
         }
+
+        result.1.push((
+            RangeIncl::new(seq_len - seq_end + 1, seq_len - seq_start),
+            mismatches,
+        ));
     }
 
     result
@@ -340,6 +681,50 @@ pub fn _seq_similarity(seq_a: &[Nucleotide], seq_b: &[Nucleotide]) -> f32 {
     matches as f32 / seq_a.len() as f32
 }
 
+/// GC content, from 0 to 1, in a window centered on each nucleotide. For circular sequences, the
+/// window wraps around the origin. For linear ones, it's truncated at the ends.
+pub fn gc_window(seq: &[Nucleotide], window: usize, circular: bool) -> Vec<f32> {
+    let len = seq.len();
+    if len == 0 {
+        return Vec::new();
+    }
+    let window = window.clamp(1, len);
+    let half = window / 2;
+
+    let is_gc = |i: usize| matches!(seq[i], C | G) as usize;
+
+    let mut result = Vec::with_capacity(len);
+
+    if circular {
+        // Slide the window, adding the nucleotide entering it, and removing the one leaving it.
+        let wrap = |i: isize| i.rem_euclid(len as isize) as usize;
+        let mut count: usize = (0..window)
+            .map(|j| is_gc(wrap(j as isize - half as isize)))
+            .sum();
+
+        for i in 0..len {
+            result.push(count as f32 / window as f32);
+
+            let leaving = wrap(i as isize - half as isize);
+            let entering = wrap((i + window) as isize - half as isize);
+            count = count + is_gc(entering) - is_gc(leaving);
+        }
+    } else {
+        let mut cumulative = vec![0; len + 1];
+        for i in 0..len {
+            cumulative[i + 1] = cumulative[i] + is_gc(i);
+        }
+
+        for i in 0..len {
+            let start = i.saturating_sub(half);
+            let end = (i + window - half).min(len);
+            result.push((cumulative[end] - cumulative[start]) as f32 / (end - start) as f32);
+        }
+    }
+
+    result
+}
+
 /// Merge a new set into an existing one. Don't add duplicates.
 pub fn merge_feature_sets(existing: &mut Vec<Feature>, new: &[Feature]) {
     for feature_new in new {
@@ -377,6 +762,48 @@ pub fn merge_feature_sets(existing: &mut Vec<Feature>, new: &[Feature]) {
 //     Ok(())
 // }
 
+/// Characters that can't be used in filenames on at least one common platform.
+const FILENAME_ILLEGAL: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Remove characters that can't be used in filenames. We apply this to plasmid names, as they
+/// seed default export filenames.
+pub fn strip_filename_chars(name: &str) -> String {
+    name.chars()
+        .filter(|c| !FILENAME_ILLEGAL.contains(c) && !c.is_control())
+        .collect()
+}
+
+/// Indices of names, other than the one at `i`, that match it. Case-insensitive, as filenames are on
+/// some platforms. Empty names aren't flagged.
+pub fn duplicate_names(names: &[&str], i: usize) -> Vec<usize> {
+    let name = names[i].trim();
+    if name.is_empty() {
+        return Vec::new();
+    }
+
+    names
+        .iter()
+        .enumerate()
+        .filter(|(j, other)| *j != i && other.trim().eq_ignore_ascii_case(name))
+        .map(|(j, _)| j)
+        .collect()
+}
+
+/// A variant of `name` that doesn't match any of `others`, by appending a number.
+pub fn unique_name(name: &str, others: &[&str]) -> String {
+    let mut suffix = 2;
+    loop {
+        let candidate = format!("{} ({suffix})", name.trim());
+        if !others
+            .iter()
+            .any(|o| o.trim().eq_ignore_ascii_case(&candidate))
+        {
+            return candidate;
+        }
+        suffix += 1;
+    }
+}
+
 /// Get the title to be displayed in the windows tilebar.
 pub fn get_window_title(path: &Path) -> String {
     let filename = path
@@ -416,6 +843,41 @@ pub fn name_from_path(path: &Option<PathBuf>, plasmid_name: &str, abbrev_name: b
     name
 }
 
+/// Restriction enzymes in the library that don't cut any of the selected sequences.
+fn filter_non_cutters<'a>(
+    data: &ReUi,
+    volatile: &[StateVolatile],
+    lib: &'a [RestrictionEnzyme],
+) -> Vec<&'a RestrictionEnzyme> {
+    let mut result = Vec::new();
+    if data.tabs_selected.is_empty() {
+        return result;
+    }
+
+    for (i, re) in lib.iter().enumerate() {
+        if data.sticky_ends_only && re.makes_blunt_ends() {
+            continue;
+        }
+
+        let cuts = data.tabs_selected.iter().any(|active| {
+            volatile[*active]
+                .restriction_enzyme_matches
+                .iter()
+                .any(|m| m.lib_index == i)
+        });
+
+        if !cuts
+            && !result
+                .iter()
+                .any(|r: &&RestrictionEnzyme| r.name == re.name)
+        {
+            result.push(re);
+        }
+    }
+
+    result
+}
+
 /// We filter for restriction enzymes based on preferences set. We do this in several stages.
 /// Note that this function includes filter characteristics that inolve matches across
 /// multiple opened tabs (sequences).
@@ -424,6 +886,11 @@ pub fn filter_res<'a>(
     volatile: &[StateVolatile],
     lib: &'a [RestrictionEnzyme],
 ) -> Vec<&'a RestrictionEnzyme> {
+    // The unique cutter and multiple sequence filters don't apply to enzymes that don't cut.
+    if data.non_cutters {
+        return filter_non_cutters(data, volatile, lib);
+    }
+
     let mut re_match_set = Vec::new(); // By tab
     for active in &data.tabs_selected {
         re_match_set.push(&volatile[*active].restriction_enzyme_matches);
@@ -443,3 +910,185 @@ pub fn filter_res<'a>(
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use na_seq::{seq_from_str, seq_to_str_lower};
+
+    use super::*;
+
+    fn feature(start: usize, end: usize) -> Feature {
+        Feature {
+            range: RangeIncl::new(start, end),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn range_wrapping_origin() {
+        let seq = seq_from_str("acgtacgtac");
+        let range = RangeIncl::new(8, 3);
+
+        let wrapped = range
+            .index_seq_topology(&seq, SeqTopology::Circular)
+            .unwrap();
+        assert_eq!(seq_to_str_lower(&wrapped), "tacacg");
+        assert_eq!(range.len_topology(seq.len(), SeqTopology::Circular), 6);
+
+        // Linear sequences don't wrap.
+        assert!(range
+            .index_seq_topology(&seq, SeqTopology::Linear)
+            .is_none());
+        assert_eq!(range.len_topology(seq.len(), SeqTopology::Linear), 0);
+    }
+
+    #[test]
+    fn buffer_across_origin() {
+        let circ = SeqTopology::Circular;
+
+        assert_eq!(
+            RangeIncl::new(2, 4).buffered(3, 10, circ),
+            RangeIncl::new(9, 7)
+        );
+        assert_eq!(
+            RangeIncl::new(9, 10).buffered(2, 10, circ),
+            RangeIncl::new(7, 2)
+        );
+        // A wrapping range, extended further.
+        assert_eq!(
+            RangeIncl::new(9, 2).buffered(1, 10, circ),
+            RangeIncl::new(8, 3)
+        );
+        // Covering the whole sequence.
+        assert_eq!(
+            RangeIncl::new(2, 4).buffered(4, 10, circ),
+            RangeIncl::new(1, 10)
+        );
+    }
+
+    #[test]
+    fn buffer_clamped_linear() {
+        let lin = SeqTopology::Linear;
+
+        assert_eq!(
+            RangeIncl::new(2, 4).buffered(3, 10, lin),
+            RangeIncl::new(1, 7)
+        );
+        assert_eq!(
+            RangeIncl::new(8, 9).buffered(5, 10, lin),
+            RangeIncl::new(3, 10)
+        );
+        assert_eq!(
+            RangeIncl::new(4, 6).buffered(2, 10, lin),
+            RangeIncl::new(2, 8)
+        );
+    }
+
+    #[test]
+    fn index_before_edit() {
+        assert_eq!(index_after_insert(4, 10, 3), 4);
+        assert_eq!(index_after_remove(4, RangeIncl::new(10, 12)), 4);
+    }
+
+    #[test]
+    fn index_at_and_after_insert() {
+        // An insert before position 10 moves the nucleotide at 10 and everything after it.
+        assert_eq!(index_after_insert(10, 10, 3), 13);
+        assert_eq!(index_after_insert(20, 10, 3), 23);
+    }
+
+    #[test]
+    fn index_within_and_after_remove() {
+        let removed = RangeIncl::new(10, 12);
+        // Within the removed range, we move to the nucleotide that follows it.
+        assert_eq!(index_after_remove(10, removed), 10);
+        assert_eq!(index_after_remove(12, removed), 10);
+        assert_eq!(index_after_remove(13, removed), 10);
+        assert_eq!(index_after_remove(20, removed), 17);
+    }
+
+    #[test]
+    fn remap_downstream_feature() {
+        let mut features = vec![feature(20, 30)];
+
+        remap_after_edit(&mut features, 10, 5);
+        assert_eq!(features[0].range, RangeIncl::new(25, 35));
+
+        remap_after_edit(&mut features, 10, -5);
+        assert_eq!(features[0].range, RangeIncl::new(20, 30));
+    }
+
+    #[test]
+    fn remap_spanning_feature() {
+        let mut features = vec![feature(20, 30)];
+
+        remap_after_edit(&mut features, 25, 4);
+        assert_eq!(features[0].range, RangeIncl::new(20, 34));
+
+        remap_after_edit(&mut features, 22, -6);
+        assert_eq!(features[0].range, RangeIncl::new(20, 28));
+    }
+
+    #[test]
+    fn remap_removed_feature() {
+        let mut features = vec![feature(5, 8), feature(20, 30)];
+
+        remap_after_edit(&mut features, 4, -6);
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].range, RangeIncl::new(14, 24));
+    }
+
+    #[test]
+    fn remap_origin_wrapping_feature() {
+        // On a 100 nt circular sequence, this feature spans the origin.
+        let mut features = vec![feature(90, 10)];
+
+        remap_after_edit(&mut features, 50, 3);
+        assert_eq!(features[0].range, RangeIncl::new(93, 10));
+
+        // Removing nucleotides after the origin shrinks it, and shifts its start.
+        remap_after_edit(&mut features, 5, -2);
+        assert_eq!(features[0].range, RangeIncl::new(91, 8));
+    }
+
+    #[test]
+    fn find_edit_insert_and_remove() {
+        let old = seq_from_pasted("acgtacgt").0;
+
+        // An insert after the 4th nucleotide.
+        assert_eq!(find_edit(&old, &seq_from_pasted("acgtggacgt").0), (5, 0, 2));
+        // Removing the 2nd and 3rd.
+        assert_eq!(find_edit(&old, &seq_from_pasted("atacgt").0), (2, 2, 0));
+        // A substitution.
+        assert_eq!(find_edit(&old, &seq_from_pasted("acgaacgt").0), (4, 1, 1));
+    }
+
+    #[test]
+    fn paste_genbank_origin() {
+        let text = "ORIGIN
+        1 gatcctccat atacaacggt atctccacct caggtttaga tctcaacaac ggaaccattg
+       61 ccgacatgag acagttaggt atcgtcgaga gttacaagct aaaacgagca gtagtcagct
+      121 ctgcatctga agccgctgaa
+//
+";
+        let (seq, ambiguous) = seq_from_pasted(text);
+
+        assert_eq!(
+            seq_to_str_lower(&seq),
+            "gatcctccatatacaacggtatctccacctcaggtttagatctcaacaacggaaccattg\
+            ccgacatgagacagttaggtatcgtcgagagttacaagctaaaacgagcagtagtcagct\
+            ctgcatctgaagccgctgaa"
+        );
+        assert!(ambiguous.is_empty());
+    }
+
+    #[test]
+    fn paste_fasta_with_ambiguity() {
+        let (seq, ambiguous) = seq_from_pasted(">pUC19 fragment\nACGTNNacgr\n");
+
+        // Ambiguous positions are kept, so later positions don't shift.
+        assert_eq!(seq.len(), 10);
+        assert_eq!(seq_to_str_lower(&seq[..4]), "acgt");
+        assert_eq!(ambiguous, vec![(5, b'n'), (6, b'n'), (10, b'r')]);
+    }
+}